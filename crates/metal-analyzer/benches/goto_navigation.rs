@@ -1,7 +1,9 @@
 use std::{hint::black_box, path::PathBuf, sync::Arc};
 
 use criterion::{Criterion, criterion_group, criterion_main};
-use metal_analyzer::{DefinitionProvider, metal::compiler::compute_include_paths, syntax::SyntaxTree};
+use metal_analyzer::{
+    DefinitionProvider, document::ContentHash, metal::compiler::compute_include_paths, syntax::SyntaxTree,
+};
 use tower_lsp::lsp_types::{Position, Url};
 
 const FIXTURE_RELATIVE_PATH: &str = "matmul/gemv/shaders/gemv_like.metal";
@@ -12,6 +14,7 @@ const CONCURRENT_SIZE: usize = 8;
 struct NavigationFixture {
     uri: Url,
     source: Arc<String>,
+    hash: ContentHash,
     include_paths: Arc<Vec<String>>,
     snapshot: Arc<SyntaxTree>,
     jump_positions: Arc<Vec<Position>>,
@@ -96,6 +99,7 @@ fn load_navigation_fixture() -> Option<NavigationFixture> {
     Some(NavigationFixture {
         uri,
         source: Arc::new(source.clone()),
+        hash: ContentHash::of(&source),
         include_paths: Arc::new(include_paths),
         snapshot: Arc::new(SyntaxTree::parse(&source)),
         jump_positions: Arc::new(jump_positions),
@@ -114,7 +118,7 @@ fn bench_goto_navigation(c: &mut Criterion) {
     };
 
     let provider = Arc::new(DefinitionProvider::new());
-    provider.index_document(&fixture.uri, fixture.source.as_str(), fixture.include_paths.as_slice());
+    provider.index_document(&fixture.uri, fixture.source.as_str(), fixture.hash, fixture.include_paths.as_slice());
 
     c.bench_function("goto_navigation/warm_single_jump", |b| {
         let provider = Arc::clone(&provider);
@@ -124,6 +128,7 @@ fn bench_goto_navigation(c: &mut Criterion) {
                 &fixture.uri,
                 fixture.jump_positions[0],
                 fixture.source.as_str(),
+                fixture.hash,
                 fixture.include_paths.as_slice(),
                 fixture.snapshot.as_ref(),
                 || false,
@@ -142,6 +147,7 @@ fn bench_goto_navigation(c: &mut Criterion) {
                     &fixture.uri,
                     position,
                     fixture.source.as_str(),
+                    fixture.hash,
                     fixture.include_paths.as_slice(),
                     fixture.snapshot.as_ref(),
                     || false,
@@ -166,6 +172,7 @@ fn bench_goto_navigation(c: &mut Criterion) {
                             &fixture.uri,
                             position,
                             fixture.source.as_str(),
+                            fixture.hash,
                             fixture.include_paths.as_slice(),
                             fixture.snapshot.as_ref(),
                             || false,
@@ -0,0 +1,105 @@
+//! Completion of project symbols whose header the document does not reach
+//! yet, inserting the `#include` that brings them in.
+//!
+//! Candidates come from [`ProjectIndex::includable_declarations`]; a header
+//! is reached when the document's translation unit already declares
+//! something from it. The include is spelled relative to the quoted search
+//! directories, the document's own directory first, and a header outside
+//! all of them is not offered.
+//!
+//! [`ProjectIndex::includable_declarations`]: crate::definition::ProjectIndex::includable_declarations
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    definition::{AstIndex, SymbolDef, paths_match},
+    ide::include_guards::{IncludeGuard, include_guard},
+};
+
+/// Kinds of declaration offered, as for the document's own project headers.
+pub(crate) const AUTO_IMPORT_KINDS: &[&str] =
+    &["FunctionDecl", "CXXRecordDecl", "EnumDecl", "TypedefDecl", "TypeAliasDecl"];
+
+/// A declaration in a header the document does not include.
+#[derive(Debug, Clone)]
+pub struct AutoImport {
+    pub def: SymbolDef,
+    /// The path written between the quotes of `#include "…"`.
+    pub spelling: String,
+}
+
+/// The declarations of `candidates` in headers that neither the translation
+/// unit indexed in `index` nor the document at `source_file` itself covers.
+pub(crate) fn auto_imports(
+    candidates: Vec<SymbolDef>,
+    index: &AstIndex,
+    source_file: &str,
+    quoted_dirs: &[PathBuf],
+) -> Vec<AutoImport> {
+    let reached: HashSet<&str> = index.file_to_defs.keys().map(|file| file.as_str()).collect();
+    candidates
+        .into_iter()
+        .filter(|def| !reached.contains(def.file.as_str()) && !paths_match(&def.file, source_file))
+        .filter_map(|def| {
+            let spelling = include_spelling(Path::new(def.file.as_str()), quoted_dirs)?;
+            Some(AutoImport {
+                def,
+                spelling,
+            })
+        })
+        .collect()
+}
+
+/// `header` relative to the one of `quoted_dirs` giving the shortest path,
+/// with `/` separators.
+pub(crate) fn include_spelling(
+    header: &Path,
+    quoted_dirs: &[PathBuf],
+) -> Option<String> {
+    let relative = quoted_dirs
+        .iter()
+        .filter_map(|dir| header.strip_prefix(dir).ok())
+        .min_by_key(|relative| relative.components().count())?;
+    let parts: Vec<_> = relative.components().map(|part| part.as_os_str().to_string_lossy()).collect();
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Line a new `#include` is inserted at in `text`: after the last
+/// `#include`, after the include guard, or after the comments and
+/// `#pragma once` opening the file.
+pub(crate) fn include_insertion_line(text: &str) -> u32 {
+    if let Some((last, _)) = text.lines().enumerate().filter(|(_, line)| is_include_line(line)).last() {
+        return last as u32 + 1;
+    }
+    if let Some(IncludeGuard::Macro {
+        ranges,
+        ..
+    }) = include_guard(text)
+    {
+        return ranges[1].start.line + 1;
+    }
+    let mut in_block_comment = false;
+    let mut insert_at = 0;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if in_block_comment || line.starts_with("/*") {
+            in_block_comment = !line.contains("*/");
+        } else if !line.starts_with("//") && !line.is_empty() && line != "#pragma once" {
+            break;
+        }
+        insert_at = index + 1;
+    }
+    insert_at as u32
+}
+
+/// Whether `line` is an `#include` directive.
+pub(crate) fn is_include_line(line: &str) -> bool {
+    line.trim_start().strip_prefix('#').is_some_and(|rest| rest.trim_start().starts_with("include"))
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/completion/auto_import_tests.rs"]
+mod tests;
@@ -1,12 +1,19 @@
-use tower_lsp::lsp_types::{
-    CompletionItem, CompletionItemKind, Documentation, InsertTextFormat, MarkupContent, MarkupKind,
-};
+use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, InsertTextFormat};
 
-use crate::metal::builtins::{self, BuiltinEntry, BuiltinKind};
+use crate::{
+    completion::resolve::CompletionData,
+    metal::builtins::{self, BuiltinEntry, BuiltinKind},
+};
 
+/// The item for the builtin at `index` in the database, without its
+/// documentation, which `completionItem/resolve` attaches.
+/// `include_line` is where a function, type or constant inserts
+/// `#include <metal_stdlib>` when the document includes nothing.
 pub(crate) fn builtin_to_completion_item(
+    index: usize,
     entry: &BuiltinEntry,
     sort_prefix: &str,
+    include_line: Option<u32>,
 ) -> CompletionItem {
     let kind = match entry.kind {
         BuiltinKind::Keyword => CompletionItemKind::KEYWORD,
@@ -27,17 +34,15 @@ pub(crate) fn builtin_to_completion_item(
         label: entry.label.clone(),
         kind: Some(kind),
         detail: Some(entry.detail.clone()),
-        documentation: if entry.documentation.is_empty() {
-            None
-        } else {
-            Some(Documentation::MarkupContent(MarkupContent {
-                kind: MarkupKind::Markdown,
-                value: entry.documentation.clone(),
-            }))
-        },
         insert_text: entry.insert_text.clone(),
         insert_text_format,
         sort_text: Some(format!("{}_{}", sort_prefix, entry.label)),
+        data: CompletionData::Builtin {
+            index,
+            include_line: include_line
+                .filter(|_| matches!(entry.kind, BuiltinKind::Function | BuiltinKind::Type | BuiltinKind::Constant)),
+        }
+        .to_value(),
         ..Default::default()
     }
 }
@@ -87,6 +92,20 @@ pub(crate) static PREPROCESSOR_DIRECTIVES: &[(&str, &str)] = &[
     ("warning", "Generate a compiler warning"),
 ];
 
+pub(crate) static DOC_COMMENT_TAGS: &[(&str, &str)] = &[
+    ("brief", "One-line summary of the documented entity"),
+    ("param", "Document a function parameter: `@param name description`"),
+    ("tparam", "Document a template parameter: `@tparam T description`"),
+    ("return", "Describe the return value"),
+    ("note", "Additional remarks"),
+    ("warning", "Caveats callers must be aware of"),
+    ("see", "Reference a related symbol"),
+    ("deprecated", "Mark the entity as deprecated"),
+    ("todo", "Outstanding work"),
+    ("code", "Start a code block (close with `@endcode`)"),
+    ("endcode", "End a code block"),
+];
+
 pub(crate) static METAL_HEADERS: &[(&str, &str)] = &[
     (
         "metal_stdlib",
@@ -1,16 +1,35 @@
 use tower_lsp::lsp_types::Position;
 
-use crate::syntax::{cst::SyntaxNode, helpers, kind::SyntaxKind};
+use crate::{
+    metal::builtins::{AttributeTarget, ShaderStage},
+    syntax::{cst::SyntaxNode, helpers, kind::SyntaxKind},
+    text_pos::byte_offset_of_column,
+};
 
 /// Describes the syntactic context at the cursor position.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum CursorContext {
     /// Inside an attribute bracket `[[ … ]]`.
-    Attribute,
-    /// After a dot (member access / swizzle).
+    Attribute {
+        /// Declaration the attribute list belongs to, when it could be told.
+        target: Option<AttributeTarget>,
+        /// Entry-point kind of the function, for parameter attributes.
+        stage: Option<ShaderStage>,
+    },
+    /// After `.` or `->` (member access / swizzle).
     MemberAccess {
+        /// Expression before the accessor, e.g. `light.color` in `light.color.r`.
         receiver: String,
     },
+    /// Inside a regular comment.
+    Comment,
+    /// Inside a documentation comment (`///`, `//!`, `/** */`).
+    DocComment {
+        /// `@` or `\` typed right before the word under the cursor.
+        marker: Option<char>,
+    },
+    /// Inside a string, character or numeric literal.
+    Literal,
     /// After `#` (preprocessor directive).
     Preprocessor,
     /// Inside an `#include` directive.
@@ -24,6 +43,10 @@ pub(crate) fn detect_context(
     position: Position,
     root: Option<SyntaxNode>,
 ) -> CursorContext {
+    if let Some(ctx) = detect_comment_or_literal(root.as_ref(), text, position) {
+        return ctx;
+    }
+
     if let Some(root) = root
         && let Some(ctx) = detect_context_from_tree(&root, text, position)
     {
@@ -33,6 +56,115 @@ pub(crate) fn detect_context(
     detect_context_from_text(text, position)
 }
 
+/// Detect comments and literals, where identifier completion is only noise.
+///
+/// Terminated comments and literals are classified by the CST token at the
+/// cursor; unterminated ones (still being typed) lex as error tokens, so the
+/// current line is scanned as well.
+fn detect_comment_or_literal(
+    root: Option<&SyntaxNode>,
+    text: &str,
+    position: Position,
+) -> Option<CursorContext> {
+    let line = text.lines().nth(position.line as usize).unwrap_or("");
+    let prefix = &line[..byte_offset_of_column(line, position.character)];
+    let trimmed = prefix.trim_start();
+    if trimmed.starts_with("#include") || trimmed.starts_with("#import") {
+        return None;
+    }
+
+    if let Some(root) = root
+        && let Some(token) = helpers::token_before_position(root, text, position)
+    {
+        let range = helpers::range_to_lsp(token.text_range(), text);
+        let after_start = range.start < position;
+        let before_end = position < range.end;
+        let at_end = position == range.end;
+        match token.kind() {
+            SyntaxKind::Comment if after_start && (before_end || (at_end && token.text().starts_with("//"))) => {
+                return Some(comment_context(token.text(), prefix));
+            },
+            SyntaxKind::String | SyntaxKind::Char | SyntaxKind::RawString if after_start && before_end => {
+                return Some(CursorContext::Literal);
+            },
+            SyntaxKind::Integer | SyntaxKind::Float if after_start => {
+                return Some(CursorContext::Literal);
+            },
+            _ => {},
+        }
+    }
+
+    scan_line_prefix(prefix)
+}
+
+/// Track quotes and comment openers across the line up to the cursor.
+fn scan_line_prefix(prefix: &str) -> Option<CursorContext> {
+    let bytes = prefix.as_bytes();
+    let mut quote = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        if let Some(open) = quote {
+            if byte == b'\\' {
+                i += 1;
+            } else if byte == open {
+                quote = None;
+            }
+            i += 1;
+            continue;
+        }
+        match byte {
+            b'\'' if is_digit_separator(bytes, i) => {},
+            b'"' | b'\'' => quote = Some(byte),
+            b'/' if bytes.get(i + 1) == Some(&b'/') => return Some(comment_context(&prefix[i..], prefix)),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => match prefix[i + 2..].find("*/") {
+                Some(end) => {
+                    i += 2 + end + 2;
+                    continue;
+                },
+                None => return Some(comment_context(&prefix[i..], prefix)),
+            },
+            _ => {},
+        }
+        i += 1;
+    }
+    quote.map(|_| CursorContext::Literal)
+}
+
+/// Whether the `'` at `bytes[index]` separates digits of a number, as in
+/// `1'000'000`, rather than opening a character literal.
+fn is_digit_separator(
+    bytes: &[u8],
+    index: usize,
+) -> bool {
+    if !bytes[..index].last().is_some_and(u8::is_ascii_alphanumeric) {
+        return false;
+    }
+    let token_start = bytes[..index]
+        .iter()
+        .rposition(|byte| !(byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'.' | b'\'')))
+        .map_or(0, |position| position + 1);
+    bytes[token_start].is_ascii_digit()
+}
+
+fn comment_context(
+    comment: &str,
+    line_prefix: &str,
+) -> CursorContext {
+    let is_doc = comment.starts_with("///")
+        || comment.starts_with("//!")
+        || comment.starts_with("/*!")
+        || (comment.starts_with("/**") && !comment.starts_with("/**/"));
+    if !is_doc {
+        return CursorContext::Comment;
+    }
+    let word_start = line_prefix.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_');
+    let marker = word_start.chars().last().filter(|c| matches!(c, '@' | '\\'));
+    CursorContext::DocComment {
+        marker,
+    }
+}
+
 fn detect_context_from_tree(
     root: &SyntaxNode,
     source: &str,
@@ -43,7 +175,7 @@ fn detect_context_from_tree(
     let mut current = node;
     loop {
         match current.kind() {
-            SyntaxKind::Attribute => return Some(CursorContext::Attribute),
+            SyntaxKind::Attribute => return Some(attribute_context(&current)),
             SyntaxKind::PreprocInclude => return Some(CursorContext::Include),
             SyntaxKind::PreprocDefine
             | SyntaxKind::PreprocIf
@@ -54,7 +186,10 @@ fn detect_context_from_tree(
             | SyntaxKind::PreprocEndif
             | SyntaxKind::PreprocPragma => return Some(CursorContext::Preprocessor),
             SyntaxKind::MemberExpr => {
-                let receiver = helpers::node_text(&current, source).to_string();
+                let line = source.lines().nth(position.line as usize).unwrap_or("");
+                let prefix = &line[..byte_offset_of_column(line, position.character)];
+                let receiver =
+                    member_receiver(prefix).unwrap_or_else(|| helpers::node_text(&current, source).to_string());
                 return Some(CursorContext::MemberAccess {
                     receiver,
                 });
@@ -65,22 +200,65 @@ fn detect_context_from_tree(
     }
 }
 
+/// Classify an `Attribute` node by the declaration the parser attached it to.
+fn attribute_context(attribute: &SyntaxNode) -> CursorContext {
+    let (target, stage) = match attribute.parent().map(|parent| parent.kind()) {
+        Some(SyntaxKind::FieldDef) => (Some(AttributeTarget::Member), None),
+        Some(SyntaxKind::VariableDef) => (Some(AttributeTarget::Variable), None),
+        Some(SyntaxKind::Parameter) => {
+            match attribute.ancestors().find(|node| node.kind() == SyntaxKind::FunctionDef) {
+                // `constant bool flag [[function_constant(0)]];` parses as a
+                // function without a parenthesized parameter list.
+                Some(function) if has_lparen_before(&function, attribute) => {
+                    (Some(AttributeTarget::Parameter), entry_point_stage(&function))
+                },
+                Some(_) => (Some(AttributeTarget::Variable), None),
+                None => (Some(AttributeTarget::Parameter), None),
+            }
+        },
+        _ => (None, None),
+    };
+    CursorContext::Attribute {
+        target,
+        stage,
+    }
+}
+
+fn has_lparen_before(
+    function: &SyntaxNode,
+    attribute: &SyntaxNode,
+) -> bool {
+    let start = attribute.text_range().start();
+    function
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .take_while(|token| token.text_range().start() < start)
+        .any(|token| token.kind() == SyntaxKind::LParen)
+}
+
+fn entry_point_stage(function: &SyntaxNode) -> Option<ShaderStage> {
+    let first = function
+        .children_with_tokens()
+        .filter_map(|element| element.into_token())
+        .find(|token| !matches!(token.kind(), SyntaxKind::Whitespace | SyntaxKind::Comment))?;
+    ShaderStage::from_qualifier(first.text())
+}
+
 fn detect_context_from_text(
     text: &str,
     position: Position,
 ) -> CursorContext {
     let line_idx = position.line as usize;
-    let char_idx = position.character as usize;
 
     let line = match text.lines().nth(line_idx) {
         Some(l) => l,
         None => return CursorContext::General,
     };
 
-    let prefix: String = line.chars().take(char_idx).collect();
+    let prefix = &line[..byte_offset_of_column(line, position.character)];
     let trimmed = prefix.trim_start();
 
-    if trimmed.starts_with("#include") {
+    if trimmed.starts_with("#include") || trimmed.starts_with("#import") {
         return CursorContext::Include;
     }
 
@@ -91,25 +269,55 @@ fn detect_context_from_text(
     let open_count = prefix.matches("[[").count();
     let close_count = prefix.matches("]]").count();
     if open_count > close_count {
-        return CursorContext::Attribute;
+        // An attribute list opening a line precedes a function declaration;
+        // anywhere else the declaration is not known without a tree.
+        let before_open = prefix.rfind("[[").map_or("", |open| prefix[..open].trim());
+        let target = (before_open.is_empty() || before_open.ends_with("]]")).then_some(AttributeTarget::Function);
+        return CursorContext::Attribute {
+            target,
+            stage: None,
+        };
     }
 
-    let trimmed_end = prefix.trim_end();
-    if let Some(before_dot) = trimmed_end.strip_suffix('.') {
-        let receiver: String = before_dot
-            .chars()
-            .rev()
-            .take_while(|c| c.is_alphanumeric() || *c == '_')
-            .collect::<Vec<_>>()
-            .into_iter()
-            .rev()
-            .collect();
-        if !receiver.is_empty() {
-            return CursorContext::MemberAccess {
-                receiver,
-            };
-        }
+    if let Some(receiver) = member_receiver(prefix) {
+        return CursorContext::MemberAccess {
+            receiver,
+        };
     }
 
     CursorContext::General
 }
+
+/// The receiver of the member being typed at the end of `prefix`.
+///
+/// Skips a partially typed member name, then the `.` or `->` accessor, and
+/// returns the postfix chain before it: `tiles[i].light` for
+/// `tiles[i].light->col`.
+pub(crate) fn member_receiver(prefix: &str) -> Option<String> {
+    let before_member = prefix.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_').trim_end();
+    let before_accessor = before_member.strip_suffix('.').or_else(|| before_member.strip_suffix("->"))?.trim_end();
+
+    let bytes = before_accessor.as_bytes();
+    let mut start = bytes.len();
+    let mut depth = 0usize;
+    while start > 0 {
+        let byte = bytes[start - 1];
+        match byte {
+            b']' => depth += 1,
+            b'[' if depth > 0 => depth -= 1,
+            _ if depth > 0 => {},
+            b'>' if start >= 2 && bytes[start - 2] == b'-' => {
+                start -= 2;
+                continue;
+            },
+            b'.' | b'_' => {},
+            _ if byte.is_ascii_alphanumeric() => {},
+            _ => break,
+        }
+        start -= 1;
+    }
+
+    let receiver = before_accessor[start..].trim_start_matches(['.', '-', '>']);
+    let starts_with_identifier = receiver.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_');
+    starts_with_identifier.then(|| receiver.to_string())
+}
@@ -0,0 +1,198 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use dashmap::DashMap;
+use tower_lsp::lsp_types::{
+    Command, CompletionItem, CompletionItemKind, CompletionTextEdit, Position, Range, TextEdit,
+};
+
+use crate::text_pos::{byte_offset_of_column, text_width};
+
+/// Extensions offered as includable files. Extension-less files are offered
+/// too, since the Metal standard headers (`metal_stdlib`, …) have none.
+const HEADER_EXTENSIONS: &[&str] = &["h", "hh", "hpp", "hxx", "inc", "def", "metal"];
+
+/// The path being typed in an `#include` or `#import` directive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IncludeDirective {
+    /// `"` or `<` once the path has been opened, `None` right after the keyword.
+    pub delimiter: Option<char>,
+    /// Directory part typed so far, including its trailing `/` (`shaders/common/`).
+    pub directory: String,
+    /// File name fragment after the last `/`.
+    pub fragment: String,
+    /// Range of [`fragment`](Self::fragment), replaced by the chosen entry.
+    pub fragment_range: Range,
+}
+
+/// Parse the include directive the cursor is in, if the path is still open.
+pub(crate) fn include_directive_at(
+    text: &str,
+    position: Position,
+) -> Option<IncludeDirective> {
+    let line = text.lines().nth(position.line as usize).unwrap_or("");
+    let prefix = &line[..byte_offset_of_column(line, position.character)];
+    let trimmed = prefix.trim_start();
+    let after_hash = trimmed.strip_prefix('#')?.trim_start();
+    let rest = after_hash.strip_prefix("include").or_else(|| after_hash.strip_prefix("import"))?;
+    if !rest.is_empty() && !rest.starts_with([' ', '\t', '"', '<']) {
+        return None;
+    }
+
+    let rest = rest.trim_start();
+    let (delimiter, path) = match rest.chars().next() {
+        None => (None, ""),
+        Some(open @ ('"' | '<')) => (Some(open), &rest[1..]),
+        Some(_) => return None,
+    };
+    let close = if delimiter == Some('<') {
+        '>'
+    } else {
+        '"'
+    };
+    if path.contains(close) {
+        return None;
+    }
+
+    let (directory, fragment) = match path.rfind('/') {
+        Some(slash) => (&path[..=slash], &path[slash + 1..]),
+        None => ("", path),
+    };
+    let start = position.character - text_width(fragment);
+    Some(IncludeDirective {
+        delimiter,
+        directory: directory.to_string(),
+        fragment: fragment.to_string(),
+        fragment_range: Range::new(Position::new(position.line, start), position),
+    })
+}
+
+/// Directories searched for quoted and angle-bracket includes, in order.
+#[derive(Debug, Clone, Default)]
+pub struct IncludeSearchDirs {
+    /// The including file's directory followed by the project include paths.
+    pub quoted: Vec<PathBuf>,
+    /// System (SDK) include paths.
+    pub angled: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IncludeEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Directory listings for include completion, kept until the file watcher
+/// reports created or deleted files.
+#[derive(Debug, Default)]
+pub(crate) struct IncludeListingCache {
+    listings: DashMap<PathBuf, Arc<[IncludeEntry]>>,
+}
+
+impl IncludeListingCache {
+    pub fn clear(&self) {
+        self.listings.clear();
+    }
+
+    /// Subdirectories and header files in `dir`, sorted by name.
+    pub fn list(
+        &self,
+        dir: &Path,
+    ) -> Arc<[IncludeEntry]> {
+        if let Some(entries) = self.listings.get(dir) {
+            return entries.clone();
+        }
+        let entries: Arc<[IncludeEntry]> = read_include_dir(dir).into();
+        self.listings.insert(dir.to_path_buf(), entries.clone());
+        entries
+    }
+}
+
+fn read_include_dir(dir: &Path) -> Vec<IncludeEntry> {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<IncludeEntry> = read_dir
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            if name.starts_with('.') {
+                return None;
+            }
+            let path = entry.path();
+            let is_dir = path.is_dir();
+            let is_header = match path.extension().and_then(|ext| ext.to_str()) {
+                Some(ext) => HEADER_EXTENSIONS.contains(&ext),
+                None => true,
+            };
+            (is_dir || is_header).then_some(IncludeEntry {
+                name,
+                is_dir,
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries
+}
+
+/// Files and directories matching `directive` under `search_dirs`; earlier
+/// search directories shadow later ones with the same entry name.
+pub(crate) fn include_path_completions(
+    directive: &IncludeDirective,
+    search_dirs: &[PathBuf],
+    cache: &IncludeListingCache,
+) -> Vec<CompletionItem> {
+    let mut seen = std::collections::HashSet::new();
+    let mut items = Vec::new();
+    for search_dir in search_dirs {
+        let dir = search_dir.join(&directive.directory);
+        for entry in cache.list(&dir).iter() {
+            if !entry.name.starts_with(&directive.fragment) || !seen.insert(entry.name.clone()) {
+                continue;
+            }
+            items.push(include_entry_item(directive, entry, &dir));
+        }
+    }
+    items
+}
+
+fn include_entry_item(
+    directive: &IncludeDirective,
+    entry: &IncludeEntry,
+    dir: &Path,
+) -> CompletionItem {
+    let (kind, new_text, command) = if entry.is_dir {
+        let retrigger = Command {
+            title: "Suggest".to_string(),
+            command: "editor.action.triggerSuggest".to_string(),
+            arguments: None,
+        };
+        (CompletionItemKind::FOLDER, format!("{}/", entry.name), Some(retrigger))
+    } else {
+        (CompletionItemKind::FILE, entry.name.clone(), None)
+    };
+    CompletionItem {
+        label: entry.name.clone(),
+        kind: Some(kind),
+        detail: Some(dir.join(&entry.name).display().to_string()),
+        filter_text: Some(entry.name.clone()),
+        sort_text: Some(format!(
+            "{}_{}",
+            if entry.is_dir {
+                1
+            } else {
+                0
+            },
+            entry.name
+        )),
+        text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(directive.fragment_range, new_text))),
+        command,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/completion/include_paths_tests.rs"]
+mod tests;
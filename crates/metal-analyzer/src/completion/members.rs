@@ -0,0 +1,120 @@
+use std::collections::HashSet;
+
+use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, Position};
+
+use crate::definition::{
+    AstIndex, SharedStr, normalize_type_name, paths_match, symbol_rank::infer_local_identifier_type_name,
+};
+
+/// Typedef and alias hops followed before giving up on a receiver type.
+const MAX_ALIAS_HOPS: usize = 8;
+
+/// Fields and methods of the record that `receiver` evaluates to, resolved
+/// through the declared types in the document's AST index.
+pub(crate) fn ast_member_completions(
+    index: &AstIndex,
+    source_file: &str,
+    position: Position,
+    receiver: &str,
+) -> Vec<CompletionItem> {
+    let Some(record) = receiver_record(index, source_file, position, receiver) else {
+        return Vec::new();
+    };
+
+    let mut seen = HashSet::new();
+    index
+        .record_members(&record)
+        .into_iter()
+        .filter(|def| !def.name.starts_with("operator") && !def.name.is_empty())
+        .filter(|def| seen.insert((def.name.clone(), def.qual_type.clone())))
+        .map(|def| {
+            let kind = if def.kind == "CXXMethodDecl" {
+                CompletionItemKind::METHOD
+            } else {
+                CompletionItemKind::FIELD
+            };
+            CompletionItem {
+                label: def.name.to_string(),
+                kind: Some(kind),
+                detail: def.qual_type.as_ref().map(SharedStr::to_string).or_else(|| def.type_name.clone()),
+                sort_text: Some(format!("0_{}", def.name)),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Walk `receiver` (`a`, `a.b`, `a[i]->b`) one segment at a time, from the
+/// local declaration of the first identifier through field types.
+fn receiver_record(
+    index: &AstIndex,
+    source_file: &str,
+    position: Position,
+    receiver: &str,
+) -> Option<String> {
+    let mut segments = receiver.split("->").flat_map(|part| part.split('.')).map(strip_subscripts);
+
+    let first = segments.next()?;
+    let mut record = if first == "this" {
+        enclosing_record(index, source_file, position.line + 1)
+    } else {
+        let type_name =
+            infer_local_identifier_type_name(index, source_file, position.line + 1, position.character + 1, first)?;
+        resolve_record(index, &type_name)
+    }?;
+
+    for segment in segments {
+        let field =
+            index.record_members(&record).into_iter().find(|def| def.kind == "FieldDecl" && def.name == segment)?;
+        record = resolve_record(index, field.type_name.as_deref()?)?;
+    }
+    Some(record)
+}
+
+/// Follow typedefs and aliases from `type_name` to a record name.
+fn resolve_record(
+    index: &AstIndex,
+    type_name: &str,
+) -> Option<String> {
+    let mut name = type_name.to_string();
+    for _ in 0..MAX_ALIAS_HOPS {
+        let defs: Vec<_> = index.name_to_defs.get(name.as_str())?.iter().map(|&i| &index.defs[i]).collect();
+        if defs.iter().any(|def| matches!(def.kind.as_str(), "CXXRecordDecl" | "ClassTemplateSpecializationDecl")) {
+            return Some(name);
+        }
+        let alias = defs
+            .iter()
+            .find(|def| matches!(def.kind.as_str(), "TypedefDecl" | "TypeAliasDecl"))
+            .and_then(|def| def.qual_type.as_deref())
+            .and_then(normalize_type_name)?;
+        if alias == name {
+            return None;
+        }
+        name = alias;
+    }
+    None
+}
+
+/// The record whose body most likely contains `line` (1-based) in `source_file`.
+fn enclosing_record(
+    index: &AstIndex,
+    source_file: &str,
+    line: u32,
+) -> Option<String> {
+    index
+        .defs
+        .iter()
+        .filter(|def| paths_match(&def.file, source_file))
+        .filter(|def| matches!(def.kind.as_str(), "CXXRecordDecl" | "ClassTemplateSpecializationDecl"))
+        .filter(|def| def.line <= line)
+        .max_by_key(|def| def.line)
+        .map(|def| def.name.to_string())
+}
+
+fn strip_subscripts(segment: &str) -> &str {
+    segment.split('[').next().unwrap_or(segment).trim()
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/completion/members_tests.rs"]
+mod tests;
@@ -1,5 +1,13 @@
+pub(crate) mod auto_import;
 pub(crate) mod builtins;
 pub(crate) mod context;
+pub(crate) mod include_paths;
+pub(crate) mod members;
 pub(crate) mod provider;
+pub(crate) mod ranking;
+pub(crate) mod resolve;
 
-pub use self::provider::CompletionProvider;
+pub use self::{
+    include_paths::IncludeSearchDirs,
+    provider::{CompletionProvider, CompletionSources},
+};
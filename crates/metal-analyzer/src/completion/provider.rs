@@ -1,22 +1,67 @@
-use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, Documentation, MarkupContent, MarkupKind, Position};
+use std::collections::HashSet;
+
+use tower_lsp::lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionItemLabelDetails, CompletionItemTag, CompletionTextEdit,
+    Documentation, InsertTextFormat, MarkupContent, MarkupKind, Position, Range, TextEdit,
+};
 
 use crate::{
     completion::{
+        auto_import::{AutoImport, include_insertion_line},
         builtins::{
-            METAL_HEADERS, PREPROCESSOR_DIRECTIVES, TEXTURE_METHODS, builtin_to_completion_item, detect_function_name,
-            first_identifier,
+            DOC_COMMENT_TAGS, METAL_HEADERS, PREPROCESSOR_DIRECTIVES, TEXTURE_METHODS, builtin_to_completion_item,
+            detect_function_name, first_identifier,
         },
         context::{CursorContext, detect_context},
+        include_paths::{
+            IncludeDirective, IncludeListingCache, IncludeSearchDirs, include_directive_at, include_path_completions,
+        },
+        members::ast_member_completions,
+        ranking::{Locality, SelectionHistory, rank},
+        resolve::{CompletionData, builtin_include_line},
+    },
+    definition::{AstIndex, SharedStr, is_system_header, paths_match},
+    metal::{
+        builtins::{self, ATTRIBUTES, AttributeTarget, BuiltinKind, ShaderStage},
+        language_version::LanguageVersion,
     },
-    metal::builtins::{self, BuiltinKind},
     syntax::SyntaxTree,
 };
 
 /// Provides intelligent completion items for Metal Shading Language.
 pub struct CompletionProvider {
-    // Currently stateless – all data lives in the lazy-static builtins database.
-    // This struct exists so we can add per-session state later (e.g. workspace
-    // symbol indices).
+    // Builtins live in the lazy-static database; per-session state is limited
+    // to cached directory listings for include-path completion and the
+    // accepted items that feed ranking.
+    include_listings: IncludeListingCache,
+    selections: SelectionHistory,
+}
+
+/// Project state completion can draw on beyond the document itself.
+pub struct CompletionSources<'a> {
+    /// The document's cached AST index and its file path.
+    pub ast: Option<(&'a AstIndex, &'a str)>,
+    /// Directories listed for `#include` path completion.
+    pub include_dirs: IncludeSearchDirs,
+    /// Whether snippet items and tab stops may be offered: the client
+    /// supports them and `completion.snippets` is on.
+    pub snippets: bool,
+    /// Language version targeted; builtins introduced later are left out.
+    pub language_version: Option<LanguageVersion>,
+    /// Project symbols in headers the document does not include yet.
+    pub auto_imports: Vec<AutoImport>,
+}
+
+impl Default for CompletionSources<'_> {
+    fn default() -> Self {
+        Self {
+            ast: None,
+            include_dirs: IncludeSearchDirs::default(),
+            snippets: true,
+            language_version: None,
+            auto_imports: Vec::new(),
+        }
+    }
 }
 
 impl Default for CompletionProvider {
@@ -27,7 +72,23 @@ impl Default for CompletionProvider {
 
 impl CompletionProvider {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            include_listings: IncludeListingCache::default(),
+            selections: SelectionHistory::default(),
+        }
+    }
+
+    /// Remember that the client accepted the item labelled `label`.
+    pub fn record_selection(
+        &self,
+        label: &str,
+    ) {
+        self.selections.record(label);
+    }
+
+    /// Forget cached include directory listings after files were created or deleted.
+    pub fn invalidate_include_listings(&self) {
+        self.include_listings.clear();
     }
 
     /// Build a completion list for the given document text and cursor position.
@@ -36,28 +97,90 @@ impl CompletionProvider {
         text: Option<&str>,
         position: Position,
         snapshot: Option<&SyntaxTree>,
+    ) -> Vec<CompletionItem> {
+        self.provide_with_sources(text, position, snapshot, &CompletionSources::default())
+    }
+
+    /// Like [`provide`](Self::provide), additionally resolving `.` and `->`
+    /// receivers through the AST index and listing include search directories.
+    pub fn provide_with_sources(
+        &self,
+        text: Option<&str>,
+        position: Position,
+        snapshot: Option<&SyntaxTree>,
+        sources: &CompletionSources<'_>,
     ) -> Vec<CompletionItem> {
         let text = text.unwrap_or("");
         let ctx = detect_context(text, position, snapshot.map(|s| s.root()));
 
         match ctx {
-            CursorContext::Attribute => self.attribute_completions(),
+            CursorContext::Attribute {
+                target,
+                stage,
+            } => self.attribute_completions(target, stage, sources.snippets),
             CursorContext::MemberAccess {
                 ref receiver,
-            } => self.member_completions(receiver),
+            } => {
+                let record_members = sources
+                    .ast
+                    .map(|(index, source_file)| ast_member_completions(index, source_file, position, receiver))
+                    .unwrap_or_default();
+                if record_members.is_empty() {
+                    self.member_completions(receiver)
+                } else {
+                    record_members
+                }
+            },
+            CursorContext::Comment | CursorContext::Literal => Vec::new(),
+            CursorContext::DocComment {
+                marker,
+            } => self.doc_comment_completions(marker),
             CursorContext::Preprocessor => self.preprocessor_completions(),
-            CursorContext::Include => self.include_completions(),
-            CursorContext::General => self.general_completions(text),
+            CursorContext::Include => self.include_completions(text, position, &sources.include_dirs),
+            CursorContext::General => self.general_completions(text, position, sources),
         }
     }
 
     // ───────────────────────────── completions ──────────────────────────────
 
-    fn attribute_completions(&self) -> Vec<CompletionItem> {
-        builtins::all()
+    /// Attributes valid on `target`, or every attribute when it is unknown.
+    /// Without snippet support only the attribute name is inserted.
+    fn attribute_completions(
+        &self,
+        target: Option<AttributeTarget>,
+        stage: Option<ShaderStage>,
+        snippets: bool,
+    ) -> Vec<CompletionItem> {
+        ATTRIBUTES
             .iter()
-            .filter(|e| e.kind == BuiltinKind::Attribute)
-            .map(|e| builtin_to_completion_item(e, "0"))
+            .filter(|spec| target.is_none_or(|target| spec.applies_to(target, stage)))
+            .map(|spec| CompletionItem {
+                label: spec.signature.to_string(),
+                kind: Some(CompletionItemKind::PROPERTY),
+                detail: Some("attribute".to_string()),
+                documentation: Some(Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: spec.doc.to_string(),
+                })),
+                insert_text: Some(
+                    if snippets {
+                        spec.snippet
+                    } else {
+                        spec.name
+                    }
+                    .to_string(),
+                ),
+                insert_text_format: Some(if snippets {
+                    InsertTextFormat::SNIPPET
+                } else {
+                    InsertTextFormat::PLAIN_TEXT
+                }),
+                filter_text: Some(spec.name.to_string()),
+                // Deprecated attributes sort after the rest and are struck through.
+                sort_text: Some(format!("{}_{}", u8::from(builtins::deprecation(spec.name).is_some()), spec.name)),
+                tags: builtins::deprecation(spec.name).map(|_| vec![CompletionItemTag::DEPRECATED]),
+                ..Default::default()
+            })
             .collect()
     }
 
@@ -66,6 +189,7 @@ impl CompletionProvider {
         receiver: &str,
     ) -> Vec<CompletionItem> {
         let mut items = Vec::new();
+        let receiver = receiver.rsplit(['.', '>']).next().unwrap_or(receiver);
 
         let is_vector = Self::looks_like_vector_type(receiver);
 
@@ -132,11 +256,82 @@ impl CompletionProvider {
             .collect()
     }
 
-    fn include_completions(&self) -> Vec<CompletionItem> {
-        METAL_HEADERS
+    fn doc_comment_completions(
+        &self,
+        marker: Option<char>,
+    ) -> Vec<CompletionItem> {
+        DOC_COMMENT_TAGS
             .iter()
             .enumerate()
-            .map(|(i, (header, doc))| CompletionItem {
+            .map(|(i, (tag, doc))| {
+                let label = format!("{}{tag}", marker.unwrap_or('@'));
+                // When the marker is already typed, only the tag name replaces the word.
+                let insert_text = if marker.is_some() {
+                    tag.to_string()
+                } else {
+                    label.clone()
+                };
+                CompletionItem {
+                    label,
+                    kind: Some(CompletionItemKind::KEYWORD),
+                    detail: Some("Documentation tag".to_string()),
+                    documentation: Some(Documentation::MarkupContent(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: doc.to_string(),
+                    })),
+                    insert_text: Some(insert_text),
+                    filter_text: Some(tag.to_string()),
+                    sort_text: Some(format!("{i:02}_{tag}")),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    fn include_completions(
+        &self,
+        text: &str,
+        position: Position,
+        include_dirs: &IncludeSearchDirs,
+    ) -> Vec<CompletionItem> {
+        let directive = include_directive_at(text, position);
+        let mut items = match &directive {
+            Some(
+                directive @ IncludeDirective {
+                    delimiter: Some(open),
+                    ..
+                },
+            ) => {
+                let search_dirs = if *open == '<' {
+                    &include_dirs.angled
+                } else {
+                    &include_dirs.quoted
+                };
+                include_path_completions(directive, search_dirs, &self.include_listings)
+            },
+            _ => Vec::new(),
+        };
+
+        // The bundled standard headers cover toolchains that could not be listed.
+        let offer_standard_headers = directive.as_ref().is_none_or(|directive| {
+            directive.directory.is_empty() && (directive.delimiter != Some('"') || items.is_empty())
+        });
+        if !offer_standard_headers {
+            return items;
+        }
+        for (i, (header, doc)) in METAL_HEADERS.iter().enumerate() {
+            if items.iter().any(|item| item.label == *header) {
+                continue;
+            }
+            let (insert_text, text_edit) = match &directive {
+                Some(IncludeDirective {
+                    delimiter: Some(_),
+                    fragment_range,
+                    ..
+                }) => (None, Some(CompletionTextEdit::Edit(TextEdit::new(*fragment_range, header.to_string())))),
+                _ => (Some(format!("<{header}>")), None),
+            };
+            items.push(CompletionItem {
                 label: header.to_string(),
                 kind: Some(CompletionItemKind::FILE),
                 detail: Some("Metal standard library header".to_string()),
@@ -144,35 +339,131 @@ impl CompletionProvider {
                     kind: MarkupKind::Markdown,
                     value: doc.to_string(),
                 })),
-                insert_text: Some(format!("<{header}>")),
+                insert_text,
+                text_edit,
                 sort_text: Some(format!("{i:02}_{header}")),
                 ..Default::default()
-            })
-            .collect()
+            });
+        }
+        items
     }
 
+    /// Document symbols, project header symbols, symbols of headers not yet
+    /// included and builtins, ranked by locality, nearby uses and earlier
+    /// selections.
     fn general_completions(
         &self,
         text: &str,
+        position: Position,
+        sources: &CompletionSources<'_>,
     ) -> Vec<CompletionItem> {
-        let mut items: Vec<CompletionItem> = builtins::all()
+        let document_items = self.document_symbol_completions(text);
+        let document_names: HashSet<&str> = document_items.iter().map(|item| item.label.as_str()).collect();
+        let project_items = sources
+            .ast
+            .map(|(index, source_file)| Self::project_header_completions(index, source_file, &document_names))
+            .unwrap_or_default();
+        let known: HashSet<&str> =
+            document_names.iter().copied().chain(project_items.iter().map(|item| item.label.as_str())).collect();
+        let include_line = include_insertion_line(text);
+        let import_items: Vec<CompletionItem> = sources
+            .auto_imports
             .iter()
-            .map(|e| {
-                let sort_prefix = match e.kind {
-                    BuiltinKind::Keyword => "3",
-                    BuiltinKind::Type => "2a",
-                    BuiltinKind::Function => "2b",
-                    BuiltinKind::Constant => "2c",
-                    BuiltinKind::Attribute => "4",
-                    BuiltinKind::Snippet => "5",
-                };
-                builtin_to_completion_item(e, sort_prefix)
-            })
+            .filter(|import| !known.contains(import.def.name.as_str()))
+            .filter_map(|import| Self::auto_import_completion(import, include_line))
             .collect();
 
-        items.extend(self.document_symbol_completions(text));
+        let mut items: Vec<(Locality, CompletionItem)> =
+            project_items.into_iter().map(|item| (Locality::ProjectHeader, item)).collect();
+        items.extend(import_items.into_iter().map(|item| (Locality::Workspace, item)));
+        items.extend(document_items.into_iter().map(|item| (Locality::CurrentFile, item)));
+        let include_line = builtin_include_line(text);
+        let builtins = builtins::all().iter().enumerate().filter(|(_, e)| {
+            (sources.snippets || e.kind != BuiltinKind::Snippet) && e.is_available_in(sources.language_version)
+        });
+        items.extend(builtins.map(|(index, e)| {
+            let sort_prefix = match e.kind {
+                BuiltinKind::Keyword => "3",
+                BuiltinKind::Type => "2a",
+                BuiltinKind::Function => "2b",
+                BuiltinKind::Constant => "2c",
+                BuiltinKind::Attribute => "4",
+                BuiltinKind::Snippet => "5",
+            };
+            (Locality::Builtin, builtin_to_completion_item(index, e, sort_prefix, include_line))
+        }));
 
-        items
+        rank(items, text, position, &self.selections)
+    }
+
+    /// Functions and types declared in the project headers the document
+    /// includes, skipping SDK headers and names the document declares itself.
+    fn project_header_completions(
+        index: &AstIndex,
+        source_file: &str,
+        document_names: &HashSet<&str>,
+    ) -> Vec<CompletionItem> {
+        let mut seen = HashSet::new();
+        index
+            .defs
+            .iter()
+            .filter(|def| !def.name.is_empty() && !def.name.starts_with("operator"))
+            .filter(|def| !is_system_header(&def.file) && !paths_match(&def.file, source_file))
+            .filter(|def| !document_names.contains(def.name.as_str()))
+            .filter_map(|def| {
+                let kind = declaration_kind(&def.kind)?;
+                seen.insert(def.name.clone()).then_some((def, kind))
+            })
+            .map(|(def, kind)| {
+                let header = std::path::Path::new(&def.file)
+                    .file_name()
+                    .map_or_else(|| def.file.to_string(), |name| name.to_string_lossy().into_owned());
+                CompletionItem {
+                    label: def.name.to_string(),
+                    kind: Some(kind),
+                    detail: Some(
+                        def.qual_type.as_ref().map_or_else(|| format!("Declared in {header}"), SharedStr::to_string),
+                    ),
+                    sort_text: Some(format!("1_{}", def.name)),
+                    data: CompletionData::ProjectSymbol {
+                        file: def.file.to_string(),
+                        line: def.line,
+                    }
+                    .to_value(),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    /// The item for a symbol of a header the document does not include,
+    /// inserting its `#include` on `include_line` when accepted.
+    fn auto_import_completion(
+        import: &AutoImport,
+        include_line: u32,
+    ) -> Option<CompletionItem> {
+        let def = &import.def;
+        let include = format!("#include \"{}\"", import.spelling);
+        let position = Position::new(include_line, 0);
+        Some(CompletionItem {
+            label: def.name.to_string(),
+            label_details: Some(CompletionItemLabelDetails {
+                detail: None,
+                description: Some("auto-import".to_string()),
+            }),
+            kind: Some(declaration_kind(&def.kind)?),
+            detail: Some(
+                def.qual_type.as_ref().map_or_else(|| include.clone(), |qual_type| format!("{qual_type} ({include})")),
+            ),
+            sort_text: Some(format!("1_{}", def.name)),
+            additional_text_edits: Some(vec![TextEdit::new(Range::new(position, position), format!("{include}\n"))]),
+            data: CompletionData::ProjectSymbol {
+                file: def.file.to_string(),
+                line: def.line,
+            }
+            .to_value(),
+            ..Default::default()
+        })
     }
 
     /// Lightweight scan of the current document to offer completions for
@@ -290,3 +581,14 @@ impl CompletionProvider {
             || lower.ends_with("coord")
     }
 }
+
+/// Item kind of a declaration offered from a project header.
+fn declaration_kind(kind: &str) -> Option<CompletionItemKind> {
+    match kind {
+        "FunctionDecl" => Some(CompletionItemKind::FUNCTION),
+        "CXXRecordDecl" => Some(CompletionItemKind::STRUCT),
+        "EnumDecl" => Some(CompletionItemKind::ENUM),
+        "TypedefDecl" | "TypeAliasDecl" => Some(CompletionItemKind::TYPE_PARAMETER),
+        _ => None,
+    }
+}
@@ -0,0 +1,118 @@
+//! Ordering of general completions.
+//!
+//! Items are tiered by where their symbol lives: the current document, then
+//! included project headers, then headers still to be included, then Metal
+//! builtins. Within a tier, identifiers already used near the cursor and
+//! items accepted earlier in the session sort first.
+//! Clients still filter and re-sort by how well the typed prefix matches;
+//! `sort_text` decides between equally good matches.
+
+use std::collections::HashMap;
+
+use dashmap::DashMap;
+use tower_lsp::lsp_types::{Command, CompletionItem, Position};
+
+use crate::text_pos::byte_offset_of_column;
+
+/// Command attached to general completion items; the client runs it when an
+/// item is accepted, with the item label as the only argument.
+pub const RECORD_COMPLETION_COMMAND: &str = "metal-analyzer.recordCompletion";
+
+/// Lines above and below the cursor scanned for nearby identifiers.
+const NEARBY_LINES: usize = 40;
+/// An accepted item counts as this many nearby uses.
+const SELECTION_WEIGHT: u32 = 4;
+const MAX_SCORE: u32 = 999;
+
+/// Where a completion item's symbol is declared, in ranking order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Locality {
+    CurrentFile,
+    ProjectHeader,
+    /// A project header the document does not include yet.
+    Workspace,
+    Builtin,
+}
+
+/// How often each completion label was accepted during this session.
+#[derive(Debug, Default)]
+pub(crate) struct SelectionHistory {
+    counts: DashMap<String, u32>,
+}
+
+impl SelectionHistory {
+    pub fn record(
+        &self,
+        label: &str,
+    ) {
+        *self.counts.entry(label.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn count(
+        &self,
+        label: &str,
+    ) -> u32 {
+        self.counts.get(label).map_or(0, |count| *count)
+    }
+}
+
+/// Assign `sort_text` from locality, nearby uses and selection history, and
+/// attach the command that records the selection.
+pub(crate) fn rank(
+    items: Vec<(Locality, CompletionItem)>,
+    text: &str,
+    position: Position,
+    history: &SelectionHistory,
+) -> Vec<CompletionItem> {
+    let nearby = nearby_identifiers(text, position);
+    items
+        .into_iter()
+        .map(|(locality, mut item)| {
+            let uses = nearby.get(item.label.as_str()).copied().unwrap_or(0);
+            let score = uses.saturating_add(history.count(&item.label).saturating_mul(SELECTION_WEIGHT)).min(MAX_SCORE);
+            let base = item.sort_text.take().unwrap_or_else(|| item.label.clone());
+            item.sort_text = Some(format!("{}{:03}_{base}", locality as u8, MAX_SCORE - score));
+            item.command = Some(Command {
+                title: String::new(),
+                command: RECORD_COMPLETION_COMMAND.to_string(),
+                arguments: Some(vec![item.label.clone().into()]),
+            });
+            item
+        })
+        .collect()
+}
+
+/// Identifier uses within `NEARBY_LINES` of the cursor, not counting the one
+/// being typed.
+fn nearby_identifiers(
+    text: &str,
+    position: Position,
+) -> HashMap<&str, u32> {
+    let cursor_line = position.line as usize;
+    let first_line = cursor_line.saturating_sub(NEARBY_LINES);
+    let mut counts = HashMap::new();
+    for (line_index, line) in text.lines().enumerate().skip(first_line).take(2 * NEARBY_LINES + 1) {
+        let cursor = (line_index == cursor_line).then(|| byte_offset_of_column(line, position.character));
+        let mut start = None;
+        for (i, ch) in line.char_indices().chain(std::iter::once((line.len(), ' '))) {
+            let is_word = ch.is_alphanumeric() || ch == '_';
+            match (start, is_word) {
+                (None, true) => start = Some(i),
+                (Some(word_start), false) => {
+                    let word = &line[word_start..i];
+                    let at_cursor = cursor.is_some_and(|cursor| (word_start..=i).contains(&cursor));
+                    if !at_cursor && !word.starts_with(|c: char| c.is_ascii_digit()) {
+                        *counts.entry(word).or_insert(0) += 1;
+                    }
+                    start = None;
+                },
+                _ => {},
+            }
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/completion/ranking_tests.rs"]
+mod tests;
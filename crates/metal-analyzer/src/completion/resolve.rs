@@ -0,0 +1,143 @@
+//! Details filled in by `completionItem/resolve`.
+//!
+//! General completion lists hold thousands of builtins, so their items carry
+//! only what the client filters and sorts by. Documentation, the
+//! declaration of a project symbol and the `#include` a builtin needs are
+//! attached once the user selects an item, from the [`CompletionData`] it
+//! carries.
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{CompletionItem, Documentation, MarkupContent, MarkupKind, Position, Range, TextEdit};
+
+use crate::{
+    completion::auto_import::{include_insertion_line, is_include_line},
+    metal::builtins,
+};
+
+/// Header whose `#include` a builtin needs in a document including nothing.
+const BUILTINS_HEADER: &str = "metal_stdlib";
+
+/// What a completion item resolves from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "camelCase")]
+pub(crate) enum CompletionData {
+    /// The builtin at `index` in the builtin database.
+    #[serde(rename_all = "camelCase")]
+    Builtin {
+        index: usize,
+        /// Line where `#include <metal_stdlib>` is inserted, when the
+        /// document includes nothing.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        include_line: Option<u32>,
+    },
+    /// A declaration in a project header, on the 1-based `line` of `file`.
+    ProjectSymbol {
+        file: String,
+        line: u32,
+    },
+}
+
+impl CompletionData {
+    pub(crate) fn to_value(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self).ok()
+    }
+
+    pub(crate) fn of(item: &CompletionItem) -> Option<Self> {
+        item.data.clone().and_then(|data| serde_json::from_value(data).ok())
+    }
+}
+
+/// The file whose text [`resolve_completion`] needs for `item`.
+pub(crate) fn completion_source_file(item: &CompletionItem) -> Option<String> {
+    match CompletionData::of(item)? {
+        CompletionData::ProjectSymbol {
+            file,
+            ..
+        } => Some(file),
+        CompletionData::Builtin {
+            ..
+        } => None,
+    }
+}
+
+/// Attach the details of `item`; `source` is the text of the file named by
+/// [`completion_source_file`]. Items without completion data are returned
+/// unchanged.
+pub(crate) fn resolve_completion(
+    mut item: CompletionItem,
+    source: Option<&str>,
+) -> CompletionItem {
+    match CompletionData::of(&item) {
+        Some(CompletionData::Builtin {
+            index,
+            include_line,
+        }) => {
+            let Some(entry) = builtins::all().get(index) else {
+                return item;
+            };
+            if !entry.documentation.is_empty() {
+                item.documentation = Some(markdown(entry.documentation.clone()));
+            }
+            if let Some(line) = include_line {
+                let position = Position::new(line, 0);
+                item.additional_text_edits = Some(vec![TextEdit::new(
+                    Range::new(position, position),
+                    format!("#include <{BUILTINS_HEADER}>\n"),
+                )]);
+            }
+        },
+        Some(CompletionData::ProjectSymbol {
+            line,
+            ..
+        }) => {
+            if let Some((declaration, docs)) = source.and_then(|source| declaration_docs(source, line)) {
+                let mut value = format!("```metal\n{declaration}\n```\n");
+                if !docs.is_empty() {
+                    value.push_str("\n---\n\n");
+                    value.push_str(&docs);
+                    value.push('\n');
+                }
+                item.documentation = Some(markdown(value));
+            }
+        },
+        None => {},
+    }
+    item
+}
+
+/// Line `#include <metal_stdlib>` is inserted at for a builtin completed in
+/// `text`, when `text` has no `#include` at all.
+pub(crate) fn builtin_include_line(text: &str) -> Option<u32> {
+    (!text.lines().any(is_include_line)).then(|| include_insertion_line(text))
+}
+
+/// The declaration on the 1-based `line` of `source` and the `///` comment
+/// lines above it.
+fn declaration_docs(
+    source: &str,
+    line: u32,
+) -> Option<(String, String)> {
+    let lines: Vec<&str> = source.lines().collect();
+    let index = (line as usize).checked_sub(1)?;
+    let declaration = lines.get(index)?.trim().to_string();
+    let mut docs: Vec<&str> = lines[..index]
+        .iter()
+        .rev()
+        .map(|line| line.trim())
+        .skip_while(|line| line.starts_with('[') || line.starts_with("template"))
+        .map_while(|line| line.strip_prefix("///").map(str::trim))
+        .collect();
+    docs.reverse();
+    Some((declaration, docs.join("\n")))
+}
+
+fn markdown(value: String) -> Documentation {
+    Documentation::MarkupContent(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value,
+    })
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/completion/resolve_tests.rs"]
+mod tests;
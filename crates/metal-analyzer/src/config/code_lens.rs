@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeLensSettings {
+    /// Show the workspace reference count above functions and structs.
+    pub references: bool,
+    /// Show "Run diagnostics" and "Re-index file" at the top of each file.
+    pub file_actions: bool,
+}
+
+impl Default for CodeLensSettings {
+    fn default() -> Self {
+        Self {
+            references: true,
+            file_actions: true,
+        }
+    }
+}
+
+impl CodeLensSettings {
+    pub(crate) fn apply_patch(
+        &mut self,
+        patch: CodeLensSettingsPatch,
+    ) {
+        if let Some(v) = patch.references {
+            self.references = v;
+        }
+        if let Some(v) = patch.file_actions {
+            self.file_actions = v;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct CodeLensSettingsPatch {
+    pub(crate) references: Option<bool>,
+    pub(crate) file_actions: Option<bool>,
+    #[serde(flatten)]
+    pub(crate) _extra: HashMap<String, Value>,
+}
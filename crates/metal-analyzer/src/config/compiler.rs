@@ -1,18 +1,102 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
 
 use serde::Deserialize;
 use serde_json::Value;
 
-use crate::metal::compiler::CompilerPlatform;
+use crate::metal::{
+    compiler::CompilerPlatform, compiler_overrides::CompilerOverride, language_version::LanguageVersion,
+};
 
-#[derive(Debug, Clone, PartialEq, Default)]
+pub const MIN_ARTIFACTS_MAX_SIZE_MB: u64 = 1;
+pub const MAX_ARTIFACTS_MAX_SIZE_MB: u64 = 4096;
+pub const DEFAULT_AST_DUMP_TIMEOUT_MS: u64 = 30_000;
+pub const MIN_AST_DUMP_TIMEOUT_MS: u64 = 1_000;
+pub const MAX_AST_DUMP_TIMEOUT_MS: u64 = 600_000;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct CompilerSettings {
     pub include_paths: Vec<String>,
+    /// Include prefixes mapped to the directory they name, e.g. `generated`
+    /// to a codegen output folder, so `#include "generated/matmul.h"`
+    /// resolves without that folder's parent on the include path.
+    pub virtual_include_roots: BTreeMap<String, String>,
     pub extra_flags: Vec<String>,
     pub platform: CompilerPlatform,
+    /// Standard compiled against when the flags pass no `-std=`; `None`
+    /// (`auto`) leaves it to the flags or the compiler default.
+    pub language_version: Option<LanguageVersion>,
+    /// Base directory for compiler temp files; `None` uses the system temp dir.
+    pub temp_dir: Option<String>,
+    /// Keep the temp translation unit of compiles that report errors.
+    pub keep_artifacts: bool,
+    pub artifacts_max_size_mb: u64,
+    /// AST dumps running longer are killed and navigation falls back to
+    /// name lookup.
+    pub ast_dump_timeout_ms: u64,
+    /// Load a precompiled `<metal_stdlib>` in diagnostics compiles.
+    pub precompiled_headers: bool,
+    /// Show per-entry-point statistics from a compile in hovers on entry
+    /// point names.
+    pub kernel_stats: bool,
+    /// Flags and include paths added for files matching a path glob.
+    pub overrides: Vec<CompilerOverride>,
+}
+
+impl Default for CompilerSettings {
+    fn default() -> Self {
+        Self {
+            include_paths: Vec::new(),
+            virtual_include_roots: BTreeMap::new(),
+            extra_flags: Vec::new(),
+            platform: CompilerPlatform::default(),
+            language_version: None,
+            temp_dir: None,
+            keep_artifacts: false,
+            artifacts_max_size_mb: 64,
+            ast_dump_timeout_ms: DEFAULT_AST_DUMP_TIMEOUT_MS,
+            precompiled_headers: true,
+            kernel_stats: false,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+/// What a change of the `compiler.*` settings makes stale, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CompilerInvalidation {
+    /// Only where compile artifacts go changed.
+    Nothing,
+    /// Warning flags changed: every file's diagnostics.
+    Diagnostics,
+    /// Defines, include paths, the platform or other flags changed: every
+    /// file's diagnostics, inactive regions and include paths.
+    Preprocessor,
 }
 
 impl CompilerSettings {
+    /// What switching from `previous` to these settings invalidates.
+    pub fn invalidation(
+        &self,
+        previous: &Self,
+    ) -> CompilerInvalidation {
+        if self.include_paths != previous.include_paths
+            || self.virtual_include_roots != previous.virtual_include_roots
+            || self.platform != previous.platform
+            || self.language_version != previous.language_version
+            || self.overrides != previous.overrides
+            || non_warning_flags(&self.extra_flags) != non_warning_flags(&previous.extra_flags)
+        {
+            CompilerInvalidation::Preprocessor
+        } else if self.extra_flags != previous.extra_flags {
+            CompilerInvalidation::Diagnostics
+        } else {
+            CompilerInvalidation::Nothing
+        }
+    }
+
     pub(crate) fn apply_patch(
         &mut self,
         patch: CompilerSettingsPatch,
@@ -20,27 +104,87 @@ impl CompilerSettings {
         if let Some(v) = patch.include_paths {
             self.include_paths = v;
         }
+        if let Some(v) = patch.virtual_include_roots {
+            self.virtual_include_roots = v;
+        }
         if let Some(v) = patch.extra_flags {
             self.extra_flags = v;
         }
         if let Some(v) = patch.platform {
             self.platform = CompilerPlatform::from_setting_value(&v);
         }
+        if let Some(v) = patch.language_version {
+            self.language_version = LanguageVersion::parse(&v);
+        }
+        if let Some(v) = patch.temp_dir {
+            self.temp_dir = Some(v);
+        }
+        if let Some(v) = patch.keep_artifacts {
+            self.keep_artifacts = v;
+        }
+        if let Some(v) = patch.artifacts_max_size_mb {
+            self.artifacts_max_size_mb = v;
+        }
+        if let Some(v) = patch.ast_dump_timeout_ms {
+            self.ast_dump_timeout_ms = v;
+        }
+        if let Some(v) = patch.precompiled_headers {
+            self.precompiled_headers = v;
+        }
+        if let Some(v) = patch.kernel_stats {
+            self.kernel_stats = v;
+        }
+        if let Some(v) = patch.overrides {
+            self.overrides = v;
+        }
     }
 
     pub(crate) fn normalize(&mut self) {
         self.include_paths =
             self.include_paths.iter().map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect();
+        self.virtual_include_roots = std::mem::take(&mut self.virtual_include_roots)
+            .into_iter()
+            .map(|(prefix, dir)| (prefix.trim().trim_matches('/').to_string(), dir.trim().to_string()))
+            .filter(|(prefix, dir)| !prefix.is_empty() && !dir.is_empty())
+            .collect();
         self.extra_flags = self.extra_flags.iter().map(|f| f.trim().to_string()).filter(|f| !f.is_empty()).collect();
+        self.temp_dir = self.temp_dir.as_deref().map(str::trim).filter(|d| !d.is_empty()).map(str::to_string);
+        self.artifacts_max_size_mb =
+            self.artifacts_max_size_mb.clamp(MIN_ARTIFACTS_MAX_SIZE_MB, MAX_ARTIFACTS_MAX_SIZE_MB);
+        self.ast_dump_timeout_ms = self.ast_dump_timeout_ms.clamp(MIN_AST_DUMP_TIMEOUT_MS, MAX_AST_DUMP_TIMEOUT_MS);
+        self.overrides.iter_mut().for_each(CompilerOverride::normalize);
+        self.overrides.retain(|entry| !entry.path_glob.is_empty());
     }
+
+    pub fn artifacts_max_size_bytes(&self) -> u64 {
+        self.artifacts_max_size_mb.saturating_mul(1024 * 1024)
+    }
+
+    pub fn ast_dump_timeout(&self) -> Duration {
+        Duration::from_millis(self.ast_dump_timeout_ms)
+    }
+}
+
+/// Flags other than `-W...` and `-w`, which only change what is reported.
+fn non_warning_flags(flags: &[String]) -> Vec<&str> {
+    flags.iter().map(String::as_str).filter(|flag| !flag.starts_with("-W") && *flag != "-w").collect()
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
 #[serde(default, rename_all = "camelCase")]
 pub(crate) struct CompilerSettingsPatch {
     pub(crate) include_paths: Option<Vec<String>>,
+    pub(crate) virtual_include_roots: Option<BTreeMap<String, String>>,
     pub(crate) extra_flags: Option<Vec<String>>,
     pub(crate) platform: Option<String>,
+    pub(crate) language_version: Option<String>,
+    pub(crate) temp_dir: Option<String>,
+    pub(crate) keep_artifacts: Option<bool>,
+    pub(crate) artifacts_max_size_mb: Option<u64>,
+    pub(crate) ast_dump_timeout_ms: Option<u64>,
+    pub(crate) precompiled_headers: Option<bool>,
+    pub(crate) kernel_stats: Option<bool>,
+    pub(crate) overrides: Option<Vec<CompilerOverride>>,
     #[serde(flatten)]
     pub(crate) _extra: HashMap<String, Value>,
 }
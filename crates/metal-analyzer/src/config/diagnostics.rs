@@ -1,8 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use serde::Deserialize;
 use serde_json::Value;
 
+use crate::ide::threadgroup_memory::DEFAULT_THREADGROUP_MEMORY_LIMIT;
+
 pub const MIN_DIAGNOSTIC_DEBOUNCE_MS: u64 = 50;
 pub const MAX_DIAGNOSTIC_DEBOUNCE_MS: u64 = 5000;
 
@@ -20,21 +22,69 @@ impl DiagnosticsScope {
     }
 }
 
+/// How diagnostics for a header are computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum HeaderContext {
+    /// Compile the `.metal` files that include the header and keep the
+    /// diagnostics reported in it, so macros they define are honored.
+    #[default]
+    Owner,
+    /// Compile the header on its own.
+    Standalone,
+    /// Both, with duplicates merged.
+    Both,
+}
+
+impl HeaderContext {
+    pub fn uses_owner(self) -> bool {
+        matches!(self, HeaderContext::Owner | HeaderContext::Both)
+    }
+
+    pub fn uses_standalone(self) -> bool {
+        matches!(self, HeaderContext::Standalone | HeaderContext::Both)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct DiagnosticsSettings {
     pub on_type: bool,
+    /// Publish syntax errors from the parser on every change, ahead of the
+    /// compiler diagnostics.
+    pub syntax: bool,
     pub on_save: bool,
     pub debounce_ms: u64,
     pub scope: DiagnosticsScope,
+    pub header_context: HeaderContext,
+    /// Hint at `#include`s of project headers the file does not use.
+    pub unused_includes: bool,
+    /// Bytes of threadgroup variables a kernel may declare before it is
+    /// warned about; `0` turns the warning off.
+    pub threadgroup_memory_limit: u64,
+    /// Hint at `static` functions and constants nothing in the workspace
+    /// uses.
+    pub unused_symbols: bool,
+    /// Names never reported as unused.
+    pub unused_symbols_allow: Vec<String>,
+    /// Paths and globs whose diagnostics are not published, independent of
+    /// `indexing.excludePaths`.
+    pub exclude: Vec<String>,
 }
 
 impl Default for DiagnosticsSettings {
     fn default() -> Self {
         Self {
             on_type: true,
+            syntax: true,
             on_save: true,
             debounce_ms: 500,
             scope: DiagnosticsScope::OpenFiles,
+            header_context: HeaderContext::Owner,
+            unused_includes: true,
+            threadgroup_memory_limit: DEFAULT_THREADGROUP_MEMORY_LIMIT,
+            unused_symbols: true,
+            unused_symbols_allow: Vec::new(),
+            exclude: Vec::new(),
         }
     }
 }
@@ -47,6 +97,9 @@ impl DiagnosticsSettings {
         if let Some(v) = patch.on_type {
             self.on_type = v;
         }
+        if let Some(v) = patch.syntax {
+            self.syntax = v;
+        }
         if let Some(v) = patch.on_save {
             self.on_save = v;
         }
@@ -56,10 +109,36 @@ impl DiagnosticsSettings {
         if let Some(v) = patch.scope {
             self.scope = v;
         }
+        if let Some(v) = patch.header_context {
+            self.header_context = v;
+        }
+        if let Some(v) = patch.unused_includes {
+            self.unused_includes = v;
+        }
+        if let Some(v) = patch.threadgroup_memory_limit {
+            self.threadgroup_memory_limit = v;
+        }
+        if let Some(v) = patch.unused_symbols {
+            self.unused_symbols = v;
+        }
+        if let Some(v) = patch.unused_symbols_allow {
+            self.unused_symbols_allow = v;
+        }
+        if let Some(v) = patch.exclude {
+            self.exclude = v;
+        }
     }
 
     pub(crate) fn normalize(&mut self) {
         self.debounce_ms = self.debounce_ms.clamp(MIN_DIAGNOSTIC_DEBOUNCE_MS, MAX_DIAGNOSTIC_DEBOUNCE_MS);
+        let mut seen = HashSet::new();
+        self.exclude = self
+            .exclude
+            .iter()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .filter(|p| seen.insert(p.clone()))
+            .collect();
     }
 }
 
@@ -67,9 +146,16 @@ impl DiagnosticsSettings {
 #[serde(default, rename_all = "camelCase")]
 pub(crate) struct DiagnosticsSettingsPatch {
     pub(crate) on_type: Option<bool>,
+    pub(crate) syntax: Option<bool>,
     pub(crate) on_save: Option<bool>,
     pub(crate) debounce_ms: Option<u64>,
     pub(crate) scope: Option<DiagnosticsScope>,
+    pub(crate) header_context: Option<HeaderContext>,
+    pub(crate) unused_includes: Option<bool>,
+    pub(crate) threadgroup_memory_limit: Option<u64>,
+    pub(crate) unused_symbols: Option<bool>,
+    pub(crate) unused_symbols_allow: Option<Vec<String>>,
+    pub(crate) exclude: Option<Vec<String>>,
     #[serde(flatten)]
     pub(crate) _extra: HashMap<String, Value>,
 }
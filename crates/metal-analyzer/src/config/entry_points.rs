@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryPointsSettings {
+    /// Show a CodeLens with the stage and bindings above each entry point.
+    pub code_lens: bool,
+    /// JSON file mapping pipeline names to the functions they use, relative
+    /// to the first workspace folder; empty when there is none.
+    pub pipelines_file: String,
+}
+
+impl Default for EntryPointsSettings {
+    fn default() -> Self {
+        Self {
+            code_lens: true,
+            pipelines_file: String::new(),
+        }
+    }
+}
+
+impl EntryPointsSettings {
+    pub(crate) fn apply_patch(
+        &mut self,
+        patch: EntryPointsSettingsPatch,
+    ) {
+        if let Some(v) = patch.code_lens {
+            self.code_lens = v;
+        }
+        if let Some(v) = patch.pipelines_file {
+            self.pipelines_file = v;
+        }
+    }
+
+    pub(crate) fn normalize(&mut self) {
+        self.pipelines_file = self.pipelines_file.trim().to_string();
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct EntryPointsSettingsPatch {
+    pub(crate) code_lens: Option<bool>,
+    pub(crate) pipelines_file: Option<String>,
+    #[serde(flatten)]
+    pub(crate) _extra: HashMap<String, Value>,
+}
@@ -3,19 +3,41 @@ use std::collections::HashMap;
 use serde::Deserialize;
 use serde_json::Value;
 
+/// Which formatter handles `textDocument/formatting`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum FormattingEngine {
+    /// `clang-format`, falling back to the built-in formatter when neither
+    /// `clang-format` nor `xcrun` is installed.
+    #[default]
+    Auto,
+    /// Always `clang-format`; a missing executable is reported to the user.
+    ClangFormat,
+    /// Always the built-in `metalfmt` formatter.
+    Internal,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct FormattingSettings {
     pub enable: bool,
+    pub engine: FormattingEngine,
     pub command: String,
     pub args: Vec<String>,
+    /// clang-format style used when no `metalfmt.toml` or `.clang-format`
+    /// applies: `metal` for the bundled style, `none` to leave files
+    /// unchanged, a predefined style name, or an inline `{Key: Value, ...}`
+    /// override of the bundled style.
+    pub fallback_style: String,
 }
 
 impl Default for FormattingSettings {
     fn default() -> Self {
         Self {
             enable: true,
+            engine: FormattingEngine::Auto,
             command: "clang-format".to_string(),
             args: Vec::new(),
+            fallback_style: "metal".to_string(),
         }
     }
 }
@@ -28,12 +50,18 @@ impl FormattingSettings {
         if let Some(v) = patch.enable {
             self.enable = v;
         }
+        if let Some(v) = patch.engine {
+            self.engine = v;
+        }
         if let Some(v) = patch.command {
             self.command = v;
         }
         if let Some(v) = patch.args {
             self.args = v;
         }
+        if let Some(v) = patch.fallback_style {
+            self.fallback_style = v;
+        }
     }
 
     pub(crate) fn normalize(&mut self) {
@@ -42,6 +70,10 @@ impl FormattingSettings {
             self.command = "clang-format".to_string();
         }
         self.args = self.args.iter().map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect();
+        self.fallback_style = self.fallback_style.trim().to_string();
+        if self.fallback_style.is_empty() {
+            self.fallback_style = "metal".to_string();
+        }
     }
 }
 
@@ -49,8 +81,10 @@ impl FormattingSettings {
 #[serde(default, rename_all = "camelCase")]
 pub(crate) struct FormattingSettingsPatch {
     pub(crate) enable: Option<bool>,
+    pub(crate) engine: Option<FormattingEngine>,
     pub(crate) command: Option<String>,
     pub(crate) args: Option<Vec<String>>,
+    pub(crate) fallback_style: Option<String>,
     #[serde(flatten)]
     pub(crate) _extra: HashMap<String, Value>,
 }
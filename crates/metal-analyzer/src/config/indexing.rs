@@ -11,6 +11,8 @@ pub const MIN_PROJECT_GRAPH_DEPTH: usize = 0;
 pub const MAX_PROJECT_GRAPH_DEPTH: usize = 8;
 pub const MIN_PROJECT_GRAPH_MAX_NODES: usize = 16;
 pub const MAX_PROJECT_GRAPH_MAX_NODES: usize = 4096;
+pub const MIN_MAX_MEMORY_MB: u64 = 64;
+pub const MAX_MAX_MEMORY_MB: u64 = 1024 * 64;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct IndexingSettings {
@@ -20,6 +22,12 @@ pub struct IndexingSettings {
     pub project_graph_depth: usize,
     pub project_graph_max_nodes: usize,
     pub exclude_paths: Vec<String>,
+    /// Skip what the workspace's `.gitignore` files ignore.
+    pub respect_gitignore: bool,
+    /// Save the project index between sessions and restore it at startup.
+    pub persist_index: bool,
+    /// Budget for the per-file AST index cache in megabytes; `0` means unlimited.
+    pub max_memory_mb: u64,
 }
 
 impl Default for IndexingSettings {
@@ -31,6 +39,9 @@ impl Default for IndexingSettings {
             project_graph_depth: 3,
             project_graph_max_nodes: 256,
             exclude_paths: Vec::new(),
+            respect_gitignore: true,
+            persist_index: true,
+            max_memory_mb: 2048,
         }
     }
 }
@@ -58,6 +69,15 @@ impl IndexingSettings {
         if let Some(v) = patch.exclude_paths {
             self.exclude_paths = v;
         }
+        if let Some(v) = patch.respect_gitignore {
+            self.respect_gitignore = v;
+        }
+        if let Some(v) = patch.persist_index {
+            self.persist_index = v;
+        }
+        if let Some(v) = patch.max_memory_mb {
+            self.max_memory_mb = v;
+        }
     }
 
     pub(crate) fn normalize(&mut self) {
@@ -66,6 +86,9 @@ impl IndexingSettings {
         self.project_graph_depth = self.project_graph_depth.clamp(MIN_PROJECT_GRAPH_DEPTH, MAX_PROJECT_GRAPH_DEPTH);
         self.project_graph_max_nodes =
             self.project_graph_max_nodes.clamp(MIN_PROJECT_GRAPH_MAX_NODES, MAX_PROJECT_GRAPH_MAX_NODES);
+        if self.max_memory_mb != 0 {
+            self.max_memory_mb = self.max_memory_mb.clamp(MIN_MAX_MEMORY_MB, MAX_MAX_MEMORY_MB);
+        }
         let mut seen = HashSet::new();
         self.exclude_paths = self
             .exclude_paths
@@ -90,6 +113,9 @@ pub(crate) struct IndexingSettingsPatch {
     pub(crate) project_graph_depth: Option<usize>,
     pub(crate) project_graph_max_nodes: Option<usize>,
     pub(crate) exclude_paths: Option<Vec<String>>,
+    pub(crate) respect_gitignore: Option<bool>,
+    pub(crate) persist_index: Option<bool>,
+    pub(crate) max_memory_mb: Option<u64>,
     #[serde(flatten)]
     pub(crate) _extra: HashMap<String, Value>,
 }
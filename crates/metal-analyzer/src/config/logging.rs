@@ -3,6 +3,8 @@ use std::collections::HashMap;
 use serde::Deserialize;
 use serde_json::Value;
 
+pub const MAX_PERF_INTERVAL_SECS: u64 = 86_400;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum LogLevel {
@@ -23,12 +25,15 @@ impl LogLevel {
 #[derive(Debug, Clone, PartialEq)]
 pub struct LoggingSettings {
     pub level: LogLevel,
+    /// Seconds between `[perf]` summary log lines; `0` turns them off.
+    pub perf_interval_secs: u64,
 }
 
 impl Default for LoggingSettings {
     fn default() -> Self {
         Self {
             level: LogLevel::Info,
+            perf_interval_secs: 0,
         }
     }
 }
@@ -41,6 +46,13 @@ impl LoggingSettings {
         if let Some(v) = patch.level {
             self.level = v;
         }
+        if let Some(v) = patch.perf_interval_secs {
+            self.perf_interval_secs = v;
+        }
+    }
+
+    pub(crate) fn normalize(&mut self) {
+        self.perf_interval_secs = self.perf_interval_secs.min(MAX_PERF_INTERVAL_SECS);
     }
 }
 
@@ -48,6 +60,7 @@ impl LoggingSettings {
 #[serde(default, rename_all = "camelCase")]
 pub(crate) struct LoggingSettingsPatch {
     pub(crate) level: Option<LogLevel>,
+    pub(crate) perf_interval_secs: Option<u64>,
     #[serde(flatten)]
     pub(crate) _extra: HashMap<String, Value>,
 }
@@ -4,39 +4,70 @@
 //! aggregates all categories and handles JSON deserialization from LSP
 //! initialization options and `didChangeConfiguration` payloads.
 
+pub(crate) mod code_lens;
 pub(crate) mod compiler;
+pub(crate) mod completion;
 pub(crate) mod diagnostics;
+pub(crate) mod entry_points;
 pub(crate) mod formatting;
 pub(crate) mod indexing;
 pub(crate) mod logging;
+pub(crate) mod navigation;
 pub(crate) mod schema;
+pub(crate) mod semantic_tokens;
+pub(crate) mod symbols;
 pub(crate) mod thread_pool;
+pub(crate) mod todos;
+pub(crate) mod validation;
+pub(crate) mod workspace_file;
 
 use std::collections::HashMap;
 
-pub use compiler::CompilerSettings;
+pub use code_lens::CodeLensSettings;
+use code_lens::CodeLensSettingsPatch;
 use compiler::CompilerSettingsPatch;
+pub use compiler::{
+    CompilerInvalidation, CompilerSettings, DEFAULT_AST_DUMP_TIMEOUT_MS, MAX_ARTIFACTS_MAX_SIZE_MB,
+    MAX_AST_DUMP_TIMEOUT_MS, MIN_ARTIFACTS_MAX_SIZE_MB, MIN_AST_DUMP_TIMEOUT_MS,
+};
+pub use completion::CompletionSettings;
+use completion::CompletionSettingsPatch;
 use diagnostics::DiagnosticsSettingsPatch;
-pub use diagnostics::{DiagnosticsScope, DiagnosticsSettings, MAX_DIAGNOSTIC_DEBOUNCE_MS, MIN_DIAGNOSTIC_DEBOUNCE_MS};
-pub use formatting::FormattingSettings;
+pub use diagnostics::{
+    DiagnosticsScope, DiagnosticsSettings, HeaderContext, MAX_DIAGNOSTIC_DEBOUNCE_MS, MIN_DIAGNOSTIC_DEBOUNCE_MS,
+};
+pub use entry_points::EntryPointsSettings;
+use entry_points::EntryPointsSettingsPatch;
 use formatting::FormattingSettingsPatch;
+pub use formatting::{FormattingEngine, FormattingSettings};
 use indexing::IndexingSettingsPatch;
 pub use indexing::{
-    IndexingSettings, MAX_INDEXING_CONCURRENCY, MAX_MAX_FILE_SIZE_KB, MAX_PROJECT_GRAPH_DEPTH,
-    MAX_PROJECT_GRAPH_MAX_NODES, MIN_INDEXING_CONCURRENCY, MIN_MAX_FILE_SIZE_KB, MIN_PROJECT_GRAPH_DEPTH,
-    MIN_PROJECT_GRAPH_MAX_NODES,
+    IndexingSettings, MAX_INDEXING_CONCURRENCY, MAX_MAX_FILE_SIZE_KB, MAX_MAX_MEMORY_MB, MAX_PROJECT_GRAPH_DEPTH,
+    MAX_PROJECT_GRAPH_MAX_NODES, MIN_INDEXING_CONCURRENCY, MIN_MAX_FILE_SIZE_KB, MIN_MAX_MEMORY_MB,
+    MIN_PROJECT_GRAPH_DEPTH, MIN_PROJECT_GRAPH_MAX_NODES,
 };
 use logging::LoggingSettingsPatch;
-pub use logging::{LogLevel, LoggingSettings};
+pub use logging::{LogLevel, LoggingSettings, MAX_PERF_INTERVAL_SECS};
+use navigation::NavigationSettingsPatch;
+pub use navigation::{MAX_RANKING_WEIGHT, NavigationSettings, RankingWeights};
 pub use schema::{
-    SchemaField, SchemaType, generate_configuration_markdown, generate_package_json_properties, schema_fields,
+    SchemaField, SchemaType, generate_configuration_markdown, generate_json_schema, generate_package_json_properties,
+    schema_fields,
 };
+pub use semantic_tokens::SemanticTokensSettings;
+use semantic_tokens::SemanticTokensSettingsPatch;
 use serde::Deserialize;
 use serde_json::Value;
+use symbols::SymbolsSettingsPatch;
+pub use symbols::{SymbolSearchScope, SymbolsSettings};
 use thread_pool::ThreadPoolSettingsPatch;
 pub use thread_pool::{
     MAX_FORMATTING_THREADS, MAX_WORKER_THREADS, MIN_FORMATTING_THREADS, MIN_WORKER_THREADS, ThreadPoolSettings,
 };
+pub use todos::TodosSettings;
+use todos::TodosSettingsPatch;
+pub use validation::{SettingsProblem, SettingsProblemKind, validate_settings_payload};
+pub use workspace_file::{WORKSPACE_SETTINGS_FILE, merge_json_values, read_workspace_settings_file};
 
 pub const SETTINGS_SECTION_KEY: &str = "metal-analyzer";
 
@@ -44,10 +75,17 @@ pub const SETTINGS_SECTION_KEY: &str = "metal-analyzer";
 pub struct ServerSettings {
     pub formatting: FormattingSettings,
     pub diagnostics: DiagnosticsSettings,
+    pub completion: CompletionSettings,
     pub indexing: IndexingSettings,
+    pub symbols: SymbolsSettings,
+    pub navigation: NavigationSettings,
     pub compiler: CompilerSettings,
     pub logging: LoggingSettings,
     pub thread_pool: ThreadPoolSettings,
+    pub todos: TodosSettings,
+    pub entry_points: EntryPointsSettings,
+    pub code_lens: CodeLensSettings,
+    pub semantic_tokens: SemanticTokensSettings,
 }
 
 impl Default for ServerSettings {
@@ -55,10 +93,17 @@ impl Default for ServerSettings {
         Self {
             formatting: FormattingSettings::default(),
             diagnostics: DiagnosticsSettings::default(),
+            completion: CompletionSettings::default(),
             indexing: IndexingSettings::default(),
+            symbols: SymbolsSettings::default(),
+            navigation: NavigationSettings::default(),
             compiler: CompilerSettings::default(),
             logging: LoggingSettings::default(),
             thread_pool: ThreadPoolSettings::default(),
+            todos: TodosSettings::default(),
+            entry_points: EntryPointsSettings::default(),
+            code_lens: CodeLensSettings::default(),
+            semantic_tokens: SemanticTokensSettings::default(),
         }
     }
 }
@@ -72,6 +117,19 @@ impl ServerSettings {
         settings
     }
 
+    /// Settings layered from lowest to highest precedence: built-in defaults,
+    /// each `.metal-analyzer.json` in workspace-folder order, then the LSP payload.
+    pub fn layered(
+        workspace_files: &[Value],
+        lsp_payload: Option<&Value>,
+    ) -> Self {
+        let mut settings = Self::default();
+        for file in workspace_files.iter().chain(lsp_payload) {
+            settings = settings.merged_with_payload(file);
+        }
+        settings
+    }
+
     pub fn merged_with_payload(
         &self,
         payload: &Value,
@@ -98,9 +156,18 @@ impl ServerSettings {
         if let Some(p) = patch.diagnostics {
             self.diagnostics.apply_patch(p);
         }
+        if let Some(p) = patch.completion {
+            self.completion.apply_patch(p);
+        }
         if let Some(p) = patch.indexing {
             self.indexing.apply_patch(p);
         }
+        if let Some(p) = patch.symbols {
+            self.symbols.apply_patch(p);
+        }
+        if let Some(p) = patch.navigation {
+            self.navigation.apply_patch(p);
+        }
         if let Some(p) = patch.compiler {
             self.compiler.apply_patch(p);
         }
@@ -110,14 +177,30 @@ impl ServerSettings {
         if let Some(p) = patch.thread_pool {
             self.thread_pool.apply_patch(p);
         }
+        if let Some(p) = patch.todos {
+            self.todos.apply_patch(p);
+        }
+        if let Some(p) = patch.entry_points {
+            self.entry_points.apply_patch(p);
+        }
+        if let Some(p) = patch.code_lens {
+            self.code_lens.apply_patch(p);
+        }
+        if let Some(p) = patch.semantic_tokens {
+            self.semantic_tokens.apply_patch(p);
+        }
     }
 
     fn normalize(&mut self) {
         self.formatting.normalize();
         self.diagnostics.normalize();
         self.indexing.normalize();
+        self.navigation.normalize();
         self.compiler.normalize();
+        self.logging.normalize();
         self.thread_pool.normalize();
+        self.todos.normalize();
+        self.entry_points.normalize();
     }
 }
 
@@ -126,10 +209,17 @@ impl ServerSettings {
 struct ServerSettingsPatch {
     formatting: Option<FormattingSettingsPatch>,
     diagnostics: Option<DiagnosticsSettingsPatch>,
+    completion: Option<CompletionSettingsPatch>,
     indexing: Option<IndexingSettingsPatch>,
+    symbols: Option<SymbolsSettingsPatch>,
+    navigation: Option<NavigationSettingsPatch>,
     compiler: Option<CompilerSettingsPatch>,
     logging: Option<LoggingSettingsPatch>,
     thread_pool: Option<ThreadPoolSettingsPatch>,
+    todos: Option<TodosSettingsPatch>,
+    entry_points: Option<EntryPointsSettingsPatch>,
+    code_lens: Option<CodeLensSettingsPatch>,
+    semantic_tokens: Option<SemanticTokensSettingsPatch>,
     #[serde(flatten)]
     _extra: HashMap<String, Value>,
 }
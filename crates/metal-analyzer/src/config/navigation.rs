@@ -0,0 +1,90 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+pub use crate::definition::symbol_rank::RankingWeights;
+
+pub const MAX_RANKING_WEIGHT: u32 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NavigationSettings {
+    /// Penalties ordering go-to-definition candidates found by name.
+    pub ranking: RankingWeights,
+}
+
+impl NavigationSettings {
+    pub(crate) fn apply_patch(
+        &mut self,
+        patch: NavigationSettingsPatch,
+    ) {
+        if let Some(p) = patch.ranking {
+            let ranking = &mut self.ranking;
+            if let Some(v) = p.other_file {
+                ranking.other_file = v;
+            }
+            if let Some(v) = p.declaration_only {
+                ranking.declaration_only = v;
+            }
+            if let Some(v) = p.parameter {
+                ranking.parameter = v;
+            }
+            if let Some(v) = p.system_header {
+                ranking.system_header = v;
+            }
+            if let Some(v) = p.builtin_outside_system {
+                ranking.builtin_outside_system = v;
+            }
+            if let Some(v) = p.project_header {
+                ranking.project_header = v;
+            }
+            if let Some(v) = p.builtin_prefixes {
+                ranking.builtin_prefixes = v;
+            }
+        }
+    }
+
+    pub(crate) fn normalize(&mut self) {
+        let ranking = &mut self.ranking;
+        for weight in [
+            &mut ranking.other_file,
+            &mut ranking.declaration_only,
+            &mut ranking.parameter,
+            &mut ranking.system_header,
+            &mut ranking.builtin_outside_system,
+            &mut ranking.project_header,
+        ] {
+            *weight = (*weight).min(MAX_RANKING_WEIGHT);
+        }
+        let mut seen = HashSet::new();
+        ranking.builtin_prefixes = ranking
+            .builtin_prefixes
+            .iter()
+            .map(|prefix| prefix.trim().to_string())
+            .filter(|prefix| !prefix.is_empty())
+            .filter(|prefix| seen.insert(prefix.clone()))
+            .collect();
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct NavigationSettingsPatch {
+    pub(crate) ranking: Option<RankingWeightsPatch>,
+    #[serde(flatten)]
+    pub(crate) _extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct RankingWeightsPatch {
+    pub(crate) other_file: Option<u32>,
+    pub(crate) declaration_only: Option<u32>,
+    pub(crate) parameter: Option<u32>,
+    pub(crate) system_header: Option<u32>,
+    pub(crate) builtin_outside_system: Option<u32>,
+    pub(crate) project_header: Option<u32>,
+    pub(crate) builtin_prefixes: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub(crate) _extra: HashMap<String, Value>,
+}
@@ -1,12 +1,23 @@
 use serde_json::Value;
 
-use crate::config::{
-    diagnostics::{MAX_DIAGNOSTIC_DEBOUNCE_MS, MIN_DIAGNOSTIC_DEBOUNCE_MS},
-    indexing::{
-        MAX_INDEXING_CONCURRENCY, MAX_MAX_FILE_SIZE_KB, MAX_PROJECT_GRAPH_DEPTH, MAX_PROJECT_GRAPH_MAX_NODES,
-        MIN_INDEXING_CONCURRENCY, MIN_MAX_FILE_SIZE_KB, MIN_PROJECT_GRAPH_DEPTH, MIN_PROJECT_GRAPH_MAX_NODES,
+use crate::{
+    config::{
+        compiler::{
+            DEFAULT_AST_DUMP_TIMEOUT_MS, MAX_ARTIFACTS_MAX_SIZE_MB, MAX_AST_DUMP_TIMEOUT_MS, MIN_ARTIFACTS_MAX_SIZE_MB,
+            MIN_AST_DUMP_TIMEOUT_MS,
+        },
+        diagnostics::{MAX_DIAGNOSTIC_DEBOUNCE_MS, MIN_DIAGNOSTIC_DEBOUNCE_MS},
+        indexing::{
+            MAX_INDEXING_CONCURRENCY, MAX_MAX_FILE_SIZE_KB, MAX_MAX_MEMORY_MB, MAX_PROJECT_GRAPH_DEPTH,
+            MAX_PROJECT_GRAPH_MAX_NODES, MIN_INDEXING_CONCURRENCY, MIN_MAX_FILE_SIZE_KB, MIN_PROJECT_GRAPH_DEPTH,
+            MIN_PROJECT_GRAPH_MAX_NODES,
+        },
+        logging::MAX_PERF_INTERVAL_SECS,
+        navigation::{MAX_RANKING_WEIGHT, RankingWeights},
+        thread_pool::{MAX_FORMATTING_THREADS, MAX_WORKER_THREADS, MIN_FORMATTING_THREADS},
     },
-    thread_pool::{MAX_FORMATTING_THREADS, MAX_WORKER_THREADS, MIN_FORMATTING_THREADS},
+    ide::threadgroup_memory::DEFAULT_THREADGROUP_MEMORY_LIMIT,
+    metal::language_version::LANGUAGE_VERSIONS,
 };
 
 /// One entry in the generated configuration schema.
@@ -31,6 +42,14 @@ pub enum SchemaType {
         values: Vec<&'static str>,
     },
     StringArray,
+    /// An object whose values are all strings.
+    StringMap,
+    /// An array of objects whose properties are all string arrays except
+    /// the required `string_key`.
+    ObjectArray {
+        string_key: &'static str,
+        array_keys: Vec<&'static str>,
+    },
 }
 
 impl SchemaField {
@@ -70,6 +89,26 @@ impl SchemaField {
                 items.insert("type".into(), Value::String("string".into()));
                 obj.insert("items".into(), Value::Object(items));
             },
+            SchemaType::StringMap => {
+                obj.insert("type".into(), Value::String("object".into()));
+                obj.insert("additionalProperties".into(), serde_json::json!({ "type": "string" }));
+            },
+            SchemaType::ObjectArray {
+                string_key,
+                array_keys,
+            } => {
+                obj.insert("type".into(), Value::String("array".into()));
+                let mut properties = serde_json::Map::new();
+                properties.insert((*string_key).into(), serde_json::json!({ "type": "string" }));
+                for key in array_keys {
+                    properties
+                        .insert((*key).into(), serde_json::json!({ "type": "array", "items": { "type": "string" } }));
+                }
+                obj.insert(
+                    "items".into(),
+                    serde_json::json!({ "type": "object", "properties": properties, "required": [string_key] }),
+                );
+            },
         }
 
         Value::Object(obj)
@@ -82,6 +121,7 @@ impl SchemaField {
 
 /// Return the full list of schema fields for every setting.
 pub fn schema_fields() -> Vec<SchemaField> {
+    let defaults = RankingWeights::default();
     vec![
         SchemaField {
             key: "formatting.enable".into(),
@@ -89,6 +129,18 @@ pub fn schema_fields() -> Vec<SchemaField> {
             schema_type: SchemaType::Bool,
             default: Value::Bool(true),
         },
+        SchemaField {
+            key: "formatting.engine".into(),
+            description: "Formatter engine. `auto` runs `clang-format` and falls back to the built-in formatter \
+                          when neither `clang-format` nor `xcrun` is available. `clangFormat` always runs the \
+                          formatting command. `internal` always uses the built-in formatter, configured by \
+                          `metalfmt.toml`."
+                .into(),
+            schema_type: SchemaType::StringEnum {
+                values: vec!["auto", "clangFormat", "internal"],
+            },
+            default: Value::String("auto".into()),
+        },
         SchemaField {
             key: "formatting.command".into(),
             description: "Formatting executable used by metal-analyzer.".into(),
@@ -101,12 +153,30 @@ pub fn schema_fields() -> Vec<SchemaField> {
             schema_type: SchemaType::StringArray,
             default: Value::Array(vec![]),
         },
+        SchemaField {
+            key: "formatting.fallbackStyle".into(),
+            description: "clang-format style used when no `metalfmt.toml` or `.clang-format` is found. `metal` is \
+                          the bundled Metal-tuned style, `none` leaves files unchanged, a predefined name such as \
+                          `LLVM` selects that style, and an inline `{ColumnLimit: 100, ...}` overrides keys of the \
+                          bundled style."
+                .into(),
+            schema_type: SchemaType::String,
+            default: Value::String("metal".into()),
+        },
         SchemaField {
             key: "diagnostics.onType".into(),
             description: "Run diagnostics while typing.".into(),
             schema_type: SchemaType::Bool,
             default: Value::Bool(true),
         },
+        SchemaField {
+            key: "diagnostics.syntax".into(),
+            description: "Report syntax errors such as unbalanced brackets and missing semicolons on every change, \
+                          before the compiler diagnostics arrive."
+                .into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(true),
+        },
         SchemaField {
             key: "diagnostics.onSave".into(),
             description: "Run diagnostics when a document is saved.".into(),
@@ -115,7 +185,7 @@ pub fn schema_fields() -> Vec<SchemaField> {
         },
         SchemaField {
             key: "diagnostics.debounceMs".into(),
-            description: "Debounce delay for on-type diagnostics and background indexing work.".into(),
+            description: "Base debounce delay for on-type diagnostics and background indexing work. Each document's delay follows its recent compile times, between a quarter of and four times this value.".into(),
             schema_type: SchemaType::Integer {
                 minimum: Some(MIN_DIAGNOSTIC_DEBOUNCE_MS as i64),
                 maximum: Some(MAX_DIAGNOSTIC_DEBOUNCE_MS as i64),
@@ -126,13 +196,80 @@ pub fn schema_fields() -> Vec<SchemaField> {
             key: "diagnostics.scope".into(),
             description: "Diagnostics scope. `openFiles` analyzes documents as they are opened/edited/saved. \
                            `workspace` also analyzes all `.metal` files in the workspace at startup and when \
-                           settings change."
+                           settings change, and re-analyzes the files that include a header when it is saved."
                 .into(),
             schema_type: SchemaType::StringEnum {
                 values: vec!["openFiles", "workspace"],
             },
             default: Value::String("openFiles".into()),
         },
+        SchemaField {
+            key: "diagnostics.headerContext".into(),
+            description: "How header diagnostics are computed. `owner` compiles the `.metal` files that include \
+                           the header and reports the errors found in it, so macros and types they define before \
+                           the include are honored. `standalone` compiles the header on its own. `both` merges the \
+                           two."
+                .into(),
+            schema_type: SchemaType::StringEnum {
+                values: vec!["owner", "standalone", "both"],
+            },
+            default: Value::String("owner".into()),
+        },
+        SchemaField {
+            key: "diagnostics.unusedIncludes".into(),
+            description: "Hint at `#include`s of project headers that declare nothing the file uses, with a quick \
+                          fix that removes them. Needs the AST index, so it applies once the file has been \
+                          indexed."
+                .into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(true),
+        },
+        SchemaField {
+            key: "diagnostics.threadgroupMemoryLimit".into(),
+            description: "Warn on kernel, mesh and object functions whose `threadgroup` variables add up to more \
+                          than this many bytes. Array extents may use literals, constants, macros and the template \
+                          arguments of explicit instantiations. `0` turns the warning off."
+                .into(),
+            schema_type: SchemaType::Integer {
+                minimum: Some(0),
+                maximum: None,
+            },
+            default: Value::Number(DEFAULT_THREADGROUP_MEMORY_LIMIT.into()),
+        },
+        SchemaField {
+            key: "diagnostics.unusedSymbols".into(),
+            description: "Hint at `static` functions and constants that nothing in the workspace uses, from the \
+                          references in the project index. Only symbols with internal linkage are checked, since \
+                          only their own translation unit can use them."
+                .into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(true),
+        },
+        SchemaField {
+            key: "diagnostics.unusedSymbolsAllow".into(),
+            description: "Names of functions and constants never reported as unused, such as helpers kept for \
+                          later or reached through macros the index does not see."
+                .into(),
+            schema_type: SchemaType::StringArray,
+            default: Value::Array(vec![]),
+        },
+        SchemaField {
+            key: "diagnostics.exclude".into(),
+            description: "Paths and globs of files whose diagnostics are not published, written as for \
+                          `indexing.excludePaths`. Excluded files are still indexed, so navigation and completion \
+                          keep working."
+                .into(),
+            schema_type: SchemaType::StringArray,
+            default: Value::Array(vec![]),
+        },
+        SchemaField {
+            key: "completion.snippets".into(),
+            description: "Offer snippet completions such as `kernel`, `vertex`, `fragment`, `mesh` and `object` \
+                          entry-point skeletons. Only applies to clients that support snippets."
+                .into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(true),
+        },
         SchemaField {
             key: "indexing.enable".into(),
             description: "Enable background workspace indexing.".into(),
@@ -179,19 +316,124 @@ pub fn schema_fields() -> Vec<SchemaField> {
         },
         SchemaField {
             key: "indexing.excludePaths".into(),
-            description: "Workspace paths to skip during background scanning. Relative paths are resolved from \
-                           each workspace root; absolute paths are also supported. Excluded folders are skipped \
-                           for both indexing and workspace-scope diagnostics."
+            description: "Workspace paths and globs to skip during background scanning and file watching. \
+                          Relative paths are resolved from each workspace root; absolute paths are also \
+                          supported. Globs follow `.gitignore`: `*.gen.metal` matches a name at any depth, \
+                          `**/generated/**` is anchored at the workspace root. Excluded files are skipped for \
+                          both indexing and workspace-scope diagnostics."
                 .into(),
             schema_type: SchemaType::StringArray,
             default: Value::Array(vec![]),
         },
+        SchemaField {
+            key: "indexing.respectGitignore".into(),
+            description: "Skip files and folders ignored by the `.gitignore` files under each workspace root when \
+                          scanning for files to index and when reacting to changes on disk."
+                .into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(true),
+        },
+        SchemaField {
+            key: "indexing.persistIndex".into(),
+            description: "Save the project index between sessions and restore the entries whose files are \
+                          unchanged at startup, so cross-file navigation works before any AST dump runs."
+                .into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(true),
+        },
+        SchemaField {
+            key: "indexing.maxMemoryMb".into(),
+            description: "Approximate memory budget for in-memory AST indexes. Past it, the least recently used \
+                          indexes are dropped and reloaded from the disk cache when needed. `0` disables the \
+                          budget; other values below 64 count as 64."
+                .into(),
+            schema_type: SchemaType::Integer {
+                minimum: Some(0),
+                maximum: Some(MAX_MAX_MEMORY_MB as i64),
+            },
+            default: Value::Number(2048.into()),
+        },
+        SchemaField {
+            key: "symbols.searchScope.includeSystemHeaders".into(),
+            description: "Include symbols from system headers and other files outside the workspace folders in \
+                          workspace symbol search."
+                .into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(false),
+        },
+        SchemaField {
+            key: "symbols.searchScope.includeGenerated".into(),
+            description: "Include symbols from generated sources (`generated/`, `build/`, `DerivedData/`, \
+                          `*_generated.*` files) in workspace symbol search."
+                .into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(false),
+        },
+        SchemaField {
+            key: "symbols.searchScope.includeTests".into(),
+            description: "Include symbols from test sources and fixtures (`tests/`, `fixtures/`, `*_test.*` \
+                          files) in workspace symbol search."
+                .into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(true),
+        },
+        ranking_weight_field(
+            "otherFile",
+            "Go-to-definition ranking penalty for candidates in another file than the cursor.",
+            defaults.other_file,
+        ),
+        ranking_weight_field(
+            "declarationOnly",
+            "Go-to-definition ranking penalty for declarations without a body.",
+            defaults.declaration_only,
+        ),
+        ranking_weight_field(
+            "parameter",
+            "Go-to-definition ranking penalty for function parameters.",
+            defaults.parameter,
+        ),
+        ranking_weight_field(
+            "systemHeader",
+            "Go-to-definition ranking penalty for candidates in system headers when the name does not look like \
+             a builtin.",
+            defaults.system_header,
+        ),
+        ranking_weight_field(
+            "builtinOutsideSystem",
+            "Go-to-definition ranking penalty for candidates outside system headers when the name looks like a \
+             builtin.",
+            defaults.builtin_outside_system,
+        ),
+        ranking_weight_field(
+            "projectHeader",
+            "Go-to-definition ranking penalty for candidates in project headers rather than `.metal` sources.",
+            defaults.project_header,
+        ),
+        SchemaField {
+            key: "navigation.ranking.builtinPrefixes".into(),
+            description: "Name prefixes that mark a symbol as a builtin for go-to-definition ranking, in addition \
+                          to the known Metal builtins."
+                .into(),
+            schema_type: SchemaType::StringArray,
+            default: Value::Array(defaults.builtin_prefixes.iter().cloned().map(Value::String).collect()),
+        },
         SchemaField {
             key: "compiler.includePaths".into(),
             description: "Extra include directories passed to the Metal compiler.".into(),
             schema_type: SchemaType::StringArray,
             default: Value::Array(vec![]),
         },
+        SchemaField {
+            key: "compiler.virtualIncludeRoots".into(),
+            description: "Include prefixes mapped to the directory they stand for, e.g. \
+                          `{ \"generated\": \"build/codegen\" }` makes `#include \"generated/matmul.h\"` find \
+                          `build/codegen/matmul.h`. For generated headers that include each other relative to a \
+                          codegen root that is not on the include path. Used by the compiler and by include \
+                          navigation. Relative directories are resolved against the workspace folder."
+                .into(),
+            schema_type: SchemaType::StringMap,
+            default: Value::Object(serde_json::Map::new()),
+        },
         SchemaField {
             key: "compiler.extraFlags".into(),
             description: "Extra compiler flags passed to `xcrun metal`.".into(),
@@ -209,6 +451,83 @@ pub fn schema_fields() -> Vec<SchemaField> {
             },
             default: Value::String("macos".into()),
         },
+        SchemaField {
+            key: "compiler.languageVersion".into(),
+            description: "Metal Shading Language version to target. `auto` reads it from a `-std=` flag. Another \
+                          value adds the matching `-std=` flag to files whose flags have none. Completion leaves \
+                          out builtins introduced in later versions, and their uses get a warning."
+                .into(),
+            schema_type: SchemaType::StringEnum {
+                values: std::iter::once("auto").chain(LANGUAGE_VERSIONS.iter().copied()).collect(),
+            },
+            default: Value::String("auto".into()),
+        },
+        SchemaField {
+            key: "compiler.tempDir".into(),
+            description: "Directory for compiler temp files and kept artifacts. Empty uses the system temp \
+                           directory."
+                .into(),
+            schema_type: SchemaType::String,
+            default: Value::String(String::new()),
+        },
+        SchemaField {
+            key: "compiler.keepArtifacts".into(),
+            description: "Keep the exact translation unit of compiles that report errors in \
+                           `metal-analyzer-artifacts` under the temp directory. Diagnostics link to the kept file."
+                .into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(false),
+        },
+        SchemaField {
+            key: "compiler.artifactsMaxSizeMb".into(),
+            description: "Total size of kept artifacts. The oldest artifacts are deleted once it is exceeded.".into(),
+            schema_type: SchemaType::Integer {
+                minimum: Some(MIN_ARTIFACTS_MAX_SIZE_MB as i64),
+                maximum: Some(MAX_ARTIFACTS_MAX_SIZE_MB as i64),
+            },
+            default: Value::Number(64.into()),
+        },
+        SchemaField {
+            key: "compiler.astDumpTimeoutMs".into(),
+            description: "Time limit for the compiler run that indexes a file for navigation. Slower runs are \
+                           stopped and navigation falls back to name lookup."
+                .into(),
+            schema_type: SchemaType::Integer {
+                minimum: Some(MIN_AST_DUMP_TIMEOUT_MS as i64),
+                maximum: Some(MAX_AST_DUMP_TIMEOUT_MS as i64),
+            },
+            default: Value::Number(DEFAULT_AST_DUMP_TIMEOUT_MS.into()),
+        },
+        SchemaField {
+            key: "compiler.precompiledHeaders".into(),
+            description: "Precompile `<metal_stdlib>` once per set of compiler flags and load it in diagnostics \
+                           compiles of files that include it first."
+                .into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(true),
+        },
+        SchemaField {
+            key: "compiler.kernelStats".into(),
+            description: "Compile the document when hovering an entry point name and show its instruction count, \
+                          threadgroup memory and stack memory, read from the disassembled `.air` object."
+                .into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(false),
+        },
+        SchemaField {
+            key: "compiler.overrides".into(),
+            description: "Extra flags and include paths for the files matching `pathGlob`, e.g. \
+                          `{ \"pathGlob\": \"ios/**\", \"flags\": [\"-D__METAL_IOS__\"] }`. `*` matches within a path \
+                          segment and `**` across segments. Relative globs match the end of a path; in \
+                          `.metal-analyzer.json` they are anchored at its folder. Matching overrides apply in order \
+                          after `extraFlags`, to diagnostics and indexing."
+                .into(),
+            schema_type: SchemaType::ObjectArray {
+                string_key: "pathGlob",
+                array_keys: vec!["flags", "includePaths"],
+            },
+            default: Value::Array(vec![]),
+        },
         SchemaField {
             key: "logging.level".into(),
             description: "Runtime logging verbosity for metal-analyzer.".into(),
@@ -217,9 +536,20 @@ pub fn schema_fields() -> Vec<SchemaField> {
             },
             default: Value::String("info".into()),
         },
+        SchemaField {
+            key: "logging.perfIntervalSecs".into(),
+            description: "Log request, AST dump and compile latencies and cache hit counts every this many seconds, \
+                          as returned by `metal-analyzer/perf`. `0` turns the log lines off."
+                .into(),
+            schema_type: SchemaType::Integer {
+                minimum: Some(0),
+                maximum: Some(MAX_PERF_INTERVAL_SECS as i64),
+            },
+            default: Value::Number(0.into()),
+        },
         SchemaField {
             key: "threadPool.workerThreads".into(),
-            description: "Worker thread pool size. `0` uses `available_parallelism`. Requires restart.".into(),
+            description: "Worker thread pool size, and the most diagnostics compiles of open documents that run at once. `0` uses `available_parallelism`. Resizing the thread pool requires a restart.".into(),
             schema_type: SchemaType::Integer {
                 minimum: Some(0),
                 maximum: Some(MAX_WORKER_THREADS as i64),
@@ -235,6 +565,59 @@ pub fn schema_fields() -> Vec<SchemaField> {
             },
             default: Value::Number(1.into()),
         },
+        SchemaField {
+            key: "todos.tags".into(),
+            description: "Comment tags listed by the `metal-analyzer/todos` request. A tag matches at the start of \
+                          a comment, optionally followed by an owner as in `TODO(name):`."
+                .into(),
+            schema_type: SchemaType::StringArray,
+            default: serde_json::json!(["TODO", "FIXME", "PERF"]),
+        },
+        SchemaField {
+            key: "todos.diagnostics".into(),
+            description: "Report tagged comments as information diagnostics.".into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(false),
+        },
+        SchemaField {
+            key: "entryPoints.codeLens".into(),
+            description: "Show a CodeLens above each kernel, vertex, fragment, mesh and object function with its \
+                          stage, buffer bindings and, with `entryPoints.pipelinesFile`, the pipelines that use it."
+                .into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(true),
+        },
+        SchemaField {
+            key: "entryPoints.pipelinesFile".into(),
+            description: "JSON file mapping pipeline names to the functions they use, such as `{\"blur\": \
+                          [\"blur_vertex\", \"blur_fragment\"]}`. Relative paths start at the first workspace \
+                          folder. Listed by `metal-analyzer/entryPoints` and counted in the entry-point CodeLens."
+                .into(),
+            schema_type: SchemaType::String,
+            default: Value::String(String::new()),
+        },
+        SchemaField {
+            key: "codeLens.references".into(),
+            description: "Show how often each function and struct is used across the workspace, counted from the \
+                          project index when the lens is shown."
+                .into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(true),
+        },
+        SchemaField {
+            key: "codeLens.fileActions".into(),
+            description: "Show \"Run diagnostics\" and \"Re-index file\" at the top of each file.".into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(true),
+        },
+        SchemaField {
+            key: "semanticTokens.enable".into(),
+            description: "Highlight functions, types, macros and parameters with semantic tokens. Clients that \
+                          register capabilities dynamically stop asking for them when this is turned off."
+                .into(),
+            schema_type: SchemaType::Bool,
+            default: Value::Bool(true),
+        },
     ]
 }
 
@@ -259,6 +642,57 @@ pub fn generate_package_json_properties() -> Value {
     Value::Object(properties)
 }
 
+/// Generate a JSON Schema for the settings object under `metal-analyzer`,
+/// nested the way LSP clients send it, e.g.
+/// `{ "formatting": { "enable": true } }`.
+pub fn generate_json_schema() -> Value {
+    let mut root = object_schema();
+    root["$schema"] = Value::String("http://json-schema.org/draft-07/schema#".into());
+    root["title"] = Value::String("metal-analyzer settings".into());
+
+    for field in schema_fields() {
+        let mut schema = field.to_schema_value();
+        schema["description"] = Value::String(field.description.clone());
+        let mut segments: Vec<&str> = field.key.split('.').collect();
+        let Some(name) = segments.pop() else {
+            continue;
+        };
+        let mut parent = &mut root;
+        for segment in segments {
+            parent = parent["properties"]
+                .as_object_mut()
+                .expect("object schema")
+                .entry(segment)
+                .or_insert_with(object_schema);
+        }
+        parent["properties"][name] = schema;
+    }
+
+    root
+}
+
+fn object_schema() -> Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+/// A `navigation.ranking.*` penalty. Candidates found by name rank by the sum
+/// of the penalties that apply to them, lowest first.
+fn ranking_weight_field(
+    name: &str,
+    description: &str,
+    default: u32,
+) -> SchemaField {
+    SchemaField {
+        key: format!("navigation.ranking.{name}"),
+        description: description.into(),
+        schema_type: SchemaType::Integer {
+            minimum: Some(0),
+            maximum: Some(MAX_RANKING_WEIGHT as i64),
+        },
+        default: Value::Number(default.into()),
+    }
+}
+
 /// Generate markdown documentation for all settings.
 pub fn generate_configuration_markdown() -> String {
     let mut out = String::new();
@@ -272,10 +706,17 @@ pub fn generate_configuration_markdown() -> String {
             let title = match section {
                 "formatting" => "Formatting",
                 "diagnostics" => "Diagnostics",
+                "completion" => "Completion",
                 "indexing" => "Indexing",
+                "symbols" => "Symbols",
+                "navigation" => "Navigation",
                 "compiler" => "Compiler",
                 "logging" => "Logging",
                 "threadPool" => "Thread Pool",
+                "todos" => "TODOs",
+                "entryPoints" => "Entry Points",
+                "codeLens" => "CodeLens",
+                "semanticTokens" => "Semantic Tokens",
                 other => other,
             };
             out.push_str(&format!("\n## {title}\n\n"));
@@ -286,3 +727,7 @@ pub fn generate_configuration_markdown() -> String {
 
     out
 }
+
+#[cfg(test)]
+#[path = "../../tests/src/config/schema_tests.rs"]
+mod tests;
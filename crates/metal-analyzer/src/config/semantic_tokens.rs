@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SemanticTokensSettings {
+    /// Highlight symbols from the syntax tree and the AST index.
+    pub enable: bool,
+}
+
+impl Default for SemanticTokensSettings {
+    fn default() -> Self {
+        Self {
+            enable: true,
+        }
+    }
+}
+
+impl SemanticTokensSettings {
+    pub(crate) fn apply_patch(
+        &mut self,
+        patch: SemanticTokensSettingsPatch,
+    ) {
+        if let Some(v) = patch.enable {
+            self.enable = v;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct SemanticTokensSettingsPatch {
+    pub(crate) enable: Option<bool>,
+    #[serde(flatten)]
+    pub(crate) _extra: HashMap<String, Value>,
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Which categories of files contribute to `workspace/symbol` results.
+///
+/// Files under the workspace folders are always searched; the flags add or
+/// remove the categories that usually only add noise to a symbol search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolSearchScope {
+    /// System headers and other files outside the workspace folders.
+    pub include_system_headers: bool,
+    /// Generated sources, e.g. under `generated/`, `build/` or `DerivedData/`.
+    pub include_generated: bool,
+    /// Test sources and fixtures, e.g. under `tests/` or `fixtures/`.
+    pub include_tests: bool,
+}
+
+impl Default for SymbolSearchScope {
+    fn default() -> Self {
+        Self {
+            include_system_headers: false,
+            include_generated: false,
+            include_tests: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SymbolsSettings {
+    pub search_scope: SymbolSearchScope,
+}
+
+impl SymbolsSettings {
+    pub(crate) fn apply_patch(
+        &mut self,
+        patch: SymbolsSettingsPatch,
+    ) {
+        if let Some(p) = patch.search_scope {
+            if let Some(v) = p.include_system_headers {
+                self.search_scope.include_system_headers = v;
+            }
+            if let Some(v) = p.include_generated {
+                self.search_scope.include_generated = v;
+            }
+            if let Some(v) = p.include_tests {
+                self.search_scope.include_tests = v;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct SymbolsSettingsPatch {
+    pub(crate) search_scope: Option<SymbolSearchScopePatch>,
+    #[serde(flatten)]
+    pub(crate) _extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct SymbolSearchScopePatch {
+    pub(crate) include_system_headers: Option<bool>,
+    pub(crate) include_generated: Option<bool>,
+    pub(crate) include_tests: Option<bool>,
+    #[serde(flatten)]
+    pub(crate) _extra: HashMap<String, Value>,
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TodosSettings {
+    /// Comment tags indexed by `metal-analyzer/todos`, matched case-sensitively
+    /// at the start of a comment.
+    pub tags: Vec<String>,
+    /// Also report each tagged comment as an information diagnostic.
+    pub diagnostics: bool,
+}
+
+impl Default for TodosSettings {
+    fn default() -> Self {
+        Self {
+            tags: default_tags(),
+            diagnostics: false,
+        }
+    }
+}
+
+fn default_tags() -> Vec<String> {
+    ["TODO", "FIXME", "PERF"].into_iter().map(String::from).collect()
+}
+
+impl TodosSettings {
+    /// Tags reported as diagnostics; empty when diagnostics are disabled.
+    pub fn diagnostic_tags(&self) -> Vec<String> {
+        if self.diagnostics {
+            self.tags.clone()
+        } else {
+            Vec::new()
+        }
+    }
+
+    pub(crate) fn apply_patch(
+        &mut self,
+        patch: TodosSettingsPatch,
+    ) {
+        if let Some(v) = patch.tags {
+            self.tags = v;
+        }
+        if let Some(v) = patch.diagnostics {
+            self.diagnostics = v;
+        }
+    }
+
+    pub(crate) fn normalize(&mut self) {
+        self.tags = self.tags.iter().map(|tag| tag.trim().to_string()).filter(|tag| !tag.is_empty()).collect();
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct TodosSettingsPatch {
+    pub(crate) tags: Option<Vec<String>>,
+    pub(crate) diagnostics: Option<bool>,
+    #[serde(flatten)]
+    pub(crate) _extra: HashMap<String, Value>,
+}
@@ -0,0 +1,238 @@
+//! Checks of a settings payload against [`schema_fields`], so that typos
+//! such as `diagnostcs.onType` and values of the wrong type or out of range
+//! are reported instead of silently ignored.
+
+use std::{collections::BTreeSet, fmt};
+
+use serde_json::Value;
+
+use crate::{
+    config::{
+        SETTINGS_SECTION_KEY,
+        schema::{SchemaField, SchemaType, schema_fields},
+    },
+    ide::spelling::closest_names,
+    metal::language_version::LanguageVersion,
+};
+
+/// Keys accepted without being settings of the server: the binary path,
+/// which only the editor extension reads.
+const CLIENT_ONLY_KEYS: &[&str] = &["serverPath"];
+
+/// A setting that is unknown or holds a value the server cannot use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingsProblem {
+    /// Dotted key, e.g. `diagnostics.onType`.
+    pub key: String,
+    pub kind: SettingsProblemKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SettingsProblemKind {
+    /// No such setting; with the closest known keys, nearest first.
+    Unknown {
+        suggestions: Vec<String>,
+    },
+    /// The value has the wrong type or is not one of the allowed values.
+    Invalid {
+        expected: String,
+    },
+    /// A number outside the bounds it is clamped to.
+    OutOfRange {
+        minimum: Option<i64>,
+        maximum: Option<i64>,
+    },
+}
+
+impl fmt::Display for SettingsProblem {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match &self.kind {
+            SettingsProblemKind::Unknown {
+                suggestions,
+            } => match suggestions.first() {
+                Some(suggestion) => write!(f, "`{}` is not a setting; did you mean `{suggestion}`?", self.key),
+                None => write!(f, "`{}` is not a setting", self.key),
+            },
+            SettingsProblemKind::Invalid {
+                expected,
+            } => write!(f, "`{}` should be {expected}", self.key),
+            SettingsProblemKind::OutOfRange {
+                minimum,
+                maximum,
+            } => match (minimum, maximum) {
+                (Some(minimum), Some(maximum)) => {
+                    write!(f, "`{}` should be between {minimum} and {maximum}", self.key)
+                },
+                (Some(minimum), None) => write!(f, "`{}` should be at least {minimum}", self.key),
+                (None, Some(maximum)) => write!(f, "`{}` should be at most {maximum}", self.key),
+                (None, None) => write!(f, "`{}` is out of range", self.key),
+            },
+        }
+    }
+}
+
+/// The problems in a settings payload, in key order. The payload may be
+/// scoped under `metal-analyzer` as editors send it, or hold the settings
+/// directly as `.metal-analyzer.json` does. `null` values stand for unset
+/// settings and are accepted.
+pub fn validate_settings_payload(payload: &Value) -> Vec<SettingsProblem> {
+    let settings = match payload.get(SETTINGS_SECTION_KEY) {
+        Some(scoped) => scoped,
+        None => payload,
+    };
+    let fields = schema_fields();
+    let sections: BTreeSet<String> = fields
+        .iter()
+        .flat_map(|field| {
+            let segments: Vec<&str> = field.key.split('.').collect();
+            (1..segments.len()).map(move |len| segments[..len].join("."))
+        })
+        .collect();
+    let mut problems = Vec::new();
+    if let Value::Object(object) = settings {
+        validate_object(object, "", &fields, &sections, &mut problems);
+    }
+    problems
+}
+
+fn validate_object(
+    object: &serde_json::Map<String, Value>,
+    prefix: &str,
+    fields: &[SchemaField],
+    sections: &BTreeSet<String>,
+    problems: &mut Vec<SettingsProblem>,
+) {
+    for (name, value) in object {
+        let key = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{prefix}.{name}")
+        };
+        if value.is_null() || name.starts_with('$') || (prefix.is_empty() && CLIENT_ONLY_KEYS.contains(&name.as_str()))
+        {
+            continue;
+        }
+        if let Some(field) = fields.iter().find(|field| field.key == key) {
+            if let Some(kind) = check_value(field, value) {
+                problems.push(SettingsProblem {
+                    key,
+                    kind,
+                });
+            }
+        } else if sections.contains(&key) {
+            match value {
+                Value::Object(section) => validate_object(section, &key, fields, sections, problems),
+                _ => problems.push(SettingsProblem {
+                    key,
+                    kind: SettingsProblemKind::Invalid {
+                        expected: "an object".to_string(),
+                    },
+                }),
+            }
+        } else {
+            // Typos are looked up among the other keys of the same section.
+            let siblings: BTreeSet<&str> = fields
+                .iter()
+                .map(|field| field.key.as_str())
+                .chain(sections.iter().map(String::as_str))
+                .filter_map(|known| match prefix {
+                    "" => known.split('.').next(),
+                    prefix => known.strip_prefix(prefix)?.strip_prefix('.')?.split('.').next(),
+                })
+                .collect();
+            let suggestions = closest_names(name, siblings)
+                .into_iter()
+                .map(|sibling| {
+                    if prefix.is_empty() {
+                        sibling.to_string()
+                    } else {
+                        format!("{prefix}.{sibling}")
+                    }
+                })
+                .collect();
+            problems.push(SettingsProblem {
+                key,
+                kind: SettingsProblemKind::Unknown {
+                    suggestions,
+                },
+            });
+        }
+    }
+}
+
+fn check_value(
+    field: &SchemaField,
+    value: &Value,
+) -> Option<SettingsProblemKind> {
+    let expected = match &field.schema_type {
+        SchemaType::Bool => (!value.is_boolean()).then(|| "`true` or `false`".to_string()),
+        SchemaType::String => (!value.is_string()).then(|| "a string".to_string()),
+        SchemaType::Integer {
+            minimum,
+            maximum,
+        } => return check_integer(value, *minimum, *maximum),
+        SchemaType::StringEnum {
+            values,
+        } => {
+            let known = value.as_str().is_some_and(|text| {
+                let text = text.trim();
+                values.iter().any(|allowed| allowed.eq_ignore_ascii_case(text))
+                    || (field.key == "compiler.languageVersion" && LanguageVersion::parse(text).is_some())
+            });
+            (!known).then(|| {
+                let values: Vec<String> = values.iter().map(|allowed| format!("`{allowed}`")).collect();
+                format!("one of {}", values.join(", "))
+            })
+        },
+        SchemaType::StringArray => {
+            let strings = value.as_array().is_some_and(|items| items.iter().all(Value::is_string));
+            (!strings).then(|| "an array of strings".to_string())
+        },
+        SchemaType::StringMap => {
+            let strings = value.as_object().is_some_and(|entries| entries.values().all(Value::is_string));
+            (!strings).then(|| "an object of strings".to_string())
+        },
+        SchemaType::ObjectArray {
+            string_key,
+            ..
+        } => {
+            let objects = value
+                .as_array()
+                .is_some_and(|items| items.iter().all(|item| item.get(*string_key).is_some_and(Value::is_string)));
+            (!objects).then(|| format!("an array of objects with a `{string_key}` string"))
+        },
+    };
+    expected.map(|expected| SettingsProblemKind::Invalid {
+        expected,
+    })
+}
+
+fn check_integer(
+    value: &Value,
+    minimum: Option<i64>,
+    maximum: Option<i64>,
+) -> Option<SettingsProblemKind> {
+    let out_of_range = SettingsProblemKind::OutOfRange {
+        minimum,
+        maximum,
+    };
+    let Some(number) = value.as_i64() else {
+        // Whole numbers too large for an `i64` are above any maximum.
+        return match value.as_u64() {
+            Some(_) => maximum.is_some().then_some(out_of_range),
+            None => Some(SettingsProblemKind::Invalid {
+                expected: "a whole number".to_string(),
+            }),
+        };
+    };
+    let below = minimum.is_some_and(|minimum| number < minimum);
+    let above = maximum.is_some_and(|maximum| number > maximum);
+    (below || above).then_some(out_of_range)
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/config/validation_tests.rs"]
+mod tests;
@@ -0,0 +1,131 @@
+//! Checked-in `.metal-analyzer.json` settings at a workspace root.
+//!
+//! The file uses the same shape as the LSP payload, e.g.
+//! `{ "compiler": { "includePaths": ["Shaders/Include"] } }`, and is layered
+//! between the built-in defaults and the editor's settings. Relative paths in
+//! it are resolved against the workspace root it lives in, so the same file
+//! works on every checkout.
+
+use std::path::Path;
+
+use serde_json::Value;
+use tracing::warn;
+
+use crate::config::SETTINGS_SECTION_KEY;
+
+/// File name of the per-workspace settings file.
+pub const WORKSPACE_SETTINGS_FILE: &str = ".metal-analyzer.json";
+
+/// Read and parse `.metal-analyzer.json` at `root`.
+///
+/// Returns `None` when the file does not exist or is not a JSON object; a
+/// malformed file is logged and ignored rather than failing startup.
+pub fn read_workspace_settings_file(root: &Path) -> Option<Value> {
+    let path = root.join(WORKSPACE_SETTINGS_FILE);
+    let text = std::fs::read_to_string(&path).ok()?;
+    let value: Value = match serde_json::from_str(&text) {
+        Ok(value) => value,
+        Err(error) => {
+            warn!("Ignoring {}: {error}", path.display());
+            return None;
+        },
+    };
+    let mut settings = match value {
+        Value::Object(mut object) => match object.remove(SETTINGS_SECTION_KEY) {
+            Some(scoped @ Value::Object(_)) => scoped,
+            _ => Value::Object(object),
+        },
+        _ => {
+            warn!("Ignoring {}: expected a JSON object", path.display());
+            return None;
+        },
+    };
+    resolve_relative_paths(&mut settings, root);
+    Some(settings)
+}
+
+/// Anchor relative `compiler.includePaths`, `compiler.virtualIncludeRoots`
+/// and `compiler.tempDir` entries, and the globs and include paths of
+/// `compiler.overrides`, at `root`.
+///
+/// `indexing.excludePaths` is left alone since it is already resolved against
+/// every workspace root.
+fn resolve_relative_paths(
+    settings: &mut Value,
+    root: &Path,
+) {
+    let Some(compiler) = settings.get_mut("compiler") else {
+        return;
+    };
+    if let Some(Value::Array(paths)) = compiler.get_mut("includePaths") {
+        for path in paths.iter_mut() {
+            resolve_path_value(path, root);
+        }
+    }
+    if let Some(Value::Object(roots)) = compiler.get_mut("virtualIncludeRoots") {
+        for dir in roots.values_mut() {
+            resolve_path_value(dir, root);
+        }
+    }
+    if let Some(temp_dir) = compiler.get_mut("tempDir") {
+        resolve_path_value(temp_dir, root);
+    }
+    if let Some(Value::Array(overrides)) = compiler.get_mut("overrides") {
+        for entry in overrides.iter_mut() {
+            if let Some(glob) = entry.get_mut("pathGlob") {
+                resolve_path_value(glob, root);
+            }
+            if let Some(Value::Array(paths)) = entry.get_mut("includePaths") {
+                for path in paths.iter_mut() {
+                    resolve_path_value(path, root);
+                }
+            }
+        }
+    }
+}
+
+fn resolve_path_value(
+    value: &mut Value,
+    root: &Path,
+) {
+    let Value::String(path) = value else {
+        return;
+    };
+    let trimmed = path.trim();
+    if trimmed.is_empty() || Path::new(trimmed).is_absolute() || trimmed.starts_with('~') {
+        return;
+    }
+    *path = root.join(trimmed).to_string_lossy().into_owned();
+}
+
+/// Deep-merge `patch` into `base`.
+///
+/// Objects are merged key by key, any other value replaces the previous one,
+/// and `null` removes the key so a lower-precedence layer shows through again.
+pub fn merge_json_values(
+    base: &mut Value,
+    patch: &Value,
+) {
+    let (Value::Object(base_object), Value::Object(patch_object)) = (&mut *base, patch) else {
+        if !patch.is_null() {
+            *base = patch.clone();
+        }
+        return;
+    };
+    for (key, value) in patch_object {
+        if value.is_null() {
+            base_object.remove(key);
+        } else if let Some(existing) = base_object.get_mut(key)
+            && existing.is_object()
+            && value.is_object()
+        {
+            merge_json_values(existing, value);
+        } else {
+            base_object.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/config/workspace_file_tests.rs"]
+mod tests;
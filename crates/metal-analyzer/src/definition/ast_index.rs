@@ -2,7 +2,12 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-use crate::definition::{ref_site::RefSite, symbol_def::SymbolDef, utils::is_system_header};
+use crate::definition::{
+    interner::SharedStr,
+    ref_site::{RefSite, RefSiteLocation},
+    symbol_def::SymbolDef,
+    utils::is_system_header,
+};
 
 /// Indexed AST data for a single translation unit.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,16 +17,125 @@ pub struct AstIndex {
     /// Map from ID to index in `defs`.
     pub id_to_def: HashMap<String, usize>,
     /// Map from name to indices in `defs`.
-    pub name_to_defs: HashMap<String, Vec<usize>>,
+    pub name_to_defs: HashMap<SharedStr, Vec<usize>>,
     /// Map from target_id to all reference sites pointing to it.
     pub target_id_to_refs: HashMap<String, Vec<usize>>,
     /// Map from file path to indices in `defs` for definitions in that file.
-    pub file_to_defs: HashMap<String, Vec<usize>>,
+    pub file_to_defs: HashMap<SharedStr, Vec<usize>>,
     /// Map from file path to indices in `refs` for references in that file.
-    pub file_to_refs: HashMap<String, Vec<usize>>,
+    pub file_to_refs: HashMap<SharedStr, Vec<usize>>,
+    /// Class template instantiations and explicit specializations.
+    #[serde(default)]
+    pub specializations: Vec<TemplateSpecialization>,
+}
+
+/// An instantiation or explicit specialization of a class template.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateSpecialization {
+    /// Id of the `ClassTemplateSpecializationDecl`.
+    pub id: String,
+    /// Template name, e.g. `Tile`.
+    pub name: String,
+    /// Arguments as Clang prints them, e.g. `["half", "4"]`.
+    pub args: Vec<String>,
+    /// Ids of the fields and methods declared in its body.
+    pub member_ids: Vec<String>,
 }
 
 impl AstIndex {
+    /// Build the lookup maps over `defs` and `refs`. When an id is both
+    /// declared and defined, `id_to_def` points at the definition.
+    pub(crate) fn from_parts(
+        defs: Vec<SymbolDef>,
+        refs: Vec<RefSite>,
+        specializations: Vec<TemplateSpecialization>,
+    ) -> Self {
+        let mut id_to_def = HashMap::with_capacity(defs.len());
+        let mut name_to_defs: HashMap<SharedStr, Vec<usize>> = HashMap::with_capacity(defs.len());
+        let mut target_id_to_refs: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut file_to_defs: HashMap<SharedStr, Vec<usize>> = HashMap::new();
+        let mut file_to_refs: HashMap<SharedStr, Vec<usize>> = HashMap::new();
+
+        for (i, def) in defs.iter().enumerate() {
+            id_to_def
+                .entry(def.id.clone())
+                .and_modify(|existing_idx: &mut usize| {
+                    let existing_def = &defs[*existing_idx];
+                    if def.is_definition && !existing_def.is_definition {
+                        *existing_idx = i;
+                    }
+                })
+                .or_insert(i);
+            name_to_defs.entry(def.name.clone()).or_default().push(i);
+            file_to_defs.entry(def.file.clone()).or_default().push(i);
+        }
+
+        for (i, ref_site) in refs.iter().enumerate() {
+            target_id_to_refs.entry(ref_site.target_id.clone()).or_default().push(i);
+            file_to_refs.entry(ref_site.file.clone()).or_default().push(i);
+        }
+
+        Self {
+            defs,
+            refs,
+            id_to_def,
+            name_to_defs,
+            target_id_to_refs,
+            file_to_defs,
+            file_to_refs,
+            specializations,
+        }
+    }
+
+    /// Approximate heap size of the index in bytes: its entries, the strings
+    /// it owns, and the lookup maps. [`SharedStr`]s live in the intern pool
+    /// and are not counted, nor are allocator slack and hash table control
+    /// bytes.
+    pub fn approximate_size(&self) -> usize {
+        let defs: usize = self
+            .defs
+            .iter()
+            .map(|def| size_of::<SymbolDef>() + def.id.len() + def.type_name.as_ref().map_or(0, String::len))
+            .sum();
+        let refs: usize = self
+            .refs
+            .iter()
+            .map(|site| {
+                let locations = [&site.expansion, &site.spelling].into_iter().flatten().count();
+                size_of::<RefSite>() + site.target_id.len() + locations * size_of::<RefSiteLocation>()
+            })
+            .sum();
+        fn position_map<K>(map: &HashMap<K, Vec<usize>>) -> usize {
+            map.values().map(|positions| size_of::<(K, Vec<usize>)>() + size_of_val(positions.as_slice())).sum()
+        }
+        let id_to_def: usize = self.id_to_def.keys().map(|id| size_of::<(String, usize)>() + id.len()).sum();
+        let specializations: usize = self
+            .specializations
+            .iter()
+            .map(|specialization| {
+                size_of::<TemplateSpecialization>()
+                    + specialization.id.len()
+                    + specialization.name.len()
+                    + specialization
+                        .args
+                        .iter()
+                        .chain(&specialization.member_ids)
+                        .map(|text| size_of::<String>() + text.len())
+                        .sum::<usize>()
+            })
+            .sum();
+        size_of::<Self>()
+            + defs
+            + refs
+            + id_to_def
+            + position_map(&self.name_to_defs)
+            + position_map(&self.target_id_to_refs)
+            + self.target_id_to_refs.keys().map(String::len).sum::<usize>()
+            + position_map(&self.file_to_defs)
+            + position_map(&self.file_to_refs)
+            + specializations
+    }
+
     /// Get all declarations (non-definitions) for a symbol by name.
     pub fn get_declarations(
         &self,
@@ -129,4 +243,59 @@ impl AstIndex {
             })
             .unwrap_or_default()
     }
+
+    /// Members named `member` of the specialization of template `name` with
+    /// `args`. Arguments are compared without whitespace.
+    pub fn specialization_members(
+        &self,
+        name: &str,
+        args: &[&str],
+        member: &str,
+    ) -> Vec<&SymbolDef> {
+        let squeeze = |arg: &str| arg.split_whitespace().collect::<String>();
+        self.specializations
+            .iter()
+            .filter(|specialization| {
+                specialization.name == name
+                    && specialization.args.len() == args.len()
+                    && specialization.args.iter().zip(args).all(|(a, b)| squeeze(a) == squeeze(b))
+            })
+            .flat_map(|specialization| &specialization.member_ids)
+            .filter_map(|id| self.id_to_def.get(id).map(|&i| &self.defs[i]))
+            .filter(|def| def.name == member)
+            .collect()
+    }
+
+    /// Fields and methods declared in the body of the record named `record_name`.
+    ///
+    /// Clang's dump does not link members to their parent, so a member belongs
+    /// to the nearest record declared before it in the same file.
+    pub fn record_members(
+        &self,
+        record_name: &str,
+    ) -> Vec<&SymbolDef> {
+        let is_record =
+            |def: &SymbolDef| matches!(def.kind.as_str(), "CXXRecordDecl" | "ClassTemplateSpecializationDecl");
+        let Some(indices) = self.name_to_defs.get(record_name) else {
+            return Vec::new();
+        };
+
+        let mut members = Vec::new();
+        for record in indices.iter().map(|&i| &self.defs[i]).filter(|def| is_record(def) && def.is_definition) {
+            let Some(file_defs) = self.file_to_defs.get(&record.file) else {
+                continue;
+            };
+            let mut in_file: Vec<&SymbolDef> = file_defs.iter().map(|&i| &self.defs[i]).collect();
+            in_file.sort_by_key(|def| (def.line, def.col));
+            members.extend(
+                in_file
+                    .into_iter()
+                    .skip_while(|def| !std::ptr::eq(*def, record))
+                    .skip(1)
+                    .take_while(|def| !is_record(def) || def.name == record_name)
+                    .filter(|def| matches!(def.kind.as_str(), "FieldDecl" | "CXXMethodDecl")),
+            );
+        }
+        members
+    }
 }
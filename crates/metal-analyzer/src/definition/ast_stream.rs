@@ -0,0 +1,186 @@
+//! Streaming parser for Clang's `-ast-dump=json` output.
+//!
+//! The dump of a translation unit including the Metal standard library runs
+//! to hundreds of megabytes. Rather than reading it into a string and
+//! deserializing the whole tree, the translation unit's children are
+//! deserialized one at a time straight from the compiler's stdout, walked
+//! into an [`IndexBuilder`] and dropped.
+
+use std::{
+    fmt,
+    io::{BufReader, Read},
+};
+
+use serde::{
+    Deserialize,
+    de::{
+        DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor,
+        value::{BorrowedStrDeserializer, Error as ValueError},
+    },
+};
+
+use crate::definition::{clang_nodes::Node, indexer::IndexBuilder};
+
+/// Collect the index of the AST dump read from `reader`.
+///
+/// Clang's dump of large Metal kernels nests expression trees deeper than
+/// serde_json's default 128-level recursion cap. The cap is disabled and
+/// deserialization routed through serde_stacker, so deep recursion grows the
+/// stack on demand instead of blowing it.
+pub(crate) fn stream_index(reader: impl Read) -> serde_json::Result<IndexBuilder> {
+    let mut builder = IndexBuilder::default();
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(reader));
+    deserializer.disable_recursion_limit();
+    with_location_context(|| {
+        serde_stacker::Deserializer::new(&mut deserializer).deserialize_map(TranslationUnit {
+            builder: &mut builder,
+        })
+    })?;
+    deserializer.end()?;
+    Ok(builder)
+}
+
+/// Visits the root node, streaming its `inner` children.
+struct TranslationUnit<'a> {
+    builder: &'a mut IndexBuilder,
+}
+
+impl<'de> Visitor<'de> for TranslationUnit<'_> {
+    type Value = ();
+
+    fn expecting(
+        &self,
+        formatter: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        formatter.write_str("a Clang AST node")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<std::borrow::Cow<'de, str>>()? {
+            if key == "inner" {
+                map.next_value_seed(TopLevelNodes {
+                    builder: &mut *self.builder,
+                })?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Walks each child of the translation unit as soon as it is parsed.
+struct TopLevelNodes<'a> {
+    builder: &'a mut IndexBuilder,
+}
+
+impl<'de> DeserializeSeed<'de> for TopLevelNodes<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TopLevelNodes<'_> {
+    type Value = ();
+
+    fn expecting(
+        &self,
+        formatter: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        formatter.write_str("a list of Clang AST nodes")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> Result<(), A::Error> {
+        while let Some(node) = seq.next_element::<Node>()? {
+            self.builder.add_top_level(&node);
+        }
+        Ok(())
+    }
+}
+
+/// Run `f` with clang-ast's location state kept across the nodes it
+/// deserializes.
+///
+/// Clang omits a location's file when it matches the previous location's,
+/// and clang-ast fills it in from thread-local state that it clears once the
+/// outermost [`Node`] is deserialized. Each streamed child is an outermost
+/// node of its own, so `f` runs while a placeholder node is deserialized:
+/// the placeholder asks for its fields only after its kind, and `f` runs
+/// instead of yielding them.
+fn with_location_context<R>(f: impl FnOnce() -> R) -> R {
+    let mut f = Some(f);
+    let mut result = None;
+    let mut run = || result = f.take().map(|f| f());
+    let _ = clang_ast::Node::<IgnoredAny>::deserialize(Placeholder {
+        run: &mut run,
+        kind_read: false,
+    });
+    match (result, f) {
+        (Some(result), _) => result,
+        // clang-ast read the placeholder differently; run without the state.
+        (None, Some(f)) => f(),
+        (None, None) => unreachable!("`f` ran without a result"),
+    }
+}
+
+/// A node map `{"kind": "TranslationUnitDecl"}` that runs a closure when
+/// asked for its remaining fields.
+struct Placeholder<'a> {
+    run: &'a mut dyn FnMut(),
+    kind_read: bool,
+}
+
+impl<'de> Deserializer<'de> for Placeholder<'_> {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        visitor.visit_map(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de> MapAccess<'de> for Placeholder<'_> {
+    type Error = ValueError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, ValueError> {
+        if !self.kind_read {
+            return seed.deserialize(BorrowedStrDeserializer::new("kind")).map(Some);
+        }
+        (self.run)();
+        Ok(None)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, ValueError> {
+        self.kind_read = true;
+        seed.deserialize(BorrowedStrDeserializer::new("TranslationUnitDecl"))
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/definition/ast_stream_tests.rs"]
+mod tests;
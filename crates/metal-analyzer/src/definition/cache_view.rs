@@ -0,0 +1,237 @@
+//! Read-only text rendering of a cached [`AstIndex`] for debugging navigation.
+//!
+//! The view lists the definitions of one file's translation unit, with the
+//! rank goto-definition gives each from that file, and the file's references. Every location in the
+//! view is a link, so editors can jump from the view back into the sources.
+
+use std::path::Path;
+
+use tower_lsp::lsp_types::{Location, Position, Range, Url};
+
+use crate::{
+    definition::{
+        ast_index::AstIndex,
+        symbol_def::SymbolDef,
+        symbol_rank::{RankFactors, RankingWeights},
+        utils::{def_to_location, is_system_header, paths_match},
+    },
+    ide::lsp::ide_location_to_lsp,
+    text_pos::{column_in_file, text_width},
+};
+
+/// URI scheme of the virtual cache documents.
+pub const CACHE_VIEW_SCHEME: &str = "metal-analyzer-cache";
+
+/// A rendered cache document.
+#[derive(Debug, Clone)]
+pub struct CacheView {
+    pub content: String,
+    pub links: Vec<CacheViewLink>,
+}
+
+/// A clickable location inside a [`CacheView`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheViewLink {
+    /// Range of the location text in the view.
+    pub range: Range,
+    pub target: Location,
+    /// Markdown shown when hovering the link's line.
+    pub detail: String,
+}
+
+impl CacheView {
+    /// The link under `position`, or the first link on its line.
+    pub fn link_at(
+        &self,
+        position: Position,
+    ) -> Option<&CacheViewLink> {
+        let on_line = || self.links.iter().filter(|link| link.range.start.line == position.line);
+        on_line()
+            .find(|link| {
+                link.range.start.character <= position.character && position.character <= link.range.end.character
+            })
+            .or_else(|| on_line().next())
+    }
+}
+
+/// Cache view URI for a `file://` document, e.g. `metal-analyzer-cache:///path/shader.metal`.
+pub fn cache_view_uri(file_uri: &Url) -> Option<Url> {
+    if file_uri.scheme() != "file" {
+        return None;
+    }
+    Url::parse(&format!("{CACHE_VIEW_SCHEME}://{}", file_uri.path())).ok()
+}
+
+/// The `file://` document a cache view URI was rendered for.
+pub fn cache_view_source_uri(view_uri: &Url) -> Option<Url> {
+    if view_uri.scheme() != CACHE_VIEW_SCHEME {
+        return None;
+    }
+    Url::parse(&format!("file://{}", view_uri.path())).ok()
+}
+
+/// Render `index` as seen from `file`: every definition of its translation
+/// unit ranked from `file`, and the references in `file`.
+///
+/// System header definitions are counted but not listed, since they are the
+/// same for every translation unit and would drown the file's own symbols.
+pub fn render_cache_view(
+    index: &AstIndex,
+    file: &Path,
+    weights: &RankingWeights,
+) -> CacheView {
+    let file_str = file.display().to_string();
+    let mut out = ViewBuilder::default();
+
+    let mut defs: Vec<&SymbolDef> = index.defs.iter().filter(|def| !is_system_header(&def.file)).collect();
+    defs.sort_by(|a, b| (&a.file, a.line, a.col, &a.name).cmp(&(&b.file, b.line, b.col, &b.name)));
+    let system_defs = index.defs.len() - defs.len();
+    let mut refs: Vec<_> = index.refs.iter().filter(|r| paths_match(&r.file, &file_str)).collect();
+    refs.sort_by_key(|r| (r.line, r.col));
+
+    out.text(&format!("# AST cache for {file_str}"));
+    out.newline();
+    out.text(&format!(
+        "# {} definitions ({} in system headers, not listed), {} references in this file",
+        index.defs.len(),
+        system_defs,
+        refs.len()
+    ));
+    out.newline();
+    out.text("# rank = sum of the navigation.ranking penalties that apply; lower wins");
+    out.newline();
+    out.newline();
+
+    out.text("## Definitions");
+    out.newline();
+    for def in defs {
+        let rank = rank_text(RankFactors::of(&def.name, def, &file_str, weights), weights);
+        let role = if def.is_definition {
+            "definition"
+        } else {
+            "declaration"
+        };
+        let detail = def_detail(def, &rank);
+        out.text(&format!("{:<20} {:<24} ", def.kind, def.name));
+        if let Some(target) = def_location(def) {
+            out.link(&format!("{}:{}:{}", def.file, def.line, def.col), target, &detail);
+        }
+        out.text(&format!("  {role}  rank {rank}"));
+        if let Some(qual_type) = &def.qual_type {
+            out.text(&format!("  {qual_type}"));
+        }
+        out.newline();
+    }
+
+    out.newline();
+    out.text("## References");
+    out.newline();
+    for site in refs {
+        let target_def = index.id_to_def.get(&site.target_id).map(|&i| &index.defs[i]);
+        let detail = match target_def {
+            Some(def) => {
+                let rank = rank_text(RankFactors::of(&def.name, def, &file_str, weights), weights);
+                format!("`{}` resolves to\n\n{}", site.target_name, def_detail(def, &rank))
+            },
+            None => format!("`{}` ({}) has no indexed declaration", site.target_name, site.target_kind),
+        };
+        if let Ok(uri) = Url::from_file_path(&site.file) {
+            let line = site.line.saturating_sub(1);
+            let col = site.col.saturating_sub(1);
+            let start = Position::new(line, column_in_file(&site.file, line, col));
+            let end = Position::new(line, column_in_file(&site.file, line, col + site.tok_len));
+            let target = Location {
+                uri,
+                range: Range::new(start, end),
+            };
+            out.link(&format!("{}:{}", site.line, site.col), target, &detail);
+        }
+        out.text(&format!("  {} -> {} {} ", site.target_name, site.target_kind, site.target_id));
+        if let Some(def) = target_def
+            && let Some(target) = def_location(def)
+        {
+            out.link(&format!("{}:{}:{}", def.file, def.line, def.col), target, &detail);
+        }
+        out.newline();
+    }
+
+    CacheView {
+        content: out.content,
+        links: out.links,
+    }
+}
+
+fn def_location(def: &SymbolDef) -> Option<Location> {
+    def_to_location(def).and_then(ide_location_to_lsp)
+}
+
+/// `12 (other file, declaration only)`, or `0` when no penalty applies.
+fn rank_text(
+    factors: RankFactors,
+    weights: &RankingWeights,
+) -> String {
+    let penalties = factors.penalties(weights);
+    if penalties.is_empty() {
+        return "0".to_string();
+    }
+    let reasons: Vec<&str> = penalties.iter().map(|(reason, _)| *reason).collect();
+    format!("{} ({})", factors.score(weights), reasons.join(", "))
+}
+
+fn def_detail(
+    def: &SymbolDef,
+    rank: &str,
+) -> String {
+    let mut detail = format!("**{}** `{}`\n\n- id: `{}`", def.kind, def.name, def.id);
+    detail.push_str(&format!("\n- location: `{}:{}:{}`", def.file, def.line, def.col));
+    detail.push_str(&format!("\n- definition: {}", def.is_definition));
+    if let Some(qual_type) = &def.qual_type {
+        detail.push_str(&format!("\n- type: `{qual_type}`"));
+    }
+    detail.push_str(&format!("\n- rank: `{rank}`"));
+    detail
+}
+
+/// Accumulates view text while tracking UTF-16 positions for links.
+#[derive(Default)]
+struct ViewBuilder {
+    content: String,
+    links: Vec<CacheViewLink>,
+    line: u32,
+    column: u32,
+}
+
+impl ViewBuilder {
+    fn text(
+        &mut self,
+        text: &str,
+    ) {
+        self.content.push_str(text);
+        self.column += text_width(text);
+    }
+
+    fn link(
+        &mut self,
+        text: &str,
+        target: Location,
+        detail: &str,
+    ) {
+        let start = Position::new(self.line, self.column);
+        self.text(text);
+        self.links.push(CacheViewLink {
+            range: Range::new(start, Position::new(self.line, self.column)),
+            target,
+            detail: detail.to_string(),
+        });
+    }
+
+    fn newline(&mut self) {
+        self.content.push('\n');
+        self.line += 1;
+        self.column = 0;
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/definition/cache_view_tests.rs"]
+mod tests;
@@ -27,6 +27,7 @@ pub enum Clang {
     UsingDecl(DeclData),
     TemplateTypeParmDecl(DeclData),
     NonTypeTemplateParmDecl(DeclData),
+    TemplateArgument(TemplateArgumentData),
 
     // --- References ---
     DeclRefExpr(RefExprData),
@@ -62,6 +63,23 @@ pub struct DeclData {
     pub is_this_declaration_a_definition: Option<bool>,
     #[serde(rename = "type")]
     pub ty: Option<QualType>,
+    /// `static` or `extern` when written on a function or variable.
+    #[serde(rename = "storageClass")]
+    pub storage_class: Option<String>,
+}
+
+/// A type or value argument of a template specialization, e.g. `half` or `4`.
+#[derive(Deserialize, Debug)]
+pub struct TemplateArgumentData {
+    #[serde(rename = "type")]
+    pub ty: Option<QualType>,
+    pub value: Option<String>,
+}
+
+impl TemplateArgumentData {
+    pub fn spelling(&self) -> Option<&str> {
+        self.ty.as_ref().and_then(|t| t.qual_type.as_deref()).or(self.value.as_deref())
+    }
 }
 
 /// Reference expression data (DeclRefExpr, MemberExpr).
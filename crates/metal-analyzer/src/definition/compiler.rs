@@ -1,11 +1,16 @@
 use std::{
-    process::Command,
+    io::{ErrorKind, Read},
+    process::{Command, ExitStatus, Stdio},
     sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
 };
 
+use tokio::sync::SemaphorePermit;
 use tower_lsp::lsp_types::Url;
 use tracing::{debug, warn};
 
+use crate::{metal::compiler::COMPILER_PROCESSES, perf};
+
 static NEXT_AST_DUMP_ID: AtomicU64 = AtomicU64::new(1);
 
 #[cfg(test)]
@@ -19,15 +24,112 @@ fn xcrun_command(args: &[String]) -> Command {
     command
 }
 
-pub(crate) fn run_ast_dump(
+/// How often a running compiler is checked for cancellation.
+const CANCELLATION_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Why an AST dump produced no JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AstDumpError {
+    Cancelled,
+    /// The compiler ran past the AST dump timeout and was killed.
+    TimedOut,
+    Failed,
+}
+
+/// Wait for a free compiler process slot, giving up once `is_cancelled`.
+fn acquire_process_slot(is_cancelled: &dyn Fn() -> bool) -> Option<SemaphorePermit<'static>> {
+    loop {
+        if let Ok(slot) = COMPILER_PROCESSES.try_acquire() {
+            return Some(slot);
+        }
+        if is_cancelled() {
+            return None;
+        }
+        std::thread::sleep(CANCELLATION_POLL_INTERVAL);
+    }
+}
+
+/// Run `command` to completion like [`Command::output`], killing it as soon
+/// as `is_cancelled` returns true or once it has run for `timeout`. Returns
+/// `None` when cancelled and an [`ErrorKind::TimedOut`] error on timeout.
+///
+/// Stdout is handed to `read_stdout` on a separate thread as it is written
+/// rather than collected, and whatever `read_stdout` leaves unread is
+/// drained so the child cannot block. Returns the exit status, the result of
+/// `read_stdout` and stderr.
+pub(crate) fn run_unless_cancelled<T: Send + 'static>(
+    command: &mut Command,
+    is_cancelled: &dyn Fn() -> bool,
+    timeout: Duration,
+    read_stdout: impl FnOnce(&mut dyn Read) -> T + Send + 'static,
+) -> Option<std::io::Result<(ExitStatus, T, Vec<u8>)>> {
+    let deadline = Instant::now() + timeout;
+    let mut child = match command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(error) => return Some(Err(error)),
+    };
+    // Read both pipes while polling so a large dump cannot block the child.
+    let stdout = child.stdout.take().map(|mut pipe| {
+        std::thread::spawn(move || {
+            let value = read_stdout(&mut pipe);
+            let _ = std::io::copy(&mut pipe, &mut std::io::sink());
+            value
+        })
+    });
+    let stderr = drain(child.stderr.take());
+
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if is_cancelled() => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            },
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Some(Err(ErrorKind::TimedOut.into()));
+            },
+            Ok(None) => std::thread::sleep(CANCELLATION_POLL_INTERVAL),
+            Err(error) => return Some(Err(error)),
+        }
+    };
+    let stdout = match stdout.map(std::thread::JoinHandle::join) {
+        Some(Ok(value)) => value,
+        Some(Err(_)) => return Some(Err(std::io::Error::other("stdout reader panicked"))),
+        None => return Some(Err(std::io::Error::other("stdout was not piped"))),
+    };
+    Some(Ok((status, stdout, stderr.join().unwrap_or_default())))
+}
+
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+/// Dump the AST of `source` as JSON into `parse` as the compiler writes it,
+/// returning what `parse` made of it with the temp file paths it was
+/// compiled under. `flags` follow the include paths. The compiler is killed
+/// when `is_cancelled` turns true or it runs longer than `timeout`.
+pub(crate) fn run_ast_dump<T: Send + 'static>(
     source: &str,
     uri: &Url,
     include_paths: &[String],
-) -> Option<(String, Vec<String>)> {
+    flags: &[String],
+    is_cancelled: &dyn Fn() -> bool,
+    timeout: Duration,
+    parse: impl FnOnce(&mut dyn Read) -> T + Send + 'static,
+) -> Result<(T, Vec<String>), AstDumpError> {
     let tmp_dir = std::env::temp_dir().join(format!("metal-analyzer-def-{}", std::process::id()));
     if std::fs::create_dir_all(&tmp_dir).is_err() {
         warn!("Failed to create temp dir for AST dump");
-        return None;
+        return Err(AstDumpError::Failed);
     }
 
     let compilation_id = NEXT_AST_DUMP_ID.fetch_add(1, Ordering::Relaxed);
@@ -46,7 +148,7 @@ pub(crate) fn run_ast_dump(
     if std::fs::write(&src_file, content).is_err() {
         warn!("Failed to write temp file for AST dump");
         let _ = std::fs::remove_dir(&tmp_dir);
-        return None;
+        return Err(AstDumpError::Failed);
     }
 
     let mut args = vec![
@@ -87,19 +189,44 @@ pub(crate) fn run_ast_dump(
         }
     }
 
+    args.extend(flags.iter().cloned());
+
     debug!("AST dump: xcrun {}", args.join(" "));
 
     let mut command = xcrun_command(&args);
-    let output = match command.output() {
-        Ok(output) => output,
-        Err(error) => {
-            warn!("Failed to run AST dump: {error}");
-            return None;
+    let output = match acquire_process_slot(is_cancelled) {
+        Some(_slot) => {
+            let started = Instant::now();
+            let output = run_unless_cancelled(&mut command, is_cancelled, timeout, parse);
+            if matches!(output, Some(Ok(_))) {
+                perf::record("astDump", started.elapsed());
+            }
+            output
         },
+        None => None,
     };
 
     let raw_tmp_file = src_file.display().to_string();
     let canonical_tmp_file = std::fs::canonicalize(&src_file).ok().map(|path| path.display().to_string());
+    let _ = std::fs::remove_file(&src_file);
+    let _ = std::fs::remove_dir(&tmp_dir);
+
+    let (status, parsed, stderr) = match output {
+        Some(Ok(output)) => output,
+        Some(Err(error)) if error.kind() == ErrorKind::TimedOut => {
+            warn!("[ast-dump] timed out after {timeout:?} for {uri}");
+            return Err(AstDumpError::TimedOut);
+        },
+        Some(Err(error)) => {
+            warn!("Failed to run AST dump: {error}");
+            return Err(AstDumpError::Failed);
+        },
+        None => {
+            debug!("[ast-dump] cancelled for {uri}");
+            return Err(AstDumpError::Cancelled);
+        },
+    };
+
     let mut tmp_files = vec![raw_tmp_file];
     if let Some(canonical) = canonical_tmp_file
         && !tmp_files.contains(&canonical)
@@ -107,11 +234,8 @@ pub(crate) fn run_ast_dump(
         tmp_files.push(canonical);
     }
 
-    let _ = std::fs::remove_file(&src_file);
-    let _ = std::fs::remove_dir(&tmp_dir);
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
         for line in stderr.lines() {
             if line.contains("error:") {
                 warn!("[ast-dump] compiler error: {line}");
@@ -120,15 +244,7 @@ pub(crate) fn run_ast_dump(
         debug!("[ast-dump] exited with non-zero status (partial AST may still be usable)");
     }
 
-    let stdout = String::from_utf8(output.stdout).ok()?;
-    if stdout.is_empty() || !stdout.starts_with('{') {
-        warn!("[ast-dump] produced no usable JSON for {uri}");
-        return None;
-    }
-
-    debug!("[ast-dump] produced {} bytes of JSON for {uri}", stdout.len());
-
-    Some((stdout, tmp_files))
+    Ok((parsed, tmp_files))
 }
 
 fn rewrite_includes(
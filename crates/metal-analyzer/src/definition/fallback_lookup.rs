@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, path::Path};
 
 use tower_lsp::lsp_types::Position;
 use tracing::debug;
@@ -10,10 +10,14 @@ use crate::{
         project_index::ProjectIndex,
         ref_site::RefSite,
         symbol_def::SymbolDef,
-        symbol_rank::{disambiguate_member_tie, rank_definition},
+        symbol_rank::{
+            RankingWeights, disambiguate_member_tie, disambiguate_overload_tie, rank_definition,
+            specialization_members_for_access,
+        },
         utils::{def_to_location, paths_match},
     },
     ide::navigation::{IdeLocation, IdePosition, IdeRange, NavigationTarget},
+    text_pos::column_in_file,
     vfs::FileId,
 };
 
@@ -23,6 +27,7 @@ pub(super) fn resolve_by_name(
     source: &str,
     position: Position,
     word: &str,
+    weights: &RankingWeights,
 ) -> Option<NavigationTarget> {
     let indices = index.name_to_defs.get(word)?;
 
@@ -42,21 +47,21 @@ pub(super) fn resolve_by_name(
     }
 
     deduped.sort_by(|a, b| {
-        rank_definition(word, a, source_file)
-            .cmp(&rank_definition(word, b, source_file))
+        rank_definition(word, a, source_file, weights)
+            .cmp(&rank_definition(word, b, source_file, weights))
             .then_with(|| a.file.cmp(&b.file))
             .then_with(|| a.line.cmp(&b.line))
             .then_with(|| a.col.cmp(&b.col))
     });
 
     let best = deduped.first().copied()?;
-    let best_rank = rank_definition(word, best, source_file);
-    let has_tie = deduped.get(1).is_some_and(|second| rank_definition(word, second, source_file) == best_rank);
+    let best_rank = rank_definition(word, best, source_file, weights);
+    let has_tie = deduped.get(1).is_some_and(|second| rank_definition(word, second, source_file, weights) == best_rank);
     if has_tie {
         let tied: Vec<&SymbolDef> = deduped
             .iter()
             .copied()
-            .take_while(|candidate| rank_definition(word, candidate, source_file) == best_rank)
+            .take_while(|candidate| rank_definition(word, candidate, source_file, weights) == best_rank)
             .collect();
 
         if let Some(disambiguated) = disambiguate_member_tie(index, &tied, source_file, source, position, word) {
@@ -75,6 +80,14 @@ pub(super) fn resolve_by_name(
             return def_to_location(disambiguated).map(NavigationTarget::Single);
         }
 
+        if let Some(disambiguated) = disambiguate_overload_tie(index, &tied, source_file, source, position, word) {
+            debug!(
+                "[goto-def] TIER-5 disambiguated overload tie '{word}' to {}:{}:{}",
+                disambiguated.file, disambiguated.line, disambiguated.col
+            );
+            return def_to_location(disambiguated).map(NavigationTarget::Single);
+        }
+
         debug!("[goto-def] TIER-5 ambiguous for '{word}' (top rank tie), suppressing fallback hit");
         return None;
     }
@@ -83,6 +96,20 @@ pub(super) fn resolve_by_name(
     def_to_location(best).map(NavigationTarget::Single)
 }
 
+/// A member access through a class template specialization: the member of
+/// each specialization the receiver is instantiated with.
+pub(super) fn resolve_specialization_member(
+    index: &AstIndex,
+    source_file: &str,
+    source: &str,
+    position: Position,
+    word: &str,
+) -> Option<NavigationTarget> {
+    let members = specialization_members_for_access(index, source_file, source, position, word);
+    debug!("[goto-def] TIER-4b {} specialization member(s) for '{word}'", members.len());
+    NavigationTarget::from_locations(members.into_iter().filter_map(def_to_location).collect())
+}
+
 pub(super) fn ref_site_to_location(ref_site: &RefSite) -> Option<IdeLocation> {
     let (file, line, col, tok_len) = if let Some(loc) = ref_site.expansion.as_ref() {
         (&loc.file, loc.line, loc.col, loc.tok_len)
@@ -92,11 +119,13 @@ pub(super) fn ref_site_to_location(ref_site: &RefSite) -> Option<IdeLocation> {
     if file.is_empty() {
         return None;
     }
+    let line = line.saturating_sub(1);
+    let col = col.saturating_sub(1);
     Some(IdeLocation::new(
         file,
         IdeRange::new(
-            IdePosition::new(line.saturating_sub(1), col.saturating_sub(1)),
-            IdePosition::new(line.saturating_sub(1), col.saturating_sub(1) + tok_len),
+            IdePosition::new(line, column_in_file(file, line, col)),
+            IdePosition::new(line, column_in_file(file, line, col + tok_len)),
         ),
     ))
 }
@@ -139,6 +168,7 @@ pub(super) fn resolve_from_project_index(
     project_graph_max_nodes: usize,
     word: &str,
     position: Position,
+    weights: &RankingWeights,
 ) -> Option<NavigationTarget> {
     let defs = source_file_id
         .map(|file_id| project_graph.scoped_files(file_id, project_graph_depth, project_graph_max_nodes))
@@ -155,7 +185,7 @@ pub(super) fn resolve_from_project_index(
                 Some(scoped_defs)
             }
         })
-        .unwrap_or_else(|| project_index.find_definitions(word));
+        .unwrap_or_else(|| project_index.find_definitions_from(word, Path::new(source_file)));
     if defs.is_empty() {
         return None;
     }
@@ -175,21 +205,21 @@ pub(super) fn resolve_from_project_index(
     }
 
     deduped.sort_by(|a, b| {
-        rank_definition(word, a, source_file)
-            .cmp(&rank_definition(word, b, source_file))
+        rank_definition(word, a, source_file, weights)
+            .cmp(&rank_definition(word, b, source_file, weights))
             .then_with(|| a.file.cmp(&b.file))
             .then_with(|| a.line.cmp(&b.line))
             .then_with(|| a.col.cmp(&b.col))
     });
 
     let best = deduped.first().copied()?;
-    let best_rank = rank_definition(word, best, source_file);
-    let has_tie = deduped.get(1).is_some_and(|second| rank_definition(word, second, source_file) == best_rank);
+    let best_rank = rank_definition(word, best, source_file, weights);
+    let has_tie = deduped.get(1).is_some_and(|second| rank_definition(word, second, source_file, weights) == best_rank);
     if has_tie {
         let tied: Vec<&SymbolDef> = deduped
             .iter()
             .copied()
-            .take_while(|candidate| rank_definition(word, candidate, source_file) == best_rank)
+            .take_while(|candidate| rank_definition(word, candidate, source_file, weights) == best_rank)
             .collect();
 
         if let Some(disambiguated) = disambiguate_parameter_tie(&tied, source_file, position) {
@@ -0,0 +1,258 @@
+//! The `#include` graph of project files.
+//!
+//! Nodes are files and edges are `#include` directives, each carrying the
+//! range of the included name in the including file. The graph is walked
+//! from root files through the headers they reach; system headers become
+//! nodes but are not read, so the SDK adds no edges of its own.
+
+use std::{
+    collections::{HashMap, VecDeque, hash_map::Entry},
+    path::{Path, PathBuf},
+};
+
+use tower_lsp::lsp_types::{Position, Range};
+
+use crate::{
+    definition::is_system_header,
+    ide::include_guards::include_guard,
+    server::header_owners::{normalize_path, resolve_include_path},
+    text_pos::column_of_byte_offset,
+    vfs::path_key,
+};
+
+/// One `#include` line of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludeDirective {
+    /// The name between the quotes or angle brackets.
+    pub name: String,
+    /// Written with angle brackets.
+    pub angled: bool,
+    /// The name with its delimiters.
+    pub range: Range,
+}
+
+/// The `#include` directives of `source`, in order.
+pub fn include_directives(source: &str) -> Vec<IncludeDirective> {
+    source
+        .lines()
+        .enumerate()
+        .filter_map(|(line_index, line)| {
+            let rest = line.trim_start().strip_prefix('#')?.trim_start().strip_prefix("include")?;
+            let offset = line.len() - rest.len();
+            let open = offset + rest.find(|ch: char| !ch.is_whitespace())?;
+            let (angled, close_char) = match line[open..].chars().next()? {
+                '<' => (true, '>'),
+                '"' => (false, '"'),
+                _ => return None,
+            };
+            let close = open + 1 + line[open + 1..].find(close_char)?;
+            let position = |byte| Position::new(line_index as u32, column_of_byte_offset(line, byte));
+            Some(IncludeDirective {
+                name: line[open + 1..close].to_string(),
+                angled,
+                range: Range::new(position(open), position(close + 1)),
+            })
+        })
+        .collect()
+}
+
+/// A file of the graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludeNode {
+    pub path: PathBuf,
+    /// An SDK or toolchain header, which is not read.
+    pub system: bool,
+    /// Read and protected by an include guard.
+    pub guarded: bool,
+}
+
+/// An `#include` of `to` by `from`, both indexes into the nodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludeEdge {
+    pub from: usize,
+    pub to: usize,
+    /// The included name in `from`.
+    pub range: Range,
+}
+
+#[derive(Debug, Default)]
+pub struct IncludeGraph {
+    nodes: Vec<IncludeNode>,
+    edges: Vec<IncludeEdge>,
+    by_key: HashMap<PathBuf, usize>,
+    files_read: usize,
+}
+
+impl IncludeGraph {
+    /// The files reachable from `roots`, each given with its text, reading
+    /// at most `max_files` project files with `read`.
+    pub fn build(
+        roots: impl IntoIterator<Item = (PathBuf, String)>,
+        include_paths: &[String],
+        max_files: usize,
+        mut read: impl FnMut(&Path) -> Option<String>,
+    ) -> Self {
+        let mut graph = Self::default();
+        for (path, source) in roots {
+            graph.add_root(&path, source, include_paths, max_files, &mut read);
+        }
+        graph
+    }
+
+    /// Add `path`, whose text is `source`, and the files it reaches through
+    /// `include_paths`, until `max_files` project files have been read.
+    /// Files already in the graph are not walked again.
+    pub fn add_root(
+        &mut self,
+        path: &Path,
+        source: String,
+        include_paths: &[String],
+        max_files: usize,
+        mut read: impl FnMut(&Path) -> Option<String>,
+    ) {
+        let (root, added) = self.add_node(path, false);
+        if !added {
+            return;
+        }
+        self.nodes[root].guarded = include_guard(&source).is_some();
+        self.files_read += 1;
+        let mut queue = VecDeque::from([(root, source)]);
+        while let Some((from, source)) = queue.pop_front() {
+            let includer = self.nodes[from].path.clone();
+            for include in include_directives(&source) {
+                let Some(target) = resolve_include_path(&includer, &include.name, include.angled, include_paths) else {
+                    continue;
+                };
+                let system = is_system_header(&target.to_string_lossy());
+                let (to, added) = self.add_node(&target, system);
+                self.edges.push(IncludeEdge {
+                    from,
+                    to,
+                    range: include.range,
+                });
+                if !added || system || self.files_read >= max_files {
+                    continue;
+                }
+                if let Some(text) = read(&target) {
+                    self.nodes[to].guarded = include_guard(&text).is_some();
+                    self.files_read += 1;
+                    queue.push_back((to, text));
+                }
+            }
+        }
+    }
+
+    /// Index of `path`, adding it if new, and whether it was added.
+    fn add_node(
+        &mut self,
+        path: &Path,
+        system: bool,
+    ) -> (usize, bool) {
+        let path = normalize_path(path);
+        let key = path_key(&path);
+        if let Some(&index) = self.by_key.get(&key) {
+            return (index, false);
+        }
+        let index = self.nodes.len();
+        self.nodes.push(IncludeNode {
+            path,
+            system,
+            guarded: false,
+        });
+        self.by_key.insert(key, index);
+        (index, true)
+    }
+
+    pub fn nodes(&self) -> &[IncludeNode] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[IncludeEdge] {
+        &self.edges
+    }
+
+    /// Index of the node for `path`.
+    pub fn node(
+        &self,
+        path: &Path,
+    ) -> Option<usize> {
+        self.by_key.get(&path_key(&normalize_path(path))).copied()
+    }
+
+    /// The `#include` directives of node `from`.
+    pub fn edges_from(
+        &self,
+        from: usize,
+    ) -> impl Iterator<Item = &IncludeEdge> {
+        self.edges.iter().filter(move |edge| edge.from == from)
+    }
+
+    /// The shortest chain of edges leading from node `from` to node `to`,
+    /// empty when they are the same node.
+    pub fn path(
+        &self,
+        from: usize,
+        to: usize,
+    ) -> Option<Vec<&IncludeEdge>> {
+        let mut reached_by: HashMap<usize, Option<&IncludeEdge>> = HashMap::from([(from, None)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut chain = Vec::new();
+                let mut node = to;
+                while let Some(Some(edge)) = reached_by.get(&node) {
+                    chain.push(*edge);
+                    node = edge.from;
+                }
+                chain.reverse();
+                return Some(chain);
+            }
+            for edge in self.edges_from(current) {
+                if let Entry::Vacant(entry) = reached_by.entry(edge.to) {
+                    entry.insert(Some(edge));
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+        None
+    }
+
+    /// The nodes reachable from node `from`, each with the `#include` of
+    /// `from` through which a breadth-first walk first reaches it.
+    pub fn reached_through(
+        &self,
+        from: usize,
+    ) -> HashMap<usize, &IncludeEdge> {
+        let mut reached: HashMap<usize, &IncludeEdge> = HashMap::new();
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        for edge in self.edges_from(from) {
+            if edge.to != from && !reached.contains_key(&edge.to) {
+                reached.insert(edge.to, edge);
+                queue.push_back(edge.to);
+            }
+        }
+        while let Some(current) = queue.pop_front() {
+            let through = reached[&current];
+            for edge in self.edges_from(current) {
+                if edge.to != from && !reached.contains_key(&edge.to) {
+                    reached.insert(edge.to, through);
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Whether `edge` closes a cycle, as the file it includes leads back to
+    /// the file including it.
+    pub fn in_cycle(
+        &self,
+        edge: &IncludeEdge,
+    ) -> bool {
+        self.path(edge.to, edge.from).is_some()
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/definition/include_graph_tests.rs"]
+mod tests;
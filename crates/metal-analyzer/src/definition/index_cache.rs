@@ -1,11 +1,20 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::Once,
+};
 
 use serde::{Deserialize, Serialize};
 use tracing::debug;
 
 use crate::definition::AstIndex;
 
-const CACHE_SCHEMA_VERSION: u32 = 1;
+const CACHE_SCHEMA_VERSION: u32 = 4;
+
+/// Length of the 64-bit FNV-1a keys used before [`stable_hash_hex`] moved to
+/// BLAKE3.
+const LEGACY_KEY_LEN: usize = 16;
+
+static LEGACY_SWEEP: Once = Once::new();
 
 #[derive(Debug, Serialize, Deserialize)]
 struct CachedAstIndex {
@@ -22,17 +31,55 @@ pub(crate) fn load(
     include_paths: &[String],
 ) -> Option<AstIndex> {
     let root = default_cache_dir();
+    LEGACY_SWEEP.call_once(|| remove_legacy_entries(&root));
     load_from_root(&root, source_file, source_hash, include_paths)
 }
 
+/// Cache `index` of `source_file`, returning whether it was written.
 pub(crate) fn save(
     source_file: &Path,
     source_hash: &str,
     include_paths: &[String],
     index: &AstIndex,
-) {
+) -> bool {
     let root = default_cache_dir();
-    save_to_root(&root, source_file, source_hash, include_paths, index);
+    LEGACY_SWEEP.call_once(|| remove_legacy_entries(&root));
+    save_to_root(&root, source_file, source_hash, include_paths, index)
+}
+
+/// Delete the cached index of `source_file`, e.g. after a header it
+/// includes changed without the source itself changing.
+pub(crate) fn remove(source_file: &Path) {
+    let _ = std::fs::remove_file(cache_file_path(&default_cache_dir(), source_file));
+}
+
+/// Delete every cached index, returning how many were removed.
+pub(crate) fn clear() -> usize {
+    clear_root(&default_cache_dir())
+}
+
+/// The number of cached indexes and the bytes they take on disk.
+pub(crate) fn disk_usage() -> (usize, u64) {
+    disk_usage_of_root(&default_cache_dir())
+}
+
+fn clear_root(root: &Path) -> usize {
+    cache_entries(root).filter(|path| std::fs::remove_file(path).is_ok()).count()
+}
+
+fn disk_usage_of_root(root: &Path) -> (usize, u64) {
+    cache_entries(root)
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .fold((0, 0), |(count, bytes), metadata| (count + 1, bytes + metadata.len()))
+}
+
+fn cache_entries(root: &Path) -> impl Iterator<Item = PathBuf> {
+    std::fs::read_dir(root)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
 }
 
 fn load_from_root(
@@ -65,9 +112,9 @@ fn save_to_root(
     source_hash: &str,
     include_paths: &[String],
     index: &AstIndex,
-) {
+) -> bool {
     if std::fs::create_dir_all(root).is_err() {
-        return;
+        return false;
     }
 
     let payload = CachedAstIndex {
@@ -79,9 +126,9 @@ fn save_to_root(
     };
     let cache_file = cache_file_path(root, source_file);
     let Ok(json) = serde_json::to_string(&payload) else {
-        return;
+        return false;
     };
-    let _ = std::fs::write(cache_file, json);
+    std::fs::write(cache_file, json).is_ok()
 }
 
 fn default_cache_dir() -> PathBuf {
@@ -99,18 +146,37 @@ fn cache_file_path(
     root.join(format!("{key}.json"))
 }
 
-fn include_paths_hash(include_paths: &[String]) -> String {
+pub(crate) fn include_paths_hash(include_paths: &[String]) -> String {
     let serialized = include_paths.join("\n");
     stable_hash_hex(&serialized)
 }
 
-fn stable_hash_hex(input: &str) -> String {
-    let mut hash: u64 = 0xcbf29ce484222325;
-    for byte in input.as_bytes() {
-        hash ^= *byte as u64;
-        hash = hash.wrapping_mul(0x100000001b3);
+/// BLAKE3 hash of `input` as hex, stable across builds and platforms.
+pub(crate) fn stable_hash_hex(input: &str) -> String {
+    blake3::hash(input.as_bytes()).to_hex().to_string()
+}
+
+/// Delete the `.json` files in `dir` named by a legacy 64-bit key. Their
+/// names no longer match any key, so nothing would read or replace them.
+pub(crate) fn remove_legacy_entries(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let legacy = path.extension().is_some_and(|extension| extension == "json")
+            && path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| stem.len() == LEGACY_KEY_LEN && stem.bytes().all(|byte| byte.is_ascii_hexdigit()));
+        if legacy && std::fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    if removed > 0 {
+        debug!("[index-cache] removed {removed} legacy entries from {}", dir.display());
     }
-    format!("{hash:016x}")
 }
 
 fn normalized_path_string(path: &Path) -> String {
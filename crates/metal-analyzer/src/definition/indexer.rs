@@ -1,20 +1,32 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::{Path, PathBuf},
+};
 
 use tracing::debug;
 
-use crate::definition::{
-    ast_index::AstIndex,
-    clang_nodes::{Clang, DeclData, Node, RefExprData, resolve_loc},
-    ref_site::{RefSite, RefSiteLocation},
-    symbol_def::SymbolDef,
-    utils::normalize_type_name,
+use crate::{
+    definition::{
+        ast_index::{AstIndex, TemplateSpecialization},
+        clang_nodes::{Clang, DeclData, Node, RefExprData, resolve_loc},
+        interner::SharedStr,
+        ref_site::{RefSite, RefSiteLocation},
+        symbol_def::SymbolDef,
+        utils::{is_system_header, normalize_type_name, paths_match},
+    },
+    server::header_owners::is_header_file,
 };
 
 /// Collect a declaration node into the definitions list.
+///
+/// `namespace_scope` tells whether the declaration sits directly in a
+/// namespace or the translation unit, where `static` and `const` give
+/// internal linkage.
 fn collect_decl(
     node: &Node,
     data: &DeclData,
     kind: &str,
+    namespace_scope: bool,
     defs: &mut Vec<SymbolDef>,
 ) {
     let name = match data.name() {
@@ -38,23 +50,32 @@ fn collect_decl(
         _ => return,
     };
 
-    let qual_type = data.qual_type().map(str::to_owned);
+    let qual_type = data.qual_type().map(SharedStr::new);
     let type_name = if matches!(kind, "VarDecl" | "FieldDecl" | "ParmVarDecl") {
         data.qual_type().and_then(normalize_type_name)
     } else {
         None
     };
 
+    let is_static = data.storage_class.as_deref() == Some("static");
+    // Only qualifiers after the last `*` or `&` apply to the variable itself.
+    let is_const = kind == "VarDecl"
+        && qual_type.as_deref().is_some_and(|ty| {
+            ty.rsplit(['*', '&']).next().unwrap_or(ty).split_whitespace().any(|word| word == "const")
+        });
+    let file_local = namespace_scope && matches!(kind, "FunctionDecl" | "VarDecl") && (is_static || is_const);
+
     defs.push(SymbolDef {
         id: node.id.to_string(),
-        name: name.to_owned(),
-        kind: kind.to_owned(),
-        file: bare.file.to_string(),
+        name: name.into(),
+        kind: kind.into(),
+        file: bare.file.as_ref().into(),
         line: bare.line as u32,
         col: bare.col as u32,
         is_definition: data.is_definition(),
         type_name,
         qual_type,
+        file_local,
     });
 }
 
@@ -90,7 +111,7 @@ fn collect_ref(
             return None;
         }
         Some(RefSiteLocation {
-            file: loc.file.to_string(),
+            file: loc.file.as_ref().into(),
             line: loc.line as u32,
             col: loc.col as u32,
             tok_len: loc.tok_len as u32,
@@ -101,133 +122,196 @@ fn collect_ref(
     let spelling = source_loc.spelling_loc.as_ref().and_then(to_ref_loc);
 
     refs.push(RefSite {
-        file: bare.file.to_string(),
+        file: bare.file.as_ref().into(),
         line: bare.line as u32,
         col: bare.col as u32,
         tok_len: bare.tok_len as u32,
         target_id: referenced.id.to_string(),
-        target_name: referenced.name.clone().unwrap_or_default(),
-        target_kind: referenced.kind.clone().unwrap_or_default(),
+        target_name: referenced.name.as_deref().unwrap_or_default().into(),
+        target_kind: referenced.kind.as_deref().unwrap_or_default().into(),
         expansion,
         spelling,
     });
 }
 
-/// Recursively walk the typed AST, collecting declarations and references.
+/// Record the arguments and members of a class template specialization.
+fn collect_specialization(
+    node: &Node,
+    data: &DeclData,
+    specializations: &mut Vec<TemplateSpecialization>,
+) {
+    let Some(name) = data.name().filter(|name| !name.is_empty()) else {
+        return;
+    };
+    let mut args = Vec::new();
+    let mut member_ids = Vec::new();
+    for child in &node.inner {
+        match &child.kind {
+            Clang::TemplateArgument(arg) => args.push(arg.spelling().unwrap_or_default().to_owned()),
+            Clang::CXXMethodDecl(member) | Clang::FieldDecl(member) if !member.is_implicit() => {
+                member_ids.push(child.id.to_string());
+            },
+            _ => {},
+        }
+    }
+    specializations.push(TemplateSpecialization {
+        id: node.id.to_string(),
+        name: name.to_owned(),
+        args,
+        member_ids,
+    });
+}
+
+/// Recursively walk the typed AST, collecting declarations, references and
+/// class template specializations.
 fn walk(
     node: &Node,
+    namespace_scope: bool,
     defs: &mut Vec<SymbolDef>,
     refs: &mut Vec<RefSite>,
+    specializations: &mut Vec<TemplateSpecialization>,
 ) {
     match &node.kind {
-        Clang::FunctionDecl(d) => collect_decl(node, d, "FunctionDecl", defs),
-        Clang::CXXRecordDecl(d) => collect_decl(node, d, "CXXRecordDecl", defs),
-        Clang::CXXMethodDecl(d) => collect_decl(node, d, "CXXMethodDecl", defs),
-        Clang::TypedefDecl(d) => collect_decl(node, d, "TypedefDecl", defs),
-        Clang::TypeAliasDecl(d) => collect_decl(node, d, "TypeAliasDecl", defs),
-        Clang::EnumDecl(d) => collect_decl(node, d, "EnumDecl", defs),
-        Clang::EnumConstantDecl(d) => collect_decl(node, d, "EnumConstantDecl", defs),
-        Clang::NamespaceDecl(d) => collect_decl(node, d, "NamespaceDecl", defs),
-        Clang::FunctionTemplateDecl(d) => collect_decl(node, d, "FunctionTemplateDecl", defs),
-        Clang::ClassTemplateDecl(d) => collect_decl(node, d, "ClassTemplateDecl", defs),
+        Clang::FunctionDecl(d) => collect_decl(node, d, "FunctionDecl", namespace_scope, defs),
+        Clang::CXXRecordDecl(d) => collect_decl(node, d, "CXXRecordDecl", namespace_scope, defs),
+        Clang::CXXMethodDecl(d) => collect_decl(node, d, "CXXMethodDecl", namespace_scope, defs),
+        Clang::TypedefDecl(d) => collect_decl(node, d, "TypedefDecl", namespace_scope, defs),
+        Clang::TypeAliasDecl(d) => collect_decl(node, d, "TypeAliasDecl", namespace_scope, defs),
+        Clang::EnumDecl(d) => collect_decl(node, d, "EnumDecl", namespace_scope, defs),
+        Clang::EnumConstantDecl(d) => collect_decl(node, d, "EnumConstantDecl", namespace_scope, defs),
+        Clang::NamespaceDecl(d) => collect_decl(node, d, "NamespaceDecl", namespace_scope, defs),
+        Clang::FunctionTemplateDecl(d) => collect_decl(node, d, "FunctionTemplateDecl", namespace_scope, defs),
+        Clang::ClassTemplateDecl(d) => collect_decl(node, d, "ClassTemplateDecl", namespace_scope, defs),
         Clang::ClassTemplateSpecializationDecl(d) => {
-            collect_decl(node, d, "ClassTemplateSpecializationDecl", defs);
+            collect_decl(node, d, "ClassTemplateSpecializationDecl", namespace_scope, defs);
+            collect_specialization(node, d, specializations);
         },
-        Clang::UsingDecl(d) => collect_decl(node, d, "UsingDecl", defs),
-        Clang::TemplateTypeParmDecl(d) => collect_decl(node, d, "TemplateTypeParmDecl", defs),
+        Clang::UsingDecl(d) => collect_decl(node, d, "UsingDecl", namespace_scope, defs),
+        Clang::TemplateTypeParmDecl(d) => collect_decl(node, d, "TemplateTypeParmDecl", namespace_scope, defs),
         Clang::NonTypeTemplateParmDecl(d) => {
-            collect_decl(node, d, "NonTypeTemplateParmDecl", defs);
+            collect_decl(node, d, "NonTypeTemplateParmDecl", namespace_scope, defs);
         },
-        Clang::VarDecl(d) => collect_decl(node, d, "VarDecl", defs),
-        Clang::FieldDecl(d) => collect_decl(node, d, "FieldDecl", defs),
-        Clang::ParmVarDecl(d) => collect_decl(node, d, "ParmVarDecl", defs),
+        Clang::VarDecl(d) => collect_decl(node, d, "VarDecl", namespace_scope, defs),
+        Clang::FieldDecl(d) => collect_decl(node, d, "FieldDecl", namespace_scope, defs),
+        Clang::ParmVarDecl(d) => collect_decl(node, d, "ParmVarDecl", namespace_scope, defs),
 
         Clang::DeclRefExpr(d) => collect_ref(node, d, refs),
         Clang::MemberExpr(d) => collect_ref(node, d, refs),
 
-        Clang::Other {
+        Clang::TemplateArgument(_)
+        | Clang::Other {
             ..
         } => {},
     }
 
+    // A function template's function sits at the template's scope.
+    let children_at_namespace_scope = match &node.kind {
+        Clang::NamespaceDecl(_) => true,
+        Clang::FunctionTemplateDecl(_) => namespace_scope,
+        _ => false,
+    };
     for child in &node.inner {
-        walk(child, defs, refs);
+        walk(child, children_at_namespace_scope, defs, refs, specializations);
     }
 }
 
-/// Build an [`AstIndex`] from a deserialized Clang AST root node.
-///
-/// `tmp_files` are the possible paths of the temp file that was compiled.
-/// `original_file` is the real document path — any definition whose file
-/// matches one of `tmp_files` will be rewritten to `original_file`.
-pub(crate) fn build_index(
-    root: &Node,
-    tmp_files: &[String],
-    original_file: Option<&str>,
-) -> AstIndex {
-    let mut defs = Vec::new();
-    let mut refs = Vec::new();
-    walk(root, &mut defs, &mut refs);
-
-    debug!("[build-index] collected {} defs, {} refs (original_file={:?})", defs.len(), refs.len(), original_file,);
-
-    if let Some(orig) = original_file {
-        for def in &mut defs {
-            if tmp_files.iter().any(|tmp| paths_equivalent(&def.file, tmp)) {
-                def.file = orig.to_owned();
-            }
-        }
-        for r in &mut refs {
-            if tmp_files.iter().any(|tmp| paths_equivalent(&r.file, tmp)) {
-                r.file = orig.to_owned();
-            }
-            if let Some(loc) = r.expansion.as_mut()
-                && tmp_files.iter().any(|tmp| paths_equivalent(&loc.file, tmp))
-            {
-                loc.file = orig.to_owned();
+/// Collects an [`AstIndex`] one top-level declaration at a time, so a
+/// declaration's subtree can be dropped once it has been walked.
+#[derive(Default)]
+pub(crate) struct IndexBuilder {
+    defs: Vec<SymbolDef>,
+    refs: Vec<RefSite>,
+    specializations: Vec<TemplateSpecialization>,
+}
+
+impl IndexBuilder {
+    /// Collect the declarations and references of a child of the
+    /// translation unit.
+    pub(crate) fn add_top_level(
+        &mut self,
+        node: &Node,
+    ) {
+        walk(node, true, &mut self.defs, &mut self.refs, &mut self.specializations);
+    }
+
+    /// Build the index.
+    ///
+    /// `tmp_files` are the possible paths of the temp file that was compiled.
+    /// `original_file` is the real document path — any definition whose file
+    /// matches one of `tmp_files` will be rewritten to `original_file`.
+    pub(crate) fn finish(
+        self,
+        tmp_files: &[String],
+        original_file: Option<&str>,
+    ) -> AstIndex {
+        let Self {
+            mut defs,
+            mut refs,
+            specializations,
+        } = self;
+
+        debug!("[build-index] collected {} defs, {} refs (original_file={:?})", defs.len(), refs.len(), original_file,);
+
+        if let Some(orig) = original_file {
+            let orig = SharedStr::new(orig);
+            for def in &mut defs {
+                if tmp_files.iter().any(|tmp| paths_equivalent(&def.file, tmp)) {
+                    def.file = orig.clone();
+                }
             }
-            if let Some(loc) = r.spelling.as_mut()
-                && tmp_files.iter().any(|tmp| paths_equivalent(&loc.file, tmp))
-            {
-                loc.file = orig.to_owned();
+            for r in &mut refs {
+                if tmp_files.iter().any(|tmp| paths_equivalent(&r.file, tmp)) {
+                    r.file = orig.clone();
+                }
+                if let Some(loc) = r.expansion.as_mut()
+                    && tmp_files.iter().any(|tmp| paths_equivalent(&loc.file, tmp))
+                {
+                    loc.file = orig.clone();
+                }
+                if let Some(loc) = r.spelling.as_mut()
+                    && tmp_files.iter().any(|tmp| paths_equivalent(&loc.file, tmp))
+                {
+                    loc.file = orig.clone();
+                }
             }
         }
-    }
 
-    let mut id_to_def = HashMap::with_capacity(defs.len());
-    let mut name_to_defs: HashMap<String, Vec<usize>> = HashMap::with_capacity(defs.len());
-    let mut target_id_to_refs: HashMap<String, Vec<usize>> = HashMap::new();
-    let mut file_to_defs: HashMap<String, Vec<usize>> = HashMap::new();
-    let mut file_to_refs: HashMap<String, Vec<usize>> = HashMap::new();
-
-    for (i, def) in defs.iter().enumerate() {
-        id_to_def
-            .entry(def.id.clone())
-            .and_modify(|existing_idx: &mut usize| {
-                let existing_def = &defs[*existing_idx];
-                if def.is_definition && !existing_def.is_definition {
-                    *existing_idx = i;
-                }
-            })
-            .or_insert(i);
-        name_to_defs.entry(def.name.clone()).or_default().push(i);
-        file_to_defs.entry(def.file.clone()).or_default().push(i);
+        AstIndex::from_parts(defs, refs, specializations)
     }
+}
 
-    for (i, ref_site) in refs.iter().enumerate() {
-        target_id_to_refs.entry(ref_site.target_id.clone()).or_default().push(i);
-        file_to_refs.entry(ref_site.file.clone()).or_default().push(i);
-    }
+/// Indices of the project headers a translation unit's dump covers, keyed by
+/// header path: each header's own declarations and references, plus the
+/// declarations those references resolve to in other files.
+pub(crate) fn header_indices(
+    index: &AstIndex,
+    source_file: &Path,
+) -> Vec<(PathBuf, AstIndex)> {
+    let source_file = source_file.display().to_string();
+    let headers: BTreeSet<&SharedStr> = index
+        .file_to_defs
+        .keys()
+        .chain(index.file_to_refs.keys())
+        .filter(|file| is_header_file(Path::new(file)) && !is_system_header(file) && !paths_match(file, &source_file))
+        .collect();
 
-    AstIndex {
-        defs,
-        refs,
-        id_to_def,
-        name_to_defs,
-        target_id_to_refs,
-        file_to_defs,
-        file_to_refs,
-    }
+    headers
+        .into_iter()
+        .map(|header| {
+            let refs: Vec<RefSite> = index.get_references_in_file(header).into_iter().cloned().collect();
+            let targets: HashSet<&str> = refs.iter().map(|r| r.target_id.as_str()).collect();
+            let defs: Vec<SymbolDef> = index
+                .defs
+                .iter()
+                .filter(|def| &def.file == header || targets.contains(def.id.as_str()))
+                .cloned()
+                .collect();
+            let ids: HashSet<&str> = defs.iter().map(|def| def.id.as_str()).collect();
+            let specializations =
+                index.specializations.iter().filter(|s| ids.contains(s.id.as_str())).cloned().collect();
+            (PathBuf::from(header), AstIndex::from_parts(defs, refs, specializations))
+        })
+        .collect()
 }
 
 /// Check if two file paths refer to the same file.
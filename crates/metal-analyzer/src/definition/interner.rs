@@ -0,0 +1,307 @@
+//! Shared strings for the AST indexes.
+//!
+//! Symbol names, kinds and file paths repeat across every index of a
+//! project: each translation unit that includes a header lists its symbols
+//! again. [`SharedStr`] keeps one allocation per distinct string in a
+//! process-wide pool, so copies cost a reference count, and equal strings
+//! compare by pointer first. Strings no index uses any more are dropped by
+//! [`purge`], or by [`purge_if_due`] where evictions may call it often.
+
+use std::{
+    borrow::Borrow,
+    collections::HashSet,
+    ffi::OsStr,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use dashmap::{DashMap, DashSet};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::vfs::path_key;
+
+static POOL: LazyLock<DashSet<Arc<str>>> = LazyLock::new(DashSet::new);
+
+/// Comparison keys of paths that exist, so comparing them again skips the
+/// filesystem. Paths that do not exist yet are resolved each time, as their
+/// key changes once they are created.
+static PATH_KEYS: LazyLock<DashMap<Arc<str>, Arc<PathBuf>>> = LazyLock::new(DashMap::new);
+
+/// The shortest time between two purges by [`purge_if_due`], as each walks
+/// the whole pool.
+const PURGE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// When the last purge ran. Held for the whole purge, so purges never walk
+/// the two maps at once.
+static LAST_PURGE: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// An immutable string shared through the intern pool.
+#[derive(Clone)]
+pub struct SharedStr(Arc<str>);
+
+impl SharedStr {
+    pub fn new(text: &str) -> Self {
+        if let Some(shared) = POOL.get(text) {
+            return Self(Arc::clone(&shared));
+        }
+        let shared: Arc<str> = Arc::from(text);
+        // Another thread may have inserted the same text meanwhile; keep the
+        // pooled copy so equal strings stay pointer-equal.
+        if POOL.insert(Arc::clone(&shared)) {
+            return Self(shared);
+        }
+        POOL.get(text).map_or(Self(shared), |pooled| Self(Arc::clone(&pooled)))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether both are the same pooled string, which for interned strings
+    /// means they are equal.
+    pub fn ptr_eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Drop the pooled strings no index holds any more, returning how many.
+pub fn purge() -> usize {
+    let mut last_purge = LAST_PURGE.lock().unwrap_or_else(PoisonError::into_inner);
+    *last_purge = Some(Instant::now());
+    purge_pool()
+}
+
+/// Like [`purge`], unless a purge ran in the last [`PURGE_INTERVAL`], in
+/// which case nothing is dropped and `0` is returned.
+pub fn purge_if_due() -> usize {
+    let mut last_purge = LAST_PURGE.lock().unwrap_or_else(PoisonError::into_inner);
+    if last_purge.is_some_and(|at| at.elapsed() < PURGE_INTERVAL) {
+        return 0;
+    }
+    *last_purge = Some(Instant::now());
+    purge_pool()
+}
+
+/// Walk the pool, then the path keys. Nothing locks the path keys while
+/// holding a pool lock, so the pool is walked against a snapshot of them.
+/// Callers hold [`LAST_PURGE`].
+fn purge_pool() -> usize {
+    // A path key holds a reference to its pooled string. Keys added after
+    // this snapshot only keep their strings a purge longer.
+    let path_keys: HashSet<*const u8> = PATH_KEYS.iter().map(|entry| Arc::as_ptr(entry.key()).cast::<u8>()).collect();
+    let before = POOL.len();
+    POOL.retain(|text| Arc::strong_count(text) > 1 + usize::from(path_keys.contains(&Arc::as_ptr(text).cast::<u8>())));
+    PATH_KEYS.retain(|text, _| POOL.contains(text));
+    before - POOL.len()
+}
+
+/// [`path_key`] of `path`, remembered for paths that exist.
+pub(crate) fn cached_path_key(path: &str) -> Arc<PathBuf> {
+    if let Some(key) = PATH_KEYS.get(path) {
+        return Arc::clone(&key);
+    }
+    let key = Arc::new(path_key(Path::new(path)));
+    if Path::new(path).exists() {
+        PATH_KEYS.insert(SharedStr::new(path).0, Arc::clone(&key));
+    }
+    key
+}
+
+impl Default for SharedStr {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl Deref for SharedStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for SharedStr {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<OsStr> for SharedStr {
+    fn as_ref(&self) -> &OsStr {
+        OsStr::new(&*self.0)
+    }
+}
+
+impl AsRef<Path> for SharedStr {
+    fn as_ref(&self) -> &Path {
+        Path::new(&*self.0)
+    }
+}
+
+impl Borrow<str> for SharedStr {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for SharedStr {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        self.ptr_eq(other) || self.0 == other.0
+    }
+}
+
+impl Eq for SharedStr {}
+
+impl PartialEq<str> for SharedStr {
+    fn eq(
+        &self,
+        other: &str,
+    ) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for SharedStr {
+    fn eq(
+        &self,
+        other: &&str,
+    ) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for SharedStr {
+    fn eq(
+        &self,
+        other: &String,
+    ) -> bool {
+        &*self.0 == other.as_str()
+    }
+}
+
+impl PartialEq<SharedStr> for str {
+    fn eq(
+        &self,
+        other: &SharedStr,
+    ) -> bool {
+        self == &*other.0
+    }
+}
+
+impl PartialEq<SharedStr> for &str {
+    fn eq(
+        &self,
+        other: &SharedStr,
+    ) -> bool {
+        *self == &*other.0
+    }
+}
+
+impl PartialEq<SharedStr> for String {
+    fn eq(
+        &self,
+        other: &SharedStr,
+    ) -> bool {
+        self.as_str() == &*other.0
+    }
+}
+
+impl Hash for SharedStr {
+    fn hash<H: Hasher>(
+        &self,
+        state: &mut H,
+    ) {
+        self.0.hash(state);
+    }
+}
+
+impl PartialOrd for SharedStr {
+    fn partial_cmp(
+        &self,
+        other: &Self,
+    ) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SharedStr {
+    fn cmp(
+        &self,
+        other: &Self,
+    ) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl From<&str> for SharedStr {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+impl From<&String> for SharedStr {
+    fn from(text: &String) -> Self {
+        Self::new(text)
+    }
+}
+
+impl From<String> for SharedStr {
+    fn from(text: String) -> Self {
+        Self::new(&text)
+    }
+}
+
+impl From<SharedStr> for String {
+    fn from(text: SharedStr) -> Self {
+        text.0.to_string()
+    }
+}
+
+impl fmt::Debug for SharedStr {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for SharedStr {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for SharedStr {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for SharedStr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        Ok(Self::new(&text))
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/definition/interner_tests.rs"]
+mod tests;
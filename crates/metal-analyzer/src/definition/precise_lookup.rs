@@ -19,6 +19,7 @@ use crate::{
         helpers,
         kind::SyntaxKind,
     },
+    vfs::virtual_docs::document_path,
 };
 
 pub(super) fn resolve_local_template_parameter(
@@ -30,7 +31,7 @@ pub(super) fn resolve_local_template_parameter(
 ) -> Option<NavigationTarget> {
     let root = snapshot.root();
     let node = helpers::node_at_position(&root, source, position)?;
-    let file_path = uri.to_file_path().ok()?;
+    let file_path = document_path(uri)?;
 
     for declaration_node in node.ancestors().filter(|ancestor| {
         matches!(ancestor.kind(), SyntaxKind::FunctionDef | SyntaxKind::StructDef | SyntaxKind::ClassDef)
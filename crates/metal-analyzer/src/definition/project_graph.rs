@@ -64,6 +64,39 @@ impl ProjectGraph {
         self.owner_to_includes.insert(owner_id, new_includes);
     }
 
+    /// Files `seed` includes, directly or through other includes, nearest
+    /// first and without `seed` itself.
+    pub(super) fn included_files(
+        &self,
+        seed: &FileId,
+        max_depth: usize,
+        max_nodes: usize,
+    ) -> Vec<FileId> {
+        let mut visited: HashSet<FileId> = HashSet::from([seed.clone()]);
+        let mut queue: VecDeque<(FileId, usize)> = VecDeque::from([(seed.clone(), 0)]);
+        let mut included = Vec::new();
+
+        while let Some((current, depth)) = queue.pop_front() {
+            if depth >= max_depth {
+                continue;
+            }
+            let Some(includes) = self.owner_to_includes.get(&current) else {
+                continue;
+            };
+            let mut next: Vec<&FileId> = includes.iter().filter(|include| !visited.contains(*include)).collect();
+            next.sort();
+            for include in next {
+                if included.len() >= max_nodes {
+                    return included;
+                }
+                visited.insert(include.clone());
+                included.push(include.clone());
+                queue.push_back((include.clone(), depth + 1));
+            }
+        }
+        included
+    }
+
     pub(super) fn scoped_files(
         &self,
         seed: &FileId,
@@ -150,7 +183,9 @@ fn resolve_include_path(
 
     for include_dir in include_paths {
         if let Some(framework_root) = include_dir.strip_prefix(crate::metal::compiler::FRAMEWORK_DIR_PREFIX) {
-            if let Some(resolved) = crate::server::header_owners::resolve_framework_include(framework_root, include_path) {
+            if let Some(resolved) =
+                crate::server::header_owners::resolve_framework_include(framework_root, include_path)
+            {
                 return Some(normalized_path(&resolved));
             }
         } else {
@@ -1,14 +1,29 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet, hash_map::Entry},
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
 };
 
 use dashmap::DashMap;
+use tracing::debug;
 
 use crate::{
-    definition::{ast_index::AstIndex, ref_site::RefSite, symbol_def::SymbolDef, utils::is_system_header},
-    vfs::FileId,
+    definition::{
+        ast_index::AstIndex,
+        index_cache,
+        indexer::header_indices,
+        interner::SharedStr,
+        project_store::FileProvenance,
+        ref_site::RefSite,
+        symbol_def::SymbolDef,
+        utils::{is_system_header, paths_match},
+    },
+    document::ContentHash,
+    server::header_owners::is_header_file,
+    vfs::{CASE_INSENSITIVE_PATHS, FileId, fold_case, path_key},
 };
 
 /// Project-wide symbol index built from AST dumps of all `.metal` files
@@ -17,12 +32,83 @@ use crate::{
 /// Each file gets its own [`AstIndex`]; cross-file queries iterate over
 /// all of them. Since Clang node IDs are per-translation-unit, cross-file
 /// lookups use symbol *names* rather than IDs.
+///
+/// In a multi-root workspace each root is its own project: the `_from`
+/// lookups only see translation units under the innermost root containing
+/// the requesting file, plus those outside every root.
 pub struct ProjectIndex {
     files: DashMap<FileId, ProjectFileIndex>,
+    /// Indices of project headers split out of translation unit dumps, so a
+    /// header opened later gets navigation without its own AST dump. Kept
+    /// apart from `files`, whose entries already cover these symbols.
+    headers: DashMap<FileId, HeaderIndex>,
+    /// Workspace roots as [`path_key`]s, scoping the `_from` lookups.
+    roots: RwLock<Vec<PathBuf>>,
+    /// Bumped whenever `files` changes.
+    generation: AtomicU64,
+    /// [`LocalUses`] of `files` at a generation.
+    local_uses: Mutex<Option<(u64, Arc<LocalUses>)>>,
+    /// Ticks for [`ProjectFileIndex::last_used`].
+    clock: AtomicU64,
 }
 
-struct ProjectFileIndex {
+/// What the translation units use of the symbols only they can see, for
+/// [`ProjectIndex::unreferenced_file_local_definitions`].
+#[derive(Default)]
+struct LocalUses {
+    /// `(file, name)` of the definitions references resolve to.
+    referenced: HashSet<(SharedStr, SharedStr)>,
+    /// Names of references whose target was not indexed, e.g. implicit
+    /// template instantiations, which keep every definition so named.
+    unresolved: HashSet<SharedStr>,
+    /// `(translation unit, identifier)` of the identifiers in the types of
+    /// its declarations, which count as uses of its own structs.
+    type_words: HashSet<(FileId, String)>,
+}
+
+struct HeaderIndex {
     index: Arc<AstIndex>,
+    /// Hash of the header text when the translation unit was dumped.
+    hash: ContentHash,
+}
+
+struct ProjectFileIndex {
+    /// `None` once evicted under the memory budget, until `disk_cache`
+    /// reloads it for a query.
+    index: Option<Arc<AstIndex>>,
+    /// What the index was built from; `None` when it cannot be persisted.
+    provenance: Option<FileProvenance>,
+    /// Where the index is in the on-disk AST cache; `None` when it is not,
+    /// which keeps it in memory.
+    disk_cache: Option<DiskCacheKey>,
+    /// [`AstIndex::approximate_size`] of `index`.
+    size: usize,
+    last_used: AtomicU64,
+}
+
+/// The key of an index in the on-disk AST cache.
+#[derive(Debug, Clone)]
+pub(crate) struct DiskCacheKey {
+    pub(crate) path: PathBuf,
+    pub(crate) source_hash: String,
+    /// Include paths and flags the index was dumped with.
+    pub(crate) inputs: Vec<String>,
+}
+
+impl ProjectFileIndex {
+    /// The index, reloaded from the disk cache if it was evicted. A reloaded
+    /// index is not kept, so queries stay within the memory budget.
+    fn load(&self) -> Option<Arc<AstIndex>> {
+        if let Some(index) = &self.index {
+            return Some(Arc::clone(index));
+        }
+        let key = self.disk_cache.as_ref()?;
+        let index = index_cache::load(&key.path, &key.source_hash, &key.inputs);
+        if index.is_none() {
+            debug!("[project-index] evicted index of {} is gone from the disk cache", key.path.display());
+        }
+        index.map(Arc::new)
+    }
 }
 
 impl Default for ProjectIndex {
@@ -35,21 +121,153 @@ impl ProjectIndex {
     pub fn new() -> Self {
         Self {
             files: DashMap::new(),
+            headers: DashMap::new(),
+            roots: RwLock::new(Vec::new()),
+            generation: AtomicU64::new(0),
+            local_uses: Mutex::new(None),
+            clock: AtomicU64::new(0),
+        }
+    }
+
+    fn files_changed(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Scope the `_from` lookups to the project of each workspace root.
+    pub fn set_workspace_roots(
+        &self,
+        roots: &[PathBuf],
+    ) {
+        if let Ok(mut guard) = self.roots.write() {
+            *guard = roots.iter().map(|root| path_key(root)).collect();
+        }
+    }
+
+    /// A filter keeping the translation units visible from `from`: those
+    /// under its innermost workspace root and those outside every root.
+    /// Files outside every root see everything.
+    fn visible_from(
+        &self,
+        from: &Path,
+    ) -> impl Fn(&FileId) -> bool + use<> {
+        let roots = self.roots.read().map(|guard| guard.clone()).unwrap_or_default();
+        let scope = innermost_root(&roots, &path_key(from)).cloned();
+        move |file_id: &FileId| {
+            let Some(scope) = &scope else {
+                return true;
+            };
+            let path = Path::new(file_id.as_str());
+            let path = if CASE_INSENSITIVE_PATHS {
+                fold_case(path)
+            } else {
+                path.to_path_buf()
+            };
+            innermost_root(&roots, &path).is_none_or(|root| root == scope)
+        }
+    }
+
+    fn file_index(
+        &self,
+        index: Arc<AstIndex>,
+        provenance: Option<FileProvenance>,
+        disk_cache: Option<DiskCacheKey>,
+    ) -> ProjectFileIndex {
+        ProjectFileIndex {
+            size: index.approximate_size(),
+            index: Some(index),
+            provenance,
+            disk_cache,
+            last_used: AtomicU64::new(self.clock.fetch_add(1, Ordering::Relaxed)),
         }
     }
 
     pub fn update_file(
         &self,
         path: PathBuf,
-        index: AstIndex,
+        index: impl Into<Arc<AstIndex>>,
     ) {
+        let index = index.into();
+        self.update_headers(&path, &index);
         let file_id = FileId::from_path(&path);
-        self.files.insert(
-            file_id,
-            ProjectFileIndex {
-                index: Arc::new(index),
-            },
-        );
+        self.files.insert(file_id, self.file_index(index, None, None));
+        self.files_changed();
+    }
+
+    /// Like [`update_file`](Self::update_file), additionally recording the
+    /// source text and include paths the index was built from so it can be
+    /// persisted, and where it is in the disk cache so it can be evicted.
+    pub(crate) fn update_file_from_source(
+        &self,
+        path: PathBuf,
+        index: impl Into<Arc<AstIndex>>,
+        source: &str,
+        include_paths: &[String],
+        disk_cache: Option<DiskCacheKey>,
+    ) {
+        let index = index.into();
+        let provenance = FileProvenance::capture(&path, source, include_paths, &index);
+        self.update_headers(&path, &index);
+        self.files.insert(FileId::from_path(&path), self.file_index(index, provenance, disk_cache));
+        self.files_changed();
+    }
+
+    /// Record the project headers covered by `index`, the index of the
+    /// translation unit at `path`, against their current contents.
+    fn update_headers(
+        &self,
+        path: &Path,
+        index: &AstIndex,
+    ) {
+        for (header, header_index) in header_indices(index, path) {
+            let Ok(text) = std::fs::read_to_string(&header) else {
+                continue;
+            };
+            self.headers.insert(
+                FileId::from_path(&header),
+                HeaderIndex {
+                    index: Arc::new(header_index),
+                    hash: ContentHash::of(&text),
+                },
+            );
+        }
+    }
+
+    /// The index of header `path` split out of a translation unit's dump,
+    /// if the header still has the contents it was dumped with.
+    pub fn translation_unit_header_index(
+        &self,
+        path: &Path,
+        hash: ContentHash,
+    ) -> Option<Arc<AstIndex>> {
+        self.headers
+            .get(&FileId::from_path(path))
+            .filter(|entry| entry.hash == hash)
+            .map(|entry| Arc::clone(&entry.index))
+    }
+
+    /// Insert an index loaded from the on-disk snapshot unless the file was
+    /// already indexed in this session.
+    pub(crate) fn restore_file(
+        &self,
+        path: PathBuf,
+        index: AstIndex,
+        provenance: FileProvenance,
+    ) {
+        self.files
+            .entry(FileId::from_path(&path))
+            .or_insert_with(|| self.file_index(Arc::new(index), Some(provenance), None));
+        self.files_changed();
+    }
+
+    /// Indexes that can be persisted, with their provenance.
+    pub(crate) fn persistable_files(&self) -> Vec<(Arc<AstIndex>, FileProvenance)> {
+        self.files
+            .iter()
+            .filter_map(|entry| {
+                let provenance = entry.value().provenance.clone()?;
+                Some((entry.value().load()?, provenance))
+            })
+            .collect()
     }
 
     pub fn remove_file(
@@ -58,12 +276,89 @@ impl ProjectIndex {
     ) {
         let file_id = FileId::from_path(path);
         self.files.remove(&file_id);
+        self.headers.remove(&file_id);
+        self.files_changed();
+    }
+
+    /// Forget every file.
+    pub fn clear(&self) {
+        self.files.clear();
+        self.headers.clear();
+        self.files_changed();
+    }
+
+    /// Whether `path` has an index, from a scan, an open document or a
+    /// restored snapshot.
+    pub fn contains_file(
+        &self,
+        path: &Path,
+    ) -> bool {
+        self.files.contains_key(&FileId::from_path(path))
     }
 
     pub fn file_count(&self) -> usize {
         self.files.len()
     }
 
+    /// Approximate heap size of the translation unit indexes in memory in
+    /// bytes. The header indexes split out of them are not counted.
+    pub fn memory_usage(&self) -> usize {
+        self.files.iter().filter(|entry| entry.index.is_some()).map(|entry| entry.size).sum()
+    }
+
+    /// Whether `index` is the one in memory for `file_id`.
+    pub(crate) fn holds(
+        &self,
+        file_id: &FileId,
+        index: &Arc<AstIndex>,
+    ) -> bool {
+        self.files.get(file_id).is_some_and(|entry| entry.index.as_ref().is_some_and(|held| Arc::ptr_eq(held, index)))
+    }
+
+    /// Drop the least recently used indexes the disk cache can reload until
+    /// `bytes` are freed or none are left. Returns the bytes freed.
+    pub(crate) fn evict_reloadable(
+        &self,
+        bytes: usize,
+    ) -> usize {
+        let mut candidates: Vec<(u64, FileId)> = self
+            .files
+            .iter()
+            .filter(|entry| entry.index.is_some() && entry.disk_cache.is_some())
+            .map(|entry| (entry.last_used.load(Ordering::Relaxed), entry.key().clone()))
+            .collect();
+        candidates.sort_by_key(|(last_used, _)| *last_used);
+        let mut freed = 0;
+        for (_, file_id) in candidates {
+            if freed >= bytes {
+                break;
+            }
+            if let Some(mut entry) = self.files.get_mut(&file_id)
+                && entry.index.take().is_some()
+            {
+                freed += entry.size;
+            }
+        }
+        freed
+    }
+
+    /// The indexes of the files `keep` accepts, reloading evicted ones, and
+    /// marked as recently used.
+    fn indexes(
+        &self,
+        keep: impl Fn(&FileId) -> bool,
+    ) -> Vec<(FileId, Arc<AstIndex>)> {
+        let tick = self.clock.fetch_add(1, Ordering::Relaxed);
+        self.files
+            .iter()
+            .filter(|entry| keep(entry.key()))
+            .filter_map(|entry| {
+                entry.last_used.store(tick, Ordering::Relaxed);
+                Some((entry.key().clone(), entry.value().load()?))
+            })
+            .collect()
+    }
+
     /// Find definitions by name across all indexed files.
     ///
     /// Results are sorted: user files before system headers, definitions
@@ -71,12 +366,30 @@ impl ProjectIndex {
     pub fn find_definitions(
         &self,
         name: &str,
+    ) -> Vec<SymbolDef> {
+        self.definitions_named(name, |_| true)
+    }
+
+    /// Like [`find_definitions`](Self::find_definitions), limited to the
+    /// translation units visible from the file at `from`.
+    pub fn find_definitions_from(
+        &self,
+        name: &str,
+        from: &Path,
+    ) -> Vec<SymbolDef> {
+        self.definitions_named(name, self.visible_from(from))
+    }
+
+    fn definitions_named(
+        &self,
+        name: &str,
+        keep: impl Fn(&FileId) -> bool,
     ) -> Vec<SymbolDef> {
         let mut results = Vec::new();
-        for entry in self.files.iter() {
-            if let Some(indices) = entry.value().index.name_to_defs.get(name) {
+        for (_, index) in self.indexes(keep) {
+            if let Some(indices) = index.name_to_defs.get(name) {
                 for &i in indices {
-                    let def = &entry.value().index.defs[i];
+                    let def = &index.defs[i];
                     if !def.file.is_empty() && def.line > 0 {
                         results.push(def.clone());
                     }
@@ -91,42 +404,129 @@ impl ProjectIndex {
         results
     }
 
-    /// Find definitions by name in a scoped subset of files.
-    pub fn find_definitions_in_files(
+    /// Every definition across all indexed files, ordered by location.
+    ///
+    /// Headers shared between translation units contribute their definitions
+    /// once. System header definitions are skipped unless `include_system`.
+    pub fn all_definitions(
         &self,
-        name: &str,
-        file_scope: &HashSet<FileId>,
+        include_system: bool,
     ) -> Vec<SymbolDef> {
+        let mut seen = HashSet::new();
         let mut results = Vec::new();
-        for entry in self.files.iter() {
-            if !file_scope.contains(entry.key()) {
-                continue;
+        for (_, index) in self.indexes(|_| true) {
+            for def in &index.defs {
+                if def.file.is_empty() || def.line == 0 || (!include_system && is_system_header(&def.file)) {
+                    continue;
+                }
+                if seen.insert((def.file.clone(), def.line, def.col, def.name.clone(), def.kind.clone())) {
+                    results.push(def.clone());
+                }
             }
-            if let Some(indices) = entry.value().index.name_to_defs.get(name) {
-                for &i in indices {
-                    let def = &entry.value().index.defs[i];
-                    if !def.file.is_empty() && def.line > 0 {
-                        results.push(def.clone());
-                    }
+        }
+        results.sort_by(|a, b| (&a.file, a.line, a.col, &a.name).cmp(&(&b.file, b.line, b.col, &b.name)));
+        results
+    }
+
+    /// The canonical project header declaring each name of `kinds`, as the
+    /// declaration there: the header holding a definition before one with
+    /// only a forward declaration, then the first path in order. Only
+    /// project headers count, since `.metal` files and SDK headers are not
+    /// included from the workspace.
+    pub fn includable_declarations(
+        &self,
+        kinds: &[&str],
+    ) -> Vec<SymbolDef> {
+        self.includable_declarations_in(kinds, |_| true)
+    }
+
+    /// Like [`includable_declarations`](Self::includable_declarations),
+    /// limited to the translation units visible from the file at `from`.
+    pub fn includable_declarations_from(
+        &self,
+        kinds: &[&str],
+        from: &Path,
+    ) -> Vec<SymbolDef> {
+        self.includable_declarations_in(kinds, self.visible_from(from))
+    }
+
+    fn includable_declarations_in(
+        &self,
+        kinds: &[&str],
+        keep: impl Fn(&FileId) -> bool,
+    ) -> Vec<SymbolDef> {
+        let mut by_name: HashMap<SharedStr, SymbolDef> = HashMap::new();
+        for (_, index) in self.indexes(keep) {
+            for def in &index.defs {
+                if def.name.is_empty()
+                    || def.line == 0
+                    || !kinds.contains(&def.kind.as_str())
+                    || is_system_header(&def.file)
+                    || !is_header_file(Path::new(def.file.as_str()))
+                {
+                    continue;
+                }
+                let canonical = |def: &SymbolDef| (!def.is_definition, def.file.clone(), def.line);
+                match by_name.entry(def.name.clone()) {
+                    Entry::Occupied(mut current) => {
+                        if canonical(def) < canonical(current.get()) {
+                            current.insert(def.clone());
+                        }
+                    },
+                    Entry::Vacant(slot) => {
+                        slot.insert(def.clone());
+                    },
                 }
             }
         }
-        results.sort_by(|a, b| {
-            let a_sys = is_system_header(&a.file);
-            let b_sys = is_system_header(&b.file);
-            a_sys.cmp(&b_sys).then_with(|| b.is_definition.cmp(&a.is_definition))
-        });
+        let mut results: Vec<SymbolDef> = by_name.into_values().collect();
+        results.sort_by(|a, b| a.name.cmp(&b.name));
         results
     }
 
+    /// Names of the definitions across all indexed files.
+    pub fn definition_names(&self) -> HashSet<String> {
+        self.indexes(|_| true)
+            .into_iter()
+            .flat_map(|(_, index)| index.name_to_defs.keys().map(SharedStr::to_string).collect::<Vec<_>>())
+            .collect()
+    }
+
+    /// Find definitions by name in a scoped subset of files.
+    pub fn find_definitions_in_files(
+        &self,
+        name: &str,
+        file_scope: &HashSet<FileId>,
+    ) -> Vec<SymbolDef> {
+        self.definitions_named(name, |file_id| file_scope.contains(file_id))
+    }
+
     /// Find all reference sites whose target name matches, across all files.
     pub fn find_references_by_name(
         &self,
         name: &str,
+    ) -> Vec<RefSite> {
+        self.references_named(name, |_| true)
+    }
+
+    /// Like [`find_references_by_name`](Self::find_references_by_name),
+    /// limited to the translation units visible from the file at `from`.
+    pub fn find_references_by_name_from(
+        &self,
+        name: &str,
+        from: &Path,
+    ) -> Vec<RefSite> {
+        self.references_named(name, self.visible_from(from))
+    }
+
+    fn references_named(
+        &self,
+        name: &str,
+        keep: impl Fn(&FileId) -> bool,
     ) -> Vec<RefSite> {
         let mut results = Vec::new();
-        for entry in self.files.iter() {
-            for r in &entry.value().index.refs {
+        for (_, index) in self.indexes(keep) {
+            for r in &index.refs {
                 if r.target_name == name && !r.file.is_empty() && r.line > 0 {
                     results.push(r.clone());
                 }
@@ -134,4 +534,152 @@ impl ProjectIndex {
         }
         results
     }
+
+    /// Reference sites that target functions or methods, grouped by the file containing them.
+    ///
+    /// Headers shared between translation units may contribute the same site
+    /// more than once.
+    pub fn function_references_by_file(&self) -> HashMap<String, Vec<RefSite>> {
+        let mut results: HashMap<String, Vec<RefSite>> = HashMap::new();
+        for (_, index) in self.indexes(|_| true) {
+            for r in &index.refs {
+                if matches!(r.target_kind.as_str(), "FunctionDecl" | "CXXMethodDecl")
+                    && !r.file.is_empty()
+                    && r.line > 0
+                {
+                    results.entry(r.file.to_string()).or_default().push(r.clone());
+                }
+            }
+        }
+        results
+    }
+
+    /// Distinct call sites of functions or methods named `name`, across all
+    /// files. Headers shared between translation units count once.
+    pub fn function_reference_count(
+        &self,
+        name: &str,
+    ) -> usize {
+        let mut sites = HashSet::new();
+        for (_, index) in self.indexes(|_| true) {
+            for r in &index.refs {
+                if r.target_name == name
+                    && matches!(r.target_kind.as_str(), "FunctionDecl" | "CXXMethodDecl")
+                    && !r.file.is_empty()
+                    && r.line > 0
+                {
+                    sites.insert((r.file.clone(), r.line, r.col));
+                }
+            }
+        }
+        sites.len()
+    }
+
+    /// Distinct variables, parameters and fields declared with the type
+    /// `name`, ignoring qualifiers, pointers and namespaces, across all files.
+    pub fn type_use_count(
+        &self,
+        name: &str,
+    ) -> usize {
+        let mut sites = HashSet::new();
+        for (_, index) in self.indexes(|_| true) {
+            for def in &index.defs {
+                let Some(type_name) = def.type_name.as_deref() else {
+                    continue;
+                };
+                if type_name.rsplit("::").next() == Some(name) && !def.file.is_empty() && def.line > 0 {
+                    sites.insert((def.file.clone(), def.line, def.col));
+                }
+            }
+        }
+        sites.len()
+    }
+
+    /// Definitions in `file` only their own translation unit can see that
+    /// none of the indexed translation units uses, ordered by location:
+    /// `static` functions and constants, and structs defined in a main file.
+    ///
+    /// References are matched to the definition they resolve to in their
+    /// own translation unit. A header defining a `static` function in many
+    /// translation units contributes it once, used if any of them uses it.
+    /// A struct is used when a declaration of its translation unit names it
+    /// in its type.
+    pub fn unreferenced_file_local_definitions(
+        &self,
+        file: &str,
+    ) -> Vec<SymbolDef> {
+        let uses = self.local_uses();
+        let mut seen = HashSet::new();
+        let mut unused = Vec::new();
+        for (file_id, index) in self.indexes(|_| true) {
+            let main_file = paths_match(file_id.as_str(), file);
+            let in_file = index.file_to_defs.iter().filter(|(def_file, _)| paths_match(def_file, file));
+            for def in in_file.flat_map(|(_, defs)| defs.iter().map(|&i| &index.defs[i])) {
+                let local_record = main_file && def.kind == "CXXRecordDecl";
+                if !def.is_definition || !(def.file_local || local_record) || uses.unresolved.contains(&def.name) {
+                    continue;
+                }
+                if uses.referenced.contains(&(def.file.clone(), def.name.clone()))
+                    || (local_record && uses.type_words.contains(&(file_id.clone(), def.name.to_string())))
+                {
+                    continue;
+                }
+                if seen.insert((def.file.clone(), def.name.clone(), def.line)) {
+                    unused.push(def.clone());
+                }
+            }
+        }
+        unused.sort_by_key(|def| (def.line, def.col));
+        unused
+    }
+
+    /// The [`LocalUses`] of the current files, computed once per generation.
+    fn local_uses(&self) -> Arc<LocalUses> {
+        let generation = self.generation.load(Ordering::Relaxed);
+        if let Ok(guard) = self.local_uses.lock()
+            && let Some((cached_generation, uses)) = guard.as_ref()
+            && *cached_generation == generation
+        {
+            return Arc::clone(uses);
+        }
+
+        let mut uses = LocalUses::default();
+        for (file_id, index) in self.indexes(|_| true) {
+            for r in &index.refs {
+                match index.id_to_def.get(&r.target_id).map(|&i| &index.defs[i]) {
+                    Some(def) => {
+                        uses.referenced.insert((def.file.clone(), def.name.clone()));
+                    },
+                    None => {
+                        uses.unresolved.insert(r.target_name.clone());
+                    },
+                }
+            }
+            for def in &index.defs {
+                let types = def.type_name.as_deref().into_iter().chain(def.qual_type.as_deref());
+                for word in types.flat_map(|ty| ty.split(|ch: char| !ch.is_ascii_alphanumeric() && ch != '_')) {
+                    if !word.is_empty() {
+                        uses.type_words.insert((file_id.clone(), word.to_string()));
+                    }
+                }
+            }
+        }
+        let uses = Arc::new(uses);
+        if let Ok(mut guard) = self.local_uses.lock() {
+            *guard = Some((generation, Arc::clone(&uses)));
+        }
+        uses
+    }
 }
+
+/// The innermost of `roots` containing `path`, both as [`path_key`]s.
+fn innermost_root<'a>(
+    roots: &'a [PathBuf],
+    path: &Path,
+) -> Option<&'a PathBuf> {
+    roots.iter().filter(|root| path.starts_with(root)).max_by_key(|root| root.components().count())
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/definition/project_index_tests.rs"]
+mod tests;
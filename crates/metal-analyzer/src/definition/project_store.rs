@@ -0,0 +1,325 @@
+//! On-disk snapshot of the [`ProjectIndex`] for warm startup.
+//!
+//! The per-file cache in `index_cache` still needs every source to be read
+//! and hashed before cross-file navigation works. This module instead saves
+//! the whole project index for a set of workspace roots in one file, so a
+//! new session can restore it before any AST dump runs.
+//!
+//! Each entry carries stamps of the source and of every workspace header its
+//! AST covered. An entry is restored only while all of its stamps still match
+//! the files on disk: by size and modification time, or by content hash when
+//! only the modification time changed (for example after a `git checkout`).
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{Arc, Once},
+    time::UNIX_EPOCH,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::{
+    definition::{
+        AstIndex, ProjectIndex,
+        index_cache::{include_paths_hash, remove_legacy_entries, stable_hash_hex},
+        utils::is_system_header,
+    },
+    vfs::{normalized_path, path_key},
+};
+
+const SNAPSHOT_SCHEMA_VERSION: u32 = 4;
+
+static LEGACY_SWEEP: Once = Once::new();
+
+/// Content and modification stamp of one file an index was built from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FileStamp {
+    path: String,
+    len: u64,
+    /// `None` when the indexed text was an unsaved buffer, so the stamp can
+    /// only be validated by content.
+    modified_ns: Option<u64>,
+    hash: String,
+}
+
+impl FileStamp {
+    /// Stamp `path` as it currently is on disk.
+    fn capture(path: &Path) -> Option<Self> {
+        let content = std::fs::read(path).ok()?;
+        Some(Self {
+            path: normalized_path(path).display().to_string(),
+            len: content.len() as u64,
+            modified_ns: modified_ns(path),
+            hash: stable_hash_hex(&String::from_utf8_lossy(&content)),
+        })
+    }
+
+    /// Stamp `path` from `content`, just read from disk.
+    pub(crate) fn of_content(
+        path: &Path,
+        content: &str,
+    ) -> Self {
+        Self {
+            path: normalized_path(path).display().to_string(),
+            len: content.len() as u64,
+            modified_ns: modified_ns(path),
+            hash: stable_hash_hex(content),
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        Path::new(&self.path)
+    }
+
+    /// Stamp `path` for an index built from `indexed_source`, which may be
+    /// an editor buffer that differs from the file on disk.
+    fn capture_indexed(
+        path: &Path,
+        indexed_source: &str,
+    ) -> Option<Self> {
+        let mut stamp = Self::capture(path)?;
+        let indexed_hash = stable_hash_hex(indexed_source);
+        if stamp.hash != indexed_hash {
+            stamp.len = indexed_source.len() as u64;
+            stamp.modified_ns = None;
+            stamp.hash = indexed_hash;
+        }
+        Some(stamp)
+    }
+
+    /// Whether the file on disk still matches this stamp.
+    pub(crate) fn is_current(&self) -> bool {
+        let path = Path::new(&self.path);
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return false;
+        };
+        if metadata.len() != self.len {
+            return false;
+        }
+        if self.modified_ns.is_some() && self.modified_ns == modified_ns(path) {
+            return true;
+        }
+        std::fs::read(path).is_ok_and(|content| stable_hash_hex(&String::from_utf8_lossy(&content)) == self.hash)
+    }
+}
+
+fn modified_ns(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+    modified.duration_since(UNIX_EPOCH).ok().map(|elapsed| elapsed.as_nanos() as u64)
+}
+
+/// What an index was built from: the include paths and the stamps of the
+/// source followed by the workspace headers its AST covered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct FileProvenance {
+    include_hash: String,
+    stamps: Vec<FileStamp>,
+}
+
+impl FileProvenance {
+    /// Record the provenance of `index`, built for `path` from
+    /// `indexed_source` with `include_paths`.
+    ///
+    /// Returns `None` when `path` cannot be read, which keeps the file out
+    /// of the snapshot.
+    pub(crate) fn capture(
+        path: &Path,
+        indexed_source: &str,
+        include_paths: &[String],
+        index: &AstIndex,
+    ) -> Option<Self> {
+        let primary = FileStamp::capture_indexed(path, indexed_source)?;
+        let mut seen = HashSet::from([primary.path.clone()]);
+        let mut stamps = vec![primary];
+        for file in index.file_to_defs.keys().chain(index.file_to_refs.keys()) {
+            if file.is_empty() || is_system_header(file) {
+                continue;
+            }
+            let normalized = normalized_path(Path::new(file)).display().to_string();
+            if seen.insert(normalized.clone())
+                && let Some(stamp) = FileStamp::capture(Path::new(&normalized))
+            {
+                stamps.push(stamp);
+            }
+        }
+        Some(Self {
+            include_hash: include_paths_hash(include_paths),
+            stamps,
+        })
+    }
+
+    fn source_path(&self) -> &Path {
+        Path::new(&self.stamps[0].path)
+    }
+
+    fn is_current(
+        &self,
+        include_paths: &[String],
+    ) -> bool {
+        self.include_hash == include_paths_hash(include_paths) && self.stamps.iter().all(FileStamp::is_current)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectSnapshot {
+    schema_version: u32,
+    workspace_roots: Vec<String>,
+    files: Vec<SnapshotFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SnapshotFile {
+    provenance: FileProvenance,
+    index: AstIndex,
+}
+
+/// Borrowed form of [`ProjectSnapshot`], so saving does not copy every index.
+#[derive(Serialize)]
+struct ProjectSnapshotRef<'a> {
+    schema_version: u32,
+    workspace_roots: Vec<String>,
+    files: Vec<SnapshotFileRef<'a>>,
+}
+
+#[derive(Serialize)]
+struct SnapshotFileRef<'a> {
+    provenance: &'a FileProvenance,
+    index: &'a AstIndex,
+}
+
+/// Write every file in `project_index` with a known provenance to the
+/// snapshot for `workspace_roots`, returning how many files were saved.
+pub(crate) fn save(
+    project_index: &ProjectIndex,
+    workspace_roots: &[PathBuf],
+) -> std::io::Result<usize> {
+    LEGACY_SWEEP.call_once(|| remove_legacy_entries(&default_snapshot_dir()));
+    save_to(&snapshot_path(workspace_roots), project_index, workspace_roots)
+}
+
+/// Restore the entries of `files` whose stamps are still current into
+/// `project_index`, returning the restored paths as given in `files`.
+pub(crate) fn restore(
+    project_index: &ProjectIndex,
+    workspace_roots: &[PathBuf],
+    files: &[PathBuf],
+    include_paths_for: impl Fn(&Path) -> Vec<String>,
+) -> Vec<PathBuf> {
+    LEGACY_SWEEP.call_once(|| remove_legacy_entries(&default_snapshot_dir()));
+    restore_from(&snapshot_path(workspace_roots), project_index, workspace_roots, files, include_paths_for)
+}
+
+fn save_to(
+    snapshot_file: &Path,
+    project_index: &ProjectIndex,
+    workspace_roots: &[PathBuf],
+) -> std::io::Result<usize> {
+    let entries: Vec<(Arc<AstIndex>, FileProvenance)> = project_index.persistable_files();
+    let snapshot = ProjectSnapshotRef {
+        schema_version: SNAPSHOT_SCHEMA_VERSION,
+        workspace_roots: root_keys(workspace_roots),
+        files: entries
+            .iter()
+            .map(|(index, provenance)| SnapshotFileRef {
+                provenance,
+                index,
+            })
+            .collect(),
+    };
+
+    let json = serde_json::to_vec(&snapshot).map_err(std::io::Error::other)?;
+    write_atomically(snapshot_file, &json)?;
+    debug!("[project-store] saved {} file(s) to {}", snapshot.files.len(), snapshot_file.display());
+    Ok(snapshot.files.len())
+}
+
+fn restore_from(
+    snapshot_file: &Path,
+    project_index: &ProjectIndex,
+    workspace_roots: &[PathBuf],
+    files: &[PathBuf],
+    include_paths_for: impl Fn(&Path) -> Vec<String>,
+) -> Vec<PathBuf> {
+    let Ok(content) = std::fs::read(snapshot_file) else {
+        return Vec::new();
+    };
+    let snapshot = match serde_json::from_slice::<ProjectSnapshot>(&content) {
+        Ok(snapshot) => snapshot,
+        Err(error) => {
+            warn!("[project-store] ignoring unreadable snapshot {}: {error}", snapshot_file.display());
+            return Vec::new();
+        },
+    };
+    if snapshot.schema_version != SNAPSHOT_SCHEMA_VERSION || snapshot.workspace_roots != root_keys(workspace_roots) {
+        return Vec::new();
+    }
+
+    let wanted: HashMap<PathBuf, &PathBuf> = files.iter().map(|path| (path_key(path), path)).collect();
+    let mut restored = Vec::new();
+    for file in snapshot.files {
+        let Some(&path) = wanted.get(&path_key(file.provenance.source_path())) else {
+            continue;
+        };
+        if !file.provenance.is_current(&include_paths_for(path)) {
+            continue;
+        }
+        project_index.restore_file(path.clone(), file.index, file.provenance);
+        restored.push(path.clone());
+    }
+    debug!("[project-store] restored {} file(s) from {}", restored.len(), snapshot_file.display());
+    restored
+}
+
+/// Delete the persisted project index of `workspace_roots`, returning
+/// whether there was one.
+pub(crate) fn remove(workspace_roots: &[PathBuf]) -> bool {
+    std::fs::remove_file(snapshot_path(workspace_roots)).is_ok()
+}
+
+/// Write `content` through a temporary file so a crash never leaves a
+/// truncated snapshot.
+pub(crate) fn write_atomically(
+    file: &Path,
+    content: &[u8],
+) -> std::io::Result<()> {
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let temp_file = file.with_extension(format!("json.{}.tmp", std::process::id()));
+    std::fs::write(&temp_file, content)?;
+    std::fs::rename(&temp_file, file)
+}
+
+pub(crate) fn root_keys(workspace_roots: &[PathBuf]) -> Vec<String> {
+    let mut keys: Vec<String> =
+        workspace_roots.iter().map(|root| normalized_path(root).display().to_string()).collect();
+    keys.sort();
+    keys
+}
+
+fn snapshot_path(workspace_roots: &[PathBuf]) -> PathBuf {
+    sibling_snapshot_path(workspace_roots, "json")
+}
+
+/// Path of a snapshot kept next to the project index of `workspace_roots`,
+/// named by the same key with `extension`.
+pub(crate) fn sibling_snapshot_path(
+    workspace_roots: &[PathBuf],
+    extension: &str,
+) -> PathBuf {
+    let key = stable_hash_hex(&root_keys(workspace_roots).join("\n"));
+    default_snapshot_dir().join(format!("{key}.{extension}"))
+}
+
+fn default_snapshot_dir() -> PathBuf {
+    if let Some(home) = std::env::var_os("HOME") {
+        return PathBuf::from(home).join(".metal-analyzer").join("project-index");
+    }
+    std::env::temp_dir().join("metal-analyzer-project-index")
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/definition/project_store_tests.rs"]
+mod tests;
@@ -1,13 +1,17 @@
 //! Definition provider implementation.
 
-use std::sync::{
-    Arc,
-    atomic::{AtomicUsize, Ordering},
+use std::{
+    collections::HashSet,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+    time::Duration,
 };
 
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
 use tower_lsp::lsp_types::*;
-use tracing::{debug, warn};
+use tracing::{debug, info, warn};
 
 #[cfg(test)]
 use crate::definition::precise_lookup::matches_position;
@@ -17,41 +21,84 @@ use crate::definition::system_lookup::{
     system_builtin_header_candidates,
 };
 use crate::{
+    config::DEFAULT_AST_DUMP_TIMEOUT_MS,
     definition::{
         ast_index::AstIndex,
-        clang_nodes::Node,
-        compiler::run_ast_dump,
-        fallback_lookup::{ref_site_to_location, resolve_by_name, resolve_from_project_index},
-        index_cache,
-        indexer::build_index,
-        perf::GotoDefPerf,
+        ast_stream::stream_index,
+        compiler::{AstDumpError, run_ast_dump},
+        fallback_lookup::{
+            ref_site_to_location, resolve_by_name, resolve_from_project_index, resolve_specialization_member,
+        },
+        index_cache, interner,
         precise_lookup::{resolve_local_template_parameter, resolve_precise, resolve_precise_def},
         project_graph::ProjectGraph,
-        project_index::ProjectIndex,
+        project_index::{DiskCacheKey, ProjectIndex},
+        project_store,
+        stdlib_map::StdlibMap,
         symbol_def::SymbolDef,
-        symbol_text::line_chars_and_cursor,
+        symbol_rank::{RankFactors, RankedCandidate, RankingWeights},
+        symbol_text::{extract_member_receiver_identifier, line_chars_and_cursor},
         system_lookup::{resolve_fast_system_symbol_location, resolve_system_header_symbol_location},
+        text_scan,
         utils::{def_to_location, is_system_header, paths_match},
     },
+    document::{AnalysisSnapshot, ContentHash, Document},
     ide::navigation::{IdeLocation, IdePosition, IdeRange, NavigationTarget},
-    metal::builtins::{BuiltinKind, lookup as lookup_builtin},
+    metal::{
+        builtins::{BuiltinKind, lookup as lookup_builtin},
+        compiler_overrides::{self, CompilerOverride},
+    },
+    perf,
     syntax::{SyntaxTree, helpers},
-    text_pos::utf16_column_of_byte_offset,
-    vfs::FileId,
+    text_pos::{char_width, column_of_byte_offset},
+    vfs::{FileId, virtual_docs::document_path},
 };
 
+/// Included headers a single lookup may index before giving up.
+const MAX_ON_DEMAND_HEADERS: usize = 8;
+
 /// Provides go-to-definition by querying the Metal compiler's AST.
 ///
 /// Maintains a per-document cache of parsed AST indices so that repeated
 /// jumps within the same file are instant.
 pub struct DefinitionProvider {
-    cache: DashMap<FileId, (String, Arc<AstIndex>)>,
+    cache: DashMap<FileId, CachedIndex>,
+    /// Ticks for [`CachedIndex::last_used`].
+    cache_clock: AtomicU64,
+    /// `indexing.maxMemoryMb` in bytes; `0` means no budget.
+    memory_budget_bytes: AtomicU64,
     build_locks: DashMap<FileId, Arc<std::sync::Mutex<()>>>,
+    /// Included headers and reference candidates indexed on demand by a
+    /// lookup, or whose dump failed; each is tried once until it changes on
+    /// disk.
+    on_demand_indexed: DashSet<FileId>,
     project_index: Arc<ProjectIndex>,
     project_graph: Arc<ProjectGraph>,
     project_graph_depth: AtomicUsize,
     project_graph_max_nodes: AtomicUsize,
-    goto_def_perf: GotoDefPerf,
+    ranking: RwLock<RankingWeights>,
+    ast_dump_timeout_ms: AtomicU64,
+    /// `compiler.extraFlags` with the platform define and language version,
+    /// applied to every AST dump.
+    compiler_flags: RwLock<Vec<String>>,
+    /// `compiler.overrides`, applied to the AST dumps of matching files.
+    compiler_overrides: RwLock<Vec<CompilerOverride>>,
+    /// Files whose AST dump timed out, with the content that timed out. They
+    /// are not dumped again until they change or the timeout does.
+    timed_out: DashMap<FileId, ContentHash>,
+    timeout_reported: AtomicBool,
+    /// Declarations of well-known `metal_stdlib` symbols in the SDK headers.
+    stdlib_map: StdlibMap,
+}
+
+/// An AST index kept in memory for lookups in its file.
+struct CachedIndex {
+    hash: ContentHash,
+    index: Arc<AstIndex>,
+    /// [`AstIndex::approximate_size`] of `index`.
+    size: usize,
+    /// Tick of the last lookup; the lowest is evicted first.
+    last_used: AtomicU64,
 }
 
 impl Default for DefinitionProvider {
@@ -64,12 +111,21 @@ impl DefinitionProvider {
     pub fn new() -> Self {
         Self {
             cache: DashMap::new(),
+            cache_clock: AtomicU64::new(0),
+            memory_budget_bytes: AtomicU64::new(0),
             build_locks: DashMap::new(),
+            on_demand_indexed: DashSet::new(),
             project_index: Arc::new(ProjectIndex::new()),
             project_graph: Arc::new(ProjectGraph::new()),
             project_graph_depth: AtomicUsize::new(3),
             project_graph_max_nodes: AtomicUsize::new(256),
-            goto_def_perf: GotoDefPerf::default(),
+            ranking: RwLock::new(RankingWeights::default()),
+            ast_dump_timeout_ms: AtomicU64::new(DEFAULT_AST_DUMP_TIMEOUT_MS),
+            compiler_flags: RwLock::new(Vec::new()),
+            compiler_overrides: RwLock::new(Vec::new()),
+            timed_out: DashMap::new(),
+            timeout_reported: AtomicBool::new(false),
+            stdlib_map: StdlibMap::default(),
         }
     }
 
@@ -77,10 +133,6 @@ impl DefinitionProvider {
         &self.project_index
     }
 
-    pub fn log_perf_summary(&self) {
-        self.goto_def_perf.log_summary();
-    }
-
     pub fn configure_project_graph_scope(
         &self,
         depth: usize,
@@ -90,10 +142,214 @@ impl DefinitionProvider {
         self.project_graph_max_nodes.store(max_nodes, Ordering::Relaxed);
     }
 
+    pub fn configure_ranking(
+        &self,
+        weights: RankingWeights,
+    ) {
+        if let Ok(mut guard) = self.ranking.write() {
+            *guard = weights;
+        }
+    }
+
+    /// Kill AST dumps running longer than `timeout`. Files that timed out
+    /// before are dumped again once it changes.
+    pub fn configure_ast_dump_timeout(
+        &self,
+        timeout: Duration,
+    ) {
+        let millis = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+        if self.ast_dump_timeout_ms.swap(millis, Ordering::Relaxed) != millis {
+            self.timed_out.clear();
+        }
+    }
+
+    /// Dump every file with `flags`, the defines and platform of the
+    /// diagnostics compile. In-memory indexes are dropped when the flags
+    /// change, so they are rebuilt with the new ones; the disk cache is keyed
+    /// on the flags.
+    pub fn configure_compiler_flags(
+        &self,
+        flags: Vec<String>,
+    ) {
+        let Ok(mut guard) = self.compiler_flags.write() else {
+            return;
+        };
+        if *guard != flags {
+            *guard = flags;
+            self.cache.clear();
+            self.timed_out.clear();
+        }
+    }
+
+    /// Dump files matching an override with its flags and include paths.
+    /// In-memory indexes are dropped when the overrides change, so they are
+    /// rebuilt with the new ones.
+    pub fn configure_compiler_overrides(
+        &self,
+        overrides: Vec<CompilerOverride>,
+    ) {
+        let Ok(mut guard) = self.compiler_overrides.write() else {
+            return;
+        };
+        if *guard != overrides {
+            *guard = overrides;
+            self.cache.clear();
+            self.timed_out.clear();
+        }
+    }
+
+    /// Keep the per-file AST cache and the project index under `max_mb`
+    /// megabytes, evicting the least recently used indexes; `0` lifts the
+    /// budget. The disk cache is kept, so evicted files reload from it.
+    pub fn configure_memory_budget(
+        &self,
+        max_mb: u64,
+    ) {
+        self.memory_budget_bytes.store(max_mb.saturating_mul(1024 * 1024), Ordering::Relaxed);
+        self.enforce_memory_budget();
+    }
+
+    /// Approximate bytes held by in-memory AST indexes: the per-file cache
+    /// and the project index.
+    pub fn memory_usage(&self) -> usize {
+        self.cache.iter().map(|entry| self.unshared_size(entry.key(), entry.value())).sum::<usize>()
+            + self.project_index.memory_usage()
+    }
+
+    /// The size of a cached index, or `0` when the project index holds the
+    /// same index and evicting it frees nothing.
+    fn unshared_size(
+        &self,
+        file_id: &FileId,
+        cached: &CachedIndex,
+    ) -> usize {
+        if self.project_index.holds(file_id, &cached.index) {
+            0
+        } else {
+            cached.size
+        }
+    }
+
+    /// The number of AST indexes in the on-disk cache and the bytes they
+    /// take.
+    pub fn disk_cache_usage(&self) -> (usize, u64) {
+        index_cache::disk_usage()
+    }
+
+    /// Evict cached per-file indexes, then the project index entries the
+    /// disk cache can reload, least recently used first, until they fit the
+    /// budget. A lookup reloads an evicted cached index from the disk cache,
+    /// and a cross-file query the evicted project entries it reads.
+    fn enforce_memory_budget(&self) {
+        let budget = self.memory_budget_bytes.load(Ordering::Relaxed) as usize;
+        if budget == 0 {
+            return;
+        }
+        let mut usage = self.memory_usage();
+        if usage <= budget {
+            return;
+        }
+        let before = usage;
+        let mut candidates: Vec<(u64, FileId, usize)> = self
+            .cache
+            .iter()
+            .map(|entry| {
+                (entry.last_used.load(Ordering::Relaxed), entry.key().clone(), self.unshared_size(entry.key(), &entry))
+            })
+            .collect();
+        candidates.sort_by_key(|(last_used, ..)| *last_used);
+        for (_, file_id, size) in candidates {
+            if usage <= budget {
+                break;
+            }
+            if self.cache.remove(&file_id).is_some() {
+                usage = usage.saturating_sub(size);
+            }
+        }
+        if usage > budget {
+            usage = usage.saturating_sub(self.project_index.evict_reloadable(usage - budget));
+        }
+        let purged = interner::purge_if_due();
+        debug!(
+            "[memory] evicted AST indexes from {} KB to {} KB (budget {} KB), purged {} interned strings",
+            before / 1024,
+            usage / 1024,
+            budget / 1024,
+            purged
+        );
+    }
+
+    /// The cached index of `file_id` if it was built from `hash`, marked as
+    /// recently used.
+    fn cached_index(
+        &self,
+        file_id: &FileId,
+        hash: ContentHash,
+    ) -> Option<Arc<AstIndex>> {
+        let entry = self.cache.get(file_id).filter(|entry| entry.hash == hash)?;
+        entry.last_used.store(self.cache_clock.fetch_add(1, Ordering::Relaxed), Ordering::Relaxed);
+        Some(Arc::clone(&entry.index))
+    }
+
+    fn insert_cached_index(
+        &self,
+        file_id: FileId,
+        hash: ContentHash,
+        index: Arc<AstIndex>,
+    ) {
+        self.cache.insert(
+            file_id,
+            CachedIndex {
+                hash,
+                size: index.approximate_size(),
+                index,
+                last_used: AtomicU64::new(self.cache_clock.fetch_add(1, Ordering::Relaxed)),
+            },
+        );
+        self.enforce_memory_budget();
+    }
+
+    /// `include_paths` and the AST dump flags for `path`: the compiler flags
+    /// followed by those of the matching overrides.
+    fn with_overrides(
+        &self,
+        path: Option<&std::path::Path>,
+        include_paths: &[String],
+    ) -> (Vec<String>, Vec<String>) {
+        let mut include_paths = include_paths.to_vec();
+        let mut flags = self.compiler_flags.read().map(|guard| guard.clone()).unwrap_or_default();
+        if let (Some(path), Ok(overrides)) = (path, self.compiler_overrides.read()) {
+            for entry in compiler_overrides::matching(&overrides, path) {
+                include_paths.extend(entry.include_paths.iter().cloned());
+                flags.extend(entry.flags.iter().cloned());
+            }
+        }
+        (include_paths, flags)
+    }
+
+    /// Whether an AST dump has timed out and this has not been reported yet.
+    /// Returns `true` at most once per session.
+    pub fn take_ast_dump_timeout(&self) -> bool {
+        !self.timed_out.is_empty() && !self.timeout_reported.swap(true, Ordering::Relaxed)
+    }
+
+    pub fn ranking_weights(&self) -> RankingWeights {
+        self.ranking.read().map(|guard| guard.clone()).unwrap_or_default()
+    }
+
     pub fn index_workspace_file(
         &self,
         path: &std::path::Path,
         include_paths: &[String],
+    ) -> bool {
+        self.index_file_unless_cancelled(path, include_paths, &|| false)
+    }
+
+    fn index_file_unless_cancelled(
+        &self,
+        path: &std::path::Path,
+        include_paths: &[String],
+        is_cancelled: &dyn Fn() -> bool,
     ) -> bool {
         let source = match std::fs::read_to_string(path) {
             Ok(s) => s,
@@ -103,16 +359,55 @@ impl DefinitionProvider {
             Ok(u) => u,
             Err(_) => return false,
         };
-        self.load_or_build_index(&uri, &source, include_paths, &|| false).is_some()
+        self.load_or_build_index(&uri, &source, ContentHash::of(&source), include_paths, is_cancelled).is_some()
+    }
+
+    /// Restore the persisted project index entries of `files` that are still
+    /// current, returning the restored paths. These need no AST dump for
+    /// cross-file navigation.
+    pub fn restore_project_snapshot(
+        &self,
+        workspace_roots: &[std::path::PathBuf],
+        files: &[std::path::PathBuf],
+        include_paths_for: impl Fn(&std::path::Path) -> Vec<String>,
+    ) -> Vec<std::path::PathBuf> {
+        let restored = project_store::restore(&self.project_index, workspace_roots, files, &include_paths_for);
+        self.enforce_memory_budget();
+        for path in &restored {
+            if let Ok(source) = std::fs::read_to_string(path) {
+                self.project_graph.update_file(path, &source, &include_paths_for(path));
+            }
+        }
+        restored
+    }
+
+    /// Persist the project index for `workspace_roots`, returning how many
+    /// files were written.
+    pub fn save_project_snapshot(
+        &self,
+        workspace_roots: &[std::path::PathBuf],
+    ) -> std::io::Result<usize> {
+        project_store::save(&self.project_index, workspace_roots)
+    }
+
+    /// Like `index_document`, for an open document, whose hash is maintained
+    /// incrementally.
+    pub fn index_open_document(
+        &self,
+        document: &Document,
+        include_paths: &[String],
+    ) {
+        self.index_document(&document.uri, &document.text, document.content_hash(), include_paths);
     }
 
     pub fn index_document(
         &self,
         uri: &Url,
         source: &str,
+        hash: ContentHash,
         include_paths: &[String],
     ) {
-        if let Some((_, load_source)) = self.load_or_build_index(uri, source, include_paths, &|| false) {
+        if let Some((_, load_source)) = self.load_or_build_index(uri, source, hash, include_paths, &|| false) {
             match load_source {
                 IndexLoadSource::Memory => {
                     debug!("Pre-indexing AST memory hit for {uri}");
@@ -120,6 +415,9 @@ impl DefinitionProvider {
                 IndexLoadSource::Disk => {
                     debug!("Pre-indexing AST cache hit for {uri}");
                 },
+                IndexLoadSource::TranslationUnit => {
+                    debug!("Pre-indexing AST taken from an including translation unit for {uri}");
+                },
                 IndexLoadSource::AstDump => {
                     debug!("Pre-indexing AST built via dump for {uri}");
                 },
@@ -136,12 +434,62 @@ impl DefinitionProvider {
         self.build_locks.remove(&file_id);
     }
 
+    /// Drop the in-memory and on-disk AST indexes of `path` so the next
+    /// lookup rebuilds it, e.g. after a header it includes changed.
+    pub fn invalidate_file(
+        &self,
+        path: &std::path::Path,
+    ) {
+        if let Ok(uri) = Url::from_file_path(path) {
+            self.evict(&uri);
+        }
+        self.on_demand_indexed.remove(&FileId::from_path(path));
+        self.timed_out.remove(&FileId::from_path(path));
+        index_cache::remove(path);
+    }
+
+    /// Drop the AST indexes kept in memory, e.g. after the include paths
+    /// changed, so each file is loaded or dumped again with the new ones when
+    /// next needed.
+    pub fn clear_memory_indexes(&self) {
+        self.cache.clear();
+        self.on_demand_indexed.clear();
+        self.timed_out.clear();
+    }
+
+    /// Drop every AST index, in memory and in the on-disk cache, along with
+    /// the persisted project index of `workspace_roots`, so each file is
+    /// dumped again when next needed. Returns how many cache files were
+    /// deleted.
+    pub fn clear_caches(
+        &self,
+        workspace_roots: &[std::path::PathBuf],
+    ) -> usize {
+        self.cache.clear();
+        self.build_locks.clear();
+        self.on_demand_indexed.clear();
+        self.timed_out.clear();
+        self.project_index.clear();
+        interner::purge();
+        index_cache::clear() + usize::from(project_store::remove(workspace_roots))
+    }
+
+    /// Forget a workspace file that was deleted from disk.
+    pub fn remove_workspace_file(
+        &self,
+        path: &std::path::Path,
+    ) {
+        self.invalidate_file(path);
+        self.project_index.remove_file(path);
+        interner::purge_if_due();
+    }
+
     pub fn get_cached_index(
         &self,
         uri: &Url,
     ) -> Option<Arc<AstIndex>> {
         let file_id = FileId::from_url(uri);
-        self.cache.get(&file_id).map(|entry| Arc::clone(&entry.1))
+        self.cache.get(&file_id).map(|entry| Arc::clone(&entry.index))
     }
 
     pub fn provide(
@@ -149,15 +497,21 @@ impl DefinitionProvider {
         uri: &Url,
         position: Position,
         source: &str,
+        hash: ContentHash,
         include_paths: &[String],
         snapshot: &SyntaxTree,
         is_cancelled: impl Fn() -> bool,
     ) -> Option<NavigationTarget> {
         let started = std::time::Instant::now();
-        let mut index_source: Option<&'static str> = None;
+        let mut index_source: Option<IndexLoadSource> = None;
         let result =
-            self.provide_inner(uri, position, source, include_paths, snapshot, &mut index_source, &is_cancelled);
-        self.goto_def_perf.record(started.elapsed(), index_source, result.is_some());
+            self.provide_inner(uri, position, source, hash, include_paths, snapshot, &mut index_source, &is_cancelled);
+        let tier = match (index_source, &result) {
+            (_, None) => "gotoDefinition.unresolved",
+            (Some(source), Some(_)) => source.latency_name(),
+            (None, Some(_)) => "gotoDefinition.fallback",
+        };
+        perf::record(tier, started.elapsed());
         result
     }
 
@@ -166,9 +520,10 @@ impl DefinitionProvider {
         uri: &Url,
         position: Position,
         source: &str,
+        hash: ContentHash,
         include_paths: &[String],
         snapshot: &SyntaxTree,
-        index_source: &mut Option<&'static str>,
+        index_source: &mut Option<IndexLoadSource>,
         is_cancelled: &dyn Fn() -> bool,
     ) -> Option<NavigationTarget> {
         let (include_info, word) = {
@@ -185,6 +540,9 @@ impl DefinitionProvider {
 
             let check_path = |p: std::path::PathBuf| -> Option<NavigationTarget> {
                 if p.exists() {
+                    // Canonical, so headers found through a virtual include
+                    // root open at their real path.
+                    let p = crate::vfs::normalized_path(&p);
                     return Some(NavigationTarget::Single(IdeLocation::new(p, IdeRange::default())));
                 }
                 None
@@ -193,7 +551,9 @@ impl DefinitionProvider {
             for dir in include_paths {
                 if let Some(framework_root) = dir.strip_prefix(crate::metal::compiler::FRAMEWORK_DIR_PREFIX) {
                     if is_system {
-                        if let Some(resolved) = crate::server::header_owners::resolve_framework_include(framework_root, &path) {
+                        if let Some(resolved) =
+                            crate::server::header_owners::resolve_framework_include(framework_root, &path)
+                        {
                             if let Some(loc) = check_path(resolved) {
                                 return Some(loc);
                             }
@@ -230,9 +590,10 @@ impl DefinitionProvider {
         }
         debug!("[goto-def] word={word} at {}:{}", position.line, position.character);
 
-        let source_path = uri.to_file_path().ok();
+        let source_path = document_path(uri);
         let source_file = source_path.as_ref().map(|path| path.display().to_string()).unwrap_or_default();
         let source_file_id = source_path.as_ref().map(|path| FileId::from_path(path));
+        let weights = self.ranking_weights();
 
         if let Some(result) = resolve_local_template_parameter(uri, snapshot, source, position, &word) {
             debug!("[goto-def] TIER-1 (local template param): hit");
@@ -240,14 +601,19 @@ impl DefinitionProvider {
         }
 
         // TIER-2: Fast system-header path for obvious Metal SDK symbols
+        let is_member = extract_member_receiver_identifier(source, position, &word).is_some();
+        if !is_member && let Some(result) = self.stdlib_map.lookup(&word, false, include_paths) {
+            debug!("[goto-def] TIER-2 (curated stdlib map): hit");
+            return Some(result);
+        }
         if let Some(result) = resolve_fast_system_symbol_location(source, position, &word, include_paths) {
             debug!("[goto-def] TIER-2 (system header fast path): hit");
             return Some(result);
         }
 
         // TIER-4: AST-based resolution (scope-aware via Clang)
-        if let Some((index, load_source)) = self.load_or_build_index(uri, source, include_paths, is_cancelled) {
-            *index_source = Some(load_source.as_str());
+        if let Some((index, load_source)) = self.load_or_build_index(uri, source, hash, include_paths, is_cancelled) {
+            *index_source = Some(load_source);
             debug!("[goto-def] AST index source: {}", load_source.as_str());
 
             if let Some(def) = resolve_precise(&index, &source_file, position, &word) {
@@ -256,8 +622,14 @@ impl DefinitionProvider {
             }
             debug!("[goto-def] TIER-4 (AST precise): miss for {word}, trying ranked fallback");
 
+            // TIER-4b: Member of the template specializations the receiver is instantiated with
+            if let Some(result) = resolve_specialization_member(&index, &source_file, source, position, &word) {
+                debug!("[goto-def] TIER-4b (template specialization member): hit");
+                return Some(result);
+            }
+
             // TIER-5: AST by-name fallback with ranking
-            if let Some(result) = resolve_by_name(&index, &source_file, source, position, &word) {
+            if let Some(result) = resolve_by_name(&index, &source_file, source, position, &word, &weights) {
                 debug!("[goto-def] TIER-5 (AST by-name): hit");
                 return Some(result);
             }
@@ -266,22 +638,43 @@ impl DefinitionProvider {
             debug!("[goto-def] AST index unavailable for {uri}; trying fallback tiers");
         }
 
+        if is_cancelled() {
+            debug!("[goto-def] cancelled before project index lookup");
+            return None;
+        }
+
         // TIER-6: Project-wide index: cross-file definition lookup by name
-        if let Some(result) = resolve_from_project_index(
-            &self.project_index,
-            &self.project_graph,
-            &source_file,
-            source_file_id.as_ref(),
-            self.project_graph_depth.load(Ordering::Relaxed),
-            self.project_graph_max_nodes.load(Ordering::Relaxed),
-            &word,
-            position,
-        ) {
+        let resolve_from_project = || {
+            resolve_from_project_index(
+                &self.project_index,
+                &self.project_graph,
+                &source_file,
+                source_file_id.as_ref(),
+                self.project_graph_depth.load(Ordering::Relaxed),
+                self.project_graph_max_nodes.load(Ordering::Relaxed),
+                &word,
+                position,
+                &weights,
+            )
+        };
+        if let Some(result) = resolve_from_project() {
             debug!("[goto-def] TIER-6 (project index): hit");
             return Some(result);
         }
+        // Headers the background scan has not reached yet.
+        if let Some(file_id) = source_file_id.as_ref()
+            && self.index_included_headers(file_id, include_paths, is_cancelled) > 0
+            && let Some(result) = resolve_from_project()
+        {
+            debug!("[goto-def] TIER-6 (project index after on-demand indexing): hit");
+            return Some(result);
+        }
 
         // TIER-7: Builtin/system fallback: map known Metal builtins to SDK headers
+        if let Some(result) = self.stdlib_map.lookup(&word, is_member, include_paths) {
+            debug!("[goto-def] TIER-7 (curated stdlib member): hit");
+            return Some(result);
+        }
         if let Some(result) = resolve_builtin_symbol_location(&word, include_paths) {
             debug!("[goto-def] TIER-7 (system builtin header): hit");
             return Some(result);
@@ -297,11 +690,64 @@ impl DefinitionProvider {
         None
     }
 
+    /// The symbol at `position` and every definition of that name, ordered
+    /// the way the ranked by-name fallbacks order them.
+    ///
+    /// Candidates come from the document's cached AST index and from the
+    /// project index; no AST dump is started.
+    pub(crate) fn explain_ranking(
+        &self,
+        uri: &Url,
+        position: Position,
+        source: &str,
+        snapshot: &SyntaxTree,
+    ) -> Option<(String, Vec<RankedCandidate>)> {
+        let word = helpers::navigation_word_at_position(&snapshot.root(), source, position)?;
+        if word.is_empty() {
+            return None;
+        }
+        let source_file = document_path(uri).map(|path| path.display().to_string()).unwrap_or_default();
+        let weights = self.ranking_weights();
+
+        let ast_defs: Vec<SymbolDef> = self
+            .get_cached_index(uri)
+            .and_then(|index| {
+                let indices = index.name_to_defs.get(word.as_str())?;
+                Some(indices.iter().map(|&i| index.defs[i].clone()).collect())
+            })
+            .unwrap_or_default();
+        let project_defs = self.project_index.find_definitions_from(&word, std::path::Path::new(&source_file));
+
+        let mut seen = HashSet::new();
+        let mut candidates: Vec<RankedCandidate> = ast_defs
+            .into_iter()
+            .map(|def| (def, false))
+            .chain(project_defs.into_iter().map(|def| (def, true)))
+            .filter(|(def, _)| !def.file.is_empty() && def.line > 0)
+            .filter(|(def, _)| seen.insert((def.file.clone(), def.line, def.col)))
+            .map(|(def, from_project_index)| RankedCandidate {
+                factors: RankFactors::of(&word, &def, &source_file, &weights),
+                def,
+                from_project_index,
+            })
+            .collect();
+        candidates.sort_by(|a, b| {
+            a.factors
+                .score(&weights)
+                .cmp(&b.factors.score(&weights))
+                .then_with(|| a.def.file.cmp(&b.def.file))
+                .then_with(|| a.def.line.cmp(&b.def.line))
+                .then_with(|| a.def.col.cmp(&b.def.col))
+        });
+        Some((word, candidates))
+    }
+
     pub fn provide_declaration(
         &self,
         uri: &Url,
         position: Position,
         source: &str,
+        hash: ContentHash,
         include_paths: &[String],
         snapshot: &SyntaxTree,
     ) -> Option<NavigationTarget> {
@@ -317,12 +763,12 @@ impl DefinitionProvider {
             return None;
         }
 
-        let (index, load_source) = self.load_or_build_index(uri, source, include_paths, &|| false)?;
+        let (index, load_source) = self.load_or_build_index(uri, source, hash, include_paths, &|| false)?;
         debug!("[goto-declaration] AST index source: {}", load_source.as_str());
 
         let declarations = index.get_declarations(&word);
         if declarations.is_empty() {
-            return self.provide(uri, position, source, include_paths, snapshot, || false);
+            return self.provide(uri, position, source, hash, include_paths, snapshot, || false);
         }
 
         let locations: Vec<IdeLocation> = declarations.iter().filter_map(|d| def_to_location(d)).collect();
@@ -335,6 +781,7 @@ impl DefinitionProvider {
         uri: &Url,
         position: Position,
         source: &str,
+        hash: ContentHash,
         include_paths: &[String],
         snapshot: &SyntaxTree,
     ) -> Option<NavigationTarget> {
@@ -350,10 +797,10 @@ impl DefinitionProvider {
             return None;
         }
 
-        let (index, load_source) = self.load_or_build_index(uri, source, include_paths, &|| false)?;
+        let (index, load_source) = self.load_or_build_index(uri, source, hash, include_paths, &|| false)?;
         debug!("[goto-type-definition] AST index source: {}", load_source.as_str());
 
-        let source_file = uri.to_file_path().ok().map(|p| p.display().to_string()).unwrap_or_default();
+        let source_file = document_path(uri).map(|p| p.display().to_string()).unwrap_or_default();
 
         if let Some(def) = resolve_precise_def(&index, &source_file, position, &word)
             && let Some(type_def) = index.get_type_definition(def)
@@ -361,7 +808,7 @@ impl DefinitionProvider {
             return def_to_location(type_def).map(NavigationTarget::Single);
         }
 
-        let indices = index.name_to_defs.get(&word)?;
+        let indices = index.name_to_defs.get(word.as_str())?;
         let candidates: Vec<&SymbolDef> = indices
             .iter()
             .map(|&i| &index.defs[i])
@@ -380,6 +827,7 @@ impl DefinitionProvider {
         uri: &Url,
         position: Position,
         source: &str,
+        hash: ContentHash,
         include_paths: &[String],
         snapshot: &SyntaxTree,
     ) -> Option<NavigationTarget> {
@@ -395,12 +843,12 @@ impl DefinitionProvider {
             return None;
         }
 
-        let (index, load_source) = self.load_or_build_index(uri, source, include_paths, &|| false)?;
+        let (index, load_source) = self.load_or_build_index(uri, source, hash, include_paths, &|| false)?;
         debug!("[goto-implementation] AST index source: {}", load_source.as_str());
 
-        let source_file = uri.to_file_path().ok().map(|p| p.display().to_string()).unwrap_or_default();
+        let source_file = document_path(uri).map(|p| p.display().to_string()).unwrap_or_default();
 
-        let indices = index.name_to_defs.get(&word)?;
+        let indices = index.name_to_defs.get(word.as_str())?;
 
         let candidates: Vec<&SymbolDef> = indices
             .iter()
@@ -438,15 +886,19 @@ impl DefinitionProvider {
 
     pub fn provide_references(
         &self,
-        uri: &Url,
+        document: &AnalysisSnapshot,
         position: Position,
-        source: &str,
         include_paths: &[String],
-        snapshot: &SyntaxTree,
         include_declaration: bool,
     ) -> Option<Vec<IdeLocation>> {
+        let AnalysisSnapshot {
+            uri,
+            text: source,
+            hash,
+            tree,
+        } = document;
         let word = {
-            let root = snapshot.root();
+            let root = tree.root();
             helpers::navigation_word_at_position(&root, source, position)
         }?;
 
@@ -457,19 +909,19 @@ impl DefinitionProvider {
             return None;
         }
 
-        let (index, load_source) = self.load_or_build_index(uri, source, include_paths, &|| false)?;
+        let (index, load_source) = self.load_or_build_index(uri, source, *hash, include_paths, &|| false)?;
         debug!("[references] AST index source: {}", load_source.as_str());
 
-        let source_file = uri.to_file_path().ok().map(|p| p.display().to_string()).unwrap_or_default();
+        let source_file = document_path(uri).map(|p| p.display().to_string()).unwrap_or_default();
 
         let target_id = if let Some(def) = resolve_precise_def(&index, &source_file, position, &word) {
             Some(def.id.clone())
         } else {
-            index.name_to_defs.get(&word)?.first().map(|&idx| index.defs[idx].id.clone())
+            index.name_to_defs.get(word.as_str())?.first().map(|&idx| index.defs[idx].id.clone())
         }?;
 
         let mut locations = Vec::new();
-        let mut seen = std::collections::HashSet::new();
+        let mut seen = HashSet::new();
 
         if include_declaration
             && let Some(&def_idx) = index.id_to_def.get(&target_id)
@@ -489,7 +941,7 @@ impl DefinitionProvider {
         }
 
         if include_declaration {
-            for def in self.project_index.find_definitions(&word) {
+            for def in self.project_index.find_definitions_from(&word, std::path::Path::new(&source_file)) {
                 if let Some(loc) = def_to_location(&def) {
                     let key = (loc.file_path.clone(), loc.range.start.line, loc.range.start.character);
                     if seen.insert(key) {
@@ -499,7 +951,7 @@ impl DefinitionProvider {
             }
         }
 
-        for ref_site in self.project_index.find_references_by_name(&word) {
+        for ref_site in self.project_index.find_references_by_name_from(&word, std::path::Path::new(&source_file)) {
             if let Some(loc) = ref_site_to_location(&ref_site) {
                 let key = (loc.file_path.clone(), loc.range.start.line, loc.range.start.character);
                 if seen.insert(key) {
@@ -515,11 +967,65 @@ impl DefinitionProvider {
         }
     }
 
+    /// Index the workspace files the project index lacks that mention the
+    /// symbol at `position`, so [`provide_references`](Self::provide_references)
+    /// also finds uses in files the workspace scan has not reached. A text
+    /// scan picks the candidates; each is tried once until it changes on
+    /// disk. Every candidate is dumped unless `is_cancelled` returns `true`
+    /// first, in which case the rest are logged as missing from the results
+    /// and left to later requests. Returns how many were indexed.
+    pub fn index_reference_candidates(
+        &self,
+        position: Position,
+        source: &str,
+        snapshot: &SyntaxTree,
+        workspace_files: &[std::path::PathBuf],
+        include_paths_for: impl Fn(&std::path::Path) -> Vec<String>,
+        is_cancelled: &dyn Fn() -> bool,
+    ) -> usize {
+        let Some(word) = helpers::navigation_word_at_position(&snapshot.root(), source, position) else {
+            return 0;
+        };
+        if word.is_empty() || is_non_navigable_symbol(word.as_str()) {
+            return 0;
+        }
+
+        let unindexed = workspace_files
+            .iter()
+            .filter(|path| !self.project_index.contains_file(path))
+            .filter(|path| !self.on_demand_indexed.contains(&FileId::from_path(path)))
+            .map(|path| path.as_path());
+        let candidates = text_scan::files_mentioning(unindexed, &word);
+        let mut indexed = 0;
+        for (tried, path) in candidates.iter().enumerate() {
+            if !is_cancelled() {
+                debug!("[references] indexing candidate file on demand: {}", path.display());
+                if self.index_file_unless_cancelled(path, &include_paths_for(path), is_cancelled) {
+                    indexed += 1;
+                    self.on_demand_indexed.insert(FileId::from_path(path));
+                    continue;
+                }
+            }
+            if is_cancelled() {
+                info!(
+                    "[references] cancelled with {} of {} candidate files for `{word}` not indexed; results may be \
+                     partial",
+                    candidates.len() - tried,
+                    candidates.len()
+                );
+                break;
+            }
+            self.on_demand_indexed.insert(FileId::from_path(path));
+        }
+        indexed
+    }
+
     pub fn prepare_rename(
         &self,
         uri: &Url,
         position: Position,
         source: &str,
+        hash: ContentHash,
         _include_paths: &[String],
         snapshot: &SyntaxTree,
     ) -> Option<IdeRange> {
@@ -536,12 +1042,10 @@ impl DefinitionProvider {
         }
 
         let file_id = FileId::from_url(uri);
-        let hash = content_hash(source);
-        if let Some(entry) = self.cache.get(&file_id).filter(|e| e.0 == hash) {
-            let index = &entry.1;
-            let source_file = uri.to_file_path().ok().map(|p| p.display().to_string()).unwrap_or_default();
+        if let Some(index) = self.cached_index(&file_id, hash) {
+            let source_file = document_path(uri).map(|p| p.display().to_string()).unwrap_or_default();
 
-            if let Some(def) = resolve_precise_def(index, &source_file, position, &word)
+            if let Some(def) = resolve_precise_def(&index, &source_file, position, &word)
                 && is_system_header(&def.file)
             {
                 return None;
@@ -561,11 +1065,11 @@ impl DefinitionProvider {
             }
 
             if start < end {
-                let start_utf16: u32 = chars[..start].iter().map(|c| c.len_utf16() as u32).sum();
-                let end_utf16: u32 = chars[..end].iter().map(|c| c.len_utf16() as u32).sum();
+                let start_column: u32 = chars[..start].iter().map(|&c| char_width(c)).sum();
+                let end_column: u32 = chars[..end].iter().map(|&c| char_width(c)).sum();
                 Some(IdeRange::new(
-                    IdePosition::new(position.line, start_utf16),
-                    IdePosition::new(position.line, end_utf16),
+                    IdePosition::new(position.line, start_column),
+                    IdePosition::new(position.line, end_column),
                 ))
             } else {
                 None
@@ -573,10 +1077,68 @@ impl DefinitionProvider {
         })
     }
 
+    /// Index the project headers `file_id` includes that the project index
+    /// lacks, nearest first, at most `MAX_ON_DEMAND_HEADERS` per call.
+    /// Returns how many were indexed.
+    fn index_included_headers(
+        &self,
+        file_id: &FileId,
+        include_paths: &[String],
+        is_cancelled: &dyn Fn() -> bool,
+    ) -> usize {
+        let mut indexed = 0;
+        for _ in 0..MAX_ON_DEMAND_HEADERS {
+            // Each indexed header adds its own includes to the graph.
+            let next = self
+                .project_graph
+                .included_files(
+                    file_id,
+                    self.project_graph_depth.load(Ordering::Relaxed),
+                    self.project_graph_max_nodes.load(Ordering::Relaxed),
+                )
+                .into_iter()
+                .filter(|include| !is_system_header(include.as_str()))
+                .filter(|include| !self.project_index.contains_file(std::path::Path::new(include.as_str())))
+                .find(|include| !self.on_demand_indexed.contains(include));
+            let Some(include) = next else {
+                break;
+            };
+            if is_cancelled() {
+                break;
+            }
+            self.on_demand_indexed.insert(include.clone());
+            debug!("[goto-def] indexing included header on demand: {}", include.as_str());
+            if self.index_file_unless_cancelled(std::path::Path::new(include.as_str()), include_paths, is_cancelled) {
+                indexed += 1;
+            } else if is_cancelled() {
+                // Not tried to completion; a later lookup may index it.
+                self.on_demand_indexed.remove(&include);
+                break;
+            }
+        }
+        indexed
+    }
+
     fn load_or_build_index(
         &self,
         uri: &Url,
         source: &str,
+        hash: ContentHash,
+        include_paths: &[String],
+        is_cancelled: &dyn Fn() -> bool,
+    ) -> Option<(Arc<AstIndex>, IndexLoadSource)> {
+        let loaded = self.load_or_build_index_uncounted(uri, source, hash, include_paths, is_cancelled);
+        if let Some((_, load_source)) = &loaded {
+            perf::count(load_source.counter_name());
+        }
+        loaded
+    }
+
+    fn load_or_build_index_uncounted(
+        &self,
+        uri: &Url,
+        source: &str,
+        hash: ContentHash,
         include_paths: &[String],
         is_cancelled: &dyn Fn() -> bool,
     ) -> Option<(Arc<AstIndex>, IndexLoadSource)> {
@@ -585,10 +1147,9 @@ impl DefinitionProvider {
         if let Some(path) = source_path.as_ref() {
             self.project_graph.update_file(path, source, include_paths);
         }
-        let hash = content_hash(source);
-        if let Some(entry) = self.cache.get(&file_id).filter(|e| e.0 == hash) {
-            debug!("[goto-def] using in-memory AST index ({} defs, {} refs)", entry.1.defs.len(), entry.1.refs.len(),);
-            return Some((Arc::clone(&entry.1), IndexLoadSource::Memory));
+        if let Some(index) = self.cached_index(&file_id, hash) {
+            debug!("[goto-def] using in-memory AST index ({} defs, {} refs)", index.defs.len(), index.refs.len(),);
+            return Some((index, IndexLoadSource::Memory));
         }
 
         let build_lock = self.build_lock(&file_id);
@@ -600,39 +1161,71 @@ impl DefinitionProvider {
             return None;
         }
 
-        if let Some(entry) = self.cache.get(&file_id).filter(|e| e.0 == hash) {
+        if let Some(index) = self.cached_index(&file_id, hash) {
             debug!(
                 "[goto-def] using in-memory AST index after wait ({} defs, {} refs)",
-                entry.1.defs.len(),
-                entry.1.refs.len(),
+                index.defs.len(),
+                index.refs.len(),
             );
-            return Some((Arc::clone(&entry.1), IndexLoadSource::Memory));
+            return Some((index, IndexLoadSource::Memory));
         }
 
+        // Defines and override flags change the AST as much as include paths do.
+        let (include_paths, flags) = self.with_overrides(source_path.as_deref(), include_paths);
+        let include_paths = include_paths.as_slice();
+        let cache_inputs: Vec<String> = include_paths.iter().chain(&flags).cloned().collect();
         if let Some(path) = source_path.as_ref()
-            && let Some(index) = index_cache::load(path, &hash, include_paths)
+            && let Some(index) = index_cache::load(path, &hash.to_string(), &cache_inputs)
         {
             debug!("[goto-def] disk AST index cache hit for {}", path.display());
-            self.project_index.update_file(path.clone(), index.clone());
             let idx = Arc::new(index);
-            self.cache.insert(file_id.clone(), (hash, Arc::clone(&idx)));
+            let disk_cache = Some(disk_cache_key(path, hash, &cache_inputs));
+            self.project_index.update_file_from_source(
+                path.clone(),
+                Arc::clone(&idx),
+                source,
+                include_paths,
+                disk_cache,
+            );
+            self.insert_cached_index(file_id, hash, Arc::clone(&idx));
             return Some((idx, IndexLoadSource::Disk));
         }
 
+        if let Some(path) = source_path.as_ref()
+            && let Some(idx) = self.project_index.translation_unit_header_index(path, hash)
+        {
+            debug!("[goto-def] using header index from an including translation unit for {}", path.display());
+            self.insert_cached_index(file_id, hash, Arc::clone(&idx));
+            return Some((idx, IndexLoadSource::TranslationUnit));
+        }
+
         // Check cancellation before the expensive AST dump.
         if is_cancelled() {
             debug!("[goto-def] cancelled before AST dump");
             return None;
         }
 
+        if self.timed_out.get(&file_id).is_some_and(|timed_out| *timed_out == hash) {
+            debug!("[goto-def] skipping AST dump that timed out before for {uri}");
+            return None;
+        }
+
         debug!("[goto-def] AST cache miss, running AST dump for {uri}");
-        let index = self.run_and_build_index(uri, source, include_paths)?;
+        let index = match self.run_and_build_index(uri, source, include_paths, &flags, is_cancelled) {
+            Ok(index) => index,
+            Err(AstDumpError::TimedOut) => {
+                self.timed_out.insert(file_id, hash);
+                return None;
+            },
+            Err(_) => return None,
+        };
+        let idx = Arc::new(index);
         if let Some(path) = source_path {
-            index_cache::save(&path, &hash, include_paths, &index);
-            self.project_index.update_file(path, index.clone());
+            let saved = index_cache::save(&path, &hash.to_string(), &cache_inputs, &idx);
+            let disk_cache = saved.then(|| disk_cache_key(&path, hash, &cache_inputs));
+            self.project_index.update_file_from_source(path, Arc::clone(&idx), source, include_paths, disk_cache);
         }
-        let idx = Arc::new(index);
-        self.cache.insert(file_id, (hash, Arc::clone(&idx)));
+        self.insert_cached_index(file_id, hash, Arc::clone(&idx));
         Some((idx, IndexLoadSource::AstDump))
     }
 
@@ -641,19 +1234,27 @@ impl DefinitionProvider {
         uri: &Url,
         source: &str,
         include_paths: &[String],
-    ) -> Option<AstIndex> {
-        let (ast_json, tmp_files) = run_ast_dump(source, uri, include_paths)?;
+        flags: &[String],
+        is_cancelled: &dyn Fn() -> bool,
+    ) -> Result<AstIndex, AstDumpError> {
+        let timeout = Duration::from_millis(self.ast_dump_timeout_ms.load(Ordering::Relaxed));
+        let (parsed, tmp_files) =
+            run_ast_dump(source, uri, include_paths, flags, is_cancelled, timeout, |stdout| stream_index(stdout))?;
+        if is_cancelled() {
+            debug!("[goto-def] cancelled after AST dump");
+            return Err(AstDumpError::Cancelled);
+        }
 
-        let root: Node = match parse_ast_json(&ast_json) {
-            Ok(v) => v,
+        let builder = match parsed {
+            Ok(builder) => builder,
             Err(error) => {
-                warn!("Failed to parse AST JSON: {error}");
-                return None;
+                warn!("[ast-dump] produced no usable JSON for {uri}: {error}");
+                return Err(AstDumpError::Failed);
             },
         };
 
-        let source_path = uri.to_file_path().ok().map(|p| p.display().to_string());
-        Some(build_index(&root, &tmp_files, source_path.as_deref()))
+        let source_path = document_path(uri).map(|p| p.display().to_string());
+        Ok(builder.finish(&tmp_files, source_path.as_deref()))
     }
 
     fn build_lock(
@@ -668,6 +1269,8 @@ impl DefinitionProvider {
 enum IndexLoadSource {
     Memory,
     Disk,
+    /// Split out of the dump of a translation unit including the header.
+    TranslationUnit,
     AstDump,
 }
 
@@ -676,9 +1279,45 @@ impl IndexLoadSource {
         match self {
             Self::Memory => "memory",
             Self::Disk => "disk",
+            Self::TranslationUnit => "translation_unit",
             Self::AstDump => "ast_dump",
         }
     }
+
+    /// Name of the `metal-analyzer/perf` counter of loads from this source.
+    fn counter_name(self) -> &'static str {
+        match self {
+            Self::Memory => "astIndex.memory",
+            Self::Disk => "astIndex.disk",
+            Self::TranslationUnit => "astIndex.translationUnit",
+            Self::AstDump => "astIndex.astDump",
+        }
+    }
+
+    /// Name of the go-to-definition latency of lookups answered from an
+    /// index loaded from this source.
+    fn latency_name(self) -> &'static str {
+        match self {
+            Self::Memory => "gotoDefinition.memory",
+            Self::Disk => "gotoDefinition.disk",
+            Self::TranslationUnit => "gotoDefinition.translationUnit",
+            Self::AstDump => "gotoDefinition.astDump",
+        }
+    }
+}
+
+/// Where the index of `path` built from `hash` with `inputs` is in the disk
+/// cache.
+fn disk_cache_key(
+    path: &std::path::Path,
+    hash: ContentHash,
+    inputs: &[String],
+) -> DiskCacheKey {
+    DiskCacheKey {
+        path: path.to_path_buf(),
+        source_hash: hash.to_string(),
+        inputs: inputs.to_vec(),
+    }
 }
 
 fn is_non_navigable_symbol(word: &str) -> bool {
@@ -708,14 +1347,14 @@ fn resolve_macro_definition(
     source: &str,
     word: &str,
 ) -> Option<NavigationTarget> {
-    let file_path = uri.to_file_path().ok()?;
+    let file_path = document_path(uri)?;
     let pattern = format!("#define {word}");
     for (line_idx, line) in source.lines().enumerate() {
         let trimmed = line.trim_start();
         if trimmed.starts_with(&pattern) {
             let col = line.find(&pattern).unwrap_or(0) + "#define ".len();
-            let start_col = utf16_column_of_byte_offset(line, col);
-            let end_col = utf16_column_of_byte_offset(line, col + word.len());
+            let start_col = column_of_byte_offset(line, col);
+            let end_col = column_of_byte_offset(line, col + word.len());
             let range =
                 IdeRange::new(IdePosition::new(line_idx as u32, start_col), IdePosition::new(line_idx as u32, end_col));
             return Some(NavigationTarget::Single(IdeLocation::new(file_path.clone(), range)));
@@ -724,25 +1363,6 @@ fn resolve_macro_definition(
     None
 }
 
-fn content_hash(source: &str) -> String {
-    use std::hash::{DefaultHasher, Hash, Hasher};
-    let mut hasher = DefaultHasher::new();
-    source.hash(&mut hasher);
-    format!("{:x}", hasher.finish())
-}
-
-// Clang's `-ast-dump=json` for large Metal kernels nests expression trees
-// deeper than serde_json's default 128-level recursion cap. Disable the cap
-// and route through serde_stacker so deep recursion grows the stack on demand
-// instead of blowing it.
-fn parse_ast_json(json: &str) -> serde_json::Result<Node> {
-    use serde::Deserialize;
-    let mut deserializer = serde_json::Deserializer::from_str(json);
-    deserializer.disable_recursion_limit();
-    let deserializer = serde_stacker::Deserializer::new(&mut deserializer);
-    Node::deserialize(deserializer)
-}
-
 #[cfg(test)]
 #[path = "../../tests/src/definition/provider_tests.rs"]
 mod tests;
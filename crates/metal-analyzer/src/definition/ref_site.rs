@@ -1,10 +1,12 @@
 use serde::{Deserialize, Serialize};
 
+use crate::definition::interner::SharedStr;
+
 /// A reference site — a place in the source where a symbol is *used*.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RefSite {
     /// File containing the reference.
-    pub file: String,
+    pub file: SharedStr,
     /// 1-based line.
     pub line: u32,
     /// 1-based column.
@@ -14,9 +16,9 @@ pub struct RefSite {
     /// AST node id of the declaration this reference points to.
     pub target_id: String,
     /// Name of the referenced symbol (for sanity-checking).
-    pub target_name: String,
+    pub target_name: SharedStr,
     /// Kind of the symbol being referenced.
-    pub target_kind: String,
+    pub target_kind: SharedStr,
     /// Where the token is expanded (macro call-site), if available.
     pub expansion: Option<RefSiteLocation>,
     /// Where the token is spelled (macro body/definition), if available.
@@ -27,7 +29,7 @@ pub struct RefSite {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RefSiteLocation {
     /// File containing this location.
-    pub file: String,
+    pub file: SharedStr,
     /// 1-based line.
     pub line: u32,
     /// 1-based column.
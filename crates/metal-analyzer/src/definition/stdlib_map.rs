@@ -0,0 +1,254 @@
+//! Curated map from well-known `metal_stdlib` symbols to their SDK header.
+//!
+//! Go-to-definition on a builtin otherwise needs an AST dump of the file or a
+//! text search through every `metal*` header. The symbols users jump to most
+//! (simdgroup matrices, texture methods, atomics, barriers) are listed here
+//! with the header that declares them. The first lookup in a toolchain's
+//! header directory reads only those headers and records each symbol's
+//! declaration, so later lookups are a map access.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use tracing::debug;
+
+use crate::{
+    definition::{
+        symbol_text::is_ident_char,
+        system_lookup::{byte_offset_to_position, find_word_boundary_offset},
+    },
+    ide::navigation::{IdeLocation, IdeRange, NavigationTarget},
+};
+
+/// A curated symbol and where to look for it.
+struct StdlibEntry {
+    symbol: &'static str,
+    /// Header file name within the directory holding `metal_stdlib`.
+    header: &'static str,
+    /// Type whose declaration precedes the symbol, for members.
+    scope: Option<&'static str>,
+}
+
+const fn free(
+    symbol: &'static str,
+    header: &'static str,
+) -> StdlibEntry {
+    StdlibEntry {
+        symbol,
+        header,
+        scope: None,
+    }
+}
+
+const fn member(
+    scope: &'static str,
+    symbol: &'static str,
+    header: &'static str,
+) -> StdlibEntry {
+    StdlibEntry {
+        symbol,
+        header,
+        scope: Some(scope),
+    }
+}
+
+const ENTRIES: &[StdlibEntry] = &[
+    free("simdgroup_matrix", "metal_simdgroup_matrix"),
+    free("simdgroup_float8x8", "metal_simdgroup_matrix"),
+    free("simdgroup_half8x8", "metal_simdgroup_matrix"),
+    free("simdgroup_bfloat8x8", "metal_simdgroup_matrix"),
+    free("make_filled_simdgroup_matrix", "metal_simdgroup_matrix"),
+    free("simdgroup_load", "metal_simdgroup_matrix"),
+    free("simdgroup_store", "metal_simdgroup_matrix"),
+    free("simdgroup_multiply", "metal_simdgroup_matrix"),
+    free("simdgroup_multiply_accumulate", "metal_simdgroup_matrix"),
+    free("simd_sum", "metal_simdgroup"),
+    free("simd_max", "metal_simdgroup"),
+    free("simd_min", "metal_simdgroup"),
+    free("simd_broadcast", "metal_simdgroup"),
+    free("simd_shuffle", "metal_simdgroup"),
+    free("simd_shuffle_down", "metal_simdgroup"),
+    free("simd_shuffle_xor", "metal_simdgroup"),
+    free("simd_prefix_exclusive_sum", "metal_simdgroup"),
+    free("simd_prefix_inclusive_sum", "metal_simdgroup"),
+    free("simd_ballot", "metal_simdgroup"),
+    free("threadgroup_barrier", "metal_compute"),
+    free("simdgroup_barrier", "metal_compute"),
+    free("mem_flags", "metal_compute"),
+    free("atomic", "metal_atomic"),
+    free("atomic_int", "metal_atomic"),
+    free("atomic_uint", "metal_atomic"),
+    free("atomic_bool", "metal_atomic"),
+    free("atomic_float", "metal_atomic"),
+    free("memory_order", "metal_atomic"),
+    free("atomic_load_explicit", "metal_atomic"),
+    free("atomic_store_explicit", "metal_atomic"),
+    free("atomic_exchange_explicit", "metal_atomic"),
+    free("atomic_compare_exchange_weak_explicit", "metal_atomic"),
+    free("atomic_fetch_add_explicit", "metal_atomic"),
+    free("atomic_fetch_sub_explicit", "metal_atomic"),
+    free("atomic_fetch_and_explicit", "metal_atomic"),
+    free("atomic_fetch_or_explicit", "metal_atomic"),
+    free("atomic_fetch_xor_explicit", "metal_atomic"),
+    free("atomic_fetch_min_explicit", "metal_atomic"),
+    free("atomic_fetch_max_explicit", "metal_atomic"),
+    free("texture1d", "metal_texture"),
+    free("texture2d", "metal_texture"),
+    free("texture2d_array", "metal_texture"),
+    free("texture3d", "metal_texture"),
+    free("texturecube", "metal_texture"),
+    free("depth2d", "metal_texture"),
+    free("sampler", "metal_texture"),
+    member("texture2d", "sample", "metal_texture"),
+    member("texture2d", "read", "metal_texture"),
+    member("texture2d", "write", "metal_texture"),
+    member("texture2d", "gather", "metal_texture"),
+    member("texture2d", "get_width", "metal_texture"),
+    member("texture2d", "get_height", "metal_texture"),
+    member("texture2d", "get_num_mip_levels", "metal_texture"),
+];
+
+/// Declarations of the curated symbols, by symbol name, for one toolchain.
+#[derive(Debug, Default)]
+pub(crate) struct StdlibLocations {
+    free: HashMap<&'static str, IdeLocation>,
+    members: HashMap<&'static str, IdeLocation>,
+}
+
+/// Curated stdlib locations per toolchain header directory, scanned on
+/// first use.
+#[derive(Default)]
+pub(crate) struct StdlibMap {
+    scanned: Mutex<HashMap<PathBuf, Arc<StdlibLocations>>>,
+}
+
+impl StdlibMap {
+    /// The declaration of the curated symbol `word`: a free function or
+    /// type, or, for `member`, a method reached through `.` or `->`.
+    pub(crate) fn lookup(
+        &self,
+        word: &str,
+        member: bool,
+        include_paths: &[String],
+    ) -> Option<NavigationTarget> {
+        if !ENTRIES.iter().any(|entry| entry.symbol == word && entry.scope.is_some() == member) {
+            return None;
+        }
+        let dir = stdlib_dir(include_paths)?;
+        let locations = {
+            let mut scanned = self.scanned.lock().ok()?;
+            scanned.entry(dir.clone()).or_insert_with(|| Arc::new(scan_stdlib_dir(&dir))).clone()
+        };
+        let map = if member {
+            &locations.members
+        } else {
+            &locations.free
+        };
+        map.get(word).cloned().map(NavigationTarget::Single)
+    }
+}
+
+/// The directory holding `metal_stdlib` among `include_paths`.
+fn stdlib_dir(include_paths: &[String]) -> Option<PathBuf> {
+    include_paths
+        .iter()
+        .filter(|path| !path.starts_with(crate::metal::compiler::FRAMEWORK_DIR_PREFIX))
+        .flat_map(|path| [PathBuf::from(path), Path::new(path).join("metal")])
+        .find(|dir| dir.join("metal_stdlib").is_file())
+}
+
+/// Read the curated headers in `dir` and locate every entry they declare.
+pub(crate) fn scan_stdlib_dir(dir: &Path) -> StdlibLocations {
+    let mut locations = StdlibLocations::default();
+    let mut sources: HashMap<&'static str, Option<(PathBuf, String)>> = HashMap::new();
+    for entry in ENTRIES {
+        let Some((path, source)) = sources
+            .entry(entry.header)
+            .or_insert_with(|| {
+                let path = dir.join(entry.header);
+                let source = std::fs::read_to_string(&path).ok()?;
+                Some((path.canonicalize().unwrap_or(path), source))
+            })
+            .as_ref()
+        else {
+            continue;
+        };
+        let from = match entry.scope {
+            Some(scope) => match find_declaration_offset(source, 0, scope) {
+                Some(offset) => offset + scope.len(),
+                None => continue,
+            },
+            None => 0,
+        };
+        let Some(start) = find_declaration_offset(source, from, entry.symbol) else {
+            continue;
+        };
+        let range = IdeRange::new(
+            byte_offset_to_position(source, start),
+            byte_offset_to_position(source, start + entry.symbol.len()),
+        );
+        let map = if entry.scope.is_some() {
+            &mut locations.members
+        } else {
+            &mut locations.free
+        };
+        map.entry(entry.symbol).or_insert_with(|| IdeLocation::new(path.clone(), range));
+    }
+    debug!(
+        "[stdlib-map] located {} of {} curated symbols in {}",
+        locations.free.len() + locations.members.len(),
+        ENTRIES.len(),
+        dir.display()
+    );
+    locations
+}
+
+/// Offset of the first occurrence of `symbol` at or after `from` that looks
+/// like its declaration, falling back to the first occurrence at all.
+///
+/// A declaration names the symbol after `struct`, `class`, `enum`, `union`
+/// or `using`, or after a type (an identifier, `>`, `*` or `&`) and before
+/// one of `(`, `;`, `{`, `=` or `<`.
+pub(crate) fn find_declaration_offset(
+    source: &str,
+    from: usize,
+    symbol: &str,
+) -> Option<usize> {
+    let mut first = None;
+    let mut search_from = from;
+    while let Some(relative) = find_word_boundary_offset(&source[search_from..], symbol) {
+        let start = search_from + relative;
+        let end = start + symbol.len();
+        first.get_or_insert(start);
+        if is_declaration(&source[..start], &source[end..]) {
+            return Some(start);
+        }
+        search_from = end;
+    }
+    first
+}
+
+fn is_declaration(
+    before: &str,
+    after: &str,
+) -> bool {
+    let before = before.trim_end();
+    let previous_word: String = {
+        let mut word: Vec<char> = before.chars().rev().take_while(|&ch| is_ident_char(ch)).collect();
+        word.reverse();
+        word.into_iter().collect()
+    };
+    if matches!(previous_word.as_str(), "struct" | "class" | "enum" | "union" | "using") {
+        return true;
+    }
+    let follows_type = !previous_word.is_empty() && previous_word != "return"
+        || before.ends_with(['>', '*', '&']) && !before.ends_with("->");
+    follows_type && after.trim_start().starts_with(['(', ';', '{', '=', '<'])
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/definition/stdlib_map_tests.rs"]
+mod tests;
@@ -1,16 +1,18 @@
 use serde::{Deserialize, Serialize};
 
+use crate::definition::interner::SharedStr;
+
 /// A definition or declaration found in the AST.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SymbolDef {
     /// Clang AST node id (e.g. `"0x714cc9008"`).
     pub id: String,
     /// Symbol name.
-    pub name: String,
+    pub name: SharedStr,
     /// AST node kind (e.g. `FunctionDecl`, `CXXRecordDecl`).
-    pub kind: String,
+    pub kind: SharedStr,
     /// Absolute file path where the symbol is defined.
-    pub file: String,
+    pub file: SharedStr,
     /// 1-based line number.
     pub line: u32,
     /// 1-based column number.
@@ -24,5 +26,10 @@ pub struct SymbolDef {
     pub type_name: Option<String>,
     /// Full qualified type string from Clang (e.g. `"void (float *, uint)"`
     /// for functions, `"float4"` for variables). Used for hover display.
-    pub qual_type: Option<String>,
+    pub qual_type: Option<SharedStr>,
+    /// Whether the symbol has internal linkage: a `static` function or
+    /// variable, or a `const` variable, at namespace scope. Only its own
+    /// translation unit can reference it.
+    #[serde(default)]
+    pub file_local: bool,
 }
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, path::Path};
 
 use tower_lsp::lsp_types::Position;
 
@@ -6,46 +6,124 @@ use crate::{
     definition::{
         ast_index::AstIndex,
         symbol_def::SymbolDef,
-        symbol_text::{extract_call_argument_count, extract_member_receiver_identifier},
-        utils::{is_system_header, paths_match},
+        symbol_text::{
+            extract_call_argument_count, extract_call_arguments, extract_member_receiver_identifier, is_ident_char,
+        },
+        utils::{is_system_header, normalize_type_name, paths_match},
     },
-    metal::builtins::lookup as lookup_builtin,
+    metal::builtins::{BuiltinKind, lookup as lookup_builtin},
+    server::header_owners::is_header_file,
 };
 
-pub(super) fn rank_definition(
-    word: &str,
-    def: &SymbolDef,
-    source_file: &str,
-) -> (u8, u8, u8, u8) {
-    let same_file = if paths_match(&def.file, source_file) {
-        0
-    } else {
-        1
-    };
-    let is_definition = if def.is_definition {
-        0
-    } else {
-        1
-    };
-    let is_parm_var = if matches!(def.kind.as_str(), "ParmVarDecl") {
-        1
-    } else {
-        0
-    };
+/// Penalties added up to order definition candidates; the lowest total wins.
+///
+/// The defaults keep each weight above the sum of the ones after it, so the
+/// criteria apply in order of precedence unless a codebase needs otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankingWeights {
+    /// The candidate is in another file than the cursor.
+    pub other_file: u32,
+    /// The candidate is a declaration without a body.
+    pub declaration_only: u32,
+    /// The candidate is a function parameter.
+    pub parameter: u32,
+    /// A name that does not look like a builtin resolves into a system header.
+    pub system_header: u32,
+    /// A name that looks like a builtin resolves outside the system headers.
+    pub builtin_outside_system: u32,
+    /// The candidate is in a project header rather than a `.metal` source.
+    pub project_header: u32,
+    /// Name prefixes that mark builtins, in addition to the known builtin names.
+    pub builtin_prefixes: Vec<String>,
+}
 
-    let system_rank = if looks_like_builtin_symbol(word) {
-        if is_system_header(&def.file) {
-            0
-        } else {
-            1
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self {
+            other_file: 8,
+            declaration_only: 4,
+            parameter: 2,
+            system_header: 1,
+            builtin_outside_system: 1,
+            project_header: 0,
+            builtin_prefixes: vec!["simd_".to_string(), "metal::".to_string()],
         }
-    } else if is_system_header(&def.file) {
-        1
-    } else {
-        0
-    };
+    }
+}
 
-    (same_file, is_definition, is_parm_var, system_rank)
+/// Which ranking criteria a candidate runs into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct RankFactors {
+    pub(crate) other_file: bool,
+    pub(crate) declaration_only: bool,
+    pub(crate) parameter: bool,
+    pub(crate) system_header: bool,
+    pub(crate) builtin_outside_system: bool,
+    pub(crate) project_header: bool,
+}
+
+impl RankFactors {
+    pub(crate) fn of(
+        word: &str,
+        def: &SymbolDef,
+        source_file: &str,
+        weights: &RankingWeights,
+    ) -> Self {
+        let in_system_header = is_system_header(&def.file);
+        let looks_builtin = looks_like_builtin_symbol(word, &weights.builtin_prefixes);
+        Self {
+            other_file: !paths_match(&def.file, source_file),
+            declaration_only: !def.is_definition,
+            parameter: def.kind == "ParmVarDecl",
+            system_header: !looks_builtin && in_system_header,
+            builtin_outside_system: looks_builtin && !in_system_header,
+            project_header: !in_system_header && is_header_file(Path::new(&def.file)),
+        }
+    }
+
+    /// Each criterion the candidate runs into, with its non-zero penalty.
+    pub(crate) fn penalties(
+        self,
+        weights: &RankingWeights,
+    ) -> Vec<(&'static str, u32)> {
+        [
+            (self.other_file, "other file", weights.other_file),
+            (self.declaration_only, "declaration only", weights.declaration_only),
+            (self.parameter, "parameter", weights.parameter),
+            (self.system_header, "system header", weights.system_header),
+            (self.builtin_outside_system, "builtin outside system headers", weights.builtin_outside_system),
+            (self.project_header, "project header", weights.project_header),
+        ]
+        .into_iter()
+        .filter(|&(applies, _, penalty)| applies && penalty > 0)
+        .map(|(_, reason, penalty)| (reason, penalty))
+        .collect()
+    }
+
+    pub(crate) fn score(
+        self,
+        weights: &RankingWeights,
+    ) -> u32 {
+        self.penalties(weights).iter().map(|(_, penalty)| penalty).sum()
+    }
+}
+
+/// A by-name definition candidate with the criteria it was ranked on.
+#[derive(Debug, Clone)]
+pub(crate) struct RankedCandidate {
+    pub(crate) def: SymbolDef,
+    /// Found in the project index rather than the document's AST index.
+    pub(crate) from_project_index: bool,
+    pub(crate) factors: RankFactors,
+}
+
+pub(crate) fn rank_definition(
+    word: &str,
+    def: &SymbolDef,
+    source_file: &str,
+    weights: &RankingWeights,
+) -> u32 {
+    RankFactors::of(word, def, source_file, weights).score(weights)
 }
 
 pub(super) fn disambiguate_member_tie<'a>(
@@ -103,7 +181,84 @@ pub(super) fn disambiguate_member_tie<'a>(
     None
 }
 
-fn infer_local_identifier_type_name(
+/// Members named `word` of the class template specializations a member
+/// access at the cursor goes through, e.g. `Tile<half>::element_at` for
+/// `tile.element_at` with `Tile<half> tile`.
+///
+/// In a function template the receiver's type is dependent, but each
+/// instantiation repeats the receiver's declaration at the same location with
+/// concrete arguments, so every instantiated specialization contributes.
+pub(super) fn specialization_members_for_access<'a>(
+    index: &'a AstIndex,
+    source_file: &str,
+    source: &str,
+    position: Position,
+    word: &str,
+) -> Vec<&'a SymbolDef> {
+    let Some(receiver) = extract_member_receiver_identifier(source, position, word) else {
+        return Vec::new();
+    };
+    let cursor_line = position.line + 1;
+    let cursor_col = position.character + 1;
+    let declarations: Vec<&SymbolDef> = index
+        .name_to_defs
+        .get(receiver.as_str())
+        .into_iter()
+        .flatten()
+        .map(|&idx| &index.defs[idx])
+        .filter(|def| paths_match(&def.file, source_file))
+        .filter(|def| matches!(def.kind.as_str(), "ParmVarDecl" | "VarDecl" | "FieldDecl"))
+        .filter(|def| def.line < cursor_line || (def.line == cursor_line && def.col <= cursor_col))
+        .collect();
+    let Some(nearest) = declarations.iter().map(|def| (def.line, def.col)).max() else {
+        return Vec::new();
+    };
+
+    let mut seen = HashSet::new();
+    let mut members = Vec::new();
+    for declaration in declarations.into_iter().filter(|def| (def.line, def.col) == nearest) {
+        let Some((name, args)) = declaration.qual_type.as_deref().and_then(template_type_args) else {
+            continue;
+        };
+        for member in index.specialization_members(name, &args, word) {
+            if seen.insert((member.file.as_str(), member.line, member.col)) {
+                members.push(member);
+            }
+        }
+    }
+    members
+}
+
+/// Template name and arguments of a type such as `const Tile<half, 4> &`.
+fn template_type_args(qual_type: &str) -> Option<(&str, Vec<&str>)> {
+    let open = qual_type.find('<')?;
+    let name = qual_type[..open].split_whitespace().last()?;
+    let name = name.rsplit("::").next().unwrap_or(name);
+    let mut depth = 0usize;
+    let mut args = Vec::new();
+    let mut arg_start = open + 1;
+    for (offset, ch) in qual_type[open..].char_indices() {
+        let idx = open + offset;
+        match ch {
+            '<' | '(' => depth += 1,
+            '>' | ')' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    args.push(qual_type[arg_start..idx].trim());
+                    return Some((name, args));
+                }
+            },
+            ',' if depth == 1 => {
+                args.push(qual_type[arg_start..idx].trim());
+                arg_start = idx + 1;
+            },
+            _ => {},
+        }
+    }
+    None
+}
+
+pub(crate) fn infer_local_identifier_type_name(
     index: &AstIndex,
     source_file: &str,
     cursor_line: u32,
@@ -214,6 +369,12 @@ fn method_constness_rank(def: &SymbolDef) -> u8 {
 }
 
 fn method_parameter_count(def: &SymbolDef) -> Option<usize> {
+    parameter_types(def).map(|types| types.len())
+}
+
+/// Parameter types spelled in a function's `qual_type`, e.g. `["float", "uint"]`
+/// for `"float (float, uint)"`.
+fn parameter_types(def: &SymbolDef) -> Option<Vec<&str>> {
     let signature = def.qual_type.as_deref()?;
     let start = signature.find('(')?;
     let mut depth = 0usize;
@@ -234,20 +395,168 @@ fn method_parameter_count(def: &SymbolDef) -> Option<usize> {
     let end = end?;
     let params = signature[start + 1..end].trim();
     if params.is_empty() || params == "void" {
-        return Some(0);
+        return Some(Vec::new());
     }
 
-    let mut count = 1usize;
+    let mut types = Vec::new();
     let mut nested = 0usize;
-    for ch in params.chars() {
+    let mut param_start = 0;
+    for (idx, ch) in params.char_indices() {
         match ch {
             '<' | '(' | '[' => nested += 1,
             '>' | ')' | ']' => nested = nested.saturating_sub(1),
-            ',' if nested == 0 => count += 1,
+            ',' if nested == 0 => {
+                types.push(params[param_start..idx].trim());
+                param_start = idx + 1;
+            },
             _ => {},
         }
     }
-    Some(count)
+    types.push(params[param_start..].trim());
+    Some(types)
+}
+
+/// Pick the overload among tied free functions whose parameters fit the
+/// call at the cursor: same argument count, then the most arguments of
+/// exactly the parameter type. Known argument types that neither match nor
+/// convert as scalars rule a candidate out.
+pub(crate) fn disambiguate_overload_tie<'a>(
+    index: &AstIndex,
+    tied_candidates: &[&'a SymbolDef],
+    source_file: &str,
+    source: &str,
+    position: Position,
+    word: &str,
+) -> Option<&'a SymbolDef> {
+    let functions: Vec<&SymbolDef> =
+        tied_candidates.iter().copied().filter(|candidate| candidate.kind == "FunctionDecl").collect();
+    if functions.len() < 2 {
+        return None;
+    }
+
+    let arguments = extract_call_arguments(source, position, word)?;
+    let cursor_line = position.line + 1;
+    let cursor_col = position.character + 1;
+    let argument_types: Vec<Option<String>> = arguments
+        .iter()
+        .map(|argument| infer_argument_type_name(index, source_file, cursor_line, cursor_col, argument))
+        .collect();
+
+    let scored: Vec<(&SymbolDef, usize)> = functions
+        .into_iter()
+        .filter_map(|candidate| {
+            let params = parameter_types(candidate)?;
+            if params.len() != argument_types.len() {
+                return None;
+            }
+            let mut score = 0;
+            for (param, argument) in params.iter().zip(&argument_types) {
+                let (Some(param), Some(argument)) = (normalize_type_name(param), argument.as_deref()) else {
+                    continue;
+                };
+                if param == argument {
+                    score += 2;
+                } else if is_scalar_type(&param) && is_scalar_type(argument) {
+                    score += 1;
+                } else {
+                    return None;
+                }
+            }
+            Some((candidate, score))
+        })
+        .collect();
+
+    let best_score = scored.iter().map(|(_, score)| *score).max()?;
+    let best: Vec<&SymbolDef> =
+        scored.into_iter().filter(|(_, score)| *score == best_score).map(|(candidate, _)| candidate).collect();
+    // Declarations and the definition of one overload share a signature;
+    // two definitions of it live in different scopes.
+    let signatures: HashSet<&str> = best.iter().filter_map(|candidate| candidate.qual_type.as_deref()).collect();
+    if signatures.len() > 1 || best.iter().filter(|candidate| candidate.is_definition).count() > 1 {
+        return None;
+    }
+    best.iter().copied().find(|candidate| candidate.is_definition).or_else(|| best.first().copied())
+}
+
+/// Type of a call argument: literals, `T(...)` constructions of a type and
+/// identifiers declared before the cursor.
+fn infer_argument_type_name(
+    index: &AstIndex,
+    source_file: &str,
+    cursor_line: u32,
+    cursor_col: u32,
+    argument: &str,
+) -> Option<String> {
+    if argument == "true" || argument == "false" {
+        return Some("bool".to_string());
+    }
+    if argument.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return numeric_literal_type(argument).map(str::to_string);
+    }
+    if let Some((callee, _)) = argument.split_once('(')
+        && argument.ends_with(')')
+    {
+        let callee = callee.trim();
+        let is_type = lookup_builtin(callee).is_some_and(|entry| entry.kind == BuiltinKind::Type)
+            || index.name_to_defs.get(callee).is_some_and(|indices| {
+                indices
+                    .iter()
+                    .any(|&i| matches!(index.defs[i].kind.as_str(), "CXXRecordDecl" | "TypedefDecl" | "TypeAliasDecl"))
+            });
+        return is_type.then(|| callee.to_string());
+    }
+    if argument.chars().all(is_ident_char) {
+        return infer_local_identifier_type_name(index, source_file, cursor_line, cursor_col, argument)
+            .and_then(|type_name| normalize_type_name(&type_name));
+    }
+    None
+}
+
+fn numeric_literal_type(literal: &str) -> Option<&'static str> {
+    let lower = literal.to_ascii_lowercase();
+    if lower.starts_with("0x") {
+        return Some(if lower.ends_with('u') {
+            "uint"
+        } else {
+            "int"
+        });
+    }
+    if lower.ends_with('h') {
+        return Some("half");
+    }
+    if lower.contains(['.', 'e']) || lower.ends_with('f') {
+        return Some("float");
+    }
+    if lower.ends_with('u') {
+        return Some("uint");
+    }
+    lower.chars().all(|c| c.is_ascii_digit()).then_some("int")
+}
+
+fn is_scalar_type(type_name: &str) -> bool {
+    matches!(
+        type_name,
+        "bool"
+            | "char"
+            | "uchar"
+            | "short"
+            | "ushort"
+            | "int"
+            | "uint"
+            | "long"
+            | "ulong"
+            | "half"
+            | "float"
+            | "int8_t"
+            | "uint8_t"
+            | "int16_t"
+            | "uint16_t"
+            | "int32_t"
+            | "uint32_t"
+            | "int64_t"
+            | "uint64_t"
+            | "size_t"
+    )
 }
 
 fn short_type_name(type_name: &str) -> &str {
@@ -255,6 +564,9 @@ fn short_type_name(type_name: &str) -> &str {
     without_namespace.split('<').next().unwrap_or(without_namespace)
 }
 
-fn looks_like_builtin_symbol(word: &str) -> bool {
-    word.starts_with("simd_") || word.starts_with("metal::") || lookup_builtin(word).is_some()
+fn looks_like_builtin_symbol(
+    word: &str,
+    builtin_prefixes: &[String],
+) -> bool {
+    builtin_prefixes.iter().any(|prefix| word.starts_with(prefix.as_str())) || lookup_builtin(word).is_some()
 }
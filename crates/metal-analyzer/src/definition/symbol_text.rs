@@ -7,6 +7,16 @@ pub(super) fn extract_call_argument_count(
     position: Position,
     word: &str,
 ) -> Option<usize> {
+    extract_call_arguments(source, position, word).map(|arguments| arguments.len())
+}
+
+/// The trimmed argument expressions of the call `word(...)` at `position`,
+/// when the call is on one line.
+pub(super) fn extract_call_arguments(
+    source: &str,
+    position: Position,
+    word: &str,
+) -> Option<Vec<String>> {
     let (chars, cursor) = line_chars_and_cursor(source, position)?;
 
     let mut word_start = cursor;
@@ -31,29 +41,35 @@ pub(super) fn extract_call_argument_count(
     }
 
     let mut depth = 0usize;
-    let mut saw_any_argument_token = false;
-    let mut commas = 0usize;
+    let mut arguments = Vec::new();
+    let mut current = String::new();
     for ch in chars[idx..].iter().copied() {
         match ch {
-            '(' => depth += 1,
-            ')' => {
+            '(' | '[' | '{' => {
+                depth += 1;
+                if depth > 1 {
+                    current.push(ch);
+                }
+            },
+            ')' | ']' | '}' => {
                 if depth == 0 {
                     return None;
                 }
                 depth -= 1;
                 if depth == 0 {
-                    return if saw_any_argument_token {
-                        Some(commas + 1)
-                    } else {
-                        Some(0)
-                    };
+                    let last = current.trim();
+                    if !last.is_empty() || !arguments.is_empty() {
+                        arguments.push(last.to_string());
+                    }
+                    return Some(arguments);
                 }
+                current.push(ch);
             },
-            ',' if depth == 1 => commas += 1,
-            c if depth == 1 && !c.is_whitespace() => {
-                saw_any_argument_token = true;
+            ',' if depth == 1 => {
+                arguments.push(current.trim().to_string());
+                current.clear();
             },
-            _ => {},
+            c => current.push(c),
         }
     }
 
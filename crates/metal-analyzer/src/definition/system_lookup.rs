@@ -278,7 +278,7 @@ fn find_matching_brace(
     None
 }
 
-fn byte_offset_to_position(
+pub(super) fn byte_offset_to_position(
     source: &str,
     byte_offset: usize,
 ) -> IdePosition {
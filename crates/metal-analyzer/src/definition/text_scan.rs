@@ -0,0 +1,37 @@
+//! Literal pre-filter for workspace-wide reference search.
+//!
+//! Building an AST index costs a compiler run, so before indexing files the
+//! project index lacks, their text is scanned for the identifier. Only files
+//! that spell it as a whole word can reference it.
+
+use std::path::{Path, PathBuf};
+
+/// Whether `text` contains `name` as a whole identifier.
+pub(crate) fn mentions_identifier(
+    text: &str,
+    name: &str,
+) -> bool {
+    if name.is_empty() {
+        return false;
+    }
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(name)
+        .any(|(start, _)| !text[..start].ends_with(is_word) && !text[start + name.len()..].starts_with(is_word))
+}
+
+/// The files among `files` whose contents mention `name`. Unreadable files
+/// are skipped.
+pub(crate) fn files_mentioning<'a>(
+    files: impl IntoIterator<Item = &'a Path>,
+    name: &str,
+) -> Vec<PathBuf> {
+    files
+        .into_iter()
+        .filter(|path| std::fs::read_to_string(path).is_ok_and(|text| mentions_identifier(&text, name)))
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/definition/text_scan_tests.rs"]
+mod tests;
@@ -1,8 +1,10 @@
 use std::path::Path;
 
 use crate::{
-    definition::symbol_def::SymbolDef,
+    definition::{interner::cached_path_key, symbol_def::SymbolDef},
     ide::navigation::{IdeLocation, IdePosition, IdeRange},
+    text_pos::column_in_file,
+    vfs::{CASE_INSENSITIVE_PATHS, fold_case},
 };
 
 /// Normalize a Clang type name by stripping qualifiers and pointers.
@@ -55,8 +57,9 @@ pub fn def_to_location(def: &SymbolDef) -> Option<IdeLocation> {
     }
 
     let line = def.line.saturating_sub(1);
-    let col = def.col.saturating_sub(1);
-    let end_col = col + def.name.len() as u32;
+    let byte_col = def.col.saturating_sub(1);
+    let col = column_in_file(&def.file, line, byte_col);
+    let end_col = column_in_file(&def.file, line, byte_col + def.name.len() as u32);
 
     Some(IdeLocation::new(
         Path::new(&def.file),
@@ -74,24 +77,28 @@ pub fn is_system_header(path: &str) -> bool {
         || path.is_empty()
 }
 
-/// Compare two file paths for equality, tolerating symlinks and the temp-file
+/// Compare two file paths for equality, tolerating symlinks, case
+/// differences on case-insensitive filesystems, and the temp-file
 /// indirection we use for the AST dump.
 pub fn paths_match(
     a: &str,
     b: &str,
 ) -> bool {
-    if a == b {
+    // Interned paths are usually the same allocation.
+    if std::ptr::eq(a, b) || a == b {
         return true;
     }
-    let pa = Path::new(a);
-    let pb = Path::new(b);
-    if let (Ok(ca), Ok(cb)) = (pa.canonicalize(), pb.canonicalize())
-        && ca == cb
-    {
+    if cached_path_key(a) == cached_path_key(b) {
         return true;
     }
+    let pa = Path::new(a);
+    let pb = Path::new(b);
     if let (Some(fa), Some(fb)) = (pa.file_name(), pb.file_name()) {
-        return fa == fb;
+        return if CASE_INSENSITIVE_PATHS {
+            fold_case(Path::new(fa)) == fold_case(Path::new(fb))
+        } else {
+            fa == fb
+        };
     }
     false
 }
@@ -0,0 +1,12 @@
+use tower_lsp::lsp_types::Url;
+
+use crate::{document::ContentHash, syntax::SyntaxTree};
+
+/// Document state resolved once for a request: the open text, its
+/// maintained content hash and its syntax tree.
+pub struct AnalysisSnapshot {
+    pub uri: Url,
+    pub text: String,
+    pub hash: ContentHash,
+    pub tree: SyntaxTree,
+}
@@ -1,7 +1,11 @@
 use dashmap::DashMap;
 use tower_lsp::lsp_types::{TextDocumentContentChangeEvent, Url};
 
-use crate::document::Document;
+use crate::{
+    document::{ContentHash, Document},
+    text_pos::{close_open_file, set_open_file},
+    vfs::virtual_docs::document_path,
+};
 
 /// Thread-safe store of all open documents.
 ///
@@ -26,7 +30,9 @@ impl DocumentStore {
         text: String,
         version: i32,
     ) {
-        self.documents.insert(uri.clone(), Document::new(uri, text, version));
+        let document = Document::new(uri.clone(), text, version);
+        sync_open_file(&document);
+        self.documents.insert(uri, document);
     }
 
     /// Replace the full content of an already-open document.
@@ -41,9 +47,10 @@ impl DocumentStore {
     ) {
         if let Some(mut doc) = self.documents.get_mut(&uri) {
             doc.set_content(text, version);
+            sync_open_file(&doc);
         } else {
             // Defensive: treat as open if not already tracked.
-            self.documents.insert(uri.clone(), Document::new(uri, text, version));
+            self.open(uri, text, version);
         }
     }
 
@@ -57,6 +64,7 @@ impl DocumentStore {
     ) {
         if let Some(mut doc) = self.documents.get_mut(uri) {
             doc.apply_changes(changes, version);
+            sync_open_file(&doc);
         }
     }
 
@@ -66,6 +74,9 @@ impl DocumentStore {
         uri: &Url,
     ) {
         self.documents.remove(uri);
+        if let Some(path) = document_path(uri) {
+            close_open_file(&path);
+        }
     }
 
     /// Return a clone of the full document text, if the URI is tracked.
@@ -76,6 +87,23 @@ impl DocumentStore {
         self.documents.get(uri).map(|r| r.value().text.clone())
     }
 
+    /// Return a clone of the document text and its maintained content hash,
+    /// so callers keying caches on the content need not rehash it.
+    pub fn get_content_with_hash(
+        &self,
+        uri: &Url,
+    ) -> Option<(String, ContentHash)> {
+        self.documents.get(uri).map(|r| (r.value().text.clone(), r.value().content_hash()))
+    }
+
+    /// The maintained content hash of the document, if the URI is tracked.
+    pub fn content_hash(
+        &self,
+        uri: &Url,
+    ) -> Option<ContentHash> {
+        self.documents.get(uri).map(|r| r.value().content_hash())
+    }
+
     /// Return a clone of the full `Document`, if the URI is tracked.
     #[allow(dead_code)]
     pub fn get(
@@ -85,6 +113,14 @@ impl DocumentStore {
         self.documents.get(uri).map(|r| r.value().clone())
     }
 
+    /// Whether the document at `uri` is open.
+    pub fn contains(
+        &self,
+        uri: &Url,
+    ) -> bool {
+        self.documents.contains_key(uri)
+    }
+
     /// Return all currently open document URIs.
     #[allow(dead_code)]
     pub fn all_uris(&self) -> Vec<Url> {
@@ -92,6 +128,14 @@ impl DocumentStore {
     }
 }
 
+/// Let compiler locations in the document's file be converted against its
+/// current text.
+fn sync_open_file(document: &Document) {
+    if let Some(path) = document_path(&document.uri) {
+        set_open_file(&path, &document.text);
+    }
+}
+
 impl Default for DocumentStore {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,167 @@
+//! Merkle tree over the line hashes of a document, with content-defined
+//! chunk boundaries.
+//!
+//! Each level groups the hashes of the level below into chunks and stores
+//! one hash per chunk, up to a single root. A chunk ends after an item whose
+//! hash picks it as a boundary, so where chunks fall depends only on the
+//! hashes themselves: the same lines give the same tree whichever edits led
+//! to them, and an edit only rehashes the chunks on its path to the root.
+
+use std::ops::Range;
+
+/// An item ends its chunk when the first byte of its hash is a multiple of
+/// this, giving chunks of this many items on average.
+const CHUNK_FANOUT: u8 = 16;
+/// Chunks hold at least two items, so every level is shorter than the one
+/// below and the tree always reaches a root.
+const MIN_CHUNK_LEN: usize = 2;
+/// Bounds the chunks of runs that never hit a boundary, such as many copies
+/// of one line.
+const MAX_CHUNK_LEN: usize = 64;
+
+#[derive(Debug, Clone)]
+pub(crate) struct HashTree {
+    /// `levels[0]` holds the leaf hashes and the last level the root alone.
+    levels: Vec<Vec<blake3::Hash>>,
+    /// Where each chunk of `levels[k]` starts, one entry per item of
+    /// `levels[k + 1]`.
+    chunk_starts: Vec<Vec<usize>>,
+}
+
+impl HashTree {
+    /// Build the tree over `leaves`, which must not be empty.
+    pub(crate) fn new(leaves: Vec<blake3::Hash>) -> Self {
+        debug_assert!(!leaves.is_empty());
+        let mut tree = Self {
+            levels: vec![leaves],
+            chunk_starts: Vec::new(),
+        };
+        tree.grow_from(0);
+        tree
+    }
+
+    pub(crate) fn root(&self) -> blake3::Hash {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    pub(crate) fn leaf_count(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Replace the leaves in `range` with `leaves`, leaving at least one.
+    pub(crate) fn splice(
+        &mut self,
+        range: Range<usize>,
+        leaves: Vec<blake3::Hash>,
+    ) {
+        debug_assert!(self.leaf_count() - range.len() + leaves.len() > 0);
+        self.splice_level(0, range, leaves);
+    }
+
+    fn splice_level(
+        &mut self,
+        level: usize,
+        range: Range<usize>,
+        items: Vec<blake3::Hash>,
+    ) {
+        if range.is_empty() && items.is_empty() {
+            return;
+        }
+        let delta = items.len() as isize - range.len() as isize;
+        let new_end = range.start + items.len();
+        self.levels[level].splice(range.clone(), items);
+
+        if level + 1 == self.levels.len() {
+            self.grow_from(level);
+            return;
+        }
+        if self.levels[level].len() == 1 {
+            self.levels.truncate(level + 1);
+            self.chunk_starts.truncate(level);
+            return;
+        }
+
+        // Rechunk from the chunk holding the item before the edit, whose end
+        // may move, until a chunk starts where an old one did after the edit.
+        let items = &self.levels[level];
+        let starts = &self.chunk_starts[level];
+        let first = starts.partition_point(|&start| start <= range.start.saturating_sub(1)) - 1;
+        let mut position = starts[first];
+        let mut new_starts = Vec::new();
+        let mut hashes = Vec::new();
+        let last = loop {
+            if position >= items.len() {
+                break starts.len();
+            }
+            if position >= new_end
+                && let Ok(old) = starts.binary_search(&position.wrapping_add_signed(-delta))
+            {
+                break old;
+            }
+            let end = chunk_end(items, position);
+            new_starts.push(position);
+            hashes.push(chunk_hash(&items[position..end]));
+            position = end;
+        };
+
+        let starts = &mut self.chunk_starts[level];
+        for start in &mut starts[last..] {
+            *start = start.wrapping_add_signed(delta);
+        }
+        starts.splice(first..last, new_starts);
+        self.splice_level(level + 1, first..last, hashes);
+    }
+
+    /// Chunk `levels[level]` and the levels it produces up to a single root,
+    /// replacing any levels above it.
+    fn grow_from(
+        &mut self,
+        level: usize,
+    ) {
+        self.levels.truncate(level + 1);
+        self.chunk_starts.truncate(level);
+        while self.levels[self.levels.len() - 1].len() > 1 {
+            let items = &self.levels[self.levels.len() - 1];
+            let mut starts = Vec::new();
+            let mut hashes = Vec::new();
+            let mut start = 0;
+            while start < items.len() {
+                let end = chunk_end(items, start);
+                starts.push(start);
+                hashes.push(chunk_hash(&items[start..end]));
+                start = end;
+            }
+            self.chunk_starts.push(starts);
+            self.levels.push(hashes);
+        }
+    }
+}
+
+/// End of the chunk of `items` that starts at `start`.
+fn chunk_end(
+    items: &[blake3::Hash],
+    start: usize,
+) -> usize {
+    let mut end = start;
+    while end < items.len() {
+        end += 1;
+        let len = end - start;
+        let boundary = len >= MIN_CHUNK_LEN && items[end - 1].as_bytes()[0].is_multiple_of(CHUNK_FANOUT);
+        if len >= MAX_CHUNK_LEN || boundary {
+            break;
+        }
+    }
+    end
+}
+
+fn chunk_hash(items: &[blake3::Hash]) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new_derive_key("metal-analyzer content hash chunk");
+    for item in items {
+        hasher.update(item.as_bytes());
+    }
+    hasher.finalize()
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/document/hash_tree_tests.rs"]
+mod tests;
@@ -1,5 +1,8 @@
+pub(crate) mod analysis_snapshot;
 pub(crate) mod document_store;
+pub(crate) mod hash_tree;
 pub(crate) mod text_document;
 
+pub use analysis_snapshot::AnalysisSnapshot;
 pub use document_store::DocumentStore;
-pub use text_document::Document;
+pub use text_document::{ContentHash, Document};
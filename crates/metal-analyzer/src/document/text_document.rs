@@ -1,5 +1,46 @@
+use std::fmt;
+
 use tower_lsp::lsp_types::*;
 
+use crate::{
+    document::hash_tree::HashTree,
+    text_pos::{byte_offset_of_column, char_width, text_width},
+};
+
+// ── ContentHash ─────────────────────────────────────────────────────────────
+
+/// BLAKE3 hash of a document's text, the root of a [`HashTree`] over its
+/// line hashes, so an open `Document` only rehashes the lines an edit touches
+/// and the tree nodes above them. Stable across builds, so it can key the
+/// on-disk index cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContentHash(blake3::Hash);
+
+impl ContentHash {
+    /// Hash `text` from scratch; equal to the hash an open `Document` with
+    /// the same text maintains incrementally.
+    pub fn of(text: &str) -> Self {
+        let line_offsets = Document::compute_line_offsets(text);
+        Self::of_tree(&HashTree::new(line_hashes(text, &line_offsets, 0..line_offsets.len())))
+    }
+
+    fn of_tree(tree: &HashTree) -> Self {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(tree.root().as_bytes());
+        hasher.update(&(tree.leaf_count() as u64).to_le_bytes());
+        Self(hasher.finalize())
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{}", self.0.to_hex())
+    }
+}
+
 // ── Document ────────────────────────────────────────────────────────────────
 
 /// Snapshot of a single open text document.
@@ -12,8 +53,11 @@ pub struct Document {
     pub text: String,
     /// Document version as reported by the client.
     pub version: i32,
-    /// Pre-computed line start byte offsets (rebuilt on every mutation).
+    /// Line start byte offsets, updated from the edited lines on mutation.
     line_offsets: Vec<usize>,
+    /// Tree over the hash of each line including its terminator, with one
+    /// leaf per entry of `line_offsets`.
+    line_hashes: HashTree,
 }
 
 impl Document {
@@ -23,16 +67,23 @@ impl Document {
         version: i32,
     ) -> Self {
         let line_offsets = Self::compute_line_offsets(&text);
+        let line_hashes = HashTree::new(line_hashes(&text, &line_offsets, 0..line_offsets.len()));
         Self {
             uri,
             text,
             version,
             line_offsets,
+            line_hashes,
         }
     }
 
     // ── queries ─────────────────────────────────────────────────────────
 
+    /// Hash of the current text, kept up to date by every mutation.
+    pub fn content_hash(&self) -> ContentHash {
+        ContentHash::of_tree(&self.line_hashes)
+    }
+
     /// Number of lines in the document.
     #[allow(dead_code)]
     pub fn line_count(&self) -> usize {
@@ -60,18 +111,7 @@ impl Document {
         let line_start = *self.line_offsets.get(line)?;
         let line_end = self.line_offsets.get(line + 1).copied().unwrap_or(self.text.len());
         let line_text = &self.text[line_start..line_end];
-
-        // LSP character offsets are UTF-16 code-unit counts.
-        let mut utf16_offset: u32 = 0;
-        let mut byte_offset = line_start;
-        for ch in line_text.chars() {
-            if utf16_offset >= pos.character {
-                break;
-            }
-            utf16_offset += ch.len_utf16() as u32;
-            byte_offset += ch.len_utf8();
-        }
-        Some(byte_offset)
+        Some(line_start + byte_offset_of_column(line_text, pos.character))
     }
 
     /// Convert a byte offset to an LSP `Position`.
@@ -86,7 +126,7 @@ impl Document {
             Err(ins) => ins.saturating_sub(1),
         };
         let line_start = self.line_offsets[line];
-        let character = self.text[line_start..offset].chars().map(|c| c.len_utf16() as u32).sum::<u32>();
+        let character = text_width(&self.text[line_start..offset]);
         Position {
             line: line as u32,
             character,
@@ -103,15 +143,15 @@ impl Document {
         let line_text = self.line_text(pos.line as usize)?;
         let chars: Vec<char> = line_text.chars().collect();
 
-        // Translate UTF-16 character offset to char index in the line.
+        // Translate the character offset to a char index in the line.
         let mut char_idx: usize = 0;
-        let mut utf16_count: u32 = 0;
+        let mut units: u32 = 0;
         for (i, &ch) in chars.iter().enumerate() {
-            if utf16_count >= pos.character {
+            if units >= pos.character {
                 char_idx = i;
                 break;
             }
-            utf16_count += ch.len_utf16() as u32;
+            units += char_width(ch);
             char_idx = i + 1;
         }
 
@@ -148,17 +188,17 @@ impl Document {
             return None;
         }
 
-        let start_utf16: u32 = chars[..start].iter().map(|c| c.len_utf16() as u32).sum();
-        let end_utf16: u32 = chars[..=end].iter().map(|c| c.len_utf16() as u32).sum();
+        let start_column: u32 = chars[..start].iter().map(|&c| char_width(c)).sum();
+        let end_column: u32 = chars[..=end].iter().map(|&c| char_width(c)).sum();
 
         let range = Range {
             start: Position {
                 line: pos.line,
-                character: start_utf16,
+                character: start_column,
             },
             end: Position {
                 line: pos.line,
-                character: end_utf16,
+                character: end_column,
             },
         };
 
@@ -175,7 +215,7 @@ impl Document {
     ) {
         self.text = text;
         self.version = version;
-        self.line_offsets = Self::compute_line_offsets(&self.text);
+        self.rebuild_lines();
     }
 
     /// Apply a list of incremental or full-content changes and bump version.
@@ -187,12 +227,11 @@ impl Document {
         for change in changes {
             if let Some(range) = change.range {
                 if let (Some(start), Some(end)) = (self.offset_of(range.start), self.offset_of(range.end)) {
-                    self.text.replace_range(start..end, &change.text);
-                    self.line_offsets = Self::compute_line_offsets(&self.text);
+                    self.replace_lines(start..end, &change.text);
                 }
             } else {
                 self.text = change.text;
-                self.line_offsets = Self::compute_line_offsets(&self.text);
+                self.rebuild_lines();
             }
         }
         self.version = version;
@@ -200,6 +239,44 @@ impl Document {
 
     // ── internal helpers ────────────────────────────────────────────────
 
+    fn rebuild_lines(&mut self) {
+        self.line_offsets = Self::compute_line_offsets(&self.text);
+        self.line_hashes = HashTree::new(line_hashes(&self.text, &self.line_offsets, 0..self.line_offsets.len()));
+    }
+
+    /// Replace `range` with `new_text`, rehashing only the lines it spans.
+    fn replace_lines(
+        &mut self,
+        range: std::ops::Range<usize>,
+        new_text: &str,
+    ) {
+        let first_line = self.line_of(range.start);
+        let last_line = self.line_of(range.end);
+        self.text.replace_range(range.clone(), new_text);
+
+        let delta = new_text.len() as isize - range.len() as isize;
+        let inserted = new_text.match_indices('\n').map(|(i, _)| range.start + i + 1);
+        let shifted = self.line_offsets[last_line + 1..].iter().map(|&offset| offset.wrapping_add_signed(delta));
+        let tail: Vec<usize> = inserted.chain(shifted).collect();
+        self.line_offsets.truncate(first_line + 1);
+        self.line_offsets.extend(tail);
+
+        let edited_lines = first_line..first_line + 1 + new_text.matches('\n').count();
+        let edited = line_hashes(&self.text, &self.line_offsets, edited_lines);
+        self.line_hashes.splice(first_line..last_line + 1, edited);
+    }
+
+    /// 0-based line containing byte `offset`.
+    fn line_of(
+        &self,
+        offset: usize,
+    ) -> usize {
+        match self.line_offsets.binary_search(&offset) {
+            Ok(exact) => exact,
+            Err(ins) => ins.saturating_sub(1),
+        }
+    }
+
     fn compute_line_offsets(text: &str) -> Vec<usize> {
         let mut offsets = vec![0usize];
         for (i, byte) in text.bytes().enumerate() {
@@ -213,6 +290,21 @@ impl Document {
 
 // ── helpers ─────────────────────────────────────────────────────────────────
 
+/// BLAKE3 hashes of `lines`, each spanning up to the next line start.
+fn line_hashes(
+    text: &str,
+    line_offsets: &[usize],
+    lines: std::ops::Range<usize>,
+) -> Vec<blake3::Hash> {
+    lines
+        .map(|line| {
+            let start = line_offsets[line];
+            let end = line_offsets.get(line + 1).copied().unwrap_or(text.len());
+            blake3::hash(&text.as_bytes()[start..end])
+        })
+        .collect()
+}
+
 #[allow(dead_code)]
 fn is_word_char(c: char) -> bool {
     c.is_alphanumeric() || c == '_'
@@ -4,6 +4,7 @@ use crate::{
     hover::builtins::make_hover_from_entry,
     metal::builtins,
     syntax::{cst::SyntaxNode, helpers},
+    text_pos::byte_offset_of_column,
 };
 
 pub(crate) fn try_attribute_hover_from_tree(
@@ -37,7 +38,7 @@ pub(crate) fn try_attribute_hover(
         return None;
     }
     let line = lines[line_idx];
-    let col = position.character as usize;
+    let col = byte_offset_of_column(line, position.character);
     let bytes = line.as_bytes();
 
     if bytes.is_empty() || col >= bytes.len() {
@@ -0,0 +1,154 @@
+//! Hover on an `#include` directive: the file it resolves to, the search
+//! root that found it, the files it shadows and what it declares.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use tower_lsp::lsp_types::{DocumentSymbol, Hover, HoverContents, MarkupContent, MarkupKind, SymbolKind};
+
+use crate::{
+    definition::include_graph::{IncludeDirective, include_directives},
+    ide::{
+        include_guards::{IncludeGuard, include_guard},
+        macros::macro_definitions,
+    },
+    metal::compiler::FRAMEWORK_DIR_PREFIX,
+    server::header_owners::IncludeRoot,
+    symbols::scanner::{build_symbols, file_level_symbols},
+    syntax::SyntaxTree,
+};
+
+/// Declarations named before the rest are summarized as a count.
+const MAX_PROVIDED: usize = 10;
+
+/// Build the hover for `directive` in `owner`. `candidates` are the files
+/// the name resolves to in search order, as from
+/// [`include_candidates`](crate::server::header_owners::include_candidates);
+/// `header_source` is the text of the first.
+pub(crate) fn make_include_hover(
+    owner: &Path,
+    directive: &IncludeDirective,
+    candidates: &[(PathBuf, IncludeRoot)],
+    include_paths: &[String],
+    header_source: Option<&str>,
+) -> Hover {
+    let mut seen = HashSet::new();
+    let mut candidates = candidates.iter().filter(|(path, _)| seen.insert(path));
+    let md = match candidates.next() {
+        Some((path, root)) => {
+            let mut md = format!("**`{}`**\n\n{}\n", path.display(), found_through(root));
+            let shadowed: Vec<_> = candidates.collect();
+            if !shadowed.is_empty() {
+                md.push_str("\nShadows:\n");
+                for (path, root) in shadowed {
+                    md.push_str(&format!("- `{}` ({})\n", path.display(), root_label(root)));
+                }
+            }
+            if let Some(summary) = header_source.and_then(provided_summary) {
+                md.push_str(&format!("\n---\n\n{summary}\n"));
+            }
+            md
+        },
+        None => {
+            let mut md = format!("`{}` was not found. Searched:\n", directive.name);
+            if let Some(dir) = owner.parent().filter(|_| !directive.angled) {
+                md.push_str(&format!("- `{}` (including file's directory)\n", dir.display()));
+            }
+            for dir in include_paths {
+                match dir.strip_prefix(FRAMEWORK_DIR_PREFIX) {
+                    Some(framework_root) => md.push_str(&format!("- `{framework_root}` (frameworks)\n")),
+                    None => md.push_str(&format!("- `{dir}`\n")),
+                }
+            }
+            md
+        },
+    };
+    Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: md,
+        }),
+        range: Some(directive.range),
+    }
+}
+
+fn found_through(root: &IncludeRoot) -> String {
+    match root {
+        IncludeRoot::Absolute => "Included by absolute path.".to_string(),
+        IncludeRoot::IncludingDirectory(dir) => format!("Found next to the including file, in `{}`.", dir.display()),
+        IncludeRoot::SearchPath(dir) => format!("Found through the include path `{}`.", dir.display()),
+        IncludeRoot::Framework(dir) => format!("Found in the framework directory `{}`.", dir.display()),
+    }
+}
+
+fn root_label(root: &IncludeRoot) -> String {
+    match root {
+        IncludeRoot::Absolute => "absolute path".to_string(),
+        IncludeRoot::IncludingDirectory(_) => "including file's directory".to_string(),
+        IncludeRoot::SearchPath(dir) => format!("include path `{}`", dir.display()),
+        IncludeRoot::Framework(dir) => format!("framework directory `{}`", dir.display()),
+    }
+}
+
+/// The file-level declarations of `source`, or the number of headers it
+/// includes when it declares nothing itself.
+fn provided_summary(source: &str) -> Option<String> {
+    let tree = SyntaxTree::parse(source);
+    let symbols = build_symbols(&tree.root(), source);
+    let guard = match include_guard(source) {
+        Some(IncludeGuard::Macro {
+            name,
+            ..
+        }) => Some(name),
+        _ => None,
+    };
+    // Macros come from the directives themselves rather than the outline.
+    let mut provided: Vec<(u32, String)> = macro_definitions(source, None)
+        .into_iter()
+        .filter(|definition| guard.as_deref() != Some(definition.name.as_str()))
+        .map(|definition| (definition.line, format!("`{}` (macro)", definition.name)))
+        .chain(file_level_symbols(&symbols).into_iter().filter_map(|symbol| {
+            Some((symbol.selection_range.start.line, format!("`{}` ({})", symbol.name, kind_label(symbol)?)))
+        }))
+        .collect();
+    provided.sort_by_key(|(line, _)| *line);
+    let provided: Vec<String> = provided.into_iter().map(|(_, label)| label).collect();
+    if provided.is_empty() {
+        let includes = include_directives(source).len();
+        return (includes > 0).then(|| {
+            let plural = if includes == 1 {
+                ""
+            } else {
+                "s"
+            };
+            format!("Declares nothing itself; includes {includes} header{plural}.")
+        });
+    }
+    let mut summary = format!("Provides {}", provided[..provided.len().min(MAX_PROVIDED)].join(", "));
+    if provided.len() > MAX_PROVIDED {
+        summary.push_str(&format!(" and {} more", provided.len() - MAX_PROVIDED));
+    }
+    summary.push('.');
+    Some(summary)
+}
+
+/// How a declaration is described, or `None` for what the header does not
+/// provide by name, such as namespaces and template parameters. Macros are
+/// listed separately.
+fn kind_label(symbol: &DocumentSymbol) -> Option<&'static str> {
+    match symbol.kind {
+        SymbolKind::STRUCT => Some("struct"),
+        SymbolKind::CLASS => Some("class"),
+        SymbolKind::ENUM => Some("enum"),
+        SymbolKind::FUNCTION => Some("function"),
+        SymbolKind::VARIABLE => Some("variable"),
+        SymbolKind::TYPE_PARAMETER if symbol.detail.as_deref() != Some("template param") => Some("type alias"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/hover/include_tests.rs"]
+mod tests;
@@ -0,0 +1,34 @@
+use tower_lsp::lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind};
+
+use crate::ide::macros::{MacroDefinition, MacroExpansion, MacroInvocation};
+
+/// Build a `Hover` showing a macro's definition and the expansions of `invocation`.
+pub(crate) fn make_macro_hover(
+    definition: &MacroDefinition,
+    invocation: &MacroInvocation,
+    expansion: Option<&MacroExpansion>,
+) -> Hover {
+    let mut md = format!("```metal\n{}\n```\n", definition.signature());
+
+    if let Some(expansion) = expansion {
+        md.push_str(&format!("\nExpands to:\n```metal\n{}\n```\n", expansion.single_step));
+        if expansion.full != expansion.single_step {
+            md.push_str(&format!("\nFully expanded:\n```metal\n{}\n```\n", expansion.full));
+        }
+    }
+
+    match definition.file.as_deref().and_then(|file| file.file_name()) {
+        Some(filename) => {
+            md.push_str(&format!("\n*Defined in `{}:{}`*\n", filename.to_string_lossy(), definition.line + 1));
+        },
+        None => md.push_str("\n*Defined by a compiler flag*\n"),
+    }
+
+    Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: md,
+        }),
+        range: Some(invocation.range),
+    }
+}
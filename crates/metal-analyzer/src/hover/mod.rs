@@ -1,6 +1,11 @@
 pub(crate) mod attribute;
 pub(crate) mod builtins;
+pub(crate) mod include;
+pub(crate) mod macro_expansion;
+pub(crate) mod number;
+pub(crate) mod overloads;
 pub(crate) mod provider;
+pub(crate) mod swizzle;
 pub(crate) mod user_symbol;
 
 pub use self::provider::HoverProvider;
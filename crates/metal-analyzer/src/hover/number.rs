@@ -0,0 +1,145 @@
+//! Hover on a numeric literal: an integer in decimal, hex and binary, and a
+//! floating-point literal as the `float` or `half` it is stored as, with its
+//! IEEE bit pattern.
+
+use half::f16;
+use tower_lsp::lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind};
+
+use crate::syntax::kind::SyntaxKind;
+
+/// Build the hover for the literal token `literal` of kind `kind`.
+pub(crate) fn make_number_hover(
+    kind: SyntaxKind,
+    literal: &str,
+) -> Option<Hover> {
+    let md = match kind {
+        SyntaxKind::Integer => integer_markdown(literal)?,
+        SyntaxKind::Float => float_markdown(literal)?,
+        _ => return None,
+    };
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: md,
+        }),
+        range: None,
+    })
+}
+
+fn integer_markdown(literal: &str) -> Option<String> {
+    let digits = literal.replace('_', "");
+    let suffix_start = digits.find(['u', 'U', 'l', 'L']).unwrap_or(digits.len());
+    let (digits, suffix) = digits.split_at(suffix_start);
+    let (radix, digits) = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        (16, hex)
+    } else if let Some(binary) = digits.strip_prefix("0b").or_else(|| digits.strip_prefix("0B")) {
+        (2, binary)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        (8, &digits[1..])
+    } else {
+        (10, digits)
+    };
+    let Ok(value) = u64::from_str_radix(digits, radix) else {
+        return Some("**Integer literal**\n\nToo large for `ulong`.\n".to_string());
+    };
+
+    let mut md = match integer_type(value, radix, suffix) {
+        Some(ty) => format!("**Integer literal** · `{ty}`\n\n"),
+        None => "**Integer literal**\n\n".to_string(),
+    };
+    md.push_str(&format!("- Decimal: `{value}`\n"));
+    md.push_str(&format!("- Hex: `0x{value:X}`\n"));
+    if radix == 8 {
+        md.push_str(&format!("- Octal: `0{value:o}`\n"));
+    }
+    md.push_str(&format!("- Binary: `0b{}`\n", grouped_binary(value)));
+    if value > i32::MAX as u64 && value <= u32::MAX as u64 {
+        md.push_str(&format!("- As `int`: `{}`\n", value as u32 as i32));
+    } else if value > i64::MAX as u64 {
+        md.push_str(&format!("- As `long`: `{}`\n", value as i64));
+    }
+    Some(md)
+}
+
+/// The type C++ gives an integer literal of `value` written in `radix` with
+/// `suffix`: the first of its candidate types the value fits in.
+fn integer_type(
+    value: u64,
+    radix: u32,
+    suffix: &str,
+) -> Option<&'static str> {
+    let unsigned = suffix.contains(['u', 'U']);
+    let long = suffix.contains(['l', 'L']);
+    let candidates: &[&'static str] = match (unsigned, long, radix == 10) {
+        (true, false, _) => &["uint", "ulong"],
+        (true, true, _) => &["ulong"],
+        (false, false, true) => &["int", "long"],
+        (false, false, false) => &["int", "uint", "long", "ulong"],
+        (false, true, true) => &["long"],
+        (false, true, false) => &["long", "ulong"],
+    };
+    candidates.iter().copied().find(|ty| {
+        let max = match *ty {
+            "int" => i32::MAX as u64,
+            "uint" => u32::MAX as u64,
+            "long" => i64::MAX as u64,
+            _ => u64::MAX,
+        };
+        value <= max
+    })
+}
+
+/// `value` in binary, in groups of four digits separated by `'`.
+fn grouped_binary(value: u64) -> String {
+    let digits = format!("{value:b}");
+    let padded = format!("{}{digits}", "0".repeat((4 - digits.len() % 4) % 4));
+    padded
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("'")
+}
+
+fn float_markdown(literal: &str) -> Option<String> {
+    let text = literal.replace('_', "");
+    let number = text.trim_end_matches(['f', 'F', 'h', 'H', 'l', 'L']);
+    let value: f64 = number.parse().ok()?;
+    let is_half = text[number.len()..].contains(['h', 'H']);
+
+    let (ty, stored, bits, pattern) = if is_half {
+        let half = f16::from_f64(value);
+        let bits = half.to_bits();
+        ("half", half.to_f64(), format!("0x{bits:04X}"), bit_fields(u64::from(bits), 5, 10))
+    } else {
+        let float = value as f32;
+        let bits = float.to_bits();
+        ("float", f64::from(float), format!("0x{bits:08X}"), bit_fields(u64::from(bits), 8, 23))
+    };
+
+    let mut md = format!("**Floating-point literal** · `{ty}`\n\n");
+    if stored == value {
+        md.push_str(&format!("- Value: `{stored}` (exact)\n"));
+    } else {
+        md.push_str(&format!("- Value: `{value}`\n"));
+        md.push_str(&format!("- Nearest `{ty}`: `{stored}`\n"));
+    }
+    md.push_str(&format!("- Bits: `{bits}`\n"));
+    md.push_str(&format!("- Sign, exponent, mantissa: `{pattern}`\n"));
+    Some(md)
+}
+
+/// The sign, exponent and mantissa fields of the IEEE value `bits`,
+/// separated by spaces.
+fn bit_fields(
+    bits: u64,
+    exponent_bits: usize,
+    mantissa_bits: usize,
+) -> String {
+    let all = format!("{bits:0width$b}", width = 1 + exponent_bits + mantissa_bits);
+    format!("{} {} {}", &all[..1], &all[1..1 + exponent_bits], &all[1 + exponent_bits..])
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/hover/number_tests.rs"]
+mod tests;
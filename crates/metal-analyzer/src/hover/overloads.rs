@@ -0,0 +1,65 @@
+use tower_lsp::lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind};
+
+use crate::definition::SymbolDef;
+
+/// The distinct signatures among the function `defs`, in order, each
+/// represented by its definition when one was indexed.
+pub(crate) fn distinct_overloads(defs: Vec<SymbolDef>) -> Vec<SymbolDef> {
+    let mut overloads: Vec<SymbolDef> = Vec::new();
+    for def in defs {
+        if !matches!(def.kind.as_str(), "FunctionDecl" | "CXXMethodDecl") || def.qual_type.is_none() {
+            continue;
+        }
+        match overloads.iter_mut().find(|overload| overload.qual_type == def.qual_type) {
+            Some(existing) if def.is_definition && !existing.is_definition => *existing = def,
+            Some(_) => {},
+            None => overloads.push(def),
+        }
+    }
+    overloads
+}
+
+/// `float dot3(float3, float3)` for a function with the Clang type
+/// `float (float3, float3)`.
+pub(crate) fn overload_signature(def: &SymbolDef) -> String {
+    let qual_type = def.qual_type.as_deref().unwrap_or_default();
+    match qual_type.find('(') {
+        Some(paren) => format!("{} {}{}", qual_type[..paren].trim(), def.name, &qual_type[paren..]),
+        None => format!("{} {}", qual_type, def.name),
+    }
+}
+
+/// Hover listing every overload with its signature and defining file. The
+/// overload at `best`, the one the call at the cursor resolves to, leads the
+/// hover and is marked in the list.
+pub(crate) fn make_overload_hover(
+    overloads: &[SymbolDef],
+    best: Option<usize>,
+) -> Hover {
+    let lead = &overloads[best.unwrap_or(0)];
+    let mut md = format!("```metal\n{}\n```\n\n**{} overloads**\n\n", overload_signature(lead), overloads.len());
+    for (i, overload) in overloads.iter().enumerate() {
+        let filename = std::path::Path::new(&overload.file)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let signature = overload_signature(overload);
+        if Some(i) == best {
+            md.push_str(&format!("- **`{signature}`** — `{filename}:{}` *(this call)*\n", overload.line));
+        } else {
+            md.push_str(&format!("- `{signature}` — `{filename}:{}`\n", overload.line));
+        }
+    }
+
+    Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: md,
+        }),
+        range: None,
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/hover/overloads_tests.rs"]
+mod tests;
@@ -3,10 +3,16 @@ use std::sync::Arc;
 use tower_lsp::lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind, Position, Url};
 
 use crate::{
-    definition::DefinitionProvider,
+    definition::{
+        DefinitionProvider, SymbolDef,
+        symbol_rank::{disambiguate_overload_tie, infer_local_identifier_type_name},
+    },
     hover::{
         attribute::{try_attribute_hover, try_attribute_hover_from_tree},
         builtins::make_hover_from_entry,
+        number::make_number_hover,
+        overloads::{distinct_overloads, make_overload_hover},
+        swizzle::make_swizzle_hover,
         user_symbol::make_hover_from_user_symbol,
     },
     metal::builtins,
@@ -41,6 +47,25 @@ impl HoverProvider {
     ) -> Option<Hover> {
         let (attr_hover, word) = {
             let root = snapshot.map(|s| s.root());
+            if let Some(token) = root.as_ref().and_then(|t| helpers::token_at_position(t, text, position))
+                && let Some(hover) = make_number_hover(token.kind(), helpers::token_text(&token, text))
+            {
+                return Some(hover);
+            }
+            let infer = |name: &str| {
+                let index = self.definition_provider.get_cached_index(uri)?;
+                let path = uri.to_file_path().ok()?;
+                infer_local_identifier_type_name(
+                    &index,
+                    &path.to_string_lossy(),
+                    position.line + 1,
+                    position.character + 1,
+                    name,
+                )
+            };
+            if let Some(hover) = root.as_ref().and_then(|t| make_swizzle_hover(t, text, position, &infer)) {
+                return Some(hover);
+            }
             let attr_hover = root
                 .as_ref()
                 .and_then(|t| try_attribute_hover_from_tree(t, text, position))
@@ -75,6 +100,9 @@ impl HoverProvider {
         }
 
         // AST-based hover: check per-file cache and project index.
+        if let Some(hover) = self.overload_hover(uri, text, position, &word) {
+            return Some(hover);
+        }
         if let Some(hover) = self.hover_from_ast(uri, &word) {
             return Some(hover);
         }
@@ -93,6 +121,41 @@ impl HoverProvider {
         None
     }
 
+    /// List the overloads of `word` when the AST and project indices hold
+    /// functions of that name with more than one signature, marking the one
+    /// the call at `position` resolves to.
+    fn overload_hover(
+        &self,
+        uri: &Url,
+        text: &str,
+        position: Position,
+        word: &str,
+    ) -> Option<Hover> {
+        let index = self.definition_provider.get_cached_index(uri);
+        let mut defs: Vec<SymbolDef> = index
+            .as_ref()
+            .and_then(|index| index.name_to_defs.get(word).map(|indices| (index, indices)))
+            .map(|(index, indices)| indices.iter().map(|&i| index.defs[i].clone()).collect())
+            .unwrap_or_default();
+        let path = uri.to_file_path().ok();
+        let project_index = self.definition_provider.project_index();
+        defs.extend(match &path {
+            Some(path) => project_index.find_definitions_from(word, path),
+            None => project_index.find_definitions(word),
+        });
+        let overloads = distinct_overloads(defs);
+        if overloads.len() < 2 {
+            return None;
+        }
+
+        let best = index.as_ref().zip(path.as_ref()).and_then(|(index, path)| {
+            let candidates: Vec<&SymbolDef> = overloads.iter().collect();
+            let best = disambiguate_overload_tie(index, &candidates, &path.to_string_lossy(), text, position, word)?;
+            overloads.iter().position(|overload| overload.qual_type == best.qual_type)
+        });
+        Some(make_overload_hover(&overloads, best))
+    }
+
     /// Build hover from AST index data (per-file cache or project index).
     fn hover_from_ast(
         &self,
@@ -112,7 +175,11 @@ impl HoverProvider {
         }
 
         // Fall back to project index.
-        let defs = self.definition_provider.project_index().find_definitions(word);
+        let project_index = self.definition_provider.project_index();
+        let defs = match uri.to_file_path() {
+            Ok(path) => project_index.find_definitions_from(word, &path),
+            Err(()) => project_index.find_definitions(word),
+        };
         for def in &defs {
             if let Some(hover) = format_symbol_hover(def) {
                 return Some(hover);
@@ -0,0 +1,361 @@
+//! Hover on a vector swizzle such as `v.zyx` or `color.rgba`: the vector it
+//! reads from, the type it yields and which source component lands where.
+//!
+//! Member expressions are flat in the CST, so the receiver is found by
+//! walking tokens back from the swizzle (`p.c.bgra`, `out[0].xy`,
+//! `float4(...).zw`) and its type by following the declaration of the first
+//! identifier: a parameter or local of the enclosing function, a global, and
+//! then the fields of structs declared in the document. Receivers declared
+//! `auto` or elsewhere are typed by `infer`, from the AST index.
+
+use rowan::TextSize;
+use tower_lsp::lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind, Position};
+
+use crate::syntax::{
+    cst::{SyntaxNode, SyntaxToken},
+    helpers,
+    kind::SyntaxKind,
+};
+
+const SCALAR_TYPES: [&str; 12] =
+    ["float", "half", "bfloat", "int", "uint", "short", "ushort", "char", "uchar", "long", "ulong", "bool"];
+
+/// Component names, by position.
+const COMPONENT_SETS: [&str; 2] = ["xyzw", "rgba"];
+
+/// A declared type: its name and how many pointer or array levels wrap it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DeclaredType {
+    name: String,
+    indirection: usize,
+}
+
+/// One step from the receiver's first identifier to the swizzled vector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Member(String),
+    Arrow(String),
+    Subscript,
+}
+
+/// Build the hover for the swizzle at `position`. `infer` types an
+/// identifier the document does not declare with a usable type.
+pub(crate) fn make_swizzle_hover(
+    root: &SyntaxNode,
+    source: &str,
+    position: Position,
+    infer: &dyn Fn(&str) -> Option<String>,
+) -> Option<Hover> {
+    let token = helpers::token_at_position(root, source, position)?;
+    if token.kind() != SyntaxKind::Ident || previous_significant(&token)?.kind() != SyntaxKind::Dot {
+        return None;
+    }
+    let swizzle = token.text().to_string();
+    let receiver = receiver_type(root, &token, infer)?;
+    let (scalar, size) = vector_type(&receiver.name).filter(|_| receiver.indirection == 0)?;
+    let indices = swizzle_indices(&swizzle, size)?;
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: swizzle_markdown(&swizzle, &receiver.name, scalar, &indices),
+        }),
+        range: None,
+    })
+}
+
+fn swizzle_markdown(
+    swizzle: &str,
+    receiver: &str,
+    scalar: &str,
+    indices: &[usize],
+) -> String {
+    let names =
+        COMPONENT_SETS.iter().find(|names| swizzle.starts_with(|c| names.contains(c))).unwrap_or(&COMPONENT_SETS[0]);
+    let component = |index: usize| &names[index..=index];
+    let result = vector_name(scalar, indices.len());
+    let mut md = format!("**Swizzle** `.{swizzle}` of `{receiver}` → `{result}`\n\n");
+
+    let count = indices.len();
+    let in_order = indices.iter().enumerate().all(|(position, index)| position == *index);
+    let reversed = indices.iter().enumerate().all(|(position, index)| count - 1 - position == *index);
+    if count == 1 {
+        md.push_str(&format!("Component `{}` (index {}).\n", component(indices[0]), indices[0]));
+        return md;
+    }
+    if in_order {
+        md.push_str(&format!("The first {} components in order.\n\n", number_word(count)));
+    } else if reversed {
+        md.push_str(&format!("The first {} components in reverse order.\n\n", number_word(count)));
+    } else if indices.iter().all(|index| *index == indices[0]) {
+        md.push_str(&format!("Component `{}` repeated {} times.\n\n", component(indices[0]), number_word(count)));
+    }
+    for (position, index) in indices.iter().enumerate() {
+        md.push_str(&format!("- `{}` ← `{}`\n", component(position), component(*index)));
+    }
+    if indices.iter().enumerate().any(|(position, index)| indices[..position].contains(index)) {
+        md.push_str("\nRepeats a component, so it cannot be assigned to.\n");
+    }
+    md
+}
+
+fn number_word(count: usize) -> &'static str {
+    match count {
+        2 => "two",
+        3 => "three",
+        _ => "four",
+    }
+}
+
+/// The source component of each component of `swizzle` on a vector of
+/// `size`; `None` when it mixes `xyzw` with `rgba` or reads past the end.
+fn swizzle_indices(
+    swizzle: &str,
+    size: usize,
+) -> Option<Vec<usize>> {
+    if swizzle.is_empty() || swizzle.len() > 4 {
+        return None;
+    }
+    let names = COMPONENT_SETS.iter().find(|names| swizzle.chars().all(|c| names.contains(c)))?;
+    swizzle.chars().map(|c| names.find(c).filter(|index| *index < size)).collect()
+}
+
+/// The scalar and size of a vector type such as `float4` or `packed_half3`.
+fn vector_type(name: &str) -> Option<(&'static str, usize)> {
+    let name = name.strip_prefix("metal::").unwrap_or(name);
+    let name = name.strip_prefix("packed_").unwrap_or(name);
+    let (scalar, size) = name.split_at(name.len().checked_sub(1)?);
+    let size = size.parse().ok().filter(|size| (2..=4).contains(size))?;
+    Some((SCALAR_TYPES.iter().copied().find(|candidate| *candidate == scalar)?, size))
+}
+
+fn vector_name(
+    scalar: &str,
+    size: usize,
+) -> String {
+    if size == 1 {
+        scalar.to_string()
+    } else {
+        format!("{scalar}{size}")
+    }
+}
+
+/// The type of the expression the `.` before `swizzle` applies to.
+fn receiver_type(
+    root: &SyntaxNode,
+    swizzle: &SyntaxToken,
+    infer: &dyn Fn(&str) -> Option<String>,
+) -> Option<DeclaredType> {
+    let mut steps = Vec::new();
+    let mut token = previous_significant(&previous_significant(swizzle)?)?;
+    let mut ty = loop {
+        match token.kind() {
+            SyntaxKind::RBracket => {
+                token = matching_open(&token, SyntaxKind::LBracket, SyntaxKind::RBracket)?;
+                steps.push(Step::Subscript);
+                token = previous_significant(&token)?;
+            },
+            SyntaxKind::RParen => {
+                // Only vector constructors such as `float4(...)`.
+                let open = matching_open(&token, SyntaxKind::LParen, SyntaxKind::RParen)?;
+                let name = previous_significant(&open)?;
+                vector_type(name.text())?;
+                break DeclaredType {
+                    name: name.text().to_string(),
+                    indirection: 0,
+                };
+            },
+            SyntaxKind::Ident => {
+                let name = token.text().to_string();
+                match previous_significant(&token).map(|before| before.kind()) {
+                    Some(SyntaxKind::Dot) => steps.push(Step::Member(name)),
+                    Some(SyntaxKind::Arrow) => steps.push(Step::Arrow(name)),
+                    _ => break identifier_type(root, &token, infer)?,
+                }
+                token = previous_significant(&previous_significant(&token)?)?;
+            },
+            _ => return None,
+        }
+    };
+
+    for step in steps.into_iter().rev() {
+        ty = match step {
+            Step::Subscript if ty.indirection > 0 => DeclaredType {
+                name: ty.name,
+                indirection: ty.indirection - 1,
+            },
+            Step::Subscript => {
+                let (scalar, _) = vector_type(&ty.name)?;
+                DeclaredType {
+                    name: scalar.to_string(),
+                    indirection: 0,
+                }
+            },
+            Step::Member(member) if ty.indirection == 0 => member_type(root, &ty.name, &member)?,
+            Step::Arrow(member) if ty.indirection == 1 => member_type(root, &ty.name, &member)?,
+            Step::Member(_) | Step::Arrow(_) => return None,
+        };
+    }
+    Some(ty)
+}
+
+/// The type of `.member` on a value of type `name`: a swizzle of a vector,
+/// or a field of a struct declared in the document.
+fn member_type(
+    root: &SyntaxNode,
+    name: &str,
+    member: &str,
+) -> Option<DeclaredType> {
+    if let Some((scalar, size)) = vector_type(name) {
+        let indices = swizzle_indices(member, size)?;
+        return Some(DeclaredType {
+            name: vector_name(scalar, indices.len()),
+            indirection: 0,
+        });
+    }
+    root.descendants()
+        .filter(|node| node.kind() == SyntaxKind::StructDef)
+        .filter(|node| {
+            node.children_with_tokens().filter_map(|child| child.into_token()).any(|token| token.text() == name)
+        })
+        .flat_map(|node| node.descendants().filter(|node| node.kind() == SyntaxKind::FieldDef))
+        .find_map(|field| declared_in(&field, member))
+}
+
+/// The declared type of the identifier `token` names: the nearest earlier
+/// declaration in the enclosing function, a parameter, a global, or `infer`.
+fn identifier_type(
+    root: &SyntaxNode,
+    token: &SyntaxToken,
+    infer: &dyn Fn(&str) -> Option<String>,
+) -> Option<DeclaredType> {
+    let name = token.text();
+    let offset = token.text_range().start();
+    let function = token.parent_ancestors().find(|node| node.kind() == SyntaxKind::FunctionDef);
+    let local = function.as_ref().and_then(|function| {
+        let mut declarations: Vec<SyntaxNode> = function
+            .descendants()
+            .filter(|node| node.kind() == SyntaxKind::DeclStmt && node.text_range().start() < offset)
+            .collect();
+        declarations.reverse();
+        declarations.extend(function.descendants().filter(|node| node.kind() == SyntaxKind::Parameter));
+        declarations.iter().find_map(|declaration| declared_before(declaration, name, offset))
+    });
+    let global = || {
+        root.children()
+            .filter(|node| node.kind() == SyntaxKind::VariableDef)
+            .find_map(|declaration| declared_before(&declaration, name, offset))
+    };
+    local.or_else(global).filter(|ty| ty.name != "auto").or_else(|| infer(name).map(|name| parse_type_name(&name)))
+}
+
+fn declared_before(
+    declaration: &SyntaxNode,
+    name: &str,
+    offset: TextSize,
+) -> Option<DeclaredType> {
+    declared_in(declaration, name).filter(|_| declaration.text_range().start() < offset)
+}
+
+/// The type `declaration` (a parameter, field, or variable declaration)
+/// gives `name`, if it declares it.
+fn declared_in(
+    declaration: &SyntaxNode,
+    name: &str,
+) -> Option<DeclaredType> {
+    let type_ref = declaration.children().find(|node| node.kind() == SyntaxKind::TypeRef)?;
+    let mut ty = type_ref_type(&type_ref)?;
+
+    // Declarators follow the type, separated by top-level commas.
+    let mut depth = 0usize;
+    let mut expects_name = true;
+    let mut stars = 0;
+    let mut tokens = declaration
+        .children_with_tokens()
+        .filter_map(|child| child.into_token())
+        .filter(|token| token.text_range().start() >= type_ref.text_range().end())
+        .peekable();
+    while let Some(token) = tokens.next() {
+        match token.kind() {
+            SyntaxKind::LParen | SyntaxKind::LBracket | SyntaxKind::LBrace => depth += 1,
+            SyntaxKind::RParen | SyntaxKind::RBracket | SyntaxKind::RBrace => depth = depth.saturating_sub(1),
+            SyntaxKind::Comma if depth == 0 => {
+                expects_name = true;
+                stars = 0;
+            },
+            SyntaxKind::Star if depth == 0 && expects_name => stars += 1,
+            SyntaxKind::Ident if depth == 0 && expects_name => {
+                expects_name = false;
+                if token.text() != name {
+                    continue;
+                }
+                let mut dimensions = 0;
+                while tokens.peek().is_some_and(|token| token.kind() == SyntaxKind::LBracket) {
+                    dimensions += 1;
+                    tokens.by_ref().find(|token| token.kind() == SyntaxKind::RBracket)?;
+                }
+                ty.indirection += stars + dimensions;
+                return Some(ty);
+            },
+            _ => {},
+        }
+    }
+    None
+}
+
+/// The type a `TypeRef` names, without address space and cv qualifiers.
+fn type_ref_type(type_ref: &SyntaxNode) -> Option<DeclaredType> {
+    let tokens: Vec<SyntaxToken> = type_ref.descendants_with_tokens().filter_map(|child| child.into_token()).collect();
+    let name = tokens.iter().rev().find(|token| {
+        token.kind() == SyntaxKind::Ident
+            || SCALAR_TYPES.contains(&token.text())
+            || matches!(token.kind(), SyntaxKind::KwAuto | SyntaxKind::KwVoid)
+    })?;
+    Some(DeclaredType {
+        name: name.text().to_string(),
+        indirection: tokens.iter().filter(|token| token.kind() == SyntaxKind::Star).count(),
+    })
+}
+
+/// A type name from the AST index, such as `const float4 &` or `float4 *`.
+fn parse_type_name(name: &str) -> DeclaredType {
+    let indirection = name.matches('*').count() + name.matches('[').count();
+    let base = name.split(['*', '&', '[']).next().unwrap_or(name);
+    let base = base.split_whitespace().last().unwrap_or(base);
+    DeclaredType {
+        name: base.to_string(),
+        indirection,
+    }
+}
+
+fn previous_significant(token: &SyntaxToken) -> Option<SyntaxToken> {
+    let mut token = token.prev_token()?;
+    while matches!(token.kind(), SyntaxKind::Whitespace | SyntaxKind::Comment) {
+        token = token.prev_token()?;
+    }
+    Some(token)
+}
+
+/// The `open` token matching the `close` token `token`.
+fn matching_open(
+    token: &SyntaxToken,
+    open: SyntaxKind,
+    close: SyntaxKind,
+) -> Option<SyntaxToken> {
+    let mut depth = 0usize;
+    let mut current = token.clone();
+    loop {
+        if current.kind() == close {
+            depth += 1;
+        } else if current.kind() == open {
+            depth -= 1;
+            if depth == 0 {
+                return Some(current);
+            }
+        }
+        current = current.prev_token()?;
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/hover/swizzle_tests.rs"]
+mod tests;
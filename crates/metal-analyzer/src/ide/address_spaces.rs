@@ -0,0 +1,839 @@
+//! Lints for address-space mistakes that the compiler reports with cryptic
+//! errors: pointer and reference parameters of entry points without an
+//! address space, pointers and references initialized or returned from
+//! memory in another address space, and writes to `constant` memory.
+//!
+//! The checks read the token stream and follow the declarations of each
+//! function in order, over its parameters and the program-scope variables
+//! declared with an address space. Names that do not resolve to such a
+//! declaration, and types deduced with `auto`, are skipped.
+
+use std::collections::{HashMap, HashSet};
+
+use rowan::TextRange;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+use crate::{
+    ide::syntax_diagnostics::significant_tokens,
+    syntax::{
+        cst::{SyntaxNode, SyntaxToken},
+        helpers,
+        kind::SyntaxKind,
+    },
+};
+
+pub const ADDRESS_SPACE_CODE: &str = "address-space";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AddressSpace {
+    Device,
+    Constant,
+    Threadgroup,
+    Thread,
+    ThreadgroupImageblock,
+    RayData,
+    ObjectData,
+}
+
+impl AddressSpace {
+    fn of(token: &SyntaxToken) -> Option<Self> {
+        match token.text() {
+            "device" => Some(Self::Device),
+            "constant" => Some(Self::Constant),
+            "threadgroup" => Some(Self::Threadgroup),
+            "thread" => Some(Self::Thread),
+            "threadgroup_imageblock" => Some(Self::ThreadgroupImageblock),
+            "ray_data" => Some(Self::RayData),
+            "object_data" => Some(Self::ObjectData),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Device => "device",
+            Self::Constant => "constant",
+            Self::Threadgroup => "threadgroup",
+            Self::Thread => "thread",
+            Self::ThreadgroupImageblock => "threadgroup_imageblock",
+            Self::RayData => "ray_data",
+            Self::ObjectData => "object_data",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Declarator {
+    Value {
+        array: bool,
+    },
+    Pointer,
+    Reference,
+}
+
+impl Declarator {
+    fn noun(self) -> &'static str {
+        match self {
+            Declarator::Pointer => "pointer",
+            Declarator::Reference => "reference",
+            Declarator::Value {
+                ..
+            } => "variable",
+        }
+    }
+
+    /// How a name declared this way relates to the memory it reaches.
+    fn verb(self) -> &'static str {
+        match self {
+            Declarator::Pointer => "points to",
+            Declarator::Reference => "refers to",
+            Declarator::Value {
+                ..
+            } => "is in",
+        }
+    }
+}
+
+/// One declarator of a declaration, e.g. `q` in `device float *p, *q;`.
+#[derive(Debug, Clone)]
+struct Declaration {
+    name: SyntaxToken,
+    /// The address space written in the type, if any.
+    space: Option<AddressSpace>,
+    declarator: Declarator,
+    /// Whether the type is `auto` or `decltype(...)`.
+    deduced: bool,
+    /// Index of the `=` starting the initializer, and the end of the
+    /// initializer.
+    initializer: Option<(usize, usize)>,
+}
+
+impl Declaration {
+    /// The address space of the memory the declaration reaches, with
+    /// `thread` as the default.
+    fn space(&self) -> AddressSpace {
+        self.space.unwrap_or(AddressSpace::Thread)
+    }
+
+    fn default_note(&self) -> &'static str {
+        if self.space.is_some() {
+            ""
+        } else {
+            " by default"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Brace {
+    Function,
+    Type,
+    Other,
+}
+
+/// The function whose body is being read.
+struct FunctionScope {
+    name: SyntaxToken,
+    returns: Option<Declaration>,
+    /// Parameters and locals declared so far, the latest declaration of a
+    /// name winning.
+    variables: HashMap<String, Declaration>,
+}
+
+/// Address-space warnings for `root`, parsed from `source`.
+pub fn address_space_diagnostics(
+    root: &SyntaxNode,
+    source: &str,
+) -> Vec<Diagnostic> {
+    let tokens: Vec<SyntaxToken> =
+        significant_tokens(root).0.into_iter().map(|significant| significant.token).collect();
+    let mut globals: HashMap<String, Declaration> = HashMap::new();
+    let mut braces: Vec<Brace> = Vec::new();
+    let mut function: Option<FunctionScope> = None;
+    let mut findings: Vec<(TextRange, String)> = Vec::new();
+    let mut statement_start = 0;
+    for index in 0..tokens.len() {
+        let kind = tokens[index].kind();
+        if !matches!(kind, SyntaxKind::Semicolon | SyntaxKind::LBrace | SyntaxKind::RBrace) {
+            continue;
+        }
+        if let Some(scope) = function.as_mut() {
+            function_statement(&tokens, statement_start, index, scope, &globals, &mut findings);
+        }
+        match kind {
+            SyntaxKind::LBrace if function.is_none() => {
+                if let Some(scope) = function_header(&tokens, statement_start, index, &mut findings) {
+                    function = Some(scope);
+                    braces.push(Brace::Function);
+                    statement_start = index + 1;
+                    continue;
+                }
+                record_globals(&tokens, statement_start, index, &braces, &mut globals);
+                let opens_type = tokens[statement_start..index].iter().any(|token| {
+                    matches!(token.kind(), SyntaxKind::KwStruct | SyntaxKind::KwClass | SyntaxKind::KwUnion)
+                });
+                braces.push(if opens_type {
+                    Brace::Type
+                } else {
+                    Brace::Other
+                });
+            },
+            SyntaxKind::LBrace => braces.push(Brace::Other),
+            SyntaxKind::RBrace => {
+                let closed = braces.pop();
+                if closed == Some(Brace::Function) {
+                    function = None;
+                }
+            },
+            _ if function.is_none() => record_globals(&tokens, statement_start, index, &braces, &mut globals),
+            _ => {},
+        }
+        statement_start = index + 1;
+    }
+
+    findings.sort_by_key(|(range, _)| range.start());
+    findings
+        .into_iter()
+        .map(|(range, message)| Diagnostic {
+            range: helpers::range_to_lsp(range, source),
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: Some(NumberOrString::String(ADDRESS_SPACE_CODE.to_string())),
+            source: Some("metal-analyzer".to_string()),
+            message,
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Record the program-scope variables declared with an address space by
+/// the statement `tokens[start..end]`.
+fn record_globals(
+    tokens: &[SyntaxToken],
+    start: usize,
+    end: usize,
+    braces: &[Brace],
+    globals: &mut HashMap<String, Declaration>,
+) {
+    if braces.contains(&Brace::Type) {
+        return;
+    }
+    for declaration in declarations(tokens, start, end) {
+        if declaration.space.is_some() {
+            globals.insert(declaration.name.text().to_string(), declaration);
+        }
+    }
+}
+
+/// The function whose body opens with the `{` at `brace`, its header
+/// starting at `start`. Entry point parameters without an address space
+/// are reported.
+fn function_header(
+    tokens: &[SyntaxToken],
+    start: usize,
+    brace: usize,
+    findings: &mut Vec<(TextRange, String)>,
+) -> Option<FunctionScope> {
+    let mut end = brace;
+    while end > start {
+        match tokens[end - 1].kind() {
+            SyntaxKind::RDoubleBracket => {
+                end = (start..end - 1).rev().find(|&i| tokens[i].kind() == SyntaxKind::LDoubleBracket)?;
+            },
+            SyntaxKind::KwConst | SyntaxKind::KwNoexcept => end -= 1,
+            _ => break,
+        }
+    }
+    let close = end.checked_sub(1).filter(|&close| close > start && tokens[close].kind() == SyntaxKind::RParen)?;
+    let open = matching_open(tokens, start, close)?;
+    let name_index = open.checked_sub(1).filter(|&name| name >= start && tokens[name].kind() == SyntaxKind::Ident)?;
+
+    // Skip access specifiers and constructor initializers before the name.
+    let mut header_start =
+        (start..name_index).rev().find(|&i| tokens[i].kind() == SyntaxKind::Colon).map_or(start, |colon| colon + 1);
+    if tokens[header_start].kind() == SyntaxKind::KwTemplate {
+        header_start = skip_template_arguments(tokens, header_start + 1, name_index);
+    }
+    let entry_point = tokens[header_start..name_index].iter().any(|token| {
+        matches!(
+            token.kind(),
+            SyntaxKind::KwKernel
+                | SyntaxKind::KwVertex
+                | SyntaxKind::KwFragment
+                | SyntaxKind::KwMesh
+                | SyntaxKind::KwObject
+        )
+    });
+    let returns = declarations(tokens, header_start, open).into_iter().next();
+
+    let mut variables = HashMap::new();
+    for (parameter_start, parameter_end) in split_parameters(tokens, open + 1, close) {
+        let Some(parameter) = declarations(tokens, parameter_start, parameter_end).into_iter().next() else {
+            continue;
+        };
+        if entry_point
+            && parameter.space.is_none()
+            && matches!(parameter.declarator, Declarator::Pointer | Declarator::Reference)
+        {
+            findings.push((
+                parameter.name.text_range(),
+                format!(
+                    "`{}` is a {} parameter of an entry point and needs an address space, such as `device` or \
+                     `constant`",
+                    parameter.name.text(),
+                    parameter.declarator.noun()
+                ),
+            ));
+        }
+        variables.insert(parameter.name.text().to_string(), parameter);
+    }
+    Some(FunctionScope {
+        name: tokens[name_index].clone(),
+        returns,
+        variables,
+    })
+}
+
+/// Check the statement `tokens[start..end]` of a function body and record
+/// the variables it declares.
+fn function_statement(
+    tokens: &[SyntaxToken],
+    mut start: usize,
+    end: usize,
+    scope: &mut FunctionScope,
+    globals: &HashMap<String, Declaration>,
+    findings: &mut Vec<(TextRange, String)>,
+) {
+    if end > start + 1 && tokens[start].kind() == SyntaxKind::KwFor && tokens[start + 1].kind() == SyntaxKind::LParen {
+        start += 2;
+    }
+    if let Some(return_index) = (start..end).find(|&i| tokens[i].kind() == SyntaxKind::KwReturn) {
+        if let Some(returns) = scope.returns.as_ref().filter(|returns| !returns.deduced)
+            && return_index + 1 < end
+        {
+            let lookup = |name: &str| scope.variables.get(name).or_else(|| globals.get(name));
+            if let Some(space) = reached_space(&tokens[return_index + 1..end], returns.declarator, lookup)
+                && space != returns.space()
+            {
+                findings.push((
+                    cover(&tokens[return_index + 1], &tokens[end - 1]),
+                    format!(
+                        "`{}` returns a `{}` {}{} but this {} `{}` memory",
+                        scope.name.text(),
+                        returns.space().name(),
+                        returns.declarator.noun(),
+                        returns.default_note(),
+                        returns.declarator.verb(),
+                        space.name()
+                    ),
+                ));
+            }
+        }
+        start = return_index + 1;
+    }
+
+    let mut initializers = HashSet::new();
+    for declaration in declarations(tokens, start, end) {
+        if let Some((equal, initializer_end)) = declaration.initializer {
+            initializers.insert(equal);
+            let lookup = |name: &str| scope.variables.get(name).or_else(|| globals.get(name));
+            if !declaration.deduced
+                && let Some(space) = reached_space(&tokens[equal + 1..initializer_end], declaration.declarator, lookup)
+                && space != declaration.space()
+            {
+                findings.push((
+                    declaration.name.text_range(),
+                    format!(
+                        "`{}` is a `{}` {}{} but {} `{}` memory",
+                        declaration.name.text(),
+                        declaration.space().name(),
+                        declaration.declarator.noun(),
+                        declaration.default_note(),
+                        declaration.declarator.verb(),
+                        space.name()
+                    ),
+                ));
+            }
+        }
+        scope.variables.insert(declaration.name.text().to_string(), declaration);
+    }
+
+    for index in start..end {
+        let lvalue = match tokens[index].kind() {
+            SyntaxKind::PlusPlus | SyntaxKind::MinusMinus => {
+                if index > start && ends_operand(tokens[index - 1].kind()) {
+                    lvalue_before(tokens, start, index)
+                } else {
+                    lvalue_after(tokens, index + 1, end)
+                }
+            },
+            kind if is_assignment(kind) && !initializers.contains(&index) => lvalue_before(tokens, start, index),
+            _ => None,
+        };
+        let Some(lvalue) = lvalue else {
+            continue;
+        };
+        let name = tokens[lvalue.root].text();
+        let Some(variable) = scope.variables.get(name).or_else(|| globals.get(name)) else {
+            continue;
+        };
+        let writes_memory = variable.declarator != Declarator::Pointer || lvalue.indirect;
+        if !variable.deduced && variable.space == Some(AddressSpace::Constant) && writes_memory {
+            findings.push((
+                cover(&tokens[lvalue.start], &tokens[lvalue.end - 1]),
+                format!("`{name}` {} read-only `constant` memory", variable.declarator.verb()),
+            ));
+        }
+    }
+}
+
+/// The address space of the memory `expression` reaches when it
+/// initializes a declarator of kind `declarator`, for the forms whose
+/// target is plain from the tokens: `&a[i]` or `p + i` for a pointer, and
+/// `a[i]`, `*p`, `p->x` or `s.x` for a reference.
+fn reached_space<'a>(
+    expression: &[SyntaxToken],
+    declarator: Declarator,
+    lookup: impl Fn(&str) -> Option<&'a Declaration>,
+) -> Option<AddressSpace> {
+    let kind_at = |index: usize| expression.get(index).map(SyntaxToken::kind);
+    let variable_at =
+        |index: usize| lookup(expression.get(index).filter(|token| token.kind() == SyntaxKind::Ident)?.text());
+    let variable = match declarator {
+        Declarator::Pointer if kind_at(0) == Some(SyntaxKind::Amp) => {
+            let variable = variable_at(1)?;
+            let through_pointer = matches!(kind_at(2), Some(SyntaxKind::LBracket | SyntaxKind::Arrow));
+            (variable.declarator != Declarator::Pointer || through_pointer).then_some(variable)?
+        },
+        Declarator::Pointer => {
+            let variable = variable_at(0)?;
+            let decays = matches!(
+                variable.declarator,
+                Declarator::Pointer
+                    | Declarator::Value {
+                        array: true,
+                    }
+            );
+            (decays && matches!(kind_at(1), None | Some(SyntaxKind::Plus | SyntaxKind::Minus))).then_some(variable)?
+        },
+        Declarator::Reference if kind_at(0) == Some(SyntaxKind::Star) => {
+            let variable = variable_at(1)?;
+            (variable.declarator == Declarator::Pointer && kind_at(2).is_none()).then_some(variable)?
+        },
+        Declarator::Reference => {
+            let variable = variable_at(0)?;
+            let fits = match kind_at(1) {
+                Some(SyntaxKind::LBracket) => variable.declarator != Declarator::Reference,
+                Some(SyntaxKind::Arrow) => variable.declarator == Declarator::Pointer,
+                Some(SyntaxKind::Dot) | None => variable.declarator != Declarator::Pointer,
+                _ => false,
+            };
+            fits.then_some(variable)?
+        },
+        Declarator::Value {
+            ..
+        } => return None,
+    };
+    (!variable.deduced).then(|| variable.space())
+}
+
+/// The declarators of the declaration `tokens[start..end]`, or none when it
+/// is not a declaration.
+fn declarations(
+    tokens: &[SyntaxToken],
+    start: usize,
+    end: usize,
+) -> Vec<Declaration> {
+    let mut index = start;
+    let mut space = None;
+    let mut deduced = false;
+    let mut has_type = false;
+    while index < end {
+        let token = &tokens[index];
+        if let Some(found) = AddressSpace::of(token) {
+            space = Some(found);
+            index += 1;
+            continue;
+        }
+        match token.kind() {
+            SyntaxKind::LDoubleBracket => index = skip_attribute(tokens, index, end),
+            kind if is_specifier(kind) => index += 1,
+            SyntaxKind::KwAuto => {
+                deduced = true;
+                has_type = true;
+                index += 1;
+            },
+            SyntaxKind::KwDecltype => {
+                deduced = true;
+                has_type = true;
+                index = skip_group(tokens, index + 1, end);
+            },
+            kind if is_builtin_type(kind) => {
+                has_type = true;
+                index += 1;
+            },
+            SyntaxKind::Ident | SyntaxKind::DoubleColon if !has_type => {
+                while index < end && matches!(tokens[index].kind(), SyntaxKind::Ident | SyntaxKind::DoubleColon) {
+                    index += 1;
+                }
+                if index < end && tokens[index].kind() == SyntaxKind::Less {
+                    index = skip_template_arguments(tokens, index, end);
+                }
+                has_type = true;
+            },
+            _ => break,
+        }
+    }
+    if !has_type {
+        return Vec::new();
+    }
+
+    let mut found = Vec::new();
+    while index < end {
+        let mut declarator = Declarator::Value {
+            array: false,
+        };
+        while index < end {
+            match tokens[index].kind() {
+                SyntaxKind::Star => declarator = Declarator::Pointer,
+                SyntaxKind::Amp | SyntaxKind::AndAnd => declarator = Declarator::Reference,
+                SyntaxKind::KwConst | SyntaxKind::KwVolatile => {},
+                _ => break,
+            }
+            index += 1;
+        }
+        if index == end || tokens[index].kind() != SyntaxKind::Ident {
+            break;
+        }
+        let name = tokens[index].clone();
+        index += 1;
+        while index < end {
+            match tokens[index].kind() {
+                SyntaxKind::LBracket => {
+                    if let Declarator::Value {
+                        array,
+                    } = &mut declarator
+                    {
+                        *array = true;
+                    }
+                    index = skip_group(tokens, index, end);
+                },
+                SyntaxKind::LDoubleBracket => index = skip_attribute(tokens, index, end),
+                _ => break,
+            }
+        }
+        let mut initializer = None;
+        if index < end && tokens[index].kind() == SyntaxKind::Equal {
+            let equal = index;
+            index += 1;
+            while index < end && tokens[index].kind() != SyntaxKind::Comma {
+                index = skip_group(tokens, index, end);
+            }
+            initializer = Some((equal, index));
+        } else if index < end && matches!(tokens[index].kind(), SyntaxKind::LParen | SyntaxKind::LBrace) {
+            index = skip_group(tokens, index, end);
+        }
+        found.push(Declaration {
+            name,
+            space,
+            declarator,
+            deduced,
+            initializer,
+        });
+        if index < end && tokens[index].kind() == SyntaxKind::Comma {
+            index += 1;
+        } else {
+            break;
+        }
+    }
+    found
+}
+
+/// An assignable expression, such as `p[i].x`, `*p` or `p->x`.
+struct Lvalue {
+    start: usize,
+    end: usize,
+    /// Index of the variable the expression starts from.
+    root: usize,
+    /// Whether the expression goes through a pointer or array.
+    indirect: bool,
+}
+
+/// The lvalue ending right before `end`, going no further back than `start`.
+fn lvalue_before(
+    tokens: &[SyntaxToken],
+    start: usize,
+    end: usize,
+) -> Option<Lvalue> {
+    let mut index = end;
+    let mut indirect = false;
+    loop {
+        let last = index.checked_sub(1).filter(|&last| last >= start)?;
+        match tokens[last].kind() {
+            SyntaxKind::RBracket => {
+                index = matching_open(tokens, start, last)?;
+                indirect = true;
+            },
+            SyntaxKind::Ident => {
+                index = last;
+                match index.checked_sub(1).filter(|&before| before >= start).map(|before| tokens[before].kind()) {
+                    Some(SyntaxKind::Dot) => index -= 1,
+                    Some(SyntaxKind::Arrow) => {
+                        indirect = true;
+                        index -= 1;
+                    },
+                    _ => break,
+                }
+            },
+            _ => return None,
+        }
+    }
+    let root = index;
+    let dereferenced = index > start
+        && tokens[index - 1].kind() == SyntaxKind::Star
+        && (index - 1 == start || !ends_operand(tokens[index - 2].kind()));
+    Some(Lvalue {
+        start: if dereferenced {
+            index - 1
+        } else {
+            index
+        },
+        end,
+        root,
+        indirect: indirect || dereferenced,
+    })
+}
+
+/// The lvalue starting at `start`, going no further than `end`.
+fn lvalue_after(
+    tokens: &[SyntaxToken],
+    start: usize,
+    end: usize,
+) -> Option<Lvalue> {
+    let dereferenced = start < end && tokens[start].kind() == SyntaxKind::Star;
+    let root = if dereferenced {
+        start + 1
+    } else {
+        start
+    };
+    if root >= end || tokens[root].kind() != SyntaxKind::Ident {
+        return None;
+    }
+    let mut index = root + 1;
+    let mut indirect = dereferenced;
+    while index < end {
+        match tokens[index].kind() {
+            SyntaxKind::LBracket => {
+                indirect = true;
+                index = skip_group(tokens, index, end);
+            },
+            SyntaxKind::Dot | SyntaxKind::Arrow if index + 1 < end && tokens[index + 1].kind() == SyntaxKind::Ident => {
+                indirect |= tokens[index].kind() == SyntaxKind::Arrow;
+                index += 2;
+            },
+            _ => break,
+        }
+    }
+    Some(Lvalue {
+        start,
+        end: index,
+        root,
+        indirect,
+    })
+}
+
+/// Start and end of each parameter between the parentheses of a function.
+fn split_parameters(
+    tokens: &[SyntaxToken],
+    start: usize,
+    end: usize,
+) -> Vec<(usize, usize)> {
+    let mut parameters = Vec::new();
+    let mut parameter_start = start;
+    let mut index = start;
+    let mut angles = 0usize;
+    while index < end {
+        match tokens[index].kind() {
+            SyntaxKind::Less => angles += 1,
+            SyntaxKind::Greater => angles = angles.saturating_sub(1),
+            SyntaxKind::RightShift => angles = angles.saturating_sub(2),
+            SyntaxKind::Comma if angles == 0 => {
+                parameters.push((parameter_start, index));
+                parameter_start = index + 1;
+            },
+            _ => {
+                index = skip_group(tokens, index, end);
+                continue;
+            },
+        }
+        index += 1;
+    }
+    if parameter_start < end {
+        parameters.push((parameter_start, end));
+    }
+    parameters
+}
+
+/// The index after the token at `index`, or after the bracketed group it
+/// opens.
+fn skip_group(
+    tokens: &[SyntaxToken],
+    index: usize,
+    end: usize,
+) -> usize {
+    if !matches!(tokens[index].kind(), SyntaxKind::LParen | SyntaxKind::LBracket | SyntaxKind::LBrace) {
+        return index + 1;
+    }
+    let mut depth = 0usize;
+    for (offset, token) in tokens[index..end].iter().enumerate() {
+        match token.kind() {
+            SyntaxKind::LParen | SyntaxKind::LBracket | SyntaxKind::LBrace => depth += 1,
+            SyntaxKind::RParen | SyntaxKind::RBracket | SyntaxKind::RBrace => {
+                depth -= 1;
+                if depth == 0 {
+                    return index + offset + 1;
+                }
+            },
+            _ => {},
+        }
+    }
+    end
+}
+
+/// The index after the `[[...]]` attribute opening at `index`.
+fn skip_attribute(
+    tokens: &[SyntaxToken],
+    index: usize,
+    end: usize,
+) -> usize {
+    (index..end).find(|&i| tokens[i].kind() == SyntaxKind::RDoubleBracket).map_or(end, |close| close + 1)
+}
+
+/// The index after the `<...>` opening at `index`, or `index` when no `<`
+/// is there.
+fn skip_template_arguments(
+    tokens: &[SyntaxToken],
+    index: usize,
+    end: usize,
+) -> usize {
+    if index >= end || tokens[index].kind() != SyntaxKind::Less {
+        return index;
+    }
+    let mut depth = 0usize;
+    let mut cursor = index;
+    while cursor < end {
+        match tokens[cursor].kind() {
+            SyntaxKind::Less => depth += 1,
+            SyntaxKind::Greater => depth = depth.saturating_sub(1),
+            SyntaxKind::RightShift => depth = depth.saturating_sub(2),
+            _ => {},
+        }
+        cursor = skip_group(tokens, cursor, end);
+        if depth == 0 {
+            break;
+        }
+    }
+    cursor
+}
+
+/// The `(` or `[` matching the closer at `close`, no earlier than `start`.
+fn matching_open(
+    tokens: &[SyntaxToken],
+    start: usize,
+    close: usize,
+) -> Option<usize> {
+    let mut depth = 0usize;
+    for index in (start..=close).rev() {
+        match tokens[index].kind() {
+            SyntaxKind::RParen | SyntaxKind::RBracket => depth += 1,
+            SyntaxKind::LParen | SyntaxKind::LBracket => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            },
+            _ => {},
+        }
+    }
+    None
+}
+
+fn cover(
+    first: &SyntaxToken,
+    last: &SyntaxToken,
+) -> TextRange {
+    first.text_range().cover(last.text_range())
+}
+
+fn ends_operand(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::Ident
+            | SyntaxKind::Integer
+            | SyntaxKind::Float
+            | SyntaxKind::RParen
+            | SyntaxKind::RBracket
+            | SyntaxKind::KwThis
+    )
+}
+
+fn is_assignment(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::Equal
+            | SyntaxKind::PlusEqual
+            | SyntaxKind::MinusEqual
+            | SyntaxKind::StarEqual
+            | SyntaxKind::SlashEqual
+            | SyntaxKind::PercentEqual
+            | SyntaxKind::CaretEqual
+            | SyntaxKind::AmpEqual
+            | SyntaxKind::PipeEqual
+            | SyntaxKind::LeftShiftEqual
+            | SyntaxKind::RightShiftEqual
+    )
+}
+
+/// Keywords that may come before or around the type of a declaration.
+fn is_specifier(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::KwConst
+            | SyntaxKind::KwVolatile
+            | SyntaxKind::KwStatic
+            | SyntaxKind::KwConstexpr
+            | SyntaxKind::KwInline
+            | SyntaxKind::KwExtern
+            | SyntaxKind::KwTypename
+            | SyntaxKind::KwKernel
+            | SyntaxKind::KwVertex
+            | SyntaxKind::KwFragment
+            | SyntaxKind::KwMesh
+            | SyntaxKind::KwObject
+            | SyntaxKind::KwVisible
+    )
+}
+
+fn is_builtin_type(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::KwBool
+            | SyntaxKind::KwChar
+            | SyntaxKind::KwShort
+            | SyntaxKind::KwInt
+            | SyntaxKind::KwLong
+            | SyntaxKind::KwSigned
+            | SyntaxKind::KwUnsigned
+            | SyntaxKind::KwFloat
+            | SyntaxKind::KwDouble
+            | SyntaxKind::KwHalf
+            | SyntaxKind::KwBFloat
+            | SyntaxKind::KwBFloat16
+            | SyntaxKind::KwVoid
+            | SyntaxKind::KwSampler
+            | SyntaxKind::KwTexture
+    )
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/address_spaces_tests.rs"]
+mod tests;
@@ -0,0 +1,234 @@
+//! Indexed binding attributes such as `[[buffer(2)]]` or `[[texture(0)]]`.
+//!
+//! Used to find every declaration bound to the same slot so editors can
+//! highlight binding reuse across kernels and files, and to warn when two
+//! parameters of one function claim the same resource slot.
+
+use std::collections::HashMap;
+
+use rowan::{TextRange, TextSize};
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, NumberOrString, Position, Range, Url,
+};
+
+use crate::syntax::{
+    ast::{self, AstNode},
+    cst::SyntaxNode,
+    helpers,
+    kind::SyntaxKind,
+};
+
+pub const DUPLICATE_BINDING_CODE: &str = "duplicate-binding";
+
+/// Attributes that bind an argument table slot, which one function may only
+/// use once per index.
+const RESOURCE_SLOTS: &[&str] = &["buffer", "texture", "sampler", "threadgroup"];
+
+/// A binding slot declared by an attribute, e.g. `buffer(2)`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BindingSlot {
+    pub name: String,
+    pub index: u32,
+}
+
+impl BindingSlot {
+    /// Text of the attribute argument as written in source, e.g. `buffer(2)`.
+    pub fn label(&self) -> String {
+        format!("{}({})", self.name, self.index)
+    }
+}
+
+/// One attribute in the source that binds a [`BindingSlot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindingSite {
+    /// Range of the `name(index)` text inside the attribute brackets.
+    pub range: Range,
+    /// Name of the function whose parameter carries the attribute.
+    pub function: Option<String>,
+    /// Name of the parameter or field carrying the attribute.
+    pub declarator: Option<String>,
+}
+
+/// Return the binding slot under the cursor, if the cursor is on an indexed attribute.
+pub fn binding_slot_at_position(
+    root: &SyntaxNode,
+    source: &str,
+    position: Position,
+) -> Option<BindingSlot> {
+    let node = helpers::node_at_position(root, source, position)?;
+    let attribute = helpers::find_ancestor(node, SyntaxKind::Attribute)?;
+    let offset = position_to_offset(source, position)?;
+    let slots = attribute_slots(&attribute, source);
+    slots
+        .iter()
+        .find(|(_, range)| range.contains_inclusive(offset))
+        .or_else(|| slots.first())
+        .map(|(slot, _)| slot.clone())
+}
+
+/// Collect every attribute in the tree that binds `slot`.
+pub fn find_binding_sites(
+    root: &SyntaxNode,
+    source: &str,
+    slot: &BindingSlot,
+) -> Vec<BindingSite> {
+    let mut sites = Vec::new();
+    for attribute in root.descendants().filter(|node| node.kind() == SyntaxKind::Attribute) {
+        for (candidate, range) in attribute_slots(&attribute, source) {
+            if candidate != *slot {
+                continue;
+            }
+            sites.push(BindingSite {
+                range: helpers::range_to_lsp(range, source),
+                function: enclosing_function_name(&attribute),
+                declarator: declarator_name(&attribute),
+            });
+        }
+    }
+    sites
+}
+
+/// A resource slot bound by a function parameter, e.g. `device float* data [[buffer(0)]]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParameterBinding {
+    pub slot: BindingSlot,
+    /// Range of the `name(index)` text inside the attribute brackets.
+    pub range: Range,
+    /// Name of the parameter carrying the attribute.
+    pub parameter: Option<String>,
+}
+
+/// The `buffer`, `texture`, `sampler` and `threadgroup` slots bound by the
+/// parameters of `function`, in source order.
+pub fn parameter_bindings(
+    function: &ast::FunctionDef,
+    source: &str,
+) -> Vec<ParameterBinding> {
+    let attributes = function
+        .syntax()
+        .descendants()
+        .filter(|node| node.kind() == SyntaxKind::Attribute && node.parent().and_then(ast::Parameter::cast).is_some());
+    let mut bindings = Vec::new();
+    for attribute in attributes {
+        for (slot, range) in attribute_slots(&attribute, source) {
+            if RESOURCE_SLOTS.contains(&slot.name.as_str()) {
+                bindings.push(ParameterBinding {
+                    slot,
+                    range: helpers::range_to_lsp(range, source),
+                    parameter: declarator_name(&attribute),
+                });
+            }
+        }
+    }
+    bindings
+}
+
+/// A warning on each parameter that binds a resource slot an earlier
+/// parameter of the same function already binds.
+pub fn duplicate_bindings(
+    root: &SyntaxNode,
+    source: &str,
+    uri: &Url,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for function in root.descendants().filter_map(ast::FunctionDef::cast) {
+        let mut first_sites: HashMap<BindingSlot, Range> = HashMap::new();
+        for binding in parameter_bindings(&function, source) {
+            let Some(first) = first_sites.get(&binding.slot) else {
+                first_sites.insert(binding.slot, binding.range);
+                continue;
+            };
+            diagnostics.push(Diagnostic {
+                range: binding.range,
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String(DUPLICATE_BINDING_CODE.to_string())),
+                source: Some("metal-analyzer".to_string()),
+                message: format!("`{}` is already bound by another parameter", binding.slot.label()),
+                related_information: Some(vec![DiagnosticRelatedInformation {
+                    location: Location {
+                        uri: uri.clone(),
+                        range: *first,
+                    },
+                    message: "first bound here".to_string(),
+                }]),
+                ..Default::default()
+            });
+        }
+    }
+    diagnostics
+}
+
+/// Parse the comma-separated items of an attribute into binding slots.
+///
+/// Returned ranges are absolute and cover `name(index)`.
+fn attribute_slots(
+    attribute: &SyntaxNode,
+    source: &str,
+) -> Vec<(BindingSlot, TextRange)> {
+    let text = helpers::node_text(attribute, source);
+    let base = attribute.text_range().start();
+    let Some(open) = text.find("[[") else {
+        return Vec::new();
+    };
+    let inner_start = open + 2;
+    let inner_end = text[inner_start..].find("]]").map(|i| inner_start + i).unwrap_or(text.len());
+
+    let mut slots = Vec::new();
+    let mut item_start = inner_start;
+    for item in text[inner_start..inner_end].split(',') {
+        let leading = item.len() - item.trim_start().len();
+        let trimmed = item.trim();
+        if let Some(slot) = parse_binding_slot(trimmed) {
+            let start = base + TextSize::from((item_start + leading) as u32);
+            let range = TextRange::at(start, TextSize::from(trimmed.len() as u32));
+            slots.push((slot, range));
+        }
+        item_start += item.len() + 1;
+    }
+    slots
+}
+
+/// Parse `name(index)` into a [`BindingSlot`]. Non-numeric arguments are ignored.
+pub fn parse_binding_slot(item: &str) -> Option<BindingSlot> {
+    let open = item.find('(')?;
+    let close = item.rfind(')')?;
+    if close < open || !item[close + 1..].trim().is_empty() {
+        return None;
+    }
+    let name = item[..open].trim();
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    let index = item[open + 1..close].trim().parse().ok()?;
+    Some(BindingSlot {
+        name: name.to_string(),
+        index,
+    })
+}
+
+fn enclosing_function_name(attribute: &SyntaxNode) -> Option<String> {
+    attribute.ancestors().find_map(ast::FunctionDef::cast)?.name_token().map(|token| token.text().to_string())
+}
+
+fn declarator_name(attribute: &SyntaxNode) -> Option<String> {
+    let parent = attribute.parent()?;
+    let token = if let Some(param) = ast::Parameter::cast(parent.clone()) {
+        param.name_token()
+    } else if let Some(field) = ast::FieldDef::cast(parent) {
+        field.name_token()
+    } else {
+        None
+    };
+    token.map(|token| token.text().to_string())
+}
+
+fn position_to_offset(
+    source: &str,
+    position: Position,
+) -> Option<TextSize> {
+    crate::text_pos::byte_offset_from_position(source, position).map(|offset| TextSize::from(offset as u32))
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/bindings_tests.rs"]
+mod tests;
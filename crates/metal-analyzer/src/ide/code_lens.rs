@@ -0,0 +1,144 @@
+//! CodeLenses: entry-point summaries, workspace reference counts, and
+//! per-file actions.
+//!
+//! Reference-count lenses are returned unresolved and counted in
+//! `codeLens/resolve` from the project index, so opening a file never waits
+//! on the index.
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{CodeLens, Command, Position, Range, Url};
+
+use crate::{
+    definition::ProjectIndex,
+    ide::entry_points::{PipelineIndex, entry_point_details, entry_point_lens_title, entry_point_stage},
+    syntax::{
+        SyntaxTree,
+        ast::{self, AstNode},
+        cst::SyntaxToken,
+        helpers,
+    },
+};
+
+/// Server command re-running diagnostics for the file given as argument.
+pub const RUN_DIAGNOSTICS_COMMAND: &str = "metal-analyzer.runDiagnostics";
+/// Server command rebuilding the AST index of the file given as argument.
+pub const REINDEX_FILE_COMMAND: &str = "metal-analyzer.reindexFile";
+
+/// What an unresolved reference lens counts, carried in its `data`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceLensData {
+    pub name: String,
+    pub kind: ReferenceLensKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReferenceLensKind {
+    /// Counted from the call sites of functions and methods with the name.
+    Function,
+    /// Counted from the declarations typed with the struct.
+    Struct,
+}
+
+/// A lens above each entry point with its stage, buffer bindings and
+/// pipelines.
+pub fn entry_point_lenses(
+    uri: &Url,
+    tree: &SyntaxTree,
+    pipelines: Option<&PipelineIndex>,
+) -> Vec<CodeLens> {
+    entry_point_details(uri, tree)
+        .iter()
+        .map(|details| CodeLens {
+            range: details.site.location.range,
+            command: Some(title_only(entry_point_lens_title(details, pipelines))),
+            data: None,
+        })
+        .collect()
+}
+
+/// An unresolved reference lens on each function definition other than an
+/// entry point, and on each struct definition.
+pub fn reference_lenses(tree: &SyntaxTree) -> Vec<CodeLens> {
+    let source = tree.source();
+    let lens = |token: SyntaxToken, kind| CodeLens {
+        range: helpers::range_to_lsp(token.text_range(), source),
+        command: None,
+        data: serde_json::to_value(ReferenceLensData {
+            name: token.text().to_string(),
+            kind,
+        })
+        .ok(),
+    };
+
+    let mut lenses = Vec::new();
+    for node in tree.root().descendants() {
+        if let Some(function) = ast::FunctionDef::cast(node.clone()) {
+            if function.body().is_some()
+                && entry_point_stage(&function).is_none()
+                && let Some(name) = function.name_token()
+            {
+                lenses.push(lens(name, ReferenceLensKind::Function));
+            }
+        } else if let Some(name) = ast::StructDef::cast(node).and_then(|def| def.body().and(def.name_token())) {
+            lenses.push(lens(name, ReferenceLensKind::Struct));
+        }
+    }
+    lenses
+}
+
+/// Fill in the reference count of a lens from [`reference_lenses`]. Lenses
+/// without reference data are returned unchanged.
+pub fn resolve_reference_lens(
+    mut lens: CodeLens,
+    project_index: &ProjectIndex,
+) -> CodeLens {
+    let Some(data) = lens.data.clone().and_then(|data| serde_json::from_value::<ReferenceLensData>(data).ok()) else {
+        return lens;
+    };
+    let count = match data.kind {
+        ReferenceLensKind::Function => project_index.function_reference_count(&data.name),
+        ReferenceLensKind::Struct => project_index.type_use_count(&data.name),
+    };
+    lens.command = Some(title_only(format!(
+        "{count} {}",
+        if count == 1 {
+            "reference"
+        } else {
+            "references"
+        }
+    )));
+    lens
+}
+
+/// "Run diagnostics" and "Re-index file" at the top of the file.
+pub fn file_action_lenses(uri: &Url) -> Vec<CodeLens> {
+    let top = Range::new(Position::new(0, 0), Position::new(0, 0));
+    [("Run diagnostics", RUN_DIAGNOSTICS_COMMAND), ("Re-index file", REINDEX_FILE_COMMAND)]
+        .into_iter()
+        .map(|(title, command)| CodeLens {
+            range: top,
+            command: Some(Command {
+                title: title.to_string(),
+                command: command.to_string(),
+                arguments: Some(vec![serde_json::Value::String(uri.to_string())]),
+            }),
+            data: None,
+        })
+        .collect()
+}
+
+/// A command that only shows its title; clients render an empty command id
+/// as plain text.
+fn title_only(title: String) -> Command {
+    Command {
+        title,
+        command: String::new(),
+        arguments: None,
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/code_lens_tests.rs"]
+mod tests;
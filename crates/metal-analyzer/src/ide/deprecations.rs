@@ -0,0 +1,93 @@
+//! Compiler deprecation warnings: the span of the deprecated use and the
+//! quick fix that rewrites it to its replacement.
+//!
+//! The compiler points at a single column. The warning is widened to the
+//! identifier it names so clients can strike it through, and uses listed in
+//! the builtin deprecation table get a `quickfix` code action.
+
+use std::collections::HashMap;
+
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, DiagnosticTag, Position, Range, TextEdit, Url,
+    WorkspaceEdit,
+};
+
+use crate::{metal::builtins, text_pos};
+
+fn is_deprecation(diagnostic: &Diagnostic) -> bool {
+    diagnostic.tags.as_ref().is_some_and(|tags| tags.contains(&DiagnosticTag::DEPRECATED))
+}
+
+/// Range of the deprecated identifier named by `diagnostic`, on its line at
+/// or after the reported column.
+pub fn deprecated_use_range(
+    diagnostic: &Diagnostic,
+    text: &str,
+) -> Option<Range> {
+    let name = builtins::deprecated_name(&diagnostic.message)?;
+    let line = diagnostic.range.start.line;
+    let (line_text, column) = text_pos::line_and_byte_column_at_position(text, diagnostic.range.start)?;
+    let start = word_occurrences(line_text, name)
+        .find(|&start| start + name.len() >= column)
+        .or_else(|| word_occurrences(line_text, name).next())?;
+    Some(Range::new(
+        Position::new(line, text_pos::column_of_byte_offset(line_text, start)),
+        Position::new(line, text_pos::column_of_byte_offset(line_text, start + name.len())),
+    ))
+}
+
+/// Widen each deprecation warning from its column to the deprecated identifier.
+pub fn widen_deprecation_ranges(
+    diagnostics: &mut [Diagnostic],
+    text: &str,
+) {
+    for diagnostic in diagnostics.iter_mut().filter(|diagnostic| is_deprecation(diagnostic)) {
+        if let Some(range) = deprecated_use_range(diagnostic, text) {
+            diagnostic.range = range;
+        }
+    }
+}
+
+/// A quick fix for each deprecation warning in `diagnostics` whose
+/// replacement is known.
+pub fn upgrade_actions(
+    uri: &Url,
+    text: &str,
+    diagnostics: &[Diagnostic],
+) -> Vec<CodeActionOrCommand> {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| is_deprecation(diagnostic))
+        .filter_map(|diagnostic| {
+            let deprecation = builtins::deprecation(builtins::deprecated_name(&diagnostic.message)?)?;
+            let range = deprecated_use_range(diagnostic, text)?;
+            let edit = TextEdit::new(range, deprecation.replacement.to_string());
+            Some(CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("Replace `{}` with `{}`", deprecation.name, deprecation.replacement),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic.clone()]),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+                    ..Default::default()
+                }),
+                is_preferred: Some(true),
+                ..Default::default()
+            }))
+        })
+        .collect()
+}
+
+/// Byte offsets of `name` in `line` as a whole identifier.
+fn word_occurrences<'a>(
+    line: &'a str,
+    name: &'a str,
+) -> impl Iterator<Item = usize> + 'a {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    line.match_indices(name)
+        .map(|(start, _)| start)
+        .filter(move |&start| !line[..start].ends_with(is_word) && !line[start + name.len()..].starts_with(is_word))
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/deprecations_tests.rs"]
+mod tests;
@@ -0,0 +1,195 @@
+//! Lints for entry-point signatures that the compiler rejects with obscure
+//! errors: thread-index attributes such as `[[thread_position_in_grid]]` on
+//! a type that is not `uint` or `ushort`, vertex functions returning a
+//! struct without a `[[position]]` field, fragment functions without a
+//! `return`, and `[[stage_in]]` on kernels.
+//!
+//! The checks read the syntax tree only. Types that are neither a builtin
+//! scalar or vector nor a struct defined in the file are not checked.
+
+use rowan::TextRange;
+use tower_lsp::lsp_types::{CodeDescription, Diagnostic, DiagnosticSeverity, NumberOrString, Url};
+
+use crate::{
+    ide::entry_points::entry_point_stage,
+    metal::diagnostic_codes::SPEC_URL,
+    syntax::{
+        ast::{self, AstNode},
+        cst::SyntaxNode,
+        helpers,
+        kind::SyntaxKind,
+    },
+};
+
+pub const ENTRY_POINT_ATTRIBUTE_CODE: &str = "entry-point-attribute";
+
+/// Kernel input attributes whose parameter must be `uint` or `ushort`, or a
+/// vector of them.
+const THREAD_INDEX_ATTRIBUTES: &[&str] = &[
+    "thread_position_in_grid",
+    "thread_position_in_threadgroup",
+    "thread_index_in_threadgroup",
+    "threadgroup_position_in_grid",
+    "threadgroups_per_grid",
+    "threads_per_grid",
+    "threads_per_threadgroup",
+    "dispatch_threads_per_threadgroup",
+    "thread_execution_width",
+    "thread_index_in_simdgroup",
+    "threads_per_simdgroup",
+    "simdgroup_index_in_threadgroup",
+    "simdgroups_per_threadgroup",
+    "thread_index_in_quadgroup",
+    "quadgroup_index_in_threadgroup",
+    "quadgroups_per_threadgroup",
+];
+
+/// Scalar element types of the builtin scalar and vector types.
+const SCALAR_TYPES: &[&str] =
+    &["bool", "char", "uchar", "short", "ushort", "int", "uint", "long", "ulong", "half", "float", "bfloat"];
+
+/// Entry-point attribute warnings for `root`, parsed from `source`.
+pub fn entry_point_attribute_diagnostics(
+    root: &SyntaxNode,
+    source: &str,
+) -> Vec<Diagnostic> {
+    let mut findings: Vec<(TextRange, String)> = Vec::new();
+    for function in root.descendants().filter_map(ast::FunctionDef::cast) {
+        let Some(stage) = entry_point_stage(&function) else {
+            continue;
+        };
+        let name = function.name_token().map(|token| token.text().to_string()).unwrap_or_default();
+        let parameters: Vec<ast::Parameter> =
+            function.parameter_list().map(|list| list.parameters().collect()).unwrap_or_default();
+        for parameter in parameters {
+            let type_text = parameter
+                .syntax()
+                .children()
+                .find_map(ast::TypeRef::cast)
+                .map(|type_ref| helpers::node_text(type_ref.syntax(), source).trim().to_string());
+            for attribute in parameter.syntax().children().filter(|node| node.kind() == SyntaxKind::Attribute) {
+                for item in attribute_names(&attribute, source) {
+                    if stage == "kernel" && item == "stage_in" {
+                        findings.push((
+                            attribute.text_range(),
+                            format!(
+                                "`[[stage_in]]` is not valid on kernel `{name}`; kernels read their inputs from \
+                                 `[[buffer(n)]]` and `[[texture(n)]]` arguments"
+                            ),
+                        ));
+                    }
+                    if THREAD_INDEX_ATTRIBUTES.contains(&item)
+                        && let Some(type_text) = type_text.as_deref()
+                        && !is_thread_index_type(type_text)
+                    {
+                        findings.push((
+                            attribute.text_range(),
+                            format!("`[[{item}]]` needs `uint` or `ushort`, or a vector of them, not `{type_text}`"),
+                        ));
+                    }
+                }
+            }
+        }
+
+        let Some(return_type) = function.return_type() else {
+            continue;
+        };
+        let return_text = helpers::node_text(return_type.syntax(), source).trim();
+        match stage {
+            "vertex" => {
+                let fields_with_position = struct_fields_with(root, source, return_text, "position");
+                if fields_with_position == Some(false) {
+                    findings.push((
+                        return_type.syntax().text_range(),
+                        format!(
+                            "vertex function `{name}` returns `{return_text}`, which has no field with the \
+                             `[[position]]` attribute"
+                        ),
+                    ));
+                }
+            },
+            "fragment" if return_text != "void" => {
+                let has_return = function
+                    .body()
+                    .is_some_and(|body| body.syntax().descendants().any(|node| node.kind() == SyntaxKind::ReturnStmt));
+                if function.body().is_some() && !has_return {
+                    let range = function.name_token().map_or(return_type.syntax().text_range(), |t| t.text_range());
+                    findings.push((
+                        range,
+                        format!("fragment function `{name}` returns `{return_text}` but has no `return` statement"),
+                    ));
+                }
+            },
+            _ => {},
+        }
+    }
+
+    let href = Url::parse(SPEC_URL).ok();
+    findings
+        .into_iter()
+        .map(|(range, message)| Diagnostic {
+            range: helpers::range_to_lsp(range, source),
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: Some(NumberOrString::String(ENTRY_POINT_ATTRIBUTE_CODE.to_string())),
+            code_description: href.clone().map(|href| CodeDescription {
+                href,
+            }),
+            source: Some("metal-analyzer".to_string()),
+            message,
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Names of the items of an attribute, e.g. `buffer` and `raster_order_group`
+/// in `[[buffer(0), raster_order_group(1)]]`.
+fn attribute_names<'a>(
+    attribute: &SyntaxNode,
+    source: &'a str,
+) -> Vec<&'a str> {
+    let text = helpers::node_text(attribute, source);
+    let inner = text.trim_start_matches("[[").trim_end_matches("]]");
+    inner.split(',').map(|item| item.split('(').next().unwrap_or_default().trim()).collect()
+}
+
+/// Whether `type_text` can hold a thread index: `uint` or `ushort` and their
+/// vectors, and types that are not builtin scalars or vectors, which are not
+/// checked.
+fn is_thread_index_type(type_text: &str) -> bool {
+    let type_text = type_text.strip_prefix("const ").unwrap_or(type_text).trim();
+    if matches!(type_text, "unsigned int" | "unsigned short") {
+        return true;
+    }
+    let element = type_text.trim_end_matches(['2', '3', '4']);
+    let element = element.strip_prefix("vec<").map_or(element, |rest| rest.split(',').next().unwrap_or_default());
+    !SCALAR_TYPES.contains(&element) || matches!(element, "uint" | "ushort")
+}
+
+/// Whether the struct `name` defined in `root` has a field carrying the
+/// `attribute`; `None` when no such struct is defined.
+fn struct_fields_with(
+    root: &SyntaxNode,
+    source: &str,
+    name: &str,
+    attribute: &str,
+) -> Option<bool> {
+    let mut found = None;
+    for def in root.descendants().filter_map(ast::StructDef::cast) {
+        if def.name_token().is_none_or(|token| token.text() != name) {
+            continue;
+        }
+        let has_attribute = def.fields().any(|field| {
+            field
+                .syntax()
+                .children()
+                .filter(|node| node.kind() == SyntaxKind::Attribute)
+                .any(|node| attribute_names(&node, source).contains(&attribute))
+        });
+        found = Some(found.unwrap_or(false) || has_attribute);
+    }
+    found
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/entry_point_attributes_tests.rs"]
+mod tests;
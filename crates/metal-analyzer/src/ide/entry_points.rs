@@ -0,0 +1,427 @@
+//! Entry points that transitively call a helper function.
+//!
+//! The inverse of call hierarchy, tuned for "which kernels does this helper
+//! affect?". Call edges come from the CST of every scanned source and are
+//! completed with AST reference sites when an index is available, which also
+//! picks up calls hidden behind macros.
+//!
+//! Also lists entry points with their attributes and resource bindings for
+//! `metal-analyzer/entryPoints` and the entry-point CodeLens.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use rowan::TextSize;
+use tower_lsp::lsp_types::{Location, Position, Url};
+
+use crate::{
+    definition::RefSite,
+    ide::bindings::{ParameterBinding, parameter_bindings},
+    syntax::{
+        SyntaxTree,
+        ast::{self, AstNode},
+        cst::{SyntaxNode, SyntaxToken},
+        helpers,
+        kind::SyntaxKind,
+    },
+};
+
+/// A function definition seen while building the [`CallGraph`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionSite {
+    pub name: String,
+    /// Entry-point qualifier such as `kernel` or `fragment`, if any.
+    pub stage: Option<&'static str>,
+    /// Location of the function name.
+    pub location: Location,
+}
+
+/// An entry point reaching the queried function, with the shortest call chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryPointPath {
+    pub entry: FunctionSite,
+    /// Function names from the entry point down to the queried function.
+    pub path: Vec<String>,
+}
+
+/// Name-based call graph over function definitions.
+///
+/// Overloads and same-named functions in different scopes share one node,
+/// which over-approximates callers rather than missing them.
+#[derive(Debug, Default)]
+pub struct CallGraph {
+    functions: HashMap<String, Vec<FunctionSite>>,
+    callers: HashMap<String, HashSet<String>>,
+}
+
+impl CallGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record every function definition in `tree` and the calls in its body.
+    pub fn add_source(
+        &mut self,
+        uri: &Url,
+        tree: &SyntaxTree,
+    ) {
+        let source = tree.source();
+        for func in tree.root().descendants().filter_map(ast::FunctionDef::cast) {
+            let Some(name) = func.name_token() else {
+                continue;
+            };
+            let site = FunctionSite {
+                name: name.text().to_string(),
+                stage: entry_point_stage(&func),
+                location: Location {
+                    uri: uri.clone(),
+                    range: helpers::range_to_lsp(name.text_range(), source),
+                },
+            };
+            if !self.functions.get(&site.name).is_some_and(|sites| sites.contains(&site)) {
+                self.functions.entry(site.name.clone()).or_default().push(site);
+            }
+            if let Some(body) = func.body() {
+                for callee in called_names(body.syntax()) {
+                    self.add_call(name.text(), &callee);
+                }
+            }
+        }
+    }
+
+    /// Add edges from resolved AST references that land inside a function body of `tree`.
+    ///
+    /// Only references to functions are considered; the caller is the CST
+    /// function enclosing the reference's expansion location.
+    pub fn add_resolved_calls(
+        &mut self,
+        tree: &SyntaxTree,
+        refs: &[RefSite],
+    ) {
+        let source = tree.source();
+        let root = tree.root();
+        for site in refs {
+            if !matches!(site.target_kind.as_str(), "FunctionDecl" | "CXXMethodDecl") {
+                continue;
+            }
+            let (line, col) = site.expansion.as_ref().map(|loc| (loc.line, loc.col)).unwrap_or((site.line, site.col));
+            let Some(offset) = offset_of_line_col(source, line, col) else {
+                continue;
+            };
+            let Some(caller) = enclosing_function(&root, offset).and_then(|func| func.name_token()) else {
+                continue;
+            };
+            self.add_call(caller.text(), &site.target_name);
+        }
+    }
+
+    pub fn add_call(
+        &mut self,
+        caller: &str,
+        callee: &str,
+    ) {
+        if caller != callee {
+            self.callers.entry(callee.to_string()).or_default().insert(caller.to_string());
+        }
+    }
+
+    /// Entry points that reach `function` through the call graph, nearest first.
+    ///
+    /// An entry point asking about itself is reported with a one-element path.
+    pub fn entry_points_reaching(
+        &self,
+        function: &str,
+    ) -> Vec<EntryPointPath> {
+        let mut parent: HashMap<&str, &str> = HashMap::new();
+        let mut visited: HashSet<&str> = HashSet::from([function]);
+        let mut queue: VecDeque<&str> = VecDeque::from([function]);
+        let mut reached: Vec<&str> = Vec::new();
+
+        while let Some(current) = queue.pop_front() {
+            if self.is_entry_point(current) {
+                reached.push(current);
+            }
+            let Some(callers) = self.callers.get(current) else {
+                continue;
+            };
+            let mut callers: Vec<&str> = callers.iter().map(String::as_str).collect();
+            callers.sort_unstable();
+            for caller in callers {
+                if visited.insert(caller) {
+                    parent.insert(caller, current);
+                    queue.push_back(caller);
+                }
+            }
+        }
+
+        let mut results = Vec::new();
+        for entry in reached {
+            let mut path = vec![entry.to_string()];
+            let mut node = entry;
+            while let Some(&next) = parent.get(node) {
+                path.push(next.to_string());
+                node = next;
+            }
+            for site in self.functions.get(entry).into_iter().flatten().filter(|site| site.stage.is_some()) {
+                results.push(EntryPointPath {
+                    entry: site.clone(),
+                    path: path.clone(),
+                });
+            }
+        }
+        results
+    }
+
+    fn is_entry_point(
+        &self,
+        name: &str,
+    ) -> bool {
+        self.functions.get(name).is_some_and(|sites| sites.iter().any(|site| site.stage.is_some()))
+    }
+}
+
+/// Entry-point definitions in `tree`, in source order.
+pub fn entry_points(
+    uri: &Url,
+    tree: &SyntaxTree,
+) -> Vec<FunctionSite> {
+    entry_point_details(uri, tree).into_iter().map(|details| details.site).collect()
+}
+
+/// An entry point with the attributes that describe its interface.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryPointDetails {
+    pub site: FunctionSite,
+    /// Items of the attribute lists written before the function, e.g.
+    /// `max_total_threads_per_threadgroup(256)`.
+    pub attributes: Vec<String>,
+    /// Resource slots bound by the parameters.
+    pub bindings: Vec<ParameterBinding>,
+}
+
+/// Entry-point definitions in `tree` with their attributes and bindings, in
+/// source order.
+pub fn entry_point_details(
+    uri: &Url,
+    tree: &SyntaxTree,
+) -> Vec<EntryPointDetails> {
+    let source = tree.source();
+    tree.root()
+        .descendants()
+        .filter_map(ast::FunctionDef::cast)
+        .filter_map(|func| {
+            let name = func.name_token()?;
+            Some(EntryPointDetails {
+                site: FunctionSite {
+                    name: name.text().to_string(),
+                    stage: Some(entry_point_stage(&func)?),
+                    location: Location {
+                        uri: uri.clone(),
+                        range: helpers::range_to_lsp(name.text_range(), source),
+                    },
+                },
+                attributes: leading_attributes(&func, source),
+                bindings: parameter_bindings(&func, source),
+            })
+        })
+        .collect()
+}
+
+/// Pipelines and the functions they use, read from the JSON object of
+/// `entryPoints.pipelinesFile` that maps each pipeline name to its function
+/// names.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PipelineIndex {
+    pipelines: BTreeMap<String, Vec<String>>,
+}
+
+impl PipelineIndex {
+    pub fn parse(json: &str) -> serde_json::Result<Self> {
+        Ok(Self {
+            pipelines: serde_json::from_str(json)?,
+        })
+    }
+
+    /// Names of the pipelines that use `function`, sorted.
+    pub fn pipelines_using(
+        &self,
+        function: &str,
+    ) -> Vec<String> {
+        self.pipelines
+            .iter()
+            .filter(|(_, functions)| functions.iter().any(|name| name == function))
+            .map(|(pipeline, _)| pipeline.clone())
+            .collect()
+    }
+}
+
+/// CodeLens title of an entry point, e.g. `kernel · 3 buffer bindings · used
+/// by 2 pipelines`. The pipeline count needs a pipelines file.
+pub fn entry_point_lens_title(
+    details: &EntryPointDetails,
+    pipelines: Option<&PipelineIndex>,
+) -> String {
+    let buffers = details.bindings.iter().filter(|binding| binding.slot.name == "buffer").count();
+    let mut title = format!(
+        "{} · {buffers} buffer {}",
+        details.site.stage.unwrap_or_default(),
+        if buffers == 1 {
+            "binding"
+        } else {
+            "bindings"
+        }
+    );
+    if let Some(pipelines) = pipelines {
+        let used_by = pipelines.pipelines_using(&details.site.name).len();
+        title.push_str(&format!(
+            " · used by {used_by} {}",
+            if used_by == 1 {
+                "pipeline"
+            } else {
+                "pipelines"
+            }
+        ));
+    }
+    title
+}
+
+/// Items of the `[[...]]` lists right before `func`, which the CST leaves as
+/// loose tokens, in source order.
+fn leading_attributes(
+    func: &ast::FunctionDef,
+    source: &str,
+) -> Vec<String> {
+    let tokens =
+        std::iter::successors(func.syntax().prev_sibling_or_token(), |element| element.prev_sibling_or_token())
+            .map_while(|element| element.into_token());
+
+    let mut lists = Vec::new();
+    let mut close = None;
+    for token in tokens {
+        match (token.kind(), close) {
+            (SyntaxKind::Whitespace | SyntaxKind::Comment, None) => {},
+            (SyntaxKind::RDoubleBracket, None) => close = Some(token.text_range().start()),
+            (SyntaxKind::LDoubleBracket, Some(end)) => {
+                lists.push(&source[usize::from(token.text_range().end())..usize::from(end)]);
+                close = None;
+            },
+            (_, Some(_)) => {},
+            _ => break,
+        }
+    }
+    lists.into_iter().rev().flat_map(split_attribute_list).collect()
+}
+
+/// Split `a, b(1, 2)` at the commas outside parentheses.
+fn split_attribute_list(list: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in list.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                items.push(&list[start..i]);
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    items.push(&list[start..]);
+    items.into_iter().map(str::trim).filter(|item| !item.is_empty()).map(String::from).collect()
+}
+
+/// Name of the function definition enclosing `position`, if any.
+pub fn enclosing_function_name(
+    root: &SyntaxNode,
+    source: &str,
+    position: Position,
+) -> Option<String> {
+    let offset = crate::text_pos::byte_offset_from_position(source, position)?;
+    let func = enclosing_function(root, TextSize::from(offset as u32))?;
+    func.name_token().map(|token| token.text().to_string())
+}
+
+fn enclosing_function(
+    root: &SyntaxNode,
+    offset: TextSize,
+) -> Option<ast::FunctionDef> {
+    let token = root.token_at_offset(offset).right_biased()?;
+    token.parent_ancestors().find_map(ast::FunctionDef::cast)
+}
+
+pub(crate) fn entry_point_stage(func: &ast::FunctionDef) -> Option<&'static str> {
+    func.syntax().children_with_tokens().filter_map(|e| e.into_token()).find_map(|token| match token.kind() {
+        SyntaxKind::KwKernel => Some("kernel"),
+        SyntaxKind::KwVertex => Some("vertex"),
+        SyntaxKind::KwFragment => Some("fragment"),
+        SyntaxKind::KwMesh => Some("mesh"),
+        SyntaxKind::KwObject => Some("object"),
+        _ => None,
+    })
+}
+
+/// Identifiers in `body` followed by a call, e.g. `f(`, `s.f(` or `f<T>(`.
+fn called_names(body: &SyntaxNode) -> Vec<String> {
+    let tokens: Vec<SyntaxToken> = body
+        .descendants_with_tokens()
+        .filter_map(|e| e.into_token())
+        .filter(|token| !matches!(token.kind(), SyntaxKind::Whitespace | SyntaxKind::Comment))
+        .collect();
+
+    let mut names = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind() != SyntaxKind::Ident {
+            continue;
+        }
+        let mut next = i + 1;
+        if tokens.get(next).is_some_and(|t| t.kind() == SyntaxKind::Less) {
+            next = skip_template_args(&tokens, next);
+        }
+        if tokens.get(next).is_some_and(|t| t.kind() == SyntaxKind::LParen) {
+            names.push(token.text().to_string());
+        }
+    }
+    names
+}
+
+/// Index just past the `>` matching the `<` at `open`, or `open` when unbalanced.
+fn skip_template_args(
+    tokens: &[SyntaxToken],
+    open: usize,
+) -> usize {
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        match token.kind() {
+            SyntaxKind::Less => depth += 1,
+            SyntaxKind::Greater => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            },
+            SyntaxKind::Semicolon | SyntaxKind::LBrace | SyntaxKind::RBrace => break,
+            _ => {},
+        }
+    }
+    open
+}
+
+/// Byte offset of a 1-based clang `line:col` location.
+fn offset_of_line_col(
+    source: &str,
+    line: u32,
+    col: u32,
+) -> Option<TextSize> {
+    let line_start = if line <= 1 {
+        0
+    } else {
+        source.match_indices('\n').nth(line as usize - 2).map(|(i, _)| i + 1)?
+    };
+    let offset = line_start + col.saturating_sub(1) as usize;
+    (offset <= source.len()).then(|| TextSize::from(offset as u32))
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/entry_points_tests.rs"]
+mod tests;
@@ -0,0 +1,97 @@
+//! Quick fixes from the fix-it hints the compiler attached to its
+//! diagnostics.
+//!
+//! A diagnostic's own fix-its become one action, preferred when it is the
+//! only one offered. Fix-its from its notes become further actions titled
+//! by the note.
+
+use std::{collections::HashMap, path::Path};
+
+use tower_lsp::lsp_types::{CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, TextEdit, Url, WorkspaceEdit};
+
+use crate::{
+    metal::fix_its::{CompilerFix, FixIt, fixes},
+    text_pos::byte_offset_of_column,
+    vfs::virtual_docs::{document_path, document_uri},
+};
+
+/// A quick fix for each compiler fix-it recorded in `diagnostics`.
+pub fn fix_it_actions(
+    uri: &Url,
+    text: &str,
+    diagnostics: &[Diagnostic],
+) -> Vec<CodeActionOrCommand> {
+    let mut actions = Vec::new();
+    for diagnostic in diagnostics {
+        let fixes = fixes(diagnostic.data.as_ref());
+        let only_fix = fixes.len() == 1;
+        for fix in fixes {
+            let Some(changes) = workspace_changes(&fix.edits) else {
+                continue;
+            };
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: title(&fix, uri, text),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic.clone()]),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(changes),
+                    ..Default::default()
+                }),
+                is_preferred: Some(only_fix && fix.message.is_none()),
+                ..Default::default()
+            }));
+        }
+    }
+    actions
+}
+
+fn workspace_changes(edits: &[FixIt]) -> Option<HashMap<Url, Vec<TextEdit>>> {
+    let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+    for edit in edits {
+        let uri = document_uri(Path::new(&edit.file))?;
+        changes.entry(uri).or_default().push(TextEdit::new(edit.range, edit.replacement.clone()));
+    }
+    (!changes.is_empty()).then_some(changes)
+}
+
+/// The note's text, or a description of a single edit.
+fn title(
+    fix: &CompilerFix,
+    uri: &Url,
+    text: &str,
+) -> String {
+    if let Some(message) = &fix.message {
+        let mut chars = message.chars();
+        return chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default();
+    }
+    let [edit] = fix.edits.as_slice() else {
+        return "Apply compiler fix-it".to_string();
+    };
+    let replaced = replaced_text(edit, uri, text);
+    match (replaced, edit.replacement.is_empty()) {
+        (None, true) => "Remove code".to_string(),
+        (None, false) if edit.range.start == edit.range.end => format!("Insert `{}`", edit.replacement),
+        (None, false) => format!("Replace with `{}`", edit.replacement),
+        (Some(old), true) => format!("Remove `{old}`"),
+        (Some(old), false) => format!("Replace `{old}` with `{}`", edit.replacement),
+    }
+}
+
+/// The single-line text `edit` replaces, when it is in this document.
+fn replaced_text<'a>(
+    edit: &FixIt,
+    uri: &Url,
+    text: &'a str,
+) -> Option<&'a str> {
+    if document_path(uri)? != Path::new(&edit.file) || edit.range.start.line != edit.range.end.line {
+        return None;
+    }
+    let line = text.lines().nth(edit.range.start.line as usize)?;
+    let start = byte_offset_of_column(line, edit.range.start.character);
+    let end = byte_offset_of_column(line, edit.range.end.character);
+    line.get(start..end).filter(|old| !old.is_empty())
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/fix_its_tests.rs"]
+mod tests;
@@ -0,0 +1,651 @@
+//! Regions disabled by preprocessor conditionals.
+//!
+//! Evaluates `#if`/`#ifdef`/`#ifndef`/`#elif`/`#else`/`#endif` against the
+//! effective compiler defines and the `#define`/`#undef` lines of the file
+//! itself, so editors can grey out code the compiler never sees.
+//!
+//! Headers are not followed. A macro the file never mentions counts as
+//! undefined only until the first quoted `#include`, since a project header
+//! could define it; reserved names (`__METAL_VERSION__`, `__HAVE_…`) are
+//! predefined by the compiler or the standard library and always unknown.
+//! Conditions that depend on an unknown macro are treated as possibly
+//! active, so only code that is certainly disabled is reported.
+
+use std::collections::HashMap;
+
+use tower_lsp::lsp_types::{Position, Range};
+
+use crate::text_pos::text_width;
+
+/// Nesting limit for expanding object-like macros inside conditions.
+const MAX_EXPANSION_DEPTH: usize = 32;
+
+/// Line ranges of `source` inside conditional branches that are disabled
+/// under `defines`.
+///
+/// Each range covers the lines between a branch's directive and the next
+/// directive of the same conditional, so the directives stay undimmed.
+pub fn inactive_regions(
+    source: &str,
+    defines: &HashMap<String, String>,
+) -> Vec<Range> {
+    let lines: Vec<&str> = source.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)).collect();
+    let mut scanner = Scanner::new(defines);
+    let mut line = 0;
+    while line < lines.len() {
+        let start = line;
+        let mut text = lines[line].to_string();
+        // Directives continue across backslash-newlines.
+        while text.ends_with('\\') && line + 1 < lines.len() {
+            text.pop();
+            line += 1;
+            text.push_str(lines[line]);
+        }
+        if let Some(directive) = text.trim_start().strip_prefix('#') {
+            scanner.directive(&strip_comments(directive), start, line);
+        }
+        line += 1;
+    }
+    scanner.finish(lines.len());
+
+    scanner
+        .regions
+        .into_iter()
+        .map(|(first, last)| Range {
+            start: Position::new(first as u32, 0),
+            end: Position::new(last as u32, text_width(lines[last])),
+        })
+        .collect()
+}
+
+/// Three-valued truth of a condition or region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Truth {
+    True,
+    False,
+    Unknown,
+}
+
+impl Truth {
+    fn of(value: Value) -> Self {
+        match value {
+            Some(0) => Self::False,
+            Some(_) => Self::True,
+            None => Self::Unknown,
+        }
+    }
+
+    fn and(
+        self,
+        other: Self,
+    ) -> Self {
+        match (self, other) {
+            (Self::False, _) | (_, Self::False) => Self::False,
+            (Self::True, Self::True) => Self::True,
+            _ => Self::Unknown,
+        }
+    }
+
+    fn or(
+        self,
+        other: Self,
+    ) -> Self {
+        match (self, other) {
+            (Self::True, _) | (_, Self::True) => Self::True,
+            (Self::False, Self::False) => Self::False,
+            _ => Self::Unknown,
+        }
+    }
+
+    fn not(self) -> Self {
+        match self {
+            Self::True => Self::False,
+            Self::False => Self::True,
+            Self::Unknown => Self::Unknown,
+        }
+    }
+}
+
+/// What is known about a macro at some point of the file.
+#[derive(Debug, Clone)]
+enum Macro {
+    /// Object-like macro with its replacement text.
+    Object(String),
+    /// Function-like macro; its expansions are not evaluated.
+    Function,
+    Undefined,
+    Unknown,
+}
+
+/// An open `#if` … `#endif` group.
+#[derive(Debug)]
+struct Conditional {
+    /// Whether the code around the group is active.
+    parent: Truth,
+    /// Whether one of the previous branches was taken.
+    taken: Truth,
+    /// Whether the current branch is active.
+    active: Truth,
+    /// First line of the current branch's body.
+    body_start: usize,
+}
+
+struct Scanner {
+    macros: HashMap<String, Macro>,
+    /// Set by the first quoted `#include` in possibly active code.
+    seen_project_include: bool,
+    stack: Vec<Conditional>,
+    /// Inclusive zero-based line spans.
+    regions: Vec<(usize, usize)>,
+}
+
+impl Scanner {
+    fn new(defines: &HashMap<String, String>) -> Self {
+        Self {
+            macros: defines.iter().map(|(name, value)| (name.clone(), Macro::Object(value.clone()))).collect(),
+            seen_project_include: false,
+            stack: Vec::new(),
+            regions: Vec::new(),
+        }
+    }
+
+    fn current(&self) -> Truth {
+        self.stack.last().map_or(Truth::True, |conditional| conditional.active)
+    }
+
+    /// Handle the directive spanning `first..=last`, without its `#`.
+    fn directive(
+        &mut self,
+        directive: &str,
+        first: usize,
+        last: usize,
+    ) {
+        let directive = directive.trim_start();
+        let keyword_end = directive.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(directive.len());
+        let (keyword, rest) = directive.split_at(keyword_end);
+        let rest = rest.trim();
+        match keyword {
+            "if" => {
+                let condition = self.condition(rest);
+                self.open(condition, last);
+            },
+            "ifdef" | "ifndef" => {
+                let defined = self.is_defined(leading_ident(rest));
+                let condition = if keyword == "ifdef" {
+                    defined
+                } else {
+                    defined.not()
+                };
+                self.open(condition, last);
+            },
+            "elif" | "elifdef" | "elifndef" => {
+                let condition = match keyword {
+                    "elif" => self.condition(rest),
+                    "elifdef" => self.is_defined(leading_ident(rest)),
+                    _ => self.is_defined(leading_ident(rest)).not(),
+                };
+                self.branch(condition, first, last);
+            },
+            "else" => self.branch(Truth::True, first, last),
+            "endif" => {
+                if let Some(conditional) = self.stack.pop() {
+                    self.close_branch(&conditional, first);
+                }
+            },
+            "define" => self.define(rest),
+            "undef" => {
+                let name = leading_ident(rest);
+                self.assign(name, Macro::Undefined);
+            },
+            "include" | "import" | "include_next" if rest.starts_with('"') && self.current() != Truth::False => {
+                self.seen_project_include = true;
+            },
+            _ => {},
+        }
+    }
+
+    fn open(
+        &mut self,
+        condition: Truth,
+        directive_line: usize,
+    ) {
+        let parent = self.current();
+        self.stack.push(Conditional {
+            parent,
+            taken: condition,
+            active: parent.and(condition),
+            body_start: directive_line + 1,
+        });
+    }
+
+    /// Start the next branch of the innermost group at `#elif`/`#else`.
+    fn branch(
+        &mut self,
+        condition: Truth,
+        first: usize,
+        last: usize,
+    ) {
+        let Some(mut conditional) = self.stack.pop() else {
+            return;
+        };
+        self.close_branch(&conditional, first);
+        conditional.active = conditional.parent.and(conditional.taken.not()).and(condition);
+        conditional.taken = conditional.taken.or(condition);
+        conditional.body_start = last + 1;
+        self.stack.push(conditional);
+    }
+
+    /// Record the body of the current branch, ending before `end_line`, when
+    /// it is disabled. Branches inside disabled code are covered by the
+    /// enclosing region already.
+    fn close_branch(
+        &mut self,
+        conditional: &Conditional,
+        end_line: usize,
+    ) {
+        if conditional.active == Truth::False && conditional.parent != Truth::False && conditional.body_start < end_line
+        {
+            self.regions.push((conditional.body_start, end_line - 1));
+        }
+    }
+
+    /// Close groups left open at the end of the file.
+    fn finish(
+        &mut self,
+        line_count: usize,
+    ) {
+        while let Some(conditional) = self.stack.pop() {
+            self.close_branch(&conditional, line_count);
+        }
+    }
+
+    fn define(
+        &mut self,
+        rest: &str,
+    ) {
+        let name = leading_ident(rest);
+        let after_name = &rest[name.len()..];
+        let value = if after_name.starts_with('(') {
+            Macro::Function
+        } else {
+            Macro::Object(after_name.trim().to_string())
+        };
+        self.assign(name, value);
+    }
+
+    /// Apply a `#define`/`#undef` of `name`, which only has a known effect in
+    /// code that is certainly active.
+    fn assign(
+        &mut self,
+        name: &str,
+        value: Macro,
+    ) {
+        if name.is_empty() {
+            return;
+        }
+        match self.current() {
+            Truth::True => {
+                self.macros.insert(name.to_string(), value);
+            },
+            Truth::Unknown => {
+                self.macros.insert(name.to_string(), Macro::Unknown);
+            },
+            Truth::False => {},
+        }
+    }
+
+    fn lookup(
+        &self,
+        name: &str,
+    ) -> Macro {
+        if let Some(known) = self.macros.get(name) {
+            return known.clone();
+        }
+        let reserved = name.starts_with("__") || name.starts_with('_') && name[1..].starts_with(char::is_uppercase);
+        if reserved || self.seen_project_include {
+            Macro::Unknown
+        } else {
+            Macro::Undefined
+        }
+    }
+
+    fn is_defined(
+        &self,
+        name: &str,
+    ) -> Truth {
+        if name.is_empty() {
+            return Truth::Unknown;
+        }
+        match self.lookup(name) {
+            Macro::Object(_) | Macro::Function => Truth::True,
+            Macro::Undefined => Truth::False,
+            Macro::Unknown => Truth::Unknown,
+        }
+    }
+
+    fn condition(
+        &self,
+        expression: &str,
+    ) -> Truth {
+        Truth::of(self.evaluate(expression, 0).unwrap_or(None))
+    }
+
+    fn evaluate(
+        &self,
+        expression: &str,
+        depth: usize,
+    ) -> Result<Value, Malformed> {
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser {
+            scanner: self,
+            tokens: &tokens,
+            position: 0,
+            depth,
+        };
+        let value = parser.ternary()?;
+        if parser.position != tokens.len() {
+            return Err(Malformed);
+        }
+        Ok(value)
+    }
+}
+
+/// Value of a condition; `None` when it depends on something unknown.
+type Value = Option<i64>;
+
+/// The condition is not a valid expression (or uses unsupported syntax).
+#[derive(Debug)]
+struct Malformed;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(Value),
+    Ident(String),
+    Punct(&'static str),
+}
+
+const PUNCTUATORS: [&str; 24] = [
+    "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "(", ")", "!", "~", "*", "/", "%", "+", "-", "<", ">", "&", "^",
+    "|", "?", ":",
+];
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, Malformed> {
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim_start();
+    while let Some(first) = rest.chars().next() {
+        let length = if first.is_ascii_digit() {
+            let length = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '\'').unwrap_or(rest.len());
+            tokens.push(Token::Number(parse_integer(&rest[..length])));
+            length
+        } else if first.is_ascii_alphabetic() || first == '_' {
+            let length = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..length].to_string()));
+            length
+        } else if first == '\'' {
+            // Character literals are rare in conditions; leave them unknown.
+            let length = rest[1..].find('\'').map(|end| end + 2).ok_or(Malformed)?;
+            tokens.push(Token::Number(None));
+            length
+        } else {
+            let punct = PUNCTUATORS.iter().find(|punct| rest.starts_with(**punct)).ok_or(Malformed)?;
+            tokens.push(Token::Punct(punct));
+            punct.len()
+        };
+        rest = rest[length..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Parse a decimal, hex, octal or binary literal with optional `u`/`l` suffixes.
+fn parse_integer(literal: &str) -> Value {
+    let digits: String = literal.chars().filter(|&c| c != '\'').collect();
+    let digits = digits.trim_end_matches(['u', 'U', 'l', 'L']);
+    let (digits, radix) = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        (hex, 16)
+    } else if let Some(binary) = digits.strip_prefix("0b").or_else(|| digits.strip_prefix("0B")) {
+        (binary, 2)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        (&digits[1..], 8)
+    } else {
+        (digits, 10)
+    };
+    u64::from_str_radix(digits, radix).ok().map(|value| value as i64)
+}
+
+/// Precedence-climbing evaluator for `#if` expressions.
+struct Parser<'a> {
+    scanner: &'a Scanner,
+    tokens: &'a [Token],
+    position: usize,
+    depth: usize,
+}
+
+/// Binary operators from lowest to highest precedence.
+const BINARY_LEVELS: [&[&str]; 10] = [
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<", ">", "<=", ">="],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+impl Parser<'_> {
+    fn peek_punct(&self) -> Option<&'static str> {
+        match self.tokens.get(self.position) {
+            Some(Token::Punct(punct)) => Some(punct),
+            _ => None,
+        }
+    }
+
+    fn expect(
+        &mut self,
+        punct: &str,
+    ) -> Result<(), Malformed> {
+        if self.peek_punct() != Some(punct) {
+            return Err(Malformed);
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn ternary(&mut self) -> Result<Value, Malformed> {
+        let condition = self.binary(0)?;
+        if self.peek_punct() != Some("?") {
+            return Ok(condition);
+        }
+        self.position += 1;
+        let then = self.ternary()?;
+        self.expect(":")?;
+        let otherwise = self.ternary()?;
+        Ok(match condition {
+            Some(0) => otherwise,
+            Some(_) => then,
+            None => then.filter(|_| then == otherwise),
+        })
+    }
+
+    fn binary(
+        &mut self,
+        level: usize,
+    ) -> Result<Value, Malformed> {
+        if level == BINARY_LEVELS.len() {
+            return self.unary();
+        }
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(op) = self.peek_punct().filter(|op| BINARY_LEVELS[level].contains(op)) {
+            self.position += 1;
+            let rhs = self.binary(level + 1)?;
+            lhs = apply_binary(op, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Value, Malformed> {
+        let Some(op) = self.peek_punct().filter(|op| matches!(*op, "!" | "~" | "-" | "+")) else {
+            return self.primary();
+        };
+        self.position += 1;
+        let operand = self.unary()?;
+        Ok(operand.map(|value| match op {
+            "!" => i64::from(value == 0),
+            "~" => !value,
+            "-" => value.wrapping_neg(),
+            _ => value,
+        }))
+    }
+
+    fn primary(&mut self) -> Result<Value, Malformed> {
+        let token = self.tokens.get(self.position).ok_or(Malformed)?.clone();
+        self.position += 1;
+        match token {
+            Token::Number(value) => Ok(value),
+            Token::Punct("(") => {
+                let value = self.ternary()?;
+                self.expect(")")?;
+                Ok(value)
+            },
+            Token::Punct(_) => Err(Malformed),
+            Token::Ident(name) if name == "defined" => {
+                let parenthesized = self.peek_punct() == Some("(");
+                if parenthesized {
+                    self.position += 1;
+                }
+                let Some(Token::Ident(name)) = self.tokens.get(self.position) else {
+                    return Err(Malformed);
+                };
+                self.position += 1;
+                if parenthesized {
+                    self.expect(")")?;
+                }
+                Ok(match self.scanner.is_defined(name) {
+                    Truth::True => Some(1),
+                    Truth::False => Some(0),
+                    Truth::Unknown => None,
+                })
+            },
+            Token::Ident(name) => self.identifier(&name),
+        }
+    }
+
+    fn identifier(
+        &mut self,
+        name: &str,
+    ) -> Result<Value, Malformed> {
+        match name {
+            "true" => return Ok(Some(1)),
+            "false" => return Ok(Some(0)),
+            _ => {},
+        }
+        match self.scanner.lookup(name) {
+            Macro::Undefined => Ok(Some(0)),
+            Macro::Object(value) if self.depth < MAX_EXPANSION_DEPTH => {
+                Ok(self.scanner.evaluate(&value, self.depth + 1).unwrap_or(None))
+            },
+            Macro::Object(_) | Macro::Unknown => Ok(None),
+            Macro::Function => {
+                self.skip_call_arguments()?;
+                Ok(None)
+            },
+        }
+    }
+
+    /// Step over the parenthesized arguments of a function-like macro call.
+    fn skip_call_arguments(&mut self) -> Result<(), Malformed> {
+        if self.peek_punct() != Some("(") {
+            return Ok(());
+        }
+        let mut depth = 0usize;
+        while let Some(token) = self.tokens.get(self.position) {
+            self.position += 1;
+            match token {
+                Token::Punct("(") => depth += 1,
+                Token::Punct(")") => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                },
+                _ => {},
+            }
+        }
+        Err(Malformed)
+    }
+}
+
+fn apply_binary(
+    op: &str,
+    lhs: Value,
+    rhs: Value,
+) -> Value {
+    // Logical operators short-circuit around unknown operands.
+    match (op, lhs, rhs) {
+        ("&&", Some(0), _) | ("&&", _, Some(0)) => return Some(0),
+        ("||", Some(l), _) | ("||", _, Some(l)) if l != 0 => return Some(1),
+        _ => {},
+    }
+    let (lhs, rhs) = (lhs?, rhs?);
+    Some(match op {
+        "||" => i64::from(lhs != 0 || rhs != 0),
+        "&&" => i64::from(lhs != 0 && rhs != 0),
+        "|" => lhs | rhs,
+        "^" => lhs ^ rhs,
+        "&" => lhs & rhs,
+        "==" => i64::from(lhs == rhs),
+        "!=" => i64::from(lhs != rhs),
+        "<" => i64::from(lhs < rhs),
+        ">" => i64::from(lhs > rhs),
+        "<=" => i64::from(lhs <= rhs),
+        ">=" => i64::from(lhs >= rhs),
+        "<<" => lhs.checked_shl(u32::try_from(rhs).ok()?)?,
+        ">>" => lhs.checked_shr(u32::try_from(rhs).ok()?)?,
+        "+" => lhs.wrapping_add(rhs),
+        "-" => lhs.wrapping_sub(rhs),
+        "*" => lhs.wrapping_mul(rhs),
+        "/" => lhs.checked_div(rhs)?,
+        _ => lhs.checked_rem(rhs)?,
+    })
+}
+
+/// The identifier at the start of `text`, or `""`.
+fn leading_ident(text: &str) -> &str {
+    let text = text.trim_start();
+    let end = text.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(text.len());
+    &text[..end]
+}
+
+/// Remove `//` and `/* */` comments from a directive line.
+pub(crate) fn strip_comments(directive: &str) -> String {
+    let mut stripped = String::with_capacity(directive.len());
+    let mut rest = directive;
+    loop {
+        let line_comment = rest.find("//");
+        let block_comment = rest.find("/*");
+        match (line_comment, block_comment) {
+            (Some(line), block) if block.is_none_or(|block| line < block) => {
+                stripped.push_str(&rest[..line]);
+                return stripped;
+            },
+            (_, Some(block)) => {
+                stripped.push_str(&rest[..block]);
+                stripped.push(' ');
+                match rest[block + 2..].find("*/") {
+                    Some(end) => rest = &rest[block + 2 + end + 2..],
+                    None => return stripped,
+                }
+            },
+            _ => {
+                stripped.push_str(rest);
+                return stripped;
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/inactive_regions_tests.rs"]
+mod tests;
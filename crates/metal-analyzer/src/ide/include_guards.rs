@@ -0,0 +1,272 @@
+//! Headers without `#pragma once` or an include guard, guards not named
+//! after their header, and the quick fixes for both.
+//!
+//! A guard follows the convention when its macro ends with the header's
+//! file name, upper-cased with every other character turned into `_`:
+//! `LIGHT_H`, `SHADERS_LIGHT_H` and `MYLIB_LIGHT_H_` all guard `Light.h`.
+//! Guards added or renamed by the quick fixes are named after the path from
+//! the workspace root, `SHADERS_COMMON_LIGHT_H` for `shaders/common/Light.h`.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use serde_json::json;
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range,
+    TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::text_pos::{column_of_byte_offset, position_from_byte_offset, text_width};
+
+pub const INCLUDE_GUARD_CODE: &str = "include-guard";
+
+/// What keeps a header from being read twice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncludeGuard {
+    PragmaOnce,
+    /// `#ifndef NAME` or `#if !defined(NAME)`, then `#define NAME`.
+    Macro {
+        name: String,
+        /// The name in the `#ifndef` and in the `#define`.
+        ranges: [Range; 2],
+    },
+}
+
+/// The guard of `source`: `#pragma once` anywhere, or an `#ifndef` and
+/// `#define` of the same macro before any other code.
+pub fn include_guard(source: &str) -> Option<IncludeGuard> {
+    if source.lines().any(|line| directive(line, "pragma").is_some_and(|rest| rest.trim() == "once")) {
+        return Some(IncludeGuard::PragmaOnce);
+    }
+    let mut lines = code_lines(source);
+    let (ifndef_line, ifndef, ifndef_text) = lines.next()?;
+    let name = guarded_macro(ifndef)?;
+    let (define_line, define, define_text) = lines.next()?;
+    let defined = directive(define, "define")?.split_whitespace().next()?;
+    (defined == name).then(|| IncludeGuard::Macro {
+        name: name.to_string(),
+        ranges: [name_range(ifndef_line, ifndef_text, name), name_range(define_line, define_text, defined)],
+    })
+}
+
+/// The guard macro for the header at `relative`, a path from the workspace
+/// root or a bare file name.
+pub fn guard_name(relative: &Path) -> String {
+    let name: String = relative
+        .to_string_lossy()
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() {
+                ch.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.starts_with(|ch: char| ch.is_ascii_digit()) {
+        format!("INCLUDE_{name}")
+    } else {
+        name
+    }
+}
+
+/// The guard macro for the header at `path`, named after its path from the
+/// first of `workspace_roots` containing it.
+pub fn expected_guard_name(
+    path: &Path,
+    workspace_roots: &[PathBuf],
+) -> String {
+    let relative = workspace_roots
+        .iter()
+        .find_map(|root| path.strip_prefix(root).ok())
+        .or_else(|| path.file_name().map(Path::new))
+        .unwrap_or(path);
+    guard_name(relative)
+}
+
+/// Whether the guard macro `name` ends with the file name of `path`.
+pub fn follows_convention(
+    name: &str,
+    path: &Path,
+) -> bool {
+    let Some(file_name) = path.file_name() else {
+        return true;
+    };
+    let file = guard_name(Path::new(file_name));
+    let name = name.trim_matches('_');
+    name.strip_suffix(file.trim_matches('_')).is_some_and(|prefix| prefix.is_empty() || prefix.ends_with('_'))
+}
+
+/// A warning when the header at `path` has no guard, or when its guard
+/// macro is not named after it. Each carries the conventional guard name
+/// for [`include_guard_actions`].
+pub fn include_guard_diagnostics(
+    path: &Path,
+    text: &str,
+    workspace_roots: &[PathBuf],
+) -> Vec<Diagnostic> {
+    let expected = expected_guard_name(path, workspace_roots);
+    let (range, message) = match include_guard(text) {
+        Some(IncludeGuard::PragmaOnce) => return Vec::new(),
+        Some(IncludeGuard::Macro {
+            name,
+            ranges,
+        }) => {
+            if follows_convention(&name, path) {
+                return Vec::new();
+            }
+            (ranges[0], format!("include guard `{name}` is not named after the header; expected `{expected}`"))
+        },
+        None => {
+            let first_line = text.lines().next().unwrap_or_default();
+            (
+                Range::new(Position::new(0, 0), Position::new(0, text_width(first_line))),
+                "header has no include guard or `#pragma once`".to_string(),
+            )
+        },
+    };
+    vec![Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::WARNING),
+        code: Some(NumberOrString::String(INCLUDE_GUARD_CODE.to_string())),
+        source: Some("metal-analyzer".to_string()),
+        message,
+        data: Some(json!({ "guard": expected })),
+        ..Default::default()
+    }]
+}
+
+/// Quick fixes for the include-guard diagnostics of `text`: add
+/// `#pragma once` or a guard to a header without one, or rename a guard to
+/// the conventional name.
+pub fn include_guard_actions(
+    uri: &Url,
+    text: &str,
+    diagnostics: &[Diagnostic],
+) -> Vec<CodeActionOrCommand> {
+    let mut actions = Vec::new();
+    for diagnostic in diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.code == Some(NumberOrString::String(INCLUDE_GUARD_CODE.to_string())))
+    {
+        let Some(expected) = diagnostic.data.as_ref().and_then(|data| data["guard"].as_str()) else {
+            continue;
+        };
+        let action = |title: String, edits: Vec<TextEdit>, preferred: bool| {
+            CodeActionOrCommand::CodeAction(CodeAction {
+                title,
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic.clone()]),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri.clone(), edits)])),
+                    ..Default::default()
+                }),
+                is_preferred: Some(preferred),
+                ..Default::default()
+            })
+        };
+        match include_guard(text) {
+            Some(IncludeGuard::PragmaOnce) => {},
+            Some(IncludeGuard::Macro {
+                name,
+                ranges,
+            }) => {
+                if name != expected {
+                    let edits = ranges.iter().map(|range| TextEdit::new(*range, expected.to_string())).collect();
+                    actions.push(action(format!("Rename include guard to `{expected}`"), edits, true));
+                }
+            },
+            None => {
+                // After the comments opening the file, such as a license.
+                let start = code_lines(text)
+                    .next()
+                    .map_or_else(|| position_from_byte_offset(text, text.len()), |(line, _, _)| Position::new(line, 0));
+                let end = position_from_byte_offset(text, text.len());
+                let separator = if text.is_empty() || text.ends_with('\n') {
+                    ""
+                } else {
+                    "\n"
+                };
+                actions.push(action(
+                    "Add `#pragma once`".to_string(),
+                    vec![TextEdit::new(Range::new(start, start), "#pragma once\n\n".to_string())],
+                    true,
+                ));
+                actions.push(action(
+                    format!("Add include guard `{expected}`"),
+                    vec![
+                        TextEdit::new(Range::new(start, start), format!("#ifndef {expected}\n#define {expected}\n\n")),
+                        TextEdit::new(Range::new(end, end), format!("{separator}\n#endif // {expected}\n")),
+                    ],
+                    false,
+                ));
+            },
+        }
+    }
+    actions
+}
+
+/// The lines of `source` holding code, each with its index, the code left
+/// after comments and the whole line, skipping blank and comment-only
+/// lines.
+fn code_lines(source: &str) -> impl Iterator<Item = (u32, &str, &str)> {
+    let mut in_block_comment = false;
+    source.lines().enumerate().filter_map(move |(index, full_line)| {
+        let mut line = full_line.trim();
+        if in_block_comment {
+            let end = line.find("*/")?;
+            in_block_comment = false;
+            line = line[end + 2..].trim();
+        }
+        if let Some(comment) = line.strip_prefix("/*") {
+            match comment.find("*/") {
+                Some(end) => line = comment[end + 2..].trim(),
+                None => {
+                    in_block_comment = true;
+                    return None;
+                },
+            }
+        }
+        (!line.is_empty() && !line.starts_with("//")).then_some((index as u32, line, full_line))
+    })
+}
+
+/// The macro tested by `#ifndef NAME` or `#if !defined(NAME)` on `line`.
+pub(crate) fn guarded_macro(line: &str) -> Option<&str> {
+    if let Some(rest) = directive(line, "ifndef") {
+        return rest.split_whitespace().next();
+    }
+    let rest = directive(line, "if")?.trim_start().strip_prefix('!')?.trim_start().strip_prefix("defined")?;
+    let rest = rest.trim_start();
+    let rest = rest.strip_prefix('(').unwrap_or(rest).trim_start();
+    let end = rest.find(|ch: char| !ch.is_ascii_alphanumeric() && ch != '_').unwrap_or(rest.len());
+    (end > 0).then(|| &rest[..end])
+}
+
+/// The text after `#name` on `line`, if it is that directive.
+fn directive<'a>(
+    line: &'a str,
+    name: &str,
+) -> Option<&'a str> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start().strip_prefix(name)?;
+    (rest.is_empty() || rest.starts_with(char::is_whitespace)).then_some(rest)
+}
+
+/// Range of `name`, a slice of `text`, the text of line `line`.
+fn name_range(
+    line: u32,
+    text: &str,
+    name: &str,
+) -> Range {
+    let start = name.as_ptr() as usize - text.as_ptr() as usize;
+    Range::new(
+        Position::new(line, column_of_byte_offset(text, start)),
+        Position::new(line, column_of_byte_offset(text, start + name.len())),
+    )
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/include_guards_tests.rs"]
+mod tests;
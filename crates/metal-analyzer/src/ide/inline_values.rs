@@ -0,0 +1,190 @@
+//! Inline values while debugging (`textDocument/inlineValue`).
+//!
+//! The analyzer only knows where the variables of the stopped frame are
+//! spelled; their values come from the debugger the client talks to (for
+//! example a GPU debugger extension replaying captured buffers). Each use of
+//! a local or parameter that is in scope at the stopped location becomes a
+//! variable lookup the client resolves against the frame.
+
+use std::collections::HashSet;
+
+use rowan::{TextRange, TextSize};
+use tower_lsp::lsp_types::{InlineValue, InlineValueVariableLookup, Range};
+
+use crate::syntax::{
+    ast::{self, AstNode},
+    cst::{SyntaxNode, SyntaxToken},
+    helpers,
+    kind::SyntaxKind,
+};
+
+/// A parameter or local declared in the stopped function.
+#[derive(Debug, Clone)]
+struct Local {
+    name: String,
+    declared_at: TextSize,
+    /// Where the name refers to this declaration.
+    scope: TextRange,
+}
+
+/// Variable lookups for the locals spelled inside `range`, up to and
+/// including the stopped line, of the function the debugger stopped in.
+pub fn inline_values(
+    root: &SyntaxNode,
+    source: &str,
+    range: Range,
+    stopped_location: Range,
+) -> Vec<InlineValue> {
+    let (Some(visible_start), Some(visible_end), Some(stopped)) =
+        (offset(source, range.start), offset(source, range.end), offset(source, stopped_location.start))
+    else {
+        return Vec::new();
+    };
+    let Some((function, body)) = root.descendants().filter_map(ast::FunctionDef::cast).find_map(|function| {
+        let body = function.body()?;
+        body.syntax().text_range().contains_inclusive(stopped).then_some((function, body))
+    }) else {
+        return Vec::new();
+    };
+    let locals = function_locals(&function, &body);
+    let last_line = stopped_location.end.line;
+
+    let mut seen = HashSet::new();
+    let mut values = Vec::new();
+    for token in function.syntax().descendants_with_tokens().filter_map(|element| element.into_token()) {
+        let start = token.text_range().start();
+        if token.kind() != SyntaxKind::Ident || start < visible_start || start > visible_end {
+            continue;
+        }
+        let range = helpers::range_to_lsp(token.text_range(), source);
+        if range.start.line > last_line || is_member_or_qualified(&token) {
+            continue;
+        }
+        let Some(local) = resolve(&locals, token.text(), start) else {
+            continue;
+        };
+        // A declaration on the stopped line belongs to the frame even when
+        // the stop column is before its name.
+        let declared_line = crate::text_pos::position_from_byte_offset(source, local.declared_at.into()).line;
+        if local.scope.end() < stopped || declared_line > last_line {
+            continue;
+        }
+        if seen.insert((range.start.line, local.name.clone())) {
+            values.push(InlineValue::VariableLookup(InlineValueVariableLookup {
+                range,
+                variable_name: Some(local.name.clone()),
+                case_sensitive_lookup: true,
+            }));
+        }
+    }
+    values
+}
+
+fn offset(
+    source: &str,
+    position: tower_lsp::lsp_types::Position,
+) -> Option<TextSize> {
+    crate::text_pos::byte_offset_from_position(source, position).map(|offset| TextSize::from(offset as u32))
+}
+
+/// The innermost declaration of `name` visible at `at`.
+fn resolve<'a>(
+    locals: &'a [Local],
+    name: &str,
+    at: TextSize,
+) -> Option<&'a Local> {
+    locals
+        .iter()
+        .filter(|local| local.name == name && local.declared_at <= at && local.scope.contains_inclusive(at))
+        .max_by_key(|local| local.declared_at)
+}
+
+fn function_locals(
+    function: &ast::FunctionDef,
+    body: &ast::Block,
+) -> Vec<Local> {
+    let body_range = body.syntax().text_range();
+
+    let parameters: Vec<ast::Parameter> =
+        function.parameter_list().map(|list| list.parameters().collect()).unwrap_or_default();
+    let mut locals: Vec<Local> = parameters
+        .iter()
+        .filter_map(ast::Parameter::name_token)
+        .map(|name| Local {
+            name: name.text().to_string(),
+            declared_at: name.text_range().start(),
+            scope: TextRange::new(name.text_range().start(), body_range.end()),
+        })
+        .collect();
+
+    for decl in body.syntax().descendants().filter(|node| node.kind() == SyntaxKind::DeclStmt) {
+        let scope_end = declaration_scope_end(&decl).unwrap_or(body_range.end());
+        for name in declared_names(&decl) {
+            let declared_at = name.text_range().start();
+            locals.push(Local {
+                name: name.text().to_string(),
+                declared_at,
+                scope: TextRange::new(declared_at, scope_end.max(declared_at)),
+            });
+        }
+    }
+    locals
+}
+
+/// End of the block a declaration lives in. A `for` header declaration is
+/// scoped to the loop body, which the CST keeps as the next sibling block.
+fn declaration_scope_end(decl: &SyntaxNode) -> Option<TextSize> {
+    let owner = decl.ancestors().skip(1).find(|node| matches!(node.kind(), SyntaxKind::Block | SyntaxKind::ForStmt))?;
+    if owner.kind() == SyntaxKind::ForStmt {
+        let body = owner.siblings(rowan::Direction::Next).skip(1).find(|node| node.kind() == SyntaxKind::Block);
+        return Some(body.map_or(owner.text_range().end(), |body| body.text_range().end()));
+    }
+    Some(owner.text_range().end())
+}
+
+/// Names declared by `float a = 1, b(2);`: the identifier after the type and
+/// each one after a top-level comma.
+fn declared_names(decl: &SyntaxNode) -> Vec<SyntaxToken> {
+    let mut names = Vec::new();
+    let mut depth = 0usize;
+    let mut expect_name = false;
+    for element in decl.children_with_tokens() {
+        match element {
+            rowan::NodeOrToken::Node(node) => {
+                expect_name = node.kind() == SyntaxKind::TypeRef;
+            },
+            rowan::NodeOrToken::Token(token) => match token.kind() {
+                SyntaxKind::Whitespace | SyntaxKind::Comment => {},
+                SyntaxKind::Ident if expect_name && depth == 0 => {
+                    names.push(token);
+                    expect_name = false;
+                },
+                SyntaxKind::LParen | SyntaxKind::LBracket | SyntaxKind::LBrace => {
+                    depth += 1;
+                    expect_name = false;
+                },
+                SyntaxKind::RParen | SyntaxKind::RBracket | SyntaxKind::RBrace => {
+                    depth = depth.saturating_sub(1);
+                },
+                SyntaxKind::Comma => expect_name = depth == 0,
+                // `float *p`, `thread float &r`
+                SyntaxKind::Star | SyntaxKind::Amp if expect_name => {},
+                _ => expect_name = false,
+            },
+        }
+    }
+    names
+}
+
+/// `a.x`, `p->x` and `ns::x` name something other than a local `x`.
+fn is_member_or_qualified(token: &SyntaxToken) -> bool {
+    let previous = std::iter::successors(token.prev_token(), |token| token.prev_token())
+        .find(|token| !matches!(token.kind(), SyntaxKind::Whitespace | SyntaxKind::Comment));
+    previous.is_some_and(|previous| {
+        matches!(previous.kind(), SyntaxKind::Dot | SyntaxKind::Arrow | SyntaxKind::DoubleColon)
+    })
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/inline_values_tests.rs"]
+mod tests;
@@ -0,0 +1,92 @@
+//! Warnings on builtins introduced after the targeted language version.
+//!
+//! Uses in preprocessor branches that are disabled for the target, such as
+//! `#if __METAL_VERSION__ >= 310`, are not reported. Member names and names
+//! qualified by a namespace other than `metal` are not builtins.
+
+use std::collections::{HashMap, HashSet};
+
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Range};
+
+use crate::{
+    ide::inactive_regions::inactive_regions,
+    metal::{
+        builtins::{self, BuiltinKind},
+        language_version::LanguageVersion,
+    },
+    syntax::{cst::SyntaxNode, kind::SyntaxKind},
+    text_pos::position_from_byte_offset,
+};
+
+pub const LANGUAGE_VERSION_CODE: &str = "language-version";
+
+/// A warning on each use of a builtin that `version` does not have.
+///
+/// `defines` are the effective compiler defines, which decide the disabled
+/// preprocessor branches.
+pub fn unavailable_builtins(
+    root: &SyntaxNode,
+    text: &str,
+    version: LanguageVersion,
+    defines: &HashMap<String, String>,
+) -> Vec<Diagnostic> {
+    let inactive_lines: HashSet<u32> =
+        inactive_regions(text, defines).into_iter().flat_map(|range| range.start.line..=range.end.line).collect();
+    let tokens: Vec<_> = root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !matches!(token.kind(), SyntaxKind::Whitespace | SyntaxKind::Comment))
+        .collect();
+
+    let mut diagnostics = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if !matches!(token.kind(), SyntaxKind::Ident | SyntaxKind::KwBFloat | SyntaxKind::KwBFloat16) {
+            continue;
+        }
+        let before = |back: usize| i.checked_sub(back).map(|index| &tokens[index]);
+        let mut start = usize::from(token.text_range().start());
+        let name = match before(1).map(|previous| previous.kind()) {
+            Some(SyntaxKind::Dot | SyntaxKind::Arrow) => continue,
+            Some(SyntaxKind::DoubleColon) => {
+                match before(2).filter(|qualifier| qualifier.kind() == SyntaxKind::Ident) {
+                    Some(qualifier) if qualifier.text() == "metal" => token.text().to_string(),
+                    Some(qualifier) if qualifier.text() == "ray_tracing" => {
+                        start = usize::from(qualifier.text_range().start());
+                        format!("ray_tracing::{}", token.text())
+                    },
+                    _ => continue,
+                }
+            },
+            _ => token.text().to_string(),
+        };
+        let Some(entry) = builtins::lookup(&name) else {
+            continue;
+        };
+        let Some(since) = entry.since.filter(|since| *since > version) else {
+            continue;
+        };
+        if !matches!(entry.kind, BuiltinKind::Type | BuiltinKind::Function | BuiltinKind::Constant) {
+            continue;
+        }
+        let range = Range::new(
+            position_from_byte_offset(text, start),
+            position_from_byte_offset(text, usize::from(token.text_range().end())),
+        );
+        if inactive_lines.contains(&range.start.line) {
+            continue;
+        }
+        diagnostics.push(Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: Some(NumberOrString::String(LANGUAGE_VERSION_CODE.to_string())),
+            source: Some("metal-analyzer".to_string()),
+            message: format!("`{name}` requires Metal {since}, but Metal {version} is targeted"),
+            ..Default::default()
+        });
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/language_version_tests.rs"]
+mod tests;
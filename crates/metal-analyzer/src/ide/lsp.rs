@@ -1,6 +1,11 @@
-use tower_lsp::lsp_types::{GotoDefinitionResponse, Location, Position, Range, Url};
+use std::path::Path;
 
-use crate::ide::navigation::{IdeLocation, IdePosition, IdeRange, NavigationTarget};
+use tower_lsp::lsp_types::{GotoDefinitionResponse, Location, Position, Range};
+
+use crate::{
+    ide::navigation::{IdeLocation, IdePosition, IdeRange, NavigationTarget},
+    vfs::virtual_docs::document_uri,
+};
 
 pub fn lsp_position_to_ide(position: Position) -> IdePosition {
     IdePosition::new(position.line, position.character)
@@ -19,7 +24,7 @@ pub fn ide_range_to_lsp(range: IdeRange) -> Range {
 }
 
 pub fn ide_location_to_lsp(location: IdeLocation) -> Option<Location> {
-    let uri = Url::from_file_path(&location.file_path).ok()?;
+    let uri = document_uri(Path::new(&location.file_path))?;
     Some(Location {
         uri,
         range: ide_range_to_lsp(location.range),
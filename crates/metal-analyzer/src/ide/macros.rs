@@ -0,0 +1,763 @@
+//! Macro definitions and expansion.
+//!
+//! [`MacroIndex`] tracks the `#define`/`#undef` lines of a file, the project
+//! headers it includes and the `-D` flags, so an invocation can be expanded
+//! without running the compiler. Expansion follows the preprocessor rules
+//! that matter for kernel generators: arguments are fully expanded before
+//! substitution unless they are operands of `#` or `##`, `__VA_ARGS__` takes
+//! the variadic arguments, and a macro is not expanded again inside its own
+//! replacement.
+//!
+//! A function-like macro produced at the end of a replacement does not take
+//! its arguments from the text after the invocation.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use tower_lsp::lsp_types::{Position, Range};
+
+use crate::{
+    ide::{inactive_regions::strip_comments, include_guards::guarded_macro},
+    text_pos::{byte_offset_from_position, position_from_byte_offset},
+};
+
+/// Nesting limit for expanding macros inside replacements.
+const MAX_EXPANSION_DEPTH: usize = 64;
+
+/// A `#define` in effect at the end of the indexed sources.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroDefinition {
+    pub name: String,
+    /// Parameter names of a function-like macro; `None` for object-like ones.
+    pub params: Option<Vec<String>>,
+    /// Whether the last parameter is `...` or `name...`.
+    pub variadic: bool,
+    /// Replacement text with comments and line continuations removed.
+    pub body: String,
+    /// Defining file; `None` for `-D` flags.
+    pub file: Option<PathBuf>,
+    /// Zero-based line of the `#define`.
+    pub line: u32,
+}
+
+impl MacroDefinition {
+    /// The definition as it would be written in source, e.g. `#define MAX(a, b) ((a) > (b) ? (a) : (b))`.
+    pub fn signature(&self) -> String {
+        let mut signature = format!("#define {}", self.name);
+        if let Some(params) = &self.params {
+            let mut names = params.clone();
+            if self.variadic {
+                match names.pop() {
+                    Some(name) if name != "__VA_ARGS__" => names.push(format!("{name}...")),
+                    _ => names.push("...".to_string()),
+                }
+            }
+            signature.push_str(&format!("({})", names.join(", ")));
+        }
+        if !self.body.is_empty() {
+            signature.push(' ');
+            signature.push_str(&self.body);
+        }
+        signature
+    }
+
+    /// Whether `other` has the same parameters and replacement list.
+    pub fn same_replacement(
+        &self,
+        other: &MacroDefinition,
+    ) -> bool {
+        self.params == other.params && self.variadic == other.variadic && self.body == other.body
+    }
+}
+
+/// Macros defined by compiler flags and `#define` lines, by name.
+#[derive(Debug, Clone, Default)]
+pub struct MacroIndex {
+    macros: HashMap<String, MacroDefinition>,
+}
+
+impl MacroIndex {
+    /// Index holding the effective `-D` defines as object-like macros.
+    pub fn from_defines(defines: &HashMap<String, String>) -> Self {
+        let macros = defines
+            .iter()
+            .map(|(name, value)| {
+                let definition = MacroDefinition {
+                    name: name.clone(),
+                    params: None,
+                    variadic: false,
+                    body: value.trim().to_string(),
+                    file: None,
+                    line: 0,
+                };
+                (name.clone(), definition)
+            })
+            .collect();
+        Self {
+            macros,
+        }
+    }
+
+    /// Apply the `#define` and `#undef` lines of `source` in order.
+    ///
+    /// Conditionals are not evaluated: the last definition of a name wins.
+    pub fn add_source(
+        &mut self,
+        source: &str,
+        file: Option<&Path>,
+    ) {
+        for (line, text) in directive_lines(source) {
+            let Some(directive) = text.trim_start().strip_prefix('#') else {
+                continue;
+            };
+            let directive = strip_comments(directive);
+            let directive = directive.trim_start();
+            if let Some(rest) = directive.strip_prefix("define") {
+                if let Some(definition) = parse_define(rest, file, line) {
+                    self.macros.insert(definition.name.clone(), definition);
+                }
+            } else if let Some(rest) = directive.strip_prefix("undef") {
+                self.macros.remove(leading_ident(rest.trim_start()));
+            }
+        }
+    }
+
+    pub fn get(
+        &self,
+        name: &str,
+    ) -> Option<&MacroDefinition> {
+        self.macros.get(name)
+    }
+}
+
+/// Every `#define` in `source` in order, including those later `#undef`ed
+/// or in alternative conditional branches.
+pub fn macro_definitions(
+    source: &str,
+    file: Option<&Path>,
+) -> Vec<MacroDefinition> {
+    directive_lines(source)
+        .into_iter()
+        .filter_map(|(line, text)| {
+            let directive = strip_comments(text.trim_start().strip_prefix('#')?);
+            parse_define(directive.trim_start().strip_prefix("define")?, file, line)
+        })
+        .collect()
+}
+
+/// The same macro defined with different replacements by two files of one
+/// translation unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroConflict {
+    /// Definition in the file reached first.
+    pub first: MacroDefinition,
+    /// Differing definition in a file reached later.
+    pub second: MacroDefinition,
+}
+
+/// Conflicting definitions between `files`, the sources of one translation
+/// unit in inclusion order.
+///
+/// Definitions within one file are alternatives for different conditions, so
+/// a definition only conflicts with another file when it matches none of
+/// that file's definitions of the name. Defaults guarded by `#ifndef NAME`
+/// or `#if !defined(NAME)` give way to an earlier definition and never
+/// conflict.
+pub fn find_macro_conflicts(files: &[(PathBuf, String)]) -> Vec<MacroConflict> {
+    let mut names: Vec<String> = Vec::new();
+    let mut by_name: HashMap<String, Vec<Vec<MacroDefinition>>> = HashMap::new();
+    for (path, source) in files {
+        let defaults = default_definition_lines(source);
+        let mut in_file: HashMap<String, Vec<MacroDefinition>> = HashMap::new();
+        for definition in macro_definitions(source, Some(path)) {
+            if defaults.contains(&definition.line) {
+                continue;
+            }
+            in_file.entry(definition.name.clone()).or_default().push(definition);
+        }
+        for (name, definitions) in in_file {
+            let groups = by_name.entry(name.clone()).or_insert_with(|| {
+                names.push(name);
+                Vec::new()
+            });
+            groups.push(definitions);
+        }
+    }
+
+    let mut conflicts = Vec::new();
+    for name in names {
+        let groups = &by_name[&name];
+        for (i, earlier) in groups.iter().enumerate() {
+            for later in &groups[i + 1..] {
+                let differing = later.iter().find(|b| !earlier.iter().any(|a| a.same_replacement(b)));
+                if let Some(second) = differing {
+                    conflicts.push(MacroConflict {
+                        first: earlier[0].clone(),
+                        second: second.clone(),
+                    });
+                }
+            }
+        }
+    }
+    let file_order =
+        |definition: &MacroDefinition| files.iter().position(|(path, _)| definition.file.as_ref() == Some(path));
+    conflicts.sort_by_key(|conflict| (file_order(&conflict.second), conflict.second.line, file_order(&conflict.first)));
+    conflicts
+}
+
+/// Lines of the `#define`s in `source` that only apply while their name is
+/// undefined, inside `#ifndef NAME` or `#if !defined(NAME)`.
+fn default_definition_lines(source: &str) -> HashSet<u32> {
+    let mut tested: Vec<Option<String>> = Vec::new();
+    let mut lines = HashSet::new();
+    for (line, text) in directive_lines(source) {
+        let Some(directive) = text.trim_start().strip_prefix('#') else {
+            continue;
+        };
+        let directive = strip_comments(directive);
+        let directive = directive.trim_start();
+        match leading_ident(directive) {
+            "if" | "ifdef" | "ifndef" => tested.push(guarded_macro(&text).map(str::to_string)),
+            "elif" | "elifdef" | "elifndef" | "else" => {
+                if let Some(last) = tested.last_mut() {
+                    *last = None;
+                }
+            },
+            "endif" => {
+                tested.pop();
+            },
+            "define" => {
+                if let Some(definition) = parse_define(&directive["define".len()..], None, line)
+                    && tested.iter().flatten().any(|name| *name == definition.name)
+                {
+                    lines.insert(line);
+                }
+            },
+            _ => {},
+        }
+    }
+    lines
+}
+
+/// A macro name in source, with its arguments when it is function-like.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroInvocation {
+    pub name: String,
+    /// The name, or the name through the closing parenthesis of the arguments.
+    pub range: Range,
+    /// Invocation text as written.
+    pub text: String,
+    /// Raw argument texts; `None` for object-like macros and function-like
+    /// names not followed by `(`.
+    pub args: Option<Vec<String>>,
+}
+
+/// Result of expanding a [`MacroInvocation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroExpansion {
+    /// The replacement list with the arguments substituted as written.
+    pub single_step: String,
+    /// The replacement after expanding every macro it contains.
+    pub full: String,
+}
+
+/// The invocation of a macro from `index` whose name contains `position`.
+pub fn macro_invocation_at(
+    source: &str,
+    position: Position,
+    index: &MacroIndex,
+) -> Option<MacroInvocation> {
+    let offset = byte_offset_from_position(source, position)?;
+    let bytes = source.as_bytes();
+    let mut start = offset;
+    while start > 0 && is_ident_byte(bytes[start - 1]) {
+        start -= 1;
+    }
+    let mut end = offset;
+    while end < bytes.len() && is_ident_byte(bytes[end]) {
+        end += 1;
+    }
+    if start == end || bytes[start].is_ascii_digit() {
+        return None;
+    }
+    let name = &source[start..end];
+    let definition = index.get(name)?;
+    if is_define_name(source, start) {
+        return None;
+    }
+
+    let mut invocation_end = end;
+    let mut args = None;
+    if definition.params.is_some() {
+        let open = end + source[end..].len() - source[end..].trim_start().len();
+        if bytes.get(open) == Some(&b'(')
+            && let Some((parsed, close)) = split_arguments(source, open)
+        {
+            args = Some(parsed);
+            invocation_end = close + 1;
+        }
+    }
+
+    Some(MacroInvocation {
+        name: name.to_string(),
+        range: Range {
+            start: position_from_byte_offset(source, start),
+            end: position_from_byte_offset(source, invocation_end),
+        },
+        text: source[start..invocation_end].to_string(),
+        args,
+    })
+}
+
+/// Expand `invocation` one step and fully, or `None` when a function-like
+/// macro has no argument list or the wrong number of arguments.
+pub fn expand_invocation(
+    invocation: &MacroInvocation,
+    index: &MacroIndex,
+) -> Option<MacroExpansion> {
+    let definition = index.get(&invocation.name)?;
+    let args = match (&definition.params, &invocation.args) {
+        (None, _) => Vec::new(),
+        (Some(_), None) => return None,
+        (Some(_), Some(args)) => bind_arguments(definition, args.iter().map(|arg| tokenize(arg)).collect())?,
+    };
+
+    let single_step = render(&substitute(definition, &args, None));
+    let mut disabled = Vec::new();
+    let full = render(&expand_tokens(&tokenize(&invocation.text), index, &mut disabled));
+    Some(MacroExpansion {
+        single_step,
+        full,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    /// Number, literal or punctuator.
+    Other(String),
+    /// `##` from a replacement list.
+    Paste,
+    Space,
+}
+
+impl Token {
+    fn text(&self) -> &str {
+        match self {
+            Self::Ident(text) | Self::Other(text) => text,
+            Self::Paste => "##",
+            Self::Space => " ",
+        }
+    }
+
+    fn is(
+        &self,
+        punct: &str,
+    ) -> bool {
+        matches!(self, Self::Other(text) if text == punct)
+    }
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            while i < chars.len() && chars[i].is_whitespace() {
+                i += 1;
+            }
+            tokens.push(Token::Space);
+            continue;
+        }
+        if c.is_ascii_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            continue;
+        }
+        if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit)) {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || matches!(chars[i], '_' | '.')) {
+                i += 1;
+            }
+        } else if c == '"' || c == '\'' {
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                i += if chars[i] == '\\' {
+                    2
+                } else {
+                    1
+                };
+            }
+            i = (i + 1).min(chars.len());
+        } else if c == '#' && chars.get(i + 1) == Some(&'#') {
+            i += 2;
+        } else {
+            i += 1;
+        }
+        tokens.push(Token::Other(chars[start..i].iter().collect()));
+    }
+    tokens
+}
+
+/// Join tokens with single spaces where the source had whitespace.
+fn render(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    for token in tokens {
+        if *token == Token::Space && (text.is_empty() || text.ends_with(' ')) {
+            continue;
+        }
+        text.push_str(token.text());
+    }
+    text.trim_end().to_string()
+}
+
+/// Arguments by parameter, with the variadic ones joined into the last.
+fn bind_arguments(
+    definition: &MacroDefinition,
+    args: Vec<Vec<Token>>,
+) -> Option<Vec<Vec<Token>>> {
+    let params = definition.params.as_ref()?;
+    let mut args: Vec<Vec<Token>> = args.into_iter().map(trim_spaces).collect();
+    if params.is_empty() {
+        // `F()` passes one empty argument.
+        return (args.len() == 1 && args[0].is_empty()).then(Vec::new);
+    }
+    if definition.variadic {
+        let named = params.len() - 1;
+        if args.len() < named {
+            return None;
+        }
+        let rest = args.split_off(named);
+        let mut variadic = Vec::new();
+        for (i, arg) in rest.into_iter().enumerate() {
+            if i > 0 {
+                variadic.push(Token::Other(",".to_string()));
+                variadic.push(Token::Space);
+            }
+            variadic.extend(arg);
+        }
+        args.push(variadic);
+    }
+    (args.len() == params.len()).then_some(args)
+}
+
+fn trim_spaces(mut tokens: Vec<Token>) -> Vec<Token> {
+    while tokens.last() == Some(&Token::Space) {
+        tokens.pop();
+    }
+    let leading = tokens.iter().take_while(|t| **t == Token::Space).count();
+    tokens.split_off(leading)
+}
+
+/// The replacement list of `definition` with `args` substituted.
+///
+/// With `expand`, arguments that are not operands of `#` or `##` are fully
+/// expanded first; otherwise they are substituted as written.
+fn substitute(
+    definition: &MacroDefinition,
+    args: &[Vec<Token>],
+    mut expand: Option<(&MacroIndex, &mut Vec<String>)>,
+) -> Vec<Token> {
+    let body = tokenize(&definition.body);
+    let params = definition.params.as_deref().unwrap_or_default();
+    let param_index = |token: &Token| match token {
+        Token::Ident(name) => params.iter().position(|param| param == name),
+        _ => None,
+    };
+    let neighbor = |i: usize, step: isize| {
+        let mut j = i as isize + step;
+        while j >= 0 && (j as usize) < body.len() && body[j as usize] == Token::Space {
+            j += step;
+        }
+        (j >= 0).then(|| body.get(j as usize)).flatten()
+    };
+
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < body.len() {
+        let token = &body[i];
+        if token.is("##") {
+            while out.last() == Some(&Token::Space) {
+                out.pop();
+            }
+            out.push(Token::Paste);
+            i += 1;
+            while body.get(i) == Some(&Token::Space) {
+                i += 1;
+            }
+            continue;
+        }
+        if token.is("#")
+            && definition.params.is_some()
+            && let Some(param) = neighbor(i, 1).and_then(param_index)
+        {
+            out.push(Token::Other(stringify(&args[param])));
+            i += 1;
+            while body.get(i) == Some(&Token::Space) {
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+        match param_index(token) {
+            Some(param) => {
+                let pasted = neighbor(i, -1).is_some_and(|t| t.is("##")) || neighbor(i, 1).is_some_and(|t| t.is("##"));
+                match expand.as_mut() {
+                    Some((index, disabled)) if !pasted => out.extend(expand_tokens(&args[param], index, disabled)),
+                    _ => out.extend(args[param].iter().cloned()),
+                }
+            },
+            None => out.push(token.clone()),
+        }
+        i += 1;
+    }
+    paste(out)
+}
+
+/// Concatenate the tokens around each `##`.
+fn paste(tokens: Vec<Token>) -> Vec<Token> {
+    let mut out: Vec<Token> = Vec::with_capacity(tokens.len());
+    let mut iter = tokens.into_iter().peekable();
+    while let Some(token) = iter.next() {
+        if token != Token::Paste {
+            out.push(token);
+            continue;
+        }
+        let left = out.pop().map(|t| t.text().to_string()).unwrap_or_default();
+        let right = match iter.peek() {
+            Some(Token::Paste) | None => String::new(),
+            Some(_) => iter.next().map(|t| t.text().to_string()).unwrap_or_default(),
+        };
+        out.extend(tokenize(&format!("{left}{right}")));
+    }
+    out
+}
+
+fn stringify(arg: &[Token]) -> String {
+    let text = render(arg);
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.trim().chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Expand every macro in `tokens` except the names in `disabled`.
+fn expand_tokens(
+    tokens: &[Token],
+    index: &MacroIndex,
+    disabled: &mut Vec<String>,
+) -> Vec<Token> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        i += 1;
+        let Token::Ident(name) = token else {
+            out.push(token.clone());
+            continue;
+        };
+        let Some(definition) =
+            index.get(name).filter(|_| !disabled.contains(name) && disabled.len() < MAX_EXPANSION_DEPTH)
+        else {
+            out.push(token.clone());
+            continue;
+        };
+
+        let args = if definition.params.is_some() {
+            let mut open = i;
+            while tokens.get(open) == Some(&Token::Space) {
+                open += 1;
+            }
+            let Some((args, close)) =
+                tokens.get(open).filter(|t| t.is("(")).and_then(|_| split_token_arguments(tokens, open))
+            else {
+                out.push(token.clone());
+                continue;
+            };
+            let Some(args) = bind_arguments(definition, args) else {
+                out.push(token.clone());
+                continue;
+            };
+            i = close + 1;
+            args
+        } else {
+            Vec::new()
+        };
+
+        let replacement = substitute(definition, &args, Some((index, disabled)));
+        disabled.push(name.clone());
+        out.extend(expand_tokens(&replacement, index, disabled));
+        disabled.pop();
+    }
+    out
+}
+
+/// Arguments of the parenthesized list opening at `tokens[open]`, and the
+/// index of its closing parenthesis.
+fn split_token_arguments(
+    tokens: &[Token],
+    open: usize,
+) -> Option<(Vec<Vec<Token>>, usize)> {
+    let mut args = vec![Vec::new()];
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(open + 1) {
+        if token.is("(") {
+            depth += 1;
+        } else if token.is(")") {
+            if depth == 0 {
+                return Some((args, i));
+            }
+            depth -= 1;
+        } else if token.is(",") && depth == 0 {
+            args.push(Vec::new());
+            continue;
+        }
+        args.last_mut()?.push(token.clone());
+    }
+    None
+}
+
+/// Raw arguments of the parenthesized list opening at byte `open` of
+/// `source`, and the byte offset of its closing parenthesis.
+fn split_arguments(
+    source: &str,
+    open: usize,
+) -> Option<(Vec<String>, usize)> {
+    let bytes = source.as_bytes();
+    let mut args = Vec::new();
+    let mut arg_start = open + 1;
+    let mut depth = 0usize;
+    let mut i = open + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += if bytes[i] == b'\\' {
+                        2
+                    } else {
+                        1
+                    };
+                }
+            },
+            b'(' => depth += 1,
+            b')' if depth == 0 => {
+                args.push(source[arg_start..i].trim().to_string());
+                return Some((args, i));
+            },
+            b')' => depth -= 1,
+            b',' if depth == 0 => {
+                args.push(source[arg_start..i].trim().to_string());
+                arg_start = i + 1;
+            },
+            _ => {},
+        }
+        i += 1;
+    }
+    None
+}
+
+fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+/// Whether the identifier at byte `start` is the name in a `#define` or `#undef`.
+fn is_define_name(
+    source: &str,
+    start: usize,
+) -> bool {
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let before = source[line_start..start].trim_start();
+    let Some(directive) = before.strip_prefix('#') else {
+        return false;
+    };
+    let directive = directive.trim();
+    ["define", "undef", "ifdef", "ifndef"].iter().any(|keyword| {
+        directive.strip_prefix(keyword).is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+    })
+}
+
+/// Preprocessor-relevant logical lines: the zero-based first line and the
+/// text with backslash-newlines joined.
+fn directive_lines(source: &str) -> Vec<(u32, String)> {
+    let lines: Vec<&str> = source.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)).collect();
+    let mut logical = Vec::new();
+    let mut line = 0;
+    while line < lines.len() {
+        let start = line;
+        let mut text = lines[line].to_string();
+        while text.ends_with('\\') && line + 1 < lines.len() {
+            text.pop();
+            line += 1;
+            text.push_str(lines[line]);
+        }
+        logical.push((start as u32, text));
+        line += 1;
+    }
+    logical
+}
+
+/// Parse the text after `#define`.
+fn parse_define(
+    rest: &str,
+    file: Option<&Path>,
+    line: u32,
+) -> Option<MacroDefinition> {
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let rest = rest.trim_start();
+    let name = leading_ident(rest);
+    if name.is_empty() {
+        return None;
+    }
+    let after_name = &rest[name.len()..];
+    let (params, variadic, body) = match after_name.strip_prefix('(') {
+        Some(list) => {
+            let close = list.find(')')?;
+            let mut params: Vec<String> =
+                list[..close].split(',').map(|param| param.trim().to_string()).filter(|p| !p.is_empty()).collect();
+            let mut variadic = false;
+            if let Some(last) = params.last_mut()
+                && let Some(named) = last.strip_suffix("...")
+            {
+                variadic = true;
+                *last = match named.trim() {
+                    "" => "__VA_ARGS__".to_string(),
+                    named => named.to_string(),
+                };
+            }
+            (Some(params), variadic, &list[close + 1..])
+        },
+        None => (None, false, after_name),
+    };
+    Some(MacroDefinition {
+        name: name.to_string(),
+        params,
+        variadic,
+        body: body.split_whitespace().collect::<Vec<_>>().join(" "),
+        file: file.map(Path::to_path_buf),
+        line,
+    })
+}
+
+fn leading_ident(text: &str) -> &str {
+    let end = text.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(text.len());
+    &text[..end]
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/macros_tests.rs"]
+mod tests;
@@ -1,2 +1,22 @@
+pub mod address_spaces;
+pub mod bindings;
+pub mod code_lens;
+pub mod deprecations;
+pub mod entry_point_attributes;
+pub mod entry_points;
+pub mod fix_its;
+pub mod inactive_regions;
+pub mod include_guards;
+pub mod inline_values;
+pub mod language_version;
 pub mod lsp;
+pub mod macros;
 pub mod navigation;
+pub mod rename;
+pub mod selection_range;
+pub mod spelling;
+pub mod syntax_diagnostics;
+pub mod threadgroup_memory;
+pub mod todos;
+pub mod unused_includes;
+pub mod unused_symbols;
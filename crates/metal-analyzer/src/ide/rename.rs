@@ -0,0 +1,312 @@
+//! Companion edits for renaming a struct or class.
+//!
+//! Constructors, destructors and conversion operators spell the type's name
+//! but are separate declarations in the AST, so the reference sites of the
+//! type alone miss them and a rename would leave code that no longer
+//! compiles. These helpers find those spellings in the CST:
+//!
+//! - `Name(...)` declared directly inside `struct Name { ... }`,
+//! - `Name::Name(...)` defined out of line,
+//! - `~Name` in destructor declarations and definitions,
+//! - `operator Name` and `operator const Name` conversion operators.
+//!
+//! The new name is validated before any edit is produced: it must be a
+//! plain identifier that is neither reserved, a keyword nor a Metal builtin,
+//! and no declaration of it may be visible where the renamed symbol is used.
+//!
+//! Clients that support change annotations get the rename as annotated
+//! document edits: references confirmed by the AST index are applied
+//! directly, while name-only matches in files the index has not covered are
+//! offered as textual candidates the user confirms in the preview.
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
+
+use rowan::{TextRange, TextSize};
+use tower_lsp::lsp_types::{
+    AnnotatedTextEdit, ChangeAnnotation, DocumentChanges, Location, OneOf, OptionalVersionedTextDocumentIdentifier,
+    Position, Range, TextDocumentEdit, TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::{
+    metal::builtins,
+    syntax::{
+        ast::{self, AstNode},
+        cst::{SyntaxNode, SyntaxToken},
+        helpers,
+        kind::SyntaxKind,
+    },
+    text_pos,
+};
+
+/// Why a rename was refused.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenameError {
+    InvalidIdentifier(String),
+    /// Starts with `__` or `_` and an uppercase letter.
+    Reserved(String),
+    Keyword(String),
+    Builtin(String),
+    /// Declarations of the new name visible at one of the renamed uses.
+    Conflict {
+        name: String,
+        declarations: Vec<Location>,
+    },
+}
+
+impl RenameError {
+    /// Machine-readable reason reported in the error data.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::InvalidIdentifier(_) => "invalidIdentifier",
+            Self::Reserved(_) => "reserved",
+            Self::Keyword(_) => "keyword",
+            Self::Builtin(_) => "builtin",
+            Self::Conflict {
+                ..
+            } => "conflict",
+        }
+    }
+}
+
+impl Display for RenameError {
+    fn fmt(
+        &self,
+        f: &mut Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::InvalidIdentifier(name) => write!(f, "`{name}` is not a valid identifier"),
+            Self::Reserved(name) => write!(f, "`{name}` is a reserved identifier"),
+            Self::Keyword(name) => write!(f, "`{name}` is a keyword"),
+            Self::Builtin(name) => write!(f, "`{name}` is a Metal builtin"),
+            Self::Conflict {
+                name,
+                declarations,
+            } => match declarations.as_slice() {
+                [declaration] => write!(
+                    f,
+                    "`{name}` is already declared at {}:{}",
+                    declaration.uri.path().rsplit('/').next().unwrap_or_default(),
+                    declaration.range.start.line + 1
+                ),
+                _ => write!(f, "`{name}` is already declared in {} places", declarations.len()),
+            },
+        }
+    }
+}
+
+impl std::error::Error for RenameError {}
+
+/// Check that `name` can replace an identifier.
+pub fn validate_new_name(name: &str) -> Result<(), RenameError> {
+    let mut chars = name.chars();
+    let starts_like_identifier = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    if !starts_like_identifier || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(RenameError::InvalidIdentifier(name.to_string()));
+    }
+    if name.starts_with("__")
+        || name.strip_prefix('_').is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_uppercase()))
+    {
+        return Err(RenameError::Reserved(name.to_string()));
+    }
+    if builtins::keywords().contains(&name) {
+        return Err(RenameError::Keyword(name.to_string()));
+    }
+    if builtins::lookup(name).is_some() {
+        return Err(RenameError::Builtin(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Range of the scope a declaration at `position` belongs to: the
+/// innermost enclosing block, or the whole function for a parameter.
+/// `None` at namespace or file scope.
+pub fn declaration_scope(
+    root: &SyntaxNode,
+    source: &str,
+    position: Position,
+) -> Option<TextRange> {
+    let offset = text_pos::byte_offset_from_position(source, position)?;
+    let token = root.token_at_offset(TextSize::from(offset as u32)).right_biased()?;
+    let scope = token.parent_ancestors().find(|node| {
+        matches!(node.kind(), SyntaxKind::Block | SyntaxKind::ParameterList)
+            && node.parent().is_none_or(|parent| parent.kind() != SyntaxKind::NamespaceDef)
+    })?;
+    if scope.kind() == SyntaxKind::ParameterList {
+        return scope.parent().map(|function| function.text_range());
+    }
+    Some(scope.text_range())
+}
+
+/// Whether one of `uses` in `source` starts inside `scope`.
+pub fn any_use_in_scope(
+    source: &str,
+    scope: TextRange,
+    uses: &[Range],
+) -> bool {
+    uses.iter().any(|range| {
+        text_pos::byte_offset_from_position(source, range.start)
+            .is_some_and(|offset| scope.contains(TextSize::from(offset as u32)))
+    })
+}
+
+/// Whether `root` defines a struct or class named `name`.
+pub fn defines_type(
+    root: &SyntaxNode,
+    name: &str,
+) -> bool {
+    root.descendants().any(|node| type_def_name(&node).is_some_and(|token| token.text() == name))
+}
+
+/// Ranges of `name` spelled as a constructor, destructor or conversion
+/// operator of the type `name`.
+pub fn type_name_companion_ranges(
+    root: &SyntaxNode,
+    source: &str,
+    name: &str,
+) -> Vec<Range> {
+    let tokens: Vec<SyntaxToken> = root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !matches!(token.kind(), SyntaxKind::Whitespace | SyntaxKind::Comment))
+        .collect();
+
+    let kind_at = |index: Option<usize>| index.and_then(|i| tokens.get(i)).map(SyntaxToken::kind);
+    let text_at = |index: Option<usize>| index.and_then(|i| tokens.get(i)).map(SyntaxToken::text);
+
+    let mut ranges = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind() != SyntaxKind::Ident || token.text() != name {
+            continue;
+        }
+        let previous = i.checked_sub(1);
+        let before_previous = i.checked_sub(2);
+
+        let destructor = kind_at(previous) == Some(SyntaxKind::Tilde);
+        let conversion = kind_at(previous) == Some(SyntaxKind::KwOperator)
+            || (kind_at(previous) == Some(SyntaxKind::KwConst)
+                && kind_at(before_previous) == Some(SyntaxKind::KwOperator));
+        let out_of_line_constructor = kind_at(previous) == Some(SyntaxKind::DoubleColon)
+            && text_at(before_previous) == Some(name)
+            && kind_at(Some(i + 1)) == Some(SyntaxKind::LParen);
+        let member_constructor = kind_at(Some(i + 1)) == Some(SyntaxKind::LParen) && is_member_of_type(token, name);
+
+        if destructor || conversion || out_of_line_constructor || member_constructor {
+            ranges.push(helpers::range_to_lsp(token.text_range(), source));
+        }
+    }
+    ranges
+}
+
+/// Whether `token` sits at member level of `struct name { ... }`, outside
+/// any nested function body or initializer.
+fn is_member_of_type(
+    token: &SyntaxToken,
+    name: &str,
+) -> bool {
+    let Some(block) = token.parent_ancestors().find(|node| node.kind() == SyntaxKind::Block) else {
+        return false;
+    };
+    block.parent().and_then(|owner| type_def_name(&owner)).is_some_and(|owner_name| owner_name.text() == name)
+}
+
+fn type_def_name(node: &SyntaxNode) -> Option<SyntaxToken> {
+    if let Some(def) = ast::StructDef::cast(node.clone()) {
+        return def.name_token();
+    }
+    ast::ClassDef::cast(node.clone()).and_then(|def| def.name_token())
+}
+
+/// Ranges of every identifier token spelled `name`, leaving out comments,
+/// string literals and longer identifiers containing it.
+pub fn identifier_ranges(
+    root: &SyntaxNode,
+    source: &str,
+    name: &str,
+) -> Vec<Range> {
+    root.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() == SyntaxKind::Ident && token.text() == name)
+        .map(|token| helpers::range_to_lsp(token.text_range(), source))
+        .collect()
+}
+
+/// Annotation of the edits at references the AST index confirmed.
+pub const DEFINITE_ANNOTATION: &str = "metal-analyzer.rename.definite";
+
+/// Annotation of the edits at name-only matches in unindexed files.
+pub const CANDIDATE_ANNOTATION: &str = "metal-analyzer.rename.candidate";
+
+/// The rename as document edits, each annotated as definite or as a
+/// textual candidate the client asks the user to confirm. Candidate ranges
+/// that are also definite edits are dropped. `version` gives the version of
+/// open documents.
+pub fn annotated_rename_edit(
+    definite: HashMap<Url, Vec<TextEdit>>,
+    candidates: HashMap<Url, Vec<TextEdit>>,
+    version: impl Fn(&Url) -> Option<i32>,
+) -> WorkspaceEdit {
+    let mut by_file: HashMap<Url, Vec<OneOf<TextEdit, AnnotatedTextEdit>>> = HashMap::new();
+    let annotate = |text_edit: TextEdit, annotation: &str| {
+        OneOf::Right(AnnotatedTextEdit {
+            text_edit,
+            annotation_id: annotation.to_string(),
+        })
+    };
+    for (uri, edits) in candidates {
+        let confirmed = definite.get(&uri).map(Vec::as_slice).unwrap_or_default();
+        let edits = edits
+            .into_iter()
+            .filter(|edit| confirmed.iter().all(|definite| definite.range != edit.range))
+            .map(|edit| annotate(edit, CANDIDATE_ANNOTATION));
+        by_file.entry(uri).or_default().extend(edits);
+    }
+    let has_candidates = by_file.values().any(|edits| !edits.is_empty());
+    for (uri, edits) in definite {
+        by_file.entry(uri).or_default().extend(edits.into_iter().map(|edit| annotate(edit, DEFINITE_ANNOTATION)));
+    }
+
+    let mut files: Vec<(Url, Vec<OneOf<TextEdit, AnnotatedTextEdit>>)> =
+        by_file.into_iter().filter(|(_, edits)| !edits.is_empty()).collect();
+    files.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+    let document_changes = files
+        .into_iter()
+        .map(|(uri, edits)| TextDocumentEdit {
+            text_document: OptionalVersionedTextDocumentIdentifier {
+                version: version(&uri),
+                uri,
+            },
+            edits,
+        })
+        .collect();
+
+    let mut change_annotations = HashMap::from([(
+        DEFINITE_ANNOTATION.to_string(),
+        ChangeAnnotation {
+            label: "Rename references".to_string(),
+            needs_confirmation: Some(false),
+            description: Some("References confirmed by the AST index".to_string()),
+        },
+    )]);
+    if has_candidates {
+        change_annotations.insert(
+            CANDIDATE_ANNOTATION.to_string(),
+            ChangeAnnotation {
+                label: "Textual matches".to_string(),
+                needs_confirmation: Some(true),
+                description: Some("Name-only matches in files that are not indexed".to_string()),
+            },
+        );
+    }
+    WorkspaceEdit {
+        changes: None,
+        document_changes: Some(DocumentChanges::Edits(document_changes)),
+        change_annotations: Some(change_annotations),
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/rename_tests.rs"]
+mod tests;
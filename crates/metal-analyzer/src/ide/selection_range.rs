@@ -0,0 +1,132 @@
+//! Smart expand selection (`textDocument/selectionRange`).
+//!
+//! Walks from the token under the cursor up through its CST ancestors,
+//! e.g. identifier → expression → statement → block body → block → function.
+
+use rowan::{TextRange, TextSize, TokenAtOffset};
+use tower_lsp::lsp_types::{Position, SelectionRange};
+
+use crate::syntax::{
+    cst::{SyntaxNode, SyntaxToken},
+    helpers,
+    kind::SyntaxKind,
+};
+
+/// Compute the selection-range chain for each position.
+pub fn selection_ranges(
+    root: &SyntaxNode,
+    source: &str,
+    positions: &[Position],
+) -> Vec<SelectionRange> {
+    positions.iter().map(|position| selection_range(root, source, *position)).collect()
+}
+
+fn selection_range(
+    root: &SyntaxNode,
+    source: &str,
+    position: Position,
+) -> SelectionRange {
+    let offset = crate::text_pos::byte_offset_from_position(source, position)
+        .map(|offset| TextSize::from(offset as u32))
+        .unwrap_or_else(|| root.text_range().end());
+
+    let mut ranges: Vec<TextRange> = Vec::new();
+    let mut push = |range: TextRange| {
+        if ranges.last().is_none_or(|last| range.contains_range(*last) && range != *last) {
+            ranges.push(range);
+        }
+    };
+
+    if let Some(token) = pick_token(root.token_at_offset(offset)) {
+        if !is_trivia(token.kind()) || token.kind() == SyntaxKind::Comment {
+            push(token.text_range());
+        }
+        for node in token.parent_ancestors() {
+            if let Some(inner) = delimited_inner_range(&node) {
+                push(inner);
+            }
+            if let Some(range) = trimmed_range(&node) {
+                push(range);
+            }
+        }
+    }
+
+    if ranges.is_empty() {
+        ranges.push(TextRange::empty(offset));
+    }
+
+    ranges
+        .into_iter()
+        .rev()
+        .fold(None, |parent, range| {
+            Some(SelectionRange {
+                range: helpers::range_to_lsp(range, source),
+                parent: parent.map(Box::new),
+            })
+        })
+        .expect("at least one range")
+}
+
+/// Prefer a meaningful token over the whitespace next to it.
+fn pick_token(tokens: TokenAtOffset<SyntaxToken>) -> Option<SyntaxToken> {
+    match tokens {
+        TokenAtOffset::None => None,
+        TokenAtOffset::Single(token) => Some(token),
+        TokenAtOffset::Between(left, right) => {
+            if is_trivia(right.kind()) && !is_trivia(left.kind()) {
+                Some(left)
+            } else {
+                Some(right)
+            }
+        },
+    }
+}
+
+fn is_trivia(kind: SyntaxKind) -> bool {
+    matches!(kind, SyntaxKind::Whitespace | SyntaxKind::Comment)
+}
+
+/// Node range without leading and trailing trivia.
+fn trimmed_range(node: &SyntaxNode) -> Option<TextRange> {
+    let mut tokens = node
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !is_trivia(token.kind()));
+    let first = tokens.next()?;
+    let last = tokens.last().unwrap_or_else(|| first.clone());
+    Some(TextRange::new(first.text_range().start(), last.text_range().end()))
+}
+
+/// Contents between the delimiters of a block, parameter list or attribute,
+/// so `{ … }` can be selected without its braces first.
+fn delimited_inner_range(node: &SyntaxNode) -> Option<TextRange> {
+    let (open, close) = match node.kind() {
+        SyntaxKind::Block => (SyntaxKind::LBrace, SyntaxKind::RBrace),
+        SyntaxKind::ParameterList => (SyntaxKind::LParen, SyntaxKind::RParen),
+        SyntaxKind::Attribute => (SyntaxKind::LDoubleBracket, SyntaxKind::RDoubleBracket),
+        _ => return None,
+    };
+    let children: Vec<SyntaxToken> = node
+        .children_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !is_trivia(token.kind()))
+        .collect();
+    let open_token = children.iter().find(|token| token.kind() == open)?;
+    let close_token = children.iter().rev().find(|token| token.kind() == close)?;
+
+    let inner = node
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !is_trivia(token.kind()))
+        .filter(|token| {
+            token.text_range().start() >= open_token.text_range().end()
+                && token.text_range().end() <= close_token.text_range().start()
+        })
+        .map(|token| token.text_range())
+        .reduce(|acc, range| acc.cover(range))?;
+    Some(inner)
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/selection_range_tests.rs"]
+mod tests;
@@ -0,0 +1,133 @@
+//! "Did you mean" suggestions for identifiers the compiler does not know.
+//!
+//! Clang offers typo corrections only for some declarations and rarely for
+//! Metal builtins. For `use of undeclared identifier` and `unknown type name`
+//! errors without one, the closest names by edit distance are attached as
+//! related information, each with a fix that replaces the identifier.
+
+use std::collections::HashSet;
+
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticRelatedInformation, Location, Position, Range, Url};
+
+use crate::{
+    metal::fix_its::{CompilerFix, FixIt, attach_fix},
+    syntax::{cst::SyntaxNode, kind::SyntaxKind},
+    text_pos::text_width,
+    vfs::virtual_docs::document_path,
+};
+
+/// Suggestions offered per identifier.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// The identifier an error reports as unknown, unless the compiler already
+/// suggested a correction.
+pub fn unknown_identifier(message: &str) -> Option<&str> {
+    if message.contains("did you mean") {
+        return None;
+    }
+    let rest = message
+        .strip_prefix("use of undeclared identifier '")
+        .or_else(|| message.strip_prefix("unknown type name '"))?;
+    let (name, _) = rest.split_once('\'')?;
+    (!name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')).then_some(name)
+}
+
+/// The names in `candidates` closest to `name`, nearest first.
+///
+/// A name qualifies when at most a third of its characters, and at least
+/// one, need editing. Differences in case alone count as the closest.
+pub fn closest_names<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Vec<&'a str> {
+    let length = name.chars().count();
+    let limit = (length / 3).max(1);
+    let mut scored: Vec<(usize, &'a str)> = candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name && candidate.chars().count().abs_diff(length) <= limit)
+        .filter_map(|candidate| {
+            if candidate.eq_ignore_ascii_case(name) {
+                return Some((0, candidate));
+            }
+            let distance = edit_distance(name, candidate);
+            (distance <= limit && distance < length).then_some((distance, candidate))
+        })
+        .collect();
+    scored.sort_unstable();
+    scored.dedup();
+    scored.into_iter().take(MAX_SUGGESTIONS).map(|(_, candidate)| candidate).collect()
+}
+
+/// Levenshtein distance over characters.
+fn edit_distance(
+    a: &str,
+    b: &str,
+) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Identifiers written in the document, which include its locals.
+pub fn identifier_names<'a>(
+    root: &SyntaxNode,
+    text: &'a str,
+) -> Vec<&'a str> {
+    root.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() == SyntaxKind::Ident)
+        .filter_map(|token| text.get(usize::from(token.text_range().start())..usize::from(token.text_range().end())))
+        .collect()
+}
+
+/// Attach suggestions from `candidates` to each unknown-identifier error in
+/// `diagnostics` for the document at `uri`.
+pub fn suggest_identifiers(
+    diagnostics: &mut [Diagnostic],
+    uri: &Url,
+    candidates: &HashSet<&str>,
+) {
+    let Some(path) = document_path(uri) else {
+        return;
+    };
+    for diagnostic in diagnostics.iter_mut() {
+        let Some(name) = unknown_identifier(&diagnostic.message).map(str::to_string) else {
+            continue;
+        };
+        let start = diagnostic.range.start;
+        let range = Range::new(start, Position::new(start.line, start.character + text_width(&name)));
+        for suggestion in closest_names(&name, candidates.iter().copied()) {
+            diagnostic.related_information.get_or_insert_with(Vec::new).push(DiagnosticRelatedInformation {
+                location: Location {
+                    uri: uri.clone(),
+                    range,
+                },
+                message: format!("did you mean `{suggestion}`?"),
+            });
+            attach_fix(
+                diagnostic,
+                CompilerFix {
+                    message: Some(format!("change `{name}` to `{suggestion}`")),
+                    edits: vec![FixIt {
+                        file: path.display().to_string(),
+                        range,
+                        replacement: suggestion.to_string(),
+                    }],
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/spelling_tests.rs"]
+mod tests;
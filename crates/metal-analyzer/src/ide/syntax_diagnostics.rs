@@ -0,0 +1,279 @@
+//! Diagnostics read off the syntax tree, published on every change before the
+//! compiler runs.
+//!
+//! Only mistakes the token stream shows unambiguously are reported:
+//! unbalanced brackets, malformed `[[...]]` attributes, and a missing `;`
+//! where the next line starts a statement or closes the block. The compiler
+//! diagnostics published after the debounce replace these.
+//!
+//! Preprocessor lines are skipped. A file with `#else` or `#elif` gets no
+//! bracket diagnostics, since both branches of a conditional are read.
+
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+use crate::syntax::{
+    cst::{SyntaxNode, SyntaxToken},
+    helpers,
+    kind::SyntaxKind,
+};
+
+pub const SYNTAX_CODE: &str = "syntax";
+
+/// A token outside comments, whitespace and preprocessor lines.
+pub(crate) struct Significant {
+    pub(crate) token: SyntaxToken,
+    /// Whether a line break separates it from the previous one.
+    pub(crate) starts_line: bool,
+}
+
+/// Syntax errors in `root`, parsed from `source`.
+pub fn syntax_diagnostics(
+    root: &SyntaxNode,
+    source: &str,
+) -> Vec<Diagnostic> {
+    let (tokens, has_alternative_branches) = significant_tokens(root);
+    let mut errors: Vec<(SyntaxToken, String)> = Vec::new();
+    if !has_alternative_branches {
+        unbalanced_brackets(&tokens, &mut errors);
+    }
+    malformed_attributes(root, &mut errors);
+    missing_semicolons(&tokens, &mut errors);
+
+    errors.sort_by_key(|(token, _)| token.text_range().start());
+    errors
+        .into_iter()
+        .map(|(token, message)| Diagnostic {
+            range: helpers::range_to_lsp(token.text_range(), source),
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: Some(NumberOrString::String(SYNTAX_CODE.to_string())),
+            source: Some("metal-analyzer".to_string()),
+            message,
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// The significant tokens of `root`, and whether it has `#else` or `#elif`.
+pub(crate) fn significant_tokens(root: &SyntaxNode) -> (Vec<Significant>, bool) {
+    let mut tokens = Vec::new();
+    let mut has_alternative_branches = false;
+    let mut at_line_start = true;
+    let mut in_directive = false;
+    let mut directive_word = true;
+    let mut continued = false;
+    let mut starts_line = true;
+    for token in root.descendants_with_tokens().filter_map(|element| element.into_token()) {
+        match token.kind() {
+            SyntaxKind::Whitespace | SyntaxKind::Comment => {
+                if token.text().contains('\n') {
+                    in_directive &= continued;
+                    at_line_start = true;
+                    starts_line = true;
+                }
+                continue;
+            },
+            SyntaxKind::Hash if at_line_start => {
+                in_directive = true;
+                directive_word = true;
+            },
+            _ if in_directive && directive_word => {
+                has_alternative_branches |= matches!(token.text(), "else" | "elif" | "elifdef" | "elifndef");
+                directive_word = false;
+            },
+            _ if !in_directive => tokens.push(Significant {
+                token: token.clone(),
+                starts_line,
+            }),
+            _ => {},
+        }
+        continued = token.text() == "\\";
+        at_line_start = false;
+        starts_line = false;
+    }
+    (tokens, has_alternative_branches)
+}
+
+fn opener_of(close: SyntaxKind) -> SyntaxKind {
+    match close {
+        SyntaxKind::RParen => SyntaxKind::LParen,
+        SyntaxKind::RBracket => SyntaxKind::LBracket,
+        _ => SyntaxKind::LBrace,
+    }
+}
+
+fn bracket_text(kind: SyntaxKind) -> &'static str {
+    match kind {
+        SyntaxKind::LParen => "(",
+        SyntaxKind::RParen => ")",
+        SyntaxKind::LBracket => "[",
+        SyntaxKind::RBracket => "]",
+        SyntaxKind::LBrace => "{",
+        _ => "}",
+    }
+}
+
+/// Brackets without a partner. A closer skips over unclosed parentheses and
+/// square brackets to reach its opener, and `}` over anything. A `;` closes
+/// the statement, so parentheses and square brackets still open there are
+/// unclosed, except for the header of a `for`.
+fn unbalanced_brackets(
+    tokens: &[Significant],
+    errors: &mut Vec<(SyntaxToken, String)>,
+) {
+    let mut open: Vec<(SyntaxKind, SyntaxToken)> = Vec::new();
+    let mut for_headers: Vec<SyntaxToken> = Vec::new();
+    for (
+        index,
+        Significant {
+            token,
+            ..
+        },
+    ) in tokens.iter().enumerate()
+    {
+        if token.kind() == SyntaxKind::LParen && index > 0 && tokens[index - 1].token.kind() == SyntaxKind::KwFor {
+            for_headers.push(token.clone());
+        }
+        if token.kind() == SyntaxKind::Semicolon {
+            while let Some((unclosed, opener)) = open.last()
+                && *unclosed != SyntaxKind::LBrace
+                && !for_headers.contains(opener)
+            {
+                errors.push((opener.clone(), format!("unclosed `{}`", bracket_text(*unclosed))));
+                open.pop();
+            }
+            continue;
+        }
+        let (kind, count) = match token.kind() {
+            SyntaxKind::LDoubleBracket if is_unterminated_attribute(token) => continue,
+            SyntaxKind::LDoubleBracket => (SyntaxKind::LBracket, 2),
+            SyntaxKind::RDoubleBracket => (SyntaxKind::RBracket, 2),
+            kind => (kind, 1),
+        };
+        for _ in 0..count {
+            match kind {
+                SyntaxKind::LParen | SyntaxKind::LBracket | SyntaxKind::LBrace => open.push((kind, token.clone())),
+                SyntaxKind::RParen | SyntaxKind::RBracket | SyntaxKind::RBrace => {
+                    let opener = opener_of(kind);
+                    let partner = open.iter().rposition(|(open_kind, _)| *open_kind == opener).filter(|&index| {
+                        kind == SyntaxKind::RBrace || open[index + 1..].iter().all(|(k, _)| *k != SyntaxKind::LBrace)
+                    });
+                    match partner {
+                        Some(index) => {
+                            for (unclosed, token) in open.drain(index + 1..) {
+                                errors.push((token, format!("unclosed `{}`", bracket_text(unclosed))));
+                            }
+                            open.pop();
+                        },
+                        None => errors.push((token.clone(), format!("unmatched `{}`", bracket_text(kind)))),
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+    for (unclosed, token) in open {
+        errors.push((token, format!("unclosed `{}`", bracket_text(unclosed))));
+    }
+    errors.dedup_by(|a, b| a.0 == b.0 && a.1 == b.1);
+}
+
+fn is_unterminated_attribute(token: &SyntaxToken) -> bool {
+    token.parent().is_some_and(|attribute| {
+        attribute.kind() == SyntaxKind::Attribute
+            && attribute.last_token().is_none_or(|last| last.kind() != SyntaxKind::RDoubleBracket)
+    })
+}
+
+/// `[[` without `]]`, and `[[]]`.
+fn malformed_attributes(
+    root: &SyntaxNode,
+    errors: &mut Vec<(SyntaxToken, String)>,
+) {
+    for attribute in root.descendants().filter(|node| node.kind() == SyntaxKind::Attribute) {
+        let Some(open) = attribute.first_token() else {
+            continue;
+        };
+        if is_unterminated_attribute(&open) {
+            errors.push((open, "unterminated attribute: expected `]]`".to_string()));
+            continue;
+        }
+        let has_content = attribute.children_with_tokens().filter_map(|element| element.into_token()).any(|token| {
+            !matches!(
+                token.kind(),
+                SyntaxKind::LDoubleBracket | SyntaxKind::RDoubleBracket | SyntaxKind::Whitespace | SyntaxKind::Comment
+            )
+        });
+        if !has_content {
+            errors.push((open, "empty attribute".to_string()));
+        }
+    }
+}
+
+/// A statement left without `;` at the end of its line: the next line
+/// starts with a statement keyword or closes the block, and this line ends
+/// in a name, literal, `]` or `++`/`--`.
+fn missing_semicolons(
+    tokens: &[Significant],
+    errors: &mut Vec<(SyntaxToken, String)>,
+) {
+    for pair in tokens.windows(2) {
+        let [previous, next] = pair else {
+            continue;
+        };
+        if !next.starts_line || !ends_expression(previous.token.kind()) || !in_statement_block(&next.token) {
+            continue;
+        }
+        let starts_statement = matches!(
+            next.token.kind(),
+            SyntaxKind::RBrace
+                | SyntaxKind::KwReturn
+                | SyntaxKind::KwIf
+                | SyntaxKind::KwFor
+                | SyntaxKind::KwWhile
+                | SyntaxKind::KwDo
+                | SyntaxKind::KwSwitch
+                | SyntaxKind::KwBreak
+                | SyntaxKind::KwContinue
+        );
+        if starts_statement {
+            errors.push((previous.token.clone(), "expected `;` at end of statement".to_string()));
+        }
+    }
+}
+
+fn ends_expression(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::Ident
+            | SyntaxKind::Integer
+            | SyntaxKind::Float
+            | SyntaxKind::String
+            | SyntaxKind::Char
+            | SyntaxKind::KwTrue
+            | SyntaxKind::KwFalse
+            | SyntaxKind::KwNullptr
+            | SyntaxKind::RBracket
+            | SyntaxKind::PlusPlus
+            | SyntaxKind::MinusMinus
+    )
+}
+
+/// Whether `token` is directly in a function or struct body, rather than an
+/// enum body or an initializer list.
+fn in_statement_block(token: &SyntaxToken) -> bool {
+    let Some(parent) = token.parent() else {
+        return false;
+    };
+    let block = match parent.kind() {
+        SyntaxKind::Block => parent,
+        _ => match parent.ancestors().find(|node| node.kind() == SyntaxKind::Block) {
+            Some(block) if token.kind() != SyntaxKind::RBrace => block,
+            _ => return false,
+        },
+    };
+    block.parent().is_none_or(|owner| owner.kind() != SyntaxKind::EnumDef)
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/syntax_diagnostics_tests.rs"]
+mod tests;
@@ -0,0 +1,641 @@
+//! Threadgroup memory declared by each kernel, and warnings past the limit.
+//!
+//! Sums the `threadgroup` variables declared in kernel, mesh and object
+//! function bodies. Array extents may use integer literals, `constant` or
+//! `constexpr` integers, object-like macros, and template parameters bound
+//! by explicit instantiations such as
+//! `template [[host_name("k16")]] kernel void k<16>(...);`, each of which is
+//! checked on its own. Memory passed as a `[[threadgroup(n)]]` parameter is
+//! set by the host and not counted.
+
+use std::collections::HashMap;
+
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, NumberOrString, Range, Url,
+};
+
+use crate::{
+    ide::{entry_points::entry_point_stage, macros::MacroIndex},
+    syntax::{
+        ast::{self, AstNode},
+        cst::{SyntaxNode, SyntaxToken},
+        helpers,
+        kind::SyntaxKind,
+    },
+};
+
+pub const THREADGROUP_MEMORY_CODE: &str = "threadgroup-memory";
+
+/// Threadgroup memory available to one threadgroup on Apple GPUs.
+pub const DEFAULT_THREADGROUP_MEMORY_LIMIT: u64 = 32 * 1024;
+
+/// Macros, constants and type sizes nest at most this deep, which also
+/// stops recursive definitions.
+const MAX_EVAL_DEPTH: usize = 16;
+
+/// A `threadgroup` variable declared in a kernel body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadgroupVariable {
+    pub name: String,
+    /// Range of the variable name.
+    pub range: Range,
+    /// Size in bytes, or `None` when its type or extents are not known.
+    pub bytes: Option<u64>,
+}
+
+/// The threadgroup variables of one kernel, or of one instantiation of a
+/// kernel template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadgroupUsage {
+    /// Kernel name, with the template arguments of an instantiation.
+    pub kernel: String,
+    /// Range of the kernel name.
+    pub range: Range,
+    pub variables: Vec<ThreadgroupVariable>,
+}
+
+impl ThreadgroupUsage {
+    /// Bytes of the variables whose size is known.
+    pub fn total_bytes(&self) -> u64 {
+        self.variables.iter().filter_map(|variable| variable.bytes).sum()
+    }
+
+    /// Whether some variable's size is not known, so the total is a lower bound.
+    pub fn is_partial(&self) -> bool {
+        self.variables.iter().any(|variable| variable.bytes.is_none())
+    }
+}
+
+/// Threadgroup memory of every kernel, mesh and object function in `root`
+/// that declares threadgroup variables. `macros` resolves macro extents.
+pub fn threadgroup_usage(
+    root: &SyntaxNode,
+    source: &str,
+    macros: &MacroIndex,
+) -> Vec<ThreadgroupUsage> {
+    let context = Context {
+        constants: integer_constants(root),
+        structs: root
+            .descendants()
+            .filter_map(ast::StructDef::cast)
+            .filter_map(|def| Some((def.name_token()?.text().to_string(), def)))
+            .collect(),
+        macros,
+    };
+
+    let mut usages = Vec::new();
+    for function in root.descendants().filter_map(ast::FunctionDef::cast) {
+        if !matches!(entry_point_stage(&function), Some("kernel" | "mesh" | "object")) {
+            continue;
+        }
+        let (Some(name), Some(body)) = (function.name_token(), function.body()) else {
+            continue;
+        };
+        let declarations: Vec<SyntaxNode> = body.syntax().descendants().filter(is_threadgroup_declaration).collect();
+        if declarations.is_empty() {
+            continue;
+        }
+        let range = helpers::range_to_lsp(name.text_range(), source);
+        let parameters = template_parameters(function.syntax());
+        let instantiations = if parameters.is_empty() {
+            Vec::new()
+        } else {
+            instantiations(root, name.text())
+        };
+
+        let mut bindings: Vec<(String, HashMap<String, String>)> = instantiations
+            .into_iter()
+            .map(|arguments| {
+                let kernel = format!("{}<{}>", name.text(), arguments.join(", "));
+                let mut bound = HashMap::new();
+                for (index, (parameter, default)) in parameters.iter().enumerate() {
+                    if let Some(value) = arguments.get(index).cloned().or_else(|| default.clone()) {
+                        bound.insert(parameter.clone(), value);
+                    }
+                }
+                (kernel, bound)
+            })
+            .collect();
+        if bindings.is_empty() {
+            let defaults = parameters
+                .iter()
+                .filter_map(|(parameter, default)| Some((parameter.clone(), default.clone()?)))
+                .collect();
+            bindings.push((name.text().to_string(), defaults));
+        }
+
+        for (kernel, bound) in bindings {
+            let variables =
+                declarations.iter().flat_map(|declaration| context.variables(declaration, source, &bound)).collect();
+            usages.push(ThreadgroupUsage {
+                kernel,
+                range,
+                variables,
+            });
+        }
+    }
+    usages
+}
+
+/// A warning on each kernel declaring more than `limit` bytes of threadgroup
+/// memory, relating the variables that take it up. A `limit` of zero turns
+/// the check off.
+pub fn threadgroup_memory_diagnostics(
+    root: &SyntaxNode,
+    source: &str,
+    uri: &Url,
+    macros: &MacroIndex,
+    limit: u64,
+) -> Vec<Diagnostic> {
+    if limit == 0 {
+        return Vec::new();
+    }
+    threadgroup_usage(root, source, macros)
+        .into_iter()
+        .filter(|usage| usage.total_bytes() > limit)
+        .map(|usage| {
+            let at_least = if usage.is_partial() {
+                "at least "
+            } else {
+                ""
+            };
+            Diagnostic {
+                range: usage.range,
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String(THREADGROUP_MEMORY_CODE.to_string())),
+                source: Some("metal-analyzer".to_string()),
+                message: format!(
+                    "`{}` declares {at_least}{} bytes of threadgroup memory, more than the {limit}-byte limit",
+                    usage.kernel,
+                    usage.total_bytes()
+                ),
+                related_information: Some(
+                    usage
+                        .variables
+                        .iter()
+                        .filter_map(|variable| {
+                            Some(DiagnosticRelatedInformation {
+                                location: Location {
+                                    uri: uri.clone(),
+                                    range: variable.range,
+                                },
+                                message: format!("`{}` takes {} bytes", variable.name, variable.bytes?),
+                            })
+                        })
+                        .collect(),
+                ),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// A declaration statement of a `threadgroup` variable, not of a pointer or
+/// reference into threadgroup memory.
+fn is_threadgroup_declaration(node: &SyntaxNode) -> bool {
+    if node.kind() != SyntaxKind::DeclStmt {
+        return false;
+    }
+    let Some(type_ref) = node.children().find(|child| child.kind() == SyntaxKind::TypeRef) else {
+        return false;
+    };
+    let kinds: Vec<SyntaxKind> = significant_tokens(&type_ref).iter().map(SyntaxToken::kind).collect();
+    kinds.contains(&SyntaxKind::KwThreadgroup)
+        && !kinds.iter().any(|kind| matches!(kind, SyntaxKind::Star | SyntaxKind::Amp | SyntaxKind::AndAnd))
+}
+
+fn significant_tokens(node: &SyntaxNode) -> Vec<SyntaxToken> {
+    node.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !matches!(token.kind(), SyntaxKind::Whitespace | SyntaxKind::Comment))
+        .collect()
+}
+
+/// Names and default arguments of the template parameters of `function`,
+/// from the `template <...>` right before it.
+fn template_parameters(function: &SyntaxNode) -> Vec<(String, Option<String>)> {
+    let Some(template) = function.prev_sibling().and_then(ast::TemplateDef::cast) else {
+        return Vec::new();
+    };
+    template
+        .parameters()
+        .filter_map(|parameter| {
+            let name = parameter.name_token()?.text().to_string();
+            let tokens = significant_tokens(parameter.syntax());
+            let default = tokens
+                .iter()
+                .position(|token| token.kind() == SyntaxKind::Equal)
+                .map(|equal| tokens[equal + 1..].iter().map(|token| token.text()).collect::<Vec<_>>().join(" "));
+            Some((name, default))
+        })
+        .collect()
+}
+
+/// Template arguments of each explicit instantiation of the function `name`,
+/// such as `16` in `template kernel void k<16>(...);`.
+fn instantiations(
+    root: &SyntaxNode,
+    name: &str,
+) -> Vec<Vec<String>> {
+    root.descendants()
+        .filter_map(ast::FunctionDef::cast)
+        .filter(|function| function.body().is_none())
+        .filter_map(|function| {
+            let tokens = significant_tokens(&function.return_type()?.syntax().clone());
+            let open = tokens.windows(2).position(|pair| {
+                pair[0].kind() == SyntaxKind::Ident && pair[0].text() == name && pair[1].kind() == SyntaxKind::Less
+            })? + 2;
+            let mut arguments = Vec::new();
+            let mut current = Vec::new();
+            let mut depth = 0usize;
+            for token in &tokens[open..] {
+                match token.kind() {
+                    SyntaxKind::Greater if depth == 0 => {
+                        arguments.push(current.join(" "));
+                        return Some(arguments);
+                    },
+                    SyntaxKind::Comma if depth == 0 => arguments.push(std::mem::take(&mut current).join(" ")),
+                    kind => {
+                        match kind {
+                            SyntaxKind::Less | SyntaxKind::LParen => depth += 1,
+                            SyntaxKind::Greater | SyntaxKind::RParen => depth = depth.saturating_sub(1),
+                            _ => {},
+                        }
+                        current.push(token.text().to_string());
+                    },
+                }
+            }
+            None
+        })
+        .collect()
+}
+
+/// Initializers of variables, by name. Array extents may only name
+/// constants, so which of these are `constexpr` does not matter.
+fn integer_constants(root: &SyntaxNode) -> HashMap<String, String> {
+    let mut constants = HashMap::new();
+    for node in root.descendants() {
+        if !matches!(node.kind(), SyntaxKind::VariableDef | SyntaxKind::DeclStmt) {
+            continue;
+        }
+        let tokens: Vec<SyntaxToken> = node
+            .children_with_tokens()
+            .filter_map(|element| element.into_token())
+            .filter(|token| !matches!(token.kind(), SyntaxKind::Whitespace | SyntaxKind::Comment))
+            .collect();
+        if let [name, equal, value @ ..] = tokens.as_slice()
+            && name.kind() == SyntaxKind::Ident
+            && equal.kind() == SyntaxKind::Equal
+        {
+            let value: Vec<&str> = value
+                .iter()
+                .take_while(|token| token.kind() != SyntaxKind::Semicolon)
+                .map(|token| token.text())
+                .collect();
+            constants.insert(name.text().to_string(), value.join(" "));
+        }
+    }
+    constants
+}
+
+struct Context<'a> {
+    constants: HashMap<String, String>,
+    structs: HashMap<String, ast::StructDef>,
+    macros: &'a MacroIndex,
+}
+
+impl Context<'_> {
+    /// The variables declared by one declaration statement, e.g. both `a`
+    /// and `b` in `threadgroup float a[4], b;`.
+    fn variables(
+        &self,
+        declaration: &SyntaxNode,
+        source: &str,
+        bound: &HashMap<String, String>,
+    ) -> Vec<ThreadgroupVariable> {
+        let Some(type_ref) = declaration.children().find(|child| child.kind() == SyntaxKind::TypeRef) else {
+            return Vec::new();
+        };
+        let element = self.type_layout(&type_ref_name(&type_ref), bound, 0);
+        let tokens: Vec<SyntaxToken> = declaration
+            .children_with_tokens()
+            .filter_map(|element| element.into_token())
+            .filter(|token| !matches!(token.kind(), SyntaxKind::Whitespace | SyntaxKind::Comment))
+            .collect();
+        self.declarators(&tokens, bound)
+            .into_iter()
+            .map(|(name, extents)| {
+                let bytes = element
+                    .and_then(|(size, _)| extents.iter().try_fold(size, |bytes, extent| bytes.checked_mul((*extent)?)));
+                ThreadgroupVariable {
+                    name: name.text().to_string(),
+                    range: helpers::range_to_lsp(name.text_range(), source),
+                    bytes,
+                }
+            })
+            .collect()
+    }
+
+    /// Names and evaluated array extents of the comma-separated declarators
+    /// in `tokens`, the tokens of a declaration after its type.
+    fn declarators(
+        &self,
+        tokens: &[SyntaxToken],
+        bound: &HashMap<String, String>,
+    ) -> Vec<(SyntaxToken, Vec<Option<u64>>)> {
+        let mut declarators: Vec<(SyntaxToken, Vec<Option<u64>>)> = Vec::new();
+        let mut extent: Option<Vec<&str>> = None;
+        let mut depth = 0usize;
+        let mut expects_name = true;
+        for token in tokens {
+            match (token.kind(), extent.as_mut()) {
+                (SyntaxKind::LBracket, None) => extent = Some(Vec::new()),
+                (SyntaxKind::RBracket, Some(_)) if depth == 0 => {
+                    let text = extent.take().unwrap_or_default().join(" ");
+                    let value = self.eval(&text, bound, 0).and_then(|value| u64::try_from(value).ok());
+                    if let Some((_, extents)) = declarators.last_mut() {
+                        extents.push(value);
+                    }
+                },
+                (kind, Some(parts)) => {
+                    match kind {
+                        SyntaxKind::LBracket | SyntaxKind::LParen => depth += 1,
+                        SyntaxKind::RBracket | SyntaxKind::RParen => depth = depth.saturating_sub(1),
+                        _ => {},
+                    }
+                    parts.push(token.text());
+                },
+                (SyntaxKind::Ident, None) if expects_name => {
+                    declarators.push((token.clone(), Vec::new()));
+                    expects_name = false;
+                },
+                (SyntaxKind::Comma, None) => expects_name = true,
+                _ => {},
+            }
+        }
+        declarators
+    }
+
+    /// Size and alignment in bytes of the type named `name`.
+    fn type_layout(
+        &self,
+        name: &str,
+        bound: &HashMap<String, String>,
+        depth: usize,
+    ) -> Option<(u64, u64)> {
+        if depth > MAX_EVAL_DEPTH {
+            return None;
+        }
+        let name = name.trim();
+        if let Some(argument) = bound.get(name) {
+            return self.type_layout(argument, &HashMap::new(), depth + 1);
+        }
+        if let Some(inner) = name.strip_prefix("atomic<").and_then(|rest| rest.strip_suffix('>')) {
+            return self.type_layout(inner, bound, depth + 1);
+        }
+        if let Some(layout) = builtin_type_layout(name) {
+            return Some(layout);
+        }
+        if let Some(inner) = name.strip_prefix("vec<").and_then(|rest| rest.strip_suffix('>')) {
+            let (scalar, count) = inner.rsplit_once(',')?;
+            let (size, _) = self.type_layout(scalar, bound, depth + 1)?;
+            let count = u64::try_from(self.eval(count, bound, depth + 1)?).ok()?;
+            let bytes = size
+                * if count == 3 {
+                    4
+                } else {
+                    count
+                };
+            return Some((bytes, bytes));
+        }
+        let def = self.structs.get(name)?;
+        let mut size = 0u64;
+        let mut align = 1u64;
+        for field in def.fields() {
+            let type_ref = field.syntax().children().find(|child| child.kind() == SyntaxKind::TypeRef)?;
+            let (field_size, field_align) = self.type_layout(&type_ref_name(&type_ref), bound, depth + 1)?;
+            let tokens: Vec<SyntaxToken> = field
+                .syntax()
+                .children_with_tokens()
+                .filter_map(|element| element.into_token())
+                .filter(|token| !matches!(token.kind(), SyntaxKind::Whitespace | SyntaxKind::Comment))
+                .collect();
+            for (_, extents) in self.declarators(&tokens, bound) {
+                let bytes = extents.iter().try_fold(field_size, |bytes, extent| bytes.checked_mul((*extent)?))?;
+                size = size.next_multiple_of(field_align) + bytes;
+                align = align.max(field_align);
+            }
+        }
+        Some((size.next_multiple_of(align), align))
+    }
+
+    /// Value of the integer expression `text`.
+    fn eval(
+        &self,
+        text: &str,
+        bound: &HashMap<String, String>,
+        depth: usize,
+    ) -> Option<i64> {
+        if depth > MAX_EVAL_DEPTH {
+            return None;
+        }
+        let tokens = expression_tokens(text)?;
+        let mut parser = ExpressionParser {
+            tokens: &tokens,
+            position: 0,
+            resolve: &|name| {
+                let value =
+                    bound.get(name).or_else(|| self.constants.get(name)).cloned().or_else(|| {
+                        self.macros.get(name).filter(|def| def.params.is_none()).map(|def| def.body.clone())
+                    })?;
+                self.eval(&value, bound, depth + 1)
+            },
+        };
+        let value = parser.expression(0)?;
+        (parser.position == tokens.len()).then_some(value)
+    }
+}
+
+/// The type named by a type reference, without qualifiers or a `metal::`
+/// prefix, e.g. `atomic<int>` for `threadgroup metal::atomic<int>`.
+fn type_ref_name(type_ref: &SyntaxNode) -> String {
+    let tokens = significant_tokens(type_ref);
+    let mut name = String::new();
+    let mut skip_next_colons = false;
+    for token in &tokens {
+        match token.kind() {
+            SyntaxKind::KwThreadgroup
+            | SyntaxKind::KwConst
+            | SyntaxKind::KwConstexpr
+            | SyntaxKind::KwVolatile
+            | SyntaxKind::KwStatic => {},
+            SyntaxKind::DoubleColon if skip_next_colons => skip_next_colons = false,
+            SyntaxKind::Ident if token.text() == "metal" => skip_next_colons = true,
+            SyntaxKind::KwUnsigned => name.push('u'),
+            SyntaxKind::KwSigned => {},
+            SyntaxKind::Comma => name.push(','),
+            _ => name.push_str(token.text()),
+        }
+    }
+    // A bare `unsigned` is an `unsigned int`.
+    if name == "u" {
+        "uint".to_string()
+    } else {
+        name
+    }
+}
+
+/// Size and alignment of the scalar, vector, packed vector, matrix and
+/// atomic types of the Metal standard library.
+fn builtin_type_layout(name: &str) -> Option<(u64, u64)> {
+    if let Some(layout) = scalar_layout(name) {
+        return Some(layout);
+    }
+    if let Some(atomic) = name.strip_prefix("atomic_") {
+        return scalar_layout(atomic);
+    }
+    if let Some(packed) = name.strip_prefix("packed_") {
+        let (scalar, count) = split_count(packed)?;
+        let (size, align) = scalar_layout(scalar)?;
+        return Some((size * count, align));
+    }
+    if let Some((columns_type, rows)) = name.rsplit_once('x') {
+        let (scalar, columns) = split_count(columns_type)?;
+        let rows = rows.parse::<u64>().ok().filter(|rows| (2..=4).contains(rows))?;
+        let (column, align) = vector_layout(scalar, rows)?;
+        return Some((column * columns, align));
+    }
+    let (scalar, count) = split_count(name)?;
+    vector_layout(scalar, count)
+}
+
+fn scalar_layout(name: &str) -> Option<(u64, u64)> {
+    let size = match name {
+        "bool" | "char" | "uchar" | "int8_t" | "uint8_t" => 1,
+        "short" | "ushort" | "half" | "bfloat" | "int16_t" | "uint16_t" => 2,
+        "int" | "uint" | "float" | "int32_t" | "uint32_t" => 4,
+        "long" | "ulong" | "int64_t" | "uint64_t" | "size_t" | "ptrdiff_t" => 8,
+        _ => return None,
+    };
+    Some((size, size))
+}
+
+/// Three-element vectors take the size and alignment of four.
+fn vector_layout(
+    scalar: &str,
+    count: u64,
+) -> Option<(u64, u64)> {
+    let (size, _) = scalar_layout(scalar)?;
+    let bytes = size
+        * if count == 3 {
+            4
+        } else {
+            count
+        };
+    Some((bytes, bytes))
+}
+
+/// Split `float4` into `float` and 4, for counts from 2 to 4.
+fn split_count(name: &str) -> Option<(&str, u64)> {
+    let scalar = name.strip_suffix(|c: char| ('2'..='4').contains(&c))?;
+    Some((scalar, name[scalar.len()..].parse().ok()?))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ExpressionToken {
+    Number(i64),
+    Name(String),
+    Operator(&'static str),
+}
+
+const OPERATORS: &[&str] = &["<<", ">>", "+", "-", "*", "/", "%", "(", ")"];
+
+/// Tokens of an integer expression, or `None` when it has anything else.
+fn expression_tokens(text: &str) -> Option<Vec<ExpressionToken>> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        if let Some(operator) = OPERATORS.iter().find(|operator| rest.starts_with(**operator)) {
+            tokens.push(ExpressionToken::Operator(operator));
+            rest = &rest[operator.len()..];
+        } else if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            let end = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+            let literal = rest[..end].trim_end_matches(['u', 'U', 'l', 'L']);
+            let value = match literal.strip_prefix("0x").or_else(|| literal.strip_prefix("0X")) {
+                Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+                None => literal.parse().ok()?,
+            };
+            tokens.push(ExpressionToken::Number(value));
+            rest = &rest[end..];
+        } else if rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            tokens.push(ExpressionToken::Name(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else {
+            return None;
+        }
+        rest = rest.trim_start();
+    }
+    Some(tokens)
+}
+
+/// Precedence climbing over `+ - * / % << >>` and parentheses.
+struct ExpressionParser<'a> {
+    tokens: &'a [ExpressionToken],
+    position: usize,
+    resolve: &'a dyn Fn(&str) -> Option<i64>,
+}
+
+impl ExpressionParser<'_> {
+    fn expression(
+        &mut self,
+        min_precedence: u8,
+    ) -> Option<i64> {
+        let mut left = self.unary()?;
+        while let Some(ExpressionToken::Operator(operator)) = self.tokens.get(self.position) {
+            let precedence = match *operator {
+                "<<" | ">>" => 1,
+                "+" | "-" => 2,
+                "*" | "/" | "%" => 3,
+                _ => break,
+            };
+            if precedence < min_precedence {
+                break;
+            }
+            self.position += 1;
+            let right = self.expression(precedence + 1)?;
+            left = match *operator {
+                "<<" => left.checked_shl(u32::try_from(right).ok()?)?,
+                ">>" => left.checked_shr(u32::try_from(right).ok()?)?,
+                "+" => left.checked_add(right)?,
+                "-" => left.checked_sub(right)?,
+                "*" => left.checked_mul(right)?,
+                "/" => left.checked_div(right)?,
+                _ => left.checked_rem(right)?,
+            };
+        }
+        Some(left)
+    }
+
+    fn unary(&mut self) -> Option<i64> {
+        let token = self.tokens.get(self.position)?.clone();
+        self.position += 1;
+        match token {
+            ExpressionToken::Number(value) => Some(value),
+            ExpressionToken::Name(name) => (self.resolve)(&name),
+            ExpressionToken::Operator("-") => self.unary()?.checked_neg(),
+            ExpressionToken::Operator("+") => self.unary(),
+            ExpressionToken::Operator("(") => {
+                let value = self.expression(0)?;
+                (self.tokens.get(self.position) == Some(&ExpressionToken::Operator(")"))).then_some(())?;
+                self.position += 1;
+                Some(value)
+            },
+            ExpressionToken::Operator(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/threadgroup_memory_tests.rs"]
+mod tests;
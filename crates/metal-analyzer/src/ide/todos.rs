@@ -0,0 +1,132 @@
+//! Tagged comments such as `// TODO(name): ...` and `/* FIXME ... */`.
+//!
+//! Only comment tokens are scanned, so a tag inside a string literal or an
+//! identifier like `TODO_COUNT` is never reported. A tag must open the
+//! comment (or a line of a block comment) and be followed by `:`, `(`,
+//! whitespace or the end of the comment.
+
+use rowan::{TextRange, TextSize};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Range};
+
+use crate::syntax::{cst::SyntaxNode, helpers, kind::SyntaxKind};
+
+pub const TODO_CODE: &str = "todo";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TodoComment {
+    pub tag: String,
+    /// Name in `TODO(name):`.
+    pub owner: Option<String>,
+    /// Comment text after the tag, owner and colon.
+    pub text: String,
+    /// From the tag to the end of the comment line.
+    pub range: Range,
+}
+
+impl TodoComment {
+    /// `TODO(name): text`, as written without the comment markers.
+    pub fn message(&self) -> String {
+        let mut message = self.tag.clone();
+        if let Some(owner) = &self.owner {
+            message.push_str(&format!("({owner})"));
+        }
+        if !self.text.is_empty() {
+            message.push_str(": ");
+            message.push_str(&self.text);
+        }
+        message
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic {
+            range: self.range,
+            severity: Some(DiagnosticSeverity::INFORMATION),
+            code: Some(NumberOrString::String(TODO_CODE.to_string())),
+            source: Some("metal-analyzer".to_string()),
+            message: self.message(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Every comment in `root` that starts with one of `tags`.
+pub fn find_todos(
+    root: &SyntaxNode,
+    source: &str,
+    tags: &[String],
+) -> Vec<TodoComment> {
+    if tags.is_empty() {
+        return Vec::new();
+    }
+    let mut todos = Vec::new();
+    for token in root.descendants_with_tokens().filter_map(|element| element.into_token()) {
+        if token.kind() != SyntaxKind::Comment {
+            continue;
+        }
+        let comment_start = usize::from(token.text_range().start());
+        let mut line_start = 0;
+        for line in token.text().split_inclusive('\n') {
+            if let Some((offset, todo)) = tagged_line(line, tags) {
+                let start = comment_start + line_start + offset;
+                let end = start + todo.span;
+                let range = TextRange::new(TextSize::from(start as u32), TextSize::from(end as u32));
+                todos.push(TodoComment {
+                    tag: todo.tag,
+                    owner: todo.owner,
+                    text: todo.text,
+                    range: helpers::range_to_lsp(range, source),
+                });
+            }
+            line_start += line.len();
+        }
+    }
+    todos
+}
+
+struct TaggedLine {
+    tag: String,
+    owner: Option<String>,
+    text: String,
+    /// Bytes from the tag to the end of the comment text.
+    span: usize,
+}
+
+/// Byte offset of the tag within `line` and what follows it.
+fn tagged_line(
+    line: &str,
+    tags: &[String],
+) -> Option<(usize, TaggedLine)> {
+    let content = line.trim_end_matches(['\n', '\r']);
+    let content = content.strip_suffix("*/").unwrap_or(content).trim_end();
+    let body = content.trim_start().trim_start_matches(['/', '*', '!']).trim_start();
+    let offset = content.len() - body.len();
+
+    let tag = tags.iter().filter(|tag| body.starts_with(tag.as_str())).max_by_key(|tag| tag.len())?;
+    let mut rest = &body[tag.len()..];
+    if !(rest.is_empty() || rest.starts_with([':', '(']) || rest.starts_with(char::is_whitespace)) {
+        return None;
+    }
+
+    let mut owner = None;
+    if let Some(inner) = rest.strip_prefix('(')
+        && let Some(close) = inner.find(')')
+    {
+        owner = Some(inner[..close].trim().to_string()).filter(|owner| !owner.is_empty());
+        rest = &inner[close + 1..];
+    }
+    let text = rest.trim_start().strip_prefix(':').unwrap_or(rest).trim();
+
+    Some((
+        offset,
+        TaggedLine {
+            tag: tag.clone(),
+            owner,
+            text: text.to_string(),
+            span: body.len(),
+        },
+    ))
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/todos_tests.rs"]
+mod tests;
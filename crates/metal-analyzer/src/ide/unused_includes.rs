@@ -0,0 +1,155 @@
+//! `#include` lines of project headers that provide nothing the file uses,
+//! and the quick fix that removes them.
+//!
+//! A header counts as used when the file references a declaration from it
+//! in the AST index of its translation unit, names a type, function or
+//! variable it declares (type uses are not indexed as references), or names
+//! a macro it defines. Headers reached through another header belong to the
+//! `#include` line that first reached them, so a line is only reported when
+//! removing it cannot hide anything the file uses.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, DiagnosticSeverity, DiagnosticTag, NumberOrString,
+    Position, Range, TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::{
+    definition::AstIndex,
+    ide::macros::macro_definitions,
+    syntax::{cst::SyntaxNode, kind::SyntaxKind},
+    text_pos::text_width,
+};
+
+pub const UNUSED_INCLUDE_CODE: &str = "unused-include";
+
+/// Declarations that are only reachable through their parent, so naming
+/// one does not show the header is needed.
+const MEMBER_KINDS: &[&str] = &["FieldDecl", "ParmVarDecl", "CXXMethodDecl"];
+
+/// A project header of the file's translation unit.
+pub struct ReachedHeader<'a> {
+    pub path: &'a Path,
+    pub source: &'a str,
+    /// Zero-based line of the file's `#include` through which the header was
+    /// first reached.
+    pub origin_line: u32,
+}
+
+/// A hint on each of `include_lines` whose headers the file does not use.
+///
+/// `include_lines` are the zero-based lines of the file's `#include`s of
+/// project headers, and `index` the AST index of its translation unit.
+pub fn unused_includes(
+    root: &SyntaxNode,
+    text: &str,
+    file: &Path,
+    index: &AstIndex,
+    include_lines: &[u32],
+    headers: &[ReachedHeader<'_>],
+) -> Vec<Diagnostic> {
+    let origin_lines: HashMap<&Path, u32> = headers.iter().map(|header| (header.path, header.origin_line)).collect();
+    let names = used_names(root, text, include_lines);
+
+    let file_name = file.display().to_string();
+    let referenced = index
+        .refs
+        .iter()
+        .filter(|reference| reference.file == file_name)
+        .filter_map(|reference| index.id_to_def.get(&reference.target_id))
+        .map(|&def| &index.defs[def]);
+    let named =
+        index.defs.iter().filter(|def| names.contains(def.name.as_str()) && !MEMBER_KINDS.contains(&def.kind.as_str()));
+    let used_files: HashSet<&str> = referenced.chain(named).map(|def| def.file.as_str()).collect();
+
+    let mut used_lines: HashSet<u32> = used_files
+        .into_iter()
+        .filter_map(|def_file| {
+            let path = Path::new(def_file);
+            origin_lines.get(path).or_else(|| origin_lines.get(normalized(path).as_path())).copied()
+        })
+        .collect();
+    for header in headers {
+        if !used_lines.contains(&header.origin_line)
+            && macro_definitions(header.source, None).iter().any(|definition| names.contains(definition.name.as_str()))
+        {
+            used_lines.insert(header.origin_line);
+        }
+    }
+
+    let mut lines: Vec<u32> = include_lines.iter().copied().filter(|line| !used_lines.contains(line)).collect();
+    lines.sort_unstable();
+    lines.dedup();
+    lines
+        .into_iter()
+        .filter_map(|line| {
+            let width = text_width(text.lines().nth(line as usize)?);
+            Some(Diagnostic {
+                range: Range::new(Position::new(line, 0), Position::new(line, width)),
+                severity: Some(DiagnosticSeverity::HINT),
+                code: Some(NumberOrString::String(UNUSED_INCLUDE_CODE.to_string())),
+                source: Some("metal-analyzer".to_string()),
+                message: "include not used directly".to_string(),
+                tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// Identifiers written in the file outside its `#include` lines.
+fn used_names<'a>(
+    root: &SyntaxNode,
+    text: &'a str,
+    include_lines: &[u32],
+) -> HashSet<&'a str> {
+    let line_starts: Vec<usize> =
+        std::iter::once(0).chain(text.match_indices('\n').map(|(offset, _)| offset + 1)).collect();
+    root.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() == SyntaxKind::Ident)
+        .filter_map(|token| {
+            let start = usize::from(token.text_range().start());
+            let line = line_starts.partition_point(|&line_start| line_start <= start).saturating_sub(1) as u32;
+            (!include_lines.contains(&line)).then(|| text.get(start..usize::from(token.text_range().end())))?
+        })
+        .collect()
+}
+
+fn normalized(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// A quick fix deleting the `#include` line of each unused-include hint.
+pub fn remove_include_actions(
+    uri: &Url,
+    diagnostics: &[Diagnostic],
+) -> Vec<CodeActionOrCommand> {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.code == Some(NumberOrString::String(UNUSED_INCLUDE_CODE.to_string())))
+        .map(|diagnostic| {
+            let line = diagnostic.range.start.line;
+            let edit = TextEdit::new(Range::new(Position::new(line, 0), Position::new(line + 1, 0)), String::new());
+            CodeActionOrCommand::CodeAction(CodeAction {
+                title: "Remove unused include".to_string(),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic.clone()]),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+                    ..Default::default()
+                }),
+                is_preferred: Some(true),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/unused_includes_tests.rs"]
+mod tests;
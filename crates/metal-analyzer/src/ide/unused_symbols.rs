@@ -0,0 +1,66 @@
+//! Hints on `static` functions and constants, and structs defined in a
+//! `.metal` file, that nothing in the workspace uses.
+//!
+//! Only symbols no other translation unit can see are reported, so the
+//! project index sees every use.
+//! Functions with external linkage may be called from another library or
+//! looked up by name at runtime, and are left alone.
+
+use std::path::Path;
+
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, DiagnosticTag, NumberOrString, Position, Range};
+
+use crate::{
+    definition::ProjectIndex,
+    text_pos::{column_of_byte_offset, text_width},
+};
+
+pub const UNUSED_SYMBOL_CODE: &str = "unused-symbol";
+
+/// A hint on each definition in `path` that
+/// [`ProjectIndex::unreferenced_file_local_definitions`] reports, except
+/// those named in `allow`.
+pub fn unused_symbol_diagnostics(
+    project_index: &ProjectIndex,
+    path: &Path,
+    source: &str,
+    allow: &[String],
+) -> Vec<Diagnostic> {
+    let path = path.display().to_string();
+    let lines: Vec<&str> = source.lines().collect();
+    project_index
+        .unreferenced_file_local_definitions(&path)
+        .into_iter()
+        .filter(|def| !allow.iter().any(|name| *name == def.name))
+        .filter_map(|def| {
+            let line = def.line.checked_sub(1)?;
+            let text = lines.get(line as usize)?;
+            let start = (def.col as usize).checked_sub(1)?;
+            // Clang columns count bytes; skip definitions the text has
+            // moved away from since the index was built.
+            if !text.get(start..)?.starts_with(def.name.as_str()) {
+                return None;
+            }
+            let character = column_of_byte_offset(text, start);
+            let width = text_width(&def.name);
+            let noun = match def.kind.as_str() {
+                "VarDecl" => "constant",
+                "CXXRecordDecl" => "struct",
+                _ => "function",
+            };
+            Some(Diagnostic {
+                range: Range::new(Position::new(line, character), Position::new(line, character + width)),
+                severity: Some(DiagnosticSeverity::HINT),
+                code: Some(NumberOrString::String(UNUSED_SYMBOL_CODE.to_string())),
+                source: Some("metal-analyzer".to_string()),
+                message: format!("{noun} `{}` is never used", def.name),
+                tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/ide/unused_symbols_tests.rs"]
+mod tests;
@@ -5,6 +5,7 @@ pub mod document;
 pub mod hover;
 pub mod ide;
 pub mod metal;
+pub(crate) mod perf;
 pub mod progress;
 pub mod semantic_tokens;
 pub mod server;
@@ -15,8 +16,8 @@ pub mod vfs;
 
 pub use completion::CompletionProvider;
 pub use definition::{
-    AstIndex, DefinitionProvider, RefSite, SymbolDef, def_to_location, is_system_header, normalize_type_name,
-    paths_match,
+    AstIndex, DefinitionProvider, RefSite, SharedStr, SymbolDef, def_to_location, is_system_header,
+    normalize_type_name, paths_match,
 };
 pub use hover::HoverProvider;
 pub use ide::navigation::{IdeLocation, IdePosition, IdeRange, NavigationTarget};
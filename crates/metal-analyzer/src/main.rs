@@ -1,14 +1,46 @@
-use std::sync::Arc;
+use std::{
+    cell::Cell,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 
 use clap::Parser;
-use metal_analyzer::server::{
-    MetalLanguageServer,
-    formatting::{FormattingError, clang_format_args, run_clang_format},
+use metal_analyzer::{
+    definition::DefinitionProvider,
+    metal::compiler::MetalCompiler,
+    server::{
+        MetalLanguageServer,
+        check::{check_files, configured_compiler, has_errors, render_diagnostic, watched_sources},
+        formatting::format_text,
+        lint::{
+            LintFinding, LintLevels, LintRule, enabled_rules, findings_json, lint_files, render_finding, sarif_rules,
+        },
+        prebuild::{prebuild_ast_cache, prebuild_targets},
+        preprocess::preprocess_file,
+        sarif::{SarifResult, compiler_rules, sarif_log},
+        settings::{
+            FormattingEngine, FormattingSettings, MAX_INDEXING_CONCURRENCY, MIN_INDEXING_CONCURRENCY, ServerSettings,
+            read_workspace_settings_file,
+        },
+        state_dump::{StateDump, set_log_file},
+    },
+    vfs::watch::{FileChange, FileSnapshot},
+};
+use tower_lsp::{
+    Client, LspService, Server,
+    lsp_types::{DiagnosticSeverity, FormattingOptions, MessageType},
 };
-use tower_lsp::{Client, LspService, Server, lsp_types::MessageType};
 use tracing::info;
 use tracing_subscriber::{EnvFilter, Layer, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 
+/// How often `--watch` modes poll the watched files for changes.
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 #[derive(Parser, Debug)]
 #[command(name = "metal-analyzer", version, about)]
 struct Args {
@@ -29,6 +61,16 @@ struct Args {
 enum Command {
     /// Format Metal source files
     Format(FormatArgs),
+    /// Compile Metal source files and print their diagnostics
+    Check(CheckArgs),
+    /// Run metal-analyzer's own lint rules, most of which need no toolchain
+    Lint(LintArgs),
+    /// Prebuild the on-disk AST cache used for go-to-definition
+    Index(IndexArgs),
+    /// Print all project definitions as line-delimited JSON
+    Symbols(SymbolsArgs),
+    /// Print a file as the compiler sees it after preprocessing
+    Preprocess(PreprocessArgs),
 }
 
 #[derive(clap::Args, Debug)]
@@ -40,6 +82,11 @@ struct FormatArgs {
     #[arg(long)]
     check: bool,
 
+    /// Formatter engine: `clang-format`, the built-in formatter, or `auto` to
+    /// fall back to the built-in one when clang-format is not installed
+    #[arg(long, default_value = "auto", value_parser = ["auto", "clang-format", "internal"])]
+    engine: String,
+
     /// Formatting command
     #[arg(long, default_value = "clang-format")]
     command: String,
@@ -47,6 +94,99 @@ struct FormatArgs {
     /// Extra arguments for the formatter
     #[arg(long)]
     args: Vec<String>,
+
+    /// Style when neither `metalfmt.toml` nor `.clang-format` is found: `metal`,
+    /// `none`, a predefined style name, or an inline `{Key: Value}` override
+    #[arg(long, default_value = "metal")]
+    fallback_style: String,
+
+    /// Keep running and reformat (or re-check with `--check`) files as they change
+    #[arg(long)]
+    watch: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct WorkspaceArgs {
+    /// Workspace roots to index. Defaults to the current directory.
+    roots: Vec<String>,
+
+    /// Maximum number of concurrent compiler invocations (defaults to `indexing.concurrency`)
+    #[arg(long)]
+    concurrency: Option<usize>,
+
+    /// Extra paths to skip, resolved from each root like `indexing.excludePaths`
+    #[arg(long)]
+    exclude: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct CheckArgs {
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+
+    /// Keep running and re-check files as they change
+    #[arg(long)]
+    watch: bool,
+
+    /// Output format: `file:line:col` text, or a SARIF log
+    #[arg(long, default_value = "text", value_parser = ["text", "sarif"], conflicts_with = "watch")]
+    format: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct LintArgs {
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+
+    /// Report a rule's findings as errors, failing the run. Repeatable.
+    #[arg(long, value_parser = lint_rule_names())]
+    deny: Vec<String>,
+
+    /// Turn a rule off. Repeatable.
+    #[arg(long, value_parser = lint_rule_names())]
+    allow: Vec<String>,
+
+    /// Output format: `file:line:col` text, a JSON array, or a SARIF log
+    #[arg(long, default_value = "text", value_parser = ["text", "json", "sarif"])]
+    format: String,
+}
+
+fn lint_rule_names() -> clap::builder::PossibleValuesParser {
+    clap::builder::PossibleValuesParser::new(LintRule::ALL.map(LintRule::name))
+}
+
+#[derive(clap::Args, Debug)]
+struct IndexArgs {
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+
+    /// Print every indexed file
+    #[arg(long)]
+    list: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct SymbolsArgs {
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+
+    /// Also print definitions from system headers such as `metal_stdlib`
+    #[arg(long)]
+    include_system: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct PreprocessArgs {
+    /// Metal source file to preprocess
+    file: String,
+
+    /// Keep only the declarations this entry point depends on
+    #[arg(long)]
+    entry: Option<String>,
+
+    /// Workspace roots for include paths and settings. Defaults to the current directory.
+    #[arg(long = "root")]
+    roots: Vec<String>,
 }
 
 fn default_log_path() -> std::path::PathBuf {
@@ -80,6 +220,11 @@ async fn run() -> Result<std::process::ExitCode, Box<dyn std::error::Error + Sen
 
     match args.command {
         Some(Command::Format(fmt_args)) => run_format(fmt_args).await,
+        Some(Command::Check(check_args)) => run_check(check_args).await,
+        Some(Command::Lint(lint_args)) => run_lint(lint_args).await,
+        Some(Command::Index(index_args)) => run_index(index_args).await,
+        Some(Command::Symbols(symbols_args)) => run_symbols(symbols_args).await,
+        Some(Command::Preprocess(preprocess_args)) => run_preprocess(preprocess_args).await,
         None => {
             run_server(args).await?;
             Ok(std::process::ExitCode::SUCCESS)
@@ -89,13 +234,32 @@ async fn run() -> Result<std::process::ExitCode, Box<dyn std::error::Error + Sen
 
 async fn run_format(fmt_args: FormatArgs) -> Result<std::process::ExitCode, Box<dyn std::error::Error + Send + Sync>> {
     let use_stdin = fmt_args.files.is_empty() || (fmt_args.files.len() == 1 && fmt_args.files[0] == "-");
+    let settings = FormattingSettings {
+        engine: match fmt_args.engine.as_str() {
+            "clang-format" => FormattingEngine::ClangFormat,
+            "internal" => FormattingEngine::Internal,
+            _ => FormattingEngine::Auto,
+        },
+        command: fmt_args.command,
+        args: fmt_args.args,
+        fallback_style: fmt_args.fallback_style,
+        ..FormattingSettings::default()
+    };
+    let options = FormattingOptions {
+        tab_size: 4,
+        insert_spaces: true,
+        ..FormattingOptions::default()
+    };
+
+    if use_stdin && fmt_args.watch {
+        return Err("--watch needs files to watch; it cannot read from stdin".into());
+    }
 
     if use_stdin {
         let mut input = String::new();
         tokio::io::AsyncReadExt::read_to_string(&mut tokio::io::stdin(), &mut input).await?;
         // When reading from stdin we don't have a real file path for config discovery.
-        let args = clang_format_args(&fmt_args.args, "shader.metal".to_string());
-        let formatted = run_clang_format_with_fallback(&fmt_args.command, &args, &input).await?;
+        let formatted = format_text(&input, None, &options, &settings).await?;
 
         if fmt_args.check {
             if formatted != input {
@@ -112,69 +276,450 @@ async fn run_format(fmt_args: FormatArgs) -> Result<std::process::ExitCode, Box<
     let mut has_error = false;
 
     for file_path in &fmt_args.files {
-        let path = std::path::Path::new(file_path);
-        let input = match std::fs::read_to_string(path) {
-            Ok(content) => content,
-            Err(error) => {
-                eprintln!("error: {file_path}: {error}");
-                has_error = true;
+        match format_file(file_path, fmt_args.check, &options, &settings).await {
+            FormatOutcome::Unchanged => {},
+            FormatOutcome::Changed => has_diff |= fmt_args.check,
+            FormatOutcome::Failed => has_error = true,
+        }
+    }
+
+    if fmt_args.watch {
+        watch_format(&fmt_args.files, fmt_args.check, &options, &settings).await;
+    }
+
+    if has_error {
+        Ok(std::process::ExitCode::FAILURE)
+    } else if has_diff {
+        Ok(std::process::ExitCode::from(1))
+    } else {
+        Ok(std::process::ExitCode::SUCCESS)
+    }
+}
+
+/// Result of formatting a single file with [`format_file`].
+enum FormatOutcome {
+    Unchanged,
+    /// The file was rewritten, or with `--check` needs formatting.
+    Changed,
+    Failed,
+}
+
+async fn format_file(
+    file_path: &str,
+    check: bool,
+    options: &FormattingOptions,
+    settings: &FormattingSettings,
+) -> FormatOutcome {
+    let path = Path::new(file_path);
+    let input = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) => {
+            eprintln!("error: {file_path}: {error}");
+            return FormatOutcome::Failed;
+        },
+    };
+
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let formatted = match format_text(&input, Some(&canonical), options, settings).await {
+        Ok(output) => output,
+        Err(error) => {
+            eprintln!("error: {file_path}: {error}");
+            return FormatOutcome::Failed;
+        },
+    };
+
+    if formatted == input {
+        return FormatOutcome::Unchanged;
+    }
+
+    if check {
+        eprintln!("{file_path}");
+        return FormatOutcome::Changed;
+    }
+
+    if let Err(error) = std::fs::write(path, &formatted) {
+        eprintln!("error: {file_path}: {error}");
+        return FormatOutcome::Failed;
+    }
+    FormatOutcome::Changed
+}
+
+/// Reformat or re-check `files` whenever they change. Runs until interrupted.
+async fn watch_format(
+    files: &[String],
+    check: bool,
+    options: &FormattingOptions,
+    settings: &FormattingSettings,
+) {
+    let paths = || files.iter().map(PathBuf::from);
+    let mut snapshot = FileSnapshot::capture(paths());
+    eprintln!("Watching {} file(s) for changes", snapshot.len());
+
+    loop {
+        tokio::time::sleep(WATCH_POLL_INTERVAL).await;
+        let changes = FileSnapshot::capture(paths()).changes_since(&snapshot);
+        for change in &changes {
+            let (FileChange::Created(path) | FileChange::Modified(path)) = change else {
                 continue;
-            },
-        };
+            };
+            let file_path = path.display().to_string();
+            match format_file(&file_path, check, options, settings).await {
+                FormatOutcome::Unchanged => eprintln!("{file_path}: ok"),
+                FormatOutcome::Changed if !check => eprintln!("{file_path}: formatted"),
+                FormatOutcome::Changed | FormatOutcome::Failed => {},
+            }
+        }
+        // Capture after formatting so our own writes are not reported as changes.
+        if !changes.is_empty() {
+            snapshot = FileSnapshot::capture(paths());
+        }
+    }
+}
 
-        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
-        let args = clang_format_args(&fmt_args.args, canonical.display().to_string());
+async fn run_check(check_args: CheckArgs) -> Result<std::process::ExitCode, Box<dyn std::error::Error + Send + Sync>> {
+    let Workspace {
+        roots,
+        settings,
+        files,
+    } = resolve_workspace(check_args.workspace)?;
+    if files.is_empty() && !check_args.watch {
+        eprintln!("No .metal files found");
+        return Ok(std::process::ExitCode::SUCCESS);
+    }
+    ensure_toolchain().await?;
+
+    let compiler = configured_compiler(&settings).await;
+    if check_args.format == "sarif" {
+        return print_check_sarif(&compiler, &roots, &files, settings.indexing.concurrency).await;
+    }
+    let failed = print_check_results(&compiler, &roots, &files, settings.indexing.concurrency).await;
+    if !check_args.watch {
+        return Ok(if failed == 0 {
+            std::process::ExitCode::SUCCESS
+        } else {
+            std::process::ExitCode::FAILURE
+        });
+    }
+
+    let mut snapshot = FileSnapshot::capture(watched_sources(&roots, &settings));
+    eprintln!("Watching {} file(s) for changes", snapshot.len());
+    loop {
+        tokio::time::sleep(WATCH_POLL_INTERVAL).await;
+        let current = FileSnapshot::capture(watched_sources(&roots, &settings));
+        let changes = current.changes_since(&snapshot);
+        snapshot = current;
+        if changes.is_empty() {
+            continue;
+        }
+
+        let targets = recheck_targets(&changes, || prebuild_targets(&roots, &settings));
+        for change in &changes {
+            if let FileChange::Removed(path) = change {
+                eprintln!("removed: {}", path.display());
+            }
+        }
+        if !targets.is_empty() {
+            print_check_results(&compiler, &roots, &targets, settings.indexing.concurrency).await;
+        }
+    }
+}
+
+/// `.metal` files to re-check after `changes`.
+///
+/// The CLI does not track which sources include a header, so a changed
+/// header re-checks every `.metal` file in the workspace.
+fn recheck_targets(
+    changes: &[FileChange],
+    all_sources: impl FnOnce() -> Vec<PathBuf>,
+) -> Vec<PathBuf> {
+    let is_metal = |path: &Path| path.extension().is_some_and(|ext| ext == "metal");
+    let header_changed = changes.iter().any(|change| !is_metal(change.path()));
+    if header_changed {
+        return all_sources();
+    }
+    changes
+        .iter()
+        .filter_map(|change| match change {
+            FileChange::Created(path) | FileChange::Modified(path) => Some(path.clone()),
+            FileChange::Removed(_) => None,
+        })
+        .collect()
+}
 
-        let formatted = match run_clang_format_with_fallback(&fmt_args.command, &args, &input).await {
-            Ok(output) => output,
+/// Check `files`, print their diagnostics and a summary, and return how
+/// many files failed to compile.
+async fn print_check_results(
+    compiler: &MetalCompiler,
+    roots: &[PathBuf],
+    files: &[PathBuf],
+    concurrency: usize,
+) -> usize {
+    let (mut errors, mut warnings, mut failed) = (0, 0, 0);
+    for (path, result) in check_files(compiler, roots, files, concurrency).await {
+        let diagnostics = match result {
+            Ok(diagnostics) => diagnostics,
             Err(error) => {
-                eprintln!("error: {file_path}: {error}");
-                has_error = true;
+                eprintln!("error: {}: {error}", path.display());
+                failed += 1;
                 continue;
             },
         };
-
-        if formatted == input {
-            continue;
+        for diag in &diagnostics {
+            println!("{}", render_diagnostic(diag, &path));
+            match diag.severity {
+                DiagnosticSeverity::ERROR => errors += 1,
+                DiagnosticSeverity::WARNING => warnings += 1,
+                _ => {},
+            }
+        }
+        if has_errors(&diagnostics) {
+            failed += 1;
         }
+    }
+    eprintln!("Checked {} file(s): {errors} error(s), {warnings} warning(s)", files.len());
+    failed
+}
 
-        if fmt_args.check {
-            eprintln!("{file_path}");
-            has_diff = true;
-            continue;
+async fn run_lint(lint_args: LintArgs) -> Result<std::process::ExitCode, Box<dyn std::error::Error + Send + Sync>> {
+    let Workspace {
+        roots,
+        settings,
+        files,
+    } = resolve_workspace(lint_args.workspace)?;
+    let rules = |names: &[String]| names.iter().filter_map(|name| LintRule::from_name(name)).collect();
+    let levels = LintLevels {
+        allow: rules(&lint_args.allow),
+        deny: rules(&lint_args.deny),
+    };
+
+    let compiler = configured_compiler(&settings).await;
+    let needs_toolchain: Vec<LintRule> =
+        enabled_rules(&levels, &settings).into_iter().filter(|rule| rule.needs_toolchain()).collect();
+    let provider = Arc::new(DefinitionProvider::new());
+    let definitions = if needs_toolchain.is_empty() || files.is_empty() {
+        None
+    } else if MetalCompiler::is_toolchain_available().await {
+        prebuild_ast_cache(&provider, &roots, &files, &settings, |path, ok| {
+            if !ok {
+                eprintln!("failed to index: {}", path.display());
+            }
+        })
+        .await;
+        Some(provider.as_ref())
+    } else {
+        for rule in &needs_toolchain {
+            eprintln!("note: skipping `{}`, which needs `xcrun metal`", rule.name());
         }
+        None
+    };
 
-        if let Err(error) = std::fs::write(path, &formatted) {
-            eprintln!("error: {file_path}: {error}");
-            has_error = true;
+    let mut findings: Vec<LintFinding> = Vec::new();
+    let mut failed = false;
+    for (path, result) in lint_files(&compiler, definitions, &roots, &settings, &levels, &files).await {
+        match result {
+            Ok(file_findings) => findings.extend(file_findings),
+            Err(error) => {
+                eprintln!("error: {}: {error}", path.display());
+                failed = true;
+            },
         }
     }
 
-    if has_error {
+    let mut stdout = std::io::stdout().lock();
+    match lint_args.format.as_str() {
+        "json" => writeln!(stdout, "{}", serde_json::to_string_pretty(&findings_json(&findings))?)?,
+        "sarif" => {
+            let results: Vec<SarifResult> = findings.iter().map(LintFinding::to_sarif).collect();
+            writeln!(stdout, "{}", serde_json::to_string_pretty(&sarif_log(&sarif_rules(), &results, &roots))?)?;
+        },
+        _ => {
+            for finding in &findings {
+                writeln!(stdout, "{}", render_finding(finding))?;
+            }
+            let errors = findings.iter().filter(|finding| finding.is_error()).count();
+            eprintln!(
+                "Linted {} file(s): {errors} error(s), {} other finding(s)",
+                files.len(),
+                findings.len() - errors
+            );
+        },
+    }
+
+    if failed || findings.iter().any(LintFinding::is_error) {
         Ok(std::process::ExitCode::FAILURE)
-    } else if has_diff {
-        Ok(std::process::ExitCode::from(1))
     } else {
         Ok(std::process::ExitCode::SUCCESS)
     }
 }
 
-async fn run_clang_format_with_fallback(
-    command: &str,
-    args: &[String],
-    input: &str,
-) -> Result<String, FormattingError> {
-    match run_clang_format(command, args, input).await {
-        Ok(output) => Ok(output),
-        Err(FormattingError::CommandNotFound(_)) if command == "clang-format" => {
-            let mut xcrun_args = vec!["clang-format".to_string()];
-            xcrun_args.extend(args.iter().cloned());
-            run_clang_format("xcrun", &xcrun_args, input).await
-        },
-        Err(error) => Err(error),
+/// Check `files` and print their diagnostics as one SARIF log.
+async fn print_check_sarif(
+    compiler: &MetalCompiler,
+    roots: &[PathBuf],
+    files: &[PathBuf],
+    concurrency: usize,
+) -> Result<std::process::ExitCode, Box<dyn std::error::Error + Send + Sync>> {
+    let mut results = Vec::new();
+    let mut failed = false;
+    for (path, result) in check_files(compiler, roots, files, concurrency).await {
+        match result {
+            Ok(diagnostics) => {
+                failed |= has_errors(&diagnostics);
+                results.extend(diagnostics.iter().map(|diag| SarifResult::from_metal_diagnostic(&path, diag)));
+            },
+            Err(error) => {
+                eprintln!("error: {}: {error}", path.display());
+                failed = true;
+            },
+        }
+    }
+    let log = sarif_log(&compiler_rules(), &results, roots);
+    writeln!(std::io::stdout().lock(), "{}", serde_json::to_string_pretty(&log)?)?;
+    Ok(if failed {
+        std::process::ExitCode::FAILURE
+    } else {
+        std::process::ExitCode::SUCCESS
+    })
+}
+
+/// Workspace roots, settings and `.metal` files selected by [`WorkspaceArgs`].
+struct Workspace {
+    roots: Vec<PathBuf>,
+    settings: ServerSettings,
+    files: Vec<PathBuf>,
+}
+
+fn resolve_workspace(args: WorkspaceArgs) -> Result<Workspace, Box<dyn std::error::Error + Send + Sync>> {
+    let roots = if args.roots.is_empty() {
+        vec![std::env::current_dir()?]
+    } else {
+        args.roots.iter().map(PathBuf::from).collect()
+    };
+    let roots: Vec<PathBuf> = roots.iter().map(|root| root.canonicalize()).collect::<Result<_, _>>()?;
+
+    // Honor the checked-in workspace settings, so the CLI indexes the same
+    // files with the same exclusions the editor would.
+    let mut settings = workspace_settings(&roots);
+    if let Some(concurrency) = args.concurrency {
+        settings.indexing.concurrency = concurrency.clamp(MIN_INDEXING_CONCURRENCY, MAX_INDEXING_CONCURRENCY);
+    }
+    settings.indexing.exclude_paths.extend(args.exclude);
+
+    let files = prebuild_targets(&roots, &settings);
+    Ok(Workspace {
+        roots,
+        settings,
+        files,
+    })
+}
+
+fn workspace_settings(roots: &[PathBuf]) -> ServerSettings {
+    let workspace_files: Vec<_> = roots.iter().filter_map(|root| read_workspace_settings_file(root)).collect();
+    ServerSettings::layered(&workspace_files, None)
+}
+
+async fn ensure_toolchain() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if MetalCompiler::is_toolchain_available().await {
+        Ok(())
+    } else {
+        Err("Metal compiler toolchain or SDK is unavailable; this command needs `xcrun metal`".into())
     }
 }
 
+async fn run_index(index_args: IndexArgs) -> Result<std::process::ExitCode, Box<dyn std::error::Error + Send + Sync>> {
+    let Workspace {
+        roots,
+        settings,
+        files,
+    } = resolve_workspace(index_args.workspace)?;
+    if files.is_empty() {
+        eprintln!("No .metal files found");
+        return Ok(std::process::ExitCode::SUCCESS);
+    }
+    ensure_toolchain().await?;
+
+    let total = files.len();
+    let mut done = 0;
+    let provider = Arc::new(DefinitionProvider::new());
+    let summary = prebuild_ast_cache(&provider, &roots, &files, &settings, |path, ok| {
+        done += 1;
+        if !ok {
+            eprintln!("[{done}/{total}] failed: {}", path.display());
+        } else if index_args.list {
+            eprintln!("[{done}/{total}] {}", path.display());
+        }
+    })
+    .await;
+
+    eprintln!("Indexed {} of {total} file(s)", summary.indexed);
+    if summary.failed.is_empty() {
+        Ok(std::process::ExitCode::SUCCESS)
+    } else {
+        Ok(std::process::ExitCode::FAILURE)
+    }
+}
+
+async fn run_symbols(
+    symbols_args: SymbolsArgs
+) -> Result<std::process::ExitCode, Box<dyn std::error::Error + Send + Sync>> {
+    let Workspace {
+        roots,
+        settings,
+        files,
+    } = resolve_workspace(symbols_args.workspace)?;
+    if files.is_empty() {
+        eprintln!("No .metal files found");
+        return Ok(std::process::ExitCode::SUCCESS);
+    }
+    ensure_toolchain().await?;
+
+    let provider = Arc::new(DefinitionProvider::new());
+    let summary = prebuild_ast_cache(&provider, &roots, &files, &settings, |path, ok| {
+        if !ok {
+            eprintln!("failed to index: {}", path.display());
+        }
+    })
+    .await;
+
+    let mut stdout = std::io::stdout().lock();
+    for def in provider.project_index().all_definitions(symbols_args.include_system) {
+        let record = serde_json::json!({
+            "name": def.name,
+            "kind": def.kind,
+            "file": def.file,
+            "line": def.line,
+            "col": def.col,
+            "is_definition": def.is_definition,
+            "qual_type": def.qual_type,
+        });
+        writeln!(stdout, "{record}")?;
+    }
+
+    if summary.failed.is_empty() {
+        Ok(std::process::ExitCode::SUCCESS)
+    } else {
+        Ok(std::process::ExitCode::FAILURE)
+    }
+}
+
+async fn run_preprocess(
+    preprocess_args: PreprocessArgs
+) -> Result<std::process::ExitCode, Box<dyn std::error::Error + Send + Sync>> {
+    let roots = if preprocess_args.roots.is_empty() {
+        vec![std::env::current_dir()?]
+    } else {
+        preprocess_args.roots.iter().map(PathBuf::from).collect()
+    };
+    let roots: Vec<PathBuf> = roots.iter().map(|root| root.canonicalize()).collect::<Result<_, _>>()?;
+    let file = Path::new(&preprocess_args.file).canonicalize()?;
+    ensure_toolchain().await?;
+
+    let compiler = configured_compiler(&workspace_settings(&roots)).await;
+    let output = preprocess_file(&compiler, &roots, &file, preprocess_args.entry.as_deref()).await?;
+    std::io::stdout().lock().write_all(output.as_bytes())?;
+    Ok(std::process::ExitCode::SUCCESS)
+}
+
 async fn run_server(args: Args) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let stderr_filter = if args.verbose {
         EnvFilter::new("metal_analyzer=debug")
@@ -207,10 +752,13 @@ async fn run_server(args: Args) -> Result<(), Box<dyn std::error::Error + Send +
     info!("Log file: {}", log_path.display());
 
     let log_messages = args.log_messages;
-    let (service, socket) = LspService::new(|client| {
-        install_panic_hook(client.clone());
-        MetalLanguageServer::new(client, log_messages)
-    });
+    set_log_file(log_path.clone());
+    let (service, socket) = MetalLanguageServer::with_custom_methods(LspService::build(|client| {
+        let server = MetalLanguageServer::new(client.clone(), log_messages);
+        install_panic_hook(client, server.state_dump());
+        server
+    }))
+    .finish();
 
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
@@ -220,23 +768,55 @@ async fn run_server(args: Args) -> Result<(), Box<dyn std::error::Error + Send +
     Ok(())
 }
 
-fn install_panic_hook(client: Client) {
+fn install_panic_hook(
+    client: Client,
+    state_dump: StateDump,
+) {
+    thread_local! {
+        /// Set while this thread runs the hook, so a panic inside it only
+        /// reaches the previous hook.
+        static IN_HOOK: Cell<bool> = const { Cell::new(false) };
+    }
+    /// Only the first panic writes a snapshot: later ones, including those
+    /// `catch_unwind` recovers from, would each stall on a capture.
+    static SNAPSHOT_WRITTEN: AtomicBool = AtomicBool::new(false);
+
     let previous_hook = std::panic::take_hook();
     let client = Arc::new(client);
     let weak_client = Arc::downgrade(&client);
 
     std::panic::set_hook(Box::new(move |panic_info| {
+        if IN_HOOK.replace(true) {
+            previous_hook(panic_info);
+            return;
+        }
         tracing::error!("Server panicked: {panic_info}");
 
+        let snapshot = if SNAPSHOT_WRITTEN.swap(true, Ordering::SeqCst) {
+            String::new()
+        } else {
+            match state_dump.capture(&format!("panic: {panic_info}"), None) {
+                Ok(path) => {
+                    tracing::error!("Wrote state snapshot to {}", path.display());
+                    format!(" A state snapshot for the bug report was written to {}.", path.display())
+                },
+                Err(error) => {
+                    tracing::error!("Failed to write the state snapshot: {error}");
+                    String::new()
+                },
+            }
+        };
+
         if let Some(client) = weak_client.upgrade() {
             let message = format!(
                 "metal-analyzer: encountered an internal error and may need to be restarted. \
-                 Details: {panic_info}"
+                 Details: {panic_info}.{snapshot}"
             );
-            let _ = futures::executor::block_on(client.show_message(MessageType::ERROR, message));
+            futures::executor::block_on(client.show_message(MessageType::ERROR, message));
         }
 
         previous_hook(panic_info);
+        IN_HOOK.set(false);
     }));
 
     // Keep the Arc alive for the lifetime of the server.
@@ -0,0 +1,280 @@
+//! Metal attributes (`[[ ... ]]`) and where each one may be written.
+
+/// The declaration an attribute list is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeTarget {
+    /// A function, written before its qualifier: `[[early_fragment_tests]] fragment ...`.
+    Function,
+    /// A function parameter, usually of an entry point.
+    Parameter,
+    /// A struct member, e.g. of a vertex output or argument buffer.
+    Member,
+    /// A program-scope variable such as a function constant.
+    Variable,
+}
+
+/// Entry-point kind whose parameters an attribute is valid on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Kernel,
+}
+
+impl ShaderStage {
+    pub fn from_qualifier(qualifier: &str) -> Option<Self> {
+        match qualifier {
+            "vertex" => Some(Self::Vertex),
+            "fragment" => Some(Self::Fragment),
+            "kernel" => Some(Self::Kernel),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AttributeSpec {
+    pub name: &'static str,
+    /// Written form with placeholder arguments, e.g. `buffer(n)`.
+    pub signature: &'static str,
+    /// Snippet inserted between the brackets.
+    pub snippet: &'static str,
+    pub doc: &'static str,
+    pub targets: &'static [AttributeTarget],
+    /// Entry points whose parameters accept the attribute; empty for all.
+    pub stages: &'static [ShaderStage],
+}
+
+impl AttributeSpec {
+    /// Whether the attribute may be written on `target`, in a parameter of a
+    /// `stage` entry point when known.
+    pub fn applies_to(
+        &self,
+        target: AttributeTarget,
+        stage: Option<ShaderStage>,
+    ) -> bool {
+        if !self.targets.contains(&target) {
+            return false;
+        }
+        match (target, stage) {
+            (AttributeTarget::Parameter, Some(stage)) => self.stages.is_empty() || self.stages.contains(&stage),
+            _ => true,
+        }
+    }
+}
+
+use AttributeTarget::{Function, Member, Parameter, Variable};
+use ShaderStage::{Fragment, Kernel, Vertex};
+
+/// An attribute without arguments.
+const fn flag(
+    name: &'static str,
+    doc: &'static str,
+    targets: &'static [AttributeTarget],
+    stages: &'static [ShaderStage],
+) -> AttributeSpec {
+    attribute(name, name, name, doc, targets, stages)
+}
+
+const fn attribute(
+    name: &'static str,
+    signature: &'static str,
+    snippet: &'static str,
+    doc: &'static str,
+    targets: &'static [AttributeTarget],
+    stages: &'static [ShaderStage],
+) -> AttributeSpec {
+    AttributeSpec {
+        name,
+        signature,
+        snippet,
+        doc,
+        targets,
+        stages,
+    }
+}
+
+pub static ATTRIBUTES: &[AttributeSpec] = &[
+    // Resource bindings.
+    attribute(
+        "buffer",
+        "buffer(n)",
+        "buffer(${1:0})",
+        "Assigns a buffer to an index in the buffer argument table.",
+        &[Parameter],
+        &[],
+    ),
+    attribute(
+        "texture",
+        "texture(n)",
+        "texture(${1:0})",
+        "Assigns a texture to an index in the texture argument table.",
+        &[Parameter],
+        &[],
+    ),
+    attribute(
+        "sampler",
+        "sampler(n)",
+        "sampler(${1:0})",
+        "Assigns a sampler to an index in the sampler argument table.",
+        &[Parameter],
+        &[],
+    ),
+    attribute(
+        "threadgroup",
+        "threadgroup(n)",
+        "threadgroup(${1:0})",
+        "Assigns threadgroup memory to an index in the threadgroup argument table.",
+        &[Parameter],
+        &[Kernel],
+    ),
+    attribute("id", "id(n)", "id(${1:0})", "Index of a member in an argument buffer.", &[Member], &[]),
+    flag(
+        "stage_in",
+        "Per-vertex or per-fragment input assembled from the vertex descriptor or the rasterizer.",
+        &[Parameter],
+        &[Vertex, Fragment, Kernel],
+    ),
+    // Vertex inputs.
+    flag("vertex_id", "The current vertex index.", &[Parameter], &[Vertex]),
+    flag("instance_id", "The current instance index.", &[Parameter], &[Vertex]),
+    flag("base_vertex", "The base vertex of the draw call.", &[Parameter], &[Vertex]),
+    flag("base_instance", "The base instance of the draw call.", &[Parameter], &[Vertex]),
+    flag("amplification_id", "The index of the vertex amplification.", &[Parameter], &[Vertex, Fragment]),
+    attribute(
+        "attribute",
+        "attribute(n)",
+        "attribute(${1:0})",
+        "Vertex descriptor attribute index of a `[[stage_in]]` member.",
+        &[Member],
+        &[],
+    ),
+    // Fragment inputs.
+    flag("front_facing", "Whether the primitive is front facing.", &[Parameter], &[Fragment]),
+    flag("point_coord", "Position of the fragment within a point primitive.", &[Parameter], &[Fragment]),
+    flag("sample_id", "The sample number of the sample being processed.", &[Parameter], &[Fragment]),
+    flag("barycentric_coord", "Barycentric weights of the fragment within the primitive.", &[Parameter], &[Fragment]),
+    flag("primitive_id", "The current primitive index.", &[Parameter, Member], &[Fragment]),
+    // Vertex outputs and fragment inputs/outputs.
+    flag("position", "Vertex position (graphics) or pixel position (fragment).", &[Parameter, Member], &[Fragment]),
+    flag("point_size", "Point size for point primitives.", &[Member], &[]),
+    flag("clip_distance", "Distance from the vertex to each clip plane.", &[Member], &[]),
+    flag("invariant", "Computes the position identically across vertex functions.", &[Member], &[]),
+    attribute(
+        "user",
+        "user(name)",
+        "user(${1:name})",
+        "Names a vertex output so the fragment input with the same name receives it.",
+        &[Member],
+        &[],
+    ),
+    flag("flat", "Takes the value of the provoking vertex without interpolation.", &[Member], &[]),
+    flag("center_perspective", "Perspective-correct interpolation at the pixel center (the default).", &[Member], &[]),
+    flag("center_no_perspective", "Linear interpolation at the pixel center.", &[Member], &[]),
+    flag("centroid_perspective", "Perspective-correct interpolation at the centroid.", &[Member], &[]),
+    flag("centroid_no_perspective", "Linear interpolation at the centroid.", &[Member], &[]),
+    flag("sample_perspective", "Perspective-correct interpolation at each sample.", &[Member], &[]),
+    flag("sample_no_perspective", "Linear interpolation at each sample.", &[Member], &[]),
+    flag(
+        "render_target_array_index",
+        "Layer of the render target array to render to.",
+        &[Parameter, Member],
+        &[Fragment],
+    ),
+    flag("viewport_array_index", "Viewport to render to.", &[Parameter, Member], &[Fragment]),
+    attribute(
+        "color",
+        "color(n)",
+        "color(${1:0})",
+        "Color attachment index of a fragment output, or the attachment read for programmable blending.",
+        &[Parameter, Member],
+        &[Fragment],
+    ),
+    attribute(
+        "depth",
+        "depth(any)",
+        "depth(${1|any,greater,less|})",
+        "Fragment depth output and its relation to the interpolated depth.",
+        &[Member],
+        &[],
+    ),
+    flag("stencil", "Fragment stencil reference output.", &[Member], &[]),
+    flag("sample_mask", "Coverage mask of the fragment.", &[Parameter, Member], &[Fragment]),
+    attribute(
+        "raster_order_group",
+        "raster_order_group(n)",
+        "raster_order_group(${1:0})",
+        "Raster order group index.",
+        &[Parameter, Member],
+        &[Fragment],
+    ),
+    // Compute inputs.
+    flag("thread_position_in_grid", "The position of the thread in the grid.", &[Parameter], &[Kernel]),
+    flag("thread_position_in_threadgroup", "The position of the thread in the threadgroup.", &[Parameter], &[Kernel]),
+    flag("thread_index_in_threadgroup", "The linear index of the thread in the threadgroup.", &[Parameter], &[Kernel]),
+    flag(
+        "thread_index_in_simdgroup",
+        "The linear index of the thread in the SIMD group.",
+        &[Parameter],
+        &[Kernel, Fragment],
+    ),
+    flag(
+        "thread_index_in_quadgroup",
+        "The linear index of the thread in the quad group.",
+        &[Parameter],
+        &[Kernel, Fragment],
+    ),
+    flag("thread_execution_width", "The SIMD group width of the device.", &[Parameter], &[Kernel, Fragment]),
+    flag("threadgroup_position_in_grid", "The position of the threadgroup in the grid.", &[Parameter], &[Kernel]),
+    flag("threadgroups_per_grid", "The size of the grid in threadgroups.", &[Parameter], &[Kernel]),
+    flag("threads_per_grid", "The size of the grid in threads.", &[Parameter], &[Kernel]),
+    flag("threads_per_threadgroup", "The size of the threadgroup in threads.", &[Parameter], &[Kernel]),
+    flag("threads_per_simdgroup", "The number of threads in the SIMD group.", &[Parameter], &[Kernel, Fragment]),
+    flag("simd_position_in_grid", "The position of the SIMD group in the grid.", &[Parameter], &[Kernel]),
+    flag("simdgroup_index_in_threadgroup", "The index of the SIMD group in the threadgroup.", &[Parameter], &[Kernel]),
+    flag("simdgroups_per_threadgroup", "The number of SIMD groups in the threadgroup.", &[Parameter], &[Kernel]),
+    flag("quadgroup_index_in_threadgroup", "The index of the quad group in the threadgroup.", &[Parameter], &[Kernel]),
+    flag(
+        "dispatch_threads_per_threadgroup",
+        "The threadgroup size of the dispatch, excluding partial threadgroups.",
+        &[Parameter],
+        &[Kernel],
+    ),
+    // Function qualifiers.
+    flag("early_fragment_tests", "Force early fragment tests.", &[Function], &[]),
+    attribute(
+        "max_total_threads_per_threadgroup",
+        "max_total_threads_per_threadgroup(n)",
+        "max_total_threads_per_threadgroup(${1:256})",
+        "Upper bound on the threadgroup size the kernel is dispatched with.",
+        &[Function],
+        &[],
+    ),
+    attribute(
+        "patch",
+        "patch(quad, n)",
+        "patch(${1|quad,triangle|}, ${2:4})",
+        "Marks a post-tessellation vertex function and its patch type.",
+        &[Function],
+        &[],
+    ),
+    flag("visible", "Makes a function callable through a visible function table.", &[Function], &[]),
+    flag("stitchable", "Makes a function usable in function stitching graphs.", &[Function], &[]),
+    attribute(
+        "host_name",
+        "host_name(name)",
+        "host_name(\"${1:name}\")",
+        "Name the function is looked up by from the API.",
+        &[Function],
+        &[],
+    ),
+    // Program-scope variables.
+    attribute(
+        "function_constant",
+        "function_constant(n)",
+        "function_constant(${1:0})",
+        "Specializes the variable from a function constant index at pipeline creation.",
+        &[Variable, Parameter, Member],
+        &[],
+    ),
+];
@@ -0,0 +1,30 @@
+//! The Metal Shading Language version that introduced a builtin.
+//!
+//! Builtins missing here are available in every version the compiler
+//! accepts. Entries name a builtin label or a prefix of labels.
+
+use crate::metal::language_version::LanguageVersion;
+
+enum Label {
+    Exact(&'static str),
+    Prefix(&'static str),
+}
+
+static INTRODUCED: &[(Label, LanguageVersion)] = &[
+    (Label::Prefix("ray_tracing::"), LanguageVersion::new(2, 3)),
+    (Label::Exact("atomic_float"), LanguageVersion::new(3, 0)),
+    (Label::Exact("mesh"), LanguageVersion::new(3, 0)),
+    (Label::Exact("object"), LanguageVersion::new(3, 0)),
+    // bfloat, bfloat16_t and the bfloat vector and matrix types.
+    (Label::Prefix("bfloat"), LanguageVersion::new(3, 1)),
+];
+
+pub(crate) fn introduced_in(label: &str) -> Option<LanguageVersion> {
+    INTRODUCED.iter().find_map(|(pattern, version)| {
+        let matches = match pattern {
+            Label::Exact(name) => label == *name,
+            Label::Prefix(prefix) => label.starts_with(prefix),
+        };
+        matches.then_some(*version)
+    })
+}
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, sync::OnceLock};
 
-use crate::metal::builtins::{functions, keywords::KEYWORDS, types::BuiltinEntry};
+use crate::metal::builtins::{availability, functions, keywords::KEYWORDS, types::BuiltinEntry};
 
 static ALL_BUILTINS: OnceLock<Vec<BuiltinEntry>> = OnceLock::new();
 static BUILTIN_MAP: OnceLock<HashMap<String, usize>> = OnceLock::new();
@@ -35,6 +35,9 @@ fn build_builtins() -> Vec<BuiltinEntry> {
     functions::add_misc_types(&mut entries);
     functions::add_builtin_constants(&mut entries);
 
+    for entry in &mut entries {
+        entry.since = availability::introduced_in(&entry.label);
+    }
     entries
 }
 
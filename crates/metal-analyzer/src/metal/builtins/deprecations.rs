@@ -0,0 +1,37 @@
+//! Deprecated Metal APIs and the spelling that replaces each one.
+//!
+//! The compiler reports uses of deprecated declarations and attributes; this
+//! table only supplies the upgrade for the ones with a direct replacement.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deprecation {
+    /// Identifier as written in source, without a namespace or brackets.
+    pub name: &'static str,
+    /// Identifier written in its place.
+    pub replacement: &'static str,
+    pub note: &'static str,
+}
+
+pub static DEPRECATIONS: &[Deprecation] = &[Deprecation {
+    name: "thread_execution_width",
+    replacement: "threads_per_simdgroup",
+    note: "The SIMD group width is read from `[[threads_per_simdgroup]]`.",
+}];
+
+pub fn deprecation(name: &str) -> Option<&'static Deprecation> {
+    DEPRECATIONS.iter().find(|deprecation| deprecation.name == name)
+}
+
+/// Whether a compiler warning reports a deprecated declaration or attribute,
+/// e.g. `'foo' is deprecated: ... [-Wdeprecated-declarations]`.
+pub fn is_deprecation_message(message: &str) -> bool {
+    message.contains("[-Wdeprecated") || message.contains(" is deprecated")
+}
+
+/// The deprecated identifier a warning names: the first quoted identifier.
+pub fn deprecated_name(message: &str) -> Option<&str> {
+    let (_, rest) = message.split_once('\'')?;
+    let (quoted, _) = rest.split_once('\'')?;
+    let name = quoted.rsplit("::").next()?.trim_start_matches("[[").trim_end_matches("]]");
+    (!name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')).then_some(name)
+}
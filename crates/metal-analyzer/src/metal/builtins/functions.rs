@@ -381,6 +381,20 @@ pub(crate) fn add_attributes(entries: &mut Vec<BuiltinEntry>) {
         // Also add the full attribute string for direct matching
         entries.push(BuiltinEntry::attr(snippet, doc, None));
     }
+
+    // The rest of the attribute table only in bracketed form, so hovering an
+    // identifier such as `depth` or `id` keeps resolving to user symbols.
+    // Arguments are spelled `n`, matching the normalized form hover looks up.
+    for spec in super::attributes::ATTRIBUTES {
+        let bracketed = if spec.signature.contains('(') {
+            format!("[[{}(n)]]", spec.name)
+        } else {
+            format!("[[{}]]", spec.signature)
+        };
+        if !entries.iter().any(|entry| entry.label == bracketed) {
+            entries.push(BuiltinEntry::attr(&bracketed, spec.doc, None));
+        }
+    }
 }
 
 pub(crate) fn add_sampler_constants(entries: &mut Vec<BuiltinEntry>) {
@@ -415,21 +429,33 @@ pub(crate) fn add_sampler_constants(entries: &mut Vec<BuiltinEntry>) {
     }
 }
 
+/// Entry-point skeletons. Binding indices and the thread-position vector
+/// width are tab stops, so a new shader only needs its names filled in.
 pub(crate) fn add_snippets(entries: &mut Vec<BuiltinEntry>) {
     entries.push(BuiltinEntry::snippet(
         "kernel",
         "Kernel Function",
-        "kernel void ${1:name}(device ${2:type}* ${3:buffer} [[buffer(0)]], uint ${4:id} [[thread_position_in_grid]]) {\n\t$0\n}",
+        "kernel void ${1:name}(device ${2:float}* ${3:data} [[buffer(${4:0})]], ${5|uint,uint2,uint3|} ${6:gid} [[thread_position_in_grid]]) {\n\t$0\n}",
     ));
     entries.push(BuiltinEntry::snippet(
         "vertex",
         "Vertex Function",
-        "vertex ${1:VertexOut} ${2:name}(uint ${3:vertexID} [[vertex_id]], constant ${4:Uniforms}& ${5:uniforms} [[buffer(0)]]) {\n\t$0\n}",
+        "vertex ${1:VertexOut} ${2:name}(uint ${3:vid} [[vertex_id]], const device ${4:Vertex}* ${5:vertices} [[buffer(${6:0})]], constant ${7:Uniforms}& ${8:uniforms} [[buffer(${9:1})]]) {\n\t$0\n}",
     ));
     entries.push(BuiltinEntry::snippet(
         "fragment",
         "Fragment Function",
-        "fragment float4 ${1:name}(${2:VertexOut} ${3:in} [[stage_in]]) {\n\treturn float4(1.0);\n}",
+        "fragment float4 ${1:name}(${2:VertexOut} ${3:in} [[stage_in]], constant ${4:Uniforms}& ${5:uniforms} [[buffer(${6:0})]]) {\n\t${0:return float4(1.0);}\n}",
+    ));
+    entries.push(BuiltinEntry::snippet(
+        "mesh",
+        "Mesh Function",
+        "[[mesh]] void ${1:name}(mesh<${2:VertexOut}, ${3:PrimitiveOut}, ${4:64}, ${5:126}, topology::${6|triangle,line,point|}> ${7:output}, const object_data ${8:Payload}& ${9:payload} [[payload]], uint ${10:tid} [[thread_index_in_threadgroup]], uint ${11:tgid} [[threadgroup_position_in_grid]]) {\n\t$0\n}",
+    ));
+    entries.push(BuiltinEntry::snippet(
+        "object",
+        "Object Function",
+        "[[object]] void ${1:name}(object_data ${2:Payload}& ${3:payload} [[payload]], mesh_grid_properties ${4:grid}, device ${5:float}* ${6:data} [[buffer(${7:0})]], uint ${8:gid} [[thread_position_in_grid]]) {\n\t$0\n}",
     ));
 }
 
@@ -1,4 +1,7 @@
+pub(crate) mod attributes;
+pub(crate) mod availability;
 pub(crate) mod database;
+pub(crate) mod deprecations;
 pub(crate) mod functions;
 pub(crate) mod keywords;
 pub(crate) mod types;
@@ -8,7 +11,9 @@ pub(crate) mod types;
 mod tests;
 
 pub use self::{
+    attributes::{ATTRIBUTES, AttributeSpec, AttributeTarget, ShaderStage},
     database::{all, lookup},
+    deprecations::{DEPRECATIONS, Deprecation, deprecated_name, deprecation, is_deprecation_message},
     keywords::KEYWORDS,
     types::{BuiltinEntry, BuiltinKind},
 };
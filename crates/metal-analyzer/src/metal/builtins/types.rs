@@ -1,3 +1,5 @@
+use crate::metal::language_version::LanguageVersion;
+
 /// What kind of symbol this builtin represents.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BuiltinKind {
@@ -19,9 +21,21 @@ pub struct BuiltinEntry {
     pub is_snippet: bool,
     pub kind: BuiltinKind,
     pub category: Option<&'static str>,
+    /// Language version that introduced the builtin, when newer than the
+    /// oldest supported one.
+    pub since: Option<LanguageVersion>,
 }
 
 impl BuiltinEntry {
+    /// Whether the builtin exists when targeting `version`; `None` targets
+    /// the newest.
+    pub fn is_available_in(
+        &self,
+        version: Option<LanguageVersion>,
+    ) -> bool {
+        self.since.zip(version).is_none_or(|(since, version)| since <= version)
+    }
+
     pub(crate) fn keyword(
         label: &str,
         doc: &str,
@@ -34,6 +48,7 @@ impl BuiltinEntry {
             is_snippet: false,
             kind: BuiltinKind::Keyword,
             category: None,
+            since: None,
         }
     }
 
@@ -49,6 +64,7 @@ impl BuiltinEntry {
             is_snippet: false,
             kind: BuiltinKind::Type,
             category: None,
+            since: None,
         }
     }
 
@@ -66,6 +82,7 @@ impl BuiltinEntry {
             is_snippet: false,
             kind: BuiltinKind::Function,
             category: Some(cat),
+            since: None,
         }
     }
 
@@ -82,6 +99,7 @@ impl BuiltinEntry {
             is_snippet: snippet.is_some(),
             kind: BuiltinKind::Attribute,
             category: None,
+            since: None,
         }
     }
 
@@ -98,6 +116,7 @@ impl BuiltinEntry {
             is_snippet: true,
             kind: BuiltinKind::Snippet,
             category: None,
+            since: None,
         }
     }
 
@@ -114,6 +133,7 @@ impl BuiltinEntry {
             is_snippet: false,
             kind: BuiltinKind::Constant,
             category: None,
+            since: None,
         }
     }
 }
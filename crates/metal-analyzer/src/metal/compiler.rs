@@ -1,23 +1,54 @@
 use std::{
-    collections::{BTreeSet, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    num::NonZeroUsize,
     path::{Path, PathBuf},
     sync::{
-        RwLock,
-        atomic::{AtomicU64, Ordering},
+        Arc, LazyLock, Mutex, RwLock, Weak,
+        atomic::{AtomicBool, AtomicU64, Ordering},
     },
 };
 
 use regex::Regex;
-use tokio::process::Command;
-use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+use tokio::{process::Command, sync::Semaphore};
+use tower_lsp::lsp_types::{
+    CodeDescription, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DiagnosticTag, Location,
+    NumberOrString, Position, Range, Url,
+};
 use tracing::{debug, error, warn};
 
+use crate::{
+    definition::is_system_header,
+    metal::{
+        compiler_overrides::{self, CompilerOverride},
+        diagnostic_codes::{SPEC_URL, classify},
+        fix_its::{CompilerFix, FixIt, attach_fix, parse_fix_it_line},
+        kernel_stats::{KernelStats, parse_air_module},
+        language_version::LanguageVersion,
+        pch::{PrecompiledHeaders, includes_prelude_first},
+        toolchain,
+        virtual_includes::VirtualIncludeRoots,
+    },
+    text_pos::{column_in_file, column_of_byte_offset, line_in_file},
+    vfs::virtual_docs::document_path,
+};
+
 static NEXT_COMPILATION_ID: AtomicU64 = AtomicU64::new(1);
 const METAL_MACOS_DEFINE: &str = "-D__METAL_MACOS__";
 const METAL_IOS_DEFINE: &str = "-D__METAL_IOS__";
 const METAL_TVOS_DEFINE: &str = "-D__METAL_TVOS__";
 const METAL_WATCHOS_DEFINE: &str = "-D__METAL_WATCHOS__";
 const METAL_XROS_DEFINE: &str = "-D__METAL_XROS__";
+/// Folder under the temp base that keeps artifacts across server restarts.
+const ARTIFACTS_DIR_NAME: &str = "metal-analyzer-artifacts";
+/// Folder under the temp directory holding [`MetalCompiler::compile_binary`] outputs.
+const BINARIES_DIR_NAME: &str = "binaries";
+/// Name the compiler gives a translation unit read from stdin.
+const STDIN_FILE_NAME: &str = "<stdin>";
+
+/// Slots for compiler processes shared by diagnostics compiles and AST dumps,
+/// one per core, so a cold toolchain cannot pile up `xcrun` processes.
+pub(crate) static COMPILER_PROCESSES: LazyLock<Semaphore> =
+    LazyLock::new(|| Semaphore::new(std::thread::available_parallelism().map_or(4, NonZeroUsize::get)));
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum CompilerPlatform {
@@ -94,9 +125,11 @@ pub fn compute_include_paths(
 ) -> Vec<String> {
     let mut unique = BTreeSet::new();
 
-    // Find the workspace root that contains this file (if any)
-    let workspace_root =
-        workspace_roots.and_then(|roots| roots.iter().find(|root| file_path.starts_with(root)).cloned());
+    // Find the innermost workspace root that contains this file (if any), so
+    // the walk never leaves the file's own project for an enclosing one.
+    let workspace_root = workspace_roots.and_then(|roots| {
+        roots.iter().filter(|root| file_path.starts_with(root)).max_by_key(|root| root.components().count()).cloned()
+    });
 
     // Helper to add a directory and its immediate children
     let add_dir_and_children = |dir: &Path, unique: &mut BTreeSet<String>| {
@@ -203,11 +236,7 @@ fn parse_include_search_paths(raw_output: &str) -> Vec<PathBuf> {
                 // Encode framework roots with a special prefix so that
                 // resolve_include_path can apply the framework lookup rule:
                 //   `<Foo/Bar.h>` → `<root>/Foo.framework/Headers/Bar.h`
-                discovered_paths.push(PathBuf::from(format!(
-                    "{}{}",
-                    FRAMEWORK_DIR_PREFIX,
-                    path.display()
-                )));
+                discovered_paths.push(PathBuf::from(format!("{}{}", FRAMEWORK_DIR_PREFIX, path.display())));
             } else {
                 discovered_paths.push(path);
             }
@@ -282,25 +311,63 @@ pub struct MetalDiagnostic {
     pub file: Option<String>,
     pub line: u32,
     pub column: u32,
+    /// End of the source the diagnostic is about, past its last character.
+    /// `None` leaves the range empty at `line`:`column`.
+    pub end: Option<Position>,
     pub severity: DiagnosticSeverity,
     pub message: String,
+    /// Extra locations, such as the kept translation unit of a failing
+    /// compile or the system header a remapped error came from.
+    pub related_information: Vec<DiagnosticRelatedInformation>,
+    /// Edits the compiler suggests for this diagnostic.
+    pub fix_its: Vec<FixIt>,
 }
 
 impl MetalDiagnostic {
+    /// Range from `line`:`column` to `end`.
+    pub fn range(&self) -> Range {
+        let start = Position::new(self.line, self.column);
+        Range::new(start, self.end.unwrap_or(start))
+    }
+
     /// Convert into an LSP `Diagnostic`.
     pub fn into_lsp_diagnostic(self) -> Diagnostic {
-        let pos = Position::new(self.line, self.column);
-        Diagnostic {
-            range: Range::new(pos, pos),
+        let range = self.range();
+        let related_information = if self.related_information.is_empty() {
+            None
+        } else {
+            Some(self.related_information)
+        };
+        // Deprecations stay warnings, tagged so clients strike the use through.
+        let tags = (self.severity == DiagnosticSeverity::WARNING
+            && crate::metal::builtins::is_deprecation_message(&self.message))
+        .then(|| vec![DiagnosticTag::DEPRECATED]);
+        let code = classify(&self.message);
+        let mut diagnostic = Diagnostic {
+            range,
             severity: Some(self.severity),
-            code: None,
-            code_description: None,
+            code: code.map(|code| NumberOrString::String(code.code.to_string())),
+            code_description: code.and_then(|_| {
+                Some(CodeDescription {
+                    href: Url::parse(SPEC_URL).ok()?,
+                })
+            }),
             source: Some("metal-compiler".to_string()),
             message: self.message,
-            related_information: None,
-            tags: None,
+            related_information,
+            tags,
             data: None,
+        };
+        if !self.fix_its.is_empty() {
+            attach_fix(
+                &mut diagnostic,
+                CompilerFix {
+                    message: None,
+                    edits: self.fix_its,
+                },
+            );
         }
+        diagnostic
     }
 }
 
@@ -312,7 +379,11 @@ impl MetalDiagnostic {
 /// as `#include "../../../common/utils.h"`) work correctly.
 pub struct MetalCompiler {
     /// Temporary directory for compilation artifacts.
-    temp_dir: PathBuf,
+    temp_dir: RwLock<Arc<TempDir>>,
+    /// Directories replaced by `set_temp_dir` that compiles may still use.
+    retired_temp_dirs: Mutex<Vec<Weak<TempDir>>>,
+    /// Whether and how much of failing compiles' temp files to keep.
+    artifact_retention: RwLock<ArtifactRetention>,
     /// Compiled regex for parsing diagnostic lines.
     diagnostic_re: Regex,
     /// System include paths discovered from the toolchain.
@@ -323,6 +394,13 @@ pub struct MetalCompiler {
     extra_flags: RwLock<Vec<String>>,
     /// Platform context used to resolve implicit compiler defines.
     platform: RwLock<CompilerPlatform>,
+    /// Flags and include paths for the files matching a path glob.
+    overrides: RwLock<Vec<CompilerOverride>>,
+    /// Settings of each workspace root, used for its files in place of the
+    /// global include paths, flags, platform and language version.
+    root_settings: RwLock<Vec<RootCompilerSettings>>,
+    /// Standard selected with `-std=` for files whose flags name none.
+    language_version: RwLock<Option<LanguageVersion>>,
     /// Serializes first-time include discovery so startup races don't trigger
     /// duplicate discovery calls.
     include_discovery_lock: tokio::sync::Mutex<()>,
@@ -330,6 +408,108 @@ pub struct MetalCompiler {
     ///
     /// Used to detect toolchain upgrades while the language server is running.
     toolchain_signature: RwLock<Option<String>>,
+    /// Whether diagnostics compiles load a precompiled `<metal_stdlib>`.
+    use_precompiled_headers: AtomicBool,
+    precompiled_headers: PrecompiledHeaders,
+    virtual_include_roots: VirtualIncludeRoots,
+    /// Whether diagnostics compiles feed the source through stdin; cleared
+    /// once the compiler rejects it, after which temp files are used.
+    stdin_input: AtomicBool,
+}
+
+/// The source of the file a diagnostics compile read, by line.
+struct MainSource<'a> {
+    /// The name the compiler reports for it.
+    file: &'a Path,
+    lines: Vec<&'a str>,
+}
+
+impl<'a> MainSource<'a> {
+    fn new(
+        file: &'a Path,
+        source: &'a str,
+    ) -> Self {
+        Self {
+            file,
+            lines: source.split('\n').map(|line| line.trim_end_matches('\r')).collect(),
+        }
+    }
+
+    fn line(
+        &self,
+        line: u32,
+    ) -> Option<&'a str> {
+        self.lines.get(line as usize).copied()
+    }
+
+    /// Column of the 0-based `byte_column` on `line` in the negotiated
+    /// encoding, kept as is past the end of the source.
+    fn column(
+        &self,
+        line: u32,
+        byte_column: u32,
+    ) -> u32 {
+        match self.line(line) {
+            Some(text) if !text.is_ascii() => column_of_byte_offset(text, byte_column as usize),
+            _ => byte_column,
+        }
+    }
+}
+
+/// Why [`MetalCompiler::compile_from_stdin`] gave no diagnostics.
+enum StdinFallback {
+    /// The compiler does not read source from stdin: compile temp files from
+    /// now on.
+    Unsupported,
+    /// Running the compiler or feeding it the source failed: compile a temp
+    /// file this time.
+    ThisCompile,
+}
+
+/// Outputs of [`MetalCompiler::compile_binary`].
+#[derive(Debug, Clone, Default)]
+pub struct CompiledBinary {
+    /// The `.air` object, when compiling succeeded.
+    pub air: Option<PathBuf>,
+    /// The `.metallib` linked from it, when linking succeeded too.
+    pub metallib: Option<PathBuf>,
+    /// Warnings and errors from compiling and linking.
+    pub diagnostics: Vec<MetalDiagnostic>,
+}
+
+/// Result of [`MetalCompiler::compile_air`].
+struct AirCompile {
+    success: bool,
+    diagnostics: Vec<MetalDiagnostic>,
+    /// The temporary source file that was compiled, already deleted.
+    source_file: PathBuf,
+}
+
+/// The compiler settings of one workspace root in a multi-root workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootCompilerSettings {
+    pub root: PathBuf,
+    pub include_paths: Vec<PathBuf>,
+    pub flags: Vec<String>,
+    pub platform: CompilerPlatform,
+    pub language_version: Option<LanguageVersion>,
+}
+
+/// Retention policy for the temp translation units of failing compiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArtifactRetention {
+    pub keep: bool,
+    /// Total size budget of the artifacts folder; the oldest files are pruned first.
+    pub max_bytes: u64,
+}
+
+impl Default for ArtifactRetention {
+    fn default() -> Self {
+        Self {
+            keep: false,
+            max_bytes: 64 * 1024 * 1024,
+        }
+    }
 }
 
 impl Default for MetalCompiler {
@@ -342,24 +522,37 @@ impl MetalCompiler {
     /// Create a new `MetalCompiler`.
     ///
     /// A unique temporary directory is created under the system temp dir
-    /// to hold intermediate compilation files.
+    /// to hold intermediate compilation files, and the directories left
+    /// behind by servers that are no longer running are removed.
     pub fn new() -> Self {
-        let temp_dir = std::env::temp_dir().join(format!("metal-analyzer-{}", std::process::id()));
+        static COLLECT_SYSTEM_TEMP: std::sync::Once = std::sync::Once::new();
+        COLLECT_SYSTEM_TEMP.call_once(|| collect_stale_temp_dirs(&std::env::temp_dir()));
+        let temp_dir = process_temp_dir(&std::env::temp_dir());
         if let Err(e) = std::fs::create_dir_all(&temp_dir) {
             warn!("Failed to create temp directory {:?}: {}", temp_dir, e);
         }
 
-        let diagnostic_re = Regex::new(r"^(.*?):(\d+):(\d+):\s*(error|warning|note):\s*(.*)$").unwrap();
+        let diagnostic_re =
+            Regex::new(r"^(.*?):(\d+):(\d+):((?:\{\d+:\d+-\d+:\d+\})*):?\s*(error|warning|note):\s*(.*)$").unwrap();
 
         Self {
-            temp_dir,
+            temp_dir: RwLock::new(Arc::new(TempDir::new(temp_dir))),
+            retired_temp_dirs: Mutex::new(Vec::new()),
+            artifact_retention: RwLock::new(ArtifactRetention::default()),
             diagnostic_re,
             system_include_paths: RwLock::new(Vec::new()),
             extra_include_paths: RwLock::new(Vec::new()),
             extra_flags: RwLock::new(Vec::new()),
             platform: RwLock::new(CompilerPlatform::Macos),
+            overrides: RwLock::new(Vec::new()),
+            root_settings: RwLock::new(Vec::new()),
+            language_version: RwLock::new(None),
             include_discovery_lock: tokio::sync::Mutex::new(()),
             toolchain_signature: RwLock::new(None),
+            use_precompiled_headers: AtomicBool::new(true),
+            precompiled_headers: PrecompiledHeaders::default(),
+            virtual_include_roots: VirtualIncludeRoots::default(),
+            stdin_input: AtomicBool::new(true),
         }
     }
 
@@ -447,6 +640,18 @@ impl MetalCompiler {
         self.extra_include_paths.read().map(|guard| guard.clone()).unwrap_or_default()
     }
 
+    /// The extra include paths for the document at `uri`: its workspace
+    /// root's when that root has settings, the global ones otherwise.
+    pub fn include_paths_for(
+        &self,
+        uri: &str,
+    ) -> Vec<PathBuf> {
+        match self.root_settings_for(uri) {
+            Some(root) => root.include_paths,
+            None => self.get_include_paths(),
+        }
+    }
+
     /// Return the discovered system include paths.
     pub fn get_system_include_paths(&self) -> Vec<PathBuf> {
         self.system_include_paths.read().map(|g| g.clone()).unwrap_or_default()
@@ -473,7 +678,8 @@ impl MetalCompiler {
         self.cached_toolchain_signature().is_some_and(|cached_signature| cached_signature == detected_signature)
     }
 
-    fn cached_toolchain_signature(&self) -> Option<String> {
+    /// The `metal` binary found when the include paths were last resolved.
+    pub fn cached_toolchain_signature(&self) -> Option<String> {
         self.toolchain_signature.read().map(|signature| signature.clone()).unwrap_or(None)
     }
 
@@ -535,6 +741,16 @@ impl MetalCompiler {
         command.output().await.map(|output| output.status.success()).unwrap_or(false)
     }
 
+    /// `xcrun metal --version`, or `None` without a working toolchain.
+    pub async fn toolchain_version() -> Option<String> {
+        let output = xcrun_command().args(["metal", "--version"]).output().await.ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (!version.is_empty()).then_some(version)
+    }
+
     /// Register additional compiler flags (e.g. `-std=metal4.0`, `-DFOO=1`).
     #[allow(dead_code)]
     pub fn add_flags(
@@ -578,6 +794,190 @@ impl MetalCompiler {
         }
     }
 
+    /// Replace the per-path flag and include path overrides.
+    pub fn set_overrides(
+        &self,
+        overrides: Vec<CompilerOverride>,
+    ) {
+        if let Ok(mut guard) = self.overrides.write() {
+            *guard = overrides;
+        }
+    }
+
+    /// Map include prefixes to directories, as `compiler.virtualIncludeRoots`.
+    pub fn set_virtual_include_roots(
+        &self,
+        roots: BTreeMap<String, String>,
+    ) {
+        self.virtual_include_roots.set(roots);
+    }
+
+    /// The include path standing in for `compiler.virtualIncludeRoots` in
+    /// compiles of `file`, with relative directories resolved against its
+    /// workspace root. `None` when no prefix is mapped.
+    pub fn virtual_include_dir(
+        &self,
+        file: &Path,
+        workspace_roots: &[PathBuf],
+    ) -> Option<PathBuf> {
+        let workspace_root =
+            workspace_roots.iter().filter(|root| file.starts_with(root)).max_by_key(|root| root.components().count());
+        self.virtual_include_roots
+            .get_or_link(&self.temp_dir().join("virtual-includes"), workspace_root.map(PathBuf::as_path))
+    }
+
+    /// Compile files whose flags pass no `-std=` against `version`; `None`
+    /// leaves the compiler default.
+    pub fn set_language_version(
+        &self,
+        version: Option<LanguageVersion>,
+    ) {
+        if let Ok(mut guard) = self.language_version.write() {
+            *guard = version;
+        }
+    }
+
+    /// The language version the document at `uri` is compiled against, when
+    /// a flag or `compiler.languageVersion` selects one.
+    pub fn language_version(
+        &self,
+        uri: &Url,
+    ) -> Option<LanguageVersion> {
+        LanguageVersion::from_flags(&self.resolve_effective_flags(uri.as_str()).1)
+    }
+
+    /// Replace the settings of the workspace roots.
+    pub fn set_root_settings(
+        &self,
+        settings: Vec<RootCompilerSettings>,
+    ) {
+        if let Ok(mut guard) = self.root_settings.write() {
+            *guard = settings;
+        }
+    }
+
+    /// A snapshot of the settings of the workspace roots.
+    pub fn root_settings(&self) -> Vec<RootCompilerSettings> {
+        self.root_settings.read().map(|guard| guard.clone()).unwrap_or_default()
+    }
+
+    /// The settings of the innermost workspace root containing the document
+    /// at `uri`.
+    fn root_settings_for(
+        &self,
+        uri: &str,
+    ) -> Option<RootCompilerSettings> {
+        let path = Url::parse(uri).ok().and_then(|url| url.to_file_path().ok())?;
+        let guard = self.root_settings.read().ok()?;
+        guard
+            .iter()
+            .filter(|settings| path.starts_with(&settings.root))
+            .max_by_key(|settings| settings.root.components().count())
+            .cloned()
+    }
+
+    /// The overrides that apply to the document at `uri`, in order.
+    fn matching_overrides(
+        &self,
+        uri: &str,
+    ) -> Vec<CompilerOverride> {
+        let Some(path) = Url::parse(uri).ok().and_then(|url| url.to_file_path().ok()) else {
+            return Vec::new();
+        };
+        let Ok(guard) = self.overrides.read() else {
+            return Vec::new();
+        };
+        compiler_overrides::matching(&guard, &path).cloned().collect()
+    }
+
+    /// Move compiler temp files under `base`, or back to the system temp dir.
+    ///
+    /// The previous per-process directory is removed once the compiles still
+    /// writing into it finish, and directories left under the new base by
+    /// servers no longer running are removed now.
+    pub fn set_temp_dir(
+        &self,
+        base: Option<PathBuf>,
+    ) {
+        let base = base.unwrap_or_else(std::env::temp_dir);
+        let next = process_temp_dir(&base);
+        let (Ok(mut guard), Ok(mut retired)) = (self.temp_dir.write(), self.retired_temp_dirs.lock()) else {
+            return;
+        };
+        if guard.path == next {
+            return;
+        }
+        guard.retired.store(true, Ordering::Relaxed);
+        retired.push(Arc::downgrade(&guard));
+        retired.retain(|dir| dir.strong_count() > 0);
+        // Switching back to a directory compiles are still using keeps it.
+        let revived = retired.iter().filter_map(Weak::upgrade).find(|dir| dir.path == next);
+        *guard = match revived {
+            Some(dir) => {
+                dir.retired.store(false, Ordering::Relaxed);
+                dir
+            },
+            None => Arc::new(TempDir::new(next)),
+        };
+        collect_stale_temp_dirs(&base);
+    }
+
+    /// Configure whether diagnostics compiles load a precompiled `<metal_stdlib>`.
+    pub fn set_precompiled_headers(
+        &self,
+        enabled: bool,
+    ) {
+        self.use_precompiled_headers.store(enabled, Ordering::Relaxed);
+    }
+
+    /// The precompiled `<metal_stdlib>` a compile of `source` can load with
+    /// the current flags, built on first use.
+    async fn precompiled_prelude(
+        &self,
+        source: &str,
+        uri: &str,
+    ) -> Option<PathBuf> {
+        if !self.use_precompiled_headers.load(Ordering::Relaxed) || !includes_prelude_first(source) {
+            return None;
+        }
+        let (_, flags) = self.resolve_effective_flags(uri);
+        let signature = self.toolchain_signature.read().ok().and_then(|guard| guard.clone());
+        self.precompiled_headers.get_or_build(&self.temp_dir().join("pch"), &flags, signature.as_deref()).await
+    }
+
+    /// Return the per-process directory holding compiler temp files.
+    pub fn temp_dir(&self) -> PathBuf {
+        self.lease_temp_dir().path.clone()
+    }
+
+    /// The per-process temp directory, kept on disk while the lease is held.
+    fn lease_temp_dir(&self) -> Arc<TempDir> {
+        self.temp_dir
+            .read()
+            .map(|guard| Arc::clone(&guard))
+            .unwrap_or_else(|_| Arc::new(TempDir::new(process_temp_dir(&std::env::temp_dir()))))
+    }
+
+    /// Directory where kept artifacts of failing compiles are stored.
+    pub fn artifacts_dir(&self) -> PathBuf {
+        let temp_dir = self.temp_dir();
+        temp_dir.parent().unwrap_or(&temp_dir).join(ARTIFACTS_DIR_NAME)
+    }
+
+    /// Configure whether failing compiles keep their translation unit on disk.
+    pub fn set_artifact_retention(
+        &self,
+        retention: ArtifactRetention,
+    ) {
+        if let Ok(mut guard) = self.artifact_retention.write() {
+            *guard = retention;
+        }
+    }
+
+    fn artifact_retention(&self) -> ArtifactRetention {
+        self.artifact_retention.read().map(|guard| *guard).unwrap_or_default()
+    }
+
     /// Register workspace root folders as include search paths.
     ///
     /// For each root we add:
@@ -632,91 +1032,326 @@ impl MetalCompiler {
         uri: &str,
         include_paths: &[String],
     ) -> Vec<MetalDiagnostic> {
+        // Kept artifacts are the temp file itself, so only compiles that
+        // keep nothing can read the source from stdin.
+        if !self.artifact_retention().keep && self.stdin_input.load(Ordering::Relaxed) {
+            match self.compile_from_stdin(source, uri, include_paths).await {
+                Ok(diagnostics) => return diagnostics,
+                Err(StdinFallback::Unsupported) => {
+                    warn!("Metal compiler does not read source from stdin; compiling temp files instead");
+                    self.stdin_input.store(false, Ordering::Relaxed);
+                },
+                Err(StdinFallback::ThisCompile) => {},
+            }
+        }
+
         // Always place temp artifacts under the process temp directory.
         // This avoids creating sibling `.lsp-*` files next to user sources.
         let compilation_id = NEXT_COMPILATION_ID.fetch_add(1, Ordering::Relaxed);
+        let lease = self.lease_temp_dir();
+        let temp_dir = &lease.path;
 
-        if let Err(e) = tokio::fs::create_dir_all(&self.temp_dir).await {
-            error!("Failed to create compiler temp dir {:?}: {}", self.temp_dir, e);
+        if let Err(e) = tokio::fs::create_dir_all(&temp_dir).await {
+            error!("Failed to create compiler temp dir {:?}: {}", temp_dir, e);
             return vec![MetalDiagnostic {
-                file: uri.strip_prefix("file://").map(|s| s.replace("%20", " ")),
+                file: original_path(uri),
                 line: 0,
                 column: 0,
+                end: None,
                 severity: DiagnosticSeverity::ERROR,
                 message: format!("Failed to create temporary directory: {e}"),
+                related_information: Vec::new(),
+                fix_its: Vec::new(),
             }];
         }
-        let temp_file = self.temp_dir.join(format!("shader-{compilation_id}.metal"));
-        let air_file = self.temp_dir.join(format!("shader-{compilation_id}.air"));
+        let temp_file = temp_dir.join(format!("shader-{compilation_id}.metal"));
+        let air_file = temp_dir.join(format!("shader-{compilation_id}.air"));
 
         if let Err(e) = tokio::fs::write(&temp_file, source).await {
             error!("Failed to write temporary shader file: {}", e);
             return vec![MetalDiagnostic {
-                file: uri.strip_prefix("file://").map(|s| s.replace("%20", " ")),
+                file: original_path(uri),
                 line: 0,
                 column: 0,
+                end: None,
                 severity: DiagnosticSeverity::ERROR,
                 message: format!("Failed to write temporary file: {e}"),
+                related_information: Vec::new(),
+                fix_its: Vec::new(),
             }];
         }
 
-        let mut args = vec![
-            "metal".to_string(),
-            "-c".to_string(),
-            temp_file.display().to_string(),
-            "-o".to_string(),
-            air_file.display().to_string(),
-            "-fno-color-diagnostics".to_string(),
-            "-Wno-unneeded-internal-declaration".to_string(),
-        ];
-
-        let merged_include_paths = self.collect_include_paths(uri, include_paths);
-        for p in &merged_include_paths {
-            if let Some(framework_root) = p.strip_prefix(FRAMEWORK_DIR_PREFIX) {
-                args.push("-F".to_string());
-                args.push(framework_root.to_string());
-            } else {
-                args.push("-I".to_string());
-                args.push(p.clone());
-            }
-        }
-
-        // ── Effective flags ──────────────────────────────────────────────
-        let (platform, effective_flags) = self.resolve_effective_flags();
-        debug!("Resolved compiler flags (platform={}): {:?}", platform.as_setting_value(), effective_flags);
-        args.extend(effective_flags);
+        let input = [temp_file.display().to_string()];
+        let args =
+            self.diagnostics_compile_args(source, uri, include_paths, &input, &air_file.display().to_string()).await;
 
         debug!("Running: xcrun {}", args.join(" "));
 
         let mut command = xcrun_command();
-        let result = command.args(&args).output().await;
+        let result = {
+            let _slot = COMPILER_PROCESSES.acquire().await.expect("compiler process semaphore is never closed");
+            command.args(&args).output().await
+        };
 
-        let _ = tokio::fs::remove_file(&temp_file).await;
         let _ = tokio::fs::remove_file(&air_file).await;
 
         match result {
             Ok(output) => {
                 let stderr = String::from_utf8_lossy(&output.stderr);
                 debug!("Metal compiler stderr:\n{}", stderr);
-                let original_path = uri.strip_prefix("file://").map(|s| s.replace("%20", " "));
-                self.parse_diagnostics(&stderr)
-                    .into_iter()
-                    .map(|diag| remap_diagnostic_file(diag, original_path.as_deref(), &temp_file))
-                    .collect()
+                let original_path = original_path(uri);
+                let mut diagnostics =
+                    self.diagnostics_from_stderr(&stderr, source, original_path.as_deref(), &temp_file);
+
+                let retention = self.artifact_retention();
+                let failed = diagnostics.iter().any(|diag| diag.severity == DiagnosticSeverity::ERROR);
+                let kept = if retention.keep && failed {
+                    self.keep_artifact(&temp_file, original_path.as_deref(), &args, retention.max_bytes).await
+                } else {
+                    None
+                };
+                match kept {
+                    Some(artifact) => attach_artifact(&mut diagnostics, &artifact, original_path.as_deref()),
+                    None => {
+                        let _ = tokio::fs::remove_file(&temp_file).await;
+                    },
+                }
+                diagnostics
             },
             Err(e) => {
+                let _ = tokio::fs::remove_file(&temp_file).await;
                 error!("Failed to run Metal compiler: {}", e);
                 vec![MetalDiagnostic {
-                    file: uri.strip_prefix("file://").map(|s| s.replace("%20", " ")),
+                    file: original_path(uri),
                     line: 0,
                     column: 0,
+                    end: None,
                     severity: DiagnosticSeverity::ERROR,
                     message: format!("Failed to run Metal compiler: {e}"),
+                    related_information: Vec::new(),
+                    fix_its: Vec::new(),
                 }]
             },
         }
     }
 
+    /// Compile `source` fed through stdin, leaving nothing on disk.
+    ///
+    /// Fails when the source could not be compiled from stdin, so the caller
+    /// falls back to a temp file.
+    async fn compile_from_stdin(
+        &self,
+        source: &str,
+        uri: &str,
+        include_paths: &[String],
+    ) -> Result<Vec<MetalDiagnostic>, StdinFallback> {
+        let input = ["-x".to_string(), "metal".to_string(), "-".to_string()];
+        let args = self.diagnostics_compile_args(source, uri, include_paths, &input, "/dev/null").await;
+        debug!("Running: xcrun {} < {}", args.join(" "), uri);
+
+        let original_path = original_path(uri);
+        let mut command = xcrun_command();
+        command
+            .args(&args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+        // Relative paths in the output then resolve like those of a compiled file.
+        if let Some(parent) = original_path.as_deref().and_then(|path| Path::new(path).parent())
+            && parent.is_dir()
+        {
+            command.current_dir(parent);
+        }
+
+        let (written, output) = {
+            let _slot = COMPILER_PROCESSES.acquire().await.expect("compiler process semaphore is never closed");
+            let mut child = command.spawn().map_err(|e| {
+                warn!("Failed to run Metal compiler on stdin, compiling a temp file instead: {e}");
+                StdinFallback::ThisCompile
+            })?;
+            let stdin = child.stdin.take();
+            let write = async move {
+                use tokio::io::AsyncWriteExt;
+                let mut stdin = stdin.ok_or_else(|| std::io::Error::other("stdin was not piped"))?;
+                stdin.write_all(source.as_bytes()).await
+            };
+            tokio::join!(write, child.wait_with_output())
+        };
+
+        let output = output.map_err(|e| {
+            warn!("Failed to run Metal compiler on stdin, compiling a temp file instead: {e}");
+            StdinFallback::ThisCompile
+        })?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        debug!("Metal compiler stderr:\n{}", stderr);
+        if rejects_stdin_input(&stderr) {
+            return Err(StdinFallback::Unsupported);
+        }
+        // A failed write may have cut the source short, so the output may not
+        // describe the document.
+        if let Err(e) = written {
+            warn!("Failed to feed source to the Metal compiler, compiling a temp file instead: {e}");
+            return Err(StdinFallback::ThisCompile);
+        }
+        Ok(self.diagnostics_from_stderr(&stderr, source, original_path.as_deref(), Path::new(STDIN_FILE_NAME)))
+    }
+
+    /// Arguments of a diagnostics compile reading `input` and writing the
+    /// object to `output`.
+    async fn diagnostics_compile_args(
+        &self,
+        source: &str,
+        uri: &str,
+        include_paths: &[String],
+        input: &[String],
+        output: &str,
+    ) -> Vec<String> {
+        let mut args = vec!["metal".to_string(), "-c".to_string()];
+        args.extend_from_slice(input);
+        args.extend([
+            "-o".to_string(),
+            output.to_string(),
+            "-fno-color-diagnostics".to_string(),
+            "-fdiagnostics-parseable-fixits".to_string(),
+            "-fdiagnostics-print-source-range-info".to_string(),
+            "-Wno-unneeded-internal-declaration".to_string(),
+        ]);
+        args.extend(self.search_path_and_flag_args(uri, include_paths));
+        if let Some(pch) = self.precompiled_prelude(source, uri).await {
+            args.push("-include-pch".to_string());
+            args.push(pch.display().to_string());
+        }
+        args
+    }
+
+    /// Run only the preprocessor over `source`, with the same search paths
+    /// and flags as [`compile_with_include_paths`](Self::compile_with_include_paths).
+    ///
+    /// Line markers name the original file rather than the temporary copy.
+    /// Returns the compiler's error output when preprocessing fails.
+    pub async fn preprocess(
+        &self,
+        source: &str,
+        uri: &str,
+        include_paths: &[String],
+    ) -> Result<String, String> {
+        let compilation_id = NEXT_COMPILATION_ID.fetch_add(1, Ordering::Relaxed);
+        let lease = self.lease_temp_dir();
+        let temp_dir = &lease.path;
+        tokio::fs::create_dir_all(temp_dir).await.map_err(|e| format!("Failed to create temporary directory: {e}"))?;
+        let temp_file = temp_dir.join(format!("preprocess-{compilation_id}.metal"));
+        tokio::fs::write(&temp_file, source).await.map_err(|e| format!("Failed to write temporary file: {e}"))?;
+
+        let mut args = vec![
+            "metal".to_string(),
+            "-E".to_string(),
+            temp_file.display().to_string(),
+            "-fno-color-diagnostics".to_string(),
+        ];
+        args.extend(self.search_path_and_flag_args(uri, include_paths));
+        debug!("Running: xcrun {}", args.join(" "));
+
+        let result = xcrun_command().args(&args).output().await;
+        let _ = tokio::fs::remove_file(&temp_file).await;
+        let output = result.map_err(|e| format!("Failed to run Metal compiler: {e}"))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).into_owned());
+        }
+
+        let preprocessed = String::from_utf8_lossy(&output.stdout);
+        let original_path = original_path(uri);
+        Ok(match original_path {
+            Some(original) => preprocessed.replace(&format!("\"{}\"", temp_file.display()), &format!("\"{original}\"")),
+            None => preprocessed.into_owned(),
+        })
+    }
+
+    /// Compile `source` to an `.air` object and link it into a `.metallib`
+    /// with `xcrun metallib`, both in the temp directory and named after the
+    /// document, so a later build of the same document replaces them.
+    ///
+    /// Compile errors are returned as diagnostics without outputs. Returns an
+    /// error when the toolchain cannot be run.
+    pub async fn compile_binary(
+        &self,
+        source: &str,
+        uri: &str,
+        include_paths: &[String],
+    ) -> Result<CompiledBinary, String> {
+        let lease = self.lease_temp_dir();
+        let output_dir = lease.path.join(BINARIES_DIR_NAME);
+        tokio::fs::create_dir_all(&output_dir)
+            .await
+            .map_err(|e| format!("Failed to create temporary directory: {e}"))?;
+        let original_path = original_path(uri);
+        let stem = original_path.as_deref().and_then(|p| Path::new(p).file_stem()).and_then(|s| s.to_str());
+        let air_file = output_dir.join(format!("{}.air", stem.unwrap_or("shader")));
+        let metallib_file = air_file.with_extension("metallib");
+
+        let compiled = self.compile_air(source, uri, include_paths, &air_file, &[]).await?;
+        let mut binary = CompiledBinary {
+            diagnostics: compiled.diagnostics,
+            ..CompiledBinary::default()
+        };
+        if !compiled.success {
+            return Ok(binary);
+        }
+        binary.air = Some(air_file.clone());
+
+        let args = [
+            "metallib".to_string(),
+            air_file.display().to_string(),
+            "-o".to_string(),
+            metallib_file.display().to_string(),
+        ];
+        debug!("Running: xcrun {}", args.join(" "));
+        let output = xcrun_command().args(&args).output().await.map_err(|e| format!("Failed to run metallib: {e}"))?;
+        if output.status.success() {
+            binary.metallib = Some(metallib_file);
+        } else {
+            binary.diagnostics.push(MetalDiagnostic {
+                file: original_path,
+                line: 0,
+                column: 0,
+                end: None,
+                severity: DiagnosticSeverity::ERROR,
+                message: format!("metallib failed: {}", String::from_utf8_lossy(&output.stderr).trim()),
+                related_information: Vec::new(),
+                fix_its: Vec::new(),
+            });
+        }
+        Ok(binary)
+    }
+
+    /// Statistics of each entry point in `source`, read from the disassembly
+    /// of its `.air` object (see [`crate::metal::kernel_stats`]).
+    ///
+    /// Returns an error when the toolchain cannot be run or `source` does not
+    /// compile.
+    pub async fn kernel_stats(
+        &self,
+        source: &str,
+        uri: &str,
+        include_paths: &[String],
+    ) -> Result<Vec<KernelStats>, String> {
+        let ir = self.disassemble_source(source, uri, include_paths, &[]).await?;
+        Ok(parse_air_module(&ir))
+    }
+
+    /// The textual AIR of `source`, compiled with line tables so
+    /// [`toolchain::ir_functions`] can map it back to the document.
+    ///
+    /// Returns an error when the toolchain cannot be run or `source` does not
+    /// compile.
+    pub async fn disassemble(
+        &self,
+        source: &str,
+        uri: &str,
+        include_paths: &[String],
+    ) -> Result<String, String> {
+        self.disassemble_source(source, uri, include_paths, &[toolchain::LINE_TABLES_FLAG]).await
+    }
+
     /// Check whether the Metal compiler toolchain is available on this system.
     pub async fn is_available() -> bool {
         Self::is_toolchain_available().await
@@ -724,36 +1359,229 @@ impl MetalCompiler {
 
     // ── Private helpers ──────────────────────────────────────────────────
 
+    /// Compile `source` to the `.air` object `air_file`, returning whether
+    /// compiling succeeded and its diagnostics. A failed compile leaves no
+    /// object behind.
+    async fn compile_air(
+        &self,
+        source: &str,
+        uri: &str,
+        include_paths: &[String],
+        air_file: &Path,
+        extra_args: &[&str],
+    ) -> Result<AirCompile, String> {
+        let compilation_id = NEXT_COMPILATION_ID.fetch_add(1, Ordering::Relaxed);
+        let lease = self.lease_temp_dir();
+        let temp_dir = &lease.path;
+        tokio::fs::create_dir_all(temp_dir).await.map_err(|e| format!("Failed to create temporary directory: {e}"))?;
+        let temp_file = temp_dir.join(format!("binary-{compilation_id}.metal"));
+        tokio::fs::write(&temp_file, source).await.map_err(|e| format!("Failed to write temporary file: {e}"))?;
+
+        let mut args = vec![
+            "metal".to_string(),
+            "-c".to_string(),
+            temp_file.display().to_string(),
+            "-o".to_string(),
+            air_file.display().to_string(),
+            "-fno-color-diagnostics".to_string(),
+            "-fdiagnostics-print-source-range-info".to_string(),
+        ];
+        args.extend(self.search_path_and_flag_args(uri, include_paths));
+        args.extend(extra_args.iter().map(|arg| arg.to_string()));
+        debug!("Running: xcrun {}", args.join(" "));
+        let result = {
+            let _slot = COMPILER_PROCESSES.acquire().await.expect("compiler process semaphore is never closed");
+            xcrun_command().args(&args).output().await
+        };
+        let output = result.map_err(|e| {
+            let _ = std::fs::remove_file(&temp_file);
+            format!("Failed to run Metal compiler: {e}")
+        })?;
+        let original_path = original_path(uri);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let diagnostics = self.diagnostics_from_stderr(&stderr, source, original_path.as_deref(), &temp_file);
+        let _ = tokio::fs::remove_file(&temp_file).await;
+        if !output.status.success() {
+            let _ = tokio::fs::remove_file(air_file).await;
+        }
+        Ok(AirCompile {
+            success: output.status.success(),
+            diagnostics,
+            source_file: temp_file,
+        })
+    }
+
+    /// Compile `source` to a temporary `.air` object and disassemble it, with
+    /// the temporary source path replaced by the document's.
+    async fn disassemble_source(
+        &self,
+        source: &str,
+        uri: &str,
+        include_paths: &[String],
+        extra_args: &[&str],
+    ) -> Result<String, String> {
+        let compilation_id = NEXT_COMPILATION_ID.fetch_add(1, Ordering::Relaxed);
+        let lease = self.lease_temp_dir();
+        let air_file = lease.path.join(format!("disassembly-{compilation_id}.air"));
+        let compiled = self.compile_air(source, uri, include_paths, &air_file, extra_args).await?;
+        if !compiled.success {
+            let error = compiled.diagnostics.iter().find(|d| d.severity == DiagnosticSeverity::ERROR);
+            return Err(error.map_or_else(|| "Compilation failed".to_string(), |d| d.message.clone()));
+        }
+        let result = toolchain::disassemble_air(&air_file).await;
+        let _ = tokio::fs::remove_file(&air_file).await;
+        let ir = result?;
+        let original_path = original_path(uri);
+        Ok(match original_path {
+            Some(original) => ir.replace(&compiled.source_file.display().to_string(), &original),
+            None => ir,
+        })
+    }
+
+    /// Move a failing compile's temp TU into the artifacts folder, next to a
+    /// `.cmd` file holding the exact command line, then prune old artifacts.
+    async fn keep_artifact(
+        &self,
+        temp_file: &Path,
+        original_path: Option<&str>,
+        args: &[String],
+        max_bytes: u64,
+    ) -> Option<PathBuf> {
+        let artifacts_dir = self.artifacts_dir();
+        if let Err(e) = tokio::fs::create_dir_all(&artifacts_dir).await {
+            warn!("Failed to create artifacts directory {:?}: {}", artifacts_dir, e);
+            return None;
+        }
+
+        let stem = original_path.and_then(|p| Path::new(p).file_stem()).and_then(|s| s.to_str()).unwrap_or("shader");
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let name = format!("{stem}-{timestamp}-{}", std::process::id());
+        let artifact = artifacts_dir.join(format!("{name}.metal"));
+
+        if tokio::fs::rename(temp_file, &artifact).await.is_err() {
+            let copied = tokio::fs::copy(temp_file, &artifact).await;
+            let _ = tokio::fs::remove_file(temp_file).await;
+            if let Err(e) = copied {
+                warn!("Failed to keep compile artifact {:?}: {}", artifact, e);
+                return None;
+            }
+        }
+        let command_line = format!(
+            "# source: {}\nxcrun {}\n",
+            original_path.unwrap_or("<unknown>"),
+            args.join(" ").replace(&temp_file.display().to_string(), &artifact.display().to_string())
+        );
+        let _ = tokio::fs::write(artifacts_dir.join(format!("{name}.cmd")), command_line).await;
+        debug!("Kept compile artifact {:?}", artifact);
+
+        let keep = artifact.clone();
+        let _ = tokio::task::spawn_blocking(move || prune_artifacts(&artifacts_dir, max_bytes, &keep)).await;
+        Some(artifact)
+    }
+
+    /// Diagnostics in the compiler's `stderr` for a compile of `source` as
+    /// `temp_file`, reported against the document at `original_path` instead.
+    fn diagnostics_from_stderr(
+        &self,
+        stderr: &str,
+        source: &str,
+        original_path: Option<&str>,
+        temp_file: &Path,
+    ) -> Vec<MetalDiagnostic> {
+        let main = MainSource::new(temp_file, source);
+        let parsed: Vec<ParsedDiagnostic> = self
+            .parse_diagnostics(stderr, Some(&main))
+            .into_iter()
+            .map(|mut parsed| {
+                parsed.diagnostic = remap_diagnostic_file(parsed.diagnostic, original_path, temp_file);
+                for frame in &mut parsed.include_stack {
+                    frame.file = remap_compiled_path(&frame.file, original_path, temp_file);
+                }
+                parsed
+            })
+            .collect();
+        attribute_to_include_sites(parsed, |path| self.is_system_path(path))
+    }
+
     /// Parse the compiler's stderr output into a list of diagnostics.
+    ///
+    /// Clang prints the `In file included from` stack only when it differs
+    /// from the previous diagnostic's, so a diagnostic without its own stack
+    /// inherits the previous one when both are in the same file. `fix-it:`
+    /// lines belong to the diagnostic printed before them.
     fn parse_diagnostics(
         &self,
         output: &str,
-    ) -> Vec<MetalDiagnostic> {
-        let mut diagnostics = Vec::new();
+        main: Option<&MainSource>,
+    ) -> Vec<ParsedDiagnostic> {
+        let mut diagnostics: Vec<ParsedDiagnostic> = Vec::new();
+        let mut pending_stack = Vec::new();
 
         for line in output.lines() {
-            if let Some(diag) = self.parse_diagnostic_line(line) {
-                diagnostics.push(diag);
+            if let Some(frame) = parse_include_frame(line) {
+                pending_stack.push(frame);
+                continue;
             }
+            if let Some(fix_it) = parse_fix_it_line(line) {
+                if let Some(previous) = diagnostics.last_mut() {
+                    previous.diagnostic.fix_its.push(fix_it);
+                }
+                continue;
+            }
+            let Some(diagnostic) = self.parse_diagnostic_line(line, main) else {
+                continue;
+            };
+            let include_stack = if !pending_stack.is_empty() {
+                std::mem::take(&mut pending_stack)
+            } else {
+                diagnostics
+                    .last()
+                    .filter(|previous| previous.diagnostic.file == diagnostic.file)
+                    .map(|previous| previous.include_stack.clone())
+                    .unwrap_or_default()
+            };
+            diagnostics.push(ParsedDiagnostic {
+                diagnostic,
+                include_stack,
+            });
         }
 
         diagnostics
     }
 
+    /// Whether `path` belongs to the SDK or toolchain rather than user code.
+    pub(crate) fn is_system_path(
+        &self,
+        path: &str,
+    ) -> bool {
+        is_system_header(path) || self.get_system_include_paths().iter().any(|root| Path::new(path).starts_with(root))
+    }
+
     /// Attempt to parse a single line of compiler output.
     ///
-    /// Expected format: `filename:line:column: severity: message`
+    /// Expected format: `filename:line:column: severity: message`, with the
+    /// `{line:column-line:column}` source ranges of
+    /// `-fdiagnostics-print-source-range-info` before the severity. The
+    /// diagnostic spans the ranges on its line, or else the token at its
+    /// column. Lines of `main` are read from its source, as the compiled
+    /// file may be `<stdin>` or gone from disk; other files are read from
+    /// disk.
     fn parse_diagnostic_line(
         &self,
         line: &str,
+        main: Option<&MainSource>,
     ) -> Option<MetalDiagnostic> {
         let caps = self.diagnostic_re.captures(line)?;
 
         let file = caps.get(1).map(|m| m.as_str().to_owned());
         let line_num: u32 = caps.get(2)?.as_str().parse().ok()?;
         let column: u32 = caps.get(3)?.as_str().parse().ok()?;
-        let severity_str = caps.get(4)?.as_str();
-        let message = caps.get(5)?.as_str().to_string();
+        let ranges = caps.get(4).map_or("", |m| m.as_str());
+        let severity_str = caps.get(5)?.as_str();
+        let message = caps.get(6)?.as_str().to_string();
 
         let severity = match severity_str {
             "error" => DiagnosticSeverity::ERROR,
@@ -762,13 +1590,41 @@ impl MetalCompiler {
             _ => DiagnosticSeverity::HINT,
         };
 
-        // Convert from 1-based (compiler) to 0-based (LSP).
+        // Convert from 1-based (compiler) to 0-based (LSP), and from bytes
+        // to the negotiated encoding.
+        let caret = (line_num.saturating_sub(1), column.saturating_sub(1));
+        let (start, mut end) = source_ranges(ranges)
+            .filter(|(start, end)| start.0 <= caret.0 && caret.0 <= end.0)
+            .fold((caret, caret), |(start, end), range| (start.min(range.0), end.max(range.1)));
+        let main = main.filter(|main| file.as_deref().is_some_and(|file| Path::new(file) == main.file));
+        if end == caret
+            && let Some(text) = match main {
+                Some(main) => main.line(caret.0).map(str::to_owned),
+                None => file.as_deref().and_then(|file| line_in_file(file, caret.0)),
+            }
+            && let Some(token_end) = token_end(&text, caret.1 as usize)
+        {
+            end = (caret.0, token_end as u32);
+        }
+        let encoded = |(line, column): (u32, u32)| {
+            let column = match (main, file.as_deref()) {
+                (Some(main), _) => main.column(line, column),
+                (None, Some(file)) => column_in_file(file, line, column),
+                (None, None) => column,
+            };
+            Position::new(line, column)
+        };
+        let start = encoded(start);
+        let end = encoded(end);
         Some(MetalDiagnostic {
             file,
-            line: line_num.saturating_sub(1),
-            column: column.saturating_sub(1),
+            line: start.line,
+            column: start.character,
+            end: (end != start).then_some(end),
             severity,
             message,
+            related_information: Vec::new(),
+            fix_its: Vec::new(),
         })
     }
 
@@ -815,7 +1671,7 @@ impl MetalCompiler {
 
     fn collect_include_paths(
         &self,
-        _uri: &str,
+        uri: &str,
         include_paths: &[String],
     ) -> Vec<String> {
         let mut merged = BTreeSet::new();
@@ -826,20 +1682,120 @@ impl MetalCompiler {
         for p in include_paths {
             merged.insert(p.clone());
         }
-        if let Ok(guard) = self.extra_include_paths.read() {
-            for p in guard.iter() {
-                merged.insert(p.display().to_string());
-            }
+        for p in self.include_paths_for(uri) {
+            merged.insert(p.display().to_string());
+        }
+        for entry in self.matching_overrides(uri) {
+            merged.extend(entry.include_paths);
         }
 
         merged.into_iter().collect()
     }
 
-    fn resolve_effective_flags(&self) -> (CompilerPlatform, Vec<String>) {
-        let user_flags = self.extra_flags.read().map(|guard| guard.clone()).unwrap_or_default();
-        let platform = self.platform.read().map(|guard| *guard).unwrap_or_default();
+    /// `-I`/`-F` search paths followed by the effective flags, shared by
+    /// every compiler invocation for a document.
+    fn search_path_and_flag_args(
+        &self,
+        uri: &str,
+        include_paths: &[String],
+    ) -> Vec<String> {
+        let mut args = Vec::new();
+        for p in &self.collect_include_paths(uri, include_paths) {
+            if let Some(framework_root) = p.strip_prefix(FRAMEWORK_DIR_PREFIX) {
+                args.push("-F".to_string());
+                args.push(framework_root.to_string());
+            } else {
+                args.push("-I".to_string());
+                args.push(p.clone());
+            }
+        }
+
+        let (platform, effective_flags) = self.resolve_effective_flags(uri);
+        debug!("Resolved compiler flags (platform={}): {:?}", platform.as_setting_value(), effective_flags);
+        args.extend(effective_flags);
+        args
+    }
+
+    /// Macros defined by the effective `-D`/`-U` flags for the document at
+    /// `uri`, including the injected platform define and, when the language
+    /// version is known, `__METAL_VERSION__`, mapped to their values.
+    pub fn effective_defines(
+        &self,
+        uri: &Url,
+    ) -> HashMap<String, String> {
+        let flags = self.resolve_effective_flags(uri.as_str()).1;
+        let mut defines = Self::defines_from_flags(&flags);
+        if let Some(version) = LanguageVersion::from_flags(&flags) {
+            defines.entry("__METAL_VERSION__".to_string()).or_insert_with(|| version.metal_version_macro());
+        }
+        defines
+    }
+
+    /// Apply `-DNAME`, `-DNAME=VALUE`, `-D NAME` and `-UNAME` flags in order.
+    /// A define without a value is `1`, as for the compiler.
+    fn defines_from_flags(flags: &[String]) -> HashMap<String, String> {
+        let mut defines = HashMap::new();
+        let mut iter = flags.iter().map(|flag| flag.trim());
+        while let Some(flag) = iter.next() {
+            let (undefine, body) = match flag {
+                "-D" => (false, iter.next()),
+                "-U" => (true, iter.next()),
+                _ => match (flag.strip_prefix("-D"), flag.strip_prefix("-U")) {
+                    (Some(body), _) => (false, Some(body)),
+                    (_, Some(body)) => (true, Some(body)),
+                    _ => continue,
+                },
+            };
+            let Some(body) = body else {
+                break;
+            };
+            let (name, value) = body.split_once('=').unwrap_or((body, "1"));
+            let name = name.trim();
+            if name.is_empty() {
+                continue;
+            }
+            if undefine {
+                defines.remove(name);
+            } else {
+                defines.insert(name.to_string(), value.to_string());
+            }
+        }
+        defines
+    }
+
+    /// The global flags, or those of the workspace root of `uri`, followed by
+    /// those of the overrides matching `uri`.
+    fn resolve_effective_flags(
+        &self,
+        uri: &str,
+    ) -> (CompilerPlatform, Vec<String>) {
+        let (mut user_flags, platform, language_version) = match self.root_settings_for(uri) {
+            Some(root) => (root.flags, root.platform, root.language_version),
+            None => (
+                self.extra_flags.read().map(|guard| guard.clone()).unwrap_or_default(),
+                self.platform.read().map(|guard| *guard).unwrap_or_default(),
+                self.language_version.read().ok().and_then(|guard| *guard),
+            ),
+        };
+        for entry in self.matching_overrides(uri) {
+            user_flags.extend(entry.flags);
+        }
+        (platform, Self::settings_flags(user_flags, platform, language_version))
+    }
 
-        (platform, Self::build_effective_flags(&user_flags, platform))
+    /// `user_flags` with the `-std` flag of `language_version` and the
+    /// platform define added unless the flags already pick them.
+    pub fn settings_flags(
+        mut user_flags: Vec<String>,
+        platform: CompilerPlatform,
+        language_version: Option<LanguageVersion>,
+    ) -> Vec<String> {
+        if LanguageVersion::from_flags(&user_flags).is_none()
+            && let Some(version) = language_version
+        {
+            user_flags.push(version.std_flag(platform));
+        }
+        Self::build_effective_flags(&user_flags, platform)
     }
 
     fn build_effective_flags(
@@ -905,37 +1861,338 @@ impl MetalCompiler {
     }
 }
 
+/// 0-based `(line, byte column)` bounds of each `{line:column-line:column}`
+/// range clang prints, the end past the range's last character.
+fn source_ranges(text: &str) -> impl Iterator<Item = ((u32, u32), (u32, u32))> + '_ {
+    let location = |text: &str| {
+        let (line, column) = text.split_once(':')?;
+        Some((line.parse::<u32>().ok()?.saturating_sub(1), column.parse::<u32>().ok()?.saturating_sub(1)))
+    };
+    text.split(['{', '}']).filter_map(move |range| {
+        let (start, end) = range.split_once('-')?;
+        Some((location(start)?, location(end)?))
+    })
+}
+
+/// Byte offset past the token at `start` in `line`: an identifier or
+/// number, a string or character literal, or else a single character.
+fn token_end(
+    line: &str,
+    start: usize,
+) -> Option<usize> {
+    let rest = line.get(start..)?;
+    let first = rest.chars().next()?;
+    let len = if first.is_alphanumeric() || first == '_' {
+        let number = first.is_ascii_digit();
+        rest.find(|ch: char| !(ch.is_alphanumeric() || ch == '_' || (number && ch == '.'))).unwrap_or(rest.len())
+    } else if first == '"' || first == '\'' {
+        let mut escaped = false;
+        rest.char_indices()
+            .skip(1)
+            .find(|&(_, ch)| {
+                let closes = !escaped && ch == first;
+                escaped = !escaped && ch == '\\';
+                closes
+            })
+            .map_or(rest.len(), |(offset, ch)| offset + ch.len_utf8())
+    } else {
+        first.len_utf8()
+    };
+    Some(start + len)
+}
+
+/// The path the document at `uri` is compiled as: its file path, or the
+/// stand-in path of a virtual document.
+fn original_path(uri: &str) -> Option<String> {
+    match uri.strip_prefix("file://") {
+        Some(stripped) => Some(stripped.replace("%20", " ")),
+        None => Url::parse(uri).ok().and_then(|url| document_path(&url)).map(|path| path.display().to_string()),
+    }
+}
+
 fn remap_diagnostic_file(
     mut diagnostic: MetalDiagnostic,
     original_path: Option<&str>,
     temp_file: &Path,
 ) -> MetalDiagnostic {
-    let Some(raw_file) = diagnostic.file.clone() else {
-        return diagnostic;
-    };
-    let diag_path = Path::new(&raw_file);
-    let temp_matches = diag_path == temp_file || diag_path.canonicalize().ok() == temp_file.canonicalize().ok();
+    if let Some(raw_file) = diagnostic.file.take() {
+        diagnostic.file = Some(remap_compiled_path(&raw_file, original_path, temp_file));
+    }
+    for fix_it in &mut diagnostic.fix_its {
+        fix_it.file = remap_compiled_path(&fix_it.file, original_path, temp_file);
+    }
+    diagnostic
+}
+
+/// Map a path printed by the compiler back to the user's file system:
+/// the temp translation unit becomes the original document, relative
+/// paths resolve against its directory.
+fn remap_compiled_path(
+    raw_file: &str,
+    original_path: Option<&str>,
+    temp_file: &Path,
+) -> String {
+    let diag_path = Path::new(raw_file);
+    let temp_matches = diag_path == temp_file
+        || matches!(
+            (diag_path.canonicalize(), temp_file.canonicalize()),
+            (Ok(diag_path), Ok(temp_file)) if diag_path == temp_file
+        );
     if temp_matches {
-        if let Some(original) = original_path {
-            diagnostic.file = Some(original.to_owned());
-        }
+        original_path.unwrap_or(raw_file).to_owned()
     } else if diag_path.is_relative()
         && let Some(original) = original_path
         && let Some(parent) = Path::new(original).parent()
     {
         let resolved = parent.join(diag_path);
-        diagnostic.file = Some(resolved.canonicalize().unwrap_or(resolved).display().to_string());
+        resolved.canonicalize().unwrap_or(resolved).display().to_string()
     } else {
-        diagnostic.file =
-            Some(diag_path.canonicalize().unwrap_or_else(|_| diag_path.to_path_buf()).display().to_string());
+        diag_path.canonicalize().unwrap_or_else(|_| diag_path.to_path_buf()).display().to_string()
+    }
+}
+
+/// One `In file included from <file>:<line>:` line of compiler output.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct IncludeFrame {
+    pub(crate) file: String,
+    /// 0-based line of the `#include` directive.
+    pub(crate) line: u32,
+}
+
+/// A diagnostic together with the include stack printed above it, outermost first.
+#[derive(Debug, Clone)]
+pub(crate) struct ParsedDiagnostic {
+    pub(crate) diagnostic: MetalDiagnostic,
+    pub(crate) include_stack: Vec<IncludeFrame>,
+}
+
+fn parse_include_frame(line: &str) -> Option<IncludeFrame> {
+    let rest = line.strip_prefix("In file included from ")?;
+    let rest = rest.strip_suffix(':').unwrap_or(rest);
+    let (file, line_num) = rest.rsplit_once(':')?;
+    let line_num: u32 = line_num.parse().ok()?;
+    Some(IncludeFrame {
+        file: file.to_string(),
+        line: line_num.saturating_sub(1),
+    })
+}
+
+/// Move errors and warnings reported inside system headers to the innermost
+/// `#include` in user code that pulled the header in.
+///
+/// SDK headers are never edited, so their locations are useless as primary
+/// positions; the original location is kept as related information instead.
+/// Several diagnostics landing on the same `#include` are folded into the
+/// first one to keep a single broken macro from flooding the include line.
+pub(crate) fn attribute_to_include_sites(
+    parsed: Vec<ParsedDiagnostic>,
+    is_system_path: impl Fn(&str) -> bool,
+) -> Vec<MetalDiagnostic> {
+    let mut diagnostics: Vec<MetalDiagnostic> = Vec::new();
+    // Include sites already holding a remapped diagnostic, with its index.
+    let mut remapped_sites: Vec<((String, u32, DiagnosticSeverity), usize)> = Vec::new();
+
+    for ParsedDiagnostic {
+        mut diagnostic,
+        include_stack,
+    } in parsed
+    {
+        let in_system_header = diagnostic.file.as_deref().is_some_and(&is_system_path);
+        let include_site = include_stack.iter().rev().find(|frame| !is_system_path(&frame.file));
+        let (Some(original_file), Some(site), true) = (diagnostic.file.clone(), include_site, in_system_header) else {
+            diagnostics.push(diagnostic);
+            continue;
+        };
+        if diagnostic.severity == DiagnosticSeverity::INFORMATION {
+            diagnostics.push(diagnostic);
+            continue;
+        }
+
+        let original = Url::from_file_path(&original_file).ok().map(|uri| DiagnosticRelatedInformation {
+            location: Location {
+                uri,
+                range: diagnostic.range(),
+            },
+            message: diagnostic.message.clone(),
+        });
+
+        let key = (site.file.clone(), site.line, diagnostic.severity);
+        if let Some((_, index)) = remapped_sites.iter().find(|(site_key, _)| *site_key == key) {
+            diagnostics[*index].related_information.extend(original);
+            continue;
+        }
+
+        diagnostic.message = format!("In included file: {}", diagnostic.message);
+        diagnostic.file = Some(site.file.clone());
+        diagnostic.line = site.line;
+        diagnostic.column = 0;
+        diagnostic.end = None;
+        diagnostic.fix_its.clear();
+        diagnostic.related_information.extend(original);
+        remapped_sites.push((key, diagnostics.len()));
+        diagnostics.push(diagnostic);
+    }
+
+    diagnostics
+}
+
+/// A per-process directory of compiler temp files. Once `set_temp_dir`
+/// retires it, it is removed when the last compile using it lets go.
+struct TempDir {
+    path: PathBuf,
+    retired: AtomicBool,
+}
+
+impl TempDir {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            retired: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if *self.retired.get_mut() {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+fn process_temp_dir(base: &Path) -> PathBuf {
+    base.join(format!("metal-analyzer-{}", std::process::id()))
+}
+
+/// Whether the compiler's `stderr` shows it took `-` for a file name or
+/// refused `-x metal`, rather than compiling the source fed through stdin.
+fn rejects_stdin_input(stderr: &str) -> bool {
+    stderr.lines().any(|line| {
+        line.contains("no such file or directory: '-'")
+            || line.contains("invalid value 'metal' in '-x metal'")
+            || line.contains("error: no input files")
+    })
+}
+
+/// The pid of the server that created `name`, for the per-process temp
+/// directories of diagnostics compiles and AST dumps.
+fn temp_dir_owner(name: &str) -> Option<u32> {
+    let rest = name.strip_prefix("metal-analyzer-")?;
+    let pid = rest.strip_prefix("def-").unwrap_or(rest);
+    if pid.is_empty() || !pid.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    pid.parse().ok()
+}
+
+/// Remove the per-process temp directories under `base` whose server is no
+/// longer running, such as those left behind by a crash.
+fn collect_stale_temp_dirs(base: &Path) {
+    let Ok(entries) = std::fs::read_dir(base) else {
+        return;
+    };
+    let current = std::process::id();
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(pid) = name.to_str().and_then(temp_dir_owner) else {
+            continue;
+        };
+        if pid == current || !entry.file_type().is_ok_and(|kind| kind.is_dir()) || process_is_running(pid) {
+            continue;
+        }
+        debug!("Removing stale compiler temp directory {:?}", entry.path());
+        let _ = std::fs::remove_dir_all(entry.path());
+    }
+}
+
+/// Whether a process with `pid` exists. Unknown counts as running, so a
+/// directory is only removed when its owner is known to be gone.
+fn process_is_running(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        return Path::new("/proc").join(pid.to_string()).exists();
+    }
+    if !cfg!(unix) {
+        return true;
+    }
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .output()
+        .map_or(true, |output| {
+            output.status.success() || !String::from_utf8_lossy(&output.stderr).contains("No such process")
+        })
+}
+
+/// Link every error to the kept translation unit.
+///
+/// Diagnostics in the main file point at the same position in the artifact,
+/// since its text is identical to the compiled source.
+fn attach_artifact(
+    diagnostics: &mut [MetalDiagnostic],
+    artifact: &Path,
+    original_path: Option<&str>,
+) {
+    let Ok(uri) = Url::from_file_path(artifact) else {
+        return;
+    };
+    for diag in diagnostics.iter_mut().filter(|diag| diag.severity == DiagnosticSeverity::ERROR) {
+        let in_main_file = original_path.is_some() && diag.file.as_deref() == original_path;
+        let range = if in_main_file {
+            diag.range()
+        } else {
+            Range::default()
+        };
+        diag.related_information.push(DiagnosticRelatedInformation {
+            location: Location {
+                uri: uri.clone(),
+                range,
+            },
+            message: "Compiled translation unit kept for inspection".to_string(),
+        });
+    }
+}
+
+/// Delete the oldest files in `dir` until its total size fits in `max_bytes`.
+///
+/// `keep` is never deleted, so the artifact just written stays reachable
+/// even when it alone exceeds the budget.
+pub(crate) fn prune_artifacts(
+    dir: &Path,
+    max_bytes: u64,
+    keep: &Path,
+) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<(std::time::SystemTime, PathBuf, u64)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            metadata
+                .is_file()
+                .then(|| (metadata.modified().unwrap_or(std::time::UNIX_EPOCH), entry.path(), metadata.len()))
+        })
+        .collect();
+    let mut total: u64 = files.iter().map(|(_, _, len)| len).sum();
+    files.sort();
+    for (_, path, len) in files {
+        if total <= max_bytes {
+            break;
+        }
+        if path == keep || path.with_extension("metal") == keep {
+            continue;
+        }
+        if std::fs::remove_file(&path).is_ok() {
+            total = total.saturating_sub(len);
+        }
     }
-    diagnostic
 }
 
 impl Drop for MetalCompiler {
     fn drop(&mut self) {
         // Best-effort cleanup of the temporary directory.
-        let _ = std::fs::remove_dir_all(&self.temp_dir);
+        let _ = std::fs::remove_dir_all(self.temp_dir());
     }
 }
 
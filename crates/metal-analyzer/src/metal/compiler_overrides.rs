@@ -0,0 +1,63 @@
+//! Per-directory compiler flags and include paths from `compiler.overrides`.
+//!
+//! Each override names the files it applies to with a path glob: `*` matches
+//! within a path segment, `?` one character, and `**` any number of
+//! segments. A relative glob such as `ios/**` may match any trailing part of
+//! the path, an absolute one the whole path. A trailing `/` stands for
+//! everything below the directory. Every matching override applies, in
+//! order, after the global flags, so a later `-D` or `-std=` wins.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::vfs::glob::{path_segments, segments_match};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CompilerOverride {
+    pub path_glob: String,
+    pub flags: Vec<String>,
+    pub include_paths: Vec<String>,
+}
+
+impl CompilerOverride {
+    pub fn matches(
+        &self,
+        path: &Path,
+    ) -> bool {
+        let glob = self.path_glob.trim();
+        if glob.is_empty() {
+            return false;
+        }
+        let path = path_segments(path);
+        let path_segments: Vec<&str> = path.iter().map(String::as_str).collect();
+        let mut glob_segments: Vec<&str> = glob.split('/').filter(|segment| !segment.is_empty()).collect();
+        if glob.ends_with('/') {
+            glob_segments.push("**");
+        }
+        if glob.starts_with('/') {
+            return segments_match(&glob_segments, &path_segments);
+        }
+        (0..path_segments.len()).any(|start| segments_match(&glob_segments, &path_segments[start..]))
+    }
+
+    pub(crate) fn normalize(&mut self) {
+        self.path_glob = self.path_glob.trim().to_string();
+        self.flags = self.flags.iter().map(|f| f.trim().to_string()).filter(|f| !f.is_empty()).collect();
+        self.include_paths =
+            self.include_paths.iter().map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect();
+    }
+}
+
+/// The overrides in `overrides` that apply to `path`, in order.
+pub fn matching<'a>(
+    overrides: &'a [CompilerOverride],
+    path: &'a Path,
+) -> impl Iterator<Item = &'a CompilerOverride> {
+    overrides.iter().filter(move |entry| entry.matches(path))
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/metal/compiler_overrides_tests.rs"]
+mod tests;
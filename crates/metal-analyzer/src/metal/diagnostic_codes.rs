@@ -0,0 +1,85 @@
+//! Stable codes for common compiler diagnostics.
+//!
+//! Clang reports Metal-specific rules in free-form messages. The ones below
+//! are recognized by their wording and tagged with a code linking to the
+//! Metal Shading Language specification; the comment on each entry names
+//! the section that states the rule. Entries are tried in order, so
+//! narrower ones come first.
+
+/// Published Metal Shading Language specification.
+pub const SPEC_URL: &str = "https://developer.apple.com/metal/Metal-Shading-Language-Specification.pdf";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiagnosticCode {
+    /// Value of `Diagnostic.code`.
+    pub code: &'static str,
+    /// A message matches when it contains every fragment of any group,
+    /// ignoring case.
+    patterns: &'static [&'static [&'static str]],
+}
+
+pub static DIAGNOSTIC_CODES: &[DiagnosticCode] = &[
+    // 4.4 Threadgroup Address Space
+    DiagnosticCode {
+        code: "threadgroup-memory",
+        patterns: &[&["threadgroup", "address space"], &["threadgroup memory"], &["threadgroup variable"]],
+    },
+    // 4 Address Spaces
+    DiagnosticCode {
+        code: "address-space",
+        patterns: &[&["address space"], &["program scope variable must reside in constant"]],
+    },
+    // 2.9 Textures
+    DiagnosticCode {
+        code: "texture-access",
+        patterns: &[
+            &["access::"],
+            &["texture", "read-only"],
+            &["texture", "write-only"],
+            &["texture", "access qualifier"],
+        ],
+    },
+    // 5.2.1 Locating Buffer, Texture, and Sampler Arguments
+    DiagnosticCode {
+        code: "resource-binding",
+        patterns: &[&["'buffer' attribute"], &["'texture' attribute"], &["'sampler' attribute"], &["argument index"]],
+    },
+    // 5.2.3 Attributes to Locate Per-Vertex Inputs
+    DiagnosticCode {
+        code: "stage-in",
+        patterns: &[&["stage_in"], &["'attribute' attribute"]],
+    },
+    // 5.1 Functions
+    DiagnosticCode {
+        code: "entry-point-signature",
+        patterns: &[
+            &["kernel function"],
+            &["vertex function"],
+            &["fragment function"],
+            &["kernel", "return type"],
+            &["kernel", "must return"],
+        ],
+    },
+    // 2.10 Samplers
+    DiagnosticCode {
+        code: "sampler",
+        patterns: &[
+            &["constexpr sampler"],
+            &["sampler", "address::"],
+            &["sampler", "filter::"],
+            &["sampler", "coord::"],
+        ],
+    },
+];
+
+/// The code for a compiler message, if it matches a known rule.
+pub fn classify(message: &str) -> Option<&'static DiagnosticCode> {
+    let message = message.to_ascii_lowercase();
+    DIAGNOSTIC_CODES.iter().find(|entry| {
+        entry.patterns.iter().any(|fragments| fragments.iter().all(|fragment| message.contains(fragment)))
+    })
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/metal/diagnostic_codes_tests.rs"]
+mod tests;
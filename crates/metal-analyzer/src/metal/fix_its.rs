@@ -0,0 +1,124 @@
+//! Fix-it hints from `-fdiagnostics-parseable-fixits` compiler output.
+//!
+//! Clang prints each suggested edit on its own line after the diagnostic it
+//! belongs to:
+//!
+//! ```text
+//! fix-it:"/path/shader.metal":{12:17-12:17}:";"
+//! ```
+//!
+//! Lines and columns are 1-based and the range is end-exclusive. The edits
+//! travel to the client in the diagnostic's `data`, so a later code action
+//! request can turn them into quick fixes without recompiling.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tower_lsp::lsp_types::{Diagnostic, Position, Range};
+
+use crate::text_pos::column_in_file;
+
+/// One edit the compiler suggests, with 0-based positions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FixIt {
+    pub file: String,
+    pub range: Range,
+    pub replacement: String,
+}
+
+/// The edits of one diagnostic. `message` is set for fixes carried by a
+/// note, whose text says what the edits do.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompilerFix {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub edits: Vec<FixIt>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FixData {
+    fix_its: Vec<CompilerFix>,
+}
+
+/// Parse one `fix-it:"<file>":{<line>:<col>-<line>:<col>}:"<text>"` line.
+pub(crate) fn parse_fix_it_line(line: &str) -> Option<FixIt> {
+    let rest = line.strip_prefix("fix-it:\"")?;
+    let (location, replacement) = rest.rsplit_once("}:\"")?;
+    let replacement = replacement.strip_suffix('"')?;
+    let (file, range) = location.rsplit_once("\":{")?;
+    let (start, end) = range.split_once('-')?;
+    let file = unescape(file);
+    let range = Range::new(parse_position(&file, start)?, parse_position(&file, end)?);
+    Some(FixIt {
+        file,
+        range,
+        replacement: unescape(replacement),
+    })
+}
+
+fn parse_position(
+    file: &str,
+    text: &str,
+) -> Option<Position> {
+    let (line, column) = text.split_once(':')?;
+    let line = line.parse::<u32>().ok()?.saturating_sub(1);
+    let column = column.parse::<u32>().ok()?.saturating_sub(1);
+    Some(Position::new(line, column_in_file(file, line, column)))
+}
+
+/// Undo clang's escaping: `\\`, `\"`, `\n`, `\t` and octal `\ooo` bytes.
+fn unescape(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        let octal_len = rest.iter().take(3).take_while(|digit| (b'0'..=b'7').contains(digit)).count();
+        if octal_len == 3 {
+            bytes.push(rest[..3].iter().fold(0u8, |value, digit| value.wrapping_mul(8) + (digit - b'0')));
+            rest = &rest[3..];
+            continue;
+        }
+        match rest.split_first() {
+            Some((b'n', tail)) => {
+                bytes.push(b'\n');
+                rest = tail;
+            },
+            Some((b't', tail)) => {
+                bytes.push(b'\t');
+                rest = tail;
+            },
+            Some((&escaped, tail)) => {
+                bytes.push(escaped);
+                rest = tail;
+            },
+            None => bytes.push(b'\\'),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Record `fix` in `diagnostic`'s `data`, after any fixes already there.
+pub fn attach_fix(
+    diagnostic: &mut Diagnostic,
+    fix: CompilerFix,
+) {
+    let mut data =
+        diagnostic.data.take().and_then(|data| serde_json::from_value::<FixData>(data).ok()).unwrap_or_default();
+    data.fix_its.push(fix);
+    diagnostic.data = serde_json::to_value(data).ok();
+}
+
+/// The fixes recorded in a diagnostic's `data`.
+pub fn fixes(data: Option<&Value>) -> Vec<CompilerFix> {
+    data.and_then(|data| FixData::deserialize(data).ok()).map(|data| data.fix_its).unwrap_or_default()
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/metal/fix_its_tests.rs"]
+mod tests;
@@ -0,0 +1,304 @@
+//! Per-entry-point statistics read from the disassembled AIR of a compile.
+//!
+//! Registers are allocated when the driver compiles AIR for a specific GPU,
+//! so register counts and spills are not in the binary. What AIR does show
+//! is the static threadgroup memory an entry point reaches, and the private
+//! memory it still allocates on the stack after optimization, which is what
+//! usually ends up spilled. Instruction and block counts give a sense of
+//! size. Helpers the compiler did not inline are counted into every entry
+//! point that calls them.
+
+use std::collections::{HashMap, HashSet};
+
+/// Statistics of one entry point in a compiled AIR module.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KernelStats {
+    pub name: String,
+    /// Entry-point qualifier: `kernel`, `vertex`, `fragment`, `mesh` or `object`.
+    pub stage: String,
+    pub instructions: usize,
+    pub basic_blocks: usize,
+    /// Bytes of `threadgroup` variables declared in the shader source. Memory
+    /// set with `setThreadgroupMemoryLength` is not included.
+    pub threadgroup_memory_bytes: u64,
+    /// Bytes of stack allocations left after optimization.
+    pub private_memory_bytes: u64,
+}
+
+impl KernelStats {
+    /// Markdown section shown in hovers on the entry point's name.
+    pub fn to_markdown(&self) -> String {
+        format!(
+            "**Compiled `{}` function**\n\n\
+             - {} instructions in {} basic blocks\n\
+             - Threadgroup memory: {} bytes\n\
+             - Stack memory: {} bytes{}",
+            self.stage,
+            self.instructions,
+            self.basic_blocks,
+            self.threadgroup_memory_bytes,
+            self.private_memory_bytes,
+            if self.private_memory_bytes > 0 {
+                " (likely spilled to device memory)"
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+/// Entry-point metadata lists and the stage each one names.
+const STAGE_LISTS: &[(&str, &str)] = &[
+    ("!air.kernel", "kernel"),
+    ("!air.vertex", "vertex"),
+    ("!air.fragment", "fragment"),
+    ("!air.mesh", "mesh"),
+    ("!air.object", "object"),
+];
+
+/// Threadgroup address space in AIR.
+const THREADGROUP_ADDRESS_SPACE: &str = "addrspace(3)";
+
+#[derive(Debug, Default)]
+struct Function {
+    instructions: usize,
+    basic_blocks: usize,
+    private_memory_bytes: u64,
+    globals: HashSet<String>,
+    callees: HashSet<String>,
+}
+
+/// Statistics for every entry point in `ir`, the textual form of an AIR
+/// module as printed by `metal-dis`, in the order the module lists them.
+pub fn parse_air_module(ir: &str) -> Vec<KernelStats> {
+    let mut named_types: HashMap<String, String> = HashMap::new();
+    let mut threadgroup_globals: HashMap<String, String> = HashMap::new();
+    let mut functions: HashMap<String, Function> = HashMap::new();
+    let mut stage_lists: Vec<(&str, Vec<String>)> = Vec::new();
+    let mut metadata: HashMap<String, String> = HashMap::new();
+
+    let mut current: Option<(String, Function)> = None;
+    for line in ir.lines() {
+        let trimmed = line.trim();
+        if let Some((name, function)) = current.as_mut() {
+            if trimmed == "}" {
+                let (name, function) = (std::mem::take(name), std::mem::take(function));
+                functions.insert(name, function);
+                current = None;
+            } else {
+                record_body_line(function, trimmed, &named_types);
+            }
+            continue;
+        }
+        if trimmed.starts_with("define ") {
+            if let Some(name) = global_names(trimmed).next() {
+                let function = Function {
+                    basic_blocks: 1,
+                    ..Function::default()
+                };
+                current = Some((name.to_string(), function));
+            }
+        } else if let Some((name, definition)) = trimmed.strip_prefix('%').and_then(|rest| rest.split_once(" = type "))
+        {
+            named_types.insert(name.to_string(), definition.trim().to_string());
+        } else if let Some((name, definition)) = trimmed.strip_prefix('@').and_then(|rest| rest.split_once(" = ")) {
+            if let Some((_, ty)) = definition.split_once(&format!("{THREADGROUP_ADDRESS_SPACE} global ")) {
+                threadgroup_globals.insert(name.trim_matches('"').to_string(), ty.to_string());
+            }
+        } else if let Some((_, stage)) = STAGE_LISTS.iter().find(|(list, _)| trimmed.starts_with(&format!("{list} "))) {
+            stage_lists.push((stage, metadata_ids(trimmed.split_once('=').map_or("", |(_, ids)| ids))));
+        } else if let Some((id, node)) = trimmed.split_once(" = ")
+            && id.starts_with('!')
+        {
+            metadata.insert(id.to_string(), node.to_string());
+        }
+    }
+
+    let threadgroup_sizes: HashMap<&str, u64> = threadgroup_globals
+        .iter()
+        .map(|(name, ty)| (name.as_str(), type_layout(ty, &named_types).map_or(0, |(size, _)| size)))
+        .collect();
+
+    let mut stats = Vec::new();
+    for (stage, ids) in stage_lists {
+        for id in ids {
+            let Some(name) = metadata.get(&id).and_then(|node| global_names(node).next()) else {
+                continue;
+            };
+            let mut entry = KernelStats {
+                name: name.to_string(),
+                stage: stage.to_string(),
+                ..KernelStats::default()
+            };
+            let mut globals = HashSet::new();
+            let mut visited = HashSet::new();
+            let mut pending = vec![name.to_string()];
+            while let Some(function_name) = pending.pop() {
+                if !visited.insert(function_name.clone()) {
+                    continue;
+                }
+                let Some(function) = functions.get(&function_name) else {
+                    continue;
+                };
+                entry.instructions += function.instructions;
+                entry.basic_blocks += function.basic_blocks;
+                entry.private_memory_bytes += function.private_memory_bytes;
+                globals.extend(function.globals.iter().map(String::as_str));
+                pending.extend(function.callees.iter().cloned());
+            }
+            entry.threadgroup_memory_bytes = globals.iter().filter_map(|global| threadgroup_sizes.get(global)).sum();
+            stats.push(entry);
+        }
+    }
+    stats
+}
+
+fn record_body_line(
+    function: &mut Function,
+    line: &str,
+    named_types: &HashMap<String, String>,
+) {
+    if line.is_empty() || line.starts_with(';') {
+        return;
+    }
+    if line.ends_with(':') || line.split_once(':').is_some_and(|(_, rest)| rest.trim_start().starts_with(';')) {
+        function.basic_blocks += 1;
+        return;
+    }
+    function.instructions += 1;
+    let instruction = line.split_once(" = ").map_or(line, |(_, rest)| rest);
+    if let Some(ty) = instruction.strip_prefix("alloca ") {
+        let ty = ty.split(", align").next().unwrap_or(ty);
+        function.private_memory_bytes += type_layout(ty, named_types).map_or(0, |(size, _)| size);
+    }
+    let called = (instruction.starts_with("call ") || instruction.contains(" call "))
+        .then(|| instruction.split_once('(').map(|(head, _)| head))
+        .flatten()
+        .and_then(|head| global_names(head).last());
+    if let Some(callee) = called
+        && !callee.starts_with("air.")
+        && !callee.starts_with("llvm.")
+    {
+        function.callees.insert(callee.to_string());
+    }
+    function.globals.extend(global_names(line).filter(|name| Some(*name) != called).map(str::to_string));
+}
+
+/// Names after each `@` in `text`, with quotes removed.
+fn global_names(text: &str) -> impl Iterator<Item = &str> {
+    text.split('@').skip(1).filter_map(|rest| {
+        if let Some(quoted) = rest.strip_prefix('"') {
+            return quoted.split_once('"').map(|(name, _)| name);
+        }
+        let end = rest.find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '.' | '$'))).unwrap_or(rest.len());
+        (end > 0).then(|| &rest[..end])
+    })
+}
+
+/// `!12` ids in a `!{!12, !13}` list.
+fn metadata_ids(list: &str) -> Vec<String> {
+    list.trim()
+        .trim_start_matches('!')
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .split(',')
+        .map(str::trim)
+        .filter(|id| id.starts_with('!'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Size and alignment in bytes of the LLVM type at the start of `ty`.
+fn type_layout(
+    ty: &str,
+    named_types: &HashMap<String, String>,
+) -> Option<(u64, u64)> {
+    parse_type(ty.trim(), named_types, 0).map(|(layout, _)| layout)
+}
+
+/// Named types nest at most this deep, which also stops recursive types.
+const MAX_TYPE_DEPTH: usize = 32;
+
+fn parse_type<'a>(
+    ty: &'a str,
+    named_types: &HashMap<String, String>,
+    depth: usize,
+) -> Option<((u64, u64), &'a str)> {
+    if depth > MAX_TYPE_DEPTH {
+        return None;
+    }
+    let (layout, rest) = if let Some(rest) = ty.strip_prefix('[') {
+        let (count, rest) = rest.split_once(" x ")?;
+        let ((size, align), rest) = parse_type(rest.trim_start(), named_types, depth + 1)?;
+        ((count.trim().parse::<u64>().ok()? * size, align), rest.trim_start().strip_prefix(']')?)
+    } else if let Some(rest) = ty.strip_prefix("<{") {
+        let (members, rest) = parse_members(rest, "}>", named_types, depth)?;
+        ((members.iter().map(|(size, _)| size).sum(), 1), rest)
+    } else if let Some(rest) = ty.strip_prefix('<') {
+        let (count, rest) = rest.split_once(" x ")?;
+        let ((size, _), rest) = parse_type(rest.trim_start(), named_types, depth + 1)?;
+        let bytes = (count.trim().parse::<u64>().ok()? * size).next_power_of_two();
+        ((bytes, bytes), rest.trim_start().strip_prefix('>')?)
+    } else if let Some(rest) = ty.strip_prefix('{') {
+        let (members, rest) = parse_members(rest, "}", named_types, depth)?;
+        let align = members.iter().map(|(_, align)| *align).max().unwrap_or(1);
+        let mut size: u64 = 0;
+        for (member_size, member_align) in members {
+            size = size.next_multiple_of(member_align) + member_size;
+        }
+        ((size.next_multiple_of(align), align), rest)
+    } else if let Some(rest) = ty.strip_prefix('%') {
+        let end = rest.find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '.' | '$'))).unwrap_or(rest.len());
+        let definition = named_types.get(&rest[..end])?;
+        let (layout, _) = parse_type(definition, named_types, depth + 1)?;
+        (layout, &rest[end..])
+    } else if let Some(rest) = ty.strip_prefix("ptr") {
+        ((8, 8), rest)
+    } else {
+        let end = ty.find(|c: char| !c.is_alphanumeric()).unwrap_or(ty.len());
+        let size = match &ty[..end] {
+            "i1" | "i8" => 1,
+            "i16" | "half" | "bfloat" => 2,
+            "i32" | "float" => 4,
+            "i64" | "double" => 8,
+            _ => return None,
+        };
+        ((size, size), &ty[end..])
+    };
+    // A pointer to the type just read, such as `float addrspace(1)*`.
+    let mut rest = rest;
+    loop {
+        let trimmed = rest.trim_start();
+        if let Some(after) = trimmed.strip_prefix("addrspace(") {
+            rest = after.split_once(')').map_or("", |(_, after)| after);
+        } else if let Some(after) = trimmed.strip_prefix('*') {
+            return Some(((8, 8), after));
+        } else {
+            return Some((layout, rest));
+        }
+    }
+}
+
+/// Comma-separated member types up to `close`.
+fn parse_members<'a>(
+    mut ty: &'a str,
+    close: &str,
+    named_types: &HashMap<String, String>,
+    depth: usize,
+) -> Option<(Vec<(u64, u64)>, &'a str)> {
+    let mut members = Vec::new();
+    loop {
+        ty = ty.trim_start();
+        if let Some(rest) = ty.strip_prefix(close) {
+            return Some((members, rest));
+        }
+        let (layout, rest) = parse_type(ty, named_types, depth + 1)?;
+        members.push(layout);
+        ty = rest.trim_start();
+        ty = ty.strip_prefix(',').unwrap_or(ty);
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/metal/kernel_stats_tests.rs"]
+mod tests;
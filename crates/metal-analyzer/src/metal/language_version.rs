@@ -0,0 +1,76 @@
+//! Metal Shading Language versions, as set by `compiler.languageVersion` or
+//! read from a `-std=` flag.
+
+use std::fmt;
+
+use crate::metal::compiler::CompilerPlatform;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LanguageVersion {
+    pub major: u8,
+    pub minor: u8,
+}
+
+/// Values accepted by `compiler.languageVersion` besides `auto`.
+pub const LANGUAGE_VERSIONS: &[&str] = &["2.0", "2.1", "2.2", "2.3", "2.4", "3.0", "3.1", "3.2", "4.0"];
+
+impl LanguageVersion {
+    pub const fn new(
+        major: u8,
+        minor: u8,
+    ) -> Self {
+        Self {
+            major,
+            minor,
+        }
+    }
+
+    /// Parse `3.1`, or a `-std=` value such as `metal3.1` or `ios-metal2.4`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        let number = match value.rsplit_once("metal") {
+            Some((prefix, number)) if prefix.is_empty() || prefix.ends_with('-') => number,
+            Some(_) => return None,
+            None => value.as_str(),
+        };
+        let (major, minor) = number.split_once('.')?;
+        Some(Self::new(major.parse().ok()?, minor.parse().ok()?))
+    }
+
+    /// The version selected by the last `-std=` flag in `flags`.
+    pub fn from_flags(flags: &[String]) -> Option<Self> {
+        flags.iter().rev().find_map(|flag| flag.trim().strip_prefix("-std=")).and_then(Self::parse)
+    }
+
+    /// The `-std=` flag selecting this version. Before Metal 3 the standard
+    /// is named per platform family.
+    pub fn std_flag(
+        self,
+        platform: CompilerPlatform,
+    ) -> String {
+        let family = match (self.major, platform) {
+            (3.., _) => "",
+            (_, CompilerPlatform::Macos) => "macos-",
+            _ => "ios-",
+        };
+        format!("-std={family}metal{self}")
+    }
+
+    /// Value of `__METAL_VERSION__`, e.g. `310` for Metal 3.1.
+    pub fn metal_version_macro(self) -> String {
+        (u32::from(self.major) * 100 + u32::from(self.minor) * 10).to_string()
+    }
+}
+
+impl fmt::Display for LanguageVersion {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/metal/language_version_tests.rs"]
+mod tests;
@@ -1,2 +1,11 @@
 pub mod builtins;
 pub mod compiler;
+pub mod compiler_overrides;
+pub mod diagnostic_codes;
+pub mod fix_its;
+pub mod kernel_stats;
+pub mod language_version;
+pub(crate) mod pch;
+pub mod preprocess;
+pub mod toolchain;
+pub(crate) mod virtual_includes;
@@ -0,0 +1,154 @@
+//! Precompiled `<metal_stdlib>` for diagnostics compiles.
+//!
+//! Parsing the standard library dominates the compile of a small shader. It
+//! is precompiled once per toolchain and set of effective flags, and files
+//! that include it before any other directive load it with `-include-pch`
+//! instead of parsing it again. A header that fails to build is not retried
+//! for the same flags; those compiles parse the library as before.
+
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+use crate::metal::compiler::COMPILER_PROCESSES;
+
+/// The header every precompiled header is built from.
+const PRELUDE: &str = "#include <metal_stdlib>\n";
+
+/// Precompiled headers built by this process, by [`flags_key`].
+#[derive(Default)]
+pub(crate) struct PrecompiledHeaders {
+    /// `None` records a build that failed.
+    built: tokio::sync::Mutex<HashMap<u64, Option<PathBuf>>>,
+}
+
+impl PrecompiledHeaders {
+    /// Path of the precompiled prelude for `flags`, building it under `dir`
+    /// on first use. Compiles with other flags need their own header, as
+    /// the compiler rejects one whose defines differ.
+    pub(crate) async fn get_or_build(
+        &self,
+        dir: &Path,
+        flags: &[String],
+        toolchain_signature: Option<&str>,
+    ) -> Option<PathBuf> {
+        let key = flags_key(flags, toolchain_signature);
+        let mut built = self.built.lock().await;
+        if let Some(entry) = built.get(&key)
+            && entry.as_ref().is_none_or(|path| path.exists())
+        {
+            return entry.clone();
+        }
+        let pch = build(dir, key, flags).await;
+        built.insert(key, pch.clone());
+        pch
+    }
+}
+
+async fn build(
+    dir: &Path,
+    key: u64,
+    flags: &[String],
+) -> Option<PathBuf> {
+    if let Err(error) = tokio::fs::create_dir_all(dir).await {
+        warn!("Failed to create precompiled header dir {}: {error}", dir.display());
+        return None;
+    }
+    let header = dir.join(format!("prelude-{key:016x}.h"));
+    let pch = dir.join(format!("prelude-{key:016x}.pch"));
+    if let Err(error) = tokio::fs::write(&header, PRELUDE).await {
+        warn!("Failed to write precompiled header source: {error}");
+        return None;
+    }
+
+    let mut command = Command::new("xcrun");
+    command.kill_on_drop(true);
+    command
+        .args(["metal", "-x", "metal-header", "-fno-color-diagnostics"])
+        .args(flags)
+        .arg(&header)
+        .arg("-o")
+        .arg(&pch);
+    let output = {
+        let _slot = COMPILER_PROCESSES.acquire().await.ok()?;
+        command.output().await
+    };
+    let _ = tokio::fs::remove_file(&header).await;
+    match output {
+        Ok(output) if output.status.success() && pch.exists() => {
+            debug!("Built precompiled <metal_stdlib> at {}", pch.display());
+            Some(pch)
+        },
+        Ok(output) => {
+            warn!(
+                "Building precompiled <metal_stdlib> failed; compiles parse it instead: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            None
+        },
+        Err(error) => {
+            warn!("Failed to run compiler for precompiled header: {error}");
+            None
+        },
+    }
+}
+
+/// Identifies the precompiled headers interchangeable for a compile.
+pub(crate) fn flags_key(
+    flags: &[String],
+    toolchain_signature: Option<&str>,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    flags.hash(&mut hasher);
+    toolchain_signature.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Whether `source` includes `<metal_stdlib>` before any other directive or
+/// code, so loading the precompiled prelude first cannot change its meaning.
+pub(crate) fn includes_prelude_first(source: &str) -> bool {
+    let mut in_block_comment = false;
+    for line in source.lines() {
+        let mut line = line.trim();
+        if in_block_comment {
+            match line.split_once("*/") {
+                Some((_, rest)) => {
+                    in_block_comment = false;
+                    line = rest.trim();
+                },
+                None => continue,
+            }
+        }
+        if let Some(rest) = line.strip_prefix("/*") {
+            match rest.split_once("*/") {
+                Some((_, rest)) if rest.trim().is_empty() => continue,
+                Some(_) => return false,
+                None => {
+                    in_block_comment = true;
+                    continue;
+                },
+            }
+        }
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        let Some(directive) = line.strip_prefix('#') else {
+            return false;
+        };
+        let directive = directive.trim_start();
+        if directive.split_whitespace().eq(["pragma", "once"]) {
+            continue;
+        }
+        return directive.strip_prefix("include").is_some_and(|rest| rest.trim_start().starts_with("<metal_stdlib>"));
+    }
+    false
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/metal/pch_tests.rs"]
+mod tests;
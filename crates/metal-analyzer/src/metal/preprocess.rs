@@ -0,0 +1,278 @@
+//! Preprocessor output and entry-point slicing.
+//!
+//! `xcrun metal -E` prints the whole translation unit, including every line
+//! of `metal_stdlib`, with `# <line> "<file>"` markers in between. This module
+//! maps the output back to source lines and can cut it down to the
+//! declarations one entry point depends on, so macro-heavy code can be read
+//! exactly as the compiler sees it.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use rowan::TextRange;
+
+use crate::syntax::{
+    SyntaxTree,
+    ast::{self, AstNode},
+    cst::{SyntaxNode, SyntaxToken},
+    kind::SyntaxKind,
+};
+
+/// Source location a preprocessed line came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineOrigin {
+    pub file: String,
+    /// One-based line number.
+    pub line: u32,
+}
+
+/// Preprocessor output with its line markers resolved.
+#[derive(Debug, Clone, Default)]
+pub struct PreprocessedSource {
+    /// Output text without line markers.
+    text: String,
+    /// Origin of every line of `text`, when a marker named a real file.
+    origins: Vec<Option<LineOrigin>>,
+}
+
+impl PreprocessedSource {
+    pub fn parse(output: &str) -> Self {
+        let mut text = String::with_capacity(output.len());
+        let mut origins = Vec::new();
+        let mut file: Option<String> = None;
+        let mut next_line = 1u32;
+        for line in output.lines() {
+            if let Some((line_number, marker_file)) = parse_line_marker(line) {
+                // `<built-in>` and `<command line>` are not files.
+                file = (!marker_file.starts_with('<')).then(|| marker_file.to_string());
+                next_line = line_number;
+                continue;
+            }
+            text.push_str(line);
+            text.push('\n');
+            origins.push(file.clone().map(|file| LineOrigin {
+                file,
+                line: next_line,
+            }));
+            next_line += 1;
+        }
+        Self {
+            text,
+            origins,
+        }
+    }
+
+    /// Preprocessed text without line markers.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Origin of each line of [`text`](Self::text), in order.
+    pub fn origins(&self) -> &[Option<LineOrigin>] {
+        &self.origins
+    }
+
+    /// The lines that did not come from files matched by `is_excluded`.
+    pub fn without_files(
+        &self,
+        is_excluded: impl Fn(&str) -> bool,
+    ) -> Self {
+        let mut text = String::new();
+        let mut origins = Vec::new();
+        for (line, origin) in self.text.lines().zip(&self.origins) {
+            if origin.as_ref().is_some_and(|origin| is_excluded(&origin.file)) {
+                continue;
+            }
+            text.push_str(line);
+            text.push('\n');
+            origins.push(origin.clone());
+        }
+        Self {
+            text,
+            origins,
+        }
+    }
+
+    /// Origin of the zero-based `line` of [`text`](Self::text).
+    pub fn origin(
+        &self,
+        line: usize,
+    ) -> Option<&LineOrigin> {
+        self.origins.get(line)?.as_ref()
+    }
+
+    /// The declarations `entry_point` transitively depends on, in output
+    /// order and each preceded by a line marker.
+    ///
+    /// Dependencies are followed by name through functions, types, typedefs
+    /// and globals outside the files matched by `is_system`, which keeps the
+    /// standard library out of the slice. Returns `None` when no function
+    /// named `entry_point` is defined outside those files.
+    pub fn slice_for_entry_point(
+        &self,
+        entry_point: &str,
+        is_system: impl Fn(&str) -> bool,
+    ) -> Option<String> {
+        let tree = SyntaxTree::parse(&self.text);
+        let mut items = Vec::new();
+        collect_items(&tree.root(), &mut Vec::new(), &mut items);
+        let line_starts: Vec<usize> =
+            std::iter::once(0).chain(self.text.match_indices('\n').map(|(offset, _)| offset + 1)).collect();
+        let origin_of = |item: &Item| {
+            let offset = usize::from(item.range.start());
+            let line = line_starts.partition_point(|&start| start <= offset) - 1;
+            self.origin(line)
+        };
+        let items: Vec<(Item, &LineOrigin)> = items
+            .into_iter()
+            .filter_map(|item| {
+                let origin = origin_of(&item)?;
+                (!is_system(&origin.file)).then_some((item, origin))
+            })
+            .collect();
+
+        let mut declared_by: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (index, (item, _)) in items.iter().enumerate() {
+            for name in &item.names {
+                declared_by.entry(name.as_str()).or_default().push(index);
+            }
+        }
+
+        let mut queue: VecDeque<usize> = items
+            .iter()
+            .enumerate()
+            .filter(|(_, (item, _))| item.is_function && item.names.iter().any(|name| name == entry_point))
+            .map(|(index, _)| index)
+            .collect();
+        if queue.is_empty() {
+            return None;
+        }
+        let mut kept: HashSet<usize> = queue.iter().copied().collect();
+        while let Some(index) = queue.pop_front() {
+            for name in &items[index].0.uses {
+                for &dependency in declared_by.get(name.as_str()).into_iter().flatten() {
+                    if kept.insert(dependency) {
+                        queue.push_back(dependency);
+                    }
+                }
+            }
+        }
+
+        let mut kept: Vec<usize> = kept.into_iter().collect();
+        kept.sort_unstable();
+        let mut slice = String::new();
+        for index in kept {
+            let (item, origin) = &items[index];
+            for namespace in &item.namespaces {
+                slice.push_str(&format!("namespace {namespace} {{\n"));
+            }
+            slice.push_str(&format!("# {} \"{}\"\n", origin.line, origin.file));
+            slice.push_str(&self.text[item.range]);
+            slice.push('\n');
+            for _ in &item.namespaces {
+                slice.push_str("}\n");
+            }
+        }
+        Some(slice)
+    }
+}
+
+/// Parse a `# 12 "file.metal" 1` or `#line 12 "file.metal"` marker.
+fn parse_line_marker(line: &str) -> Option<(u32, &str)> {
+    let rest = line.strip_prefix('#')?.trim_start();
+    let rest = rest.strip_prefix("line").unwrap_or(rest).trim_start();
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let line_number = rest[..digits].parse().ok()?;
+    let quoted = rest[digits..].trim_start().strip_prefix('"')?;
+    let file = &quoted[..quoted.find('"')?];
+    Some((line_number, file))
+}
+
+/// A top-level declaration of the preprocessed translation unit.
+#[derive(Debug)]
+struct Item {
+    /// Text range, including a preceding `template <...>` header.
+    range: TextRange,
+    /// Enclosing namespaces, outermost first.
+    namespaces: Vec<String>,
+    /// Names the declaration introduces.
+    names: Vec<String>,
+    /// Every identifier the declaration mentions.
+    uses: HashSet<String>,
+    is_function: bool,
+}
+
+fn collect_items(
+    parent: &SyntaxNode,
+    namespaces: &mut Vec<String>,
+    items: &mut Vec<Item>,
+) {
+    let mut template_start = None;
+    for child in parent.children() {
+        let start = template_start.take().unwrap_or(child.text_range().start());
+        let names = match child.kind() {
+            SyntaxKind::TemplateDef => {
+                template_start = Some(start);
+                continue;
+            },
+            SyntaxKind::NamespaceDef => {
+                let namespace = ast::NamespaceDef::cast(child).expect("kind checked");
+                if let Some(body) = namespace.body() {
+                    let name = namespace.name_token().map(|token| token.text().to_string());
+                    // Anonymous namespaces are emitted as `namespace {`.
+                    namespaces.push(name.unwrap_or_default());
+                    collect_items(body.syntax(), namespaces, items);
+                    namespaces.pop();
+                }
+                continue;
+            },
+            SyntaxKind::FunctionDef => {
+                ast::FunctionDef::cast(child.clone()).and_then(|f| f.name_token()).into_iter().collect()
+            },
+            SyntaxKind::StructDef => {
+                ast::StructDef::cast(child.clone()).and_then(|s| s.name_token()).into_iter().collect()
+            },
+            SyntaxKind::ClassDef => {
+                ast::ClassDef::cast(child.clone()).and_then(|c| c.name_token()).into_iter().collect()
+            },
+            SyntaxKind::EnumDef => {
+                // Enumerators are used without naming the enum.
+                let mut names: Vec<SyntaxToken> =
+                    ast::EnumDef::cast(child.clone()).and_then(|e| e.name_token()).into_iter().collect();
+                names.extend(child.children().flat_map(|body| idents(&body)));
+                names
+            },
+            SyntaxKind::VariableDef => {
+                ast::VariableDef::cast(child.clone()).and_then(|v| v.name_token()).into_iter().collect()
+            },
+            // `typedef <type> Name;` names the last identifier, `using Name = <type>;` the first.
+            SyntaxKind::TypedefDef => direct_idents(&child).pop().into_iter().collect(),
+            SyntaxKind::UsingDef => direct_idents(&child).into_iter().take(1).collect(),
+            _ => continue,
+        };
+        items.push(Item {
+            range: TextRange::new(start, child.text_range().end()),
+            namespaces: namespaces.clone(),
+            names: names.iter().map(|token| token.text().to_string()).collect(),
+            uses: idents(&child).iter().map(|token| token.text().to_string()).collect(),
+            is_function: child.kind() == SyntaxKind::FunctionDef,
+        });
+    }
+}
+
+fn idents(node: &SyntaxNode) -> Vec<SyntaxToken> {
+    node.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() == SyntaxKind::Ident)
+        .collect()
+}
+
+fn direct_idents(node: &SyntaxNode) -> Vec<SyntaxToken> {
+    node.children_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() == SyntaxKind::Ident)
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/metal/preprocess_tests.rs"]
+mod tests;
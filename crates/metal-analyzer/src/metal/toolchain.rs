@@ -0,0 +1,191 @@
+//! Metal toolchain tools beyond the compiler, and the parsing of their output.
+//!
+//! `metal-dis` prints an `.air` object as LLVM IR text. When the object was
+//! compiled with `-gline-tables-only`, each instruction points to a
+//! `!DILocation` naming a source line, which [`ir_functions`] resolves to
+//! map the IR of each function back to the shader source.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use tokio::process::Command;
+use tracing::debug;
+
+/// Compiler flag that records the source line of each instruction.
+pub const LINE_TABLES_FLAG: &str = "-gline-tables-only";
+
+/// A function defined in an IR module.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IrFunction {
+    pub name: String,
+    /// Zero-based line of the `define`.
+    pub start_line: u32,
+    /// Zero-based line of the closing `}`.
+    pub end_line: u32,
+    /// Zero-based source line of the definition, from its debug info, when
+    /// it is in the source file asked for.
+    pub source_line: Option<u32>,
+    /// Zero-based IR lines of instructions with their zero-based source line,
+    /// for the instructions located in the source file asked for.
+    pub lines: Vec<(u32, u32)>,
+}
+
+/// The textual IR of the `.air` object `air_file`.
+pub async fn disassemble_air(air_file: &Path) -> Result<String, String> {
+    let args = ["metal-dis".to_string(), air_file.display().to_string(), "-o".to_string(), "-".to_string()];
+    debug!("Running: xcrun {}", args.join(" "));
+    let mut command = Command::new("xcrun");
+    command.kill_on_drop(true);
+    let output = command.args(&args).output().await.map_err(|e| format!("Failed to run metal-dis: {e}"))?;
+    if !output.status.success() {
+        return Err(format!("metal-dis failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The functions defined in `ir`, in order, with the lines of `source_file`
+/// their instructions come from.
+pub fn ir_functions(
+    ir: &str,
+    source_file: &Path,
+) -> Vec<IrFunction> {
+    let metadata = MetadataNodes::parse(ir);
+    let mut functions = Vec::new();
+    let mut current: Option<IrFunction> = None;
+    for (line_number, line) in (0u32..).zip(ir.lines()) {
+        let trimmed = line.trim();
+        if let Some(function) = current.as_mut() {
+            if trimmed == "}" {
+                function.end_line = line_number;
+                functions.extend(current.take());
+            } else if let Some(location) = attachment(trimmed, "!dbg")
+                && let Some(source_line) = metadata.line_in(location, source_file)
+            {
+                function.lines.push((line_number, source_line));
+            }
+            continue;
+        }
+        if !trimmed.starts_with("define ") {
+            continue;
+        }
+        let Some(name) = function_name(trimmed) else {
+            continue;
+        };
+        current = Some(IrFunction {
+            name: name.to_string(),
+            start_line: line_number,
+            end_line: line_number,
+            source_line: attachment(trimmed, "!dbg").and_then(|subprogram| metadata.line_in(subprogram, source_file)),
+            lines: Vec::new(),
+        });
+    }
+    functions
+}
+
+/// Name of the function a `define` line defines.
+fn function_name(define: &str) -> Option<&str> {
+    let rest = define.split_once('@')?.1;
+    if let Some(quoted) = rest.strip_prefix('"') {
+        return quoted.split_once('"').map(|(name, _)| name);
+    }
+    rest.split_once('(').map(|(name, _)| name)
+}
+
+/// The `!12` id attached to `line` under `key`, e.g. `!dbg !12`.
+fn attachment<'a>(
+    line: &'a str,
+    key: &str,
+) -> Option<&'a str> {
+    let (_, rest) = line.rsplit_once(&format!("{key} "))?;
+    let end = rest.find(|c: char| !(c == '!' || c.is_ascii_digit())).unwrap_or(rest.len());
+    Some(&rest[..end]).filter(|id| id.len() > 1)
+}
+
+/// Specialized metadata nodes, such as `!7 = !DILocation(line: 3, scope: !4)`,
+/// by id.
+struct MetadataNodes<'a> {
+    nodes: HashMap<&'a str, (&'a str, HashMap<&'a str, &'a str>)>,
+}
+
+/// Scopes nest at most this deep, which also stops cycles.
+const MAX_SCOPE_DEPTH: usize = 64;
+
+impl<'a> MetadataNodes<'a> {
+    fn parse(ir: &'a str) -> Self {
+        let nodes = ir
+            .lines()
+            .filter_map(|line| {
+                let (id, node) = line.trim().split_once(" = ")?;
+                let node = node.strip_prefix("distinct ").unwrap_or(node);
+                let (kind, fields) = node.strip_prefix('!')?.split_once('(')?;
+                let fields = fields.strip_suffix(')')?;
+                Some((id.starts_with('!').then_some(id)?, (kind, metadata_fields(fields))))
+            })
+            .collect();
+        Self {
+            nodes,
+        }
+    }
+
+    /// Zero-based line of the location or subprogram `id`, when its scope
+    /// lies in `source_file`.
+    fn line_in(
+        &self,
+        id: &str,
+        source_file: &Path,
+    ) -> Option<u32> {
+        let (_, fields) = self.nodes.get(id)?;
+        let line = fields.get("line")?.parse::<u32>().ok()?.checked_sub(1)?;
+        self.file_of(id, 0).is_some_and(|file| file == source_file).then_some(line)
+    }
+
+    /// Path of the file a location or scope is in.
+    fn file_of(
+        &self,
+        id: &str,
+        depth: usize,
+    ) -> Option<PathBuf> {
+        if depth > MAX_SCOPE_DEPTH {
+            return None;
+        }
+        let (kind, fields) = self.nodes.get(id)?;
+        if *kind == "DIFile" {
+            let filename = unquote(fields.get("filename")?);
+            let directory = fields.get("directory").map_or("", |directory| unquote(directory));
+            return Some(Path::new(directory).join(filename));
+        }
+        let next = fields.get("file").or_else(|| fields.get("scope"))?;
+        self.file_of(next, depth + 1)
+    }
+}
+
+/// `key: value` pairs of a specialized metadata node, split at commas
+/// outside quotes.
+fn metadata_fields(fields: &str) -> HashMap<&str, &str> {
+    let mut pairs = HashMap::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (index, c) in fields.char_indices().chain([(fields.len(), ',')]) {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                if let Some((key, value)) = fields[start..index].split_once(':') {
+                    pairs.insert(key.trim(), value.trim());
+                }
+                start = index + 1;
+            },
+            _ => {},
+        }
+    }
+    pairs
+}
+
+fn unquote(value: &str) -> &str {
+    value.trim_matches('"')
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/metal/toolchain_tests.rs"]
+mod tests;
@@ -0,0 +1,141 @@
+//! Include prefixes that stand for a directory (`compiler.virtualIncludeRoots`).
+//!
+//! Generated headers often include each other relative to a codegen root
+//! that is not on the include path, e.g. `#include "generated/matmul.h"` for
+//! a file in `build/codegen`. Each mapped prefix becomes a link to its
+//! directory inside a folder under the compiler temp dir, and that folder is
+//! added to the include paths. The compiler and include resolution then find
+//! the header through the same search path, and resolved paths are
+//! canonicalized back to the real file.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
+
+use tracing::{debug, warn};
+
+/// The configured mapping and the link folders built for it, by workspace
+/// root.
+#[derive(Default)]
+pub(crate) struct VirtualIncludeRoots {
+    roots: Mutex<BTreeMap<String, String>>,
+    /// `None` records a folder that could not be created.
+    linked: Mutex<HashMap<Option<PathBuf>, Option<PathBuf>>>,
+}
+
+impl VirtualIncludeRoots {
+    /// Replace the mapping; folders built for the previous one are no longer
+    /// handed out.
+    pub(crate) fn set(
+        &self,
+        roots: BTreeMap<String, String>,
+    ) {
+        let Ok(mut guard) = self.roots.lock() else {
+            return;
+        };
+        if *guard != roots {
+            *guard = roots;
+            if let Ok(mut linked) = self.linked.lock() {
+                linked.clear();
+            }
+        }
+    }
+
+    /// The folder of links for files under `workspace_root`, built under
+    /// `dir` on first use. `None` when no prefix is mapped.
+    pub(crate) fn get_or_link(
+        &self,
+        dir: &Path,
+        workspace_root: Option<&Path>,
+    ) -> Option<PathBuf> {
+        let roots = self.roots.lock().ok()?.clone();
+        if roots.is_empty() {
+            return None;
+        }
+        let mut linked = self.linked.lock().ok()?;
+        let key = workspace_root.map(Path::to_path_buf);
+        if let Some(entry) = linked.get(&key)
+            && entry.as_ref().is_none_or(|path| path.exists())
+        {
+            return entry.clone();
+        }
+        let folder = dir.join(format!("{:016x}", roots_key(&roots, workspace_root)));
+        let folder = match link_roots(&folder, &roots, workspace_root) {
+            Ok(()) => Some(folder),
+            Err(error) => {
+                warn!("Failed to link virtual include roots in {}: {error}", folder.display());
+                None
+            },
+        };
+        linked.insert(key, folder.clone());
+        folder
+    }
+}
+
+fn roots_key(
+    roots: &BTreeMap<String, String>,
+    workspace_root: Option<&Path>,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    roots.hash(&mut hasher);
+    workspace_root.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Recreate `folder` with a link named by each prefix in `roots` to its
+/// directory. Relative directories are resolved against `workspace_root` and
+/// skipped without one, as are prefixes that would leave `folder`.
+///
+/// Links to directories that do not exist yet are still created, so output
+/// of a code generator that runs later is found once it appears.
+pub(crate) fn link_roots(
+    folder: &Path,
+    roots: &BTreeMap<String, String>,
+    workspace_root: Option<&Path>,
+) -> std::io::Result<()> {
+    if folder.exists() {
+        std::fs::remove_dir_all(folder)?;
+    }
+    std::fs::create_dir_all(folder)?;
+    for (prefix, target) in roots {
+        if !Path::new(prefix).components().all(|component| matches!(component, Component::Normal(_))) {
+            debug!("Skipping virtual include root {prefix:?}: not a relative path");
+            continue;
+        }
+        let target = Path::new(target);
+        let target = match workspace_root {
+            _ if target.is_absolute() => target.to_path_buf(),
+            Some(root) => root.join(target),
+            None => continue,
+        };
+        let link = folder.join(prefix);
+        if let Some(parent) = link.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        symlink_dir(&target, &link)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink_dir(
+    target: &Path,
+    link: &Path,
+) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink_dir(
+    target: &Path,
+    link: &Path,
+) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_dir(target, link)
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/metal/virtual_includes_tests.rs"]
+mod tests;
@@ -0,0 +1,205 @@
+//! Latency histograms and counters behind `metal-analyzer/perf`.
+//!
+//! Requests, go-to-definition by the tier that answered it, AST dumps and
+//! diagnostics compiles record how long they took under a name; AST index
+//! loads count where the index came from, giving the cache hit rates. The
+//! report is meant to be attached to performance bug reports, and can also
+//! be logged periodically through `logging.perfIntervalSecs`.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        LazyLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use tracing::{debug, info};
+
+use crate::server::ext::{HistogramBucket, LatencyHistogram, PerfReport};
+
+/// Upper bounds of the histogram buckets in milliseconds; a last bucket
+/// holds everything slower.
+const BUCKET_BOUNDS_MS: [u64; 13] = [1, 2, 5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+struct Metrics {
+    since: std::sync::RwLock<Instant>,
+    histograms: DashMap<&'static str, Histogram>,
+    counters: DashMap<&'static str, AtomicU64>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            since: std::sync::RwLock::new(Instant::now()),
+            histograms: DashMap::new(),
+            counters: DashMap::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [AtomicU64; BUCKET_BOUNDS_MS.len() + 1],
+    count: AtomicU64,
+    total_us: AtomicU64,
+    max_us: AtomicU64,
+}
+
+/// Times an LSP request until dropped.
+pub(crate) struct RequestTimer {
+    method: &'static str,
+    started: Instant,
+}
+
+/// Start timing the request `method`, e.g. `textDocument/hover`.
+pub(crate) fn request(method: &'static str) -> RequestTimer {
+    RequestTimer {
+        method,
+        started: Instant::now(),
+    }
+}
+
+impl Drop for RequestTimer {
+    fn drop(&mut self) {
+        let elapsed = self.started.elapsed();
+        record(self.method, elapsed);
+        debug!(
+            target: "metal_analyzer::rpc",
+            method = self.method,
+            elapsed_ms = elapsed.as_secs_f64() * 1000.0,
+            "request handled"
+        );
+    }
+}
+
+/// Add a sample of how long `name` took.
+pub(crate) fn record(
+    name: &'static str,
+    elapsed: Duration,
+) {
+    let elapsed_us = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+    let elapsed_ms = elapsed_us / 1000;
+    let bucket = BUCKET_BOUNDS_MS.iter().position(|bound| elapsed_ms < *bound).unwrap_or(BUCKET_BOUNDS_MS.len());
+    let histogram = METRICS.histograms.entry(name).or_default();
+    histogram.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+    histogram.count.fetch_add(1, Ordering::Relaxed);
+    histogram.total_us.fetch_add(elapsed_us, Ordering::Relaxed);
+    histogram.max_us.fetch_max(elapsed_us, Ordering::Relaxed);
+}
+
+/// Count one occurrence of `name`.
+pub(crate) fn count(name: &'static str) {
+    METRICS.counters.entry(name).or_default().fetch_add(1, Ordering::Relaxed);
+}
+
+/// Everything recorded since startup or the last reset, with `reset`
+/// starting over afterwards.
+pub(crate) fn report(reset: bool) -> PerfReport {
+    let since = METRICS.since.read().map(|since| *since).unwrap_or_else(|_| Instant::now());
+    let mut latencies: Vec<LatencyHistogram> =
+        METRICS.histograms.iter().map(|entry| summarize(entry.key(), entry.value())).collect();
+    latencies.sort_by(|a, b| a.name.cmp(&b.name));
+    let counters: BTreeMap<String, u64> =
+        METRICS.counters.iter().map(|entry| (entry.key().to_string(), entry.value().load(Ordering::Relaxed))).collect();
+    if reset {
+        METRICS.histograms.clear();
+        METRICS.counters.clear();
+        if let Ok(mut since) = METRICS.since.write() {
+            *since = Instant::now();
+        }
+    }
+    PerfReport {
+        elapsed_secs: since.elapsed().as_secs(),
+        latencies,
+        counters,
+    }
+}
+
+/// Log one line per latency histogram and one for the counters.
+pub(crate) fn log_summary() {
+    let report = report(false);
+    if report.latencies.is_empty() && report.counters.is_empty() {
+        info!("[perf] nothing recorded yet");
+        return;
+    }
+    for latency in &report.latencies {
+        info!(
+            "[perf] {}: count={}, mean_ms={:.2}, p50_ms<={}, p90_ms<={}, p99_ms<={}, max_ms={:.2}",
+            latency.name,
+            latency.count,
+            latency.mean_ms,
+            bound_text(latency.p50_ms),
+            bound_text(latency.p90_ms),
+            bound_text(latency.p99_ms),
+            latency.max_ms,
+        );
+    }
+    if !report.counters.is_empty() {
+        let counters: Vec<String> = report.counters.iter().map(|(name, count)| format!("{name}={count}")).collect();
+        info!("[perf] counters: {}", counters.join(", "));
+    }
+}
+
+fn bound_text(bound: Option<u64>) -> String {
+    bound.map_or_else(|| "inf".to_string(), |bound| bound.to_string())
+}
+
+fn summarize(
+    name: &str,
+    histogram: &Histogram,
+) -> LatencyHistogram {
+    let counts: Vec<u64> = histogram.buckets.iter().map(|bucket| bucket.load(Ordering::Relaxed)).collect();
+    let count = histogram.count.load(Ordering::Relaxed);
+    let total_us = histogram.total_us.load(Ordering::Relaxed);
+    LatencyHistogram {
+        name: name.to_string(),
+        count,
+        mean_ms: if count == 0 {
+            0.0
+        } else {
+            total_us as f64 / count as f64 / 1000.0
+        },
+        max_ms: histogram.max_us.load(Ordering::Relaxed) as f64 / 1000.0,
+        p50_ms: percentile_bound(&counts, 0.5),
+        p90_ms: percentile_bound(&counts, 0.9),
+        p99_ms: percentile_bound(&counts, 0.99),
+        buckets: counts
+            .iter()
+            .enumerate()
+            .map(|(index, count)| HistogramBucket {
+                under_ms: BUCKET_BOUNDS_MS.get(index).copied(),
+                count: *count,
+            })
+            .collect(),
+    }
+}
+
+/// Upper bound of the bucket holding the `quantile` of the samples in
+/// `counts`; `None` for the open last bucket or no samples.
+fn percentile_bound(
+    counts: &[u64],
+    quantile: f64,
+) -> Option<u64> {
+    let total: u64 = counts.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let rank = ((total as f64) * quantile).ceil().max(1.0) as u64;
+    let mut seen = 0;
+    for (index, count) in counts.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return BUCKET_BOUNDS_MS.get(index).copied();
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+#[path = "../tests/src/perf_tests.rs"]
+mod tests;
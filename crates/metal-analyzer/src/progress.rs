@@ -12,13 +12,26 @@
 //! token.report(Some("parsing…".into()), Some(50)).await;
 //! token.end(Some("done".into())).await;
 //! ```
+//!
+//! Sessions started with [`ProgressToken::begin_cancellable`] show a cancel
+//! button; the client's `window/workDoneProgress/cancel` sets a flag that the
+//! work polls through [`ProgressToken::is_cancelled`].
 
-use std::panic::AssertUnwindSafe;
+use std::{
+    panic::AssertUnwindSafe,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
 
+use dashmap::DashMap;
 use futures::FutureExt;
 use tower_lsp::{Client, lsp_types::*};
 use tracing::{debug, warn};
 
+use crate::server::cancellation::CancellationToken;
+
 const PROGRESS_TITLE_PREFIX: &str = "metal-analyzer:";
 
 /// A handle to an active work-done progress session.
@@ -33,6 +46,71 @@ const PROGRESS_TITLE_PREFIX: &str = "metal-analyzer:";
 pub struct ProgressToken {
     client: Option<Client>,
     token: Option<NumberOrString>,
+    cancellation: Option<Cancellation>,
+}
+
+/// The cancellation flag of a cancellable session and the registry it is
+/// listed in until the session ends.
+struct Cancellation {
+    registry: Arc<ProgressCancellations>,
+    token: NumberOrString,
+    flag: Arc<AtomicBool>,
+}
+
+/// Cancellation flags of the cancellable progress sessions in flight, keyed
+/// by token.
+#[derive(Debug, Default)]
+pub struct ProgressCancellations {
+    flags: DashMap<NumberOrString, Arc<AtomicBool>>,
+    next_id: AtomicU64,
+}
+
+impl ProgressCancellations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flag the session with `token` as cancelled. Returns `false` when no
+    /// such session is in flight, e.g. because it already ended.
+    pub fn cancel(
+        &self,
+        token: &NumberOrString,
+    ) -> bool {
+        match self.flags.get(token) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Number of cancellable sessions in flight.
+    pub fn len(&self) -> usize {
+        self.flags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty()
+    }
+
+    /// List a new session under a token unique to this registry.
+    fn register(
+        &self,
+        title: &str,
+    ) -> (NumberOrString, Arc<AtomicBool>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let token = NumberOrString::String(format!("metal-analyzer: {title} #{id}"));
+        let flag = Arc::new(AtomicBool::new(false));
+        self.flags.insert(token.clone(), flag.clone());
+        (token, flag)
+    }
+}
+
+impl Drop for Cancellation {
+    fn drop(&mut self) {
+        self.registry.flags.remove(&self.token);
+    }
 }
 
 impl ProgressToken {
@@ -49,7 +127,38 @@ impl ProgressToken {
         message: Option<String>,
     ) -> Self {
         let token = NumberOrString::String(format!("metal-analyzer: {title}"));
+        Self::start(client, token, title, message, None).await
+    }
+
+    /// Start a progress session the user can cancel from the editor.
+    ///
+    /// The session is listed in `registry` until it ends, so the server's
+    /// `window/workDoneProgress/cancel` handler can flag it through
+    /// [`ProgressCancellations::cancel`].
+    pub async fn begin_cancellable(
+        client: &Client,
+        registry: &Arc<ProgressCancellations>,
+        title: &str,
+        message: Option<String>,
+    ) -> Self {
+        let (token, flag) = registry.register(title);
+        let cancellation = Cancellation {
+            registry: registry.clone(),
+            token: token.clone(),
+            flag,
+        };
+        Self::start(client, token, title, message, Some(cancellation)).await
+    }
+
+    async fn start(
+        client: &Client,
+        token: NumberOrString,
+        title: &str,
+        message: Option<String>,
+        cancellation: Option<Cancellation>,
+    ) -> Self {
         let display_title = prefixed_progress_title(title);
+        let cancellable = cancellation.is_some();
 
         // Send workDoneProgress/create as a background task so that:
         // 1. We don't block if the editor is slow to respond.
@@ -81,7 +190,7 @@ impl ProgressToken {
             token: token.clone(),
             value: ProgressParamsValue::WorkDone(WorkDoneProgress::Begin(WorkDoneProgressBegin {
                 title: display_title.clone(),
-                cancellable: Some(false),
+                cancellable: Some(cancellable),
                 message,
                 percentage: None,
             })),
@@ -94,6 +203,7 @@ impl ProgressToken {
             return Self {
                 client: None,
                 token: None,
+                cancellation,
             };
         }
 
@@ -102,9 +212,26 @@ impl ProgressToken {
         Self {
             client: Some(client.clone()),
             token: Some(token),
+            cancellation,
         }
     }
 
+    /// Whether the user cancelled the session. Always `false` for sessions
+    /// started with [`begin`](Self::begin).
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.as_ref().is_some_and(|cancellation| cancellation.flag.load(Ordering::Relaxed))
+    }
+
+    /// A token following [`is_cancelled`](Self::is_cancelled), for work
+    /// moved onto other tasks.
+    pub(crate) fn cancellation_token(&self) -> CancellationToken {
+        let flag = match &self.cancellation {
+            Some(cancellation) => cancellation.flag.clone(),
+            None => Arc::new(AtomicBool::new(false)),
+        };
+        CancellationToken::from_flag(flag)
+    }
+
     /// Send an intermediate progress update.
     ///
     /// `percentage` should be in `0..=100`.
@@ -120,7 +247,7 @@ impl ProgressToken {
             let _ = AssertUnwindSafe(client.send_notification::<notification::Progress>(ProgressParams {
                 token: token.clone(),
                 value: ProgressParamsValue::WorkDone(WorkDoneProgress::Report(WorkDoneProgressReport {
-                    cancellable: Some(false),
+                    cancellable: Some(self.cancellation.is_some()),
                     message,
                     percentage,
                 })),
@@ -1,39 +1,85 @@
+use tower_lsp::lsp_types::SemanticTokenType;
+
 use crate::{
-    definition::AstIndex,
-    semantic_tokens::{RawToken, mapping::map_ast_kind_to_token_type},
+    definition::{AstIndex, RefSite},
+    semantic_tokens::{LineIndex, MACRO_EXPANSION_BIT, RawToken, mapping::map_ast_kind_to_token_type},
 };
 
+/// Tokens of the declarations and references `index` has in `path`, whose
+/// text is `source`.
 pub(crate) fn tokens_from_ast_index(
     index: &AstIndex,
     path: &str,
+    source: &str,
 ) -> Vec<RawToken> {
+    let line_index = LineIndex::new(source);
     let mut tokens = Vec::new();
 
     for def in &index.defs {
         if def.file == path
             && let Some(token_type) = map_ast_kind_to_token_type(&def.kind)
         {
+            let line = def.line.saturating_sub(1);
+            let (col, length) = line_index.span(line, def.col.saturating_sub(1), def.name.len() as u32);
             tokens.push(RawToken {
-                line: def.line.saturating_sub(1),
-                col: def.col.saturating_sub(1),
-                length: def.name.len() as u32,
+                line,
+                col,
+                length,
                 token_type,
+                modifiers: 0,
             });
         }
     }
 
     for r in &index.refs {
+        let from_macro_body = spelled_in_macro_body(r);
         if r.file == path
             && let Some(token_type) = map_ast_kind_to_token_type(&r.target_kind)
         {
+            let line = r.line.saturating_sub(1);
+            let (col, length) = line_index.span(line, r.col.saturating_sub(1), r.tok_len);
             tokens.push(RawToken {
-                line: r.line.saturating_sub(1),
-                col: r.col.saturating_sub(1),
-                length: r.tok_len,
+                line,
+                col,
+                length,
                 token_type,
+                modifiers: if from_macro_body {
+                    MACRO_EXPANSION_BIT
+                } else {
+                    0
+                },
+            });
+        }
+        // The invocation that produced the token, such as `CLAMP01` in
+        // `CLAMP01(x)`.
+        if from_macro_body && let Some(expansion) = r.expansion.as_ref().filter(|expansion| expansion.file == path) {
+            let line = expansion.line.saturating_sub(1);
+            let (col, length) = line_index.span(line, expansion.col.saturating_sub(1), expansion.tok_len);
+            tokens.push(RawToken {
+                line,
+                col,
+                length,
+                token_type: SemanticTokenType::MACRO,
+                modifiers: MACRO_EXPANSION_BIT,
             });
         }
     }
 
     tokens
 }
+
+/// Whether a reference is spelled inside a `#define` rather than written at
+/// the macro invocation.
+///
+/// A macro is defined before it is used, so spelling that follows the
+/// expansion in the same file is a macro argument, written by hand.
+fn spelled_in_macro_body(r: &RefSite) -> bool {
+    let Some(expansion) = &r.expansion else {
+        return false;
+    };
+    expansion.file != r.file || (r.line, r.col) < (expansion.line, expansion.col)
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/semantic_tokens/ast_tokens_tests.rs"]
+mod tests;
@@ -6,7 +6,9 @@ pub(crate) mod mapping;
 pub(crate) mod provider;
 pub(crate) mod syntactic;
 
-use tower_lsp::lsp_types::{SemanticToken, SemanticTokenType, SemanticTokensLegend};
+use tower_lsp::lsp_types::{SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokensLegend};
+
+use crate::text_pos::column_of_byte_offset;
 
 pub use self::provider::SemanticTokenProvider;
 
@@ -35,10 +37,17 @@ pub const LEGEND_TYPES: &[SemanticTokenType] = &[
     SemanticTokenType::OPERATOR,
 ];
 
+/// Modifier on macro invocations and on tokens spelled in a macro body,
+/// marking code produced by an expansion rather than written in place.
+pub const MACRO_EXPANSION_MODIFIER: &str = "macroExpansion";
+
+/// Bits of [`RawToken::modifiers`], in legend order.
+pub(crate) const MACRO_EXPANSION_BIT: u32 = 1 << 0;
+
 pub fn get_legend() -> SemanticTokensLegend {
     SemanticTokensLegend {
         token_types: LEGEND_TYPES.into(),
-        token_modifiers: vec![],
+        token_modifiers: vec![SemanticTokenModifier::new(MACRO_EXPANSION_MODIFIER)],
     }
 }
 
@@ -49,17 +58,18 @@ pub(crate) struct RawToken {
     pub(crate) col: u32,
     pub(crate) length: u32,
     pub(crate) token_type: SemanticTokenType,
+    pub(crate) modifiers: u32,
 }
 
-/// Fast mapping from byte offsets to (line, column).
-///
-/// Note: columns are byte-based (sufficient for typical Metal code).
-pub(crate) struct LineIndex {
+/// Fast mapping from byte offsets to (line, column), with columns in the
+/// negotiated position encoding.
+pub(crate) struct LineIndex<'a> {
+    source: &'a str,
     line_starts: Box<[usize]>,
 }
 
-impl LineIndex {
-    pub(crate) fn new(source: &str) -> Self {
+impl<'a> LineIndex<'a> {
+    pub(crate) fn new(source: &'a str) -> Self {
         let mut starts = Vec::with_capacity(source.len() / 40);
         starts.push(0usize);
         for (i, b) in source.bytes().enumerate() {
@@ -68,10 +78,21 @@ impl LineIndex {
             }
         }
         Self {
+            source,
             line_starts: starts.into_boxed_slice(),
         }
     }
 
+    /// Text of the 0-based `line`, without its line break.
+    fn line_text(
+        &self,
+        line: usize,
+    ) -> Option<&'a str> {
+        let start = *self.line_starts.get(line)?;
+        let end = self.line_starts.get(line + 1).map_or(self.source.len(), |next| next - 1);
+        Some(&self.source[start..end])
+    }
+
     pub(crate) fn line_col(
         &self,
         byte_offset: usize,
@@ -82,7 +103,28 @@ impl LineIndex {
             Err(ins) => ins.saturating_sub(1),
         };
         let col = off.saturating_sub(self.line_starts[line]);
-        (line as u32, col as u32)
+        let col = self.line_text(line).map_or(col as u32, |text| column_of_byte_offset(text, col));
+        (line as u32, col)
+    }
+
+    /// Column and length of the token `byte_len` bytes long at the 0-based
+    /// `byte_col` on `line`. Tokens the line does not hold, as when the
+    /// text changed since Clang reported them, keep their byte values.
+    pub(crate) fn span(
+        &self,
+        line: u32,
+        byte_col: u32,
+        byte_len: u32,
+    ) -> (u32, u32) {
+        let start = byte_col as usize;
+        let end = start + byte_len as usize;
+        match self.line_text(line as usize) {
+            Some(text) if end <= text.len() => {
+                let col = column_of_byte_offset(text, start);
+                (col, column_of_byte_offset(text, end) - col)
+            },
+            _ => (byte_col, byte_len),
+        }
     }
 }
 
@@ -122,7 +164,7 @@ pub(crate) fn encode_delta(mut tokens: Vec<RawToken>) -> Vec<SemanticToken> {
             delta_start: delta_col,
             length: tok.length,
             token_type: mapping::get_token_type_index(tok.token_type),
-            token_modifiers_bitset: 0,
+            token_modifiers_bitset: tok.modifiers,
         });
 
         prev_line = tok.line;
@@ -6,6 +6,7 @@ use crate::{
         RawToken, ast_tokens::tokens_from_ast_index, encode_delta, merge_tokens, syntactic::syntactic_tokens,
     },
     syntax::SyntaxTree,
+    vfs::virtual_docs::document_path,
 };
 
 /// Semantic token provider with two tiers: instant rowan syntactic tokens
@@ -33,8 +34,9 @@ impl SemanticTokenProvider {
         };
 
         if let Some(index) = self.definition_provider.get_cached_index(uri) {
-            let path = uri.to_file_path().ok().map(|p| p.display().to_string()).unwrap_or_default();
-            let ast_tokens = tokens_from_ast_index(&index, &path);
+            let path = document_path(uri).map(|p| p.display().to_string()).unwrap_or_default();
+            let source = snapshot.map_or("", SyntaxTree::source);
+            let ast_tokens = tokens_from_ast_index(&index, &path, source);
             merge_tokens(&mut raw_tokens, &ast_tokens);
         }
 
@@ -7,6 +7,7 @@ use crate::{
         ast::{self, AstNode},
         queries::{TokenClass, classify_token},
     },
+    text_pos::text_width,
 };
 
 pub(crate) fn syntactic_tokens(snapshot: &SyntaxTree) -> Vec<RawToken> {
@@ -79,7 +80,7 @@ pub(crate) fn syntactic_tokens(snapshot: &SyntaxTree) -> Vec<RawToken> {
 
 pub(crate) fn raw_token_from_range(
     range: rowan::TextRange,
-    line_index: &LineIndex,
+    line_index: &LineIndex<'_>,
     token_type: SemanticTokenType,
 ) -> RawToken {
     let start: usize = range.start().into();
@@ -88,7 +89,8 @@ pub(crate) fn raw_token_from_range(
     RawToken {
         line,
         col,
-        length: (end - start) as u32,
+        length: text_width(&line_index.source[start..end]),
         token_type,
+        modifiers: 0,
     }
 }
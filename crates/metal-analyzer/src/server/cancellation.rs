@@ -0,0 +1,59 @@
+//! Cancellation of requests whose work runs on blocking threads.
+//!
+//! tower-lsp answers `$/cancelRequest` by dropping the request's future,
+//! which cannot stop a blocking task the future awaits. The future holds a
+//! [`RequestCancellation`] instead; dropping it raises a flag the blocking
+//! work polls through its [`CancellationToken`].
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// Cancels its tokens when dropped.
+pub(crate) struct RequestCancellation {
+    flag: Arc<AtomicBool>,
+}
+
+#[derive(Clone)]
+pub(crate) struct CancellationToken {
+    flag: Arc<AtomicBool>,
+}
+
+impl RequestCancellation {
+    pub(crate) fn new() -> Self {
+        Self {
+            flag: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub(crate) fn token(&self) -> CancellationToken {
+        CancellationToken {
+            flag: Arc::clone(&self.flag),
+        }
+    }
+}
+
+impl Drop for RequestCancellation {
+    fn drop(&mut self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+}
+
+impl CancellationToken {
+    /// A token raised through `flag`, for cancellation that does not come
+    /// from dropping a request, such as a cancelled progress session.
+    pub(crate) fn from_flag(flag: Arc<AtomicBool>) -> Self {
+        Self {
+            flag,
+        }
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/server/cancellation_tests.rs"]
+mod tests;
@@ -0,0 +1,102 @@
+//! Command-line diagnostics behind `metal-analyzer check`.
+//!
+//! Compiles workspace `.metal` files with the compiler settings and include
+//! paths the server would use and renders the results in the familiar
+//! `file:line:col: severity: message` form, so compile errors can be surfaced
+//! from a terminal or CI job without an editor.
+
+use std::path::{Path, PathBuf};
+
+use futures::StreamExt;
+use tower_lsp::lsp_types::{DiagnosticSeverity, Url};
+
+use crate::{
+    metal::compiler::{MetalCompiler, MetalDiagnostic},
+    server::{
+        diagnostics::{compute_include_paths_for, discover_workspace_files, should_suppress_primary_diagnostic},
+        header_owners::is_header_file,
+        settings::ServerSettings,
+        state::configure_compiler,
+    },
+    vfs::glob::PathExclusions,
+};
+
+/// A compiler configured from the `compiler.*` settings.
+pub async fn configured_compiler(settings: &ServerSettings) -> MetalCompiler {
+    let compiler = MetalCompiler::new();
+    configure_compiler(&compiler, &settings.compiler);
+    compiler.ensure_system_includes_ready().await;
+    compiler
+}
+
+/// `.metal` sources and headers under `workspace_roots` whose changes should
+/// trigger a re-check in watch mode.
+pub fn watched_sources(
+    workspace_roots: &[PathBuf],
+    settings: &ServerSettings,
+) -> Vec<PathBuf> {
+    let exclusions = PathExclusions::new(workspace_roots, &settings.indexing.exclude_paths);
+    discover_workspace_files(
+        workspace_roots,
+        settings.indexing.max_file_size_bytes(),
+        &exclusions,
+        settings.indexing.respect_gitignore,
+        |path| path.extension().is_some_and(|ext| ext == "metal") || is_header_file(path),
+    )
+}
+
+/// Compile `path` and return its diagnostics, including those reported in
+/// the headers it includes and the notes attached to them.
+pub async fn check_file(
+    compiler: &MetalCompiler,
+    workspace_roots: &[PathBuf],
+    path: &Path,
+) -> std::io::Result<Vec<MetalDiagnostic>> {
+    let source = tokio::fs::read_to_string(path).await?;
+    let uri = Url::from_file_path(path)
+        .map_err(|()| std::io::Error::new(std::io::ErrorKind::InvalidInput, "not an absolute path"))?;
+    let include_paths = compute_include_paths_for(&path.to_path_buf(), workspace_roots, compiler);
+    let mut diagnostics = compiler.compile_with_include_paths(&source, uri.as_str(), &include_paths).await;
+    diagnostics.retain(|diag| !should_suppress_primary_diagnostic(diag));
+    Ok(diagnostics)
+}
+
+/// Run [`check_file`] on `files` with up to `concurrency` compiles in
+/// flight, returning the results in the order of `files`.
+pub async fn check_files(
+    compiler: &MetalCompiler,
+    workspace_roots: &[PathBuf],
+    files: &[PathBuf],
+    concurrency: usize,
+) -> Vec<(PathBuf, std::io::Result<Vec<MetalDiagnostic>>)> {
+    futures::stream::iter(files)
+        .map(|path| async move { (path.clone(), check_file(compiler, workspace_roots, path).await) })
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+/// Render `diag` as `file:line:col: severity: message` with one-based
+/// positions, using `path` when the compiler reported no file.
+pub fn render_diagnostic(
+    diag: &MetalDiagnostic,
+    path: &Path,
+) -> String {
+    let severity = match diag.severity {
+        DiagnosticSeverity::ERROR => "error",
+        DiagnosticSeverity::WARNING => "warning",
+        DiagnosticSeverity::INFORMATION => "note",
+        _ => "hint",
+    };
+    let file = diag.file.clone().unwrap_or_else(|| path.display().to_string());
+    format!("{file}:{}:{}: {severity}: {}", diag.line + 1, diag.column + 1, diag.message)
+}
+
+/// Whether any of `diagnostics` is an error.
+pub fn has_errors(diagnostics: &[MetalDiagnostic]) -> bool {
+    diagnostics.iter().any(|diag| diag.severity == DiagnosticSeverity::ERROR)
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/server/check_tests.rs"]
+mod tests;
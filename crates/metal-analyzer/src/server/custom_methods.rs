@@ -0,0 +1,1246 @@
+//! Handlers for the `metal-analyzer/*` protocol extensions defined in
+//! [`crate::server::ext`].
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{Arc, atomic::Ordering},
+};
+
+use tower_lsp::{
+    LspServiceBuilder,
+    jsonrpc::Result,
+    lsp_types::{
+        Diagnostic, DocumentLink, GotoDefinitionResponse, Hover, HoverContents, Location, MarkupContent, MarkupKind,
+        Position, Range, TextDocumentIdentifier, TextDocumentPositionParams, Url, WorkDoneProgressCancelParams,
+        notification::{Notification, WorkDoneProgressCancel},
+        request::Request,
+    },
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::generate_json_schema,
+    definition::{
+        cache_view::{CACHE_VIEW_SCHEME, CacheViewLink, cache_view_source_uri, cache_view_uri, render_cache_view},
+        def_to_location, include_graph,
+    },
+    document::{AnalysisSnapshot, ContentHash},
+    hover::{include::make_include_hover, macro_expansion::make_macro_hover},
+    ide::{
+        bindings::{BindingSlot, binding_slot_at_position, find_binding_sites},
+        entry_points::{CallGraph, PipelineIndex, enclosing_function_name, entry_point_details, entry_points},
+        inactive_regions::inactive_regions,
+        lsp::{ide_location_to_lsp, navigation_target_to_lsp},
+        macros::{MacroIndex, expand_invocation, macro_invocation_at},
+        todos::find_todos,
+    },
+    metal::{compiler::MetalCompiler, kernel_stats, preprocess::PreprocessedSource, toolchain::ir_functions},
+    perf,
+    progress::ProgressToken,
+    server::{
+        cancellation::RequestCancellation,
+        diagnostics::discover_workspace_files,
+        ext::{
+            AstCacheView, AstCacheViewDocument, AstCacheViewParams, BatchPositionsParams, BinaryDiagnostic,
+            BinaryEntryPoint, BinaryFile, BindingUse, BindingUses, BindingUsesParams, BindingUsesScope, CacheStatus,
+            CaptureState, CapturedState, ClearCache, CompileBinary, CompileBinaryParams, CompiledBinaryReport,
+            ConfigurationSchema, DefinitionRanking, Definitions, Disassemble, Disassembly, EnclosingEntryPoint,
+            EnclosingEntryPoints, EntryPointBinding, EntryPointInfo, EntryPointStats, EntryPoints, EntryPointsParams,
+            ExpandMacro, ExpandedMacro, ExplainDefinitionRanking, Hovers, IncludeGraph, IncludeGraphEdge,
+            IncludeGraphFile, IncludeGraphParams, IncludeGraphResult, IrFunctionInfo, IrSourceLine, KernelStats,
+            KernelStatsParams, Perf, PerfParams, PerfReport, Preprocess, PreprocessParams, PreprocessedDocument,
+            PreprocessedLineOrigin, RankPenalty, RankedDefinition, RebuildFile, RebuildFileParams, Reindex,
+            ReindexParams, SarifLog, SarifLogParams, ServerStatus, Status, TodoItem, Todos, TodosParams,
+        },
+        header_owners::{include_candidates, is_header_file, normalize_path},
+        lint,
+        sarif::{SarifResult, compiler_rules, sarif_log},
+        state::MetalLanguageServer,
+    },
+    syntax::SyntaxTree,
+    vfs::glob::PathExclusions,
+};
+
+/// Cap on the project files read for `metal-analyzer/includeGraph`.
+const MAX_GRAPH_FILES: usize = 4096;
+
+impl MetalLanguageServer {
+    /// Register every `metal-analyzer/*` extension method on `builder`, along
+    /// with the standard notifications tower-lsp does not route itself.
+    pub fn with_custom_methods(builder: LspServiceBuilder<Self>) -> LspServiceBuilder<Self> {
+        builder
+            .custom_method(WorkDoneProgressCancel::METHOD, Self::work_done_progress_cancel)
+            .custom_method(BindingUses::METHOD, Self::binding_uses)
+            .custom_method(EnclosingEntryPoints::METHOD, Self::enclosing_entry_points)
+            .custom_method(AstCacheView::METHOD, Self::ast_cache_view)
+            .custom_method(ExplainDefinitionRanking::METHOD, Self::explain_definition_ranking)
+            .custom_method(ExpandMacro::METHOD, Self::expand_macro)
+            .custom_method(Definitions::METHOD, Self::definitions)
+            .custom_method(Hovers::METHOD, Self::hovers)
+            .custom_method(Todos::METHOD, Self::todos)
+            .custom_method(EntryPoints::METHOD, Self::entry_points)
+            .custom_method(IncludeGraph::METHOD, Self::include_graph)
+            .custom_method(RebuildFile::METHOD, Self::rebuild_file)
+            .custom_method(Reindex::METHOD, Self::reindex)
+            .custom_method(ClearCache::METHOD, Self::clear_cache)
+            .custom_method(Status::METHOD, Self::status)
+            .custom_method(Perf::METHOD, Self::perf)
+            .custom_method(CaptureState::METHOD, Self::capture_state)
+            .custom_method(ConfigurationSchema::METHOD, Self::configuration_schema)
+            .custom_method(SarifLog::METHOD, Self::sarif_log)
+            .custom_method(Preprocess::METHOD, Self::preprocess)
+            .custom_method(CompileBinary::METHOD, Self::compile_binary)
+            .custom_method(KernelStats::METHOD, Self::kernel_stats)
+            .custom_method(Disassemble::METHOD, Self::disassemble)
+    }
+
+    pub(crate) async fn binding_uses(
+        &self,
+        params: BindingUsesParams,
+    ) -> Result<Option<Vec<BindingUse>>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        let Some(text) = self.document_store.get_content(&uri) else {
+            return Ok(None);
+        };
+        let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
+        let Some(slot) = binding_slot_at_position(&tree.root(), &text, position) else {
+            return Ok(None);
+        };
+
+        let mut uses = binding_uses_in_source(&uri, &tree, &slot);
+        if params.scope == BindingUsesScope::File {
+            return Ok(Some(uses));
+        }
+
+        let mut visited = HashSet::from([uri.clone()]);
+        for open_uri in self.document_store.all_uris() {
+            if !visited.insert(open_uri.clone()) {
+                continue;
+            }
+            let Some(open_tree) = self.document_trees.get(&open_uri) else {
+                continue;
+            };
+            uses.extend(binding_uses_in_source(&open_uri, &open_tree, &slot));
+        }
+
+        let needle = format!("{}(", slot.name);
+        for path in self.workspace_shader_sources().await {
+            let Ok(file_uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            if !visited.insert(file_uri.clone()) {
+                continue;
+            }
+            let Ok(source) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            if !source.contains(&needle) {
+                continue;
+            }
+            uses.extend(binding_uses_in_source(&file_uri, &SyntaxTree::parse(&source), &slot));
+        }
+
+        Ok(Some(uses))
+    }
+
+    pub(crate) async fn enclosing_entry_points(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<Vec<EnclosingEntryPoint>>> {
+        let uri = params.text_document.uri;
+        let Some(text) = self.document_store.get_content(&uri) else {
+            return Ok(None);
+        };
+        let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
+        let Some(function) = enclosing_function_name(&tree.root(), &text, params.position) else {
+            return Ok(None);
+        };
+
+        let mut trees: HashMap<PathBuf, SyntaxTree> = HashMap::new();
+        let mut graph = CallGraph::new();
+        graph.add_source(&uri, &tree);
+        if let Ok(path) = uri.to_file_path() {
+            trees.insert(path, tree);
+        }
+
+        let mut visited = HashSet::from([uri]);
+        for open_uri in self.document_store.all_uris() {
+            if !visited.insert(open_uri.clone()) {
+                continue;
+            }
+            let Some(open_tree) = self.document_trees.get(&open_uri) else {
+                continue;
+            };
+            graph.add_source(&open_uri, &open_tree);
+            if let Ok(path) = open_uri.to_file_path() {
+                trees.insert(path, open_tree);
+            }
+        }
+
+        for path in self.workspace_shader_sources().await {
+            let Ok(file_uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            if !visited.insert(file_uri.clone()) {
+                continue;
+            }
+            let Ok(source) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            let file_tree = SyntaxTree::parse(&source);
+            graph.add_source(&file_uri, &file_tree);
+            trees.insert(path, file_tree);
+        }
+
+        for (file, refs) in self.definition_provider.project_index().function_references_by_file() {
+            if let Some(file_tree) = trees.get(&PathBuf::from(file)) {
+                graph.add_resolved_calls(file_tree, &refs);
+            }
+        }
+
+        let entry_points = graph
+            .entry_points_reaching(&function)
+            .into_iter()
+            .map(|reached| EnclosingEntryPoint {
+                name: reached.entry.name,
+                stage: reached.entry.stage.unwrap_or_default().to_string(),
+                location: reached.entry.location,
+                call_path: reached.path,
+            })
+            .collect();
+        Ok(Some(entry_points))
+    }
+
+    pub(crate) async fn ast_cache_view(
+        &self,
+        params: AstCacheViewParams,
+    ) -> Result<Option<AstCacheViewDocument>> {
+        let uri = params.text_document.uri;
+        let (Ok(path), Some(view_uri)) = (uri.to_file_path(), cache_view_uri(&uri)) else {
+            return Ok(None);
+        };
+        let Some(index) = self.definition_provider.get_cached_index(&uri) else {
+            return Ok(None);
+        };
+
+        let view = render_cache_view(&index, &path, &self.definition_provider.ranking_weights());
+        let document = AstCacheViewDocument {
+            uri: view_uri.clone(),
+            content: view.content.clone(),
+            links: view.links.iter().map(cache_view_document_link).collect(),
+        };
+        // Drop the views of documents closed since they were rendered.
+        self.ast_cache_views.retain(|view_uri, _| {
+            cache_view_source_uri(view_uri).is_some_and(|source| self.document_store.contains(&source))
+        });
+        self.ast_cache_views.insert(view_uri, view);
+        Ok(Some(document))
+    }
+
+    pub(crate) async fn rebuild_file(
+        &self,
+        params: RebuildFileParams,
+    ) -> Result<bool> {
+        let uri = params.text_document.uri;
+        let Some(document) = self.document_store.get(&uri) else {
+            return Ok(false);
+        };
+        if let Ok(path) = uri.to_file_path() {
+            self.definition_provider.invalidate_file(&path);
+            let cache_key = path.canonicalize().unwrap_or(path);
+            self.include_paths_cache.remove(&cache_key);
+        }
+
+        let settings = self.settings_snapshot().await;
+        if settings.indexing.enable {
+            let provider = self.definition_provider.clone();
+            let includes = self.include_paths(&uri).await;
+            let _ = tokio::task::spawn_blocking(move || provider.index_open_document(&document, &includes)).await;
+        }
+        self.run_diagnostics(&uri).await;
+        Ok(true)
+    }
+
+    pub(crate) async fn reindex(
+        &self,
+        params: ReindexParams,
+    ) -> Result<usize> {
+        let Some(text_document) = params.text_document else {
+            let workspace_roots: Vec<PathBuf> =
+                self.workspace_roots.read().await.iter().filter_map(|f| f.uri.to_file_path().ok()).collect();
+            let removed = self.definition_provider.clear_caches(&workspace_roots)
+                + usize::from(self.symbol_provider.remove_snapshot(&workspace_roots));
+            self.include_paths_cache.clear();
+            info!("Reindexing the workspace after clearing {removed} cached index file(s)");
+            self.clone_for_background().await.index_workspace().await;
+            return Ok(self.definition_provider.project_index().file_count());
+        };
+
+        let uri = text_document.uri;
+        if self.document_store.get(&uri).is_some() {
+            return Ok(usize::from(
+                self.rebuild_file(RebuildFileParams {
+                    text_document: TextDocumentIdentifier {
+                        uri,
+                    },
+                })
+                .await?,
+            ));
+        }
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(0);
+        };
+        self.definition_provider.invalidate_file(&path);
+        self.include_paths_cache.remove(&normalize_path(&path));
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned());
+        let progress = ProgressToken::begin(&self.client, "Indexing", name).await;
+        let provider = self.definition_provider.clone();
+        let includes = self.include_paths(&uri).await;
+        let indexed =
+            tokio::task::spawn_blocking(move || provider.index_workspace_file(&path, &includes)).await.unwrap_or(false);
+        progress
+            .end(Some(
+                if indexed {
+                    "Indexed"
+                } else {
+                    "Failed to index"
+                }
+                .to_string(),
+            ))
+            .await;
+        Ok(usize::from(indexed))
+    }
+
+    pub(crate) async fn clear_cache(&self) -> Result<usize> {
+        let workspace_roots: Vec<PathBuf> =
+            self.workspace_roots.read().await.iter().filter_map(|f| f.uri.to_file_path().ok()).collect();
+        let removed = self.definition_provider.clear_caches(&workspace_roots)
+            + usize::from(self.symbol_provider.remove_snapshot(&workspace_roots));
+        self.include_paths_cache.clear();
+        info!("Cleared {removed} cached index file(s)");
+        Ok(removed)
+    }
+
+    pub(crate) async fn status(&self) -> Result<ServerStatus> {
+        let summary = self.status.summary();
+        let (disk_entries, disk_bytes) = self.definition_provider.disk_cache_usage();
+        Ok(ServerStatus {
+            health: summary.health,
+            quiescent: summary.quiescent,
+            message: summary.message,
+            toolchain_available: self.status.toolchain_available(),
+            system_include_paths: self
+                .compiler
+                .get_system_include_paths()
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect(),
+            indexed_files: self.definition_provider.project_index().file_count(),
+            cache: CacheStatus {
+                memory_bytes: self.definition_provider.memory_usage(),
+                disk_entries,
+                disk_bytes,
+            },
+            pending_jobs: self.status.pending_jobs(),
+            diagnostics_queue: self.diagnostics_scheduler.status(),
+            last_error: self.status.last_error(),
+        })
+    }
+
+    pub(crate) async fn perf(
+        &self,
+        params: PerfParams,
+    ) -> Result<PerfReport> {
+        Ok(perf::report(params.reset))
+    }
+
+    pub(crate) async fn capture_state(&self) -> Result<CapturedState> {
+        let toolchain_version = MetalCompiler::toolchain_version().await;
+        let path =
+            self.state_dump().capture("requested", toolchain_version).map_err(|error| tower_lsp::jsonrpc::Error {
+                code: tower_lsp::jsonrpc::ErrorCode::InternalError,
+                message: format!("Failed to write the state snapshot: {error}").into(),
+                data: None,
+            })?;
+        info!("Wrote state snapshot to {}", path.display());
+        Ok(CapturedState {
+            path: path.display().to_string(),
+        })
+    }
+
+    pub(crate) async fn configuration_schema(&self) -> Result<serde_json::Value> {
+        Ok(generate_json_schema())
+    }
+
+    pub(crate) async fn explain_definition_ranking(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<DefinitionRanking>> {
+        let uri = params.text_document.uri;
+        let Some(text) = self.document_store.get_content(&uri) else {
+            return Ok(None);
+        };
+        let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
+        let Some((symbol, candidates)) = self.definition_provider.explain_ranking(&uri, params.position, &text, &tree)
+        else {
+            return Ok(None);
+        };
+
+        let weights = self.definition_provider.ranking_weights();
+        let candidates = candidates
+            .into_iter()
+            .filter_map(|candidate| {
+                let location = def_to_location(&candidate.def).and_then(ide_location_to_lsp)?;
+                Some(RankedDefinition {
+                    location,
+                    kind: candidate.def.kind.to_string(),
+                    is_definition: candidate.def.is_definition,
+                    from_project_index: candidate.from_project_index,
+                    score: candidate.factors.score(&weights),
+                    penalties: candidate
+                        .factors
+                        .penalties(&weights)
+                        .into_iter()
+                        .map(|(reason, penalty)| RankPenalty {
+                            reason: reason.to_string(),
+                            penalty,
+                        })
+                        .collect(),
+                })
+            })
+            .collect();
+        Ok(Some(DefinitionRanking {
+            symbol,
+            candidates,
+        }))
+    }
+
+    pub(crate) async fn expand_macro(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<ExpandedMacro>> {
+        let uri = params.text_document.uri;
+        let Some((text, hash)) = self.document_store.get_content_with_hash(&uri) else {
+            return Ok(None);
+        };
+        let index = self.macro_index(&uri, &text, hash).await;
+        let Some(invocation) = macro_invocation_at(&text, params.position, &index) else {
+            return Ok(None);
+        };
+        let Some(definition) = index.get(&invocation.name) else {
+            return Ok(None);
+        };
+
+        let expansion = expand_invocation(&invocation, &index);
+        let location = definition.file.as_ref().and_then(|file| Url::from_file_path(file).ok()).map(|file_uri| {
+            let start = Position::new(definition.line, 0);
+            Location {
+                uri: file_uri,
+                range: Range::new(start, start),
+            }
+        });
+        Ok(Some(ExpandedMacro {
+            name: invocation.name,
+            range: invocation.range,
+            definition: location,
+            single_step: expansion.as_ref().map(|expansion| expansion.single_step.clone()),
+            full: expansion.map(|expansion| expansion.full),
+        }))
+    }
+
+    pub(crate) async fn definitions(
+        &self,
+        params: BatchPositionsParams,
+    ) -> Result<Option<Vec<Option<GotoDefinitionResponse>>>> {
+        let uri = params.text_document.uri;
+        if uri.scheme() == CACHE_VIEW_SCHEME {
+            return Ok(Some(
+                params.positions.into_iter().map(|position| self.cache_view_definition(&uri, position)).collect(),
+            ));
+        }
+        let Some(snapshot) = self.analysis_snapshot(uri) else {
+            return Ok(None);
+        };
+
+        let includes = self.include_paths(&snapshot.uri).await;
+        let cancellation = RequestCancellation::new();
+        let token = cancellation.token();
+        let provider = Arc::clone(&self.definition_provider);
+        let definitions = tokio::task::spawn_blocking(move || {
+            params
+                .positions
+                .into_iter()
+                .map_while(|position| {
+                    let is_cancelled = || token.is_cancelled();
+                    (!is_cancelled()).then(|| {
+                        provider
+                            .provide(
+                                &snapshot.uri,
+                                position,
+                                &snapshot.text,
+                                snapshot.hash,
+                                &includes,
+                                &snapshot.tree,
+                                is_cancelled,
+                            )
+                            .and_then(navigation_target_to_lsp)
+                    })
+                })
+                .collect()
+        })
+        .await
+        .ok();
+        Ok(definitions)
+    }
+
+    pub(crate) async fn hovers(
+        &self,
+        params: BatchPositionsParams,
+    ) -> Result<Option<Vec<Option<Hover>>>> {
+        let uri = params.text_document.uri;
+        if uri.scheme() == CACHE_VIEW_SCHEME {
+            return Ok(Some(
+                params.positions.into_iter().map(|position| self.cache_view_hover(&uri, position)).collect(),
+            ));
+        }
+        let Some(snapshot) = self.analysis_snapshot(uri) else {
+            return Ok(None);
+        };
+
+        let macros = self.macro_index(&snapshot.uri, &snapshot.text, snapshot.hash).await;
+        let mut hovers = Vec::with_capacity(params.positions.len());
+        for position in params.positions {
+            let hover = match macro_hover(&macros, &snapshot.text, position) {
+                Some(hover) => Some(hover),
+                None => {
+                    self.hover_provider.provide(&snapshot.uri, &snapshot.text, position, Some(&snapshot.tree)).await
+                },
+            };
+            hovers.push(hover);
+        }
+        Ok(Some(hovers))
+    }
+
+    /// The open document's text and syntax tree, shared by every position of a batch request.
+    pub(crate) async fn todos(
+        &self,
+        params: TodosParams,
+    ) -> Result<Option<Vec<TodoItem>>> {
+        let tags = self.settings_snapshot().await.todos.tags;
+        if let Some(document) = params.text_document {
+            let uri = document.uri;
+            let Some(text) = self.document_store.get_content(&uri) else {
+                return Ok(None);
+            };
+            let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
+            return Ok(Some(todo_items(&uri, &tree, &tags)));
+        }
+
+        let mut items = Vec::new();
+        let mut visited = HashSet::new();
+        for open_uri in self.document_store.all_uris() {
+            if !visited.insert(open_uri.clone()) {
+                continue;
+            }
+            let Some(open_tree) = self.document_trees.get(&open_uri) else {
+                continue;
+            };
+            items.extend(todo_items(&open_uri, &open_tree, &tags));
+        }
+        for path in self.workspace_shader_sources().await {
+            let Ok(file_uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            if !visited.insert(file_uri.clone()) {
+                continue;
+            }
+            let Ok(source) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            if !tags.iter().any(|tag| source.contains(tag.as_str())) {
+                continue;
+            }
+            items.extend(todo_items(&file_uri, &SyntaxTree::parse(&source), &tags));
+        }
+        items.sort_by(|a, b| {
+            (a.location.uri.as_str(), a.location.range.start.line, a.location.range.start.character).cmp(&(
+                b.location.uri.as_str(),
+                b.location.range.start.line,
+                b.location.range.start.character,
+            ))
+        });
+        Ok(Some(items))
+    }
+
+    pub(crate) async fn entry_points(
+        &self,
+        params: EntryPointsParams,
+    ) -> Result<Option<Vec<EntryPointInfo>>> {
+        let settings = self.settings_snapshot().await;
+        let pipelines = self.pipeline_index(&settings.entry_points.pipelines_file).await;
+        if let Some(document) = params.text_document {
+            let uri = document.uri;
+            let Some(text) = self.document_store.get_content(&uri) else {
+                return Ok(None);
+            };
+            let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
+            return Ok(Some(entry_point_infos(&uri, &tree, pipelines.as_ref())));
+        }
+
+        let mut items = Vec::new();
+        let mut visited = HashSet::new();
+        for open_uri in self.document_store.all_uris() {
+            if !visited.insert(open_uri.clone()) {
+                continue;
+            }
+            let Some(open_tree) = self.document_trees.get(&open_uri) else {
+                continue;
+            };
+            items.extend(entry_point_infos(&open_uri, &open_tree, pipelines.as_ref()));
+        }
+        for path in self.workspace_shader_sources().await {
+            let Ok(file_uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            if !visited.insert(file_uri.clone()) {
+                continue;
+            }
+            let Ok(source) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            items.extend(entry_point_infos(&file_uri, &SyntaxTree::parse(&source), pipelines.as_ref()));
+        }
+        items.sort_by(|a, b| {
+            (a.location.uri.as_str(), a.location.range.start.line, a.location.range.start.character).cmp(&(
+                b.location.uri.as_str(),
+                b.location.range.start.line,
+                b.location.range.start.character,
+            ))
+        });
+        Ok(Some(items))
+    }
+
+    pub(crate) async fn include_graph(
+        &self,
+        params: IncludeGraphParams,
+    ) -> Result<Option<IncludeGraphResult>> {
+        let roots: Vec<Url> = match params.text_document {
+            Some(document) => vec![document.uri],
+            None => {
+                let mut sources = self.workspace_shader_sources().await;
+                // Main files first, so headers they reach are walked with
+                // their include paths rather than as roots of their own.
+                sources.sort_by_key(|path| is_header_file(path));
+                let mut roots: Vec<Url> = self.document_store.all_uris();
+                roots.sort_by_key(|uri| uri.to_file_path().is_ok_and(|path| is_header_file(&path)));
+                roots.extend(sources.iter().filter_map(|path| Url::from_file_path(path).ok()));
+                roots
+            },
+        };
+        let read = |path: &Path| {
+            Url::from_file_path(path)
+                .ok()
+                .and_then(|uri| self.document_store.get_content(&uri))
+                .or_else(|| std::fs::read_to_string(path).ok())
+        };
+
+        let mut graph = include_graph::IncludeGraph::default();
+        for uri in roots {
+            let Ok(path) = uri.to_file_path() else {
+                continue;
+            };
+            if graph.node(&path).is_some() {
+                continue;
+            }
+            let Some(source) = read(&path) else {
+                continue;
+            };
+            let include_paths = self.include_paths(&uri).await;
+            graph.add_root(&path, source, &include_paths, MAX_GRAPH_FILES, read);
+        }
+        if graph.nodes().is_empty() {
+            return Ok(None);
+        }
+
+        // Includes refer to files by index, so every file needs its URI.
+        let Some(files) = graph
+            .nodes()
+            .iter()
+            .map(|node| {
+                Some(IncludeGraphFile {
+                    uri: Url::from_file_path(&node.path).ok()?,
+                    system: node.system,
+                    guarded: node.guarded,
+                })
+            })
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Ok(None);
+        };
+        let includes = graph
+            .edges()
+            .iter()
+            .map(|edge| IncludeGraphEdge {
+                from: edge.from,
+                to: edge.to,
+                range: edge.range,
+                in_cycle: graph.in_cycle(edge),
+            })
+            .collect();
+        Ok(Some(IncludeGraphResult {
+            files,
+            includes,
+        }))
+    }
+
+    pub(crate) async fn sarif_log(
+        &self,
+        params: SarifLogParams,
+    ) -> Result<serde_json::Value> {
+        let mut published: Vec<(Url, Vec<Diagnostic>)> = match params.text_document {
+            Some(document) => self
+                .diagnostics_cache
+                .get(&document.uri)
+                .map(|entry| vec![(document.uri, entry.clone())])
+                .unwrap_or_default(),
+            None => self.diagnostics_cache.iter().map(|entry| (entry.key().clone(), entry.value().clone())).collect(),
+        };
+        published.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+
+        let mut results = Vec::new();
+        for (uri, diagnostics) in &published {
+            let Ok(path) = uri.to_file_path() else {
+                continue;
+            };
+            results.extend(diagnostics.iter().map(|diagnostic| SarifResult::from_diagnostic(&path, diagnostic)));
+        }
+        let mut rules = compiler_rules();
+        rules.extend(lint::sarif_rules());
+        let workspace_roots: Vec<PathBuf> =
+            self.workspace_roots.read().await.iter().filter_map(|f| f.uri.to_file_path().ok()).collect();
+        Ok(sarif_log(&rules, &results, &workspace_roots))
+    }
+
+    pub(crate) async fn preprocess(
+        &self,
+        params: PreprocessParams,
+    ) -> Result<Option<PreprocessedDocument>> {
+        let uri = params.text_document.uri;
+        let Some(text) = self.document_store.get_content(&uri) else {
+            return Ok(None);
+        };
+        let include_paths = self.include_paths(&uri).await;
+        let output = self.compiler.preprocess(&text, uri.as_str(), &include_paths).await.map_err(|message| {
+            tower_lsp::jsonrpc::Error {
+                code: tower_lsp::jsonrpc::ErrorCode::InternalError,
+                message: format!("Preprocessing failed:\n{}", message.trim_end()).into(),
+                data: None,
+            }
+        })?;
+
+        let is_system = |file: &str| self.compiler.is_system_path(file);
+        let source = PreprocessedSource::parse(&output);
+        let source = match params.entry_point {
+            Some(entry_point) => {
+                let slice = source.slice_for_entry_point(&entry_point, is_system).ok_or_else(|| {
+                    tower_lsp::jsonrpc::Error::invalid_params(format!(
+                        "No function named `{entry_point}` is defined outside system headers"
+                    ))
+                })?;
+                PreprocessedSource::parse(&slice)
+            },
+            None if params.include_system_headers => source,
+            None => source.without_files(is_system),
+        };
+        Ok(Some(PreprocessedDocument {
+            text: source.text().to_string(),
+            line_map: source
+                .origins()
+                .iter()
+                .map(|origin| {
+                    let origin = origin.as_ref()?;
+                    Some(PreprocessedLineOrigin {
+                        uri: Url::from_file_path(&origin.file).ok()?,
+                        line: origin.line.saturating_sub(1),
+                    })
+                })
+                .collect(),
+        }))
+    }
+
+    pub(crate) async fn compile_binary(
+        &self,
+        params: CompileBinaryParams,
+    ) -> Result<Option<CompiledBinaryReport>> {
+        let uri = params.text_document.uri;
+        let Some(text) = self.document_store.get_content(&uri) else {
+            return Ok(None);
+        };
+        let include_paths = self.include_paths(&uri).await;
+        let binary = self.compiler.compile_binary(&text, uri.as_str(), &include_paths).await.map_err(|message| {
+            tower_lsp::jsonrpc::Error {
+                code: tower_lsp::jsonrpc::ErrorCode::InternalError,
+                message: message.into(),
+                data: None,
+            }
+        })?;
+
+        let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
+        let inactive = inactive_regions(&text, &self.compiler.effective_defines(&uri));
+        let entry_points = entry_points(&uri, &tree)
+            .into_iter()
+            .filter(|site| {
+                let line = site.location.range.start.line;
+                !inactive.iter().any(|range| (range.start.line..=range.end.line).contains(&line))
+            })
+            .map(|site| BinaryEntryPoint {
+                name: site.name,
+                stage: site.stage.unwrap_or_default().to_string(),
+            })
+            .collect();
+        let file = |path: Option<PathBuf>| {
+            let path = path?;
+            let size = std::fs::metadata(&path).ok()?.len();
+            Some(BinaryFile {
+                path: path.display().to_string(),
+                size,
+            })
+        };
+        let diagnostics = binary
+            .diagnostics
+            .into_iter()
+            .map(|diagnostic| BinaryDiagnostic {
+                location: Location {
+                    uri: diagnostic
+                        .file
+                        .as_deref()
+                        .and_then(|file| Url::from_file_path(file).ok())
+                        .unwrap_or_else(|| uri.clone()),
+                    range: diagnostic.range(),
+                },
+                severity: diagnostic.severity,
+                message: diagnostic.message,
+            })
+            .collect();
+        let (air, metallib) = (file(binary.air), file(binary.metallib));
+        Ok(Some(CompiledBinaryReport {
+            success: air.is_some() && metallib.is_some(),
+            air,
+            metallib,
+            entry_points,
+            diagnostics,
+        }))
+    }
+
+    pub(crate) async fn kernel_stats(
+        &self,
+        params: KernelStatsParams,
+    ) -> Result<Option<Vec<EntryPointStats>>> {
+        let uri = params.text_document.uri;
+        let Some((text, hash)) = self.document_store.get_content_with_hash(&uri) else {
+            return Ok(None);
+        };
+        let stats =
+            self.document_kernel_stats(&uri, &text, hash).await.map_err(|message| tower_lsp::jsonrpc::Error {
+                code: tower_lsp::jsonrpc::ErrorCode::InternalError,
+                message: message.into(),
+                data: None,
+            })?;
+        let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
+        let sites = entry_points(&uri, &tree);
+        Ok(Some(
+            stats
+                .iter()
+                .map(|entry| EntryPointStats {
+                    name: entry.name.clone(),
+                    stage: entry.stage.clone(),
+                    location: sites.iter().find(|site| site.name == entry.name).map(|site| site.location.clone()),
+                    instructions: entry.instructions,
+                    basic_blocks: entry.basic_blocks,
+                    threadgroup_memory_bytes: entry.threadgroup_memory_bytes,
+                    private_memory_bytes: entry.private_memory_bytes,
+                })
+                .collect(),
+        ))
+    }
+
+    pub(crate) async fn disassemble(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<Disassembly>> {
+        let uri = params.text_document.uri;
+        let Some(text) = self.document_store.get_content(&uri) else {
+            return Ok(None);
+        };
+        let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
+        let Some(function) = enclosing_function_name(&tree.root(), &text, params.position) else {
+            return Ok(None);
+        };
+        let mut graph = CallGraph::new();
+        graph.add_source(&uri, &tree);
+        let Some(entry) = graph.entry_points_reaching(&function).into_iter().next().map(|path| path.entry) else {
+            return Ok(None);
+        };
+
+        let include_paths = self.include_paths(&uri).await;
+        let ir = self.compiler.disassemble(&text, uri.as_str(), &include_paths).await.map_err(|message| {
+            tower_lsp::jsonrpc::Error {
+                code: tower_lsp::jsonrpc::ErrorCode::InternalError,
+                message: message.into(),
+                data: None,
+            }
+        })?;
+        let source_file = uri.to_file_path().unwrap_or_default();
+        let mut functions: Vec<IrFunctionInfo> = ir_functions(&ir, &source_file)
+            .into_iter()
+            .map(|function| IrFunctionInfo {
+                name: function.name,
+                ir_start_line: function.start_line,
+                ir_end_line: function.end_line,
+                source_line: function.source_line,
+                lines: function
+                    .lines
+                    .into_iter()
+                    .map(|(ir_line, source_line)| IrSourceLine {
+                        ir_line,
+                        source_line,
+                    })
+                    .collect(),
+            })
+            .collect();
+        functions.sort_by_key(|function| function.name != entry.name);
+        Ok(Some(Disassembly {
+            entry_point: entry.name,
+            stage: entry.stage.unwrap_or_default().to_string(),
+            ir,
+            functions,
+        }))
+    }
+
+    /// Flag a cancellable progress session, such as workspace indexing, as
+    /// cancelled. The work stops at its next check.
+    pub(crate) async fn work_done_progress_cancel(
+        &self,
+        params: WorkDoneProgressCancelParams,
+    ) {
+        if !self.progress_cancellations.cancel(&params.token) {
+            debug!("Ignoring cancellation of unknown progress {:?}", params.token);
+        }
+    }
+
+    /// Add the statistics of the entry point named at `position`, if any, to
+    /// `hover`. Statistics that fail to build are left out.
+    pub(crate) async fn with_kernel_stats(
+        &self,
+        uri: &Url,
+        text: &str,
+        hash: ContentHash,
+        position: Position,
+        hover: Option<Hover>,
+    ) -> Option<Hover> {
+        let tree = self.document_trees.get(uri).unwrap_or_else(|| SyntaxTree::parse(text));
+        let Some(site) = entry_points(uri, &tree).into_iter().find(|site| {
+            let range = site.location.range;
+            range.start <= position && position <= range.end
+        }) else {
+            return hover;
+        };
+        let stats = match self.document_kernel_stats(uri, text, hash).await {
+            Ok(stats) => stats,
+            Err(error) => {
+                debug!("kernel statistics of {uri} unavailable: {error}");
+                return hover;
+            },
+        };
+        let Some(entry) = stats.iter().find(|entry| entry.name == site.name) else {
+            return hover;
+        };
+        let section = entry.to_markdown();
+        Some(match hover {
+            Some(Hover {
+                contents: HoverContents::Markup(markup),
+                range,
+            }) => Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: markup.kind,
+                    value: format!("{}\n\n---\n\n{section}", markup.value),
+                }),
+                range,
+            },
+            _ => Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: section,
+                }),
+                range: Some(site.location.range),
+            },
+        })
+    }
+
+    /// Kernel statistics of `text`, the content of `uri` with content hash
+    /// `hash`, compiling it again only when it changed since the last call.
+    async fn document_kernel_stats(
+        &self,
+        uri: &Url,
+        text: &str,
+        hash: ContentHash,
+    ) -> std::result::Result<Arc<Vec<kernel_stats::KernelStats>>, String> {
+        if let Some(cached) = self.kernel_stats_cache.get(uri).filter(|cached| cached.0 == hash) {
+            return Ok(cached.1.clone());
+        }
+        let include_paths = self.include_paths(uri).await;
+        let stats = Arc::new(self.compiler.kernel_stats(text, uri.as_str(), &include_paths).await?);
+        self.kernel_stats_cache.insert(uri.clone(), (hash, stats.clone()));
+        Ok(stats)
+    }
+
+    pub(crate) fn analysis_snapshot(
+        &self,
+        uri: Url,
+    ) -> Option<AnalysisSnapshot> {
+        let (text, hash) = self.document_store.get_content_with_hash(&uri)?;
+        let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
+        Some(AnalysisSnapshot {
+            uri,
+            text,
+            hash,
+            tree,
+        })
+    }
+
+    /// Hover on an `#include` line: the file it resolves to, the search root
+    /// that found it, the files it shadows and what it declares.
+    pub(crate) async fn include_hover(
+        &self,
+        uri: &Url,
+        text: &str,
+        position: Position,
+    ) -> Option<Hover> {
+        let directive = include_graph::include_directives(text)
+            .into_iter()
+            .find(|directive| directive.range.start.line == position.line)?;
+        let owner = uri.to_file_path().ok()?;
+        let include_paths = self.include_paths(uri).await;
+        let candidates: Vec<_> =
+            include_candidates(&owner, &directive.name, directive.angled, &include_paths).collect();
+        let mut header_source = None;
+        if let Some((header, _)) = candidates.first() {
+            header_source =
+                Url::from_file_path(header).ok().and_then(|header_uri| self.document_store.get_content(&header_uri));
+            if header_source.is_none() {
+                header_source = tokio::fs::read_to_string(header).await.ok();
+            }
+        }
+        Some(make_include_hover(&owner, &directive, &candidates, &include_paths, header_source.as_deref()))
+    }
+
+    /// Hover on a macro invocation: its definition and expansions.
+    pub(crate) async fn macro_hover(
+        &self,
+        uri: &Url,
+        text: &str,
+        hash: ContentHash,
+        position: Position,
+    ) -> Option<Hover> {
+        macro_hover(&*self.macro_index(uri, text, hash).await, text, position)
+    }
+
+    /// Macros visible at the end of `text`: the effective compiler defines,
+    /// the project headers the document includes, then the document itself.
+    ///
+    /// Headers are applied deepest first so a file's own definitions win over
+    /// those of the headers it includes. The index is kept until the cached
+    /// include graph of the document is replaced, which happens when the
+    /// document, a header it reads or the workspace generation changes.
+    async fn macro_index(
+        &self,
+        uri: &Url,
+        text: &str,
+        hash: ContentHash,
+    ) -> Arc<MacroIndex> {
+        let mut index = MacroIndex::from_defines(&self.compiler.effective_defines(uri));
+        let Ok(path) = uri.to_file_path() else {
+            index.add_source(text, None);
+            return Arc::new(index);
+        };
+        let include_paths = self.include_paths(uri).await;
+        let workspace_generation = self.workspace_generation.load(Ordering::Relaxed);
+        let graph = self.include_graphs.graph(&path, (text, hash), None, &include_paths, workspace_generation).await;
+        if let Some(cached) = self.macro_indexes.get(uri).filter(|cached| Arc::ptr_eq(&cached.0, &graph)) {
+            return Arc::clone(&cached.1);
+        }
+
+        let root = graph.node(&path);
+        for (node, file) in graph.nodes().iter().enumerate().rev() {
+            if Some(node) == root || file.system {
+                continue;
+            }
+            if let Ok(source) = tokio::fs::read_to_string(&file.path).await {
+                index.add_source(&source, Some(&file.path));
+            }
+        }
+        index.add_source(text, Some(&path));
+        let index = Arc::new(index);
+        self.macro_indexes.insert(uri.clone(), (graph, Arc::clone(&index)));
+        index
+    }
+
+    /// Hover inside a `metal-analyzer-cache://` view: details of the entry under the cursor.
+    pub(crate) fn cache_view_hover(
+        &self,
+        uri: &Url,
+        position: Position,
+    ) -> Option<Hover> {
+        let view = self.ast_cache_views.get(uri)?;
+        let link = view.link_at(position)?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: link.detail.clone(),
+            }),
+            range: Some(link.range),
+        })
+    }
+
+    /// Goto-definition inside a `metal-analyzer-cache://` view: the source location under the cursor.
+    pub(crate) fn cache_view_definition(
+        &self,
+        uri: &Url,
+        position: Position,
+    ) -> Option<GotoDefinitionResponse> {
+        let view = self.ast_cache_views.get(uri)?;
+        view.link_at(position).map(|link| GotoDefinitionResponse::Scalar(link.target.clone()))
+    }
+
+    /// `.metal` files and headers under the workspace roots, honoring indexing exclusions.
+    pub(crate) async fn workspace_shader_sources(&self) -> Vec<PathBuf> {
+        let settings = self.settings_snapshot().await;
+        let workspace_roots: Vec<PathBuf> =
+            self.workspace_roots.read().await.iter().filter_map(|f| f.uri.to_file_path().ok()).collect();
+        tokio::task::spawn_blocking(move || {
+            let exclusions = PathExclusions::new(&workspace_roots, &settings.indexing.exclude_paths);
+            discover_workspace_files(
+                &workspace_roots,
+                settings.indexing.max_file_size_bytes(),
+                &exclusions,
+                settings.indexing.respect_gitignore,
+                |path| path.extension().is_some_and(|ext| ext == "metal") || is_header_file(path),
+            )
+        })
+        .await
+        .unwrap_or_default()
+    }
+
+    /// The pipelines of `entryPoints.pipelinesFile`, resolved against the
+    /// first workspace root. `None` when unset, unreadable or not valid JSON.
+    pub(crate) async fn pipeline_index(
+        &self,
+        pipelines_file: &str,
+    ) -> Option<PipelineIndex> {
+        if pipelines_file.is_empty() {
+            return None;
+        }
+        let mut path = PathBuf::from(pipelines_file);
+        if path.is_relative()
+            && let Some(root) = self.workspace_roots.read().await.first().and_then(|f| f.uri.to_file_path().ok())
+        {
+            path = root.join(path);
+        }
+        let json = tokio::fs::read_to_string(&path).await.ok()?;
+        match PipelineIndex::parse(&json) {
+            Ok(index) => Some(index),
+            Err(error) => {
+                warn!("Ignoring pipelines file {}: {error}", path.display());
+                None
+            },
+        }
+    }
+}
+
+fn todo_items(
+    uri: &Url,
+    tree: &SyntaxTree,
+    tags: &[String],
+) -> Vec<TodoItem> {
+    find_todos(&tree.root(), tree.source(), tags)
+        .into_iter()
+        .map(|todo| TodoItem {
+            location: Location {
+                uri: uri.clone(),
+                range: todo.range,
+            },
+            tag: todo.tag,
+            owner: todo.owner,
+            text: todo.text,
+        })
+        .collect()
+}
+
+fn entry_point_infos(
+    uri: &Url,
+    tree: &SyntaxTree,
+    pipelines: Option<&PipelineIndex>,
+) -> Vec<EntryPointInfo> {
+    entry_point_details(uri, tree)
+        .into_iter()
+        .map(|details| EntryPointInfo {
+            stage: details.site.stage.unwrap_or_default().to_string(),
+            pipelines: pipelines.map(|index| index.pipelines_using(&details.site.name)).unwrap_or_default(),
+            name: details.site.name,
+            location: details.site.location,
+            attributes: details.attributes,
+            bindings: details
+                .bindings
+                .into_iter()
+                .map(|binding| EntryPointBinding {
+                    kind: binding.slot.name,
+                    index: binding.slot.index,
+                    parameter: binding.parameter,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+fn macro_hover(
+    index: &MacroIndex,
+    text: &str,
+    position: Position,
+) -> Option<Hover> {
+    let invocation = macro_invocation_at(text, position, index)?;
+    let definition = index.get(&invocation.name)?;
+    Some(make_macro_hover(definition, &invocation, expand_invocation(&invocation, index).as_ref()))
+}
+
+fn cache_view_document_link(link: &CacheViewLink) -> DocumentLink {
+    let mut target = link.target.uri.clone();
+    let start = link.target.range.start;
+    target.set_fragment(Some(&format!("L{},{}", start.line + 1, start.character + 1)));
+    DocumentLink {
+        range: link.range,
+        target: Some(target),
+        tooltip: None,
+        data: None,
+    }
+}
+
+fn binding_uses_in_source(
+    uri: &Url,
+    tree: &SyntaxTree,
+    slot: &BindingSlot,
+) -> Vec<BindingUse> {
+    find_binding_sites(&tree.root(), tree.source(), slot)
+        .into_iter()
+        .map(|site| BindingUse {
+            location: Location {
+                uri: uri.clone(),
+                range: site.range,
+            },
+            attribute: slot.label(),
+            function: site.function,
+            declarator: site.declarator,
+        })
+        .collect()
+}
@@ -2,32 +2,65 @@ use std::{
     collections::{BTreeSet, HashSet},
     panic::AssertUnwindSafe,
     path::{Component, Path, PathBuf},
-    sync::atomic::Ordering,
+    sync::{Arc, atomic::Ordering},
+    time::Duration,
 };
 
-use futures::FutureExt;
-
 use dashmap::DashMap;
-use tower_lsp::lsp_types::{
-    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, Position, Range, Url,
-};
+use futures::FutureExt;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, Url};
 use tracing::{debug, info, warn};
 use walkdir::{DirEntry, WalkDir};
 
 use crate::{
-    metal::compiler::MetalDiagnostic,
+    completion::IncludeSearchDirs,
+    config::IndexingSettings,
+    definition::SharedStr,
+    document::ContentHash,
+    ide::{
+        address_spaces::address_space_diagnostics,
+        bindings::duplicate_bindings,
+        deprecations::widen_deprecation_ranges,
+        entry_point_attributes::entry_point_attribute_diagnostics,
+        include_guards::include_guard_diagnostics,
+        language_version::unavailable_builtins,
+        macros::MacroIndex,
+        spelling::{identifier_names, suggest_identifiers, unknown_identifier},
+        threadgroup_memory::threadgroup_memory_diagnostics,
+        todos::{TodoComment, find_todos},
+        unused_symbols::unused_symbol_diagnostics,
+    },
+    metal::{
+        compiler::MetalDiagnostic,
+        fix_its::{CompilerFix, attach_fix},
+    },
     progress::ProgressToken,
     server::{
+        file_watch::is_watched_source,
         header_owners::{
-            collect_included_headers, get_owner_candidates_for_header, is_header_file, normalize_path,
-            update_owner_links,
+            collect_dependent_owners, collect_included_headers, get_owner_candidates_for_header, is_header_file,
+            normalize_path, update_owner_links,
         },
-        settings::ServerSettings,
+        include_cycles::include_cycle_diagnostics,
+        include_graphs::IncludeGraphCache,
+        macro_conflicts::macro_conflict_diagnostics,
+        settings::{HeaderContext, ServerSettings},
         state::MetalLanguageServer,
+        unused_includes::unused_include_diagnostics,
+    },
+    syntax::SyntaxTree,
+    vfs::{
+        gitignore::Gitignore,
+        glob::PathExclusions,
+        virtual_docs::{document_path, document_uri},
     },
 };
 
 const HEADER_OWNER_COMPILE_CAP: usize = 256;
+/// Maximum owner files recompiled after a single header save.
+const DEPENDENT_REFRESH_OWNER_CAP: usize = 64;
+/// Maximum headers read while following header-to-header includes.
+const DEPENDENT_REFRESH_HEADER_SCAN_CAP: usize = 1024;
 
 impl MetalLanguageServer {
     /// Run the Metal compiler on the document identified by `uri` and publish
@@ -43,27 +76,49 @@ impl MetalLanguageServer {
                 return;
             },
         };
+        let text_hash = document.content_hash();
         let text = document.text;
         let version = document.version;
+        let settings = self.settings_snapshot().await;
+        if self.diagnostics_excluded(uri, &settings).await {
+            self.clear_diagnostics(uri).await;
+            return;
+        }
 
         let generation = next_diagnostic_generation(&self.diagnostics_generation, uri);
+        let ticket = self.diagnostics_scheduler.schedule(uri, generation).await;
+        let Some(ticket) =
+            ticket.filter(|_| is_latest_diagnostic_generation(&self.diagnostics_generation, uri, generation))
+        else {
+            debug!("Skipping superseded diagnostics for {uri} (generation={generation})");
+            return;
+        };
         let workspace_roots: Vec<PathBuf> =
             self.workspace_roots.read().await.iter().filter_map(|f| f.uri.to_file_path().ok()).collect();
 
+        let _job = self.status.start_job().await;
         let progress = ProgressToken::begin(&self.client, "Diagnostics", Some("Running compiler…".into())).await;
-        let workspace_generation = self.workspace_generation.load(Ordering::Relaxed);
+        let context = DiagnosticsContext {
+            compiler: &self.compiler,
+            workspace_roots: &workspace_roots,
+            header_owners: &self.header_owners,
+            owner_headers: &self.owner_headers,
+            include_paths_cache: &self.include_paths_cache,
+            include_graphs: &self.include_graphs,
+            workspace_generation: self.workspace_generation.load(Ordering::Relaxed),
+        };
+        let options = DiagnosticsOptions::from_settings(&settings);
 
         let diagnostics = compile_filtered_diagnostics_for_document(
-            &self.compiler,
-            &workspace_roots,
-            &self.header_owners,
-            &self.owner_headers,
-            &self.include_paths_cache,
-            workspace_generation,
+            &context,
+            &self.definition_provider,
+            &options,
             uri,
             &text,
+            text_hash,
         )
         .await;
+        ticket.finish();
 
         let count = diagnostics.len();
         if !is_latest_diagnostic_generation(&self.diagnostics_generation, uri, generation) {
@@ -114,6 +169,53 @@ impl MetalLanguageServer {
         .await
     }
 
+    /// Directories listed for `#include` path completion in `uri`.
+    ///
+    /// Quoted includes search the file's own directory, the project include
+    /// paths and configured `includePaths`; angle-bracket includes search the
+    /// system include paths.
+    pub(crate) async fn include_search_dirs(
+        &self,
+        uri: &Url,
+    ) -> IncludeSearchDirs {
+        let include_paths = self.include_paths(uri).await;
+        let angled = self.compiler.get_system_include_paths();
+        let mut quoted: Vec<PathBuf> =
+            uri.to_file_path().ok().and_then(|path| path.parent().map(Path::to_path_buf)).into_iter().collect();
+        quoted.extend(
+            include_paths
+                .iter()
+                .filter(|path| !path.starts_with(crate::metal::compiler::FRAMEWORK_DIR_PREFIX))
+                .map(PathBuf::from)
+                .chain(self.compiler.include_paths_for(uri.as_str()))
+                .filter(|path| !angled.contains(path)),
+        );
+        let mut seen = HashSet::new();
+        quoted.retain(|path| seen.insert(path.clone()));
+        IncludeSearchDirs {
+            quoted,
+            angled,
+        }
+    }
+
+    /// Whether `diagnostics.exclude` keeps the diagnostics of `uri` from being
+    /// published.
+    pub(crate) async fn diagnostics_excluded(
+        &self,
+        uri: &Url,
+        settings: &ServerSettings,
+    ) -> bool {
+        let Ok(path) = uri.to_file_path() else {
+            return false;
+        };
+        if settings.diagnostics.exclude.is_empty() {
+            return false;
+        }
+        let workspace_roots: Vec<PathBuf> =
+            self.workspace_roots.read().await.iter().filter_map(|f| f.uri.to_file_path().ok()).collect();
+        PathExclusions::new(&workspace_roots, &settings.diagnostics.exclude).is_excluded(&normalize_path(&path))
+    }
+
     /// Clear any previously published diagnostics for a document.
     pub(crate) async fn clear_diagnostics(
         &self,
@@ -128,6 +230,33 @@ impl MetalLanguageServer {
         }
     }
 
+    /// Recompile the `.metal` files that depend on a saved header.
+    ///
+    /// Runs after the diagnostics debounce delay; headers saved within that
+    /// window are refreshed together.
+    pub(crate) async fn schedule_dependent_diagnostics(
+        &self,
+        header: PathBuf,
+    ) {
+        if let Ok(mut pending) = self.pending_dependent_headers.lock() {
+            pending.insert(normalize_path(&header));
+        }
+        let generation = self.dependent_refresh_generation.fetch_add(1, Ordering::Relaxed) + 1;
+        let latest_generation = self.dependent_refresh_generation.clone();
+        let pending = self.pending_dependent_headers.clone();
+        let settings = self.settings_snapshot().await;
+        let handle = self.clone_for_background().await;
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(settings.diagnostics.debounce_ms)).await;
+            if latest_generation.load(Ordering::Relaxed) != generation {
+                return;
+            }
+            let headers = pending.lock().map(|mut pending| std::mem::take(&mut *pending)).unwrap_or_default();
+            handle.refresh_dependent_diagnostics(&settings, headers).await;
+        });
+    }
+
     /// Create a lightweight handle suitable for passing into `tokio::spawn`.
     pub(crate) async fn clone_for_background(&self) -> BackgroundHandle {
         let workspace_roots =
@@ -141,9 +270,15 @@ impl MetalLanguageServer {
             header_owners: self.header_owners.clone(),
             owner_headers: self.owner_headers.clone(),
             include_paths_cache: self.include_paths_cache.clone(),
+            include_graphs: self.include_graphs.clone(),
+            completion_provider: self.completion_provider.clone(),
             diagnostics_generation: self.diagnostics_generation.clone(),
+            diagnostics_scheduler: self.diagnostics_scheduler.clone(),
             workspace_generation: self.workspace_generation.load(Ordering::Relaxed),
             settings: self.settings.clone(),
+            symbol_provider: self.symbol_provider.clone(),
+            progress_cancellations: self.progress_cancellations.clone(),
+            status: self.status.clone(),
         }
     }
 }
@@ -152,17 +287,23 @@ impl MetalLanguageServer {
 /// definition provider and include-path computation without holding
 /// references to the full server state.
 pub(crate) struct BackgroundHandle {
-    client: tower_lsp::Client,
-    compiler: std::sync::Arc<crate::metal::compiler::MetalCompiler>,
-    definition_provider: std::sync::Arc<crate::definition::DefinitionProvider>,
-    document_store: std::sync::Arc<crate::document::DocumentStore>,
-    workspace_roots: Vec<PathBuf>,
-    header_owners: std::sync::Arc<DashMap<PathBuf, std::collections::BTreeSet<PathBuf>>>,
-    owner_headers: std::sync::Arc<DashMap<PathBuf, std::collections::BTreeSet<PathBuf>>>,
-    include_paths_cache: std::sync::Arc<DashMap<PathBuf, (u64, Vec<String>)>>,
+    pub(super) client: tower_lsp::Client,
+    pub(super) compiler: std::sync::Arc<crate::metal::compiler::MetalCompiler>,
+    pub(super) definition_provider: std::sync::Arc<crate::definition::DefinitionProvider>,
+    pub(super) document_store: std::sync::Arc<crate::document::DocumentStore>,
+    pub(super) workspace_roots: Vec<PathBuf>,
+    pub(super) header_owners: std::sync::Arc<DashMap<PathBuf, std::collections::BTreeSet<PathBuf>>>,
+    pub(super) owner_headers: std::sync::Arc<DashMap<PathBuf, std::collections::BTreeSet<PathBuf>>>,
+    pub(super) include_paths_cache: std::sync::Arc<DashMap<PathBuf, (u64, Vec<String>)>>,
+    pub(super) include_graphs: std::sync::Arc<IncludeGraphCache>,
+    pub(super) completion_provider: std::sync::Arc<crate::completion::CompletionProvider>,
     diagnostics_generation: std::sync::Arc<DashMap<Url, u64>>,
+    diagnostics_scheduler: std::sync::Arc<crate::server::scheduler::DiagnosticsScheduler>,
     workspace_generation: u64,
-    settings: std::sync::Arc<tokio::sync::RwLock<ServerSettings>>,
+    pub(super) settings: std::sync::Arc<tokio::sync::RwLock<ServerSettings>>,
+    pub(super) symbol_provider: std::sync::Arc<crate::symbols::SymbolProvider>,
+    progress_cancellations: std::sync::Arc<crate::progress::ProgressCancellations>,
+    pub(super) status: std::sync::Arc<crate::server::status::StatusTracker>,
 }
 
 impl BackgroundHandle {
@@ -179,11 +320,13 @@ impl BackgroundHandle {
             return;
         }
 
+        let exclusions = PathExclusions::new(&self.workspace_roots, &settings.indexing.exclude_paths);
+        if indexing_enabled {
+            self.scan_workspace_symbols(&settings, &exclusions).await;
+        }
+
         self.compiler.ensure_system_includes_ready().await;
-        let max_indexed_file_size = settings.indexing.max_file_size_bytes();
-        let excluded_prefixes =
-            build_workspace_scan_exclude_prefixes(&self.workspace_roots, &settings.indexing.exclude_paths);
-        let metal_files = self.discover_metal_files(max_indexed_file_size, &excluded_prefixes);
+        let metal_files = self.discover_metal_files(&settings.indexing, &exclusions);
         let total = metal_files.len();
         if total == 0 {
             info!("No .metal files found in workspace");
@@ -201,6 +344,21 @@ impl BackgroundHandle {
         }
     }
 
+    /// Re-run workspace diagnostics without re-indexing, e.g. after compiler
+    /// flags changed.
+    pub async fn refresh_workspace_diagnostics(&self) {
+        let settings = self.settings.read().await.clone();
+        if !settings.diagnostics.scope.is_workspace() {
+            return;
+        }
+        self.compiler.ensure_system_includes_ready().await;
+        let exclusions = PathExclusions::new(&self.workspace_roots, &settings.indexing.exclude_paths);
+        let metal_files = self.discover_metal_files(&settings.indexing, &exclusions);
+        if !metal_files.is_empty() {
+            self.run_workspace_diagnostics(&settings, &metal_files).await;
+        }
+    }
+
     async fn run_workspace_indexing(
         &self,
         settings: &ServerSettings,
@@ -208,10 +366,28 @@ impl BackgroundHandle {
     ) {
         let total = metal_files.len();
         info!("Indexing {total} .metal file(s) in workspace…");
-        let progress = ProgressToken::begin(&self.client, "Indexing", Some(format!("0 / {total} files"))).await;
+        let _job = self.status.start_job().await;
+        let progress = ProgressToken::begin_cancellable(
+            &self.client,
+            &self.progress_cancellations,
+            "Indexing",
+            Some(format!("0 / {total} files")),
+        )
+        .await;
+
+        let restored: std::sync::Arc<HashSet<PathBuf>> = if settings.indexing.persist_index {
+            let restored = self.restore_project_snapshot(metal_files).await;
+            if !restored.is_empty() {
+                info!("Restored {} of {total} file(s) from the persisted project index", restored.len());
+            }
+            std::sync::Arc::new(restored.into_iter().collect())
+        } else {
+            std::sync::Arc::default()
+        };
 
         let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(settings.indexing.concurrency));
         let indexed = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let cancellation = progress.cancellation_token();
 
         let mut handles = Vec::with_capacity(total);
 
@@ -223,57 +399,184 @@ impl BackgroundHandle {
             let count = indexed.clone();
             let header_owners = self.header_owners.clone();
             let owner_headers = self.owner_headers.clone();
+            let restored = restored.clone();
+            let cancellation = cancellation.clone();
 
             handles.push(tokio::spawn(async move {
-                let _permit = sem.acquire().await;
+                // Closing the semaphore on cancellation wakes every task still
+                // waiting for a permit, so the remaining files are skipped.
+                let Ok(_permit) = sem.acquire().await else {
+                    return (path, None);
+                };
+                if cancellation.is_cancelled() {
+                    sem.close();
+                    return (path, None);
+                }
                 let include_paths = compute_include_paths_for(&path, &roots, &compiler);
                 if let Ok(source) = tokio::fs::read_to_string(&path).await {
                     let headers = collect_included_headers(&path, &source, &include_paths);
                     update_owner_links(&header_owners, &owner_headers, &path, headers);
                 }
-                let ok = provider.index_workspace_file(&path, &include_paths);
+                let ok = restored.contains(&path) || provider.index_workspace_file(&path, &include_paths);
                 count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                (path, ok)
+                (path, Some(ok))
             }));
         }
 
+        let mut skipped = 0usize;
         for handle in handles {
-            if let Ok((path, ok)) = handle.await {
-                let done = indexed.load(std::sync::atomic::Ordering::Relaxed);
-                if done % 5 == 0 || done == total {
-                    progress.report(Some(format!("{done} / {total} files")), Some((done * 100 / total) as u32)).await;
-                }
-                if !ok {
-                    debug!("Failed to index: {}", path.display());
-                }
+            match handle.await {
+                Ok((_, None)) => skipped += 1,
+                Ok((path, Some(ok))) => {
+                    let done = indexed.load(std::sync::atomic::Ordering::Relaxed);
+                    if (done % 5 == 0 || done == total) && !progress.is_cancelled() {
+                        progress
+                            .report(Some(format!("{done} / {total} files")), Some((done * 100 / total) as u32))
+                            .await;
+                    }
+                    if !ok {
+                        debug!("Failed to index: {}", path.display());
+                    }
+                },
+                Err(_) => {},
             }
         }
 
+        // Files indexed before a cancellation stay in the project index and
+        // the persisted snapshot, so the next scan restores them.
         let count = self.definition_provider.project_index().file_count();
-        info!("Project index complete: {count} file(s) indexed");
-        progress.end(Some(format!("{count} file(s) indexed"))).await;
+        if skipped > 0 {
+            info!("Project indexing cancelled: {count} file(s) indexed, {skipped} skipped");
+            progress.end(Some(format!("Cancelled: {count} file(s) indexed, {skipped} skipped"))).await;
+        } else {
+            info!("Project index complete: {count} file(s) indexed");
+            progress.end(Some(format!("{count} file(s) indexed"))).await;
+        }
+
+        if settings.indexing.persist_index {
+            self.save_project_snapshot().await;
+        }
+    }
+
+    /// Fill the workspace symbol index from the CST of every workspace source
+    /// and header, restoring unchanged files from the persisted snapshot
+    /// first. Open documents keep the symbols of their buffers.
+    async fn scan_workspace_symbols(
+        &self,
+        settings: &ServerSettings,
+        exclusions: &PathExclusions,
+    ) {
+        let files: HashSet<PathBuf> = discover_workspace_files(
+            &self.workspace_roots,
+            settings.indexing.max_file_size_bytes(),
+            exclusions,
+            settings.indexing.respect_gitignore,
+            is_watched_source,
+        )
+        .into_iter()
+        .collect();
+        let provider = self.symbol_provider.clone();
+        let document_store = self.document_store.clone();
+        let roots = self.workspace_roots.clone();
+        let persist = settings.indexing.persist_index;
+        let started = std::time::Instant::now();
+        let result = tokio::task::spawn_blocking(move || {
+            let restored = if persist {
+                provider.restore_snapshot(&roots, |uri| {
+                    document_store.get(uri).is_none()
+                        && uri.to_file_path().is_ok_and(|path| files.contains(&normalize_path(&path)))
+                })
+            } else {
+                Vec::new()
+            };
+            let mut scanned = 0usize;
+            for path in &files {
+                let is_open = Url::from_file_path(path).is_ok_and(|uri| document_store.get(&uri).is_some());
+                if !is_open && provider.refresh_from_disk(path) {
+                    scanned += 1;
+                }
+            }
+            (restored.len(), scanned)
+        })
+        .await;
+        if let Ok((restored, scanned)) = result {
+            info!("Workspace symbols ready in {:?}: {restored} file(s) restored, {scanned} scanned", started.elapsed());
+        }
+    }
+
+    /// Restore the persisted project index entries of `metal_files` that are
+    /// still current.
+    async fn restore_project_snapshot(
+        &self,
+        metal_files: &[PathBuf],
+    ) -> Vec<PathBuf> {
+        let provider = self.definition_provider.clone();
+        let compiler = self.compiler.clone();
+        let roots = self.workspace_roots.clone();
+        let files = metal_files.to_vec();
+        tokio::task::spawn_blocking(move || {
+            provider.restore_project_snapshot(&roots, &files, |path| {
+                compute_include_paths_for(&path.to_path_buf(), &roots, &compiler)
+            })
+        })
+        .await
+        .unwrap_or_default()
     }
 
-    async fn run_workspace_diagnostics(
+    /// Persist the project index and the workspace symbols so the next
+    /// session can restore them.
+    pub(crate) async fn save_project_snapshot(&self) {
+        if self.workspace_roots.is_empty() {
+            return;
+        }
+        let symbols = self.symbol_provider.clone();
+        let roots = self.workspace_roots.clone();
+        match tokio::task::spawn_blocking(move || symbols.save_snapshot(&roots)).await {
+            Ok(Ok(count)) => debug!("Persisted workspace symbols of {count} file(s)"),
+            Ok(Err(error)) => warn!("Failed to persist workspace symbols: {error}"),
+            Err(error) => warn!("Failed to persist workspace symbols: {error}"),
+        }
+        let provider = self.definition_provider.clone();
+        let roots = self.workspace_roots.clone();
+        let result = tokio::task::spawn_blocking(move || provider.save_project_snapshot(&roots)).await;
+        match result {
+            Ok(Ok(count)) => debug!("Persisted project index with {count} file(s)"),
+            Ok(Err(error)) => {
+                warn!("Failed to persist project index: {error}");
+                self.status.record_error(format!("Failed to persist project index: {error}")).await;
+            },
+            Err(error) => warn!("Failed to persist project index: {error}"),
+        }
+    }
+
+    pub(super) async fn run_workspace_diagnostics(
         &self,
         settings: &ServerSettings,
         metal_files: &[PathBuf],
     ) {
+        let exclusions = PathExclusions::new(&self.workspace_roots, &settings.diagnostics.exclude);
+        let metal_files: Vec<PathBuf> =
+            metal_files.iter().filter(|path| !exclusions.is_excluded(path)).cloned().collect();
         let total = metal_files.len();
         info!("Analyzing diagnostics for {total} .metal file(s) in workspace…");
+        let _job = self.status.start_job().await;
         let progress = ProgressToken::begin(&self.client, "Diagnostics", Some(format!("0 / {total} files"))).await;
 
         let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(settings.indexing.concurrency));
         let processed = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
         let mut handles = Vec::with_capacity(total);
+        let options = Arc::new(DiagnosticsOptions::from_settings(settings).for_closed_files());
 
-        for path in metal_files.iter().cloned() {
+        for path in metal_files {
             let sem = semaphore.clone();
             let compiler = self.compiler.clone();
             let workspace_roots = self.workspace_roots.clone();
             let header_owners = self.header_owners.clone();
             let owner_headers = self.owner_headers.clone();
             let include_paths_cache = self.include_paths_cache.clone();
+            let include_graphs = self.include_graphs.clone();
+            let options = options.clone();
+            let definitions = self.definition_provider.clone();
             let workspace_generation = self.workspace_generation;
             let open_documents = self.document_store.clone();
             let diagnostics_generation = self.diagnostics_generation.clone();
@@ -282,14 +585,20 @@ impl BackgroundHandle {
 
             handles.push(tokio::spawn(async move {
                 let _permit = sem.acquire().await;
+                let context = DiagnosticsContext {
+                    compiler: &compiler,
+                    workspace_roots: &workspace_roots,
+                    header_owners: &header_owners,
+                    owner_headers: &owner_headers,
+                    include_paths_cache: &include_paths_cache,
+                    include_graphs: &include_graphs,
+                    workspace_generation,
+                };
                 let result = publish_workspace_diagnostics_for_file(
                     &client,
-                    &compiler,
-                    &workspace_roots,
-                    &header_owners,
-                    &owner_headers,
-                    &include_paths_cache,
-                    workspace_generation,
+                    &context,
+                    &definitions,
+                    &options,
                     &open_documents,
                     &diagnostics_generation,
                     path,
@@ -337,45 +646,213 @@ impl BackgroundHandle {
         progress.end(Some(end_message)).await;
     }
 
-    fn discover_metal_files(
+    /// Recompile and republish diagnostics for owners of the given headers.
+    ///
+    /// Open owners are always refreshed (their diagnostics come from the
+    /// pre-save header); closed owners only when diagnostics cover the workspace.
+    pub(super) async fn refresh_dependent_diagnostics(
         &self,
-        max_file_size_bytes: u64,
-        excluded_prefixes: &[PathBuf],
-    ) -> Vec<PathBuf> {
-        let mut files = Vec::new();
-        let mut seen = std::collections::HashSet::new();
-
-        for root in &self.workspace_roots {
-            for entry in WalkDir::new(root)
-                .follow_links(true)
-                .into_iter()
-                .filter_entry(|entry| should_descend_into_workspace_entry(entry, excluded_prefixes))
-                .filter_map(|e| e.ok())
-            {
-                if !entry.file_type().is_file() {
-                    continue;
-                }
+        settings: &ServerSettings,
+        headers: BTreeSet<PathBuf>,
+    ) {
+        if headers.is_empty() {
+            return;
+        }
+        let _job = self.status.start_job().await;
+        self.compiler.ensure_system_includes_ready().await;
 
-                let path = entry.path();
-                if !path.extension().is_some_and(|ext| ext == "metal") {
-                    continue;
-                }
+        let header_owners = self.header_owners.clone();
+        let owner_headers = self.owner_headers.clone();
+        let compiler = self.compiler.clone();
+        let roots = self.workspace_roots.clone();
+        let owners = tokio::task::spawn_blocking(move || {
+            let includes_of = |header: &Path| {
+                let include_paths = compute_include_paths_for(&header.to_path_buf(), &roots, &compiler);
+                std::fs::read_to_string(header)
+                    .map(|source| collect_included_headers(header, &source, &include_paths))
+                    .unwrap_or_default()
+            };
+            let mut owners = BTreeSet::new();
+            for header in &headers {
+                owners.extend(collect_dependent_owners(
+                    &header_owners,
+                    &owner_headers,
+                    header,
+                    includes_of,
+                    DEPENDENT_REFRESH_HEADER_SCAN_CAP,
+                    DEPENDENT_REFRESH_OWNER_CAP,
+                ));
+            }
+            owners.into_iter().take(DEPENDENT_REFRESH_OWNER_CAP).collect::<Vec<_>>()
+        })
+        .await
+        .unwrap_or_default();
+        if owners.is_empty() {
+            return;
+        }
 
-                if let Ok(metadata) = entry.metadata()
-                    && metadata.len() > max_file_size_bytes
-                {
-                    debug!("Skipping large workspace shader file ({} bytes): {}", metadata.len(), path.display());
-                    continue;
+        let include_closed = settings.diagnostics.scope.is_workspace();
+        let options = Arc::new(DiagnosticsOptions::from_settings(settings));
+        let closed_options = Arc::new(DiagnosticsOptions::from_settings(settings).for_closed_files());
+        info!("Refreshing diagnostics for {} file(s) depending on saved header(s)", owners.len());
+        let exclusions = PathExclusions::new(&self.workspace_roots, &settings.diagnostics.exclude);
+
+        let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(settings.indexing.concurrency));
+        let mut handles = Vec::with_capacity(owners.len());
+        for path in owners {
+            if exclusions.is_excluded(&path) {
+                continue;
+            }
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            let is_open = self.document_store.get(&uri).is_some();
+            if !is_open && !include_closed {
+                continue;
+            }
+
+            let options = options.clone();
+            let closed_options = closed_options.clone();
+            let sem = semaphore.clone();
+            let compiler = self.compiler.clone();
+            let workspace_roots = self.workspace_roots.clone();
+            let header_owners = self.header_owners.clone();
+            let owner_headers = self.owner_headers.clone();
+            let include_paths_cache = self.include_paths_cache.clone();
+            let include_graphs = self.include_graphs.clone();
+            let definitions = self.definition_provider.clone();
+            let workspace_generation = self.workspace_generation;
+            let open_documents = self.document_store.clone();
+            let diagnostics_generation = self.diagnostics_generation.clone();
+            let diagnostics_scheduler = self.diagnostics_scheduler.clone();
+            let client = self.client.clone();
+
+            handles.push(tokio::spawn(async move {
+                let _permit = sem.acquire().await;
+                let context = DiagnosticsContext {
+                    compiler: &compiler,
+                    workspace_roots: &workspace_roots,
+                    header_owners: &header_owners,
+                    owner_headers: &owner_headers,
+                    include_paths_cache: &include_paths_cache,
+                    include_graphs: &include_graphs,
+                    workspace_generation,
+                };
+                if !is_open {
+                    publish_workspace_diagnostics_for_file(
+                        &client,
+                        &context,
+                        &definitions,
+                        &closed_options,
+                        &open_documents,
+                        &diagnostics_generation,
+                        path,
+                    )
+                    .await;
+                    return;
                 }
 
-                let normalized = normalize_path(path);
-                if seen.insert(normalized.clone()) {
-                    files.push(normalized);
+                let Some(document) = open_documents.get(&uri) else {
+                    return;
+                };
+                let generation = next_diagnostic_generation(&diagnostics_generation, &uri);
+                let Some(ticket) = diagnostics_scheduler.schedule(&uri, generation).await else {
+                    return;
+                };
+                let diagnostics = compile_filtered_diagnostics_for_document(
+                    &context,
+                    &definitions,
+                    &options,
+                    &uri,
+                    &document.text,
+                    document.content_hash(),
+                )
+                .await;
+                ticket.finish();
+                if !is_latest_diagnostic_generation(&diagnostics_generation, &uri, generation) {
+                    return;
                 }
+                let result = AssertUnwindSafe(client.publish_diagnostics(uri, diagnostics, Some(document.version)))
+                    .catch_unwind()
+                    .await;
+                if result.is_err() {
+                    warn!("publish_diagnostics panicked (client may have disconnected)");
+                }
+            }));
+        }
+
+        for handle in handles {
+            let _ = handle.await;
+        }
+    }
+
+    fn discover_metal_files(
+        &self,
+        settings: &IndexingSettings,
+        exclusions: &PathExclusions,
+    ) -> Vec<PathBuf> {
+        let max_file_size_bytes = settings.max_file_size_bytes();
+        discover_workspace_files(
+            &self.workspace_roots,
+            max_file_size_bytes,
+            exclusions,
+            settings.respect_gitignore,
+            |path| path.extension().is_some_and(|ext| ext == "metal"),
+        )
+    }
+}
+
+/// Walk the workspace roots and collect files accepted by `include`.
+///
+/// Applies the same directory exclusions and size limit as background
+/// indexing, and skips what `.gitignore` files ignore when
+/// `respect_gitignore` is set.
+pub(super) fn discover_workspace_files(
+    workspace_roots: &[PathBuf],
+    max_file_size_bytes: u64,
+    exclusions: &PathExclusions,
+    respect_gitignore: bool,
+    include: impl Fn(&Path) -> bool,
+) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    let mut gitignore = respect_gitignore.then(|| Gitignore::new(workspace_roots));
+
+    for root in workspace_roots {
+        for entry in WalkDir::new(root)
+            .follow_links(true)
+            .into_iter()
+            .filter_entry(|entry| {
+                should_descend_into_workspace_entry(entry, exclusions)
+                    && !gitignore
+                        .as_mut()
+                        .is_some_and(|gitignore| gitignore.is_ignored_entry(entry.path(), entry.file_type().is_dir()))
+            })
+            .filter_map(|e| e.ok())
+        {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let path = entry.path();
+            if !include(path) {
+                continue;
+            }
+
+            if let Ok(metadata) = entry.metadata()
+                && metadata.len() > max_file_size_bytes
+            {
+                debug!("Skipping large workspace shader file ({} bytes): {}", metadata.len(), path.display());
+                continue;
+            }
+
+            let normalized = normalize_path(path);
+            if seen.insert(normalized.clone()) {
+                files.push(normalized);
             }
         }
-        files
     }
+    files
 }
 
 struct WorkspaceDiagnosticsFileResult {
@@ -387,12 +864,9 @@ struct WorkspaceDiagnosticsFileResult {
 
 async fn publish_workspace_diagnostics_for_file(
     client: &tower_lsp::Client,
-    compiler: &crate::metal::compiler::MetalCompiler,
-    workspace_roots: &[PathBuf],
-    header_owners: &DashMap<PathBuf, BTreeSet<PathBuf>>,
-    owner_headers: &DashMap<PathBuf, BTreeSet<PathBuf>>,
-    include_paths_cache: &DashMap<PathBuf, (u64, Vec<String>)>,
-    workspace_generation: u64,
+    context: &DiagnosticsContext<'_>,
+    definitions: &crate::definition::DefinitionProvider,
+    options: &DiagnosticsOptions,
     open_documents: &crate::document::DocumentStore,
     diagnostics_generation: &DashMap<Url, u64>,
     path: PathBuf,
@@ -419,11 +893,14 @@ async fn publish_workspace_diagnostics_for_file(
     }
 
     // Prefer in-memory content for open files, fall back to disk.
-    let source = if let Some(text) = open_documents.get_content(&uri) {
-        text
+    let (source, source_hash) = if let Some(content) = open_documents.get_content_with_hash(&uri) {
+        content
     } else {
         match tokio::fs::read_to_string(&path).await {
-            Ok(s) => s,
+            Ok(s) => {
+                let hash = ContentHash::of(&s);
+                (s, hash)
+            },
             Err(_) => {
                 return WorkspaceDiagnosticsFileResult {
                     path,
@@ -435,17 +912,8 @@ async fn publish_workspace_diagnostics_for_file(
         }
     };
 
-    let diagnostics = compile_filtered_diagnostics_for_document(
-        compiler,
-        workspace_roots,
-        header_owners,
-        owner_headers,
-        include_paths_cache,
-        workspace_generation,
-        &uri,
-        &source,
-    )
-    .await;
+    let diagnostics =
+        compile_filtered_diagnostics_for_document(context, definitions, options, &uri, &source, source_hash).await;
     let diagnostic_count = diagnostics.len();
 
     let result = AssertUnwindSafe(client.publish_diagnostics(uri, diagnostics, None)).catch_unwind().await;
@@ -462,10 +930,10 @@ async fn publish_workspace_diagnostics_for_file(
 
 fn should_descend_into_workspace_entry(
     entry: &DirEntry,
-    excluded_prefixes: &[PathBuf],
+    exclusions: &PathExclusions,
 ) -> bool {
     let normalized = normalize_path(entry.path());
-    if is_path_excluded(&normalized, excluded_prefixes) {
+    if exclusions.is_excluded(&normalized) {
         return false;
     }
 
@@ -488,48 +956,14 @@ fn should_descend_into_workspace_entry(
     !matches!(name, "target" | "build" | "node_modules" | "out" | "bin" | "obj" | "DerivedData")
 }
 
-fn build_workspace_scan_exclude_prefixes(
-    workspace_roots: &[PathBuf],
-    exclude_paths: &[String],
-) -> Vec<PathBuf> {
-    let mut excluded_prefixes = Vec::new();
-    let mut seen = HashSet::new();
-
-    for raw_path in exclude_paths {
-        let exclude_path = PathBuf::from(raw_path);
-        if exclude_path.is_absolute() {
-            let normalized = normalize_path(&exclude_path);
-            if seen.insert(normalized.clone()) {
-                excluded_prefixes.push(normalized);
-            }
-            continue;
-        }
-
-        for workspace_root in workspace_roots {
-            let normalized = normalize_path(&workspace_root.join(&exclude_path));
-            if seen.insert(normalized.clone()) {
-                excluded_prefixes.push(normalized);
-            }
-        }
-    }
-
-    excluded_prefixes
-}
-
-fn is_path_excluded(
-    path: &Path,
-    excluded_prefixes: &[PathBuf],
-) -> bool {
-    excluded_prefixes.iter().any(|excluded_prefix| path.starts_with(excluded_prefix))
-}
-
 /// Compute include paths for a file during project scanning.
-fn compute_include_paths_for(
+pub(super) fn compute_include_paths_for(
     file: &PathBuf,
     workspace_roots: &[PathBuf],
     compiler: &crate::metal::compiler::MetalCompiler,
 ) -> Vec<String> {
     let mut paths = crate::metal::compiler::compute_include_paths(file, Some(workspace_roots));
+    paths.extend(compiler.virtual_include_dir(file, workspace_roots).map(|dir| dir.display().to_string()));
     let system_paths = compiler.get_system_include_paths();
     paths.extend(system_paths.iter().map(|p| p.display().to_string()));
     paths
@@ -551,70 +985,201 @@ pub(super) async fn compute_include_paths_for_uri_cached(
     if let Some(entry) = include_paths_cache.get(&cache_key)
         && entry.0 == workspace_generation
     {
+        crate::perf::count("includePaths.cacheHit");
         return entry.1.clone();
     }
+    crate::perf::count("includePaths.cacheMiss");
 
     let mut paths = crate::metal::compiler::compute_include_paths(&cache_key, Some(workspace_roots));
+    paths.extend(compiler.virtual_include_dir(&cache_key, workspace_roots).map(|dir| dir.display().to_string()));
     let system_paths = compiler.get_system_include_paths();
     paths.extend(system_paths.iter().map(|p| p.display().to_string()));
     include_paths_cache.insert(cache_key, (workspace_generation, paths.clone()));
     paths
 }
 
+/// Server state the diagnostics of a document are computed against.
+pub(crate) struct DiagnosticsContext<'a> {
+    pub(crate) compiler: &'a crate::metal::compiler::MetalCompiler,
+    pub(crate) workspace_roots: &'a [PathBuf],
+    pub(crate) header_owners: &'a DashMap<PathBuf, BTreeSet<PathBuf>>,
+    pub(crate) owner_headers: &'a DashMap<PathBuf, BTreeSet<PathBuf>>,
+    pub(crate) include_paths_cache: &'a DashMap<PathBuf, (u64, Vec<String>)>,
+    pub(crate) include_graphs: &'a IncludeGraphCache,
+    pub(crate) workspace_generation: u64,
+}
+
+/// The diagnostics settings that pick which checks run over a document.
+#[derive(Clone)]
+pub(crate) struct DiagnosticsOptions {
+    pub(crate) header_context: HeaderContext,
+    pub(crate) todo_tags: Vec<String>,
+    pub(crate) unused_includes: bool,
+    pub(crate) threadgroup_memory_limit: u64,
+    /// Names exempt from unused-symbol warnings; `None` turns them off.
+    pub(crate) unused_symbols_allow: Option<Vec<String>>,
+}
+
+impl DiagnosticsOptions {
+    pub(crate) fn from_settings(settings: &ServerSettings) -> Self {
+        let diagnostics = &settings.diagnostics;
+        Self {
+            header_context: diagnostics.header_context,
+            todo_tags: settings.todos.diagnostic_tags(),
+            unused_includes: diagnostics.unused_includes,
+            threadgroup_memory_limit: diagnostics.threadgroup_memory_limit,
+            unused_symbols_allow: diagnostics.unused_symbols.then(|| diagnostics.unused_symbols_allow.clone()),
+        }
+    }
+
+    /// The options of files compiled in the background rather than edited,
+    /// which leave out the unused-include and unused-symbol checks.
+    pub(crate) fn for_closed_files(self) -> Self {
+        Self {
+            unused_includes: false,
+            unused_symbols_allow: None,
+            ..self
+        }
+    }
+}
+
 pub(super) async fn compile_filtered_diagnostics_for_document(
-    compiler: &crate::metal::compiler::MetalCompiler,
-    workspace_roots: &[PathBuf],
-    header_owners: &DashMap<PathBuf, BTreeSet<PathBuf>>,
-    owner_headers: &DashMap<PathBuf, BTreeSet<PathBuf>>,
-    include_paths_cache: &DashMap<PathBuf, (u64, Vec<String>)>,
-    workspace_generation: u64,
+    context: &DiagnosticsContext<'_>,
+    definitions: &crate::definition::DefinitionProvider,
+    options: &DiagnosticsOptions,
     uri: &Url,
     text: &str,
+    text_hash: ContentHash,
 ) -> Vec<Diagnostic> {
-    let target_path = uri.to_file_path().ok().map(|p| normalize_path(&p));
+    let DiagnosticsContext {
+        compiler,
+        workspace_roots,
+        include_paths_cache,
+        workspace_generation,
+        ..
+    } = *context;
+    let target_path = document_path(uri).map(|p| normalize_path(&p));
     let strict_file_match = target_path.as_ref().is_some_and(|path| is_header_file(path));
 
     let raw_diagnostics = if strict_file_match {
-        if let Some(path) = target_path.as_deref() {
-            compile_header_owner_diagnostics(
+        let mut raw_diagnostics = Vec::new();
+        if options.header_context.uses_owner()
+            && let Some(path) = target_path.as_deref()
+        {
+            raw_diagnostics = compile_header_owner_diagnostics(context, uri, path).await;
+        }
+        if options.header_context.uses_standalone() {
+            // Duplicates of owner diagnostics are dropped by the filter below.
+            raw_diagnostics.extend(
+                compile_document(compiler, workspace_roots, include_paths_cache, workspace_generation, uri, text).await,
+            );
+        }
+        raw_diagnostics
+    } else {
+        compile_document(compiler, workspace_roots, include_paths_cache, workspace_generation, uri, text).await
+    };
+
+    let mut diagnostics = filter_target_diagnostics(raw_diagnostics, target_path.as_deref(), strict_file_match);
+    widen_deprecation_ranges(&mut diagnostics, text);
+    let index = definitions.get_cached_index(uri);
+    let language_version = compiler.language_version(uri);
+    if diagnostics.iter().any(|diagnostic| unknown_identifier(&diagnostic.message).is_some()) {
+        let project_names = definitions.project_index().definition_names();
+        let tree = SyntaxTree::parse(text);
+        let names = identifier_names(&tree.root(), text);
+        let candidates: HashSet<&str> = project_names
+            .iter()
+            .map(String::as_str)
+            .chain(index.iter().flat_map(|index| index.name_to_defs.keys().map(SharedStr::as_str)))
+            .chain(
+                crate::metal::builtins::all()
+                    .iter()
+                    .filter(|entry| entry.is_available_in(language_version))
+                    .map(|entry| entry.label.as_str()),
+            )
+            .chain(names)
+            .collect();
+        suggest_identifiers(&mut diagnostics, uri, &candidates);
+    }
+    diagnostics.extend(macro_conflict_diagnostics(context, uri, text, text_hash).await);
+    diagnostics.extend(
+        include_cycle_diagnostics(compiler, workspace_roots, include_paths_cache, workspace_generation, uri, text)
+            .await,
+    );
+    if let Some(path) = target_path.as_deref().filter(|path| is_header_file(path)) {
+        diagnostics.extend(include_guard_diagnostics(path, text, workspace_roots));
+    }
+    let tree = SyntaxTree::parse(text);
+    diagnostics.extend(duplicate_bindings(&tree.root(), text, uri));
+    diagnostics.extend(address_space_diagnostics(&tree.root(), text));
+    diagnostics.extend(entry_point_attribute_diagnostics(&tree.root(), text));
+    if options.threadgroup_memory_limit > 0 {
+        let mut macros = MacroIndex::from_defines(&compiler.effective_defines(uri));
+        macros.add_source(text, target_path.as_deref());
+        diagnostics.extend(threadgroup_memory_diagnostics(
+            &tree.root(),
+            text,
+            uri,
+            &macros,
+            options.threadgroup_memory_limit,
+        ));
+    }
+    if let Some(version) = language_version {
+        diagnostics.extend(unavailable_builtins(&tree.root(), text, version, &compiler.effective_defines(uri)));
+    }
+    if let Some(index) = index.as_deref().filter(|_| options.unused_includes) {
+        diagnostics.extend(
+            unused_include_diagnostics(
                 compiler,
                 workspace_roots,
-                header_owners,
-                owner_headers,
                 include_paths_cache,
                 workspace_generation,
+                index,
                 uri,
-                path,
+                text,
             )
-            .await
-        } else {
-            Vec::new()
-        }
-    } else {
-        let include_paths = compute_include_paths_for_uri_cached(
-            compiler,
-            uri,
-            workspace_roots,
-            include_paths_cache,
-            workspace_generation,
-        )
-        .await;
-        compiler.compile_with_include_paths(text, uri.as_str(), &include_paths).await
-    };
-
-    filter_target_diagnostics(raw_diagnostics, target_path.as_deref(), strict_file_match)
+            .await,
+        );
+    }
+    if let Some((allow, path)) = options.unused_symbols_allow.as_deref().zip(target_path.as_deref()) {
+        diagnostics.extend(unused_symbol_diagnostics(definitions.project_index(), path, text, allow));
+    }
+    if !options.todo_tags.is_empty() {
+        let tree = SyntaxTree::parse(text);
+        diagnostics.extend(find_todos(&tree.root(), text, &options.todo_tags).iter().map(TodoComment::to_diagnostic));
+    }
+    diagnostics
 }
 
-async fn compile_header_owner_diagnostics(
+/// Compile `text` as a translation unit of its own.
+async fn compile_document(
     compiler: &crate::metal::compiler::MetalCompiler,
     workspace_roots: &[PathBuf],
-    header_owners: &DashMap<PathBuf, BTreeSet<PathBuf>>,
-    owner_headers: &DashMap<PathBuf, BTreeSet<PathBuf>>,
     include_paths_cache: &DashMap<PathBuf, (u64, Vec<String>)>,
     workspace_generation: u64,
+    uri: &Url,
+    text: &str,
+) -> Vec<MetalDiagnostic> {
+    let include_paths =
+        compute_include_paths_for_uri_cached(compiler, uri, workspace_roots, include_paths_cache, workspace_generation)
+            .await;
+    compiler.compile_with_include_paths(text, uri.as_str(), &include_paths).await
+}
+
+async fn compile_header_owner_diagnostics(
+    context: &DiagnosticsContext<'_>,
     header_uri: &Url,
     header_path: &Path,
 ) -> Vec<MetalDiagnostic> {
+    let DiagnosticsContext {
+        compiler,
+        workspace_roots,
+        header_owners,
+        owner_headers,
+        include_paths_cache,
+        workspace_generation,
+        ..
+    } = *context;
     let normalized_header = normalize_path(header_path);
     let mut owners = get_owner_candidates_for_header(header_owners, &normalized_header, HEADER_OWNER_COMPILE_CAP);
     if owners.is_empty() {
@@ -701,15 +1266,21 @@ fn filter_target_diagnostics(
     for diag in diagnostics {
         if diag.severity == DiagnosticSeverity::INFORMATION {
             if last_primary_kept {
-                let note_location = diag.file.as_deref().and_then(|f| Url::from_file_path(f).ok()).map(|uri| {
-                    let pos = Position::new(diag.line, diag.column);
-                    Location {
-                        uri,
-                        range: Range::new(pos, pos),
-                    }
+                let note_location = diag.file.as_deref().and_then(|f| document_uri(Path::new(f))).map(|uri| Location {
+                    uri,
+                    range: diag.range(),
                 });
+                let last = out.last_mut().expect("last_primary_kept implies non-empty");
+                if !diag.fix_its.is_empty() {
+                    attach_fix(
+                        last,
+                        CompilerFix {
+                            message: Some(diag.message.clone()),
+                            edits: diag.fix_its,
+                        },
+                    );
+                }
                 if let Some(location) = note_location {
-                    let last = out.last_mut().expect("last_primary_kept implies non-empty");
                     let related = last.related_information.get_or_insert_with(Vec::new);
                     related.push(DiagnosticRelatedInformation {
                         location,
@@ -748,7 +1319,7 @@ fn filter_target_diagnostics(
     out
 }
 
-fn should_suppress_primary_diagnostic(diag: &MetalDiagnostic) -> bool {
+pub(super) fn should_suppress_primary_diagnostic(diag: &MetalDiagnostic) -> bool {
     diag.severity == DiagnosticSeverity::WARNING && diag.message.contains("[-Wmacro-redefined]")
 }
 
@@ -0,0 +1,777 @@
+//! `metal-analyzer/*` LSP protocol extensions.
+//!
+//! Each request or notification is described by a marker type implementing
+//! [`lsp_types::request::Request`] or [`lsp_types::notification::Notification`]
+//! so clients and tests can share the method name and the params/result
+//! shapes.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{
+    DiagnosticSeverity, DocumentLink, GotoDefinitionResponse, Hover, Location, Position, Range, TextDocumentIdentifier,
+    TextDocumentPositionParams, Url, VersionedTextDocumentIdentifier, notification::Notification, request::Request,
+};
+
+/// Find every declaration bound to the same slot as the attribute under the cursor.
+pub enum BindingUses {}
+
+impl Request for BindingUses {
+    type Params = BindingUsesParams;
+    type Result = Option<Vec<BindingUse>>;
+    const METHOD: &'static str = "metal-analyzer/bindingUses";
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BindingUsesScope {
+    /// Only the document containing the cursor.
+    File,
+    /// The current document, all open documents, and workspace shader sources.
+    #[default]
+    Workspace,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BindingUsesParams {
+    #[serde(flatten)]
+    pub text_document_position: TextDocumentPositionParams,
+    #[serde(default)]
+    pub scope: BindingUsesScope,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BindingUse {
+    pub location: Location,
+    /// Binding attribute text, e.g. `buffer(2)`.
+    pub attribute: String,
+    /// Function whose parameter carries the attribute, if any.
+    pub function: Option<String>,
+    /// Parameter or field name carrying the attribute, if any.
+    pub declarator: Option<String>,
+}
+
+/// Find the entry points that transitively call the function enclosing the cursor.
+pub enum EnclosingEntryPoints {}
+
+impl Request for EnclosingEntryPoints {
+    type Params = TextDocumentPositionParams;
+    type Result = Option<Vec<EnclosingEntryPoint>>;
+    const METHOD: &'static str = "metal-analyzer/enclosingEntryPoints";
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnclosingEntryPoint {
+    pub name: String,
+    /// Entry-point qualifier: `kernel`, `vertex`, `fragment`, `mesh` or `object`.
+    pub stage: String,
+    /// Location of the entry point's name.
+    pub location: Location,
+    /// Shortest call chain from the entry point down to the function at the cursor.
+    pub call_path: Vec<String>,
+}
+
+/// Render the cached AST index of a document as a read-only virtual document.
+///
+/// The returned `metal-analyzer-cache://` URI also answers `textDocument/hover`
+/// and `textDocument/definition` for the locations listed in the view.
+pub enum AstCacheView {}
+
+impl Request for AstCacheView {
+    type Params = AstCacheViewParams;
+    type Result = Option<AstCacheViewDocument>;
+    const METHOD: &'static str = "metal-analyzer/astCacheView";
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AstCacheViewParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AstCacheViewDocument {
+    /// `metal-analyzer-cache://` URI of the view.
+    pub uri: Url,
+    pub content: String,
+    /// Clickable locations; targets are `file://` URIs with an `#L<line>,<column>` fragment.
+    pub links: Vec<DocumentLink>,
+}
+
+/// List every by-name definition candidate for the symbol under the cursor
+/// with the `navigation.ranking` penalties that ordered it.
+///
+/// Candidates come from the document's cached AST index and the project
+/// index, in the order the ranked go-to-definition fallbacks consider them.
+pub enum ExplainDefinitionRanking {}
+
+impl Request for ExplainDefinitionRanking {
+    type Params = TextDocumentPositionParams;
+    type Result = Option<DefinitionRanking>;
+    const METHOD: &'static str = "metal-analyzer/explainDefinitionRanking";
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefinitionRanking {
+    pub symbol: String,
+    /// Best candidate first; equal scores are ambiguous unless a member or
+    /// parameter tie-break applies.
+    pub candidates: Vec<RankedDefinition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedDefinition {
+    pub location: Location,
+    /// Clang declaration kind, e.g. `FunctionDecl`.
+    pub kind: String,
+    pub is_definition: bool,
+    /// Whether the candidate came from the project index rather than the
+    /// document's AST index.
+    pub from_project_index: bool,
+    /// Sum of the penalties; lower ranks first.
+    pub score: u32,
+    pub penalties: Vec<RankPenalty>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankPenalty {
+    /// Criterion the candidate runs into, e.g. `other file`.
+    pub reason: String,
+    pub penalty: u32,
+}
+
+/// Expand the macro invocation under the cursor one step and fully.
+///
+/// Definitions come from the effective `-D` flags, the document and the
+/// project headers it includes; `null` when the cursor is not on a macro.
+pub enum ExpandMacro {}
+
+impl Request for ExpandMacro {
+    type Params = TextDocumentPositionParams;
+    type Result = Option<ExpandedMacro>;
+    const METHOD: &'static str = "metal-analyzer/expandMacro";
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpandedMacro {
+    pub name: String,
+    /// The macro name and its argument list, if any.
+    pub range: Range,
+    /// The `#define` line; `None` for macros from compiler flags.
+    pub definition: Option<Location>,
+    /// The replacement list with the arguments substituted as written;
+    /// `None` when a function-like macro has no matching argument list.
+    pub single_step: Option<String>,
+    /// The replacement after expanding every macro it contains.
+    pub full: Option<String>,
+}
+
+/// Resolve `textDocument/definition` at many positions of one document.
+///
+/// Every position is answered from the same document text, syntax tree and
+/// include paths, so tooling that annotates whole files pays the per-request
+/// setup once. The result has one entry per position, in order.
+pub enum Definitions {}
+
+impl Request for Definitions {
+    type Params = BatchPositionsParams;
+    type Result = Option<Vec<Option<GotoDefinitionResponse>>>;
+    const METHOD: &'static str = "metal-analyzer/definitions";
+}
+
+/// Resolve `textDocument/hover` at many positions of one document, sharing
+/// the document state like [`Definitions`].
+pub enum Hovers {}
+
+impl Request for Hovers {
+    type Params = BatchPositionsParams;
+    type Result = Option<Vec<Option<Hover>>>;
+    const METHOD: &'static str = "metal-analyzer/hovers";
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchPositionsParams {
+    pub text_document: TextDocumentIdentifier,
+    pub positions: Vec<Position>,
+}
+
+/// Ranges of a document disabled by preprocessor conditionals under the
+/// effective compiler defines, pushed after every open and change.
+///
+/// Only sent to clients that set `experimental.inactiveRegions` in their
+/// capabilities.
+pub enum InactiveRegions {}
+
+impl Notification for InactiveRegions {
+    type Params = InactiveRegionsParams;
+    const METHOD: &'static str = "metal-analyzer/inactiveRegions";
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InactiveRegionsParams {
+    pub text_document: VersionedTextDocumentIdentifier,
+    /// Whole-line ranges, excluding the conditional directives themselves.
+    pub regions: Vec<Range>,
+}
+
+/// List the comments starting with one of the `todos.tags`.
+///
+/// Without a document, every open document and workspace shader source is
+/// scanned. Results are ordered by URI and position.
+pub enum Todos {}
+
+impl Request for Todos {
+    type Params = TodosParams;
+    type Result = Option<Vec<TodoItem>>;
+    const METHOD: &'static str = "metal-analyzer/todos";
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TodosParams {
+    /// Restrict the scan to one document.
+    #[serde(default)]
+    pub text_document: Option<TextDocumentIdentifier>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TodoItem {
+    /// From the tag to the end of the comment line.
+    pub location: Location,
+    pub tag: String,
+    /// Name in `TODO(name):`, if any.
+    pub owner: Option<String>,
+    pub text: String,
+}
+
+/// A SARIF 2.1.0 log of the diagnostics last published for open documents,
+/// for CI annotation tools such as GitHub code scanning.
+///
+/// Without a document, every document with published diagnostics is
+/// included. Paths are written relative to the workspace roots.
+pub enum SarifLog {}
+
+impl Request for SarifLog {
+    type Params = SarifLogParams;
+    type Result = serde_json::Value;
+    const METHOD: &'static str = "metal-analyzer/sarif";
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifLogParams {
+    /// Restrict the log to one document.
+    #[serde(default)]
+    pub text_document: Option<TextDocumentIdentifier>,
+}
+
+/// Compile an open document to `.air` and link it into a `.metallib` in the
+/// server's temp directory, for a "Build shader" command.
+///
+/// Returns `None` when the document is not open, and an error when the Metal
+/// toolchain cannot be run.
+pub enum CompileBinary {}
+
+impl Request for CompileBinary {
+    type Params = CompileBinaryParams;
+    type Result = Option<CompiledBinaryReport>;
+    const METHOD: &'static str = "metal-analyzer/compileBinary";
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompileBinaryParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompiledBinaryReport {
+    /// Whether both the `.air` and the `.metallib` were produced.
+    pub success: bool,
+    pub air: Option<BinaryFile>,
+    pub metallib: Option<BinaryFile>,
+    /// Entry points in the document's active preprocessor branches.
+    pub entry_points: Vec<BinaryEntryPoint>,
+    /// Warnings and errors from compiling and linking.
+    pub diagnostics: Vec<BinaryDiagnostic>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinaryFile {
+    pub path: String,
+    /// Size in bytes.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinaryEntryPoint {
+    pub name: String,
+    /// Entry-point qualifier: `kernel`, `vertex`, `fragment`, `mesh` or `object`.
+    pub stage: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinaryDiagnostic {
+    /// Where the compiler reported it, which may be an included header.
+    pub location: Location,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+}
+
+/// Statistics of each entry point of an open document, read from a compile
+/// of its current text (see [`crate::metal::kernel_stats`]).
+///
+/// Returns `None` when the document is not open, and an error when the Metal
+/// toolchain cannot be run or the document does not compile.
+pub enum KernelStats {}
+
+impl Request for KernelStats {
+    type Params = KernelStatsParams;
+    type Result = Option<Vec<EntryPointStats>>;
+    const METHOD: &'static str = "metal-analyzer/kernelStats";
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KernelStatsParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryPointStats {
+    pub name: String,
+    /// Entry-point qualifier: `kernel`, `vertex`, `fragment`, `mesh` or `object`.
+    pub stage: String,
+    /// Location of the entry point's name, when it is found in the document.
+    pub location: Option<Location>,
+    pub instructions: usize,
+    pub basic_blocks: usize,
+    pub threadgroup_memory_bytes: u64,
+    /// Stack memory left after optimization, a sign of register spills.
+    pub private_memory_bytes: u64,
+}
+
+/// Compile an open document with line tables and return its textual AIR
+/// (LLVM IR), for a panel showing what an entry point compiled to.
+///
+/// The entry point is the one enclosing the position, or the first entry
+/// point of the document calling the function there. Returns `None` when
+/// the document is not open or no entry point is found, and an error when
+/// the Metal toolchain cannot be run or the document does not compile.
+pub enum Disassemble {}
+
+impl Request for Disassemble {
+    type Params = TextDocumentPositionParams;
+    type Result = Option<Disassembly>;
+    const METHOD: &'static str = "metal-analyzer/disassemble";
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Disassembly {
+    /// Name of the selected entry point.
+    pub entry_point: String,
+    /// Entry-point qualifier: `kernel`, `vertex`, `fragment`, `mesh` or `object`.
+    pub stage: String,
+    /// The whole IR module, since helpers that were not inlined are defined
+    /// next to the entry point.
+    pub ir: String,
+    /// Functions defined in `ir`, the entry point first.
+    pub functions: Vec<IrFunctionInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IrFunctionInfo {
+    pub name: String,
+    /// Zero-based lines of the `define` and its closing `}` in `ir`.
+    pub ir_start_line: u32,
+    pub ir_end_line: u32,
+    /// Zero-based line of the definition in the document, when known.
+    pub source_line: Option<u32>,
+    /// IR lines of the function mapped to the document lines they came from.
+    pub lines: Vec<IrSourceLine>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IrSourceLine {
+    pub ir_line: u32,
+    pub source_line: u32,
+}
+
+/// Drop the cached AST index and include paths of an open document, then
+/// rebuild them and re-run its diagnostics.
+///
+/// Returns `false` when the document is not open.
+pub enum RebuildFile {}
+
+impl Request for RebuildFile {
+    type Params = RebuildFileParams;
+    type Result = bool;
+    const METHOD: &'static str = "metal-analyzer/rebuildFile";
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RebuildFileParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+/// Re-run the AST dumps of one file, open or not, or of every workspace
+/// source, bypassing the in-memory and on-disk caches, e.g. after a
+/// toolchain upgrade.
+///
+/// Without a document, the caches are cleared as by `clearCache` and the
+/// workspace is scanned again. Returns how many files are indexed.
+pub enum Reindex {}
+
+impl Request for Reindex {
+    type Params = ReindexParams;
+    type Result = usize;
+    const METHOD: &'static str = "metal-analyzer/reindex";
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReindexParams {
+    /// Restrict the reindex to one document.
+    #[serde(default)]
+    pub text_document: Option<TextDocumentIdentifier>,
+}
+
+/// Drop every cached AST index, in memory and on disk, and the persisted
+/// project index of the workspace. Files are dumped again when next needed.
+///
+/// Returns how many cache files were deleted.
+pub enum ClearCache {}
+
+impl Request for ClearCache {
+    type Params = ();
+    type Result = usize;
+    const METHOD: &'static str = "metal-analyzer/clearCache";
+}
+
+/// Latency histograms and counters recorded since startup, for attaching to
+/// performance bug reports.
+pub enum Perf {}
+
+impl Request for Perf {
+    type Params = PerfParams;
+    type Result = PerfReport;
+    const METHOD: &'static str = "metal-analyzer/perf";
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerfParams {
+    /// Start recording over after reporting.
+    #[serde(default)]
+    pub reset: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerfReport {
+    /// Seconds recorded over, since startup or the last reset.
+    pub elapsed_secs: u64,
+    /// LSP requests by method, `gotoDefinition.<tier>` by the index tier
+    /// that answered, `astDump` and `diagnostics.compile`.
+    pub latencies: Vec<LatencyHistogram>,
+    /// Where AST indexes were loaded from, and include path cache hits.
+    pub counters: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyHistogram {
+    pub name: String,
+    pub count: u64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    /// Upper bound of the bucket holding the median; `None` past the last bound.
+    pub p50_ms: Option<u64>,
+    pub p90_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+    pub buckets: Vec<HistogramBucket>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistogramBucket {
+    /// Samples faster than this many milliseconds and not in an earlier
+    /// bucket; `None` for the last bucket.
+    pub under_ms: Option<u64>,
+    pub count: u64,
+}
+
+/// Run the preprocessor over a document with the include paths and flags of
+/// its diagnostics compile, returning the output and where each line came
+/// from. Lines from system headers are left out unless asked for, and
+/// `entryPoint` cuts the output down to what one function depends on.
+///
+/// Returns `None` when the document is not open, and an error when
+/// preprocessing fails or the entry point is not found.
+pub enum Preprocess {}
+
+impl Request for Preprocess {
+    type Params = PreprocessParams;
+    type Result = Option<PreprocessedDocument>;
+    const METHOD: &'static str = "metal-analyzer/preprocess";
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreprocessParams {
+    pub text_document: TextDocumentIdentifier,
+    #[serde(default)]
+    pub entry_point: Option<String>,
+    /// Keep the lines of system headers such as `metal_stdlib`, which make up
+    /// most of the output. Ignored with `entryPoint`.
+    #[serde(default)]
+    pub include_system_headers: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreprocessedDocument {
+    /// Preprocessed source without line markers.
+    pub text: String,
+    /// For each line of `text`, the file and zero-based line it came from;
+    /// `None` for compiler built-ins.
+    pub line_map: Vec<Option<PreprocessedLineOrigin>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreprocessedLineOrigin {
+    pub uri: Url,
+    pub line: u32,
+}
+
+/// Write a state snapshot for a bug report to a zip in the log directory:
+/// open documents, settings, cache stats, the last log lines and the
+/// toolchain version. Returns the path of the zip.
+pub enum CaptureState {}
+
+impl Request for CaptureState {
+    type Params = ();
+    type Result = CapturedState;
+    const METHOD: &'static str = "metal-analyzer/captureState";
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedState {
+    pub path: String,
+}
+
+/// Report the server's health: whether the Metal toolchain works, the SDK
+/// include paths, what is indexed and cached, and the jobs in flight.
+pub enum Status {}
+
+impl Request for Status {
+    type Params = ();
+    type Result = ServerStatus;
+    const METHOD: &'static str = "metal-analyzer/status";
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Health {
+    Ok,
+    /// Something failed since the server started; see `lastError`.
+    Warning,
+    /// The Metal toolchain or SDK is unavailable, so compiler-backed
+    /// features do not work.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatus {
+    pub health: Health,
+    /// Whether no background job is running.
+    pub quiescent: bool,
+    /// What the health is about, when it is not `ok`.
+    pub message: Option<String>,
+    /// `None` until the toolchain has been probed after `initialized`.
+    pub toolchain_available: Option<bool>,
+    /// Include paths discovered from `xcrun metal -v`.
+    pub system_include_paths: Vec<String>,
+    /// Files in the project index.
+    pub indexed_files: usize,
+    pub cache: CacheStatus,
+    /// Workspace scans and diagnostics runs in flight.
+    pub pending_jobs: usize,
+    pub diagnostics_queue: DiagnosticsQueueStatus,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStatus {
+    /// Approximate bytes held by in-memory AST indexes.
+    pub memory_bytes: usize,
+    /// AST indexes in the on-disk cache.
+    pub disk_entries: usize,
+    pub disk_bytes: u64,
+}
+
+/// Diagnostics compiles of open documents.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsQueueStatus {
+    pub running: usize,
+    /// Documents with a compile waiting for a slot or for their running one.
+    pub queued: usize,
+    /// Most compiles running at once, from `threadPool.workerThreads`.
+    pub limit: usize,
+    /// Compiles replaced by a later one of the same document before running.
+    pub coalesced: u64,
+    pub completed: u64,
+}
+
+/// The server's health or quiescence changed, for editors to render a
+/// status-bar item. Call `metal-analyzer/status` for the details.
+///
+/// Only sent to clients that set `experimental.statusChanged` in their
+/// capabilities.
+pub enum StatusChanged {}
+
+impl Notification for StatusChanged {
+    type Params = StatusChangedParams;
+    const METHOD: &'static str = "metal-analyzer/statusChanged";
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusChangedParams {
+    pub health: Health,
+    pub quiescent: bool,
+    pub message: Option<String>,
+}
+
+/// The JSON Schema of the settings under `metal-analyzer`, with each
+/// setting's type, default, description and bounds, for clients building a
+/// settings UI or validating user configuration.
+pub enum ConfigurationSchema {}
+
+impl Request for ConfigurationSchema {
+    type Params = ();
+    type Result = serde_json::Value;
+    const METHOD: &'static str = "metal-analyzer/configurationSchema";
+}
+
+/// List the kernel, vertex, fragment, mesh and object functions with their
+/// attributes, resource bindings and pipelines.
+///
+/// Without a document, every open document and workspace shader source is
+/// scanned. Results are ordered by URI and position.
+pub enum EntryPoints {}
+
+impl Request for EntryPoints {
+    type Params = EntryPointsParams;
+    type Result = Option<Vec<EntryPointInfo>>;
+    const METHOD: &'static str = "metal-analyzer/entryPoints";
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryPointsParams {
+    /// Restrict the scan to one document.
+    #[serde(default)]
+    pub text_document: Option<TextDocumentIdentifier>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryPointInfo {
+    pub name: String,
+    /// Entry-point qualifier: `kernel`, `vertex`, `fragment`, `mesh` or `object`.
+    pub stage: String,
+    /// Location of the entry point's name.
+    pub location: Location,
+    /// Attributes written before the function, e.g.
+    /// `max_total_threads_per_threadgroup(256)`.
+    pub attributes: Vec<String>,
+    pub bindings: Vec<EntryPointBinding>,
+    /// Pipelines listed in `entryPoints.pipelinesFile` that use the function.
+    pub pipelines: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryPointBinding {
+    /// `buffer`, `texture`, `sampler` or `threadgroup`.
+    pub kind: String,
+    pub index: u32,
+    /// Parameter carrying the attribute, if any.
+    pub parameter: Option<String>,
+}
+
+/// The `#include` graph of a document, or of every open document and
+/// workspace shader source, for visualization.
+///
+/// Files are listed once each; includes refer to them by index.
+pub enum IncludeGraph {}
+
+impl Request for IncludeGraph {
+    type Params = IncludeGraphParams;
+    type Result = Option<IncludeGraphResult>;
+    const METHOD: &'static str = "metal-analyzer/includeGraph";
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncludeGraphParams {
+    /// Start from one document instead of the whole workspace.
+    #[serde(default)]
+    pub text_document: Option<TextDocumentIdentifier>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncludeGraphResult {
+    pub files: Vec<IncludeGraphFile>,
+    pub includes: Vec<IncludeGraphEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncludeGraphFile {
+    pub uri: Url,
+    /// An SDK or toolchain header, whose own includes are not listed.
+    pub system: bool,
+    /// Protected by `#pragma once` or an `#ifndef` guard.
+    pub guarded: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncludeGraphEdge {
+    /// Index of the including file in `files`.
+    pub from: usize,
+    /// Index of the included file in `files`.
+    pub to: usize,
+    /// The included name in the including file.
+    pub range: Range,
+    /// Whether the included file leads back to the including one.
+    pub in_cycle: bool,
+}
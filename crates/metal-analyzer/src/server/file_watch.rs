@@ -0,0 +1,291 @@
+//! Workspace changes made outside the editor.
+//!
+//! Git operations and code generators create, modify and delete `.metal`
+//! files and headers without the editor sending `didOpen`/`didSave`. Clients
+//! that support it get a `workspace/didChangeWatchedFiles` registration;
+//! otherwise a `notify` watcher on the workspace roots feeds the same
+//! handler, so the project index, the header-owner map and diagnostics stay
+//! current without restarting the server.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use tower_lsp::lsp_types::{
+    DidChangeWatchedFilesRegistrationOptions, FileChangeType, FileSystemWatcher, GlobPattern, Registration, Url,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    server::{
+        diagnostics::{BackgroundHandle, compute_include_paths_for},
+        header_owners::{collect_included_headers, is_header_file, normalize_path, update_owner_links},
+    },
+    vfs::{gitignore::Gitignore, glob::PathExclusions, path_key},
+};
+
+/// Registration id of the `workspace/didChangeWatchedFiles` watchers.
+pub(crate) const WATCHED_FILES_REGISTRATION_ID: &str = "metal-analyzer-watched-files";
+
+/// How long the fallback watcher collects events before applying them, so a
+/// checkout touching many files is handled as one batch.
+const FALLBACK_BATCH_WINDOW: Duration = Duration::from_millis(200);
+
+/// The dynamic registration asking the client to watch sources and headers.
+pub(crate) fn watched_files_registration() -> Registration {
+    let watchers = ["**/*.metal", "**/*.{h,hh,hpp,hxx}"]
+        .into_iter()
+        .map(|glob| FileSystemWatcher {
+            glob_pattern: GlobPattern::String(glob.to_string()),
+            kind: None,
+        })
+        .collect();
+    Registration {
+        id: WATCHED_FILES_REGISTRATION_ID.to_string(),
+        method: "workspace/didChangeWatchedFiles".to_string(),
+        register_options: serde_json::to_value(DidChangeWatchedFilesRegistrationOptions {
+            watchers,
+        })
+        .ok(),
+    }
+}
+
+/// Whether changes to `path` affect indexing or diagnostics.
+pub(crate) fn is_watched_source(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "metal") || is_header_file(path)
+}
+
+/// Collapse a batch of events to one change per watched file.
+///
+/// The latest event wins, except that a file created and then modified
+/// within the batch still counts as created.
+pub(crate) fn coalesce_changes(
+    events: impl IntoIterator<Item = (PathBuf, FileChangeType)>
+) -> Vec<(PathBuf, FileChangeType)> {
+    let mut changes: BTreeMap<PathBuf, FileChangeType> = BTreeMap::new();
+    for (path, kind) in events {
+        if !is_watched_source(&path) {
+            continue;
+        }
+        let kind = match changes.get(&path) {
+            Some(&FileChangeType::CREATED) if kind == FileChangeType::CHANGED => FileChangeType::CREATED,
+            _ => kind,
+        };
+        changes.insert(path, kind);
+    }
+    changes.into_iter().collect()
+}
+
+/// Translate a `notify` event into watched-file changes.
+///
+/// Renames and platform-specific event kinds are resolved by checking
+/// whether each path still exists.
+pub(crate) fn changes_from_notify_event(event: &notify::Event) -> Vec<(PathBuf, FileChangeType)> {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return Vec::new();
+    }
+    event
+        .paths
+        .iter()
+        .map(|path| {
+            let kind = if !path.exists() {
+                FileChangeType::DELETED
+            } else if matches!(event.kind, EventKind::Create(_)) {
+                FileChangeType::CREATED
+            } else {
+                FileChangeType::CHANGED
+            };
+            (path.clone(), kind)
+        })
+        .collect()
+}
+
+/// Watch the workspace roots with `notify` and apply changes through
+/// `handle`. Dropping the returned watcher stops watching.
+pub(crate) fn start_fallback_watcher(handle: BackgroundHandle) -> Option<notify::RecommendedWatcher> {
+    if handle.workspace_roots.is_empty() {
+        return None;
+    }
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = match notify::recommended_watcher(move |result: notify::Result<notify::Event>| match result {
+        Ok(event) => {
+            let _ = sender.send(event);
+        },
+        Err(error) => warn!("File watcher error: {error}"),
+    }) {
+        Ok(watcher) => watcher,
+        Err(error) => {
+            warn!("Failed to start file watcher: {error}");
+            return None;
+        },
+    };
+    for root in &handle.workspace_roots {
+        if let Err(error) = watcher.watch(root, RecursiveMode::Recursive) {
+            warn!("Failed to watch {}: {error}", root.display());
+        }
+    }
+    info!("Watching {} workspace root(s) for file changes", handle.workspace_roots.len());
+
+    tokio::spawn(async move {
+        while let Some(first) = receiver.recv().await {
+            let mut events = changes_from_notify_event(&first);
+            let deadline = tokio::time::Instant::now() + FALLBACK_BATCH_WINDOW;
+            while let Ok(Some(event)) = tokio::time::timeout_at(deadline, receiver.recv()).await {
+                events.extend(changes_from_notify_event(&event));
+            }
+            let changes = coalesce_changes(events);
+            if !changes.is_empty() {
+                handle.apply_watched_changes(changes).await;
+            }
+        }
+    });
+    Some(watcher)
+}
+
+impl BackgroundHandle {
+    /// Bring the project index, header-owner map and diagnostics up to date
+    /// with files changed on disk.
+    ///
+    /// Files open in the editor are skipped since their buffers, not the
+    /// disk, are authoritative and the editor reports their changes itself.
+    pub(crate) async fn apply_watched_changes(
+        &self,
+        changes: Vec<(PathBuf, FileChangeType)>,
+    ) {
+        let settings = self.settings.read().await.clone();
+        let exclusions = PathExclusions::new(&self.workspace_roots, &settings.indexing.exclude_paths);
+        let mut gitignore = settings.indexing.respect_gitignore.then(|| Gitignore::new(&self.workspace_roots));
+
+        let mut changed_sources = Vec::new();
+        let mut changed_headers = BTreeSet::new();
+        for (path, kind) in coalesce_changes(changes) {
+            if kind != FileChangeType::CHANGED {
+                self.completion_provider.invalidate_include_listings();
+            }
+            if exclusions.is_excluded(&path)
+                || gitignore.as_mut().is_some_and(|gitignore| gitignore.is_ignored(&path, path.is_dir()))
+            {
+                continue;
+            }
+            let path = normalize_path(&path);
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            if self.document_store.get(&uri).is_some() {
+                continue;
+            }
+            if kind == FileChangeType::CREATED {
+                // New directories can add include search paths.
+                self.include_paths_cache.clear();
+            }
+
+            if is_header_file(&path) {
+                changed_headers.insert(path);
+            } else if kind == FileChangeType::DELETED {
+                self.remove_deleted_source(&uri, &path).await;
+            } else {
+                changed_sources.push(path);
+            }
+        }
+
+        if settings.indexing.enable {
+            self.refresh_workspace_symbols(changed_headers.iter().chain(&changed_sources).cloned().collect()).await;
+        }
+        if !changed_headers.is_empty() {
+            self.refresh_header_dependents(&changed_headers).await;
+            if settings.diagnostics.on_save || settings.diagnostics.scope.is_workspace() {
+                self.refresh_dependent_diagnostics(&settings, changed_headers).await;
+            }
+        }
+        if !changed_sources.is_empty() {
+            debug!("Re-indexing {} file(s) changed outside the editor", changed_sources.len());
+            self.reindex_sources(&changed_sources, settings.indexing.enable).await;
+            if settings.diagnostics.scope.is_workspace() {
+                self.run_workspace_diagnostics(&settings, &changed_sources).await;
+            }
+        }
+    }
+
+    async fn remove_deleted_source(
+        &self,
+        uri: &Url,
+        path: &Path,
+    ) {
+        debug!("Forgetting deleted file {}", path.display());
+        self.definition_provider.remove_workspace_file(path);
+        self.symbol_provider.remove_file(uri);
+        update_owner_links(&self.header_owners, &self.owner_headers, path, BTreeSet::new());
+        let _ = self.client.publish_diagnostics(uri.clone(), Vec::new(), None).await;
+    }
+
+    /// Re-scan the workspace symbols of `paths` from disk, dropping the ones
+    /// that were deleted.
+    async fn refresh_workspace_symbols(
+        &self,
+        paths: Vec<PathBuf>,
+    ) {
+        if paths.is_empty() {
+            return;
+        }
+        let provider = self.symbol_provider.clone();
+        let _ = tokio::task::spawn_blocking(move || {
+            for path in &paths {
+                provider.refresh_from_disk(path);
+            }
+        })
+        .await;
+    }
+
+    /// Refresh the include links and, with indexing enabled, the project
+    /// index entries of `sources`.
+    async fn reindex_sources(
+        &self,
+        sources: &[PathBuf],
+        indexing_enabled: bool,
+    ) {
+        self.compiler.ensure_system_includes_ready().await;
+        for path in sources {
+            let include_paths = compute_include_paths_for(path, &self.workspace_roots, &self.compiler);
+            let Ok(source) = tokio::fs::read_to_string(path).await else {
+                continue;
+            };
+            let headers = collect_included_headers(path, &source, &include_paths);
+            update_owner_links(&self.header_owners, &self.owner_headers, path, headers);
+            if indexing_enabled {
+                let provider = self.definition_provider.clone();
+                let path = path.clone();
+                let _ = tokio::task::spawn_blocking(move || provider.index_workspace_file(&path, &include_paths)).await;
+            }
+        }
+    }
+
+    /// Re-index the sources including `headers`, whose cached AST indexes
+    /// still describe the previous header contents.
+    async fn refresh_header_dependents(
+        &self,
+        headers: &BTreeSet<PathBuf>,
+    ) {
+        let owners: BTreeSet<PathBuf> = headers
+            .iter()
+            .filter_map(|header| self.header_owners.get(&path_key(header)).map(|owners| owners.clone()))
+            .flatten()
+            .collect();
+        for owner in &owners {
+            self.definition_provider.invalidate_file(owner);
+        }
+        // Open owners are rebuilt from their buffers on the next request.
+        let closed_owners: Vec<PathBuf> = owners
+            .into_iter()
+            .filter(|owner| Url::from_file_path(owner).is_ok_and(|uri| self.document_store.get(&uri).is_none()))
+            .collect();
+        let indexing_enabled = self.settings.read().await.indexing.enable;
+        self.reindex_sources(&closed_owners, indexing_enabled).await;
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/server/file_watch_tests.rs"]
+mod tests;
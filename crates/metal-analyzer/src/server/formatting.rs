@@ -1,32 +1,27 @@
 use std::{
     fmt::{Display, Formatter},
+    path::Path,
     process::Stdio,
 };
 
 use tokio::{io::AsyncWriteExt, process::Command};
 use tower_lsp::lsp_types::{FormattingOptions, Position, Range, TextEdit};
 
-use crate::{document::Document, server::metalfmt, server::settings::FormattingSettings};
+use crate::{
+    document::Document,
+    server::{
+        metalfmt,
+        settings::{FormattingEngine, FormattingSettings},
+    },
+};
 
 pub async fn format_document(
     document: &Document,
-    _options: &FormattingOptions,
+    options: &FormattingOptions,
     formatting_settings: &FormattingSettings,
 ) -> Result<Option<TextEdit>, FormattingError> {
-    let assume_filename =
-        document.uri.to_file_path().ok().map(|p| p.display().to_string()).unwrap_or_else(|| "shader.metal".to_string());
-
-    let args = clang_format_args(&formatting_settings.args, assume_filename);
-
-    let formatted = match run_clang_format(&formatting_settings.command, &args, &document.text).await {
-        Ok(output) => output,
-        Err(FormattingError::CommandNotFound(_)) if formatting_settings.command == "clang-format" => {
-            let mut xcrun_args = vec!["clang-format".to_string()];
-            xcrun_args.extend(args);
-            run_clang_format("xcrun", &xcrun_args, &document.text).await?
-        },
-        Err(error) => return Err(error),
-    };
+    let path = document.uri.to_file_path().ok();
+    let formatted = format_text(&document.text, path.as_deref(), options, formatting_settings).await?;
 
     if formatted == document.text {
         return Ok(None);
@@ -38,6 +33,55 @@ pub async fn format_document(
     }))
 }
 
+/// Format `text`, read from `path` when known, with the configured engine.
+pub async fn format_text(
+    text: &str,
+    path: Option<&Path>,
+    options: &FormattingOptions,
+    formatting_settings: &FormattingSettings,
+) -> Result<String, FormattingError> {
+    match formatting_settings.engine {
+        FormattingEngine::Internal => Ok(format_internal(text, path, options)),
+        FormattingEngine::ClangFormat => format_with_clang_format(text, path, formatting_settings).await,
+        FormattingEngine::Auto => match format_with_clang_format(text, path, formatting_settings).await {
+            // Neither `clang-format` nor `xcrun clang-format` exists on this machine.
+            Err(FormattingError::CommandNotFound(command)) if command == "xcrun" => {
+                Ok(format_internal(text, path, options))
+            },
+            result => result,
+        },
+    }
+}
+
+async fn format_with_clang_format(
+    text: &str,
+    path: Option<&Path>,
+    formatting_settings: &FormattingSettings,
+) -> Result<String, FormattingError> {
+    let assume_filename = path.map(|p| p.display().to_string()).unwrap_or_else(|| "shader.metal".to_string());
+    let args = clang_format_args(&formatting_settings.args, assume_filename, &formatting_settings.fallback_style);
+
+    match run_clang_format(&formatting_settings.command, &args, text).await {
+        Ok(output) => Ok(output),
+        Err(FormattingError::CommandNotFound(_)) if formatting_settings.command == "clang-format" => {
+            let mut xcrun_args = vec!["clang-format".to_string()];
+            xcrun_args.extend(args);
+            run_clang_format("xcrun", &xcrun_args, text).await
+        },
+        Err(error) => Err(error),
+    }
+}
+
+/// Format with the built-in `metalfmt` formatter.
+fn format_internal(
+    text: &str,
+    path: Option<&Path>,
+    options: &FormattingOptions,
+) -> String {
+    let style = metalfmt::resolve_internal_style(path, options);
+    metalfmt::format_source(text, &style)
+}
+
 fn full_document_range(document: &Document) -> Range {
     let end = document.position_of(document.text.len());
     Range {
@@ -46,18 +90,45 @@ fn full_document_range(document: &Document) -> Range {
     }
 }
 
+/// Bundled clang-format style for Metal sources without a `metalfmt.toml` or
+/// `.clang-format`: LLVM with left-bound pointers and a column limit wide
+/// enough for parameters carrying `[[buffer(n)]]`-style attributes, one
+/// parameter per line once a signature wraps.
+pub const METAL_FALLBACK_STYLE: &[(&str, &str)] = &[
+    ("BasedOnStyle", "LLVM"),
+    ("IndentWidth", "4"),
+    ("ColumnLimit", "120"),
+    ("PointerAlignment", "Left"),
+    ("ReferenceAlignment", "Left"),
+    ("DerivePointerAlignment", "false"),
+    ("AlignAfterOpenBracket", "Align"),
+    ("BinPackParameters", "false"),
+    ("AllowShortFunctionsOnASingleLine", "Empty"),
+    ("SortIncludes", "false"),
+    ("Standard", "c++17"),
+];
+
 pub fn clang_format_args(
     extra_args: &[String],
     assume_filename: String,
+    fallback_style: &str,
 ) -> Vec<String> {
     let mut args = extra_args.to_vec();
 
-    // Try to resolve a metalfmt.toml for the source file.
-    let style = std::path::Path::new(&assume_filename)
+    // A metalfmt.toml wins, then a .clang-format, then the fallback style.
+    let source = Path::new(&assume_filename);
+    let style = source
         .canonicalize()
         .ok()
         .and_then(|p| metalfmt::resolve_inline_style(&p))
         .map(|inline| format!("{{{inline}}}"))
+        .or_else(|| {
+            if has_clang_format_file(source) {
+                None
+            } else {
+                resolve_fallback_style(fallback_style)
+            }
+        })
         .unwrap_or_else(|| "file".to_string());
 
     args.extend([
@@ -71,6 +142,65 @@ pub fn clang_format_args(
     args
 }
 
+/// Whether clang-format's `--style=file` finds a `.clang-format` or
+/// `_clang-format` for `source`.
+fn has_clang_format_file(source: &Path) -> bool {
+    let Ok(source) = std::path::absolute(source) else {
+        return false;
+    };
+    source.ancestors().skip(1).any(|dir| dir.join(".clang-format").is_file() || dir.join("_clang-format").is_file())
+}
+
+/// The `--style` value for the `formatting.fallbackStyle` setting, or `None`
+/// for `none`.
+pub fn resolve_fallback_style(fallback_style: &str) -> Option<String> {
+    let fallback_style = fallback_style.trim();
+    if fallback_style.eq_ignore_ascii_case("none") {
+        return None;
+    }
+    if !fallback_style.eq_ignore_ascii_case("metal") && !fallback_style.starts_with('{') {
+        return Some(fallback_style.to_string());
+    }
+
+    let mut style: Vec<(String, String)> =
+        METAL_FALLBACK_STYLE.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+    if let Some(inline) = fallback_style.strip_prefix('{').and_then(|rest| rest.strip_suffix('}')) {
+        for (key, value) in parse_inline_style(inline) {
+            match style.iter_mut().find(|(existing, _)| *existing == key) {
+                Some(entry) => entry.1 = value,
+                None => style.push((key, value)),
+            }
+        }
+    }
+    let entries: Vec<String> = style.iter().map(|(key, value)| format!("{key}: {value}")).collect();
+    Some(format!("{{{}}}", entries.join(", ")))
+}
+
+/// Split the body of an inline `{Key: Value, ...}` style into its top-level
+/// entries; nested `{...}` and `[...]` values are kept whole.
+fn parse_inline_style(inline: &str) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in inline.char_indices().chain(std::iter::once((inline.len(), ','))) {
+        match c {
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                if let Some((key, value)) = inline[start..i].split_once(':') {
+                    let key = key.trim();
+                    if !key.is_empty() {
+                        entries.push((key.to_string(), value.trim().to_string()));
+                    }
+                }
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    entries
+}
+
 pub async fn run_clang_format(
     executable: &str,
     args: &[String],
@@ -1,25 +1,77 @@
-use std::{panic::AssertUnwindSafe, sync::atomic::Ordering, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    panic::AssertUnwindSafe,
+    path::{Path, PathBuf},
+    sync::{Arc, atomic::Ordering},
+    time::Instant,
+};
 
 use futures::FutureExt;
 use tower_lsp::{LanguageServer, jsonrpc::Result, lsp_types::*};
 use tracing::{debug, info, warn};
 
 use crate::{
-    ide::lsp::{ide_location_to_lsp, ide_range_to_lsp, navigation_target_to_lsp},
+    completion::{
+        CompletionSources, IncludeSearchDirs,
+        auto_import::{AUTO_IMPORT_KINDS, auto_imports},
+        context::{CursorContext, detect_context},
+        include_paths::include_directive_at,
+        ranking::RECORD_COMPLETION_COMMAND,
+        resolve::{completion_source_file, resolve_completion},
+    },
+    config::CompilerInvalidation,
+    definition::{
+        SymbolDef,
+        cache_view::{CACHE_VIEW_SCHEME, cache_view_uri},
+        def_to_location, is_system_header,
+        text_scan::files_mentioning,
+    },
+    document::ContentHash,
+    ide::{
+        code_lens::{
+            REINDEX_FILE_COMMAND, RUN_DIAGNOSTICS_COMMAND, entry_point_lenses, file_action_lenses, reference_lenses,
+            resolve_reference_lens,
+        },
+        deprecations::upgrade_actions,
+        fix_its::fix_it_actions,
+        inactive_regions::inactive_regions,
+        include_guards::include_guard_actions,
+        inline_values::inline_values,
+        lsp::{ide_location_to_lsp, ide_range_to_lsp, navigation_target_to_lsp},
+        rename::{
+            RenameError, annotated_rename_edit, any_use_in_scope, declaration_scope, defines_type, identifier_ranges,
+            type_name_companion_ranges, validate_new_name,
+        },
+        selection_range::selection_ranges,
+        syntax_diagnostics::syntax_diagnostics,
+        unused_includes::remove_include_actions,
+    },
     metal::compiler::MetalCompiler,
+    perf,
     progress::ProgressToken,
     semantic_tokens::get_legend,
     server::{
-        diagnostics::{compile_filtered_diagnostics_for_document, compute_include_paths_for_uri_cached},
+        cancellation::RequestCancellation,
+        diagnostics::{
+            DiagnosticsContext, DiagnosticsOptions, compile_filtered_diagnostics_for_document,
+            compute_include_paths_for, compute_include_paths_for_uri_cached,
+        },
+        ext::{InactiveRegions, InactiveRegionsParams, RebuildFileParams},
+        file_watch::{is_watched_source, start_fallback_watcher, watched_files_registration},
         formatting::{FormattingError, format_document},
-        header_owners::{collect_included_headers, update_owner_links},
-        settings::ServerSettings,
+        header_owners::{collect_included_headers, is_header_file, normalize_path, update_owner_links},
+        registrations::DynamicCapability,
+        settings::{WORKSPACE_SETTINGS_FILE, read_workspace_settings_file, validate_settings_payload},
         state::MetalLanguageServer,
     },
-    syntax::SyntaxTree,
+    symbols::SymbolSearchFilter,
+    syntax::{SyntaxTree, helpers},
+    text_pos::PositionEncoding,
 };
 
 const CLIENT_NOTIFICATION_PREFIX: &str = "metal-analyzer:";
+/// How often the perf log task checks whether `logging.perfIntervalSecs` was set.
+const PERF_LOG_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
 
 #[tower_lsp::async_trait]
 impl LanguageServer for MetalLanguageServer {
@@ -29,9 +81,6 @@ impl LanguageServer for MetalLanguageServer {
     ) -> Result<InitializeResult> {
         info!("Initializing metal-analyzer...");
 
-        let initial_settings = ServerSettings::from_lsp_payload(params.initialization_options.as_ref());
-        self.apply_settings(initial_settings).await;
-
         if let Some(folders) = params.workspace_folders {
             *self.workspace_roots.write().await = folders;
         } else if let Some(root) = params.root_uri {
@@ -40,6 +89,64 @@ impl LanguageServer for MetalLanguageServer {
                 name: "root".to_string(),
             }];
         }
+        let workspace_roots: Vec<PathBuf> =
+            self.workspace_roots.read().await.iter().filter_map(|folder| folder.uri.to_file_path().ok()).collect();
+        self.definition_provider.project_index().set_workspace_roots(&workspace_roots);
+
+        let client_watches_files = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.did_change_watched_files.as_ref())
+            .and_then(|capability| capability.dynamic_registration)
+            .unwrap_or(false);
+        self.client_watches_files.store(client_watches_files, Ordering::Relaxed);
+        let client_shows_inactive_regions = params
+            .capabilities
+            .experimental
+            .as_ref()
+            .and_then(|experimental| experimental.get("inactiveRegions"))
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false);
+        self.client_shows_inactive_regions.store(client_shows_inactive_regions, Ordering::Relaxed);
+        let client_shows_status = params
+            .capabilities
+            .experimental
+            .as_ref()
+            .and_then(|experimental| experimental.get("statusChanged"))
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false);
+        self.status.set_notify(client_shows_status);
+        let client_supports_snippets = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|text_document| text_document.completion.as_ref())
+            .and_then(|completion| completion.completion_item.as_ref())
+            .and_then(|item| item.snippet_support)
+            .unwrap_or(false);
+        self.client_supports_snippets.store(client_supports_snippets, Ordering::Relaxed);
+        let client_annotates_edits = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.workspace_edit.as_ref())
+            .is_some_and(|edit| edit.document_changes == Some(true) && edit.change_annotation_support.is_some());
+        self.client_annotates_edits.store(client_annotates_edits, Ordering::Relaxed);
+        let client_provides_configuration =
+            params.capabilities.workspace.as_ref().and_then(|workspace| workspace.configuration).unwrap_or(false);
+        self.client_provides_configuration.store(client_provides_configuration, Ordering::Relaxed);
+        self.dynamic_registrations.set_supported(&params.capabilities);
+        let position_encoding = PositionEncoding::negotiate(&params.capabilities);
+        position_encoding.set_current();
+        info!("Position encoding: {}", position_encoding.kind().as_str());
+
+        if let Some(options) = params.initialization_options.as_ref() {
+            self.record_lsp_settings_payload(options).await;
+        }
+        let initial_settings = self.resolve_settings().await;
+        self.apply_settings(initial_settings).await;
+        self.apply_root_settings(&self.resolve_root_settings().await);
         self.workspace_generation.fetch_add(1, Ordering::Relaxed);
         self.include_paths_cache.clear();
 
@@ -51,9 +158,11 @@ impl LanguageServer for MetalLanguageServer {
 
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
+                position_encoding: Some(position_encoding.kind()),
                 text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::INCREMENTAL)),
                 completion_provider: Some(CompletionOptions {
                     trigger_characters: Some(vec![".".to_string(), ":".to_string(), "#".to_string()]),
+                    resolve_provider: Some(true),
                     ..Default::default()
                 }),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
@@ -63,21 +172,40 @@ impl LanguageServer for MetalLanguageServer {
                 implementation_provider: Some(ImplementationProviderCapability::Simple(true)),
                 references_provider: Some(OneOf::Left(true)),
                 document_highlight_provider: Some(OneOf::Left(true)),
+                selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+                inline_value_provider: Some(OneOf::Left(true)),
+                code_lens_provider: Some(CodeLensOptions {
+                    resolve_provider: Some(true),
+                }),
                 document_symbol_provider: Some(OneOf::Left(true)),
                 workspace_symbol_provider: Some(OneOf::Left(true)),
                 rename_provider: Some(OneOf::Right(RenameOptions {
                     prepare_provider: Some(true),
                     work_done_progress_options: Default::default(),
                 })),
-                semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
-                    SemanticTokensOptions {
-                        legend: get_legend(),
-                        full: Some(SemanticTokensFullOptions::Bool(true)),
-                        range: Some(false),
-                        work_done_progress_options: Default::default(),
-                    },
-                )),
-                document_formatting_provider: Some(OneOf::Left(true)),
+                semantic_tokens_provider: (!self.dynamic_registrations.is_dynamic(DynamicCapability::SemanticTokens))
+                    .then(|| {
+                        SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
+                            legend: get_legend(),
+                            full: Some(SemanticTokensFullOptions::Bool(true)),
+                            range: Some(false),
+                            work_done_progress_options: Default::default(),
+                        })
+                    }),
+                document_formatting_provider: (!self.dynamic_registrations.is_dynamic(DynamicCapability::Formatting))
+                    .then_some(OneOf::Left(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+                    code_action_kinds: Some(vec![CodeActionKind::QUICKFIX]),
+                    ..Default::default()
+                })),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![
+                        RECORD_COMPLETION_COMMAND.to_string(),
+                        RUN_DIAGNOSTICS_COMMAND.to_string(),
+                        REINDEX_FILE_COMMAND.to_string(),
+                    ],
+                    work_done_progress_options: Default::default(),
+                }),
                 ..Default::default()
             },
             server_info: Some(ServerInfo {
@@ -94,8 +222,10 @@ impl LanguageServer for MetalLanguageServer {
         info!("metal-analyzer initialized");
 
         let client = self.client.clone();
+        let status = self.status.clone();
         tokio::spawn(async move {
             let available = MetalCompiler::is_toolchain_available().await;
+            status.set_toolchain_available(available).await;
             if !available {
                 warn!("Metal compiler toolchain/SDK unavailable — notifying client");
                 client
@@ -111,6 +241,32 @@ impl LanguageServer for MetalLanguageServer {
             }
         });
 
+        // Log the perf summary every `logging.perfIntervalSecs` while it is set.
+        let settings = self.settings.clone();
+        tokio::spawn(async move {
+            loop {
+                let interval = settings.read().await.logging.perf_interval_secs;
+                if interval == 0 {
+                    tokio::time::sleep(PERF_LOG_POLL_INTERVAL).await;
+                    continue;
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+                if settings.read().await.logging.perf_interval_secs != 0 {
+                    perf::log_summary();
+                }
+            }
+        });
+
+        self.watch_workspace_files().await;
+        self.sync_dynamic_registrations().await;
+        self.fetch_root_settings_payloads().await;
+        if self.apply_root_settings(&self.resolve_root_settings().await) {
+            self.workspace_generation.fetch_add(1, Ordering::Relaxed);
+            self.include_paths_cache.clear();
+        }
+        let payload = self.lsp_settings_payload.read().await.clone();
+        self.report_settings_problems(&payload).await;
+
         let settings = self.settings_snapshot().await;
         let should_scan_workspace = settings.indexing.enable || settings.diagnostics.scope.is_workspace();
         if !should_scan_workspace {
@@ -132,9 +288,13 @@ impl LanguageServer for MetalLanguageServer {
         &self,
         params: DidChangeConfigurationParams,
     ) {
+        self.record_lsp_settings_payload(&params.settings).await;
+        self.report_settings_problems(&params.settings).await;
+        self.fetch_root_settings_payloads().await;
+        let roots_changed = self.apply_root_settings(&self.resolve_root_settings().await);
         let current = self.settings_snapshot().await;
-        let merged = current.merged_with_payload(&params.settings);
-        if merged == current {
+        let merged = self.resolve_settings().await;
+        if merged == current && !roots_changed {
             return;
         }
 
@@ -142,24 +302,63 @@ impl LanguageServer for MetalLanguageServer {
         let scope_became_workspace =
             !current.diagnostics.scope.is_workspace() && merged.diagnostics.scope.is_workspace();
         let indexing_inputs_changed = merged.indexing != current.indexing && merged.indexing.enable;
-        let compiler_inputs_changed = merged.compiler != current.compiler;
-        let should_start_workspace_scan = workspace_scan_enabled_after_change
-            && (scope_became_workspace || indexing_inputs_changed || compiler_inputs_changed);
+        let mut compiler_invalidation = merged.compiler.invalidation(&current.compiler);
+        if roots_changed {
+            compiler_invalidation = CompilerInvalidation::Preprocessor;
+        }
+        // AST dumps take the include paths, defines and platform flags, so a
+        // preprocessor change re-indexes the workspace. Turning workspace
+        // diagnostics on only needs the diagnostics pass. Applying the settings
+        // drops the in-memory indexes dumped with other flags; those of other
+        // include paths are dropped below.
+        let compiler_inputs_changed = compiler_invalidation == CompilerInvalidation::Preprocessor;
+        let should_start_workspace_scan =
+            workspace_scan_enabled_after_change && (indexing_inputs_changed || compiler_inputs_changed);
+        let refresh_workspace_diagnostics = !should_start_workspace_scan
+            && merged.diagnostics.scope.is_workspace()
+            && (scope_became_workspace || compiler_invalidation != CompilerInvalidation::Nothing);
+        let refresh_open_diagnostics = (merged.diagnostics.on_type || merged.diagnostics.on_save)
+            && compiler_invalidation != CompilerInvalidation::Nothing;
         self.apply_settings(merged).await;
-        self.workspace_generation.fetch_add(1, Ordering::Relaxed);
-        self.include_paths_cache.clear();
+        self.sync_dynamic_registrations().await;
+        if compiler_inputs_changed {
+            self.workspace_generation.fetch_add(1, Ordering::Relaxed);
+            self.include_paths_cache.clear();
+            self.kernel_stats_cache.clear();
+            self.definition_provider.clear_memory_indexes();
+        }
         info!("Applied updated metal-analyzer settings");
 
-        if should_start_workspace_scan {
+        if compiler_inputs_changed {
+            for uri in self.document_store.all_uris() {
+                if let Some(doc) = self.document_store.get(&uri) {
+                    self.publish_inactive_regions(uri, &doc.text, doc.version).await;
+                }
+            }
+        }
+        if refresh_open_diagnostics {
+            for uri in self.document_store.all_uris() {
+                self.run_diagnostics(&uri).await;
+            }
+        }
+
+        if should_start_workspace_scan || refresh_workspace_diagnostics {
             let handle = self.clone_for_background().await;
             tokio::spawn(async move {
-                handle.index_workspace().await;
+                if should_start_workspace_scan {
+                    handle.index_workspace().await;
+                } else {
+                    handle.refresh_workspace_diagnostics().await;
+                }
             });
         }
     }
 
     async fn shutdown(&self) -> Result<()> {
         info!("Shutting down metal-analyzer");
+        if self.settings_snapshot().await.indexing.persist_index {
+            self.clone_for_background().await.save_project_snapshot().await;
+        }
         Ok(())
     }
 
@@ -172,7 +371,9 @@ impl LanguageServer for MetalLanguageServer {
         let version = params.text_document.version;
         let filename = short_name(&uri);
         let settings = self.settings_snapshot().await;
-        let diagnostics_on_type = settings.diagnostics.on_type;
+        let diagnostics_excluded = self.diagnostics_excluded(&uri, &settings).await;
+        let diagnostics_on_type = settings.diagnostics.on_type && !diagnostics_excluded;
+        let diagnostics_options = DiagnosticsOptions::from_settings(&settings);
         let indexing_enabled = settings.indexing.enable;
         let allow_client_info_logs = settings.logging.level.allows_info();
 
@@ -187,9 +388,12 @@ impl LanguageServer for MetalLanguageServer {
 
         // Lightweight synchronous work only.
         self.document_store.open(uri.clone(), text.clone(), version);
+        let content_hash = self.document_store.content_hash(&uri).unwrap_or_else(|| ContentHash::of(&text));
         let tree = SyntaxTree::parse(&text);
         self.document_trees.insert(uri.clone(), tree.clone());
         self.symbol_provider.scan_file(&uri, &text);
+        self.note_active_document(&uri);
+        self.publish_inactive_regions(uri.clone(), &text, version).await;
 
         // Heavy work (include paths, diagnostics, AST indexing) in background
         // so the editor gets a response immediately.
@@ -202,7 +406,10 @@ impl LanguageServer for MetalLanguageServer {
         let owner_headers = self.owner_headers.clone();
         let document_store = self.document_store.clone();
         let diagnostics_generation = self.diagnostics_generation.clone();
+        let diagnostics_scheduler = self.diagnostics_scheduler.clone();
+        let diagnostics_cache = self.diagnostics_cache.clone();
         let include_paths_cache = self.include_paths_cache.clone();
+        let include_graphs = self.include_graphs.clone();
         let workspace_generation = self.workspace_generation.load(Ordering::Relaxed);
         let fname = filename.clone();
 
@@ -236,21 +443,35 @@ impl LanguageServer for MetalLanguageServer {
 
             // Diagnostics.
             if let Some(generation) = diagnostics_generation_value {
-                if let Some(doc) = document_store.get(&uri) {
-                    let diagnostics = compile_filtered_diagnostics_for_document(
-                        &compiler,
-                        &workspace_roots,
-                        &header_owners,
-                        &owner_headers,
-                        &include_paths_cache,
+                let ticket = diagnostics_scheduler.schedule(&uri, generation).await;
+                let still_current = diagnostics_generation.get(&uri).is_some_and(|current| *current == generation);
+                if let Some(ticket) = ticket
+                    && still_current
+                    && let Some(doc) = document_store.get(&uri)
+                {
+                    let context = DiagnosticsContext {
+                        compiler: &compiler,
+                        workspace_roots: &workspace_roots,
+                        header_owners: &header_owners,
+                        owner_headers: &owner_headers,
+                        include_paths_cache: &include_paths_cache,
+                        include_graphs: &include_graphs,
                         workspace_generation,
+                    };
+                    let diagnostics = compile_filtered_diagnostics_for_document(
+                        &context,
+                        &provider,
+                        &diagnostics_options,
                         &uri,
                         &doc.text,
+                        doc.content_hash(),
                     )
                     .await;
+                    ticket.finish();
 
                     let still_latest = diagnostics_generation.get(&uri).is_some_and(|current| *current == generation);
                     if still_latest {
+                        diagnostics_cache.insert(uri.clone(), diagnostics.clone());
                         let result =
                             AssertUnwindSafe(client.publish_diagnostics(uri.clone(), diagnostics, Some(version)))
                                 .catch_unwind()
@@ -264,7 +485,7 @@ impl LanguageServer for MetalLanguageServer {
 
             // AST indexing.
             if indexing_enabled {
-                provider.index_document(&uri, &text, &includes);
+                provider.index_document(&uri, &text, content_hash, &includes);
                 if allow_client_info_logs {
                     let _ =
                         AssertUnwindSafe(client.log_message(
@@ -287,18 +508,35 @@ impl LanguageServer for MetalLanguageServer {
 
         self.document_store.apply_changes(&uri, params.content_changes, version);
 
-        let Some(text) = self.document_store.get_content(&uri) else {
+        let Some(document) = self.document_store.get(&uri) else {
             return;
         };
         let settings = self.settings_snapshot().await;
-        let diagnostics_on_type = settings.diagnostics.on_type;
-        let diagnostics_debounce_ms = settings.diagnostics.debounce_ms;
+        let diagnostics_excluded = self.diagnostics_excluded(&uri, &settings).await;
+        let diagnostics_on_type = settings.diagnostics.on_type && !diagnostics_excluded;
+        let diagnostics_options = DiagnosticsOptions::from_settings(&settings);
+        let diagnostics_debounce = self.diagnostics_scheduler.debounce(&uri, settings.diagnostics.debounce_ms);
         let indexing_enabled = settings.indexing.enable;
 
         // Lightweight synchronous work only: parse tree + symbol scan.
-        let tree = SyntaxTree::parse(&text);
+        let tree = SyntaxTree::parse(&document.text);
         self.document_trees.insert(uri.clone(), tree.clone());
-        self.symbol_provider.scan_file(&uri, &text);
+        self.symbol_provider.scan_file(&uri, &document.text);
+        self.note_active_document(&uri);
+        self.publish_inactive_regions(uri.clone(), &document.text, version).await;
+
+        // Syntax errors go out now, alongside the last compiler diagnostics;
+        // the debounced compile below replaces both.
+        if settings.diagnostics.syntax && !diagnostics_excluded {
+            let mut diagnostics = self.diagnostics_cache.get(&uri).map(|cached| cached.clone()).unwrap_or_default();
+            diagnostics.extend(syntax_diagnostics(&tree.root(), &document.text));
+            let result = AssertUnwindSafe(self.client.publish_diagnostics(uri.clone(), diagnostics, Some(version)))
+                .catch_unwind()
+                .await;
+            if result.is_err() {
+                warn!("publish_diagnostics panicked (client may have disconnected)");
+            }
+        }
 
         // Bump debounce generation for both AST indexing and diagnostics.
         let ast_generation = if indexing_enabled {
@@ -319,6 +557,7 @@ impl LanguageServer for MetalLanguageServer {
         // Clone shared state for the single debounced background task.
         let ast_gen_map = self.ast_index_generation.clone();
         let diag_gen_map = self.diagnostics_generation.clone();
+        let diagnostics_scheduler = self.diagnostics_scheduler.clone();
         let provider = self.definition_provider.clone();
         let compiler = self.compiler.clone();
         let client = self.client.clone();
@@ -327,13 +566,15 @@ impl LanguageServer for MetalLanguageServer {
         let header_owners = self.header_owners.clone();
         let owner_headers = self.owner_headers.clone();
         let include_paths_cache = self.include_paths_cache.clone();
+        let include_graphs = self.include_graphs.clone();
+        let diagnostics_cache = self.diagnostics_cache.clone();
         let workspace_generation = self.workspace_generation.load(Ordering::Relaxed);
 
         // Single debounced task: include paths, header ownership, AST index,
         // and diagnostics all run together after the idle delay so we avoid
         // computing include paths on every keystroke.
         tokio::spawn(async move {
-            tokio::time::sleep(Duration::from_millis(diagnostics_debounce_ms)).await;
+            tokio::time::sleep(diagnostics_debounce).await;
 
             // Check both generations — bail out if a newer change arrived.
             let ast_current =
@@ -359,32 +600,49 @@ impl LanguageServer for MetalLanguageServer {
             if let Ok(path) = uri.to_file_path()
                 && path.extension().is_some_and(|ext| ext == "metal")
             {
-                let headers = collect_included_headers(&path, &text, &includes);
+                let headers = collect_included_headers(&path, &document.text, &includes);
                 update_owner_links(&header_owners, &owner_headers, &path, headers);
             }
 
             // AST indexing (if still current).
             if ast_current {
-                provider.index_document(&uri, &text, &includes);
+                provider.index_open_document(&document, &includes);
             }
 
             // Diagnostics (if still current).
             if let Some(diag_generation) = diag_generation.filter(|_| diag_current) {
-                let diagnostics = compile_filtered_diagnostics_for_document(
-                    &compiler,
-                    &workspace_roots,
-                    &header_owners,
-                    &owner_headers,
-                    &include_paths_cache,
+                // Wait out a compile of this document still running, then
+                // leave it to the newer edit if one arrived meanwhile.
+                let Some(ticket) = diagnostics_scheduler.schedule(&uri, diag_generation).await else {
+                    return;
+                };
+                if diag_gen_map.get(&uri).is_none_or(|current| *current != diag_generation) {
+                    return;
+                }
+                let context = DiagnosticsContext {
+                    compiler: &compiler,
+                    workspace_roots: &workspace_roots,
+                    header_owners: &header_owners,
+                    owner_headers: &owner_headers,
+                    include_paths_cache: &include_paths_cache,
+                    include_graphs: &include_graphs,
                     workspace_generation,
+                };
+                let diagnostics = compile_filtered_diagnostics_for_document(
+                    &context,
+                    &provider,
+                    &diagnostics_options,
                     &uri,
-                    &text,
+                    &document.text,
+                    document.content_hash(),
                 )
                 .await;
+                ticket.finish();
 
                 // Re-check staleness after compilation.
                 let still_latest = diag_gen_map.get(&uri).is_some_and(|current| *current == diag_generation);
                 if still_latest {
+                    diagnostics_cache.insert(uri.clone(), diagnostics.clone());
                     let result = AssertUnwindSafe(client.publish_diagnostics(uri, diagnostics, Some(version)))
                         .catch_unwind()
                         .await;
@@ -409,13 +667,20 @@ impl LanguageServer for MetalLanguageServer {
             self.run_diagnostics(&uri).await;
         }
 
-        if let Some(text) = self.document_store.get_content(&uri) {
+        if (settings.diagnostics.on_save || settings.diagnostics.scope.is_workspace())
+            && let Ok(path) = uri.to_file_path()
+            && is_header_file(&path)
+        {
+            self.schedule_dependent_diagnostics(path).await;
+        }
+
+        if let Some(document) = self.document_store.get(&uri) {
             let provider = self.definition_provider.clone();
             let includes = self.include_paths(&uri).await;
             if let Ok(path) = uri.to_file_path()
                 && path.extension().is_some_and(|ext| ext == "metal")
             {
-                let headers = collect_included_headers(&path, &text, &includes);
+                let headers = collect_included_headers(&path, &document.text, &includes);
                 update_owner_links(&self.header_owners, &self.owner_headers, &path, headers);
             }
             let file_path = uri.to_file_path().ok();
@@ -425,7 +690,7 @@ impl LanguageServer for MetalLanguageServer {
             let indexing_enabled = settings.indexing.enable;
             tokio::spawn(async move {
                 if indexing_enabled {
-                    provider.index_document(&uri, &text, &includes);
+                    provider.index_open_document(&document, &includes);
                     if let Some(path) = file_path {
                         provider.index_workspace_file(&path, &includes);
                     }
@@ -446,14 +711,46 @@ impl LanguageServer for MetalLanguageServer {
         params: DidCloseTextDocumentParams,
     ) {
         let uri = params.text_document.uri;
-        let keep_workspace_diagnostics = self.settings_snapshot().await.diagnostics.scope.is_workspace();
+        let settings = self.settings_snapshot().await;
+        let keep_workspace_diagnostics = settings.diagnostics.scope.is_workspace();
+        let mut workspace_file = None;
         if let Ok(path) = uri.to_file_path() {
             let cache_key = path.canonicalize().unwrap_or(path);
             self.include_paths_cache.remove(&cache_key);
+            self.include_graphs.forget(&cache_key);
+            self.macro_indexes.remove(&uri);
+            let in_workspace = self
+                .workspace_roots
+                .read()
+                .await
+                .iter()
+                .filter_map(|folder| folder.uri.to_file_path().ok())
+                .any(|root| cache_key.starts_with(normalize_path(&root)));
+            if in_workspace && is_watched_source(&cache_key) {
+                workspace_file = Some(cache_key);
+            }
         }
         self.document_store.close(&uri);
         self.document_trees.remove(&uri);
         self.symbol_provider.remove_file(&uri);
+        if let Ok(mut active) = self.active_document.lock()
+            && active.as_ref() == Some(&uri)
+        {
+            *active = None;
+        }
+        // The workspace symbol index keeps closed workspace files, as they
+        // are on disk.
+        if settings.indexing.enable
+            && let Some(path) = workspace_file
+        {
+            let provider = self.symbol_provider.clone();
+            let _ = tokio::task::spawn_blocking(move || provider.refresh_from_disk(&path)).await;
+        }
+        self.kernel_stats_cache.remove(&uri);
+        self.ast_cache_views.remove(&uri);
+        if let Some(view_uri) = cache_view_uri(&uri) {
+            self.ast_cache_views.remove(&view_uri);
+        }
         if keep_workspace_diagnostics {
             self.diagnostics_cache.remove(&uri);
             self.diagnostics_generation.remove(&uri);
@@ -462,25 +759,125 @@ impl LanguageServer for MetalLanguageServer {
         }
         self.definition_provider.evict(&uri);
         self.ast_index_generation.remove(&uri);
+        self.diagnostics_scheduler.forget(&uri);
+    }
+
+    async fn did_change_watched_files(
+        &self,
+        params: DidChangeWatchedFilesParams,
+    ) {
+        let changes: Vec<_> = params
+            .changes
+            .into_iter()
+            .filter_map(|event| event.uri.to_file_path().ok().map(|path| (path, event.typ)))
+            .collect();
+        if changes.is_empty() {
+            return;
+        }
+        let handle = self.clone_for_background().await;
+        tokio::spawn(async move {
+            handle.apply_watched_changes(changes).await;
+        });
     }
 
     async fn completion(
         &self,
         params: CompletionParams,
     ) -> Result<Option<CompletionResponse>> {
+        let _timer = perf::request("textDocument/completion");
         let uri = params.text_document_position.text_document.uri;
+        self.note_active_document(&uri);
         let position = params.text_document_position.position;
         let text = self.document_store.get_content(&uri);
         let tree = self.document_trees.get(&uri);
 
-        let items = self.completion_provider.provide(text.as_deref(), position, tree.as_ref());
+        let index = self.definition_provider.get_cached_index(&uri);
+        let source_file = uri.to_file_path().ok().map(|path| path.to_string_lossy().into_owned());
+        // Headers to auto-import from are only offered where any identifier
+        // completes, and only once the document's own includes are indexed.
+        let offers_imports = index.is_some()
+            && text.as_deref().is_some_and(|text| {
+                detect_context(text, position, tree.as_ref().map(SyntaxTree::root)) == CursorContext::General
+            });
+        let in_include = text.as_deref().and_then(|text| include_directive_at(text, position)).is_some();
+        let include_dirs = if in_include || offers_imports {
+            self.include_search_dirs(&uri).await
+        } else {
+            IncludeSearchDirs::default()
+        };
+        let auto_imports = match index.as_deref().zip(source_file.as_deref()) {
+            Some((index, source_file)) if offers_imports => auto_imports(
+                self.definition_provider
+                    .project_index()
+                    .includable_declarations_from(AUTO_IMPORT_KINDS, Path::new(source_file)),
+                index,
+                source_file,
+                &include_dirs.quoted,
+            ),
+            _ => Vec::new(),
+        };
+        let snippets =
+            self.client_supports_snippets.load(Ordering::Relaxed) && self.settings_snapshot().await.completion.snippets;
+        let sources = CompletionSources {
+            ast: index.as_deref().zip(source_file.as_deref()),
+            include_dirs,
+            snippets,
+            language_version: self.compiler.language_version(&uri),
+            auto_imports,
+        };
+        let items = self.completion_provider.provide_with_sources(text.as_deref(), position, tree.as_ref(), &sources);
         Ok(Some(CompletionResponse::Array(items)))
     }
 
+    async fn completion_resolve(
+        &self,
+        item: CompletionItem,
+    ) -> Result<CompletionItem> {
+        let _timer = perf::request("completionItem/resolve");
+        let source = match completion_source_file(&item) {
+            Some(file) => match Url::from_file_path(&file).ok().and_then(|uri| self.document_store.get_content(&uri)) {
+                Some(text) => Some(text),
+                None => tokio::fs::read_to_string(&file).await.ok(),
+            },
+            None => None,
+        };
+        Ok(resolve_completion(item, source.as_deref()))
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        let Some(argument) = params.arguments.first().and_then(serde_json::Value::as_str) else {
+            return Ok(None);
+        };
+        match params.command.as_str() {
+            RECORD_COMPLETION_COMMAND => self.completion_provider.record_selection(argument),
+            RUN_DIAGNOSTICS_COMMAND | REINDEX_FILE_COMMAND => {
+                let Ok(uri) = Url::parse(argument) else {
+                    return Ok(None);
+                };
+                if params.command == RUN_DIAGNOSTICS_COMMAND {
+                    self.run_diagnostics(&uri).await;
+                } else {
+                    self.rebuild_file(RebuildFileParams {
+                        text_document: TextDocumentIdentifier {
+                            uri,
+                        },
+                    })
+                    .await?;
+                }
+            },
+            _ => {},
+        }
+        Ok(None)
+    }
+
     async fn formatting(
         &self,
         params: DocumentFormattingParams,
     ) -> Result<Option<Vec<TextEdit>>> {
+        let _timer = perf::request("textDocument/formatting");
         let uri = params.text_document.uri;
         let Some(document) = self.document_store.get(&uri) else {
             return Ok(None);
@@ -495,13 +892,14 @@ impl LanguageServer for MetalLanguageServer {
             Ok(None) => Ok(Some(Vec::new())),
             Err(error) => {
                 warn!("Formatting failed for {uri}: {error}");
+                self.status.record_error(format!("Formatting failed: {error}")).await;
                 match error {
                     FormattingError::CommandNotFound(command) => {
                         self.client
                             .show_message(
                                 MessageType::WARNING,
                                 prefixed_client_message(format!(
-                                    "Formatting command '{command}' is not available. Install it or update metal-analyzer.formatting.command."
+                                    "Formatting command '{command}' is not available. Install it, update metal-analyzer.formatting.command, or set metal-analyzer.formatting.engine to \"internal\"."
                                 )),
                             )
                             .await;
@@ -524,37 +922,60 @@ impl LanguageServer for MetalLanguageServer {
         &self,
         params: HoverParams,
     ) -> Result<Option<Hover>> {
+        let _timer = perf::request("textDocument/hover");
         let uri = params.text_document_position_params.text_document.uri;
+        self.note_active_document(&uri);
         let position = params.text_document_position_params.position;
+        if uri.scheme() == CACHE_VIEW_SCHEME {
+            return Ok(self.cache_view_hover(&uri, position));
+        }
 
-        let text = match self.document_store.get_content(&uri) {
+        let (text, hash) = match self.document_store.get_content_with_hash(&uri) {
             Some(t) => t,
             None => return Ok(None),
         };
+        if let Some(hover) = self.include_hover(&uri, &text, position).await {
+            return Ok(Some(hover));
+        }
+        if let Some(hover) = self.macro_hover(&uri, &text, hash, position).await {
+            return Ok(Some(hover));
+        }
         let tree = self.document_trees.get(&uri);
 
-        Ok(self.hover_provider.provide(&uri, &text, position, tree.as_ref()).await)
+        let hover = self.hover_provider.provide(&uri, &text, position, tree.as_ref()).await;
+        if !self.settings_snapshot().await.compiler.kernel_stats {
+            return Ok(hover);
+        }
+        Ok(self.with_kernel_stats(&uri, &text, hash, position, hover).await)
     }
 
     async fn goto_definition(
         &self,
         params: GotoDefinitionParams,
     ) -> Result<Option<GotoDefinitionResponse>> {
+        let _timer = perf::request("textDocument/definition");
         let uri = params.text_document_position_params.text_document.uri;
+        self.note_active_document(&uri);
         let position = params.text_document_position_params.position;
+        if uri.scheme() == CACHE_VIEW_SCHEME {
+            return Ok(self.cache_view_definition(&uri, position));
+        }
 
-        let text = match self.document_store.get_content(&uri) {
+        let (text, hash) = match self.document_store.get_content_with_hash(&uri) {
             Some(t) => t,
             None => return Ok(None),
         };
         let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
 
+        // Superseded by a newer go-to-definition, or cancelled by the client.
         let generation = self.goto_def_generation.fetch_add(1, Ordering::Relaxed) + 1;
         let gen_ref = self.goto_def_generation.clone();
-        let is_cancelled = move || gen_ref.load(Ordering::Relaxed) != generation;
+        let cancellation = RequestCancellation::new();
+        let token = cancellation.token();
+        let is_cancelled = move || gen_ref.load(Ordering::Relaxed) != generation || token.is_cancelled();
 
         let progress = ProgressToken::begin(&self.client, "Definition", Some("Finding definition…".to_string())).await;
-        let include_start = std::time::Instant::now();
+        let include_start = Instant::now();
         let includes = self.include_paths(&uri).await;
         let include_elapsed = include_start.elapsed();
 
@@ -564,9 +985,17 @@ impl LanguageServer for MetalLanguageServer {
             return Ok(None);
         }
 
-        let start = std::time::Instant::now();
-        let nav_result = self.definition_provider.provide(&uri, position, &text, &includes, &tree, &is_cancelled);
+        let start = Instant::now();
+        let provider = Arc::clone(&self.definition_provider);
+        let request_uri = uri.clone();
+        let nav_result = tokio::task::spawn_blocking(move || {
+            provider.provide(&request_uri, position, &text, hash, &includes, &tree, is_cancelled)
+        })
+        .await
+        .ok()
+        .flatten();
         let elapsed = start.elapsed();
+        self.report_ast_dump_timeout().await;
 
         let filename = short_name(&uri);
         debug!(
@@ -602,7 +1031,11 @@ impl LanguageServer for MetalLanguageServer {
         &self,
         params: SemanticTokensParams,
     ) -> Result<Option<SemanticTokensResult>> {
+        let _timer = perf::request("textDocument/semanticTokens/full");
         let uri = params.text_document.uri;
+        if !self.settings_snapshot().await.semantic_tokens.enable {
+            return Ok(Some(SemanticTokensResult::Tokens(SemanticTokens::default())));
+        }
         let tree = self.document_trees.get(&uri);
 
         let tokens = self.semantic_token_provider.provide(&uri, tree.as_ref());
@@ -616,32 +1049,55 @@ impl LanguageServer for MetalLanguageServer {
         &self,
         params: DocumentSymbolParams,
     ) -> Result<Option<DocumentSymbolResponse>> {
+        let _timer = perf::request("textDocument/documentSymbol");
         let uri = params.text_document.uri;
-        let symbols = self.symbol_provider.document_symbols(&uri);
-        Ok(Some(DocumentSymbolResponse::Flat(symbols)))
+        let symbols = match self.document_trees.get(&uri) {
+            Some(tree) => self.symbol_provider.extract_symbols_from_snapshot(&tree),
+            None => match self.document_store.get_content(&uri) {
+                Some(text) => self.symbol_provider.extract_symbols(&text),
+                None => return Ok(None),
+            },
+        };
+        Ok(Some(DocumentSymbolResponse::Nested(symbols)))
     }
 
     async fn symbol(
         &self,
         params: WorkspaceSymbolParams,
     ) -> Result<Option<Vec<SymbolInformation>>> {
-        Ok(Some(self.symbol_provider.workspace_symbols(&params.query)))
+        let _timer = perf::request("workspace/symbol");
+        let scope = self.settings_snapshot().await.symbols.search_scope;
+        let filter = SymbolSearchFilter {
+            workspace_roots: self
+                .workspace_roots
+                .read()
+                .await
+                .iter()
+                .filter_map(|f| f.uri.to_file_path().ok())
+                .collect(),
+            include_system: scope.include_system_headers,
+            include_generated: scope.include_generated,
+            include_tests: scope.include_tests,
+        };
+        let anchor = self.active_document.lock().ok().and_then(|active| active.clone());
+        Ok(Some(self.symbol_provider.workspace_symbols(&params.query, anchor.as_ref(), &filter)))
     }
 
     async fn goto_declaration(
         &self,
         params: GotoDefinitionParams,
     ) -> Result<Option<GotoDefinitionResponse>> {
+        let _timer = perf::request("textDocument/declaration");
         let uri = params.text_document_position_params.text_document.uri;
         let position = params.text_document_position_params.position;
-        let text = match self.document_store.get_content(&uri) {
+        let (text, hash) = match self.document_store.get_content_with_hash(&uri) {
             Some(t) => t,
             None => return Ok(None),
         };
         let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
         let includes = self.include_paths(&uri).await;
 
-        let result = self.definition_provider.provide_declaration(&uri, position, &text, &includes, &tree);
+        let result = self.definition_provider.provide_declaration(&uri, position, &text, hash, &includes, &tree);
         Ok(result.and_then(navigation_target_to_lsp))
     }
 
@@ -649,16 +1105,17 @@ impl LanguageServer for MetalLanguageServer {
         &self,
         params: GotoDefinitionParams,
     ) -> Result<Option<GotoDefinitionResponse>> {
+        let _timer = perf::request("textDocument/typeDefinition");
         let uri = params.text_document_position_params.text_document.uri;
         let position = params.text_document_position_params.position;
-        let text = match self.document_store.get_content(&uri) {
+        let (text, hash) = match self.document_store.get_content_with_hash(&uri) {
             Some(t) => t,
             None => return Ok(None),
         };
         let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
         let includes = self.include_paths(&uri).await;
 
-        let result = self.definition_provider.provide_type_definition(&uri, position, &text, &includes, &tree);
+        let result = self.definition_provider.provide_type_definition(&uri, position, &text, hash, &includes, &tree);
         Ok(result.and_then(navigation_target_to_lsp))
     }
 
@@ -666,16 +1123,17 @@ impl LanguageServer for MetalLanguageServer {
         &self,
         params: GotoDefinitionParams,
     ) -> Result<Option<GotoDefinitionResponse>> {
+        let _timer = perf::request("textDocument/implementation");
         let uri = params.text_document_position_params.text_document.uri;
         let position = params.text_document_position_params.position;
-        let text = match self.document_store.get_content(&uri) {
+        let (text, hash) = match self.document_store.get_content_with_hash(&uri) {
             Some(t) => t,
             None => return Ok(None),
         };
         let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
         let includes = self.include_paths(&uri).await;
 
-        let result = self.definition_provider.provide_implementation(&uri, position, &text, &includes, &tree);
+        let result = self.definition_provider.provide_implementation(&uri, position, &text, hash, &includes, &tree);
         Ok(result.and_then(navigation_target_to_lsp))
     }
 
@@ -683,21 +1141,19 @@ impl LanguageServer for MetalLanguageServer {
         &self,
         params: ReferenceParams,
     ) -> Result<Option<Vec<Location>>> {
+        let _timer = perf::request("textDocument/references");
         let uri = params.text_document_position.text_document.uri;
         let position = params.text_document_position.position;
-        let text = match self.document_store.get_content(&uri) {
-            Some(t) => t,
-            None => return Ok(None),
+        let Some(document) = self.analysis_snapshot(uri.clone()) else {
+            return Ok(None);
         };
-        let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
         let includes = self.include_paths(&uri).await;
+        self.index_reference_candidates(position, &document.text, &document.tree).await;
 
         let result = self.definition_provider.provide_references(
-            &uri,
+            &document,
             position,
-            &text,
             &includes,
-            &tree,
             params.context.include_declaration,
         );
         Ok(result.map(|locs| locs.into_iter().filter_map(ide_location_to_lsp).collect()))
@@ -707,16 +1163,15 @@ impl LanguageServer for MetalLanguageServer {
         &self,
         params: DocumentHighlightParams,
     ) -> Result<Option<Vec<DocumentHighlight>>> {
+        let _timer = perf::request("textDocument/documentHighlight");
         let uri = params.text_document_position_params.text_document.uri;
         let position = params.text_document_position_params.position;
-        let text = match self.document_store.get_content(&uri) {
-            Some(t) => t,
-            None => return Ok(None),
+        let Some(document) = self.analysis_snapshot(uri.clone()) else {
+            return Ok(None);
         };
-        let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
         let includes = self.include_paths(&uri).await;
 
-        let refs = self.definition_provider.provide_references(&uri, position, &text, &includes, &tree, true);
+        let refs = self.definition_provider.provide_references(&document, position, &includes, true);
 
         Ok(refs.map(|locs| {
             locs.into_iter()
@@ -730,20 +1185,96 @@ impl LanguageServer for MetalLanguageServer {
         }))
     }
 
+    async fn selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> Result<Option<Vec<SelectionRange>>> {
+        let _timer = perf::request("textDocument/selectionRange");
+        let uri = params.text_document.uri;
+        let Some(text) = self.document_store.get_content(&uri) else {
+            return Ok(None);
+        };
+        let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
+        Ok(Some(selection_ranges(&tree.root(), tree.source(), &params.positions)))
+    }
+
+    async fn code_action(
+        &self,
+        params: CodeActionParams,
+    ) -> Result<Option<CodeActionResponse>> {
+        let _timer = perf::request("textDocument/codeAction");
+        let uri = params.text_document.uri;
+        let Some(text) = self.document_store.get_content(&uri) else {
+            return Ok(None);
+        };
+        let mut actions = upgrade_actions(&uri, &text, &params.context.diagnostics);
+        actions.extend(fix_it_actions(&uri, &text, &params.context.diagnostics));
+        actions.extend(remove_include_actions(&uri, &params.context.diagnostics));
+        actions.extend(include_guard_actions(&uri, &text, &params.context.diagnostics));
+        Ok(Some(actions))
+    }
+
+    async fn inline_value(
+        &self,
+        params: InlineValueParams,
+    ) -> Result<Option<Vec<InlineValue>>> {
+        let _timer = perf::request("textDocument/inlineValue");
+        let uri = params.text_document.uri;
+        let Some(text) = self.document_store.get_content(&uri) else {
+            return Ok(None);
+        };
+        let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
+        Ok(Some(inline_values(&tree.root(), tree.source(), params.range, params.context.stopped_location)))
+    }
+
+    async fn code_lens(
+        &self,
+        params: CodeLensParams,
+    ) -> Result<Option<Vec<CodeLens>>> {
+        let _timer = perf::request("textDocument/codeLens");
+        let settings = self.settings_snapshot().await;
+        let uri = params.text_document.uri;
+        let Some(text) = self.document_store.get_content(&uri) else {
+            return Ok(None);
+        };
+        let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
+        let mut lenses = Vec::new();
+        if settings.code_lens.file_actions {
+            lenses.extend(file_action_lenses(&uri));
+        }
+        if settings.entry_points.code_lens {
+            let pipelines = self.pipeline_index(&settings.entry_points.pipelines_file).await;
+            lenses.extend(entry_point_lenses(&uri, &tree, pipelines.as_ref()));
+        }
+        if settings.code_lens.references {
+            lenses.extend(reference_lenses(&tree));
+        }
+        Ok(Some(lenses))
+    }
+
+    async fn code_lens_resolve(
+        &self,
+        params: CodeLens,
+    ) -> Result<CodeLens> {
+        let _timer = perf::request("codeLens/resolve");
+        Ok(resolve_reference_lens(params, self.definition_provider.project_index()))
+    }
+
     async fn prepare_rename(
         &self,
         params: TextDocumentPositionParams,
     ) -> Result<Option<PrepareRenameResponse>> {
+        let _timer = perf::request("textDocument/prepareRename");
         let uri = params.text_document.uri;
         let position = params.position;
-        let text = match self.document_store.get_content(&uri) {
+        let (text, hash) = match self.document_store.get_content_with_hash(&uri) {
             Some(t) => t,
             None => return Ok(None),
         };
         let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
         let includes = self.include_paths(&uri).await;
 
-        let range = self.definition_provider.prepare_rename(&uri, position, &text, &includes, &tree);
+        let range = self.definition_provider.prepare_rename(&uri, position, &text, hash, &includes, &tree);
         Ok(range.map(|r| PrepareRenameResponse::Range(ide_range_to_lsp(r))))
     }
 
@@ -751,21 +1282,22 @@ impl LanguageServer for MetalLanguageServer {
         &self,
         params: RenameParams,
     ) -> Result<Option<WorkspaceEdit>> {
+        let _timer = perf::request("textDocument/rename");
         let uri = params.text_document_position.text_document.uri;
         let position = params.text_document_position.position;
         let new_name = params.new_name;
+        validate_new_name(&new_name).map_err(rename_error)?;
 
-        let text = match self.document_store.get_content(&uri) {
-            Some(t) => t,
-            None => return Ok(None),
+        let Some(document) = self.analysis_snapshot(uri.clone()) else {
+            return Ok(None);
         };
-        let tree = self.document_trees.get(&uri).unwrap_or_else(|| SyntaxTree::parse(&text));
         let includes = self.include_paths(&uri).await;
+        self.index_reference_candidates(position, &document.text, &document.tree).await;
 
-        let refs = self.definition_provider.provide_references(&uri, position, &text, &includes, &tree, true);
+        let refs = self.definition_provider.provide_references(&document, position, &includes, true);
 
         if let Some(ide_locations) = refs {
-            let mut changes = std::collections::HashMap::new();
+            let mut changes = HashMap::new();
             for ide_loc in ide_locations {
                 if let Some(lsp_loc) = ide_location_to_lsp(ide_loc) {
                     changes.entry(lsp_loc.uri).or_insert_with(Vec::new).push(TextEdit {
@@ -774,6 +1306,29 @@ impl LanguageServer for MetalLanguageServer {
                     });
                 }
             }
+            let old_name = helpers::navigation_word_at_position(&document.tree.root(), &document.text, position);
+            if let Some(old_name) = &old_name {
+                changes.entry(uri.clone()).or_default();
+                self.add_type_companion_edits(&mut changes, old_name, &new_name).await;
+            }
+            changes.retain(|_, edits| !edits.is_empty());
+            if old_name.as_deref() != Some(new_name.as_str()) {
+                let declarations = self.rename_conflicts(&uri, &new_name, &changes).await;
+                if !declarations.is_empty() {
+                    return Err(rename_error(RenameError::Conflict {
+                        name: new_name,
+                        declarations,
+                    }));
+                }
+            }
+            if self.client_annotates_edits.load(Ordering::Relaxed)
+                && let Some(old_name) = &old_name
+            {
+                let candidates = self.textual_rename_candidates(old_name, &new_name, &changes).await;
+                let documents = &self.document_store;
+                let edit = annotated_rename_edit(changes, candidates, |uri| documents.get(uri).map(|doc| doc.version));
+                return Ok(Some(edit));
+            }
             let edit = WorkspaceEdit {
                 changes: Some(changes),
                 document_changes: None,
@@ -786,6 +1341,294 @@ impl LanguageServer for MetalLanguageServer {
     }
 }
 
+impl MetalLanguageServer {
+    /// Follow changes made to workspace files outside the editor, through the
+    /// client's file watcher when it offers one and a `notify` watcher
+    /// otherwise.
+    async fn watch_workspace_files(&self) {
+        if self.client_watches_files.load(Ordering::Relaxed) {
+            if let Err(error) = self.client.register_capability(vec![watched_files_registration()]).await {
+                warn!("Failed to register watched files: {error}");
+            }
+            return;
+        }
+        let watcher = start_fallback_watcher(self.clone_for_background().await);
+        if let Ok(mut slot) = self.file_watcher.lock() {
+            *slot = watcher;
+        }
+    }
+
+    /// Send the ranges of `text` disabled under the effective compiler
+    /// defines to clients that can grey them out.
+    async fn publish_inactive_regions(
+        &self,
+        uri: Url,
+        text: &str,
+        version: i32,
+    ) {
+        if !self.client_shows_inactive_regions.load(Ordering::Relaxed) {
+            return;
+        }
+        let regions = inactive_regions(text, &self.compiler.effective_defines(&uri));
+        let params = InactiveRegionsParams {
+            text_document: VersionedTextDocumentIdentifier {
+                uri,
+                version,
+            },
+            regions,
+        };
+        let _ = AssertUnwindSafe(self.client.send_notification::<InactiveRegions>(params)).catch_unwind().await;
+    }
+
+    /// Warn the client about unknown settings and invalid values in the
+    /// latest editor settings `payload` and the `.metal-analyzer.json`
+    /// files, whenever the list of problems changes.
+    async fn report_settings_problems(
+        &self,
+        payload: &serde_json::Value,
+    ) {
+        let mut problems = Vec::new();
+        let roots: Vec<_> =
+            self.workspace_roots.read().await.iter().filter_map(|folder| folder.uri.to_file_path().ok()).collect();
+        for root in roots {
+            let Some(file) = read_workspace_settings_file(&root) else {
+                continue;
+            };
+            let path = root.join(WORKSPACE_SETTINGS_FILE);
+            problems.extend(
+                validate_settings_payload(&file).iter().map(|problem| format!("{problem} in {}", path.display())),
+            );
+        }
+        problems.extend(
+            validate_settings_payload(payload).iter().map(|problem| format!("{problem} in the editor settings")),
+        );
+
+        {
+            let Ok(mut reported) = self.reported_settings_problems.lock() else {
+                return;
+            };
+            if *reported == problems {
+                return;
+            }
+            reported.clone_from(&problems);
+        }
+        if problems.is_empty() {
+            return;
+        }
+        for problem in &problems {
+            warn!("Settings: {problem}");
+        }
+        self.client
+            .show_message(
+                MessageType::WARNING,
+                prefixed_client_message(format!("Check your settings: {}.", problems.join("; "))),
+            )
+            .await;
+    }
+
+    /// Warn the client the first time an AST dump times out.
+    async fn report_ast_dump_timeout(&self) {
+        if !self.definition_provider.take_ast_dump_timeout() {
+            return;
+        }
+        let timeout_ms = self.settings_snapshot().await.compiler.ast_dump_timeout_ms;
+        self.status.record_error(format!("Indexing a file for navigation took longer than {timeout_ms} ms")).await;
+        self.client
+            .show_message(
+                MessageType::WARNING,
+                prefixed_client_message(format!(
+                    "Indexing a file for navigation took longer than {timeout_ms} ms and was stopped; go-to-definition falls back to name lookup for it. Raise metal-analyzer.compiler.astDumpTimeoutMs for large files."
+                )),
+            )
+            .await;
+    }
+
+    /// Index the workspace files that mention the symbol at `position` but
+    /// are not in the project index yet, so references and renames cover the
+    /// whole workspace before the background scan reaches them.
+    async fn index_reference_candidates(
+        &self,
+        position: Position,
+        text: &str,
+        tree: &SyntaxTree,
+    ) {
+        if !self.settings_snapshot().await.indexing.enable {
+            return;
+        }
+        let workspace_files = self.workspace_shader_sources().await;
+        let workspace_roots: Vec<PathBuf> =
+            self.workspace_roots.read().await.iter().filter_map(|f| f.uri.to_file_path().ok()).collect();
+        self.compiler.ensure_system_includes_ready().await;
+        let provider = self.definition_provider.clone();
+        let compiler = self.compiler.clone();
+        let (text, tree) = (text.to_owned(), tree.clone());
+        // Dropped with the request, so a cancelled request stops dumping.
+        let cancellation = RequestCancellation::new();
+        let token = cancellation.token();
+        let indexed = tokio::task::spawn_blocking(move || {
+            provider.index_reference_candidates(
+                position,
+                &text,
+                &tree,
+                &workspace_files,
+                |path| compute_include_paths_for(&path.to_path_buf(), &workspace_roots, &compiler),
+                &|| token.is_cancelled(),
+            )
+        })
+        .await
+        .unwrap_or(0);
+        if indexed > 0 {
+            debug!("Indexed {indexed} reference candidate file(s) on demand");
+        }
+        self.report_ast_dump_timeout().await;
+    }
+
+    /// Declarations of `new_name` that would be visible at one of the edits
+    /// in `changes`: in the same scope as an edit, or at file scope in a
+    /// file with edits or in the translation unit of `uri`.
+    async fn rename_conflicts(
+        &self,
+        uri: &Url,
+        new_name: &str,
+        changes: &HashMap<Url, Vec<TextEdit>>,
+    ) -> Vec<Location> {
+        let mut candidates: Vec<(SymbolDef, bool)> = Vec::new();
+        if let Some(index) = self.definition_provider.get_cached_index(uri)
+            && let Some(indices) = index.name_to_defs.get(new_name)
+        {
+            candidates.extend(indices.iter().map(|&i| (index.defs[i].clone(), true)));
+        }
+        candidates.extend(
+            self.definition_provider.project_index().find_definitions(new_name).into_iter().map(|def| (def, false)),
+        );
+
+        let mut seen = HashSet::new();
+        let mut conflicts = Vec::new();
+        for (def, in_translation_unit) in candidates {
+            if is_system_header(&def.file) {
+                continue;
+            }
+            let Some(location) = def_to_location(&def).and_then(ide_location_to_lsp) else {
+                continue;
+            };
+            if !in_translation_unit && !changes.contains_key(&location.uri) {
+                continue;
+            }
+            if !seen.insert((location.uri.clone(), location.range.start.line, location.range.start.character)) {
+                continue;
+            }
+            let source = match self.document_store.get_content(&location.uri) {
+                Some(source) => source,
+                None => match tokio::fs::read_to_string(&def.file).await {
+                    Ok(source) => source,
+                    Err(_) => continue,
+                },
+            };
+            let tree = self.document_trees.get(&location.uri).unwrap_or_else(|| SyntaxTree::parse(&source));
+            let uses: Vec<Range> = changes.get(&location.uri).into_iter().flatten().map(|edit| edit.range).collect();
+            let visible = match declaration_scope(&tree.root(), &source, location.range.start) {
+                Some(scope) => any_use_in_scope(&source, scope, &uses),
+                None => !uses.is_empty() || (in_translation_unit && changes.contains_key(uri)),
+            };
+            if visible {
+                conflicts.push(location);
+            }
+        }
+        conflicts
+    }
+
+    /// Identifier tokens spelled `old_name` in the workspace files that the
+    /// project index has not covered and `changes` does not touch, as edits
+    /// to `new_name`. The AST index cannot confirm these are the renamed
+    /// symbol, so they are offered for the user to opt into.
+    async fn textual_rename_candidates(
+        &self,
+        old_name: &str,
+        new_name: &str,
+        changes: &HashMap<Url, Vec<TextEdit>>,
+    ) -> HashMap<Url, Vec<TextEdit>> {
+        let project_index = self.definition_provider.project_index();
+        let unindexed: Vec<PathBuf> = self
+            .workspace_shader_sources()
+            .await
+            .into_iter()
+            .filter(|path| !project_index.contains_file(path))
+            .filter(|path| Url::from_file_path(path).is_ok_and(|uri| !changes.contains_key(&uri)))
+            .collect();
+        let name = old_name.to_owned();
+        let mentioning =
+            tokio::task::spawn_blocking(move || files_mentioning(unindexed.iter().map(PathBuf::as_path), &name))
+                .await
+                .unwrap_or_default();
+
+        let mut candidates = HashMap::new();
+        for path in mentioning {
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            let source = match self.document_store.get_content(&uri) {
+                Some(source) => source,
+                None => match tokio::fs::read_to_string(&path).await {
+                    Ok(source) => source,
+                    Err(_) => continue,
+                },
+            };
+            let tree = SyntaxTree::parse(&source);
+            let edits: Vec<TextEdit> = identifier_ranges(&tree.root(), &source, old_name)
+                .into_iter()
+                .map(|range| TextEdit {
+                    range,
+                    new_text: new_name.to_string(),
+                })
+                .collect();
+            if !edits.is_empty() {
+                candidates.insert(uri, edits);
+            }
+        }
+        candidates
+    }
+
+    /// When `old_name` is a struct or class, extend `changes` with its
+    /// constructor, destructor and conversion operator spellings in the files
+    /// the rename already touches.
+    async fn add_type_companion_edits(
+        &self,
+        changes: &mut HashMap<Url, Vec<TextEdit>>,
+        old_name: &str,
+        new_name: &str,
+    ) {
+        let mut trees = Vec::with_capacity(changes.len());
+        for uri in changes.keys() {
+            let tree = match self.document_trees.get(uri) {
+                Some(tree) => tree,
+                None => match uri.to_file_path() {
+                    Ok(path) => match tokio::fs::read_to_string(&path).await {
+                        Ok(source) => SyntaxTree::parse(&source),
+                        Err(_) => continue,
+                    },
+                    Err(_) => continue,
+                },
+            };
+            trees.push((uri.clone(), tree));
+        }
+        if !trees.iter().any(|(_, tree)| defines_type(&tree.root(), old_name)) {
+            return;
+        }
+
+        for (uri, tree) in trees {
+            let edits = changes.entry(uri).or_default();
+            for range in type_name_companion_ranges(&tree.root(), tree.source(), old_name) {
+                if edits.iter().all(|edit| edit.range != range) {
+                    edits.push(TextEdit {
+                        range,
+                        new_text: new_name.to_string(),
+                    });
+                }
+            }
+        }
+    }
+}
+
 fn short_name(uri: &Url) -> String {
     uri.path().rsplit('/').next().unwrap_or(uri.path()).to_owned()
 }
@@ -794,6 +1637,24 @@ fn short_path(path: &str) -> &str {
     path.rsplit('/').next().unwrap_or(path)
 }
 
+/// A rename refused by validation, with its reason and any conflicting
+/// declarations in the error data.
+fn rename_error(error: RenameError) -> tower_lsp::jsonrpc::Error {
+    let mut data = serde_json::json!({ "reason": error.reason() });
+    if let RenameError::Conflict {
+        declarations,
+        ..
+    } = &error
+    {
+        data["conflicts"] = serde_json::json!(declarations);
+    }
+    tower_lsp::jsonrpc::Error {
+        code: tower_lsp::jsonrpc::ErrorCode::InvalidParams,
+        message: error.to_string().into(),
+        data: Some(data),
+    }
+}
+
 fn prefixed_client_message(message: impl AsRef<str>) -> String {
     format!("{CLIENT_NOTIFICATION_PREFIX} {}", message.as_ref())
 }
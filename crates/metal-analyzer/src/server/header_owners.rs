@@ -1,10 +1,12 @@
 use std::{
-    collections::BTreeSet,
+    collections::{BTreeSet, HashMap, VecDeque},
     path::{Path, PathBuf},
 };
 
 use dashmap::DashMap;
 
+use crate::vfs::path_key;
+
 pub(crate) fn is_header_file(path: &Path) -> bool {
     matches!(path.extension().and_then(|s| s.to_str()), Some("h" | "hh" | "hpp" | "hxx"))
 }
@@ -43,6 +45,73 @@ pub(crate) fn collect_included_headers(
         .collect()
 }
 
+/// A project header reached from the main file of a translation unit.
+pub(crate) struct IncludedHeader {
+    pub path: PathBuf,
+    pub source: String,
+    /// Zero-based line of the main file's `#include` through which the header
+    /// was first reached.
+    pub origin_line: u32,
+}
+
+/// Project headers reachable from `main`, breadth-first, at most `limit`.
+///
+/// System headers are skipped. `overlay` supplies the unsaved text of an open
+/// header in place of its contents on disk.
+pub(crate) async fn collect_translation_unit_headers(
+    main: &Path,
+    source: &str,
+    include_paths: &[String],
+    limit: usize,
+    overlay: Option<(&Path, &str)>,
+) -> Vec<IncludedHeader> {
+    let mut visited = std::collections::HashSet::from([normalize_path(main)]);
+    let mut headers: Vec<IncludedHeader> = Vec::new();
+    let mut queue: VecDeque<(PathBuf, u32, String)> = VecDeque::new();
+
+    let direct = source.lines().enumerate().flat_map(|(line, text)| {
+        collect_included_headers(main, text, include_paths).into_iter().map(move |header| (header, line as u32))
+    });
+    let mut pending: VecDeque<(PathBuf, u32)> = direct.collect();
+    loop {
+        while let Some((header, origin_line)) = pending.pop_front() {
+            if headers.len() >= limit
+                || crate::definition::is_system_header(&header.to_string_lossy())
+                || !visited.insert(header.clone())
+            {
+                continue;
+            }
+            let header_source = match overlay {
+                Some((path, text)) if path == header => text.to_string(),
+                _ => match tokio::fs::read_to_string(&header).await {
+                    Ok(text) => text,
+                    Err(_) => continue,
+                },
+            };
+            headers.push(IncludedHeader {
+                path: header.clone(),
+                source: header_source.clone(),
+                origin_line,
+            });
+            queue.push_back((header, origin_line, header_source));
+        }
+        let Some((file, origin_line, file_source)) = queue.pop_front() else {
+            break;
+        };
+        pending.extend(
+            collect_included_headers(&file, &file_source, include_paths)
+                .into_iter()
+                .map(|header| (header, origin_line)),
+        );
+    }
+    headers
+}
+
+/// Record that `owner` includes `new_headers`, replacing its previous links.
+///
+/// Both maps are keyed by [`path_key`], so spellings differing only in case
+/// share an entry on case-insensitive filesystems; the owner and header sets
+/// keep normalized paths for display and URIs.
 pub(crate) fn update_owner_links(
     header_owners: &DashMap<PathBuf, BTreeSet<PathBuf>>,
     owner_headers: &DashMap<PathBuf, BTreeSet<PathBuf>>,
@@ -50,25 +119,28 @@ pub(crate) fn update_owner_links(
     new_headers: BTreeSet<PathBuf>,
 ) {
     let owner = normalize_path(owner);
+    let owner_key = path_key(&owner);
 
-    if let Some((_, previous_headers)) = owner_headers.remove(&owner) {
+    if let Some((_, previous_headers)) = owner_headers.remove(&owner_key) {
         for header in previous_headers {
-            if let Some(mut owners) = header_owners.get_mut(&header) {
-                owners.remove(&owner);
+            let header_key = path_key(&header);
+            if let Some(mut owners) = header_owners.get_mut(&header_key) {
+                owners.retain(|existing| path_key(existing) != owner_key);
                 if owners.is_empty() {
                     drop(owners);
-                    header_owners.remove(&header);
+                    header_owners.remove(&header_key);
                 }
             }
         }
     }
 
     for header in &new_headers {
-        let mut owners = header_owners.entry(header.clone()).or_default();
+        let mut owners = header_owners.entry(path_key(header)).or_default();
+        owners.retain(|existing| path_key(existing) != owner_key);
         owners.insert(owner.clone());
     }
 
-    owner_headers.insert(owner, new_headers);
+    owner_headers.insert(owner_key, new_headers);
 }
 
 pub(crate) fn get_owner_candidates_for_header(
@@ -76,49 +148,128 @@ pub(crate) fn get_owner_candidates_for_header(
     header: &Path,
     cap: usize,
 ) -> Vec<PathBuf> {
-    let Some(owners) = header_owners.get(&normalize_path(header)) else {
+    let Some(owners) = header_owners.get(&path_key(header)) else {
         return Vec::new();
     };
     owners.iter().take(cap).cloned().collect()
 }
 
+/// Owner `.metal` files that include `header` directly or through other headers.
+///
+/// Header-to-header edges are not tracked in the owner maps, so they are
+/// discovered on demand with `includes_of`, starting from the headers each
+/// owner includes directly. At most `scan_cap` headers are expanded and at most
+/// `cap` owners are returned.
+pub(crate) fn collect_dependent_owners(
+    header_owners: &DashMap<PathBuf, BTreeSet<PathBuf>>,
+    owner_headers: &DashMap<PathBuf, BTreeSet<PathBuf>>,
+    header: &Path,
+    mut includes_of: impl FnMut(&Path) -> BTreeSet<PathBuf>,
+    scan_cap: usize,
+    cap: usize,
+) -> Vec<PathBuf> {
+    let header = path_key(header);
+
+    // Forward header graph reachable from the owners' direct includes, keyed
+    // by `path_key` like the owner maps.
+    let mut header_includes: HashMap<PathBuf, BTreeSet<PathBuf>> = HashMap::new();
+    let mut queue: VecDeque<PathBuf> =
+        owner_headers.iter().flat_map(|entry| entry.value().iter().map(|h| path_key(h)).collect::<Vec<_>>()).collect();
+    while let Some(current) = queue.pop_front() {
+        if header_includes.len() >= scan_cap {
+            break;
+        }
+        if header_includes.contains_key(&current) {
+            continue;
+        }
+        let includes: BTreeSet<PathBuf> = includes_of(&current).iter().map(|include| path_key(include)).collect();
+        queue.extend(includes.iter().filter(|inc| !header_includes.contains_key(*inc)).cloned());
+        header_includes.insert(current, includes);
+    }
+
+    // Walk the reversed edges from the changed header.
+    let mut affected = BTreeSet::from([header.clone()]);
+    let mut frontier = vec![header];
+    while let Some(target) = frontier.pop() {
+        for (includer, includes) in &header_includes {
+            if includes.contains(&target) && affected.insert(includer.clone()) {
+                frontier.push(includer.clone());
+            }
+        }
+    }
+
+    let mut owners = BTreeSet::new();
+    for affected_header in &affected {
+        if let Some(direct) = header_owners.get(affected_header) {
+            owners.extend(direct.iter().cloned());
+        }
+    }
+    owners.into_iter().take(cap).collect()
+}
+
 pub(crate) fn resolve_include_path(
     owner: &Path,
     include_path: &str,
     is_system: bool,
     include_paths: &[String],
 ) -> Option<PathBuf> {
-    let include = Path::new(include_path);
-    if include.is_absolute() && include.exists() {
-        return Some(normalize_path(include));
-    }
+    include_candidates(owner, include_path, is_system, include_paths).next().map(|(path, _)| path)
+}
 
-    if !is_system && let Some(parent) = owner.parent() {
-        let candidate = parent.join(include);
-        if candidate.exists() {
-            return Some(normalize_path(&candidate));
-        }
-    }
+/// Where an `#include` was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum IncludeRoot {
+    /// The name is an absolute path.
+    Absolute,
+    /// The directory of the including file, searched first for quoted
+    /// includes.
+    IncludingDirectory(PathBuf),
+    /// An include search path.
+    SearchPath(PathBuf),
+    /// A framework directory, searched as `Name.framework/Headers`.
+    Framework(PathBuf),
+}
 
-    for include_dir in include_paths {
+/// Every existing file `include_path` names, in the order the compiler
+/// searches: the first is the one included, the rest are shadowed by it.
+pub(crate) fn include_candidates<'a>(
+    owner: &'a Path,
+    include_path: &'a str,
+    is_system: bool,
+    include_paths: &'a [String],
+) -> impl Iterator<Item = (PathBuf, IncludeRoot)> + 'a {
+    let include = Path::new(include_path);
+    let absolute = include.is_absolute();
+    let absolute_match = (absolute && include.exists()).then(|| (normalize_path(include), IncludeRoot::Absolute));
+    let local_match = owner.parent().filter(|_| !is_system && !absolute).and_then(|parent| {
+        let candidate = parent.join(include);
+        candidate.exists().then(|| (normalize_path(&candidate), IncludeRoot::IncludingDirectory(parent.to_path_buf())))
+    });
+    let search_paths = if absolute {
+        &[][..]
+    } else {
+        include_paths
+    };
+    let search_matches = search_paths.iter().filter_map(move |include_dir| {
         if let Some(framework_root) = include_dir.strip_prefix(crate::metal::compiler::FRAMEWORK_DIR_PREFIX) {
-            if let Some(resolved) = resolve_framework_include(framework_root, include_path) {
-                return Some(resolved);
-            }
+            resolve_framework_include(framework_root, include_path)
+                .map(|resolved| (resolved, IncludeRoot::Framework(PathBuf::from(framework_root))))
         } else {
-            let candidate = Path::new(include_dir).join(include);
-            if candidate.exists() {
-                return Some(normalize_path(&candidate));
-            }
+            let candidate = Path::new(include_dir).join(include_path);
+            candidate
+                .exists()
+                .then(|| (normalize_path(&candidate), IncludeRoot::SearchPath(PathBuf::from(include_dir))))
         }
-    }
-
-    None
+    });
+    absolute_match.into_iter().chain(local_match).chain(search_matches)
 }
 
 /// Resolve a framework-style include using clang's lookup rule:
 ///   `Foo/Bar.h`  →  `<framework_root>/Foo.framework/Headers/Bar.h`
-pub(crate) fn resolve_framework_include(framework_root: &str, include_path: &str) -> Option<PathBuf> {
+pub(crate) fn resolve_framework_include(
+    framework_root: &str,
+    include_path: &str,
+) -> Option<PathBuf> {
     let include = Path::new(include_path);
     let mut components = include.components();
     let first = components.next()?.as_os_str().to_str()?;
@@ -126,11 +277,12 @@ pub(crate) fn resolve_framework_include(framework_root: &str, include_path: &str
     if rest.as_os_str().is_empty() {
         return None;
     }
-    let candidate = Path::new(framework_root)
-        .join(format!("{first}.framework"))
-        .join("Headers")
-        .join(rest);
-    if candidate.exists() { Some(normalize_path(&candidate)) } else { None }
+    let candidate = Path::new(framework_root).join(format!("{first}.framework")).join("Headers").join(rest);
+    if candidate.exists() {
+        Some(normalize_path(&candidate))
+    } else {
+        None
+    }
 }
 
 pub(crate) fn normalize_path(path: &Path) -> PathBuf {
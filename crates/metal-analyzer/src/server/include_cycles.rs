@@ -0,0 +1,114 @@
+//! Warnings for `#include` cycles and unguarded self-includes.
+//!
+//! Include guards stop a cycle from recursing forever, but whichever file
+//! the compiler reaches first still sees the other half of the cycle
+//! undeclared, so cycles are reported even between guarded headers. A file
+//! that includes itself only recurses without a guard, and only then is it
+//! reported.
+
+use std::path::{Path, PathBuf};
+
+use dashmap::DashMap;
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, NumberOrString, Url,
+};
+
+use crate::{
+    definition::include_graph::{IncludeEdge, IncludeGraph},
+    metal::compiler::MetalCompiler,
+    server::{diagnostics::compute_include_paths_for_uri_cached, include_graphs::MAX_INCLUDE_GRAPH_FILES},
+};
+
+pub(super) const INCLUDE_CYCLE_CODE: &str = "include-cycle";
+
+pub(super) async fn include_cycle_diagnostics(
+    compiler: &MetalCompiler,
+    workspace_roots: &[PathBuf],
+    include_paths_cache: &DashMap<PathBuf, (u64, Vec<String>)>,
+    workspace_generation: u64,
+    uri: &Url,
+    text: &str,
+) -> Vec<Diagnostic> {
+    let Ok(path) = uri.to_file_path() else {
+        return Vec::new();
+    };
+    if !text.contains("include") {
+        return Vec::new();
+    }
+    let include_paths =
+        compute_include_paths_for_uri_cached(compiler, uri, workspace_roots, include_paths_cache, workspace_generation)
+            .await;
+    let root = (path.clone(), text.to_string());
+    let graph = tokio::task::spawn_blocking(move || {
+        IncludeGraph::build([root], &include_paths, MAX_INCLUDE_GRAPH_FILES, |file| std::fs::read_to_string(file).ok())
+    })
+    .await
+    .unwrap_or_default();
+    cycle_diagnostics(&graph, &path)
+}
+
+/// A warning on each `#include` of `path` that leads back to it.
+pub(super) fn cycle_diagnostics(
+    graph: &IncludeGraph,
+    path: &Path,
+) -> Vec<Diagnostic> {
+    let Some(root) = graph.node(path) else {
+        return Vec::new();
+    };
+    let nodes = graph.nodes();
+    let mut diagnostics = Vec::new();
+    for edge in graph.edges_from(root) {
+        if edge.to == root {
+            if !nodes[root].guarded {
+                diagnostics.push(warning(edge, "file includes itself without an include guard".to_string(), None));
+            }
+            continue;
+        }
+        let Some(chain) = graph.path(edge.to, root) else {
+            continue;
+        };
+        let names: Vec<String> = std::iter::once(root)
+            .chain(std::iter::once(edge).chain(chain.iter().copied()).map(|step| step.to))
+            .map(|node| file_name(&nodes[node].path))
+            .collect();
+        let related = chain
+            .iter()
+            .filter_map(|step| {
+                let uri = Url::from_file_path(&nodes[step.from].path).ok()?;
+                Some(DiagnosticRelatedInformation {
+                    location: Location {
+                        uri,
+                        range: step.range,
+                    },
+                    message: format!("includes {}", file_name(&nodes[step.to].path)),
+                })
+            })
+            .collect();
+        diagnostics.push(warning(edge, format!("include cycle: {}", names.join(" -> ")), Some(related)));
+    }
+    diagnostics
+}
+
+fn warning(
+    edge: &IncludeEdge,
+    message: String,
+    related_information: Option<Vec<DiagnosticRelatedInformation>>,
+) -> Diagnostic {
+    Diagnostic {
+        range: edge.range,
+        severity: Some(DiagnosticSeverity::WARNING),
+        code: Some(NumberOrString::String(INCLUDE_CYCLE_CODE.to_string())),
+        source: Some("metal-analyzer".to_string()),
+        message,
+        related_information,
+        ..Default::default()
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/server/include_cycles_tests.rs"]
+mod tests;
@@ -0,0 +1,130 @@
+//! Include graphs of translation units, kept between diagnostics passes.
+//!
+//! Walking the includes of a main file reads every project header it
+//! reaches, and the checks over a translation unit each need that walk. The
+//! graph of each root is kept until the workspace generation changes, the
+//! root's text or the unsaved header laid over it changes, or a file the walk
+//! read changes on disk.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use dashmap::DashMap;
+
+use crate::{
+    definition::include_graph::IncludeGraph, document::ContentHash, server::header_owners::normalize_path,
+    vfs::path_key,
+};
+
+/// Project files read to build the graph of one root.
+pub(crate) const MAX_INCLUDE_GRAPH_FILES: usize = 256;
+
+#[derive(Default)]
+pub(crate) struct IncludeGraphCache {
+    graphs: DashMap<PathBuf, CachedGraph>,
+}
+
+#[derive(Clone)]
+struct CachedGraph {
+    workspace_generation: u64,
+    root: ContentHash,
+    overlay: Option<(PathBuf, ContentHash)>,
+    /// Files the walk read from disk, with their modification times then.
+    read: Arc<[(PathBuf, Option<SystemTime>)]>,
+    graph: Arc<IncludeGraph>,
+}
+
+impl IncludeGraphCache {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// The graph of the files reachable from `root`, whose text is `source`
+    /// with hash `source_hash`. `overlay` supplies the unsaved text of an open
+    /// header, with its hash, in place of its contents on disk.
+    ///
+    /// The hashes are taken as given, so open documents pass the one their
+    /// [`DocumentStore`](crate::document::DocumentStore) entry maintains.
+    pub(crate) async fn graph(
+        &self,
+        root: &Path,
+        (source, source_hash): (&str, ContentHash),
+        overlay: Option<(&Path, &str, ContentHash)>,
+        include_paths: &[String],
+        workspace_generation: u64,
+    ) -> Arc<IncludeGraph> {
+        let root = normalize_path(root);
+        let key = path_key(&root);
+        let root_hash = source_hash;
+        let overlay_hash = overlay.map(|(path, _, hash)| (normalize_path(path), hash));
+        let overlay = overlay.map(|(path, text, _)| (normalize_path(path), text.to_string()));
+
+        let cached = self.graphs.get(&key).map(|entry| entry.clone()).filter(|cached| {
+            cached.workspace_generation == workspace_generation
+                && cached.root == root_hash
+                && cached.overlay == overlay_hash
+        });
+        if let Some(cached) = cached {
+            let read = Arc::clone(&cached.read);
+            let unchanged =
+                tokio::task::spawn_blocking(move || read.iter().all(|(path, when)| modified(path) == *when))
+                    .await
+                    .unwrap_or(false);
+            if unchanged {
+                crate::perf::count("includeGraph.cacheHit");
+                return cached.graph;
+            }
+        }
+        crate::perf::count("includeGraph.cacheMiss");
+
+        let source = source.to_string();
+        let include_paths = include_paths.to_vec();
+        let build_root = root.clone();
+        let built = tokio::task::spawn_blocking(move || {
+            let mut read = Vec::new();
+            let graph = IncludeGraph::build([(build_root, source)], &include_paths, MAX_INCLUDE_GRAPH_FILES, |file| {
+                if let Some((_, text)) = overlay.as_ref().filter(|(path, _)| path_key(path) == path_key(file)) {
+                    return Some(text.clone());
+                }
+                read.push((file.to_path_buf(), modified(file)));
+                std::fs::read_to_string(file).ok()
+            });
+            (graph, read)
+        })
+        .await;
+        let Ok((graph, read)) = built else {
+            return Arc::new(IncludeGraph::default());
+        };
+        let graph = Arc::new(graph);
+        self.graphs.insert(
+            key,
+            CachedGraph {
+                workspace_generation,
+                root: root_hash,
+                overlay: overlay_hash,
+                read: read.into(),
+                graph: Arc::clone(&graph),
+            },
+        );
+        graph
+    }
+
+    /// Drop the graph rooted at `root`, e.g. once its document is closed.
+    pub(crate) fn forget(
+        &self,
+        root: &Path,
+    ) {
+        self.graphs.remove(&path_key(&normalize_path(root)));
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/server/include_graphs_tests.rs"]
+mod tests;
@@ -0,0 +1,368 @@
+//! Analyzer-only lint rules behind `metal-analyzer lint`.
+//!
+//! Runs the checks the server adds on top of compiler diagnostics, without
+//! compiling. Every rule but `unused-include` and `unused-symbol` reads only
+//! the syntax tree and the include graph, so it works without the Metal
+//! toolchain; those two need the AST index. Findings are rendered as
+//! text, as a JSON array, or as a SARIF log (see [`crate::server::sarif`]).
+
+use std::path::{Path, PathBuf};
+
+use dashmap::DashMap;
+use futures::StreamExt;
+use serde_json::{Value, json};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Url};
+
+use crate::{
+    definition::DefinitionProvider,
+    document::ContentHash,
+    ide::{
+        address_spaces::{ADDRESS_SPACE_CODE, address_space_diagnostics},
+        bindings::{DUPLICATE_BINDING_CODE, duplicate_bindings},
+        entry_point_attributes::{ENTRY_POINT_ATTRIBUTE_CODE, entry_point_attribute_diagnostics},
+        include_guards::{INCLUDE_GUARD_CODE, include_guard_diagnostics},
+        language_version::{LANGUAGE_VERSION_CODE, unavailable_builtins},
+        macros::MacroIndex,
+        syntax_diagnostics::{SYNTAX_CODE, syntax_diagnostics},
+        threadgroup_memory::{THREADGROUP_MEMORY_CODE, threadgroup_memory_diagnostics},
+        todos::{TODO_CODE, TodoComment, find_todos},
+        unused_includes::UNUSED_INCLUDE_CODE,
+        unused_symbols::{UNUSED_SYMBOL_CODE, unused_symbol_diagnostics},
+    },
+    metal::compiler::MetalCompiler,
+    server::{
+        diagnostics::DiagnosticsContext,
+        header_owners::is_header_file,
+        include_cycles::{INCLUDE_CYCLE_CODE, include_cycle_diagnostics},
+        include_graphs::IncludeGraphCache,
+        macro_conflicts::{MACRO_CONFLICT_CODE, macro_conflict_diagnostics},
+        sarif::{SarifResult, SarifRule},
+        settings::ServerSettings,
+        unused_includes::unused_include_diagnostics,
+    },
+    syntax::SyntaxTree,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintRule {
+    Syntax,
+    DuplicateBinding,
+    AddressSpace,
+    EntryPointAttribute,
+    LanguageVersion,
+    MacroConflict,
+    IncludeCycle,
+    IncludeGuard,
+    ThreadgroupMemory,
+    UnusedInclude,
+    UnusedSymbol,
+    Todo,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 12] = [
+        LintRule::Syntax,
+        LintRule::DuplicateBinding,
+        LintRule::AddressSpace,
+        LintRule::EntryPointAttribute,
+        LintRule::LanguageVersion,
+        LintRule::MacroConflict,
+        LintRule::IncludeCycle,
+        LintRule::IncludeGuard,
+        LintRule::ThreadgroupMemory,
+        LintRule::UnusedInclude,
+        LintRule::UnusedSymbol,
+        LintRule::Todo,
+    ];
+
+    /// Name used on the command line, which is also the diagnostic code.
+    pub fn name(self) -> &'static str {
+        match self {
+            LintRule::Syntax => SYNTAX_CODE,
+            LintRule::DuplicateBinding => DUPLICATE_BINDING_CODE,
+            LintRule::AddressSpace => ADDRESS_SPACE_CODE,
+            LintRule::EntryPointAttribute => ENTRY_POINT_ATTRIBUTE_CODE,
+            LintRule::LanguageVersion => LANGUAGE_VERSION_CODE,
+            LintRule::MacroConflict => MACRO_CONFLICT_CODE,
+            LintRule::IncludeCycle => INCLUDE_CYCLE_CODE,
+            LintRule::IncludeGuard => INCLUDE_GUARD_CODE,
+            LintRule::ThreadgroupMemory => THREADGROUP_MEMORY_CODE,
+            LintRule::UnusedInclude => UNUSED_INCLUDE_CODE,
+            LintRule::UnusedSymbol => UNUSED_SYMBOL_CODE,
+            LintRule::Todo => TODO_CODE,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            LintRule::Syntax => "Unbalanced brackets, malformed attributes, or a missing `;`",
+            LintRule::DuplicateBinding => "Two parameters of one function bind the same resource slot",
+            LintRule::AddressSpace => {
+                "A pointer or reference in the wrong address space, or a write to `constant` memory"
+            },
+            LintRule::EntryPointAttribute => {
+                "An entry-point attribute on the wrong type or stage, or a vertex or fragment function without its output"
+            },
+            LintRule::LanguageVersion => "A builtin introduced after the targeted Metal language version",
+            LintRule::MacroConflict => "A macro defined differently by files of one translation unit",
+            LintRule::IncludeCycle => "An `#include` that leads back to the including file",
+            LintRule::IncludeGuard => "A header without `#pragma once` or an include guard named after it",
+            LintRule::ThreadgroupMemory => {
+                "A kernel declaring more threadgroup memory than `diagnostics.threadgroupMemoryLimit`"
+            },
+            LintRule::UnusedInclude => "A project header the file does not use",
+            LintRule::UnusedSymbol => "A `static` function or constant nothing in the workspace uses",
+            LintRule::Todo => "A comment starting with a `todos.tags` tag",
+        }
+    }
+
+    /// Whether the rule needs the Metal toolchain to build an AST index.
+    pub fn needs_toolchain(self) -> bool {
+        matches!(self, LintRule::UnusedInclude | LintRule::UnusedSymbol)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    Allow,
+    /// Report findings at the severity the rule gives them.
+    Warn,
+    /// Report findings as errors.
+    Deny,
+}
+
+/// Rule levels from `--allow` and `--deny`, over the defaults: `todo` follows
+/// `todos.diagnostics`, `unused-symbol` follows `diagnostics.unusedSymbols`,
+/// every other rule warns. A rule both allowed and
+/// denied is denied.
+#[derive(Debug, Clone, Default)]
+pub struct LintLevels {
+    pub allow: Vec<LintRule>,
+    pub deny: Vec<LintRule>,
+}
+
+impl LintLevels {
+    pub fn level(
+        &self,
+        rule: LintRule,
+        settings: &ServerSettings,
+    ) -> LintLevel {
+        if self.deny.contains(&rule) {
+            LintLevel::Deny
+        } else if self.allow.contains(&rule)
+            || (rule == LintRule::Todo && !settings.todos.diagnostics)
+            || (rule == LintRule::UnusedSymbol && !settings.diagnostics.unused_symbols)
+        {
+            LintLevel::Allow
+        } else {
+            LintLevel::Warn
+        }
+    }
+}
+
+/// One rule violation in a linted file.
+#[derive(Debug, Clone)]
+pub struct LintFinding {
+    pub rule: LintRule,
+    pub path: PathBuf,
+    pub diagnostic: Diagnostic,
+}
+
+impl LintFinding {
+    pub fn is_error(&self) -> bool {
+        self.diagnostic.severity == Some(DiagnosticSeverity::ERROR)
+    }
+
+    pub fn to_sarif(&self) -> SarifResult {
+        SarifResult {
+            rule_id: self.rule.name().to_string(),
+            ..SarifResult::from_diagnostic(&self.path, &self.diagnostic)
+        }
+    }
+}
+
+/// The rules enabled by `levels` under `settings`.
+pub fn enabled_rules(
+    levels: &LintLevels,
+    settings: &ServerSettings,
+) -> Vec<LintRule> {
+    LintRule::ALL.into_iter().filter(|rule| levels.level(*rule, settings) != LintLevel::Allow).collect()
+}
+
+/// Lint `path` with the rules `levels` enables. `definitions` holds the AST
+/// indexes for `unused-include`, which is skipped without one.
+pub async fn lint_file(
+    compiler: &MetalCompiler,
+    definitions: Option<&DefinitionProvider>,
+    workspace_roots: &[PathBuf],
+    settings: &ServerSettings,
+    levels: &LintLevels,
+    path: &Path,
+) -> std::io::Result<Vec<LintFinding>> {
+    let text = tokio::fs::read_to_string(path).await?;
+    let uri = Url::from_file_path(path)
+        .map_err(|()| std::io::Error::new(std::io::ErrorKind::InvalidInput, "not an absolute path"))?;
+    let tree = SyntaxTree::parse(&text);
+    let root = tree.root();
+    let include_paths_cache = DashMap::new();
+    let include_graphs = IncludeGraphCache::new();
+
+    let mut findings = Vec::new();
+    for rule in enabled_rules(levels, settings) {
+        let diagnostics = match rule {
+            LintRule::Syntax => syntax_diagnostics(&root, &text),
+            LintRule::DuplicateBinding => duplicate_bindings(&root, &text, &uri),
+            LintRule::AddressSpace => address_space_diagnostics(&root, &text),
+            LintRule::EntryPointAttribute => entry_point_attribute_diagnostics(&root, &text),
+            LintRule::LanguageVersion => match compiler.language_version(&uri) {
+                Some(version) => unavailable_builtins(&root, &text, version, &compiler.effective_defines(&uri)),
+                None => Vec::new(),
+            },
+            LintRule::MacroConflict => {
+                let context = DiagnosticsContext {
+                    compiler,
+                    workspace_roots,
+                    header_owners: &DashMap::new(),
+                    owner_headers: &DashMap::new(),
+                    include_paths_cache: &include_paths_cache,
+                    include_graphs: &include_graphs,
+                    workspace_generation: 0,
+                };
+                macro_conflict_diagnostics(&context, &uri, &text, ContentHash::of(&text)).await
+            },
+            LintRule::IncludeCycle => {
+                include_cycle_diagnostics(compiler, workspace_roots, &include_paths_cache, 0, &uri, &text).await
+            },
+            LintRule::IncludeGuard if is_header_file(path) => include_guard_diagnostics(path, &text, workspace_roots),
+            LintRule::IncludeGuard => Vec::new(),
+            LintRule::ThreadgroupMemory => {
+                let mut macros = MacroIndex::from_defines(&compiler.effective_defines(&uri));
+                macros.add_source(&text, Some(path));
+                threadgroup_memory_diagnostics(
+                    &root,
+                    &text,
+                    &uri,
+                    &macros,
+                    settings.diagnostics.threadgroup_memory_limit,
+                )
+            },
+            LintRule::UnusedInclude => match definitions.and_then(|definitions| definitions.get_cached_index(&uri)) {
+                Some(index) => {
+                    unused_include_diagnostics(compiler, workspace_roots, &include_paths_cache, 0, &index, &uri, &text)
+                        .await
+                },
+                None => Vec::new(),
+            },
+            LintRule::UnusedSymbol => match definitions {
+                Some(definitions) => unused_symbol_diagnostics(
+                    definitions.project_index(),
+                    path,
+                    &text,
+                    &settings.diagnostics.unused_symbols_allow,
+                ),
+                None => Vec::new(),
+            },
+            LintRule::Todo => {
+                find_todos(&root, &text, &settings.todos.tags).iter().map(TodoComment::to_diagnostic).collect()
+            },
+        };
+        let deny = levels.level(rule, settings) == LintLevel::Deny;
+        findings.extend(diagnostics.into_iter().map(|mut diagnostic| {
+            if deny {
+                diagnostic.severity = Some(DiagnosticSeverity::ERROR);
+            }
+            LintFinding {
+                rule,
+                path: path.to_path_buf(),
+                diagnostic,
+            }
+        }));
+    }
+    findings.sort_by_key(|finding| (finding.diagnostic.range.start.line, finding.diagnostic.range.start.character));
+    Ok(findings)
+}
+
+/// Run [`lint_file`] on `files` with up to `indexing.concurrency` files in flight,
+/// returning the results in the order of `files`.
+pub async fn lint_files(
+    compiler: &MetalCompiler,
+    definitions: Option<&DefinitionProvider>,
+    workspace_roots: &[PathBuf],
+    settings: &ServerSettings,
+    levels: &LintLevels,
+    files: &[PathBuf],
+) -> Vec<(PathBuf, std::io::Result<Vec<LintFinding>>)> {
+    futures::stream::iter(files)
+        .map(|path| async move {
+            (path.clone(), lint_file(compiler, definitions, workspace_roots, settings, levels, path).await)
+        })
+        .buffered(settings.indexing.concurrency.max(1))
+        .collect()
+        .await
+}
+
+fn severity_name(severity: Option<DiagnosticSeverity>) -> &'static str {
+    match severity {
+        Some(DiagnosticSeverity::ERROR) => "error",
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        Some(DiagnosticSeverity::INFORMATION) => "note",
+        _ => "hint",
+    }
+}
+
+/// Render `finding` as `file:line:col: severity: message [rule]` with
+/// one-based positions.
+pub fn render_finding(finding: &LintFinding) -> String {
+    let start = finding.diagnostic.range.start;
+    format!(
+        "{}:{}:{}: {}: {} [{}]",
+        finding.path.display(),
+        start.line + 1,
+        start.character + 1,
+        severity_name(finding.diagnostic.severity),
+        finding.diagnostic.message,
+        finding.rule.name()
+    )
+}
+
+/// `findings` as a JSON array with one-based positions.
+pub fn findings_json(findings: &[LintFinding]) -> Value {
+    Value::Array(
+        findings
+            .iter()
+            .map(|finding| {
+                let range = finding.diagnostic.range;
+                json!({
+                    "file": finding.path.display().to_string(),
+                    "rule": finding.rule.name(),
+                    "severity": severity_name(finding.diagnostic.severity),
+                    "message": finding.diagnostic.message,
+                    "line": range.start.line + 1,
+                    "column": range.start.character + 1,
+                    "endLine": range.end.line + 1,
+                    "endColumn": range.end.character + 1,
+                })
+            })
+            .collect(),
+    )
+}
+
+/// One SARIF rule per lint rule, in the order of [`LintRule::ALL`].
+pub fn sarif_rules() -> Vec<SarifRule> {
+    LintRule::ALL
+        .iter()
+        .map(|rule| SarifRule {
+            id: rule.name().to_string(),
+            description: rule.description().to_string(),
+            help_uri: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/server/lint_tests.rs"]
+mod tests;
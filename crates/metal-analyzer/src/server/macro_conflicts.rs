@@ -0,0 +1,242 @@
+//! Warnings for macros `#define`d differently by files of one translation unit.
+//!
+//! Clang reports `-Wmacro-redefined` only at the second definition and only
+//! for the translation unit being compiled, so a header whose constant is
+//! silently overridden elsewhere never hears about it. Here every translation
+//! unit the document takes part in is walked through the include graph, and
+//! each conflict is reported on the document's own `#define`, or, for a main
+//! file, on the `#include` that brings in the later definition.
+
+use std::{collections::HashSet, path::Path};
+
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, NumberOrString, Position, Range, Url,
+};
+
+use crate::{
+    definition::include_graph::IncludeGraph,
+    document::ContentHash,
+    ide::macros::{MacroConflict, MacroDefinition, find_macro_conflicts},
+    server::{
+        diagnostics::{DiagnosticsContext, compute_include_paths_for_uri_cached},
+        header_owners::{IncludedHeader, get_owner_candidates_for_header, is_header_file, normalize_path},
+    },
+};
+
+/// Main files examined for a header document.
+const MAX_CONFLICT_OWNERS: usize = 16;
+
+pub(super) const MACRO_CONFLICT_CODE: &str = "macro-conflict";
+
+/// Conflicts involving the document at `uri`, whose text is `text` with the
+/// hash `text_hash`.
+pub(super) async fn macro_conflict_diagnostics(
+    context: &DiagnosticsContext<'_>,
+    uri: &Url,
+    text: &str,
+    text_hash: ContentHash,
+) -> Vec<Diagnostic> {
+    let DiagnosticsContext {
+        compiler,
+        workspace_roots,
+        header_owners,
+        include_paths_cache,
+        include_graphs,
+        workspace_generation,
+        ..
+    } = *context;
+    let Ok(path) = uri.to_file_path() else {
+        return Vec::new();
+    };
+    let path = normalize_path(&path);
+
+    let mut main_files = Vec::new();
+    if is_header_file(&path) {
+        for owner in get_owner_candidates_for_header(header_owners, &path, MAX_CONFLICT_OWNERS) {
+            if let Ok(source) = tokio::fs::read_to_string(&owner).await {
+                let hash = ContentHash::of(&source);
+                main_files.push((owner, source, hash));
+            }
+        }
+    } else {
+        main_files.push((path.clone(), text.to_string(), text_hash));
+    }
+
+    let mut seen = HashSet::new();
+    let mut diagnostics = Vec::new();
+    for (main, source, source_hash) in main_files {
+        let Ok(main_uri) = Url::from_file_path(&main) else {
+            continue;
+        };
+        let include_paths = compute_include_paths_for_uri_cached(
+            compiler,
+            &main_uri,
+            workspace_roots,
+            include_paths_cache,
+            workspace_generation,
+        )
+        .await;
+        let overlay = Some((path.as_path(), text, text_hash)).filter(|_| main != path);
+        let graph =
+            include_graphs.graph(&main, (&source, source_hash), overlay, &include_paths, workspace_generation).await;
+        let headers = translation_unit_headers(&graph, &main, &path, text).await;
+
+        let mut files = vec![(main.clone(), source)];
+        files.extend(headers.iter().map(|header| (header.path.clone(), header.source.clone())));
+        for conflict in find_macro_conflicts(&files) {
+            let origin_line = |file: &Path| headers.iter().find(|header| header.path == file).map(|h| h.origin_line);
+            let diagnostic = if main == path
+                && let Some(second_file) = conflict.second.file.as_deref()
+                && second_file != path
+                && conflict.first.file.as_deref() != Some(&path)
+            {
+                origin_line(second_file).map(|line| included_conflict_diagnostic(&conflict, text, line))
+            } else {
+                own_conflict_diagnostic(&conflict, &path, text)
+            };
+            if let Some(diagnostic) = diagnostic
+                && seen.insert((diagnostic.range.start.line, diagnostic.message.clone()))
+            {
+                diagnostics.push(diagnostic);
+            }
+        }
+    }
+    diagnostics
+}
+
+/// The project files reached from `main` in `graph`, in inclusion order,
+/// with the unsaved `text` of the document at `path` in place of its
+/// contents on disk.
+async fn translation_unit_headers(
+    graph: &IncludeGraph,
+    main: &Path,
+    path: &Path,
+    text: &str,
+) -> Vec<IncludedHeader> {
+    let Some(root) = graph.node(main) else {
+        return Vec::new();
+    };
+    let reached = graph.reached_through(root);
+    let mut headers = Vec::new();
+    for (node, file) in graph.nodes().iter().enumerate() {
+        let Some(through) = reached.get(&node).filter(|_| !file.system) else {
+            continue;
+        };
+        let source = if file.path == path {
+            text.to_string()
+        } else {
+            match tokio::fs::read_to_string(&file.path).await {
+                Ok(source) => source,
+                Err(_) => continue,
+            }
+        };
+        headers.push(IncludedHeader {
+            path: file.path.clone(),
+            source,
+            origin_line: through.range.start.line,
+        });
+    }
+    headers
+}
+
+/// Warning on the document's own definition, pointing at the other one.
+fn own_conflict_diagnostic(
+    conflict: &MacroConflict,
+    document: &Path,
+    text: &str,
+) -> Option<Diagnostic> {
+    let (own, other) = if conflict.first.file.as_deref() == Some(document) {
+        (&conflict.first, &conflict.second)
+    } else if conflict.second.file.as_deref() == Some(document) {
+        (&conflict.second, &conflict.first)
+    } else {
+        return None;
+    };
+    let message =
+        format!("macro `{}` is defined differently in {}: `{}`", own.name, display_location(other), other.signature());
+    Some(warning(definition_name_range(own, text), message, vec![related(other, "conflicting definition")?]))
+}
+
+/// Warning on the main file's `#include` line that brings in both definitions.
+fn included_conflict_diagnostic(
+    conflict: &MacroConflict,
+    text: &str,
+    line: u32,
+) -> Diagnostic {
+    let width = text.lines().nth(line as usize).map_or(0, |line| line.chars().count() as u32);
+    let message = format!(
+        "macro `{}` is defined differently by included headers: `{}` in {} and `{}` in {}",
+        conflict.first.name,
+        conflict.first.signature(),
+        display_location(&conflict.first),
+        conflict.second.signature(),
+        display_location(&conflict.second),
+    );
+    let related_information = [(&conflict.first, "first definition"), (&conflict.second, "conflicting definition")]
+        .into_iter()
+        .filter_map(|(definition, label)| related(definition, label))
+        .collect();
+    warning(Range::new(Position::new(line, 0), Position::new(line, width)), message, related_information)
+}
+
+fn warning(
+    range: Range,
+    message: String,
+    related_information: Vec<DiagnosticRelatedInformation>,
+) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::WARNING),
+        code: Some(NumberOrString::String(MACRO_CONFLICT_CODE.to_string())),
+        source: Some("metal-analyzer".to_string()),
+        message,
+        related_information: Some(related_information),
+        ..Default::default()
+    }
+}
+
+fn related(
+    definition: &MacroDefinition,
+    label: &str,
+) -> Option<DiagnosticRelatedInformation> {
+    let uri = Url::from_file_path(definition.file.as_ref()?).ok()?;
+    let position = Position::new(definition.line, 0);
+    Some(DiagnosticRelatedInformation {
+        location: Location {
+            uri,
+            range: Range::new(position, position),
+        },
+        message: format!("{label}: `{}`", definition.signature()),
+    })
+}
+
+fn display_location(definition: &MacroDefinition) -> String {
+    let file = definition
+        .file
+        .as_deref()
+        .and_then(Path::file_name)
+        .map_or_else(|| "<command line>".to_string(), |name| name.to_string_lossy().into_owned());
+    format!("{file}:{}", definition.line + 1)
+}
+
+/// The macro name on its `#define` line, or the start of the line.
+fn definition_name_range(
+    definition: &MacroDefinition,
+    text: &str,
+) -> Range {
+    let line = text.lines().nth(definition.line as usize).unwrap_or("");
+    let start = line
+        .find("define")
+        .and_then(|keyword| line[keyword..].find(&definition.name).map(|offset| keyword + offset))
+        .map_or(0, |byte| line[..byte].chars().count() as u32);
+    let end = if start == 0 {
+        0
+    } else {
+        start + definition.name.chars().count() as u32
+    };
+    Range::new(Position::new(definition.line, start), Position::new(definition.line, end))
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/server/macro_conflicts_tests.rs"]
+mod tests;
@@ -4,6 +4,9 @@ use std::{
 };
 
 use serde::Deserialize;
+use tower_lsp::lsp_types::FormattingOptions;
+
+use crate::syntax::{kind::SyntaxKind, lexer::Lexer};
 
 const METALFMT_FILENAME: &str = "metalfmt.toml";
 
@@ -238,6 +241,998 @@ fn toml_value_to_clang(value: &toml::Value) -> String {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Built-in formatter
+// ---------------------------------------------------------------------------
+
+/// Brace placement of the built-in formatter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BraceStyle {
+    /// `void f() {`
+    Attach,
+    /// `{` on its own line after functions, types and control statements.
+    Allman,
+}
+
+/// Where `*` and `&` go in declarations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PointerAlignment {
+    /// `float* p`
+    Left,
+    /// `float *p`
+    Right,
+    /// `float * p`
+    Middle,
+}
+
+/// Style of the built-in formatter, from editor options and `metalfmt.toml`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct InternalStyle {
+    pub(crate) indent_width: usize,
+    pub(crate) use_tab: bool,
+    pub(crate) tab_width: usize,
+    pub(crate) braces: BraceStyle,
+    pub(crate) pointer_alignment: PointerAlignment,
+    pub(crate) reference_alignment: PointerAlignment,
+    pub(crate) max_empty_lines: usize,
+}
+
+impl Default for InternalStyle {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            use_tab: false,
+            tab_width: 4,
+            braces: BraceStyle::Attach,
+            pointer_alignment: PointerAlignment::Right,
+            reference_alignment: PointerAlignment::Right,
+            max_empty_lines: 1,
+        }
+    }
+}
+
+impl InternalStyle {
+    /// Style derived from the editor's `FormattingOptions` alone.
+    pub(crate) fn from_options(options: &FormattingOptions) -> Self {
+        let width = (options.tab_size as usize).max(1);
+        Self {
+            indent_width: width,
+            use_tab: !options.insert_spaces,
+            tab_width: width,
+            ..Self::default()
+        }
+    }
+}
+
+/// Style for `source_path`: editor options overridden by the nearest `metalfmt.toml`.
+pub(crate) fn resolve_internal_style(
+    source_path: Option<&Path>,
+    options: &FormattingOptions,
+) -> InternalStyle {
+    let mut style = InternalStyle::from_options(options);
+    let config = source_path
+        .and_then(find_metalfmt_toml)
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| toml::from_str::<MetalFmtConfig>(&content).ok());
+    if let Some(config) = config {
+        config.apply_to(&mut style);
+    }
+    style
+}
+
+impl MetalFmtConfig {
+    /// Apply the keys the built-in formatter understands; the rest only affect clang-format.
+    fn apply_to(
+        &self,
+        style: &mut InternalStyle,
+    ) {
+        if let Some(width) = self.indent_width {
+            style.indent_width = (width as usize).max(1);
+        }
+        if let Some(use_tab) = self.use_tab {
+            style.use_tab = use_tab;
+        }
+        if let Some(width) = self.tab_width {
+            style.tab_width = (width as usize).max(1);
+        }
+        if let Some(braces) = &self.break_before_braces {
+            style.braces = match braces.as_str() {
+                "Allman" | "GNU" | "Whitesmiths" => BraceStyle::Allman,
+                _ => BraceStyle::Attach,
+            };
+        }
+        if let Some(alignment) = self.pointer_alignment.as_deref().and_then(parse_pointer_alignment) {
+            style.pointer_alignment = alignment;
+            style.reference_alignment = alignment;
+        }
+        if let Some(alignment) = self.reference_alignment.as_deref().and_then(parse_pointer_alignment) {
+            style.reference_alignment = alignment;
+        }
+        if let Some(lines) = self.max_empty_lines_to_keep {
+            style.max_empty_lines = lines as usize;
+        }
+    }
+}
+
+fn parse_pointer_alignment(value: &str) -> Option<PointerAlignment> {
+    match value {
+        "Left" => Some(PointerAlignment::Left),
+        "Right" => Some(PointerAlignment::Right),
+        "Middle" => Some(PointerAlignment::Middle),
+        _ => None,
+    }
+}
+
+/// Format Metal source with the built-in formatter.
+///
+/// Line breaks are kept as written, except around braces: the formatter
+/// re-indents every line, places braces per [`BraceStyle`], normalizes
+/// spacing around commas, keywords and pointer declarators, and aligns
+/// the trailing `[[attribute]]`s of consecutive parameters and fields.
+/// Preprocessor directives are left untouched at column zero.
+pub(crate) fn format_source(
+    source: &str,
+    style: &InternalStyle,
+) -> String {
+    // The lexer would take a byte order mark for a token of the first line.
+    let (bom, source) = match source.strip_prefix('\u{feff}') {
+        Some(rest) => ("\u{feff}", rest),
+        None => ("", source),
+    };
+    let crlf = source.contains("\r\n");
+    let normalized;
+    let source = if crlf {
+        normalized = source.replace("\r\n", "\n");
+        normalized.as_str()
+    } else {
+        source
+    };
+
+    let mut toks = tokenize(source, style.tab_width);
+    if toks.is_empty() {
+        return bom.to_string();
+    }
+    let braces = classify_braces(&toks);
+    place_braces(&mut toks, &braces, style.braces);
+    let declarators = find_declarators(&toks);
+
+    let mut renderer = Renderer::new(style, &toks, &braces, &declarators);
+    for i in 0..toks.len() {
+        renderer.token(i);
+    }
+    let mut lines = renderer.finish();
+    align_attributes(&mut lines, style);
+
+    let mut out = bom.to_string();
+    out.push_str(&lines.into_iter().map(|line| line.text).collect::<Vec<_>>().join("\n"));
+    out.push('\n');
+    if crlf {
+        out = out.replace('\n', "\r\n");
+    }
+    out
+}
+
+/// A significant token with the layout it had in the source.
+struct Tok<'a> {
+    kind: SyntaxKind,
+    text: &'a str,
+    /// A whole preprocessor directive, including continuation lines.
+    directive: bool,
+    /// Line breaks between the previous token and this one.
+    newlines: usize,
+    /// Whitespace between the previous token and this one.
+    space_before: bool,
+    /// Visual column of the token in the source.
+    column: usize,
+}
+
+fn tokenize(
+    source: &str,
+    tab_width: usize,
+) -> Vec<Tok<'_>> {
+    let mut toks = Vec::new();
+    let mut lexer = Lexer::new(source).peekable();
+    let mut offset = 0;
+    let mut newlines = 0;
+    let mut space_before = false;
+    let mut column = 0;
+    let mut at_line_start = true;
+
+    while let Some((kind, text)) = lexer.next() {
+        let start = offset;
+        offset += text.len();
+        if kind == SyntaxKind::Whitespace {
+            let breaks = text.matches('\n').count();
+            newlines += breaks;
+            space_before = true;
+            column = advance_column(column, text, tab_width);
+            at_line_start |= breaks > 0;
+            continue;
+        }
+
+        let directive = kind == SyntaxKind::Hash && at_line_start;
+        if directive {
+            while let Some((next_kind, next_text)) = lexer.peek() {
+                if *next_kind == SyntaxKind::Whitespace
+                    && next_text.contains('\n')
+                    && !source[start..offset].ends_with('\\')
+                {
+                    break;
+                }
+                offset += next_text.len();
+                lexer.next();
+            }
+        }
+        let text = if directive {
+            source[start..offset].trim_end()
+        } else {
+            text
+        };
+        toks.push(Tok {
+            kind,
+            text,
+            directive,
+            newlines: if toks.is_empty() {
+                0
+            } else {
+                newlines
+            },
+            space_before,
+            column,
+        });
+        column = advance_column(column, &source[start..offset], tab_width);
+        newlines = 0;
+        space_before = false;
+        at_line_start = false;
+    }
+    toks
+}
+
+/// Visual column after writing `text` at `column`, expanding tabs.
+fn advance_column(
+    column: usize,
+    text: &str,
+    tab_width: usize,
+) -> usize {
+    let (start, rest) = match text.rfind('\n') {
+        Some(i) => (0, &text[i + 1..]),
+        None => (column, text),
+    };
+    rest.chars().fold(start, |column, c| {
+        if c == '\t' {
+            (column / tab_width + 1) * tab_width
+        } else {
+            column + 1
+        }
+    })
+}
+
+fn is_trivia(tok: &Tok<'_>) -> bool {
+    tok.kind == SyntaxKind::Comment
+}
+
+/// How a `{` or `}` token is treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BraceKind {
+    /// Not a brace.
+    None,
+    /// Body of a function, type or control statement; subject to [`BraceStyle`].
+    Body,
+    /// Body of a namespace; placed like [`BraceKind::Body`] but not indented.
+    Namespace,
+    /// A bare compound statement or `case` block; left where it is.
+    Block,
+    /// A braced initializer list; left where it is.
+    Init,
+}
+
+fn classify_braces(toks: &[Tok<'_>]) -> Vec<BraceKind> {
+    let mut kinds = vec![BraceKind::None; toks.len()];
+    let mut stack: Vec<BraceKind> = Vec::new();
+    let mut type_head = false;
+    let mut namespace_head = false;
+    let mut prev: Option<usize> = None;
+    let mut prev_prev: Option<usize> = None;
+
+    for (i, tok) in toks.iter().enumerate() {
+        if is_trivia(tok) {
+            continue;
+        }
+        if tok.directive {
+            type_head = false;
+            namespace_head = false;
+        } else {
+            match tok.kind {
+                SyntaxKind::LBrace => {
+                    let in_init = stack.last() == Some(&BraceKind::Init);
+                    let kind = match prev.map(|p| &toks[p]) {
+                        _ if in_init => BraceKind::Init,
+                        None => BraceKind::Block,
+                        Some(p) if p.directive => BraceKind::Block,
+                        Some(p) => match p.kind {
+                            SyntaxKind::Semicolon | SyntaxKind::LBrace | SyntaxKind::RBrace | SyntaxKind::Colon => {
+                                BraceKind::Block
+                            },
+                            _ if namespace_head => BraceKind::Namespace,
+                            SyntaxKind::RParen
+                            | SyntaxKind::KwElse
+                            | SyntaxKind::KwDo
+                            | SyntaxKind::KwTry
+                            | SyntaxKind::KwStruct
+                            | SyntaxKind::KwClass
+                            | SyntaxKind::KwEnum
+                            | SyntaxKind::KwUnion => BraceKind::Body,
+                            SyntaxKind::KwConst | SyntaxKind::KwNoexcept
+                                if prev_prev.is_some_and(|pp| toks[pp].kind == SyntaxKind::RParen) =>
+                            {
+                                BraceKind::Body
+                            },
+                            _ if type_head => BraceKind::Body,
+                            _ => BraceKind::Init,
+                        },
+                    };
+                    kinds[i] = kind;
+                    stack.push(kind);
+                    type_head = false;
+                    namespace_head = false;
+                },
+                SyntaxKind::RBrace => {
+                    kinds[i] = stack.pop().unwrap_or(BraceKind::Block);
+                    type_head = false;
+                    namespace_head = false;
+                },
+                SyntaxKind::Semicolon | SyntaxKind::Equal => {
+                    type_head = false;
+                    namespace_head = false;
+                },
+                SyntaxKind::KwStruct | SyntaxKind::KwClass | SyntaxKind::KwEnum | SyntaxKind::KwUnion => {
+                    type_head = true
+                },
+                SyntaxKind::KwNamespace => namespace_head = true,
+                _ => {},
+            }
+        }
+        prev_prev = prev;
+        prev = Some(i);
+    }
+    kinds
+}
+
+/// Move body braces and `else` according to `style`.
+fn place_braces(
+    toks: &mut [Tok<'_>],
+    braces: &[BraceKind],
+    style: BraceStyle,
+) {
+    let mut open: Vec<usize> = Vec::new();
+    let mut single_line = vec![false; toks.len()];
+    for (i, tok) in toks.iter().enumerate() {
+        match tok.kind {
+            SyntaxKind::LBrace => open.push(i),
+            SyntaxKind::RBrace => {
+                if let Some(start) = open.pop() {
+                    single_line[start] = toks[start + 1..=i].iter().all(|t| t.newlines == 0);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    for i in 1..toks.len() {
+        let prev = &toks[i - 1];
+        let can_join = !is_trivia(prev) && !prev.directive;
+        let after_body_close = prev.kind == SyntaxKind::RBrace && braces[i - 1] == BraceKind::Body;
+        let is_body_open =
+            toks[i].kind == SyntaxKind::LBrace && matches!(braces[i], BraceKind::Body | BraceKind::Namespace);
+        let is_else = toks[i].kind == SyntaxKind::KwElse && after_body_close;
+        if !is_body_open && !is_else {
+            continue;
+        }
+        match style {
+            BraceStyle::Attach if can_join => toks[i].newlines = 0,
+            // Short bodies such as `{ return x; }` stay on one line.
+            BraceStyle::Allman if !single_line[i] || is_else => toks[i].newlines = toks[i].newlines.max(1),
+            BraceStyle::Allman => {},
+            BraceStyle::Attach => {},
+        }
+    }
+}
+
+/// Marks `*`, `&` and `&&` tokens that belong to a declarator rather than an expression.
+fn find_declarators(toks: &[Tok<'_>]) -> Vec<bool> {
+    let mut declarators = vec![false; toks.len()];
+    let mut parens: Vec<usize> = Vec::new();
+    for i in 0..toks.len() {
+        match toks[i].kind {
+            SyntaxKind::LParen => parens.push(i),
+            SyntaxKind::RParen => {
+                parens.pop();
+            },
+            SyntaxKind::Star | SyntaxKind::Amp | SyntaxKind::AndAnd if !toks[i].directive => {
+                declarators[i] = is_declarator(toks, &declarators, i, parens.last().copied());
+            },
+            _ => {},
+        }
+    }
+    declarators
+}
+
+fn is_declarator(
+    toks: &[Tok<'_>],
+    declarators: &[bool],
+    index: usize,
+    enclosing_paren: Option<usize>,
+) -> bool {
+    let Some(next) = next_significant(toks, index) else {
+        return false;
+    };
+    if !matches!(
+        toks[next].kind,
+        SyntaxKind::Ident
+            | SyntaxKind::KwConst
+            | SyntaxKind::KwVolatile
+            | SyntaxKind::Star
+            | SyntaxKind::Amp
+            | SyntaxKind::AndAnd
+            | SyntaxKind::RParen
+            | SyntaxKind::Comma
+            | SyntaxKind::Greater
+            | SyntaxKind::Ellipsis
+    ) {
+        return false;
+    }
+    let next_is_name = toks[next].kind == SyntaxKind::Ident;
+
+    // Walk back over the type: identifiers, type keywords, `::` and template arguments.
+    let mut type_tokens = 0;
+    let mut last_type_token = None;
+    let mut j = index;
+    let delimiter = loop {
+        let Some(p) = previous_significant(toks, j) else {
+            break None;
+        };
+        let tok = &toks[p];
+        if tok.directive {
+            break Some(p);
+        }
+        match tok.kind {
+            SyntaxKind::Star | SyntaxKind::Amp | SyntaxKind::AndAnd if p + 1 == index || j == index => {
+                return declarators[p];
+            },
+            SyntaxKind::Ident => {
+                type_tokens += 1;
+                last_type_token.get_or_insert(p);
+            },
+            kind if is_type_keyword(kind) => {
+                type_tokens += 1;
+                last_type_token.get_or_insert(p);
+            },
+            SyntaxKind::DoubleColon => {},
+            SyntaxKind::Greater | SyntaxKind::RightShift => match skip_template_back(toks, p) {
+                Some(open) => {
+                    j = open;
+                    continue;
+                },
+                None => return false,
+            },
+            SyntaxKind::LParen
+            | SyntaxKind::Comma
+            | SyntaxKind::Semicolon
+            | SyntaxKind::LBrace
+            | SyntaxKind::RBrace
+            | SyntaxKind::Less
+            | SyntaxKind::Colon
+            | SyntaxKind::RDoubleBracket => break Some(p),
+            _ => return false,
+        }
+        j = p;
+    };
+
+    if type_tokens == 0 {
+        return false;
+    }
+    if type_tokens >= 2 || last_type_token.is_some_and(|t| is_type_keyword(toks[t].kind)) {
+        return true;
+    }
+    // A lone identifier: `Foo *p;` declares, but `f(a * b)` multiplies.
+    match delimiter.map(|d| (toks[d].kind, toks[d].directive)) {
+        None | Some((_, true)) => next_is_name,
+        Some((SyntaxKind::Semicolon | SyntaxKind::LBrace | SyntaxKind::RBrace | SyntaxKind::Colon, _)) => next_is_name,
+        Some((SyntaxKind::RDoubleBracket, _)) => next_is_name,
+        Some((SyntaxKind::LParen | SyntaxKind::Comma, _)) => {
+            !next_is_name || enclosing_paren.is_some_and(|paren| is_declaration_paren(toks, paren))
+        },
+        Some(_) => !next_is_name,
+    }
+}
+
+/// Index of the `<` matching the `>` (or `>>`) at `close`.
+fn skip_template_back(
+    toks: &[Tok<'_>],
+    close: usize,
+) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut j = close + 1;
+    while let Some(p) = previous_significant(toks, j) {
+        match toks[p].kind {
+            SyntaxKind::Greater => depth += 1,
+            SyntaxKind::RightShift => depth += 2,
+            SyntaxKind::Less => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return Some(p);
+                }
+            },
+            SyntaxKind::Semicolon | SyntaxKind::LBrace | SyntaxKind::RBrace => return None,
+            _ => {},
+        }
+        j = p;
+    }
+    None
+}
+
+/// Whether the `(` at `paren` opens a function parameter list, e.g. `float4 f(`.
+fn is_declaration_paren(
+    toks: &[Tok<'_>],
+    paren: usize,
+) -> bool {
+    let Some(name) = previous_significant(toks, paren) else {
+        return false;
+    };
+    if toks[name].kind != SyntaxKind::Ident {
+        return false;
+    }
+    previous_significant(toks, name).is_some_and(|p| {
+        let kind = toks[p].kind;
+        !toks[p].directive
+            && (kind == SyntaxKind::Ident
+                || kind == SyntaxKind::Greater
+                || is_type_keyword(kind)
+                || matches!(kind, SyntaxKind::Star | SyntaxKind::Amp))
+    })
+}
+
+fn is_type_keyword(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::KwAuto
+            | SyntaxKind::KwBool
+            | SyntaxKind::KwChar
+            | SyntaxKind::KwChar8
+            | SyntaxKind::KwChar16
+            | SyntaxKind::KwChar32
+            | SyntaxKind::KwClass
+            | SyntaxKind::KwConst
+            | SyntaxKind::KwConstexpr
+            | SyntaxKind::KwDouble
+            | SyntaxKind::KwEnum
+            | SyntaxKind::KwExtern
+            | SyntaxKind::KwFloat
+            | SyntaxKind::KwInline
+            | SyntaxKind::KwInt
+            | SyntaxKind::KwLong
+            | SyntaxKind::KwMutable
+            | SyntaxKind::KwShort
+            | SyntaxKind::KwSigned
+            | SyntaxKind::KwStatic
+            | SyntaxKind::KwStruct
+            | SyntaxKind::KwTypename
+            | SyntaxKind::KwUnion
+            | SyntaxKind::KwUnsigned
+            | SyntaxKind::KwVoid
+            | SyntaxKind::KwVolatile
+            | SyntaxKind::KwWchar
+            | SyntaxKind::KwDevice
+            | SyntaxKind::KwThreadgroup
+            | SyntaxKind::KwConstant
+            | SyntaxKind::KwThread
+            | SyntaxKind::KwRayData
+            | SyntaxKind::KwSampler
+            | SyntaxKind::KwTexture
+            | SyntaxKind::KwHalf
+            | SyntaxKind::KwBFloat
+            | SyntaxKind::KwBFloat16
+    )
+}
+
+fn previous_significant(
+    toks: &[Tok<'_>],
+    index: usize,
+) -> Option<usize> {
+    (0..index).rev().find(|&i| !is_trivia(&toks[i]))
+}
+
+fn next_significant(
+    toks: &[Tok<'_>],
+    index: usize,
+) -> Option<usize> {
+    (index + 1..toks.len()).find(|&i| !is_trivia(&toks[i]))
+}
+
+/// One rendered output line.
+struct OutLine {
+    text: String,
+    /// Brace depth the line was rendered at.
+    depth: usize,
+    /// Byte offset and column of a trailing `[[attribute]]` that may be aligned.
+    attribute: Option<(usize, usize)>,
+}
+
+struct Renderer<'a, 't> {
+    style: &'a InternalStyle,
+    toks: &'a [Tok<'t>],
+    braces: &'a [BraceKind],
+    declarators: &'a [bool],
+    lines: Vec<OutLine>,
+    line: String,
+    column: usize,
+    line_depth: usize,
+    /// `None` until a candidate is seen, `Some(None)` when the line has several.
+    attribute: Option<Option<(usize, usize)>>,
+    depth: usize,
+    /// Continuation columns of the open parentheses and brackets.
+    parens: Vec<usize>,
+    statement_level: usize,
+    line_level: usize,
+    line_first: SyntaxKind,
+    line_control: bool,
+    last_significant: Option<usize>,
+    last_line_first: SyntaxKind,
+    last_line_control: bool,
+}
+
+impl<'a, 't> Renderer<'a, 't> {
+    fn new(
+        style: &'a InternalStyle,
+        toks: &'a [Tok<'t>],
+        braces: &'a [BraceKind],
+        declarators: &'a [bool],
+    ) -> Self {
+        Self {
+            style,
+            toks,
+            braces,
+            declarators,
+            lines: Vec::new(),
+            line: String::new(),
+            column: 0,
+            line_depth: 0,
+            attribute: None,
+            depth: 0,
+            parens: Vec::new(),
+            statement_level: 0,
+            line_level: 0,
+            line_first: SyntaxKind::Error,
+            line_control: false,
+            last_significant: None,
+            last_line_first: SyntaxKind::Error,
+            last_line_control: false,
+        }
+    }
+
+    fn token(
+        &mut self,
+        i: usize,
+    ) {
+        let tok = &self.toks[i];
+        if i == 0 || tok.newlines > 0 {
+            if i > 0 {
+                self.end_line();
+                let blank_lines = (tok.newlines - 1).min(self.style.max_empty_lines);
+                for _ in 0..blank_lines {
+                    self.lines.push(OutLine {
+                        text: String::new(),
+                        depth: 0,
+                        attribute: None,
+                    });
+                }
+            }
+            self.start_line(i);
+        } else if self.space_between(i - 1, i) {
+            self.line.push(' ');
+            self.column += 1;
+        }
+
+        if tok.kind == SyntaxKind::LDoubleBracket
+            && !self.line.trim().is_empty()
+            && self.last_significant.is_some_and(|p| self.toks[p].kind == SyntaxKind::Ident)
+        {
+            self.attribute = match self.attribute {
+                None => Some(Some((self.line.len(), self.column))),
+                Some(_) => Some(None),
+            };
+        }
+        self.write(tok);
+        self.update(i);
+    }
+
+    fn start_line(
+        &mut self,
+        i: usize,
+    ) {
+        let tok = &self.toks[i];
+        self.line_first = tok.kind;
+        self.line_control = false;
+        self.line_depth = self.depth;
+        if tok.directive {
+            self.column = 0;
+            return;
+        }
+        if is_trivia(tok) && tok.text.starts_with("//") && tok.column == 0 && self.depth == 0 {
+            self.column = 0;
+            return;
+        }
+
+        let column = if let Some(&column) = self.parens.last() {
+            column
+        } else {
+            let level = self.line_level_for(i);
+            self.line_level = level;
+            level * self.style.indent_width
+        };
+        self.indent_to(column);
+    }
+
+    fn line_level_for(
+        &mut self,
+        i: usize,
+    ) -> usize {
+        let kind = self.toks[i].kind;
+        let terminated = self.last_significant.is_none_or(|p| self.is_terminator(p));
+        if kind == SyntaxKind::RBrace {
+            let level = if self.braces[i] == BraceKind::Namespace {
+                self.depth
+            } else {
+                self.depth.saturating_sub(1)
+            };
+            self.statement_level = level;
+            return level;
+        }
+        if self.is_label(i) {
+            self.statement_level = self.depth;
+            return self.depth.saturating_sub(1);
+        }
+        if terminated {
+            self.statement_level = self.depth;
+            return self.depth;
+        }
+        if kind == SyntaxKind::LBrace {
+            return self.statement_level;
+        }
+        if self.continues_control_statement() {
+            return self.line_level + 1;
+        }
+        self.statement_level + 1
+    }
+
+    fn is_terminator(
+        &self,
+        index: usize,
+    ) -> bool {
+        let tok = &self.toks[index];
+        if tok.directive {
+            return true;
+        }
+        match tok.kind {
+            SyntaxKind::Semicolon
+            | SyntaxKind::LBrace
+            | SyntaxKind::RBrace
+            | SyntaxKind::Colon
+            | SyntaxKind::RDoubleBracket => true,
+            SyntaxKind::Comma => self.parens.is_empty(),
+            SyntaxKind::Greater => self.last_line_first == SyntaxKind::KwTemplate,
+            _ => false,
+        }
+    }
+
+    /// `case x:`, `default:` and access specifiers sit one level left of their block.
+    fn is_label(
+        &self,
+        i: usize,
+    ) -> bool {
+        let next_is_colon = next_significant(self.toks, i).is_some_and(|n| self.toks[n].kind == SyntaxKind::Colon);
+        match self.toks[i].kind {
+            SyntaxKind::KwCase => true,
+            SyntaxKind::KwDefault | SyntaxKind::KwPublic | SyntaxKind::KwPrivate | SyntaxKind::KwProtected => {
+                next_is_colon
+            },
+            _ => false,
+        }
+    }
+
+    /// The previous line was `if (…)`, `else`, `for (…)` or similar without a brace.
+    fn continues_control_statement(&self) -> bool {
+        let Some(p) = self.last_significant else {
+            return false;
+        };
+        match self.toks[p].kind {
+            SyntaxKind::KwElse | SyntaxKind::KwDo => true,
+            SyntaxKind::RParen => self.last_line_control,
+            _ => false,
+        }
+    }
+
+    fn indent_to(
+        &mut self,
+        column: usize,
+    ) {
+        let indent = self.indent_string(column);
+        self.line.push_str(&indent);
+        self.column = column;
+    }
+
+    fn indent_string(
+        &self,
+        column: usize,
+    ) -> String {
+        if self.style.use_tab {
+            let tabs = column / self.style.tab_width;
+            "\t".repeat(tabs) + &" ".repeat(column - tabs * self.style.tab_width)
+        } else {
+            " ".repeat(column)
+        }
+    }
+
+    fn space_between(
+        &self,
+        prev: usize,
+        current: usize,
+    ) -> bool {
+        let (p, c) = (&self.toks[prev], &self.toks[current]);
+        if matches!(c.kind, SyntaxKind::Comma | SyntaxKind::Semicolon) && !is_trivia(p) {
+            return false;
+        }
+        if p.kind == SyntaxKind::Comma || is_trivia(c) {
+            return true;
+        }
+        if p.kind == SyntaxKind::Semicolon {
+            return !matches!(c.kind, SyntaxKind::RParen);
+        }
+        if (c.kind == SyntaxKind::LBrace && matches!(self.braces[current], BraceKind::Body | BraceKind::Namespace))
+            || (p.kind == SyntaxKind::RBrace && c.kind == SyntaxKind::KwElse)
+        {
+            return true;
+        }
+        if matches!(p.kind, SyntaxKind::KwIf | SyntaxKind::KwFor | SyntaxKind::KwWhile | SyntaxKind::KwSwitch)
+            && c.kind == SyntaxKind::LParen
+        {
+            return true;
+        }
+        if self.declarators[current] {
+            if self.declarators[prev] {
+                return false;
+            }
+            return self.alignment_of(c) != PointerAlignment::Left;
+        }
+        if self.declarators[prev] {
+            if matches!(c.kind, SyntaxKind::RParen | SyntaxKind::Comma | SyntaxKind::Greater | SyntaxKind::Ellipsis) {
+                return false;
+            }
+            return self.alignment_of(p) != PointerAlignment::Right;
+        }
+        c.space_before
+    }
+
+    fn alignment_of(
+        &self,
+        tok: &Tok<'_>,
+    ) -> PointerAlignment {
+        if tok.kind == SyntaxKind::Star {
+            self.style.pointer_alignment
+        } else {
+            self.style.reference_alignment
+        }
+    }
+
+    fn write(
+        &mut self,
+        tok: &Tok<'_>,
+    ) {
+        if is_trivia(tok) && tok.text.contains('\n') {
+            // Shift the continuation lines of a block comment along with its first line.
+            let shift = self.column as isize - tok.column as isize;
+            for (n, part) in tok.text.split('\n').enumerate() {
+                if n > 0 {
+                    let content = part.trim_start_matches([' ', '\t']);
+                    let width = advance_column(0, &part[..part.len() - content.len()], self.style.tab_width);
+                    let indent = self.indent_string((width as isize + shift).max(0) as usize);
+                    self.line.push('\n');
+                    self.line.push_str(&indent);
+                    self.line.push_str(content);
+                } else {
+                    self.line.push_str(part);
+                }
+            }
+            self.column = advance_column(self.column, &self.line, self.style.tab_width);
+            return;
+        }
+        self.line.push_str(tok.text);
+        self.column = advance_column(self.column, tok.text, self.style.tab_width);
+    }
+
+    fn update(
+        &mut self,
+        i: usize,
+    ) {
+        let tok = &self.toks[i];
+        if is_trivia(tok) {
+            return;
+        }
+        if tok.directive {
+            self.last_significant = Some(i);
+            return;
+        }
+        match tok.kind {
+            SyntaxKind::LBrace | SyntaxKind::RBrace if self.braces[i] == BraceKind::Namespace => {},
+            SyntaxKind::LBrace => self.depth += 1,
+            SyntaxKind::RBrace => self.depth = self.depth.saturating_sub(1),
+            SyntaxKind::LParen | SyntaxKind::LBracket => {
+                let breaks_after = self.toks.get(i + 1).is_some_and(|next| next.newlines > 0);
+                let column = if breaks_after {
+                    (self.statement_level + 1) * self.style.indent_width
+                } else {
+                    self.column
+                };
+                self.parens.push(column);
+            },
+            SyntaxKind::RParen | SyntaxKind::RBracket => {
+                self.parens.pop();
+            },
+            SyntaxKind::KwIf
+            | SyntaxKind::KwElse
+            | SyntaxKind::KwFor
+            | SyntaxKind::KwWhile
+            | SyntaxKind::KwSwitch
+            | SyntaxKind::KwDo => self.line_control = true,
+            _ => {},
+        }
+        self.last_significant = Some(i);
+        self.last_line_first = self.line_first;
+        self.last_line_control = self.line_control;
+    }
+
+    fn end_line(&mut self) {
+        let text = std::mem::take(&mut self.line);
+        self.lines.push(OutLine {
+            text,
+            depth: self.line_depth,
+            attribute: self.attribute.take().flatten(),
+        });
+    }
+
+    fn finish(mut self) -> Vec<OutLine> {
+        self.end_line();
+        self.lines
+    }
+}
+
+/// Pad consecutive lines at the same depth so their trailing attributes line up.
+fn align_attributes(
+    lines: &mut [OutLine],
+    style: &InternalStyle,
+) {
+    let mut start = 0;
+    while start < lines.len() {
+        let mut end = start;
+        while end < lines.len() && lines[end].attribute.is_some() && lines[end].depth == lines[start].depth {
+            end += 1;
+        }
+        if end - start >= 2 {
+            let column_of = |line: &OutLine| {
+                let (offset, _) = line.attribute.expect("grouped lines have attributes");
+                advance_column(0, &line.text[..offset], style.tab_width)
+            };
+            let target = lines[start..end].iter().map(column_of).max().unwrap_or(0);
+            for line in &mut lines[start..end] {
+                let padding = target - column_of(line);
+                let (offset, _) = line.attribute.expect("grouped lines have attributes");
+                line.text.insert_str(offset, &" ".repeat(padding));
+            }
+        }
+        start = end.max(start + 1);
+    }
+}
+
 #[cfg(test)]
 #[path = "../../tests/src/server/metalfmt_tests.rs"]
 mod tests;
@@ -1,10 +1,27 @@
+pub(crate) mod cancellation;
+pub mod check;
+pub(crate) mod custom_methods;
 pub(crate) mod diagnostics;
+pub mod ext;
+pub(crate) mod file_watch;
 pub mod formatting;
 pub(crate) mod handler;
 pub(crate) mod header_owners;
+pub(crate) mod include_cycles;
+pub(crate) mod include_graphs;
+pub mod lint;
+pub(crate) mod macro_conflicts;
 pub mod metalfmt;
+pub mod prebuild;
+pub mod preprocess;
+pub(crate) mod registrations;
+pub mod sarif;
+pub(crate) mod scheduler;
 pub mod settings;
 pub(crate) mod state;
+pub mod state_dump;
+pub(crate) mod status;
+pub(crate) mod unused_includes;
 
 pub use settings::ServerSettings;
 pub use state::MetalLanguageServer;
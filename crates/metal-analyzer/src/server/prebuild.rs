@@ -0,0 +1,128 @@
+//! Offline AST cache prebuild behind `metal-analyzer index`.
+//!
+//! Walks the workspace the same way background indexing does and runs the AST
+//! dump for every `.metal` file, so the on-disk cache in
+//! `definition::index_cache` is warm before the editor first asks for a
+//! definition. Include paths are computed exactly like the server computes
+//! them, otherwise the cache entries would not match.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use tracing::warn;
+
+use crate::{
+    definition::DefinitionProvider,
+    metal::compiler::MetalCompiler,
+    server::{
+        diagnostics::{compute_include_paths_for, discover_workspace_files},
+        settings::ServerSettings,
+        state::compiler_settings_flags,
+    },
+    vfs::glob::PathExclusions,
+};
+
+/// Outcome of [`prebuild_ast_cache`].
+#[derive(Debug, Default)]
+pub struct PrebuildSummary {
+    /// Files whose AST index was loaded from or written to the cache.
+    pub indexed: usize,
+    /// Files whose AST dump failed, e.g. because of a missing toolchain.
+    pub failed: Vec<PathBuf>,
+}
+
+/// `.metal` files under `workspace_roots` that [`prebuild_ast_cache`] would index.
+pub fn prebuild_targets(
+    workspace_roots: &[PathBuf],
+    settings: &ServerSettings,
+) -> Vec<PathBuf> {
+    let exclusions = PathExclusions::new(workspace_roots, &settings.indexing.exclude_paths);
+    discover_workspace_files(
+        workspace_roots,
+        settings.indexing.max_file_size_bytes(),
+        &exclusions,
+        settings.indexing.respect_gitignore,
+        |path| path.extension().is_some_and(|ext| ext == "metal"),
+    )
+}
+
+/// Index `files` into `provider` with up to `indexing.concurrency` AST dumps
+/// in flight, calling `on_file` with each file and whether it succeeded as it
+/// finishes. The provider's [`ProjectIndex`](crate::definition::ProjectIndex)
+/// holds every indexed file afterwards.
+///
+/// With `indexing.persistIndex`, unchanged files are restored from the
+/// persisted project index instead of dumped, and the index is saved again
+/// at the end so the editor's next session starts warm.
+pub async fn prebuild_ast_cache(
+    provider: &Arc<DefinitionProvider>,
+    workspace_roots: &[PathBuf],
+    files: &[PathBuf],
+    settings: &ServerSettings,
+    mut on_file: impl FnMut(&Path, bool),
+) -> PrebuildSummary {
+    let compiler = Arc::new(MetalCompiler::new());
+    compiler.ensure_system_includes_ready().await;
+    provider.configure_compiler_flags(compiler_settings_flags(&settings.compiler));
+    provider.configure_compiler_overrides(settings.compiler.overrides.clone());
+
+    let restored: HashSet<PathBuf> = if settings.indexing.persist_index {
+        provider
+            .restore_project_snapshot(workspace_roots, files, |path| {
+                compute_include_paths_for(&path.to_path_buf(), workspace_roots, &compiler)
+            })
+            .into_iter()
+            .collect()
+    } else {
+        HashSet::new()
+    };
+
+    let roots: Arc<[PathBuf]> = workspace_roots.into();
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(settings.indexing.concurrency));
+    let mut handles = Vec::with_capacity(files.len());
+    let mut summary = PrebuildSummary::default();
+
+    for path in files.iter().cloned() {
+        if restored.contains(&path) {
+            on_file(&path, true);
+            summary.indexed += 1;
+            continue;
+        }
+        let sem = semaphore.clone();
+        let provider = provider.clone();
+        let compiler = compiler.clone();
+        let roots = roots.clone();
+        handles.push(tokio::spawn(async move {
+            let _permit = sem.acquire().await;
+            let include_paths = compute_include_paths_for(&path, &roots, &compiler);
+            let ok = provider.index_workspace_file(&path, &include_paths);
+            (path, ok)
+        }));
+    }
+
+    for handle in handles {
+        let Ok((path, ok)) = handle.await else {
+            continue;
+        };
+        on_file(&path, ok);
+        if ok {
+            summary.indexed += 1;
+        } else {
+            summary.failed.push(path);
+        }
+    }
+
+    if settings.indexing.persist_index
+        && let Err(error) = provider.save_project_snapshot(workspace_roots)
+    {
+        warn!("Failed to persist project index: {error}");
+    }
+    summary
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/server/prebuild_tests.rs"]
+mod tests;
@@ -0,0 +1,63 @@
+//! Post-preprocessor source behind `metal-analyzer preprocess`.
+//!
+//! Runs `xcrun metal -E` with the include paths and flags the server would
+//! compile the file with, optionally cut down to the declarations one entry
+//! point depends on.
+
+use std::{
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+use tower_lsp::lsp_types::Url;
+
+use crate::{
+    metal::{compiler::MetalCompiler, preprocess::PreprocessedSource},
+    server::diagnostics::compute_include_paths_for,
+};
+
+#[derive(Debug)]
+pub enum PreprocessError {
+    Io(std::io::Error),
+    /// The preprocessor failed; holds its error output.
+    Compiler(String),
+    EntryPointNotFound(String),
+}
+
+impl Display for PreprocessError {
+    fn fmt(
+        &self,
+        f: &mut Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Compiler(output) => write!(f, "preprocessing failed:\n{}", output.trim_end()),
+            Self::EntryPointNotFound(name) => write!(f, "no function named `{name}` is defined outside system headers"),
+        }
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+/// Preprocess `path` as the server would compile it, keeping only the
+/// declarations `entry_point` depends on when one is given.
+pub async fn preprocess_file(
+    compiler: &MetalCompiler,
+    workspace_roots: &[PathBuf],
+    path: &Path,
+    entry_point: Option<&str>,
+) -> Result<String, PreprocessError> {
+    let source = tokio::fs::read_to_string(path).await.map_err(PreprocessError::Io)?;
+    let uri = Url::from_file_path(path).map_err(|()| {
+        PreprocessError::Io(std::io::Error::new(std::io::ErrorKind::InvalidInput, "not an absolute path"))
+    })?;
+    let include_paths = compute_include_paths_for(&path.to_path_buf(), workspace_roots, compiler);
+    let output = compiler.preprocess(&source, uri.as_str(), &include_paths).await.map_err(PreprocessError::Compiler)?;
+
+    let Some(entry_point) = entry_point else {
+        return Ok(output);
+    };
+    PreprocessedSource::parse(&output)
+        .slice_for_entry_point(entry_point, |file| compiler.is_system_path(file))
+        .ok_or_else(|| PreprocessError::EntryPointNotFound(entry_point.to_string()))
+}
@@ -0,0 +1,184 @@
+//! Capabilities that follow the settings at runtime.
+//!
+//! Formatting and semantic tokens can be turned off while the server runs.
+//! Clients that support dynamic registration for them are not offered them
+//! in the `initialize` result; they are registered once initialized while
+//! enabled, and unregistered or registered again as the settings change, so
+//! the client stops asking instead of receiving empty answers. Other clients
+//! get the static capabilities, and the handlers check the settings.
+
+use std::{collections::BTreeSet, sync::Mutex};
+
+use tower_lsp::lsp_types::{
+    ClientCapabilities, Registration, SemanticTokensFullOptions, SemanticTokensOptions,
+    SemanticTokensRegistrationOptions, StaticRegistrationOptions, TextDocumentRegistrationOptions, Unregistration,
+};
+use tracing::warn;
+
+use crate::{
+    semantic_tokens::get_legend,
+    server::{settings::ServerSettings, state::MetalLanguageServer},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum DynamicCapability {
+    Formatting,
+    SemanticTokens,
+}
+
+impl DynamicCapability {
+    pub(crate) const ALL: [Self; 2] = [Self::Formatting, Self::SemanticTokens];
+
+    fn id(self) -> &'static str {
+        match self {
+            Self::Formatting => "metal-analyzer-formatting",
+            Self::SemanticTokens => "metal-analyzer-semantic-tokens",
+        }
+    }
+
+    fn method(self) -> &'static str {
+        match self {
+            Self::Formatting => "textDocument/formatting",
+            Self::SemanticTokens => "textDocument/semanticTokens",
+        }
+    }
+
+    pub(crate) fn is_enabled(
+        self,
+        settings: &ServerSettings,
+    ) -> bool {
+        match self {
+            Self::Formatting => settings.formatting.enable,
+            Self::SemanticTokens => settings.semantic_tokens.enable,
+        }
+    }
+
+    /// Whether the client accepts a dynamic registration for this capability.
+    pub(crate) fn client_registers(
+        self,
+        capabilities: &ClientCapabilities,
+    ) -> bool {
+        let text_document = capabilities.text_document.as_ref();
+        let dynamic_registration = match self {
+            Self::Formatting => text_document
+                .and_then(|text_document| text_document.formatting.as_ref())
+                .and_then(|formatting| formatting.dynamic_registration),
+            Self::SemanticTokens => text_document
+                .and_then(|text_document| text_document.semantic_tokens.as_ref())
+                .and_then(|semantic_tokens| semantic_tokens.dynamic_registration),
+        };
+        dynamic_registration.unwrap_or(false)
+    }
+
+    /// The registration for the client's own document selector.
+    pub(crate) fn registration(self) -> Registration {
+        let text_document_registration_options = TextDocumentRegistrationOptions {
+            document_selector: None,
+        };
+        let register_options = match self {
+            Self::Formatting => serde_json::to_value(text_document_registration_options),
+            Self::SemanticTokens => serde_json::to_value(SemanticTokensRegistrationOptions {
+                text_document_registration_options,
+                semantic_tokens_options: SemanticTokensOptions {
+                    legend: get_legend(),
+                    full: Some(SemanticTokensFullOptions::Bool(true)),
+                    range: Some(false),
+                    work_done_progress_options: Default::default(),
+                },
+                static_registration_options: StaticRegistrationOptions {
+                    id: None,
+                },
+            }),
+        };
+        Registration {
+            id: self.id().to_string(),
+            method: self.method().to_string(),
+            register_options: register_options.ok(),
+        }
+    }
+
+    fn unregistration(self) -> Unregistration {
+        Unregistration {
+            id: self.id().to_string(),
+            method: self.method().to_string(),
+        }
+    }
+}
+
+/// Which capabilities the client registers dynamically and which of them
+/// are registered now.
+#[derive(Debug, Default)]
+pub(crate) struct DynamicRegistrations {
+    supported: Mutex<BTreeSet<DynamicCapability>>,
+    registered: tokio::sync::Mutex<BTreeSet<DynamicCapability>>,
+}
+
+impl DynamicRegistrations {
+    /// Record what the client supports, during `initialize`.
+    pub(crate) fn set_supported(
+        &self,
+        capabilities: &ClientCapabilities,
+    ) {
+        if let Ok(mut supported) = self.supported.lock() {
+            *supported = DynamicCapability::ALL
+                .into_iter()
+                .filter(|capability| capability.client_registers(capabilities))
+                .collect();
+        }
+    }
+
+    /// Whether `capability` is registered dynamically rather than offered in
+    /// the `initialize` result.
+    pub(crate) fn is_dynamic(
+        &self,
+        capability: DynamicCapability,
+    ) -> bool {
+        self.supported.lock().is_ok_and(|supported| supported.contains(&capability))
+    }
+}
+
+/// The dynamic capabilities to register and to unregister so that exactly
+/// the enabled ones are registered.
+pub(crate) fn registration_changes(
+    supported: &BTreeSet<DynamicCapability>,
+    registered: &BTreeSet<DynamicCapability>,
+    settings: &ServerSettings,
+) -> (Vec<DynamicCapability>, Vec<DynamicCapability>) {
+    let wanted: BTreeSet<DynamicCapability> =
+        supported.iter().copied().filter(|capability| capability.is_enabled(settings)).collect();
+    (wanted.difference(registered).copied().collect(), registered.difference(&wanted).copied().collect())
+}
+
+impl MetalLanguageServer {
+    /// Register the enabled dynamic capabilities and unregister the disabled
+    /// ones.
+    pub(crate) async fn sync_dynamic_registrations(&self) {
+        let settings = self.settings_snapshot().await;
+        let supported =
+            self.dynamic_registrations.supported.lock().map(|supported| supported.clone()).unwrap_or_default();
+        let mut registered = self.dynamic_registrations.registered.lock().await;
+        let (register, unregister) = registration_changes(&supported, &registered, &settings);
+        if !register.is_empty() {
+            let registrations = register.iter().map(|capability| capability.registration()).collect();
+            match self.client.register_capability(registrations).await {
+                Ok(()) => registered.extend(register),
+                Err(error) => warn!("Failed to register capabilities: {error}"),
+            }
+        }
+        if !unregister.is_empty() {
+            let unregistrations = unregister.iter().map(|capability| capability.unregistration()).collect();
+            match self.client.unregister_capability(unregistrations).await {
+                Ok(()) => {
+                    for capability in unregister {
+                        registered.remove(&capability);
+                    }
+                },
+                Err(error) => warn!("Failed to unregister capabilities: {error}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/server/registrations_tests.rs"]
+mod tests;
@@ -0,0 +1,223 @@
+//! SARIF 2.1.0 logs of diagnostics, for code scanning tools such as GitHub's.
+//!
+//! Used by `metal-analyzer check --format sarif`, `metal-analyzer lint
+//! --format sarif`, and the `metal-analyzer/sarif` request. Files under a
+//! workspace root are located relative to it through `originalUriBaseIds`,
+//! so uploads made from another checkout still line up. Each result carries
+//! a `primaryLocationLineHash` fingerprint built from its rule and the text
+//! of its line, which survives edits that only move the line.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{Map, Value, json};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Range, Url};
+
+use crate::metal::{
+    compiler::MetalDiagnostic,
+    diagnostic_codes::{DIAGNOSTIC_CODES, SPEC_URL},
+};
+
+/// Rule of compiler diagnostics that match no known code.
+pub const COMPILER_RULE: &str = "compiler";
+
+pub const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SarifRule {
+    pub id: String,
+    pub description: String,
+    pub help_uri: Option<String>,
+}
+
+/// One diagnostic in one file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SarifResult {
+    pub rule_id: String,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    pub path: PathBuf,
+    pub range: Range,
+}
+
+impl SarifResult {
+    /// A result for `diagnostic` in `path`, under the rule its code names.
+    pub fn from_diagnostic(
+        path: &Path,
+        diagnostic: &Diagnostic,
+    ) -> Self {
+        let rule_id = match &diagnostic.code {
+            Some(NumberOrString::String(code)) => code.clone(),
+            Some(NumberOrString::Number(code)) => code.to_string(),
+            None => COMPILER_RULE.to_string(),
+        };
+        Self {
+            rule_id,
+            severity: diagnostic.severity.unwrap_or(DiagnosticSeverity::WARNING),
+            message: diagnostic.message.clone(),
+            path: path.to_path_buf(),
+            range: diagnostic.range,
+        }
+    }
+
+    /// A result for a compiler diagnostic of `path`, located in the file the
+    /// compiler reported when there is one.
+    pub fn from_metal_diagnostic(
+        path: &Path,
+        diagnostic: &MetalDiagnostic,
+    ) -> Self {
+        let file = diagnostic.file.as_deref().map_or_else(|| path.to_path_buf(), PathBuf::from);
+        Self::from_diagnostic(&file, &diagnostic.clone().into_lsp_diagnostic())
+    }
+}
+
+/// The compiler rules: one per diagnostic code, and [`COMPILER_RULE`].
+pub fn compiler_rules() -> Vec<SarifRule> {
+    let mut rules = vec![SarifRule {
+        id: COMPILER_RULE.to_string(),
+        description: "A Metal compiler error or warning".to_string(),
+        help_uri: None,
+    }];
+    rules.extend(DIAGNOSTIC_CODES.iter().map(|code| SarifRule {
+        id: code.code.to_string(),
+        description: format!("A Metal compiler diagnostic about `{}`", code.code),
+        help_uri: Some(SPEC_URL.to_string()),
+    }));
+    rules
+}
+
+/// A SARIF log with one run of metal-analyzer over `results`. Paths under
+/// one of `roots` are written relative to it.
+pub fn sarif_log(
+    rules: &[SarifRule],
+    results: &[SarifResult],
+    roots: &[PathBuf],
+) -> Value {
+    let mut sources: HashMap<PathBuf, Option<String>> = HashMap::new();
+    let mut occurrences: HashMap<(PathBuf, u64), usize> = HashMap::new();
+    let results: Vec<Value> = results
+        .iter()
+        .map(|result| {
+            let source =
+                sources.entry(result.path.clone()).or_insert_with(|| std::fs::read_to_string(&result.path).ok());
+            let line = source.as_deref().and_then(|source| source.lines().nth(result.range.start.line as usize));
+            let hash = line_hash(&result.rule_id, line.map_or(result.message.as_str(), str::trim));
+            let occurrence = occurrences.entry((result.path.clone(), hash)).or_default();
+            *occurrence += 1;
+
+            let mut value = json!({
+                "ruleId": result.rule_id,
+                "level": level(result.severity),
+                "message": { "text": result.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": artifact_location(&result.path, roots),
+                        "region": {
+                            "startLine": result.range.start.line + 1,
+                            "startColumn": result.range.start.character + 1,
+                            "endLine": result.range.end.line + 1,
+                            "endColumn": result.range.end.character + 1,
+                        },
+                    },
+                }],
+                "partialFingerprints": {
+                    "primaryLocationLineHash": format!("{hash:016x}:{occurrence}"),
+                },
+            });
+            if let Some(index) = rules.iter().position(|rule| rule.id == result.rule_id) {
+                value["ruleIndex"] = json!(index);
+            }
+            value
+        })
+        .collect();
+
+    let rules: Vec<Value> = rules
+        .iter()
+        .map(|rule| {
+            let mut value = json!({
+                "id": rule.id,
+                "shortDescription": { "text": rule.description },
+            });
+            if let Some(help_uri) = &rule.help_uri {
+                value["helpUri"] = json!(help_uri);
+            }
+            value
+        })
+        .collect();
+    let base_ids: Map<String, Value> = roots
+        .iter()
+        .enumerate()
+        .filter_map(|(index, root)| {
+            let uri = Url::from_directory_path(root).ok()?;
+            Some((root_base_id(index), json!({ "uri": uri.as_str() })))
+        })
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "metal-analyzer",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/computer-graphics-tools/metal-analyzer",
+                    "rules": rules,
+                },
+            },
+            "originalUriBaseIds": base_ids,
+            "results": results,
+        }],
+    })
+}
+
+fn level(severity: DiagnosticSeverity) -> &'static str {
+    match severity {
+        DiagnosticSeverity::ERROR => "error",
+        DiagnosticSeverity::WARNING => "warning",
+        _ => "note",
+    }
+}
+
+fn root_base_id(index: usize) -> String {
+    if index == 0 {
+        "SRCROOT".to_string()
+    } else {
+        format!("SRCROOT{index}")
+    }
+}
+
+/// `path` relative to the first root containing it, or as a `file://` URI.
+fn artifact_location(
+    path: &Path,
+    roots: &[PathBuf],
+) -> Value {
+    for (index, root) in roots.iter().enumerate() {
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let uri: Vec<String> = relative.components().map(|part| part.as_os_str().to_string_lossy().into()).collect();
+        return json!({ "uri": uri.join("/"), "uriBaseId": root_base_id(index) });
+    }
+    let uri = Url::from_file_path(path).map_or_else(|()| path.display().to_string(), String::from);
+    json!({ "uri": uri })
+}
+
+/// FNV-1a over the rule and line text, stable across runs and platforms.
+fn line_hash(
+    rule_id: &str,
+    line: &str,
+) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in rule_id.bytes().chain([0]).chain(line.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/server/sarif_tests.rs"]
+mod tests;
@@ -0,0 +1,237 @@
+//! Scheduling of diagnostics compiles of open documents.
+//!
+//! Each document has a queue holding at most one waiting compile: a compile
+//! requested while an earlier one still waits replaces it, so a burst of
+//! edits costs one compile. A document compiles once at a time, and no more
+//! compiles run at once across documents than `threadPool.workerThreads`
+//! allows.
+//!
+//! A fixed debounce either holds back the results of a file that compiles in
+//! a few milliseconds or starts compiles of a large file faster than they
+//! finish, so the on-type debounce of a document follows its recent compile
+//! durations instead, within a quarter of and four times
+//! `diagnostics.debounceMs`.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use tokio::sync::Notify;
+use tower_lsp::lsp_types::Url;
+
+use crate::{
+    config::{MAX_DIAGNOSTIC_DEBOUNCE_MS, MIN_DIAGNOSTIC_DEBOUNCE_MS},
+    perf,
+    server::ext::DiagnosticsQueueStatus,
+};
+
+pub(crate) struct DiagnosticsScheduler {
+    queues: Mutex<Queues>,
+    /// Woken whenever a compile finishes or a queued one is replaced.
+    changed: Notify,
+    /// Running average of the compile durations of each document.
+    durations: DashMap<Url, Duration>,
+}
+
+struct Queues {
+    /// Most compiles running at once.
+    limit: usize,
+    running: usize,
+    documents: HashMap<Url, DocumentQueue>,
+    coalesced: u64,
+    completed: u64,
+}
+
+#[derive(Default)]
+struct DocumentQueue {
+    running: bool,
+    /// Generation of the compile waiting to run.
+    waiting: Option<u64>,
+}
+
+/// A scheduled compile, holding its slot until dropped. A compile that ran
+/// to the end is [`finish`](Self::finish)ed, so one dropped unused, e.g.
+/// because a newer edit superseded it, stays out of the statistics.
+pub(crate) struct CompileTicket {
+    scheduler: Arc<DiagnosticsScheduler>,
+    uri: Url,
+    started: Instant,
+}
+
+impl DiagnosticsScheduler {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            queues: Mutex::new(Queues {
+                limit: limit.max(1),
+                running: 0,
+                documents: HashMap::new(),
+                coalesced: 0,
+                completed: 0,
+            }),
+            changed: Notify::new(),
+            durations: DashMap::new(),
+        }
+    }
+
+    /// Set the most compiles running at once; running compiles beyond a
+    /// lowered limit finish first.
+    pub(crate) fn set_limit(
+        &self,
+        limit: usize,
+    ) {
+        if let Ok(mut queues) = self.queues.lock() {
+            queues.limit = limit.max(1);
+        }
+        self.changed.notify_waiters();
+    }
+
+    /// Queue the compile of `uri` for diagnostics `generation` and wait for
+    /// its turn. `None` when a compile queued later replaced it.
+    pub(crate) async fn schedule(
+        self: &Arc<Self>,
+        uri: &Url,
+        generation: u64,
+    ) -> Option<CompileTicket> {
+        {
+            let mut queues = self.queues.lock().ok()?;
+            let replaced = queues.documents.entry(uri.clone()).or_default().waiting.replace(generation);
+            if replaced.is_some() {
+                queues.coalesced += 1;
+            }
+        }
+        self.changed.notify_waiters();
+        let _waiting = WaitingGuard {
+            scheduler: self,
+            uri,
+            generation,
+        };
+
+        loop {
+            let notified = self.changed.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            {
+                let mut queues = self.queues.lock().ok()?;
+                let queues = &mut *queues;
+                let document = queues.documents.entry(uri.clone()).or_default();
+                if document.waiting != Some(generation) {
+                    return None;
+                }
+                if !document.running && queues.running < queues.limit {
+                    document.running = true;
+                    document.waiting = None;
+                    queues.running += 1;
+                    return Some(CompileTicket {
+                        scheduler: Arc::clone(self),
+                        uri: uri.clone(),
+                        started: Instant::now(),
+                    });
+                }
+            }
+            notified.await;
+        }
+    }
+
+    /// The on-type debounce of `uri`: its average compile duration, bounded
+    /// around `configured_ms`, or `configured_ms` before its first compile.
+    pub(crate) fn debounce(
+        &self,
+        uri: &Url,
+        configured_ms: u64,
+    ) -> Duration {
+        let Some(average) = self.durations.get(uri).map(|duration| *duration) else {
+            return Duration::from_millis(configured_ms);
+        };
+        let lower = (configured_ms / 4).max(MIN_DIAGNOSTIC_DEBOUNCE_MS);
+        let upper = configured_ms.saturating_mul(4).min(MAX_DIAGNOSTIC_DEBOUNCE_MS).max(lower);
+        let average_ms = u64::try_from(average.as_millis()).unwrap_or(u64::MAX);
+        Duration::from_millis(average_ms.clamp(lower, upper))
+    }
+
+    /// Fold a finished compile of `uri` into its average, weighing the
+    /// latest compile as much as all earlier ones together.
+    fn record(
+        &self,
+        uri: &Url,
+        elapsed: Duration,
+    ) {
+        self.durations.entry(uri.clone()).and_modify(|average| *average = (*average + elapsed) / 2).or_insert(elapsed);
+    }
+
+    /// Drop what is known about `uri` once it is closed.
+    pub(crate) fn forget(
+        &self,
+        uri: &Url,
+    ) {
+        self.durations.remove(uri);
+        if let Ok(mut queues) = self.queues.lock()
+            && queues.documents.get(uri).is_some_and(|document| !document.running && document.waiting.is_none())
+        {
+            queues.documents.remove(uri);
+        }
+    }
+
+    pub(crate) fn status(&self) -> DiagnosticsQueueStatus {
+        let Ok(queues) = self.queues.lock() else {
+            return DiagnosticsQueueStatus::default();
+        };
+        DiagnosticsQueueStatus {
+            running: queues.running,
+            queued: queues.documents.values().filter(|document| document.waiting.is_some()).count(),
+            limit: queues.limit,
+            coalesced: queues.coalesced,
+            completed: queues.completed,
+        }
+    }
+}
+
+/// Takes a compile out of its document's queue when the future waiting for
+/// it is dropped before the compile starts or is replaced.
+struct WaitingGuard<'a> {
+    scheduler: &'a DiagnosticsScheduler,
+    uri: &'a Url,
+    generation: u64,
+}
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut queues) = self.scheduler.queues.lock()
+            && let Some(document) = queues.documents.get_mut(self.uri)
+            && document.waiting == Some(self.generation)
+        {
+            document.waiting = None;
+        }
+    }
+}
+
+impl CompileTicket {
+    /// Release the slot of a compile that ran, folding its duration into the
+    /// document's debounce and the compile statistics.
+    pub(crate) fn finish(self) {
+        let elapsed = self.started.elapsed();
+        self.scheduler.record(&self.uri, elapsed);
+        perf::record("diagnostics.compile", elapsed);
+        if let Ok(mut queues) = self.scheduler.queues.lock() {
+            queues.completed += 1;
+        }
+    }
+}
+
+impl Drop for CompileTicket {
+    fn drop(&mut self) {
+        if let Ok(mut queues) = self.scheduler.queues.lock() {
+            queues.running -= 1;
+            if let Some(document) = queues.documents.get_mut(&self.uri) {
+                document.running = false;
+            }
+        }
+        self.scheduler.changed.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/server/scheduler_tests.rs"]
+mod tests;
@@ -1,22 +1,50 @@
 use std::{
-    collections::BTreeSet,
-    path::PathBuf,
-    sync::{Arc, atomic::AtomicU64},
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
 };
 
 use dashmap::DashMap;
+use serde_json::Value;
 use tokio::sync::RwLock;
 use tower_lsp::{
     Client,
-    lsp_types::{Diagnostic, Url, WorkspaceFolder},
+    lsp_types::{ConfigurationItem, Diagnostic, Url, WorkspaceFolder},
 };
+use tracing::warn;
 
 use crate::{
-    completion::CompletionProvider, definition::DefinitionProvider, document::DocumentStore, hover::HoverProvider,
-    metal::compiler::MetalCompiler, semantic_tokens::SemanticTokenProvider, server::settings::ServerSettings,
-    symbols::SymbolProvider, syntax::DocumentTrees,
+    completion::CompletionProvider,
+    definition::{DefinitionProvider, cache_view::CacheView, include_graph::IncludeGraph},
+    document::{ContentHash, DocumentStore},
+    hover::HoverProvider,
+    ide::macros::MacroIndex,
+    metal::{
+        compiler::{ArtifactRetention, MetalCompiler, RootCompilerSettings},
+        kernel_stats::KernelStats,
+    },
+    progress::ProgressCancellations,
+    semantic_tokens::SemanticTokenProvider,
+    server::{
+        include_graphs::IncludeGraphCache,
+        registrations::DynamicRegistrations,
+        scheduler::DiagnosticsScheduler,
+        settings::{
+            CompilerSettings, SETTINGS_SECTION_KEY, ServerSettings, merge_json_values, read_workspace_settings_file,
+        },
+        state_dump::StateDump,
+        status::StatusTracker,
+    },
+    symbols::SymbolProvider,
+    syntax::DocumentTrees,
 };
 
+/// Kernel statistics per document, with the hash of the text they were compiled from.
+pub(crate) type KernelStatsCache = DashMap<Url, (ContentHash, Arc<Vec<KernelStats>>)>;
+
 /// The metal-analyzer backend that implements the Language Server Protocol.
 pub struct MetalLanguageServer {
     /// The LSP client handle, used to send notifications (e.g. diagnostics) back.
@@ -46,11 +74,20 @@ pub struct MetalLanguageServer {
     /// Rowan-parsed syntax trees for all open documents.
     pub(crate) document_trees: Arc<DocumentTrees>,
 
+    /// The document the user last opened, edited or queried at a position.
+    ///
+    /// `workspace/symbol` carries no document, so this stands in for the one
+    /// the request came from when ranking nearby symbols first.
+    pub(crate) active_document: Mutex<Option<Url>>,
+
     /// Workspace root folders, populated during `initialize`.
     pub(crate) workspace_roots: RwLock<Vec<WorkspaceFolder>>,
 
     /// Per-document diagnostics cache so we can clear them on close.
-    pub(crate) diagnostics_cache: DashMap<Url, Vec<Diagnostic>>,
+    pub(crate) diagnostics_cache: Arc<DashMap<Url, Vec<Diagnostic>>>,
+
+    /// Kernel statistics of open documents.
+    pub(crate) kernel_stats_cache: Arc<KernelStatsCache>,
 
     /// Monotonic per-document generation for diagnostics runs.
     ///
@@ -58,6 +95,9 @@ pub struct MetalLanguageServer {
     /// can be dropped instead of overwriting newer editor state.
     pub(crate) diagnostics_generation: Arc<DashMap<Url, u64>>,
 
+    /// Queues and paces the diagnostics compiles of open documents.
+    pub(crate) diagnostics_scheduler: Arc<DiagnosticsScheduler>,
+
     /// Reverse include graph: header file -> owner `.metal` files that include it.
     pub(crate) header_owners: Arc<DashMap<PathBuf, BTreeSet<PathBuf>>>,
 
@@ -81,6 +121,15 @@ pub struct MetalLanguageServer {
     /// Value format: `(workspace_generation, include_paths)`.
     pub(crate) include_paths_cache: Arc<DashMap<PathBuf, (u64, Vec<String>)>>,
 
+    /// Include graphs of translation units, shared by the diagnostics that
+    /// walk them.
+    pub(crate) include_graphs: Arc<IncludeGraphCache>,
+
+    /// Macro indexes of open documents, with the include graph each was
+    /// built from. A graph replaced in `include_graphs` means the document
+    /// or a header it reads changed.
+    pub(crate) macro_indexes: DashMap<Url, (Arc<IncludeGraph>, Arc<MacroIndex>)>,
+
     /// Monotonic generation for workspace-root changes.
     ///
     /// Bumping this invalidates stale include-path cache entries.
@@ -88,6 +137,69 @@ pub struct MetalLanguageServer {
 
     /// Runtime server settings updated from LSP configuration.
     pub(crate) settings: Arc<RwLock<ServerSettings>>,
+
+    /// Every LSP settings payload received so far, deep-merged.
+    ///
+    /// Kept separately from `settings` so `.metal-analyzer.json` files can be
+    /// re-read and layered underneath it whenever settings are recomputed.
+    pub(crate) lsp_settings_payload: RwLock<Value>,
+
+    /// Settings the client returned for each workspace root through
+    /// `workspace/configuration`, layered over `lsp_settings_payload` for the
+    /// files of that root.
+    pub(crate) root_settings_payloads: RwLock<HashMap<PathBuf, Value>>,
+
+    /// Settings problems last shown to the user, so an unchanged list is not
+    /// shown again on every configuration change.
+    pub(crate) reported_settings_problems: Mutex<Vec<String>>,
+
+    /// Saved headers whose dependent owner files still need recompiling.
+    pub(crate) pending_dependent_headers: Arc<Mutex<BTreeSet<PathBuf>>>,
+
+    /// Debounce generation for dependent-diagnostics refreshes.
+    ///
+    /// Every header save bumps the counter; only the latest scheduled refresh
+    /// runs, picking up all headers saved during the debounce window.
+    pub(crate) dependent_refresh_generation: Arc<AtomicU64>,
+
+    /// Rendered `metal-analyzer-cache://` documents, keyed by view URI, so
+    /// hover and goto-definition can answer inside them.
+    pub(crate) ast_cache_views: DashMap<Url, CacheView>,
+
+    /// Whether the client can register `workspace/didChangeWatchedFiles`
+    /// watchers for us, recorded during `initialize`.
+    pub(crate) client_watches_files: AtomicBool,
+
+    /// Whether the client handles `metal-analyzer/inactiveRegions`
+    /// notifications, recorded during `initialize`.
+    pub(crate) client_shows_inactive_regions: AtomicBool,
+
+    /// Whether the client expands snippet completion items, recorded during
+    /// `initialize`.
+    pub(crate) client_supports_snippets: AtomicBool,
+
+    /// Whether the client accepts `documentChanges` with change annotations,
+    /// recorded during `initialize`.
+    pub(crate) client_annotates_edits: AtomicBool,
+
+    /// Whether the client answers `workspace/configuration` requests,
+    /// recorded during `initialize`.
+    pub(crate) client_provides_configuration: AtomicBool,
+
+    /// Capabilities registered dynamically so they follow the settings.
+    pub(crate) dynamic_registrations: DynamicRegistrations,
+
+    /// Fallback watcher on the workspace roots for clients that cannot watch
+    /// files themselves. Dropping it stops watching.
+    pub(crate) file_watcher: Mutex<Option<notify::RecommendedWatcher>>,
+
+    /// Cancellable progress sessions in flight, flagged by
+    /// `window/workDoneProgress/cancel`.
+    pub(crate) progress_cancellations: Arc<ProgressCancellations>,
+
+    /// Toolchain health, background jobs and the last error, reported by
+    /// `metal-analyzer/status`.
+    pub(crate) status: Arc<StatusTracker>,
 }
 
 impl MetalLanguageServer {
@@ -116,6 +228,7 @@ impl MetalLanguageServer {
         let goto_def_generation = Arc::new(AtomicU64::new(0));
         let workspace_generation = Arc::new(AtomicU64::new(0));
         let settings = Arc::new(RwLock::new(ServerSettings::default()));
+        let status = Arc::new(StatusTracker::new(client.clone()));
 
         Self {
             client,
@@ -127,16 +240,60 @@ impl MetalLanguageServer {
             semantic_token_provider,
             symbol_provider,
             document_trees,
+            active_document: Mutex::new(None),
             workspace_roots: RwLock::new(Vec::new()),
-            diagnostics_cache: DashMap::new(),
+            diagnostics_cache: Arc::new(DashMap::new()),
+            kernel_stats_cache: Arc::new(DashMap::new()),
             diagnostics_generation,
+            diagnostics_scheduler: Arc::new(DiagnosticsScheduler::new(
+                ServerSettings::default().thread_pool.resolved_worker_threads(),
+            )),
             header_owners,
             owner_headers,
             goto_def_generation,
             ast_index_generation,
             include_paths_cache,
+            include_graphs: Arc::new(IncludeGraphCache::new()),
+            macro_indexes: DashMap::new(),
             workspace_generation,
             settings,
+            lsp_settings_payload: RwLock::new(Value::Null),
+            root_settings_payloads: RwLock::new(HashMap::new()),
+            reported_settings_problems: Mutex::new(Vec::new()),
+            pending_dependent_headers: Arc::new(Mutex::new(BTreeSet::new())),
+            dependent_refresh_generation: Arc::new(AtomicU64::new(0)),
+            ast_cache_views: DashMap::new(),
+            client_watches_files: AtomicBool::new(false),
+            client_shows_inactive_regions: AtomicBool::new(false),
+            client_supports_snippets: AtomicBool::new(false),
+            client_annotates_edits: AtomicBool::new(false),
+            client_provides_configuration: AtomicBool::new(false),
+            dynamic_registrations: DynamicRegistrations::default(),
+            file_watcher: Mutex::new(None),
+            progress_cancellations: Arc::new(ProgressCancellations::new()),
+            status,
+        }
+    }
+
+    /// The state written to bug-report snapshots, for the panic hook.
+    pub fn state_dump(&self) -> StateDump {
+        StateDump {
+            document_store: Arc::clone(&self.document_store),
+            settings: Arc::clone(&self.settings),
+            status: Arc::clone(&self.status),
+            definition_provider: Arc::clone(&self.definition_provider),
+            compiler: Arc::clone(&self.compiler),
+            diagnostics_scheduler: Arc::clone(&self.diagnostics_scheduler),
+        }
+    }
+
+    /// Record `uri` as the document the user is working in.
+    pub(crate) fn note_active_document(
+        &self,
+        uri: &Url,
+    ) {
+        if let Ok(mut active) = self.active_document.lock() {
+            *active = Some(uri.clone());
         }
     }
 
@@ -144,15 +301,145 @@ impl MetalLanguageServer {
         self.settings.read().await.clone()
     }
 
+    /// Merge an LSP settings payload into the ones received before.
+    pub(crate) async fn record_lsp_settings_payload(
+        &self,
+        payload: &Value,
+    ) {
+        merge_json_values(&mut *self.lsp_settings_payload.write().await, payload);
+    }
+
+    /// Compute settings from the workspace `.metal-analyzer.json` files and
+    /// the recorded LSP payload, which takes precedence.
+    pub(crate) async fn resolve_settings(&self) -> ServerSettings {
+        let workspace_files: Vec<Value> = self
+            .workspace_roots
+            .read()
+            .await
+            .iter()
+            .filter_map(|folder| folder.uri.to_file_path().ok())
+            .filter_map(|root| read_workspace_settings_file(&root))
+            .collect();
+        let payload = self.lsp_settings_payload.read().await;
+        ServerSettings::layered(&workspace_files, Some(&payload))
+    }
+
+    /// Ask the client for the settings of each workspace root, scoped to the
+    /// root's URI, if it answers `workspace/configuration`.
+    pub(crate) async fn fetch_root_settings_payloads(&self) {
+        if !self.client_provides_configuration.load(Ordering::Relaxed) {
+            return;
+        }
+        let folders = self.workspace_roots.read().await.clone();
+        let items = folders
+            .iter()
+            .map(|folder| ConfigurationItem {
+                scope_uri: Some(folder.uri.clone()),
+                section: Some(SETTINGS_SECTION_KEY.to_string()),
+            })
+            .collect();
+        let values = match self.client.configuration(items).await {
+            Ok(values) => values,
+            Err(error) => {
+                warn!("Failed to fetch workspace folder settings: {error}");
+                return;
+            },
+        };
+        let payloads = folders
+            .iter()
+            .zip(values)
+            .filter_map(|(folder, value)| Some((folder.uri.to_file_path().ok()?, value)))
+            .collect();
+        *self.root_settings_payloads.write().await = payloads;
+    }
+
+    /// Compute the settings of each workspace root from its own
+    /// `.metal-analyzer.json`, the recorded LSP payload and the settings the
+    /// client returned for the root, later ones taking precedence.
+    pub(crate) async fn resolve_root_settings(&self) -> Vec<(PathBuf, ServerSettings)> {
+        let roots: Vec<PathBuf> =
+            self.workspace_roots.read().await.iter().filter_map(|folder| folder.uri.to_file_path().ok()).collect();
+        let payload = self.lsp_settings_payload.read().await;
+        let root_payloads = self.root_settings_payloads.read().await;
+        roots
+            .into_iter()
+            .map(|root| {
+                let workspace_files: Vec<Value> = read_workspace_settings_file(&root).into_iter().collect();
+                let mut settings = ServerSettings::layered(&workspace_files, Some(&payload));
+                if let Some(root_payload) = root_payloads.get(&root) {
+                    settings = settings.merged_with_payload(root_payload);
+                }
+                (root, settings)
+            })
+            .collect()
+    }
+
+    /// Push the `compiler.*` settings of each workspace root into the
+    /// compiler, returning whether they changed.
+    pub(crate) fn apply_root_settings(
+        &self,
+        roots: &[(PathBuf, ServerSettings)],
+    ) -> bool {
+        let root_settings: Vec<RootCompilerSettings> =
+            roots.iter().map(|(root, settings)| root_compiler_settings(root, &settings.compiler)).collect();
+        if root_settings == self.compiler.root_settings() {
+            return false;
+        }
+        self.compiler.set_root_settings(root_settings);
+        true
+    }
+
     pub(crate) async fn apply_settings(
         &self,
         settings: ServerSettings,
     ) {
-        let include_paths = settings.compiler.include_paths.iter().map(PathBuf::from).collect::<Vec<_>>();
-        self.compiler.set_include_paths(include_paths);
-        self.compiler.set_flags(settings.compiler.extra_flags.clone());
-        self.compiler.set_platform(settings.compiler.platform);
-
+        configure_compiler(&self.compiler, &settings.compiler);
+        self.definition_provider.configure_ranking(settings.navigation.ranking.clone());
+        self.definition_provider.configure_ast_dump_timeout(settings.compiler.ast_dump_timeout());
+        self.definition_provider.configure_compiler_flags(compiler_settings_flags(&settings.compiler));
+        self.definition_provider.configure_compiler_overrides(settings.compiler.overrides.clone());
+        self.definition_provider.configure_memory_budget(settings.indexing.max_memory_mb);
+        self.diagnostics_scheduler.set_limit(settings.thread_pool.resolved_worker_threads());
         *self.settings.write().await = settings;
     }
 }
+
+/// The compiler settings `compiler` of the workspace root `root`, with
+/// relative include paths resolved against the root.
+pub(crate) fn root_compiler_settings(
+    root: &Path,
+    compiler: &CompilerSettings,
+) -> RootCompilerSettings {
+    RootCompilerSettings {
+        root: root.to_path_buf(),
+        include_paths: compiler.include_paths.iter().map(|path| root.join(path)).collect(),
+        flags: compiler.extra_flags.clone(),
+        platform: compiler.platform,
+        language_version: compiler.language_version,
+    }
+}
+
+/// The flags `compiler` adds to every compile and AST dump.
+pub(crate) fn compiler_settings_flags(compiler: &CompilerSettings) -> Vec<String> {
+    MetalCompiler::settings_flags(compiler.extra_flags.clone(), compiler.platform, compiler.language_version)
+}
+
+/// Push the `compiler.*` settings into `compiler`.
+pub(crate) fn configure_compiler(
+    compiler: &MetalCompiler,
+    settings: &CompilerSettings,
+) {
+    let include_paths = settings.include_paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+    compiler.set_include_paths(include_paths);
+    compiler.set_virtual_include_roots(settings.virtual_include_roots.clone());
+    compiler.set_flags(settings.extra_flags.clone());
+    compiler.set_platform(settings.platform);
+    compiler.set_language_version(settings.language_version);
+    compiler.set_overrides(settings.overrides.clone());
+    compiler.set_temp_dir(settings.temp_dir.as_ref().map(PathBuf::from));
+    compiler.set_artifact_retention(ArtifactRetention {
+        keep: settings.keep_artifacts,
+        max_bytes: settings.artifacts_max_size_bytes(),
+    });
+    compiler.set_precompiled_headers(settings.precompiled_headers);
+}
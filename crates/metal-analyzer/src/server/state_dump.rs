@@ -0,0 +1,342 @@
+//! State snapshots for bug reports, written on panic and by
+//! `metal-analyzer/captureState`.
+//!
+//! A snapshot is a zip in the log directory holding `state.json` (open
+//! documents, cache and queue stats, the perf report and the toolchain),
+//! `settings.txt` (the resolved settings) and `log.txt` (the last lines of
+//! the log file). Document text is never included, and the home directory
+//! is replaced by `~` in every entry.
+//!
+//! The panic hook may run while the panicking task holds locks the snapshot
+//! needs, so the state is gathered on a thread of its own. When that thread
+//! does not answer in time, the snapshot keeps only what needs no lock.
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock, mpsc},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{Compression, Crc, write::DeflateEncoder};
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::{
+    definition::DefinitionProvider,
+    document::DocumentStore,
+    metal::compiler::MetalCompiler,
+    perf,
+    server::{
+        ServerSettings,
+        ext::{DiagnosticsQueueStatus, PerfReport},
+        scheduler::DiagnosticsScheduler,
+        status::StatusTracker,
+    },
+};
+
+/// Lines of the log file kept in a snapshot.
+const LOG_TAIL_LINES: usize = 200;
+/// Bytes read from the end of the log file for its last lines.
+const LOG_TAIL_BYTES: u64 = 256 * 1024;
+/// How long the state is waited for before a snapshot is written without it.
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The log file of the running server, set once logging is set up.
+static LOG_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Record where the server logs, for the log tail and the snapshot folder.
+pub fn set_log_file(path: PathBuf) {
+    let _ = LOG_FILE.set(path);
+}
+
+/// The server state a snapshot is taken from.
+#[derive(Clone)]
+pub struct StateDump {
+    pub(crate) document_store: Arc<DocumentStore>,
+    pub(crate) settings: Arc<RwLock<ServerSettings>>,
+    pub(crate) status: Arc<StatusTracker>,
+    pub(crate) definition_provider: Arc<DefinitionProvider>,
+    pub(crate) compiler: Arc<MetalCompiler>,
+    pub(crate) diagnostics_scheduler: Arc<DiagnosticsScheduler>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StateSnapshot {
+    pub(crate) reason: String,
+    pub(crate) server_version: &'static str,
+    pub(crate) os: &'static str,
+    pub(crate) arch: &'static str,
+    pub(crate) captured_at_unix_secs: u64,
+    /// Whether the state below was gathered; `false` when it was locked for
+    /// longer than the capture waits.
+    pub(crate) complete: bool,
+    pub(crate) toolchain: ToolchainSnapshot,
+    pub(crate) open_documents: Vec<OpenDocument>,
+    pub(crate) pending_jobs: usize,
+    pub(crate) last_error: Option<String>,
+    pub(crate) indexed_files: usize,
+    pub(crate) cache_memory_bytes: usize,
+    pub(crate) cache_disk_entries: usize,
+    pub(crate) cache_disk_bytes: u64,
+    pub(crate) diagnostics_queue: DiagnosticsQueueStatus,
+    /// `None` in a partial snapshot.
+    pub(crate) perf: Option<PerfReport>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ToolchainSnapshot {
+    pub(crate) available: Option<bool>,
+    /// The `metal` binary in use.
+    pub(crate) compiler_path: Option<String>,
+    /// `xcrun metal --version`, when it was asked for.
+    pub(crate) version: Option<String>,
+    pub(crate) system_include_paths: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OpenDocument {
+    pub(crate) uri: String,
+    pub(crate) version: i32,
+    pub(crate) lines: usize,
+}
+
+impl StateDump {
+    /// Write a snapshot to a zip next to the log file and return its path.
+    /// `toolchain_version` is the output of `xcrun metal --version`, which
+    /// the panic hook does not run.
+    pub fn capture(
+        &self,
+        reason: &str,
+        toolchain_version: Option<String>,
+    ) -> std::io::Result<PathBuf> {
+        let snapshot = self.snapshot_with_timeout(reason, toolchain_version);
+        let settings = match self.settings.try_read() {
+            Ok(settings) => format!("{:#?}\n", *settings),
+            Err(_) => "Settings were locked while capturing.\n".to_string(),
+        };
+        let log_tail = LOG_FILE.get().and_then(|path| log_tail(path, LOG_TAIL_LINES).ok()).unwrap_or_default();
+
+        let home = std::env::var("HOME").ok();
+        let state = serde_json::to_string_pretty(&snapshot).map_err(std::io::Error::other)?;
+        let entries = [
+            ("state.json", sanitize(&state, home.as_deref())),
+            ("settings.txt", sanitize(&settings, home.as_deref())),
+            ("log.txt", sanitize(&log_tail, home.as_deref())),
+        ];
+        let archive = zip_archive(entries.iter().map(|(name, text)| (*name, text.as_bytes())))?;
+
+        let directory =
+            LOG_FILE.get().and_then(|path| path.parent()).map(Path::to_path_buf).unwrap_or_else(std::env::temp_dir);
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join(format!("metal-analyzer-state-{}.zip", snapshot.captured_at_unix_secs));
+        std::fs::write(&path, archive)?;
+        Ok(path)
+    }
+
+    /// [`snapshot`](Self::snapshot) on a thread of its own, or the fields
+    /// that need no lock when it takes longer than [`SNAPSHOT_TIMEOUT`].
+    fn snapshot_with_timeout(
+        &self,
+        reason: &str,
+        toolchain_version: Option<String>,
+    ) -> StateSnapshot {
+        let (sender, receiver) = mpsc::channel();
+        let state = self.clone();
+        let thread_reason = reason.to_string();
+        let thread_version = toolchain_version.clone();
+        let spawned = std::thread::Builder::new().name("state-snapshot".into()).spawn(move || {
+            let _ = sender.send(state.snapshot(&thread_reason, thread_version));
+        });
+        match spawned.ok().and_then(|_| receiver.recv_timeout(SNAPSHOT_TIMEOUT).ok()) {
+            Some(snapshot) => snapshot,
+            None => partial_snapshot(reason, toolchain_version),
+        }
+    }
+
+    fn snapshot(
+        &self,
+        reason: &str,
+        toolchain_version: Option<String>,
+    ) -> StateSnapshot {
+        let mut open_documents: Vec<OpenDocument> = self
+            .document_store
+            .all_uris()
+            .into_iter()
+            .filter_map(|uri| self.document_store.get(&uri))
+            .map(|document| OpenDocument {
+                uri: document.uri.to_string(),
+                version: document.version,
+                lines: document.line_count(),
+            })
+            .collect();
+        open_documents.sort_by(|a, b| a.uri.cmp(&b.uri));
+        let (cache_disk_entries, cache_disk_bytes) = self.definition_provider.disk_cache_usage();
+        StateSnapshot {
+            reason: reason.to_string(),
+            server_version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            captured_at_unix_secs: unix_secs(),
+            complete: true,
+            toolchain: ToolchainSnapshot {
+                available: self.status.toolchain_available(),
+                compiler_path: self.compiler.cached_toolchain_signature(),
+                version: toolchain_version,
+                system_include_paths: self
+                    .compiler
+                    .get_system_include_paths()
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect(),
+            },
+            open_documents,
+            pending_jobs: self.status.pending_jobs(),
+            last_error: self.status.last_error(),
+            indexed_files: self.definition_provider.project_index().file_count(),
+            cache_memory_bytes: self.definition_provider.memory_usage(),
+            cache_disk_entries,
+            cache_disk_bytes,
+            diagnostics_queue: self.diagnostics_scheduler.status(),
+            perf: Some(perf::report(false)),
+        }
+    }
+}
+
+/// `text` with every occurrence of the home directory `home` replaced by `~`.
+pub(crate) fn sanitize(
+    text: &str,
+    home: Option<&str>,
+) -> String {
+    match home.map(|home| home.trim_end_matches('/')) {
+        Some(home) if !home.is_empty() => text.replace(home, "~"),
+        _ => text.to_string(),
+    }
+}
+
+/// The last `count` lines of `text`.
+pub(crate) fn last_lines(
+    text: &str,
+    count: usize,
+) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut tail = lines[lines.len().saturating_sub(count)..].join("\n");
+    if !tail.is_empty() {
+        tail.push('\n');
+    }
+    tail
+}
+
+/// The last `count` lines of the log at `path`, read from its last
+/// [`LOG_TAIL_BYTES`] so a large log is not read whole.
+pub(crate) fn log_tail(
+    path: &Path,
+    count: usize,
+) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let start = file.metadata()?.len().saturating_sub(LOG_TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let text = String::from_utf8_lossy(&bytes);
+    // A read from the middle of the file starts inside a line.
+    let text = match text.find('\n') {
+        Some(newline) if start > 0 => &text[newline + 1..],
+        _ => &text,
+    };
+    Ok(last_lines(text, count))
+}
+
+/// A zip archive of `entries`, each deflated.
+pub(crate) fn zip_archive<'a>(entries: impl IntoIterator<Item = (&'a str, &'a [u8])>) -> std::io::Result<Vec<u8>> {
+    let mut archive = Vec::new();
+    let mut central_directory = Vec::new();
+    let mut entry_count: u16 = 0;
+    for (name, contents) in entries {
+        let mut crc = Crc::new();
+        crc.update(contents);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(contents)?;
+        let compressed = encoder.finish()?;
+        let offset = archive.len() as u32;
+
+        // Version needed, flags, method (deflate), time, date, then sizes.
+        let fields = |header: &mut Vec<u8>| {
+            header.extend_from_slice(&20u16.to_le_bytes());
+            header.extend_from_slice(&0u16.to_le_bytes());
+            header.extend_from_slice(&8u16.to_le_bytes());
+            header.extend_from_slice(&0u16.to_le_bytes());
+            header.extend_from_slice(&0x21u16.to_le_bytes());
+            header.extend_from_slice(&crc.sum().to_le_bytes());
+            header.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+            header.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            header.extend_from_slice(&0u16.to_le_bytes());
+        };
+
+        archive.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        fields(&mut archive);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(&compressed);
+
+        central_directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central_directory.extend_from_slice(&20u16.to_le_bytes());
+        fields(&mut central_directory);
+        // Comment length, disk number, internal and external attributes.
+        central_directory.extend_from_slice(&[0; 10]);
+        central_directory.extend_from_slice(&offset.to_le_bytes());
+        central_directory.extend_from_slice(name.as_bytes());
+        entry_count += 1;
+    }
+
+    let directory_offset = archive.len() as u32;
+    archive.extend_from_slice(&central_directory);
+    archive.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    archive.extend_from_slice(&[0; 4]);
+    archive.extend_from_slice(&entry_count.to_le_bytes());
+    archive.extend_from_slice(&entry_count.to_le_bytes());
+    archive.extend_from_slice(&(central_directory.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&directory_offset.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes());
+    Ok(archive)
+}
+
+/// The snapshot written when the state could not be gathered in time.
+fn partial_snapshot(
+    reason: &str,
+    toolchain_version: Option<String>,
+) -> StateSnapshot {
+    StateSnapshot {
+        reason: reason.to_string(),
+        server_version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        captured_at_unix_secs: unix_secs(),
+        complete: false,
+        toolchain: ToolchainSnapshot {
+            version: toolchain_version,
+            ..ToolchainSnapshot::default()
+        },
+        open_documents: Vec::new(),
+        pending_jobs: 0,
+        last_error: None,
+        indexed_files: 0,
+        cache_memory_bytes: 0,
+        cache_disk_entries: 0,
+        cache_disk_bytes: 0,
+        diagnostics_queue: DiagnosticsQueueStatus::default(),
+        perf: None,
+    }
+}
+
+fn unix_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/server/state_dump_tests.rs"]
+mod tests;
@@ -0,0 +1,161 @@
+//! Server health behind `metal-analyzer/status` and the
+//! `metal-analyzer/statusChanged` notification.
+//!
+//! The tracker holds what the rest of the server state cannot answer: the
+//! outcome of the toolchain probe, the background jobs in flight and the
+//! last error. Clients that opted in are sent a summary whenever it
+//! changes, so a status-bar item can follow it without polling.
+
+use std::{
+    panic::AssertUnwindSafe,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
+
+use futures::FutureExt;
+use tower_lsp::Client;
+
+use crate::server::ext::{Health, StatusChanged, StatusChangedParams};
+
+/// Toolchain health, background jobs and the last error of the server.
+pub(crate) struct StatusTracker {
+    client: Client,
+    /// Whether the client handles `metal-analyzer/statusChanged`, recorded
+    /// during `initialize`.
+    notify: AtomicBool,
+    toolchain_available: Mutex<Option<bool>>,
+    pending_jobs: AtomicUsize,
+    last_error: Mutex<Option<String>>,
+    /// The summary last sent, held while sending so notifications go out
+    /// in order and unchanged summaries are not sent again.
+    last_sent: tokio::sync::Mutex<Option<StatusChangedParams>>,
+}
+
+/// A background job counted by the tracker until dropped.
+pub(crate) struct StatusJob {
+    tracker: Arc<StatusTracker>,
+}
+
+impl StatusTracker {
+    pub(crate) fn new(client: Client) -> Self {
+        Self {
+            client,
+            notify: AtomicBool::new(false),
+            toolchain_available: Mutex::new(None),
+            pending_jobs: AtomicUsize::new(0),
+            last_error: Mutex::new(None),
+            last_sent: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn set_notify(
+        &self,
+        notify: bool,
+    ) {
+        self.notify.store(notify, Ordering::Relaxed);
+    }
+
+    /// `None` until the toolchain has been probed.
+    pub(crate) fn toolchain_available(&self) -> Option<bool> {
+        self.toolchain_available.lock().map(|available| *available).unwrap_or(None)
+    }
+
+    pub(crate) fn pending_jobs(&self) -> usize {
+        self.pending_jobs.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn last_error(&self) -> Option<String> {
+        self.last_error.lock().map(|error| error.clone()).unwrap_or(None)
+    }
+
+    /// Record the outcome of the toolchain probe.
+    pub(crate) async fn set_toolchain_available(
+        &self,
+        available: bool,
+    ) {
+        if let Ok(mut guard) = self.toolchain_available.lock() {
+            *guard = Some(available);
+        }
+        self.publish().await;
+    }
+
+    /// Record a failure worth surfacing in the status, replacing the
+    /// previous one.
+    pub(crate) async fn record_error(
+        &self,
+        message: impl Into<String>,
+    ) {
+        if let Ok(mut guard) = self.last_error.lock() {
+            *guard = Some(message.into());
+        }
+        self.publish().await;
+    }
+
+    /// Count a background job until the returned guard is dropped.
+    pub(crate) async fn start_job(self: &Arc<Self>) -> StatusJob {
+        self.pending_jobs.fetch_add(1, Ordering::Relaxed);
+        self.publish().await;
+        StatusJob {
+            tracker: self.clone(),
+        }
+    }
+
+    pub(crate) fn summary(&self) -> StatusChangedParams {
+        summarize(self.toolchain_available(), self.last_error(), self.pending_jobs())
+    }
+
+    /// Send the summary to the client if it opted in and the summary
+    /// changed since the last one sent.
+    pub(crate) async fn publish(&self) {
+        if !self.notify.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut last_sent = self.last_sent.lock().await;
+        let summary = self.summary();
+        if last_sent.as_ref() == Some(&summary) {
+            return;
+        }
+        *last_sent = Some(summary.clone());
+        let _ = AssertUnwindSafe(self.client.send_notification::<StatusChanged>(summary)).catch_unwind().await;
+    }
+}
+
+impl Drop for StatusJob {
+    fn drop(&mut self) {
+        self.tracker.pending_jobs.fetch_sub(1, Ordering::Relaxed);
+        if !self.tracker.notify.load(Ordering::Relaxed) {
+            return;
+        }
+        let tracker = self.tracker.clone();
+        tokio::spawn(async move {
+            tracker.publish().await;
+        });
+    }
+}
+
+/// The health and message for a toolchain probe outcome and last error.
+/// An unavailable toolchain outweighs any error.
+pub(crate) fn summarize(
+    toolchain_available: Option<bool>,
+    last_error: Option<String>,
+    pending_jobs: usize,
+) -> StatusChangedParams {
+    let (health, message) = if toolchain_available == Some(false) {
+        (Health::Error, Some("Metal compiler toolchain or SDK is unavailable".to_string()))
+    } else if let Some(error) = last_error {
+        (Health::Warning, Some(error))
+    } else {
+        (Health::Ok, None)
+    };
+    StatusChangedParams {
+        health,
+        quiescent: pending_jobs == 0,
+        message,
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/server/status_tests.rs"]
+mod tests;
@@ -0,0 +1,75 @@
+//! Hints on `#include` lines whose project headers the document does not use.
+//!
+//! The include graph is walked from the document, and each header is matched
+//! against the declarations the document's AST index says it uses. Headers
+//! are not checked on their own, as only a translation unit has an index.
+
+use std::path::PathBuf;
+
+use dashmap::DashMap;
+use tower_lsp::lsp_types::{Diagnostic, Url};
+
+use crate::{
+    definition::AstIndex,
+    ide::unused_includes::{ReachedHeader, unused_includes},
+    metal::compiler::MetalCompiler,
+    server::{
+        diagnostics::compute_include_paths_for_uri_cached,
+        header_owners::{collect_included_headers, collect_translation_unit_headers, is_header_file, normalize_path},
+    },
+    syntax::SyntaxTree,
+};
+
+/// Headers read per translation unit.
+const MAX_INCLUDE_HEADERS: usize = 256;
+
+pub(super) async fn unused_include_diagnostics(
+    compiler: &MetalCompiler,
+    workspace_roots: &[PathBuf],
+    include_paths_cache: &DashMap<PathBuf, (u64, Vec<String>)>,
+    workspace_generation: u64,
+    index: &AstIndex,
+    uri: &Url,
+    text: &str,
+) -> Vec<Diagnostic> {
+    let Ok(path) = uri.to_file_path() else {
+        return Vec::new();
+    };
+    if is_header_file(&path) {
+        return Vec::new();
+    }
+    let main = normalize_path(&path);
+    let include_paths =
+        compute_include_paths_for_uri_cached(compiler, uri, workspace_roots, include_paths_cache, workspace_generation)
+            .await;
+
+    let include_lines: Vec<u32> = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            collect_included_headers(&main, line, &include_paths)
+                .iter()
+                .any(|header| !crate::definition::is_system_header(&header.to_string_lossy()))
+        })
+        .map(|(line, _)| line as u32)
+        .collect();
+    if include_lines.is_empty() {
+        return Vec::new();
+    }
+    let headers = collect_translation_unit_headers(&main, text, &include_paths, MAX_INCLUDE_HEADERS, None).await;
+    if headers.len() >= MAX_INCLUDE_HEADERS {
+        // Uses of headers past the limit would go unseen.
+        return Vec::new();
+    }
+    let reached: Vec<ReachedHeader<'_>> = headers
+        .iter()
+        .map(|header| ReachedHeader {
+            path: &header.path,
+            source: &header.source,
+            origin_line: header.origin_line,
+        })
+        .collect();
+
+    let tree = SyntaxTree::parse(text);
+    unused_includes(&tree.root(), text, &path, index, &include_lines, &reached)
+}
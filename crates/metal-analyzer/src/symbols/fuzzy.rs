@@ -0,0 +1,126 @@
+//! Subsequence fuzzy matching for symbol search.
+//!
+//! Scoring follows the usual editor heuristics: every pattern character must
+//! appear in order, and matches on word boundaries (`snake_case` segments,
+//! `camelCase` humps) or in consecutive runs are rewarded.
+
+const MATCH_SCORE: i64 = 1;
+const CONSECUTIVE_BONUS: i64 = 5;
+const BOUNDARY_BONUS: i64 = 8;
+const FIRST_CHAR_BONUS: i64 = 10;
+const PREFIX_BONUS: i64 = 50;
+const EXACT_BONUS: i64 = 100;
+const CASE_EXACT_BONUS: i64 = 20;
+
+/// Score `candidate` against `pattern` as a case-insensitive subsequence.
+///
+/// Returns `None` when the pattern does not match. An empty pattern matches
+/// everything with a score of zero.
+pub(crate) fn fuzzy_score(
+    pattern: &str,
+    candidate: &str,
+) -> Option<i64> {
+    if pattern.is_empty() {
+        return Some(0);
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let chars: Vec<char> = candidate.chars().collect();
+    if pattern.len() > chars.len() {
+        return None;
+    }
+
+    // best[j]: best score for the pattern prefix processed so far, with its
+    // last character matched at candidate index `j`.
+    let mut best: Vec<Option<i64>> = vec![None; chars.len()];
+    for (i, &p) in pattern.iter().enumerate() {
+        let mut next: Vec<Option<i64>> = vec![None; chars.len()];
+        let mut best_before: Option<i64> = None;
+        for j in 0..chars.len() {
+            if !chars_match(p, chars[j]) {
+                if i > 0 {
+                    best_before = max_option(best_before, best[j]);
+                }
+                continue;
+            }
+
+            let bonus = MATCH_SCORE + boundary_bonus(&chars, j);
+            let score = if i == 0 {
+                let first_char = if j == 0 {
+                    FIRST_CHAR_BONUS
+                } else {
+                    0
+                };
+                Some(bonus + first_char)
+            } else {
+                let consecutive = j.checked_sub(1).and_then(|k| best[k]).map(|s| s + bonus + CONSECUTIVE_BONUS);
+                let gapped = best_before.map(|s| s + bonus);
+                max_option(consecutive, gapped)
+            };
+            next[j] = score;
+            if i > 0 {
+                best_before = max_option(best_before, best[j]);
+            }
+        }
+        best = next;
+    }
+
+    let mut score = best.into_iter().flatten().max()?;
+
+    let pattern_text: String = pattern.iter().collect();
+    if candidate.eq_ignore_ascii_case(&pattern_text) {
+        score += EXACT_BONUS;
+        if candidate == pattern_text {
+            score += CASE_EXACT_BONUS;
+        }
+    } else if candidate.len() >= pattern_text.len()
+        && candidate.is_char_boundary(pattern_text.len())
+        && candidate[..pattern_text.len()].eq_ignore_ascii_case(&pattern_text)
+    {
+        score += PREFIX_BONUS;
+    }
+
+    // Prefer shorter candidates among otherwise equal matches.
+    score -= (chars.len() - pattern.len()) as i64 / 4;
+    Some(score)
+}
+
+fn chars_match(
+    pattern: char,
+    candidate: char,
+) -> bool {
+    pattern.eq_ignore_ascii_case(&candidate)
+}
+
+fn boundary_bonus(
+    chars: &[char],
+    index: usize,
+) -> i64 {
+    let Some(previous) = index.checked_sub(1).map(|i| chars[i]) else {
+        return BOUNDARY_BONUS;
+    };
+    let current = chars[index];
+    let after_separator = !previous.is_alphanumeric();
+    let camel_hump = previous.is_lowercase() && current.is_uppercase();
+    let digit_edge = previous.is_ascii_digit() != current.is_ascii_digit();
+    if after_separator || camel_hump || digit_edge {
+        BOUNDARY_BONUS
+    } else {
+        0
+    }
+}
+
+fn max_option(
+    a: Option<i64>,
+    b: Option<i64>,
+) -> Option<i64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/symbols/fuzzy_tests.rs"]
+mod tests;
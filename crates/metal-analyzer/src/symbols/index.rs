@@ -1,6 +1,11 @@
 use dashmap::DashMap;
+use tower_lsp::lsp_types::{SymbolKind, Url};
 
-use crate::symbols::types::SymbolLocation;
+use crate::symbols::{
+    fuzzy::fuzzy_score,
+    origin::{SymbolOrigin, SymbolSearchFilter},
+    types::SymbolLocation,
+};
 
 pub struct SymbolIndex {
     pub(crate) map: DashMap<String, Vec<SymbolLocation>>,
@@ -34,28 +39,143 @@ impl SymbolIndex {
         self.map.get(name).map(|v| v.clone()).unwrap_or_default()
     }
 
-    /// Search for symbols matching a query (case-insensitive substring match).
-    /// Returns up to `limit` results.
+    /// Search for symbols fuzzily matching a query and return up to `limit`
+    /// results, best first.
+    ///
+    /// The query may start with `#` to restrict results to types or `f ` to
+    /// restrict them to functions. Ties between equally good matches are broken
+    /// by symbol kind and then by proximity to `anchor` (usually the file the
+    /// user is working in).
     pub fn search(
         &self,
         query: &str,
+        anchor: Option<&Url>,
         limit: usize,
     ) -> Vec<(String, SymbolLocation)> {
-        let query_lower = query.to_lowercase();
+        self.search_in_scope(query, anchor, &SymbolSearchFilter::everything(), limit)
+            .into_iter()
+            .map(|(name, loc, _)| (name, loc))
+            .collect()
+    }
+
+    /// Like [`SymbolIndex::search`], restricted to the file origins `filter`
+    /// accepts. Each result carries the origin of the file it was found in.
+    pub fn search_in_scope(
+        &self,
+        query: &str,
+        anchor: Option<&Url>,
+        filter: &SymbolSearchFilter,
+        limit: usize,
+    ) -> Vec<(String, SymbolLocation, SymbolOrigin)> {
+        let query = SymbolQuery::parse(query);
         let mut results = Vec::new();
 
         for entry in self.map.iter() {
             let symbol_name = entry.key();
-            if symbol_name.to_lowercase().contains(&query_lower) {
-                for loc in entry.value() {
-                    results.push((symbol_name.clone(), loc.clone()));
-                    if results.len() >= limit {
-                        return results;
-                    }
+            let Some(score) = fuzzy_score(query.pattern, symbol_name) else {
+                continue;
+            };
+            for loc in entry.value() {
+                if !query.filter.accepts(loc.kind) {
+                    continue;
+                }
+                let origin = SymbolOrigin::classify(&loc.uri, &filter.workspace_roots);
+                if !filter.accepts(origin) {
+                    continue;
                 }
+                let proximity = anchor.map_or(0, |anchor| file_proximity(anchor, &loc.uri));
+                results.push((score, kind_rank(loc.kind), proximity, symbol_name.clone(), loc.clone(), origin));
             }
         }
 
-        results
+        results.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then(a.1.cmp(&b.1))
+                .then(b.2.cmp(&a.2))
+                .then_with(|| a.3.cmp(&b.3))
+                .then_with(|| a.4.uri.as_str().cmp(b.4.uri.as_str()))
+                .then_with(|| a.4.range.start.cmp(&b.4.range.start))
+        });
+        results.truncate(limit);
+        results.into_iter().map(|(_, _, _, name, loc, origin)| (name, loc, origin)).collect()
+    }
+}
+
+/// Symbol kinds a workspace symbol query is restricted to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SymbolKindFilter {
+    All,
+    Types,
+    Functions,
+}
+
+impl SymbolKindFilter {
+    fn accepts(
+        self,
+        kind: SymbolKind,
+    ) -> bool {
+        match self {
+            Self::All => true,
+            Self::Types => {
+                matches!(kind, SymbolKind::STRUCT | SymbolKind::CLASS | SymbolKind::ENUM | SymbolKind::TYPE_PARAMETER)
+            },
+            Self::Functions => kind == SymbolKind::FUNCTION,
+        }
+    }
+}
+
+/// A workspace symbol query split into its kind filter and fuzzy pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct SymbolQuery<'a> {
+    pub(crate) filter: SymbolKindFilter,
+    pub(crate) pattern: &'a str,
+}
+
+impl<'a> SymbolQuery<'a> {
+    pub(crate) fn parse(query: &'a str) -> Self {
+        let query = query.trim_start();
+        let (filter, pattern) = if let Some(rest) = query.strip_prefix('#') {
+            (SymbolKindFilter::Types, rest)
+        } else if let Some(rest) = query.strip_prefix("f ") {
+            (SymbolKindFilter::Functions, rest)
+        } else {
+            (SymbolKindFilter::All, query)
+        };
+        Self {
+            filter,
+            pattern: pattern.trim(),
+        }
     }
 }
+
+/// Lower ranks sort first: declarations users usually search for come before
+/// members and locals.
+fn kind_rank(kind: SymbolKind) -> u8 {
+    match kind {
+        SymbolKind::FUNCTION | SymbolKind::STRUCT | SymbolKind::CLASS | SymbolKind::ENUM => 0,
+        SymbolKind::TYPE_PARAMETER | SymbolKind::CONSTANT => 1,
+        SymbolKind::ENUM_MEMBER | SymbolKind::FIELD => 2,
+        _ => 3,
+    }
+}
+
+/// Higher is closer: the anchor file itself ranks above everything, then
+/// files sharing more leading path segments with the anchor.
+fn file_proximity(
+    anchor: &Url,
+    uri: &Url,
+) -> usize {
+    if anchor == uri {
+        return usize::MAX;
+    }
+    let (Some(anchor_segments), Some(segments)) = (anchor.path_segments(), uri.path_segments()) else {
+        return 0;
+    };
+    let anchor_dirs: Vec<&str> = anchor_segments.collect();
+    let anchor_dirs = &anchor_dirs[..anchor_dirs.len().saturating_sub(1)];
+    anchor_dirs.iter().zip(segments).take_while(|(a, b)| **a == *b).count()
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/symbols/index_tests.rs"]
+mod tests;
@@ -1,8 +1,12 @@
+pub(crate) mod fuzzy;
 pub(crate) mod index;
+pub(crate) mod origin;
 pub(crate) mod provider;
 pub(crate) mod scanner;
+pub(crate) mod store;
 pub(crate) mod types;
 
 pub use index::SymbolIndex;
+pub use origin::{SymbolOrigin, SymbolSearchFilter};
 pub use provider::SymbolProvider;
 pub use types::SymbolLocation;
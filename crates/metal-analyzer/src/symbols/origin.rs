@@ -0,0 +1,123 @@
+//! Classification of indexed files for scoped workspace symbol search.
+//!
+//! Origins are derived from the file path alone: anything outside the
+//! workspace folders counts as a system header, and generated or test
+//! sources are recognized by conventional directory and file names below
+//! the workspace root they belong to.
+
+use std::path::{Path, PathBuf};
+
+use tower_lsp::lsp_types::Url;
+
+use crate::definition::is_system_header;
+
+/// Directory names whose contents are treated as generated sources.
+const GENERATED_DIRS: &[&str] = &["generated", "gen", "build", ".build", "deriveddata"];
+/// File stem suffixes marking generated sources, e.g. `shaders_generated.h`.
+const GENERATED_SUFFIXES: &[&str] = &["_generated", ".generated", "_gen"];
+/// Directory names whose contents are treated as tests or test fixtures.
+const TEST_DIRS: &[&str] = &["test", "tests", "testdata", "fixtures", "__tests__"];
+/// File stem suffixes marking test sources, e.g. `blur_test.metal`.
+const TEST_SUFFIXES: &[&str] = &["_test", "_tests"];
+
+/// Where an indexed symbol comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolOrigin {
+    Workspace,
+    System,
+    Generated,
+    Test,
+}
+
+impl SymbolOrigin {
+    /// Classify `uri` relative to `workspace_roots`.
+    ///
+    /// Without workspace roots every non-system file counts as part of the
+    /// workspace, so single-file sessions still find their own symbols.
+    pub fn classify(
+        uri: &Url,
+        workspace_roots: &[PathBuf],
+    ) -> Self {
+        let Ok(path) = uri.to_file_path() else {
+            return Self::Workspace;
+        };
+        if is_system_header(&path.to_string_lossy()) {
+            return Self::System;
+        }
+        let relative = if workspace_roots.is_empty() {
+            path.as_path()
+        } else {
+            match workspace_roots.iter().find_map(|root| path.strip_prefix(root).ok()) {
+                Some(relative) => relative,
+                None => return Self::System,
+            }
+        };
+        if path_matches(relative, GENERATED_DIRS, GENERATED_SUFFIXES) {
+            Self::Generated
+        } else if path_matches(relative, TEST_DIRS, TEST_SUFFIXES) {
+            Self::Test
+        } else {
+            Self::Workspace
+        }
+    }
+
+    /// Short annotation shown next to results, `None` for workspace files.
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            Self::Workspace => None,
+            Self::System => Some("system"),
+            Self::Generated => Some("generated"),
+            Self::Test => Some("test"),
+        }
+    }
+}
+
+/// File categories a workspace symbol query searches.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolSearchFilter {
+    pub workspace_roots: Vec<PathBuf>,
+    pub include_system: bool,
+    pub include_generated: bool,
+    pub include_tests: bool,
+}
+
+impl SymbolSearchFilter {
+    /// A filter that accepts symbols of every origin.
+    pub fn everything() -> Self {
+        Self {
+            workspace_roots: Vec::new(),
+            include_system: true,
+            include_generated: true,
+            include_tests: true,
+        }
+    }
+
+    pub fn accepts(
+        &self,
+        origin: SymbolOrigin,
+    ) -> bool {
+        match origin {
+            SymbolOrigin::Workspace => true,
+            SymbolOrigin::System => self.include_system,
+            SymbolOrigin::Generated => self.include_generated,
+            SymbolOrigin::Test => self.include_tests,
+        }
+    }
+}
+
+fn path_matches(
+    relative: &Path,
+    dirs: &[&str],
+    suffixes: &[&str],
+) -> bool {
+    let in_dir = relative.parent().into_iter().flat_map(Path::components).any(|component| {
+        let name = component.as_os_str().to_string_lossy().to_ascii_lowercase();
+        dirs.contains(&name.as_str())
+    });
+    let stem = relative.file_stem().map(|stem| stem.to_string_lossy().to_ascii_lowercase()).unwrap_or_default();
+    in_dir || suffixes.iter().any(|suffix| stem.ends_with(suffix))
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/symbols/origin_tests.rs"]
+mod tests;
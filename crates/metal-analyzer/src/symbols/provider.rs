@@ -1,12 +1,20 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use tower_lsp::lsp_types::{DocumentSymbol, Location, Range, SymbolInformation, SymbolKind, Url};
+use dashmap::DashMap;
+use tower_lsp::lsp_types::{DocumentSymbol, Location, Range, SymbolInformation, Url};
 use tracing::debug;
 
 use crate::{
+    definition::project_store::FileStamp,
     symbols::{
         index::SymbolIndex,
-        scanner::{build_symbols, flatten_symbols},
+        origin::{SymbolOrigin, SymbolSearchFilter},
+        scanner::{build_symbols, file_level_symbols, flatten_symbols},
+        store::{self, SnapshotFile, StoredSymbol},
         types::SymbolLocation,
     },
     syntax::SyntaxTree,
@@ -15,6 +23,17 @@ use crate::{
 #[derive(Clone)]
 pub struct SymbolProvider {
     index: Arc<SymbolIndex>,
+    /// The names each indexed file contributed, so a file can be removed
+    /// without walking the whole index.
+    files: Arc<DashMap<Url, IndexedFile>>,
+}
+
+#[derive(Default)]
+struct IndexedFile {
+    names: Vec<String>,
+    /// Stamp of the disk content the symbols were scanned from; `None` for
+    /// an editor buffer, which is never persisted.
+    stamp: Option<FileStamp>,
 }
 
 impl Default for SymbolProvider {
@@ -27,6 +46,7 @@ impl SymbolProvider {
     pub fn new() -> Self {
         Self {
             index: Arc::new(SymbolIndex::new()),
+            files: Arc::new(DashMap::new()),
         }
     }
 
@@ -34,25 +54,92 @@ impl SymbolProvider {
         &self.index
     }
 
-    /// Index all symbols in a file for workspace-wide lookup.
+    /// Index all symbols in an editor buffer for workspace-wide lookup.
     pub fn scan_file(
         &self,
         uri: &Url,
         text: &str,
     ) {
-        let symbols = self.extract_symbols(text);
-        // Remove old entries for this file first
-        self.remove_file(uri);
+        self.replace_file(uri, self.flat_symbols(text), None);
+    }
+
+    /// Index the symbols of `path` as it is on disk, unless they were already
+    /// scanned from the current content.
+    ///
+    /// A file that can no longer be read is removed from the index. Returns
+    /// whether the file was scanned.
+    pub(crate) fn refresh_from_disk(
+        &self,
+        path: &Path,
+    ) -> bool {
+        let Ok(uri) = Url::from_file_path(path) else {
+            return false;
+        };
+        if self.files.get(&uri).is_some_and(|file| file.stamp.as_ref().is_some_and(FileStamp::is_current)) {
+            return false;
+        }
+        let Ok(text) = std::fs::read_to_string(path) else {
+            self.remove_file(&uri);
+            return false;
+        };
+        self.scan_disk_file(&uri, path, &text);
+        true
+    }
+
+    /// Index `text`, just read from `path`, for the file `uri`.
+    pub(crate) fn scan_disk_file(
+        &self,
+        uri: &Url,
+        path: &Path,
+        text: &str,
+    ) {
+        self.replace_file(uri, self.flat_symbols(text), Some(FileStamp::of_content(path, text)));
+    }
+
+    fn flat_symbols(
+        &self,
+        text: &str,
+    ) -> Vec<StoredSymbol> {
+        flatten_symbols(&self.extract_symbols(text))
+            .into_iter()
+            .map(|(sym, container)| StoredSymbol {
+                name: sym.name.clone(),
+                range: sym.selection_range,
+                kind: sym.kind,
+                container_name: container.map(str::to_string),
+            })
+            .collect()
+    }
 
-        for sym in flatten_symbols(&symbols) {
+    fn replace_file(
+        &self,
+        uri: &Url,
+        symbols: Vec<StoredSymbol>,
+        stamp: Option<FileStamp>,
+    ) {
+        self.remove_file(uri);
+        let mut names = Vec::with_capacity(symbols.len());
+        for symbol in symbols {
+            names.push(symbol.name.clone());
             self.index.insert(
-                sym.name.clone(),
+                symbol.name,
                 SymbolLocation {
                     uri: uri.clone(),
-                    range: sym.selection_range,
+                    range: symbol.range,
+                    kind: symbol.kind,
+                    container_name: symbol.container_name,
                 },
             );
         }
+        names.sort_unstable();
+        names.dedup();
+        self.files.insert(
+            uri.clone(),
+            IndexedFile {
+                names,
+                stamp,
+            },
+        );
     }
 
     /// Remove all symbols for a file from the index.
@@ -60,17 +147,100 @@ impl SymbolProvider {
         &self,
         uri: &Url,
     ) {
-        // DashMap doesn't support easy iteration with removal of value items.
-        // We have to iterate all entries and retain only locations that don't match URI.
-        for mut entry in self.index.map.iter_mut() {
-            let locs: &mut Vec<SymbolLocation> = entry.value_mut();
-            locs.retain(|l| l.uri != *uri);
+        let Some((_, file)) = self.files.remove(uri) else {
+            return;
+        };
+        for name in file.names {
+            if let Some(mut locs) = self.index.map.get_mut(&name) {
+                locs.retain(|l| l.uri != *uri);
+            }
+            self.index.map.remove_if(&name, |_, locs| locs.is_empty());
         }
-        // Cleanup empty entries
-        self.index.map.retain(|_, v: &mut Vec<SymbolLocation>| !v.is_empty());
     }
 
-    /// Extract document symbols from source text (parses internally).
+    /// Restore the files in the persisted symbol snapshot of
+    /// `workspace_roots` that are unchanged on disk and accepted by
+    /// `wanted`, returning their paths.
+    pub(crate) fn restore_snapshot(
+        &self,
+        workspace_roots: &[PathBuf],
+        wanted: impl Fn(&Url) -> bool,
+    ) -> Vec<PathBuf> {
+        self.restore_files(store::load(workspace_roots), wanted)
+    }
+
+    fn restore_files(
+        &self,
+        files: Vec<SnapshotFile>,
+        wanted: impl Fn(&Url) -> bool,
+    ) -> Vec<PathBuf> {
+        let mut restored = Vec::new();
+        for file in files {
+            let path = file.stamp.path().to_path_buf();
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            if !wanted(&uri) {
+                continue;
+            }
+            if self.files.get(&uri).is_none_or(|indexed| indexed.stamp.as_ref() != Some(&file.stamp)) {
+                self.replace_file(&uri, file.symbols, Some(file.stamp));
+            }
+            restored.push(path);
+        }
+        restored
+    }
+
+    /// Persist the symbols of every file scanned from disk, returning how
+    /// many files were written.
+    pub(crate) fn save_snapshot(
+        &self,
+        workspace_roots: &[PathBuf],
+    ) -> std::io::Result<usize> {
+        store::save(self.snapshot_files(), workspace_roots)
+    }
+
+    /// The symbols of every file scanned from disk, with their stamps.
+    fn snapshot_files(&self) -> Vec<SnapshotFile> {
+        let mut files: HashMap<Url, SnapshotFile> = self
+            .files
+            .iter()
+            .filter_map(|entry| {
+                let stamp = entry.value().stamp.clone()?;
+                Some((
+                    entry.key().clone(),
+                    SnapshotFile {
+                        stamp,
+                        symbols: Vec::new(),
+                    },
+                ))
+            })
+            .collect();
+        for entry in self.index.map.iter() {
+            for loc in entry.value() {
+                if let Some(file) = files.get_mut(&loc.uri) {
+                    file.symbols.push(StoredSymbol {
+                        name: entry.key().clone(),
+                        range: loc.range,
+                        kind: loc.kind,
+                        container_name: loc.container_name.clone(),
+                    });
+                }
+            }
+        }
+        files.into_values().collect()
+    }
+
+    /// Delete the persisted symbol snapshot of `workspace_roots`, returning
+    /// whether there was one.
+    pub(crate) fn remove_snapshot(
+        &self,
+        workspace_roots: &[PathBuf],
+    ) -> bool {
+        store::remove(workspace_roots)
+    }
+
+    /// Extract the nested document outline from source text (parses internally).
     pub fn extract_symbols(
         &self,
         source: &str,
@@ -108,7 +278,8 @@ impl SymbolProvider {
         snapshot: &SyntaxTree,
         word: &str,
     ) -> Option<Range> {
-        let symbols = self.extract_symbols_from_snapshot(snapshot);
+        let tree = self.extract_symbols_from_snapshot(snapshot);
+        let symbols = file_level_symbols(&tree);
         let mut matches = symbols.iter().filter(|s| s.name == word);
         let first = matches.next()?;
         if matches.next().is_some() {
@@ -124,54 +295,47 @@ impl SymbolProvider {
         Some(first.selection_range)
     }
 
-    /// Returns symbols for the given document (flat list from index).
-    pub fn document_symbols(
-        &self,
-        uri: &Url,
-    ) -> Vec<SymbolInformation> {
-        let mut results = Vec::new();
-        for entry in self.index.map.iter() {
-            let name: &String = entry.key();
-            for loc in entry.value() {
-                if loc.uri == *uri {
-                    #[allow(deprecated)]
-                    results.push(SymbolInformation {
-                        name: name.clone(),
-                        kind: SymbolKind::VARIABLE, // Kind is not stored in SymbolLocation
-                        tags: None,
-                        deprecated: None,
-                        location: Location {
-                            uri: loc.uri.clone(),
-                            range: loc.range,
-                        },
-                        container_name: None,
-                    });
-                }
-            }
-        }
-        results
-    }
-
+    /// Fuzzy workspace symbol search over the files `filter` accepts, ranking
+    /// symbols near `anchor` first; see [`SymbolIndex::search`] for the query
+    /// syntax.
+    ///
+    /// Results from outside the regular workspace sources are annotated with
+    /// their origin in the container name, e.g. `Light (generated)`.
     pub fn workspace_symbols(
         &self,
         query: &str,
+        anchor: Option<&Url>,
+        filter: &SymbolSearchFilter,
     ) -> Vec<SymbolInformation> {
-        let hits = self.index.search(query, 100);
-        hits.into_iter()
-            .map(|(name, loc)| {
-                #[allow(deprecated)]
-                SymbolInformation {
-                    name,
-                    kind: SymbolKind::VARIABLE, // Kind is not stored in SymbolLocation
-                    tags: None,
-                    deprecated: None,
-                    location: Location {
-                        uri: loc.uri,
-                        range: loc.range,
-                    },
-                    container_name: None,
-                }
-            })
-            .collect()
+        let hits = self.index.search_in_scope(query, anchor, filter, 100);
+        hits.into_iter().map(|(name, loc, origin)| symbol_information(name, loc, origin)).collect()
     }
 }
+
+fn symbol_information(
+    name: String,
+    loc: SymbolLocation,
+    origin: SymbolOrigin,
+) -> SymbolInformation {
+    let container_name = match (loc.container_name, origin.label()) {
+        (Some(container), Some(label)) => Some(format!("{container} ({label})")),
+        (None, Some(label)) => Some(format!("({label})")),
+        (container, None) => container,
+    };
+    #[allow(deprecated)]
+    SymbolInformation {
+        name,
+        kind: loc.kind,
+        tags: None,
+        deprecated: None,
+        location: Location {
+            uri: loc.uri,
+            range: loc.range,
+        },
+        container_name,
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/symbols/provider_tests.rs"]
+mod tests;
@@ -1,4 +1,4 @@
-use tower_lsp::lsp_types::{DocumentSymbol, SymbolKind};
+use tower_lsp::lsp_types::{DocumentSymbol, Range, SymbolKind};
 
 use crate::syntax::{
     ast::{self, AstNode},
@@ -7,34 +7,36 @@ use crate::syntax::{
     kind::SyntaxKind,
 };
 
+/// Build the document outline: namespaces contain their declarations,
+/// structs and classes contain fields and methods, enums contain their
+/// members, and entry points contain their parameters.
 pub(crate) fn build_symbols(
     root: &SyntaxNode,
     text: &str,
+) -> Vec<DocumentSymbol> {
+    item_symbols(root, text, None)
+}
+
+/// Symbols declared directly in `parent` (the root, a namespace body or a
+/// struct/class body named `record`).
+fn item_symbols(
+    parent: &SyntaxNode,
+    text: &str,
+    record: Option<&str>,
 ) -> Vec<DocumentSymbol> {
     let mut symbols = Vec::new();
 
-    for node in root.descendants() {
+    for node in parent.children() {
         if let Some(func) = ast::FunctionDef::cast(node.clone()) {
-            if let Some(name) = func.name_token() {
-                let range = helpers::range_to_lsp(name.text_range(), text);
-                let detail = detect_function_detail(&func);
-                symbols.push(DocumentSymbol {
-                    name: name.text().to_string(),
-                    detail: Some(detail),
-                    kind: SymbolKind::FUNCTION,
-                    tags: None,
-                    #[allow(deprecated)]
-                    deprecated: None,
-                    range,
-                    selection_range: range,
-                    children: None,
-                });
+            if let Some(symbol) = function_symbol(&func, text, record) {
+                symbols.push(symbol);
             }
             continue;
         }
 
         if let Some(def) = ast::StructDef::cast(node.clone()) {
-            let symbol = struct_symbol(&def, text, SymbolKind::STRUCT);
+            let body = def.body();
+            let symbol = record_symbol(def.syntax(), def.name_token(), body, text, SymbolKind::STRUCT, "struct");
             if let Some(symbol) = symbol {
                 symbols.push(symbol);
             }
@@ -42,7 +44,8 @@ pub(crate) fn build_symbols(
         }
 
         if let Some(def) = ast::ClassDef::cast(node.clone()) {
-            let symbol = named_symbol(def.syntax(), text, SymbolKind::CLASS, "class");
+            let body = def.body();
+            let symbol = record_symbol(def.syntax(), def.name_token(), body, text, SymbolKind::CLASS, "class");
             if let Some(symbol) = symbol {
                 symbols.push(symbol);
             }
@@ -57,6 +60,22 @@ pub(crate) fn build_symbols(
             continue;
         }
 
+        if let Some(def) = ast::NamespaceDef::cast(node.clone()) {
+            let symbol = namespace_symbol(&def, text);
+            if let Some(symbol) = symbol {
+                symbols.push(symbol);
+            }
+            continue;
+        }
+
+        if let Some(def) = ast::FieldDef::cast(node.clone()) {
+            if let Some(name) = def.name_token() {
+                let range = helpers::range_to_lsp(name.text_range(), text);
+                symbols.push(symbol(name.text(), None, SymbolKind::FIELD, node_range(&node, text), range, Vec::new()));
+            }
+            continue;
+        }
+
         if let Some(def) = ast::TypedefDef::cast(node.clone()) {
             let symbol = named_symbol(def.syntax(), text, SymbolKind::TYPE_PARAMETER, "typedef");
             if let Some(symbol) = symbol {
@@ -73,21 +92,15 @@ pub(crate) fn build_symbols(
             continue;
         }
 
-        if let Some(def) = ast::TemplateParameter::cast(node.clone())
-            && let Some(name) = def.name_token()
-        {
-            let range = helpers::range_to_lsp(name.text_range(), text);
-            symbols.push(DocumentSymbol {
-                name: name.text().to_string(),
-                detail: Some("template param".to_string()),
-                kind: SymbolKind::TYPE_PARAMETER,
-                tags: None,
-                #[allow(deprecated)]
-                deprecated: None,
-                range,
-                selection_range: range,
-                children: None,
-            });
+        if let Some(def) = ast::TemplateDef::cast(node.clone()) {
+            for param in def.parameters() {
+                if let Some(name) = param.name_token() {
+                    let range = helpers::range_to_lsp(name.text_range(), text);
+                    let detail = Some("template param".to_string());
+                    symbols.push(symbol(name.text(), detail, SymbolKind::TYPE_PARAMETER, range, range, Vec::new()));
+                }
+            }
+            continue;
         }
 
         if let Some(def) = ast::PreprocDefine::cast(node.clone()) {
@@ -106,93 +119,157 @@ pub(crate) fn build_symbols(
         }
     }
 
-    symbols.sort_by_key(|s| (s.range.start.line, s.range.start.character));
+    symbols.sort_by_key(|s| (s.selection_range.start.line, s.selection_range.start.character));
     symbols
 }
 
-fn struct_symbol(
-    def: &ast::StructDef,
-    text: &str,
+fn symbol(
+    name: &str,
+    detail: Option<String>,
     kind: SymbolKind,
-) -> Option<DocumentSymbol> {
-    let name = def.name_token()?;
-    let range = helpers::range_to_lsp(name.text_range(), text);
-    let mut children = Vec::new();
-    for field in def.fields() {
-        if let Some(field_name) = field.name_token() {
-            let field_range = helpers::range_to_lsp(field_name.text_range(), text);
-            children.push(DocumentSymbol {
-                name: field_name.text().to_string(),
-                detail: None,
-                kind: SymbolKind::FIELD,
-                tags: None,
-                #[allow(deprecated)]
-                deprecated: None,
-                range: field_range,
-                selection_range: field_range,
-                children: None,
-            });
-        }
-    }
-
-    Some(DocumentSymbol {
-        name: name.text().to_string(),
-        detail: Some(format!("struct {}", name.text())),
+    range: Range,
+    selection_range: Range,
+    children: Vec<DocumentSymbol>,
+) -> DocumentSymbol {
+    DocumentSymbol {
+        name: name.to_string(),
+        detail,
         kind,
         tags: None,
         #[allow(deprecated)]
         deprecated: None,
         range,
-        selection_range: range,
+        selection_range,
         children: if children.is_empty() {
             None
         } else {
             Some(children)
         },
+    }
+}
+
+fn node_range(
+    node: &SyntaxNode,
+    text: &str,
+) -> Range {
+    helpers::range_to_lsp(node.text_range(), text)
+}
+
+fn function_symbol(
+    func: &ast::FunctionDef,
+    text: &str,
+    record: Option<&str>,
+) -> Option<DocumentSymbol> {
+    let name = func.name_token()?;
+    let selection_range = helpers::range_to_lsp(name.text_range(), text);
+    let display_name = function_display_name(func, name.text());
+
+    let kind = match record {
+        Some(record) if name.text() == record => SymbolKind::CONSTRUCTOR,
+        Some(_) => SymbolKind::METHOD,
+        None => SymbolKind::FUNCTION,
+    };
+
+    let mut children = Vec::new();
+    if is_entry_point(func)
+        && let Some(list) = func.parameter_list()
+    {
+        for param in list.parameters() {
+            let Some(param_name) = param.name_token() else {
+                continue;
+            };
+            let param_range = helpers::range_to_lsp(param_name.text_range(), text);
+            let detail = param
+                .syntax()
+                .children()
+                .find(|n| n.kind() == SyntaxKind::Attribute)
+                .map(|attribute| helpers::node_text(&attribute, text).to_string());
+            children.push(symbol(
+                param_name.text(),
+                detail,
+                SymbolKind::VARIABLE,
+                node_range(param.syntax(), text),
+                param_range,
+                Vec::new(),
+            ));
+        }
+    }
+
+    let detail = Some(detect_function_detail(func));
+    Some(symbol(&display_name, detail, kind, node_range(func.syntax(), text), selection_range, children))
+}
+
+/// Destructors keep their `~` in the outline.
+fn function_display_name(
+    func: &ast::FunctionDef,
+    name: &str,
+) -> String {
+    let has_tilde =
+        func.syntax().children_with_tokens().filter_map(|e| e.into_token()).any(|t| t.kind() == SyntaxKind::Tilde);
+    if has_tilde {
+        format!("~{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+fn is_entry_point(func: &ast::FunctionDef) -> bool {
+    func.syntax().children_with_tokens().filter_map(|e| e.into_token()).any(|token| {
+        matches!(
+            token.kind(),
+            SyntaxKind::KwKernel
+                | SyntaxKind::KwVertex
+                | SyntaxKind::KwFragment
+                | SyntaxKind::KwMesh
+                | SyntaxKind::KwObject
+        )
     })
 }
 
+fn record_symbol(
+    syntax: &SyntaxNode,
+    name: Option<crate::syntax::cst::SyntaxToken>,
+    body: Option<ast::Block>,
+    text: &str,
+    kind: SymbolKind,
+    keyword: &str,
+) -> Option<DocumentSymbol> {
+    let name = name?;
+    let selection_range = helpers::range_to_lsp(name.text_range(), text);
+    let children = body.map(|body| item_symbols(body.syntax(), text, Some(name.text()))).unwrap_or_default();
+    let detail = Some(format!("{keyword} {}", name.text()));
+    Some(symbol(name.text(), detail, kind, node_range(syntax, text), selection_range, children))
+}
+
+fn namespace_symbol(
+    def: &ast::NamespaceDef,
+    text: &str,
+) -> Option<DocumentSymbol> {
+    let name = def.name_token()?;
+    let selection_range = helpers::range_to_lsp(name.text_range(), text);
+    let children = def.body().map(|body| item_symbols(body.syntax(), text, None)).unwrap_or_default();
+    let detail = Some(format!("namespace {}", name.text()));
+    Some(symbol(name.text(), detail, SymbolKind::NAMESPACE, node_range(def.syntax(), text), selection_range, children))
+}
+
 fn enum_symbol(
     def: &ast::EnumDef,
     text: &str,
 ) -> Option<DocumentSymbol> {
     let name = def.name_token()?;
-    let range = helpers::range_to_lsp(name.text_range(), text);
+    let selection_range = helpers::range_to_lsp(name.text_range(), text);
     let mut children = Vec::new();
     if let Some(block) = def.syntax().children().find(|n| n.kind() == SyntaxKind::Block) {
         for token in block.descendants_with_tokens().filter_map(|e| e.into_token()) {
             if token.kind() == SyntaxKind::Ident {
                 let tok_range = helpers::range_to_lsp(token.text_range(), text);
-                children.push(DocumentSymbol {
-                    name: token.text().to_string(),
-                    detail: None,
-                    kind: SymbolKind::ENUM_MEMBER,
-                    tags: None,
-                    #[allow(deprecated)]
-                    deprecated: None,
-                    range: tok_range,
-                    selection_range: tok_range,
-                    children: None,
-                });
+                children.push(symbol(token.text(), None, SymbolKind::ENUM_MEMBER, tok_range, tok_range, Vec::new()));
             }
         }
     }
 
-    Some(DocumentSymbol {
-        name: name.text().to_string(),
-        detail: Some(format!("enum {}", name.text())),
-        kind: SymbolKind::ENUM,
-        tags: None,
-        #[allow(deprecated)]
-        deprecated: None,
-        range,
-        selection_range: range,
-        children: if children.is_empty() {
-            None
-        } else {
-            Some(children)
-        },
-    })
+    let detail = Some(format!("enum {}", name.text()));
+    Some(symbol(name.text(), detail, SymbolKind::ENUM, node_range(def.syntax(), text), selection_range, children))
 }
 
 fn named_symbol(
@@ -202,18 +279,9 @@ fn named_symbol(
     prefix: &str,
 ) -> Option<DocumentSymbol> {
     let name = syntax.children_with_tokens().filter_map(|e| e.into_token()).find(|t| t.kind() == SyntaxKind::Ident)?;
-    let range = helpers::range_to_lsp(name.text_range(), text);
-    Some(DocumentSymbol {
-        name: name.text().to_string(),
-        detail: Some(format!("{prefix} {}", name.text())),
-        kind,
-        tags: None,
-        #[allow(deprecated)]
-        deprecated: None,
-        range,
-        selection_range: range,
-        children: None,
-    })
+    let selection_range = helpers::range_to_lsp(name.text_range(), text);
+    let detail = Some(format!("{prefix} {}", name.text()));
+    Some(symbol(name.text(), detail, kind, node_range(syntax, text), selection_range, Vec::new()))
 }
 
 fn detect_function_detail(func: &ast::FunctionDef) -> String {
@@ -235,14 +303,46 @@ fn detect_function_detail(func: &ast::FunctionDef) -> String {
     }
 }
 
-/// Flatten nested DocumentSymbols into a single list (for scan_file indexing).
-pub(crate) fn flatten_symbols(symbols: &[DocumentSymbol]) -> Vec<&DocumentSymbol> {
+/// Flatten nested DocumentSymbols into a single list (for scan_file indexing),
+/// pairing each symbol with the name of its parent. Function parameters are
+/// omitted.
+pub(crate) fn flatten_symbols(symbols: &[DocumentSymbol]) -> Vec<(&DocumentSymbol, Option<&str>)> {
     let mut result = Vec::new();
+    flatten_into(symbols, None, &mut result);
+    result
+}
+
+fn flatten_into<'a>(
+    symbols: &'a [DocumentSymbol],
+    container: Option<&'a str>,
+    result: &mut Vec<(&'a DocumentSymbol, Option<&'a str>)>,
+) {
     for sym in symbols {
-        result.push(sym);
+        result.push((sym, container));
+        if is_function_kind(sym.kind) {
+            continue;
+        }
         if let Some(children) = &sym.children {
-            result.extend(flatten_symbols(children));
+            flatten_into(children, Some(&sym.name), result);
+        }
+    }
+}
+
+/// Symbols that can be resolved by name without scope information: those
+/// declared at file level, looking through namespaces.
+pub(crate) fn file_level_symbols(symbols: &[DocumentSymbol]) -> Vec<&DocumentSymbol> {
+    let mut result = Vec::new();
+    for sym in symbols {
+        result.push(sym);
+        if sym.kind == SymbolKind::NAMESPACE
+            && let Some(children) = &sym.children
+        {
+            result.extend(file_level_symbols(children));
         }
     }
     result
 }
+
+fn is_function_kind(kind: SymbolKind) -> bool {
+    matches!(kind, SymbolKind::FUNCTION | SymbolKind::METHOD | SymbolKind::CONSTRUCTOR)
+}
@@ -0,0 +1,108 @@
+//! On-disk snapshot of the workspace [`SymbolIndex`](super::SymbolIndex).
+//!
+//! The snapshot is saved next to the persisted project index and holds the
+//! symbols of every file last scanned from disk, each with the stamp of the
+//! content it was scanned from. A new session restores the files whose
+//! stamps still match, so workspace symbol search is complete before the
+//! background scan has parsed anything.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{Range, SymbolKind};
+use tracing::{debug, warn};
+
+use crate::definition::project_store::{FileStamp, root_keys, sibling_snapshot_path, write_atomically};
+
+const SNAPSHOT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SymbolSnapshot {
+    schema_version: u32,
+    workspace_roots: Vec<String>,
+    files: Vec<SnapshotFile>,
+}
+
+/// The symbols of one file and the stamp of the content they came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SnapshotFile {
+    pub(crate) stamp: FileStamp,
+    pub(crate) symbols: Vec<StoredSymbol>,
+}
+
+/// A [`SymbolLocation`](super::SymbolLocation) without the URI, which the
+/// file's stamp already records.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct StoredSymbol {
+    pub(crate) name: String,
+    pub(crate) range: Range,
+    pub(crate) kind: SymbolKind,
+    pub(crate) container_name: Option<String>,
+}
+
+/// Write `files` to the symbol snapshot of `workspace_roots`, returning how
+/// many files were saved.
+pub(crate) fn save(
+    files: Vec<SnapshotFile>,
+    workspace_roots: &[PathBuf],
+) -> std::io::Result<usize> {
+    save_to(&snapshot_path(workspace_roots), files, workspace_roots)
+}
+
+/// The files in the symbol snapshot of `workspace_roots` whose stamps still
+/// match the files on disk.
+pub(crate) fn load(workspace_roots: &[PathBuf]) -> Vec<SnapshotFile> {
+    load_from(&snapshot_path(workspace_roots), workspace_roots)
+}
+
+/// Delete the symbol snapshot of `workspace_roots`, returning whether there
+/// was one.
+pub(crate) fn remove(workspace_roots: &[PathBuf]) -> bool {
+    std::fs::remove_file(snapshot_path(workspace_roots)).is_ok()
+}
+
+fn save_to(
+    snapshot_file: &Path,
+    files: Vec<SnapshotFile>,
+    workspace_roots: &[PathBuf],
+) -> std::io::Result<usize> {
+    let snapshot = SymbolSnapshot {
+        schema_version: SNAPSHOT_SCHEMA_VERSION,
+        workspace_roots: root_keys(workspace_roots),
+        files,
+    };
+    let json = serde_json::to_vec(&snapshot).map_err(std::io::Error::other)?;
+    write_atomically(snapshot_file, &json)?;
+    debug!("[symbol-store] saved {} file(s) to {}", snapshot.files.len(), snapshot_file.display());
+    Ok(snapshot.files.len())
+}
+
+fn load_from(
+    snapshot_file: &Path,
+    workspace_roots: &[PathBuf],
+) -> Vec<SnapshotFile> {
+    let Ok(content) = std::fs::read(snapshot_file) else {
+        return Vec::new();
+    };
+    let snapshot = match serde_json::from_slice::<SymbolSnapshot>(&content) {
+        Ok(snapshot) => snapshot,
+        Err(error) => {
+            warn!("[symbol-store] ignoring unreadable snapshot {}: {error}", snapshot_file.display());
+            return Vec::new();
+        },
+    };
+    if snapshot.schema_version != SNAPSHOT_SCHEMA_VERSION || snapshot.workspace_roots != root_keys(workspace_roots) {
+        return Vec::new();
+    }
+    let files: Vec<SnapshotFile> = snapshot.files.into_iter().filter(|file| file.stamp.is_current()).collect();
+    debug!("[symbol-store] loaded {} current file(s) from {}", files.len(), snapshot_file.display());
+    files
+}
+
+fn snapshot_path(workspace_roots: &[PathBuf]) -> PathBuf {
+    sibling_snapshot_path(workspace_roots, "symbols.json")
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/symbols/store_tests.rs"]
+mod tests;
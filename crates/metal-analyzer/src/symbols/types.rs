@@ -1,7 +1,10 @@
-use tower_lsp::lsp_types::{Range, Url};
+use tower_lsp::lsp_types::{Range, SymbolKind, Url};
 
 #[derive(Clone, Debug)]
 pub struct SymbolLocation {
     pub uri: Url,
     pub range: Range,
+    pub kind: SymbolKind,
+    /// Name of the enclosing symbol (struct for fields, enum for members).
+    pub container_name: Option<String>,
 }
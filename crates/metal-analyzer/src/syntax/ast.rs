@@ -101,8 +101,12 @@ impl StructDef {
         first_ident_token(&self.syntax)
     }
 
+    pub fn body(&self) -> Option<Block> {
+        self.syntax.children().find_map(Block::cast)
+    }
+
     pub fn fields(&self) -> impl Iterator<Item = FieldDef> {
-        self.syntax.children().filter_map(FieldDef::cast)
+        self.body().into_iter().flat_map(|body| body.syntax().children().filter_map(FieldDef::cast))
     }
 }
 
@@ -131,6 +135,10 @@ impl ClassDef {
     pub fn name_token(&self) -> Option<SyntaxToken> {
         first_ident_token(&self.syntax)
     }
+
+    pub fn body(&self) -> Option<Block> {
+        self.syntax.children().find_map(Block::cast)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -181,6 +189,16 @@ impl AstNode for NamespaceDef {
     }
 }
 
+impl NamespaceDef {
+    pub fn name_token(&self) -> Option<SyntaxToken> {
+        first_ident_token(&self.syntax)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        self.syntax.children().find_map(Block::cast)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TemplateDef {
     syntax: SyntaxNode,
@@ -40,51 +40,56 @@ impl<'a> Parser<'a> {
 
     fn parse_root(&mut self) {
         while !self.is_eof() {
-            if self.line_start && self.at(SyntaxKind::Hash) {
-                self.parse_preprocessor();
-                continue;
-            }
-            self.skip_trivia();
-            if self.is_eof() {
-                break;
-            }
+            self.parse_item();
+        }
+    }
 
-            match self.peek() {
-                SyntaxKind::KwKernel
-                | SyntaxKind::KwVertex
-                | SyntaxKind::KwFragment
-                | SyntaxKind::KwMesh
-                | SyntaxKind::KwObject => {
-                    self.parse_function_def();
-                },
-                SyntaxKind::KwStruct => {
-                    self.parse_struct_def();
-                },
-                SyntaxKind::KwClass => {
-                    self.parse_class_def();
-                },
-                SyntaxKind::KwEnum => {
-                    self.parse_enum_def();
-                },
-                SyntaxKind::KwNamespace => {
-                    self.parse_namespace_def();
-                },
-                SyntaxKind::KwTemplate => {
-                    self.parse_template_def();
-                },
-                SyntaxKind::KwTypedef => {
-                    self.parse_typedef_def();
-                },
-                SyntaxKind::KwUsing => {
-                    self.parse_using_def();
-                },
-                _ => {
-                    if !self.parse_function_or_variable_def() {
-                        // Consume unexpected token to make progress
-                        self.bump();
-                    }
-                },
-            }
+    /// Parse one top-level (or namespace-level) declaration.
+    fn parse_item(&mut self) {
+        if self.line_start && self.at(SyntaxKind::Hash) {
+            self.parse_preprocessor();
+            return;
+        }
+        self.skip_trivia();
+        if self.is_eof() {
+            return;
+        }
+
+        match self.peek() {
+            SyntaxKind::KwKernel
+            | SyntaxKind::KwVertex
+            | SyntaxKind::KwFragment
+            | SyntaxKind::KwMesh
+            | SyntaxKind::KwObject => {
+                self.parse_function_def();
+            },
+            SyntaxKind::KwStruct => {
+                self.parse_struct_def();
+            },
+            SyntaxKind::KwClass => {
+                self.parse_class_def();
+            },
+            SyntaxKind::KwEnum => {
+                self.parse_enum_def();
+            },
+            SyntaxKind::KwNamespace => {
+                self.parse_namespace_def();
+            },
+            SyntaxKind::KwTemplate => {
+                self.parse_template_def();
+            },
+            SyntaxKind::KwTypedef => {
+                self.parse_typedef_def();
+            },
+            SyntaxKind::KwUsing => {
+                self.parse_using_def();
+            },
+            _ => {
+                if !self.parse_function_or_variable_def() {
+                    // Consume unexpected token to make progress
+                    self.bump();
+                }
+            },
         }
     }
 
@@ -201,26 +206,110 @@ impl<'a> Parser<'a> {
         self.skip_trivia();
 
         if self.at(SyntaxKind::LBrace) {
-            self.start_node(SyntaxKind::Block);
+            self.parse_member_block();
+        }
+        self.skip_trivia();
+
+        if self.at(SyntaxKind::Semicolon) {
+            self.bump();
+        }
+
+        self.finish_node();
+    }
+
+    /// Parse a struct or class body: fields, member functions and nested types.
+    fn parse_member_block(&mut self) {
+        self.start_node(SyntaxKind::Block);
+        self.bump(); // LBrace
+        loop {
+            if self.line_start && self.at(SyntaxKind::Hash) {
+                self.parse_preprocessor();
+                continue;
+            }
+            self.skip_trivia();
+            if self.is_eof() || self.at(SyntaxKind::RBrace) {
+                break;
+            }
+
+            match self.peek() {
+                SyntaxKind::KwPublic | SyntaxKind::KwPrivate | SyntaxKind::KwProtected
+                    if self.peek_nth_non_trivia(1) == Some(SyntaxKind::Colon) =>
+                {
+                    self.bump();
+                    self.skip_trivia();
+                    self.bump(); // Colon
+                },
+                SyntaxKind::KwStruct => self.parse_struct_def(),
+                SyntaxKind::KwClass => self.parse_class_def(),
+                SyntaxKind::KwEnum => self.parse_enum_def(),
+                SyntaxKind::KwTemplate => self.parse_template_def(),
+                SyntaxKind::KwTypedef => self.parse_typedef_def(),
+                SyntaxKind::KwUsing => self.parse_using_def(),
+                _ if self.looks_like_member_function() => self.parse_member_function(),
+                _ => self.parse_field_def(),
+            }
+        }
+        if self.at(SyntaxKind::RBrace) {
+            self.bump();
+        }
+        self.finish_node();
+    }
+
+    /// Parse a method, constructor, destructor or operator inside a struct body.
+    fn parse_member_function(&mut self) {
+        self.start_node(SyntaxKind::FunctionDef);
+        let at_name = |p: &Self| {
+            p.at(SyntaxKind::Tilde)
+                || p.at(SyntaxKind::KwOperator)
+                || (p.at(SyntaxKind::Ident) && p.peek_nth_non_trivia(1) == Some(SyntaxKind::LParen))
+        };
+
+        if !at_name(self) {
+            self.parse_type_ref();
+        }
+        // Specifiers the type parser stops at, e.g. `inline` or macros.
+        while !self.is_eof() && !at_name(self) && !self.at(SyntaxKind::LParen) && !self.at(SyntaxKind::RBrace) {
+            self.bump();
+        }
+
+        if self.at(SyntaxKind::Tilde) {
+            self.bump();
+            self.skip_trivia();
+        }
+        if self.at(SyntaxKind::KwOperator) {
             self.bump();
-            while !self.is_eof() && !self.at(SyntaxKind::RBrace) {
+            self.skip_trivia();
+            // `operator()` names itself with a parenthesis pair.
+            if self.at(SyntaxKind::LParen) && self.peek_nth_non_trivia(1) == Some(SyntaxKind::RParen) {
+                self.bump();
                 self.skip_trivia();
-                if self.is_eof() || self.at(SyntaxKind::RBrace) {
-                    break;
-                }
-                self.parse_field_def();
+                self.bump();
             }
-            if self.at(SyntaxKind::RBrace) {
+            while !self.is_eof() && !self.at(SyntaxKind::LParen) && !self.at(SyntaxKind::RBrace) {
                 self.bump();
             }
-            self.finish_node();
+        } else if self.at(SyntaxKind::Ident) {
+            self.bump();
         }
         self.skip_trivia();
 
-        if self.at(SyntaxKind::Semicolon) {
-            self.bump();
+        if self.at(SyntaxKind::LParen) {
+            self.parse_parameter_list();
         }
 
+        // Qualifiers, initializer lists and `= default` up to the body or terminator.
+        while !self.is_eof()
+            && !self.at(SyntaxKind::LBrace)
+            && !self.at(SyntaxKind::Semicolon)
+            && !self.at(SyntaxKind::RBrace)
+        {
+            self.bump();
+        }
+        if self.at(SyntaxKind::LBrace) {
+            self.parse_block();
+        } else if self.at(SyntaxKind::Semicolon) {
+            self.bump();
+        }
         self.finish_node();
     }
 
@@ -233,7 +322,7 @@ impl<'a> Parser<'a> {
         }
         self.skip_trivia();
         if self.at(SyntaxKind::LBrace) {
-            self.parse_block_like();
+            self.parse_member_block();
         }
         if self.at(SyntaxKind::Semicolon) {
             self.bump();
@@ -271,7 +360,19 @@ impl<'a> Parser<'a> {
         }
         self.skip_trivia();
         if self.at(SyntaxKind::LBrace) {
-            self.parse_block_like();
+            self.start_node(SyntaxKind::Block);
+            self.bump();
+            loop {
+                self.skip_trivia();
+                if self.is_eof() || self.at(SyntaxKind::RBrace) {
+                    break;
+                }
+                self.parse_item();
+            }
+            if self.at(SyntaxKind::RBrace) {
+                self.bump();
+            }
+            self.finish_node();
         }
         self.finish_node();
     }
@@ -1144,6 +1245,29 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// Whether the member declaration at the cursor is a function: a `(`
+    /// directly after the declarator name, before any field terminator.
+    fn looks_like_member_function(&self) -> bool {
+        let mut previous = None;
+        for &(kind, _) in &self.tokens[self.pos..] {
+            match kind {
+                SyntaxKind::Whitespace | SyntaxKind::Comment => continue,
+                SyntaxKind::LParen => {
+                    return matches!(previous, Some(SyntaxKind::Ident | SyntaxKind::KwOperator));
+                },
+                SyntaxKind::KwOperator => return true,
+                SyntaxKind::Semicolon
+                | SyntaxKind::LBrace
+                | SyntaxKind::RBrace
+                | SyntaxKind::Equal
+                | SyntaxKind::Colon
+                | SyntaxKind::LDoubleBracket => return false,
+                _ => previous = Some(kind),
+            }
+        }
+        false
+    }
+
     fn is_type_keyword(
         &self,
         kind: SyntaxKind,
@@ -2,9 +2,12 @@
 use rowan::{TextRange, TextSize, TokenAtOffset};
 use tower_lsp::lsp_types::{Position, Range};
 
-use crate::syntax::{
-    cst::{SyntaxNode, SyntaxToken},
-    kind::SyntaxKind,
+use crate::{
+    syntax::{
+        cst::{SyntaxNode, SyntaxToken},
+        kind::SyntaxKind,
+    },
+    text_pos::{byte_offset_of_column, column_of_byte_offset, text_width},
 };
 
 pub fn range_to_lsp(
@@ -76,6 +79,16 @@ pub fn word_at_position(
     None
 }
 
+/// Return the token under the cursor, preferring identifiers and literals.
+pub fn token_at_position(
+    root: &SyntaxNode,
+    source: &str,
+    position: Position,
+) -> Option<SyntaxToken> {
+    let offset = position_to_offset(source, position);
+    pick_token(root.token_at_offset(offset))
+}
+
 /// Return the syntax-token kind under the cursor, if any.
 pub fn token_kind_at_position(
     root: &SyntaxNode,
@@ -87,6 +100,16 @@ pub fn token_kind_at_position(
     Some(token.kind())
 }
 
+/// Return the token containing the position, preferring the one that ends at it.
+pub fn token_before_position(
+    root: &SyntaxNode,
+    source: &str,
+    position: Position,
+) -> Option<SyntaxToken> {
+    let offset = position_to_offset(source, position);
+    root.token_at_offset(offset).left_biased()
+}
+
 /// Extract a navigable symbol at a position with parser-aware fallback rules.
 ///
 /// We only use text fallback when the parser cannot classify the token (`Error`)
@@ -122,12 +145,11 @@ pub fn word_at_position_text_fallback(
     position: Position,
 ) -> Option<String> {
     let line = source.lines().nth(position.line as usize)?;
-    let chars: Vec<char> = line.chars().collect();
-    let col = position.character as usize;
-
-    if col > chars.len() {
+    if position.character > text_width(line) {
         return None;
     }
+    let col = line[..byte_offset_of_column(line, position.character)].chars().count();
+    let chars: Vec<char> = line.chars().collect();
 
     let is_identifier_char = |c: char| c.is_alphanumeric() || c == '_';
 
@@ -181,8 +203,7 @@ pub fn include_at_position_text_fallback(
     };
     let end_index = line[start_index + 1..].find(end_char)? + start_index + 1;
 
-    let col = position.character as usize;
-    if col < line.len() {
+    if byte_offset_of_column(line, position.character) < line.len() {
         return Some((line[start_index + 1..end_index].to_string(), is_system));
     }
 
@@ -210,7 +231,7 @@ pub fn attribute_at_position_text_fallback(
     let line = source.lines().nth(position.line as usize)?;
     let start = line.find("[[")?;
     let end = line[start + 2..].find("]]")? + start + 2;
-    let col = position.character as usize;
+    let col = byte_offset_of_column(line, position.character);
     if col >= start && col <= end + 2 {
         return Some(line[start..end + 2].to_string());
     }
@@ -255,16 +276,7 @@ fn position_to_offset(
     }
 
     if let Some(line) = lines.next() {
-        let mut utf16_col = 0u32;
-        let mut char_offset = 0usize;
-        for ch in line.chars() {
-            if utf16_col >= position.character {
-                break;
-            }
-            utf16_col += ch.len_utf16() as u32;
-            char_offset += ch.len_utf8();
-        }
-        byte_offset += char_offset;
+        byte_offset += byte_offset_of_column(line, position.character);
     }
 
     TextSize::from((byte_offset as u32).min(source.len() as u32))
@@ -278,18 +290,9 @@ fn offset_to_position(
     for (line_index, line) in source.split('\n').enumerate() {
         let line_len = line.len();
         if remaining <= line_len {
-            let mut utf16_col = 0u32;
-            let mut byte_count = 0usize;
-            for ch in line.chars() {
-                if byte_count >= remaining {
-                    break;
-                }
-                utf16_col += ch.len_utf16() as u32;
-                byte_count += ch.len_utf8();
-            }
             return Position {
                 line: line_index as u32,
-                character: utf16_col,
+                character: column_of_byte_offset(line, remaining),
             };
         }
         remaining = remaining.saturating_sub(line_len + 1);
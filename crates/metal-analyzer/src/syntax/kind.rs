@@ -563,9 +563,9 @@ pub enum TokenKind {
     #[regex(r"0[0-7](_?[0-7])*([uUlL]+)?")]
     #[regex(r"[0-9](_?[0-9])*([uUlL]+)?")]
     Integer,
-    #[regex(r"[0-9](_?[0-9])*\.[0-9](_?[0-9])*([eE][+-]?[0-9](_?[0-9])*)?([fFlL]+)?")]
-    #[regex(r"\.[0-9](_?[0-9])*([eE][+-]?[0-9](_?[0-9])*)?([fFlL]+)?")]
-    #[regex(r"[0-9](_?[0-9])*[eE][+-]?[0-9](_?[0-9])*([fFlL]+)?")]
+    #[regex(r"[0-9](_?[0-9])*\.[0-9](_?[0-9])*([eE][+-]?[0-9](_?[0-9])*)?([fFhHlL]+)?")]
+    #[regex(r"\.[0-9](_?[0-9])*([eE][+-]?[0-9](_?[0-9])*)?([fFhHlL]+)?")]
+    #[regex(r"[0-9](_?[0-9])*[eE][+-]?[0-9](_?[0-9])*([fFhHlL]+)?")]
     Float,
 }
 
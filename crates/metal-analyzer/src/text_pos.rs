@@ -1,4 +1,140 @@
-use tower_lsp::lsp_types::Position;
+//! Conversions between byte offsets and LSP positions.
+//!
+//! LSP columns count code units of the position encoding negotiated in
+//! `initialize`: UTF-16 unless the client offers UTF-8 or UTF-32. Every
+//! column the server sends or receives goes through this module, so lines
+//! with non-ASCII comments or string literals map the same way everywhere.
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicU8, Ordering},
+    },
+    time::SystemTime,
+};
+
+use tower_lsp::lsp_types::{ClientCapabilities, Position, PositionEncodingKind};
+
+/// The unit LSP columns are counted in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PositionEncoding {
+    Utf8,
+    Utf16,
+    Utf32,
+}
+
+static ENCODING: AtomicU8 = AtomicU8::new(PositionEncoding::Utf16 as u8);
+
+impl PositionEncoding {
+    /// The first encoding the client offers that the server supports, in
+    /// the client's order of preference, or UTF-16, which every client
+    /// supports.
+    pub fn negotiate(capabilities: &ClientCapabilities) -> Self {
+        capabilities
+            .general
+            .as_ref()
+            .and_then(|general| general.position_encodings.as_ref())
+            .and_then(|offered| offered.iter().find_map(Self::from_kind))
+            .unwrap_or(Self::Utf16)
+    }
+
+    fn from_kind(kind: &PositionEncodingKind) -> Option<Self> {
+        match kind.as_str() {
+            "utf-8" => Some(Self::Utf8),
+            "utf-16" => Some(Self::Utf16),
+            "utf-32" => Some(Self::Utf32),
+            _ => None,
+        }
+    }
+
+    pub fn kind(self) -> PositionEncodingKind {
+        match self {
+            Self::Utf8 => PositionEncodingKind::UTF8,
+            Self::Utf16 => PositionEncodingKind::UTF16,
+            Self::Utf32 => PositionEncodingKind::UTF32,
+        }
+    }
+
+    /// The encoding negotiated with the client.
+    pub fn current() -> Self {
+        match ENCODING.load(Ordering::Relaxed) {
+            0 => Self::Utf8,
+            2 => Self::Utf32,
+            _ => Self::Utf16,
+        }
+    }
+
+    pub(crate) fn set_current(self) {
+        ENCODING.store(self as u8, Ordering::Relaxed);
+    }
+
+    /// Code units of `ch`.
+    pub fn width(
+        self,
+        ch: char,
+    ) -> u32 {
+        match self {
+            Self::Utf8 => ch.len_utf8() as u32,
+            Self::Utf16 => ch.len_utf16() as u32,
+            Self::Utf32 => 1,
+        }
+    }
+
+    /// Code units of `text`.
+    pub fn len(
+        self,
+        text: &str,
+    ) -> u32 {
+        match self {
+            Self::Utf8 => text.len() as u32,
+            Self::Utf16 => text.encode_utf16().count() as u32,
+            Self::Utf32 => text.chars().count() as u32,
+        }
+    }
+
+    /// Column of `byte_offset` in `line`.
+    pub fn column(
+        self,
+        line: &str,
+        byte_offset: usize,
+    ) -> u32 {
+        let mut end = byte_offset.min(line.len());
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.len(&line[..end])
+    }
+
+    /// Byte offset of `column` in `line`, past the character it falls in
+    /// and clamped to the end of the line.
+    pub fn byte_offset(
+        self,
+        line: &str,
+        column: u32,
+    ) -> usize {
+        let mut units = 0u32;
+        for (offset, ch) in line.char_indices() {
+            if units >= column {
+                return offset;
+            }
+            units += self.width(ch);
+        }
+        line.len()
+    }
+}
+
+/// Code units of `text` in the negotiated encoding.
+pub fn text_width(text: &str) -> u32 {
+    PositionEncoding::current().len(text)
+}
+
+/// Code units of `ch` in the negotiated encoding.
+pub fn char_width(ch: char) -> u32 {
+    PositionEncoding::current().width(ch)
+}
 
 #[allow(dead_code)]
 pub fn byte_offset_from_position(
@@ -15,17 +151,7 @@ pub fn byte_offset_from_position(
     }
 
     let line = lines.next()?;
-    let mut utf16_offset = 0u32;
-    let mut char_offset = 0usize;
-    for ch in line.chars() {
-        if utf16_offset >= position.character {
-            break;
-        }
-        utf16_offset += ch.len_utf16() as u32;
-        char_offset += ch.len_utf8();
-    }
-
-    Some(byte_offset + char_offset)
+    Some(byte_offset + byte_offset_of_column(line, position.character))
 }
 
 #[allow(dead_code)]
@@ -38,16 +164,7 @@ pub fn position_from_byte_offset(
     for (line_index, line) in source.split('\n').enumerate() {
         let line_len = line.len();
         if remaining <= line_len {
-            let mut utf16_col = 0u32;
-            let mut byte_count = 0usize;
-            for ch in line.chars() {
-                if byte_count >= remaining {
-                    break;
-                }
-                utf16_col += ch.len_utf16() as u32;
-                byte_count += ch.len_utf8();
-            }
-            return Position::new(line_index as u32, utf16_col);
+            return Position::new(line_index as u32, column_of_byte_offset(line, remaining));
         }
         remaining = remaining.saturating_sub(line_len + 1);
     }
@@ -66,10 +183,128 @@ pub fn line_and_byte_column_at_position(
     Some((&source[line_start..line_end], byte_offset - line_start))
 }
 
-#[allow(dead_code)]
-pub fn utf16_column_of_byte_offset(
+/// Column of `byte_offset` in `line` in the negotiated encoding.
+pub fn column_of_byte_offset(
     line: &str,
     byte_offset: usize,
 ) -> u32 {
-    line[..byte_offset.min(line.len())].encode_utf16().count() as u32
+    PositionEncoding::current().column(line, byte_offset)
+}
+
+/// Byte offset of the negotiated-encoding `column` in `line`.
+pub fn byte_offset_of_column(
+    line: &str,
+    column: u32,
+) -> usize {
+    PositionEncoding::current().byte_offset(line, column)
+}
+
+/// Files whose lines [`column_in_file`] converted recently.
+const FILE_CACHE_CAPACITY: usize = 32;
+
+struct CachedFile {
+    modified: Option<SystemTime>,
+    text: Arc<str>,
+    line_starts: Arc<[usize]>,
+}
+
+static FILES: LazyLock<Mutex<HashMap<String, CachedFile>>> = LazyLock::new(Default::default);
+
+/// Text and line starts of the documents open in the editor, by path. The
+/// indexes of an open document are built from its unsaved text, so their
+/// locations are converted against it rather than the file on disk.
+static OPEN_FILES: LazyLock<Mutex<HashMap<String, FileLines>>> = LazyLock::new(Default::default);
+
+/// A file's text and the byte offset each of its lines starts at.
+type FileLines = (Arc<str>, Arc<[usize]>);
+
+/// Convert locations in the file at `path` against `text`, the current
+/// content of the open document, until [`close_open_file`].
+pub(crate) fn set_open_file(
+    path: &Path,
+    text: &str,
+) {
+    let text: Arc<str> = text.into();
+    let line_starts = line_starts(&text);
+    let mut open = OPEN_FILES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    open.insert(path.display().to_string(), (text, line_starts));
+}
+
+/// Go back to reading the file at `path` from disk.
+pub(crate) fn close_open_file(path: &Path) {
+    let mut open = OPEN_FILES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    open.remove(&path.display().to_string());
+}
+
+/// Column of the 0-based `byte_column` on the 0-based `line` of the file at
+/// `path`, as Clang reports locations, against the open document's text
+/// when the file is open. Keeps the byte column if the file cannot be read.
+pub(crate) fn column_in_file(
+    path: &str,
+    line: u32,
+    byte_column: u32,
+) -> u32 {
+    let encoding = PositionEncoding::current();
+    if encoding == PositionEncoding::Utf8 {
+        return byte_column;
+    }
+    let Some((text, line_starts)) = file_lines(path) else {
+        return byte_column;
+    };
+    let Some(&start) = line_starts.get(line as usize) else {
+        return byte_column;
+    };
+    let end = line_starts.get(line as usize + 1).map_or(text.len(), |next| next - 1);
+    let line_text = &text[start..end];
+    if line_text.is_ascii() {
+        return byte_column;
+    }
+    encoding.column(line_text, byte_column as usize)
+}
+
+/// Text of the 0-based `line` of the file at `path`, without its line
+/// break.
+pub(crate) fn line_in_file(
+    path: &str,
+    line: u32,
+) -> Option<String> {
+    let (text, line_starts) = file_lines(path)?;
+    let start = *line_starts.get(line as usize)?;
+    let end = line_starts.get(line as usize + 1).map_or(text.len(), |next| next - 1);
+    Some(text[start..end].trim_end_matches('\r').to_owned())
 }
+
+fn file_lines(path: &str) -> Option<FileLines> {
+    if let Some((text, line_starts)) = OPEN_FILES.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get(path) {
+        return Some((Arc::clone(text), Arc::clone(line_starts)));
+    }
+    let modified = std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    let mut files = FILES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(cached) = files.get(path)
+        && cached.modified == modified
+    {
+        return Some((Arc::clone(&cached.text), Arc::clone(&cached.line_starts)));
+    }
+    let text: Arc<str> = std::fs::read_to_string(Path::new(path)).ok()?.into();
+    let line_starts = line_starts(&text);
+    if files.len() >= FILE_CACHE_CAPACITY {
+        files.clear();
+    }
+    files.insert(
+        path.to_owned(),
+        CachedFile {
+            modified,
+            text: Arc::clone(&text),
+            line_starts: Arc::clone(&line_starts),
+        },
+    );
+    Some((text, line_starts))
+}
+
+fn line_starts(text: &str) -> Arc<[usize]> {
+    std::iter::once(0).chain(text.match_indices('\n').map(|(offset, _)| offset + 1)).collect()
+}
+
+#[cfg(test)]
+#[path = "../tests/src/text_pos_tests.rs"]
+mod tests;
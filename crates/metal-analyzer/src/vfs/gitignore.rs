@@ -0,0 +1,187 @@
+//! `.gitignore` rules of the workspace, for scans that should skip build
+//! output and vendored trees the way git does.
+//!
+//! Every `.gitignore` from a workspace root down to the file's directory
+//! applies, deeper files after shallower ones, and the last matching rule
+//! wins: `!pattern` re-includes what an earlier rule ignored. A pattern
+//! with a `/` other than a trailing one is anchored at its `.gitignore`'s
+//! directory, a trailing `/` matches only directories, and anything below an
+//! ignored directory stays ignored. Files above the workspace roots and
+//! `.git/info/exclude` are not read.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::vfs::glob::{path_segments, segments_match};
+
+/// Name of the files holding the rules.
+pub const GITIGNORE_FILE: &str = ".gitignore";
+
+/// The `.gitignore` files under a set of workspace roots, read as they are
+/// needed.
+#[derive(Debug, Default)]
+pub struct Gitignore {
+    roots: Vec<PathBuf>,
+    /// Rules by directory; `None` for a directory without a `.gitignore`.
+    files: HashMap<PathBuf, Option<Arc<Vec<Rule>>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    segments: Vec<String>,
+    /// Matched against the path from the `.gitignore`'s directory rather
+    /// than the name alone.
+    anchored: bool,
+    negated: bool,
+    directory_only: bool,
+}
+
+impl Gitignore {
+    pub fn new(workspace_roots: &[PathBuf]) -> Self {
+        Self {
+            roots: workspace_roots.to_vec(),
+            files: HashMap::new(),
+        }
+    }
+
+    /// Whether `path` or a directory above it, up to its workspace root, is
+    /// ignored.
+    pub fn is_ignored(
+        &mut self,
+        path: &Path,
+        is_dir: bool,
+    ) -> bool {
+        let Some(root) = self.root_of(path) else {
+            return false;
+        };
+        let Ok(relative) = path.strip_prefix(&root) else {
+            return false;
+        };
+        let mut ancestor = root.clone();
+        let components: Vec<_> = relative.components().collect();
+        for (index, component) in components.iter().enumerate() {
+            ancestor.push(component);
+            let last = index + 1 == components.len();
+            if self.matches(&root, &ancestor, !last || is_dir) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Whether the rules ignore `path` itself, assuming the directories
+    /// above it are not ignored. This is what a walk that does not descend
+    /// into ignored directories needs.
+    pub fn is_ignored_entry(
+        &mut self,
+        path: &Path,
+        is_dir: bool,
+    ) -> bool {
+        match self.root_of(path) {
+            Some(root) if root != path => self.matches(&root, path, is_dir),
+            _ => false,
+        }
+    }
+
+    /// The innermost workspace root containing `path`.
+    fn root_of(
+        &self,
+        path: &Path,
+    ) -> Option<PathBuf> {
+        self.roots.iter().filter(|root| path.starts_with(root)).max_by_key(|root| root.components().count()).cloned()
+    }
+
+    /// The verdict of the last rule matching `path` across the `.gitignore`
+    /// files from `root` down to its parent directory.
+    fn matches(
+        &mut self,
+        root: &Path,
+        path: &Path,
+        is_dir: bool,
+    ) -> bool {
+        let Some(parent) = path.parent() else {
+            return false;
+        };
+        let mut ignored = false;
+        for dir in parent.ancestors().take_while(|dir| dir.starts_with(root)).collect::<Vec<_>>().into_iter().rev() {
+            let Some(rules) = self.rules_in(dir) else {
+                continue;
+            };
+            let Ok(relative) = path.strip_prefix(dir) else {
+                continue;
+            };
+            let segments = path_segments(relative);
+            let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+            for rule in rules.iter() {
+                if rule.matches(&segments, is_dir) {
+                    ignored = !rule.negated;
+                }
+            }
+        }
+        ignored
+    }
+
+    fn rules_in(
+        &mut self,
+        dir: &Path,
+    ) -> Option<Arc<Vec<Rule>>> {
+        self.files
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                let text = std::fs::read_to_string(dir.join(GITIGNORE_FILE)).ok()?;
+                Some(Arc::new(parse_rules(&text)))
+            })
+            .clone()
+    }
+}
+
+impl Rule {
+    fn matches(
+        &self,
+        path: &[&str],
+        is_dir: bool,
+    ) -> bool {
+        if self.directory_only && !is_dir {
+            return false;
+        }
+        let glob: Vec<&str> = self.segments.iter().map(String::as_str).collect();
+        if self.anchored {
+            segments_match(&glob, path)
+        } else {
+            path.last().is_some_and(|name| segments_match(&glob, &[name]))
+        }
+    }
+}
+
+/// The rules of a `.gitignore` file, skipping blank lines and comments.
+fn parse_rules(text: &str) -> Vec<Rule> {
+    text.lines().filter_map(parse_rule).collect()
+}
+
+fn parse_rule(line: &str) -> Option<Rule> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (negated, pattern) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+    };
+    let directory_only = pattern.ends_with('/');
+    let pattern = pattern.trim_end_matches('/');
+    let anchored = pattern.contains('/');
+    let segments: Vec<String> = pattern.split('/').filter(|segment| !segment.is_empty()).map(str::to_string).collect();
+    (!segments.is_empty()).then_some(Rule {
+        segments,
+        anchored,
+        negated,
+        directory_only,
+    })
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/vfs/gitignore_tests.rs"]
+mod tests;
@@ -0,0 +1,173 @@
+//! Path globs and the exclusion lists built from them.
+//!
+//! `*` matches within a path segment, `?` one character, and `**` any number
+//! of segments. Exclusion patterns follow `.gitignore`: a pattern without a
+//! `/` other than a trailing one, such as `*.gen.metal`, matches a file or
+//! directory name at any depth; one with a `/`, such as `**/generated/**`,
+//! is anchored at each workspace root. Everything below a matched directory
+//! is excluded too. Patterns without wildcards keep naming a path prefix,
+//! resolved from each workspace root when relative.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::vfs::normalized_path;
+
+/// Paths excluded by a list of prefixes and globs.
+#[derive(Debug, Clone, Default)]
+pub struct PathExclusions {
+    prefixes: Vec<PathBuf>,
+    globs: Vec<ExclusionGlob>,
+}
+
+#[derive(Debug, Clone)]
+struct ExclusionGlob {
+    segments: Vec<String>,
+    /// Directories the glob is matched from; empty to match the whole path.
+    roots: Vec<PathBuf>,
+    /// Whether the glob may start at any depth below its roots.
+    floating: bool,
+}
+
+impl PathExclusions {
+    pub fn new(
+        workspace_roots: &[PathBuf],
+        patterns: &[String],
+    ) -> Self {
+        let roots: Vec<PathBuf> = workspace_roots.iter().map(|root| normalized_path(root)).collect();
+        let mut exclusions = Self::default();
+        let mut seen = HashSet::new();
+        for pattern in patterns.iter().map(|pattern| pattern.trim()).filter(|pattern| !pattern.is_empty()) {
+            let path = Path::new(pattern);
+            if !is_glob(pattern) {
+                let prefixes: Vec<PathBuf> = if path.is_absolute() {
+                    vec![normalized_path(path)]
+                } else {
+                    roots.iter().map(|root| normalized_path(&root.join(path))).collect()
+                };
+                for prefix in prefixes {
+                    if seen.insert(prefix.clone()) {
+                        exclusions.prefixes.push(prefix);
+                    }
+                }
+                continue;
+            }
+            let trimmed = pattern.trim_end_matches('/');
+            let mut segments: Vec<String> =
+                trimmed.split('/').filter(|segment| !segment.is_empty()).map(str::to_string).collect();
+            // A matched directory excludes everything below it.
+            segments.push("**".to_string());
+            exclusions.globs.push(ExclusionGlob {
+                segments,
+                roots: if path.is_absolute() {
+                    Vec::new()
+                } else {
+                    roots.clone()
+                },
+                floating: !path.is_absolute() && !trimmed.contains('/'),
+            });
+        }
+        exclusions
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty() && self.globs.is_empty()
+    }
+
+    /// Whether `path` lies below an excluded prefix or matches a glob.
+    pub fn is_excluded(
+        &self,
+        path: &Path,
+    ) -> bool {
+        if self.prefixes.iter().any(|prefix| path.starts_with(prefix)) {
+            return true;
+        }
+        self.globs.iter().any(|glob| glob.matches(path))
+    }
+}
+
+impl ExclusionGlob {
+    fn matches(
+        &self,
+        path: &Path,
+    ) -> bool {
+        let glob: Vec<&str> = self.segments.iter().map(String::as_str).collect();
+        let candidates: Vec<&Path> = if self.roots.is_empty() {
+            vec![path]
+        } else {
+            self.roots.iter().filter_map(|root| path.strip_prefix(root).ok()).collect()
+        };
+        candidates.into_iter().any(|relative| {
+            let relative = path_segments(relative);
+            let path: Vec<&str> = relative.iter().map(String::as_str).collect();
+            if self.floating {
+                (0..path.len()).any(|start| segments_match(&glob, &path[start..]))
+            } else {
+                segments_match(&glob, &path)
+            }
+        })
+    }
+}
+
+/// Whether `pattern` holds a wildcard.
+pub(crate) fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// The non-empty segments of `path`, with `/` and `\` as separators.
+pub(crate) fn path_segments(path: &Path) -> Vec<String> {
+    path.to_string_lossy().split(['/', '\\']).filter(|segment| !segment.is_empty()).map(str::to_string).collect()
+}
+
+/// Match path segments against glob segments, where `**` spans any number
+/// of them.
+pub(crate) fn segments_match(
+    glob: &[&str],
+    path: &[&str],
+) -> bool {
+    match glob.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| segments_match(rest, &path[skip..])),
+        Some((first, rest)) => path
+            .split_first()
+            .is_some_and(|(segment, tail)| segment_matches(first, segment) && segments_match(rest, tail)),
+    }
+}
+
+/// Match one path segment against `*` and `?` wildcards.
+pub(crate) fn segment_matches(
+    glob: &str,
+    segment: &str,
+) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let segment: Vec<char> = segment.chars().collect();
+    let (mut g, mut s) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while s < segment.len() {
+        match glob.get(g) {
+            Some('*') => {
+                backtrack = Some((g, s));
+                g += 1;
+            },
+            Some(&c) if c == '?' || c == segment[s] => {
+                g += 1;
+                s += 1;
+            },
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    g = star + 1;
+                    s = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                },
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/vfs/glob_tests.rs"]
+mod tests;
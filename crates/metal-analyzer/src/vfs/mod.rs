@@ -1,21 +1,44 @@
+pub mod gitignore;
+pub mod glob;
+pub mod virtual_docs;
+pub mod watch;
+
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 use tower_lsp::lsp_types::Url;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
-pub struct FileId(String);
+/// Identity of a file in caches and maps.
+///
+/// Paths are canonicalized, and on platforms with case-insensitive
+/// filesystems two spellings of the same path are the same file. The
+/// spelling the id was created from is kept for display.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct FileId {
+    path: String,
+    /// Case-folded `path`, set only where paths are case-insensitive.
+    folded: Option<String>,
+}
 
 impl FileId {
+    fn new(path: String) -> Self {
+        let folded = CASE_INSENSITIVE_PATHS.then(|| path.to_lowercase());
+        Self {
+            path,
+            folded,
+        }
+    }
+
     pub fn from_path(path: &Path) -> Self {
-        Self(normalized_path(path).display().to_string())
+        Self::new(normalized_path(path).display().to_string())
     }
 
     pub fn from_url(url: &Url) -> Self {
         if let Ok(path) = url.to_file_path() {
             return Self::from_path(&path);
         }
-        Self(url.as_str().to_owned())
+        Self::new(url.as_str().to_owned())
     }
 
     pub fn from_source_path(path: &str) -> Option<Self> {
@@ -26,7 +49,61 @@ impl FileId {
     }
 
     pub fn as_str(&self) -> &str {
-        &self.0
+        &self.path
+    }
+
+    fn key(&self) -> &str {
+        self.folded.as_deref().unwrap_or(&self.path)
+    }
+}
+
+impl PartialEq for FileId {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for FileId {}
+
+impl std::hash::Hash for FileId {
+    fn hash<H: std::hash::Hasher>(
+        &self,
+        state: &mut H,
+    ) {
+        self.key().hash(state);
+    }
+}
+
+impl PartialOrd for FileId {
+    fn partial_cmp(
+        &self,
+        other: &Self,
+    ) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FileId {
+    fn cmp(
+        &self,
+        other: &Self,
+    ) -> std::cmp::Ordering {
+        self.key().cmp(other.key())
+    }
+}
+
+impl From<String> for FileId {
+    fn from(path: String) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<FileId> for String {
+    fn from(id: FileId) -> Self {
+        id.path
     }
 }
 
@@ -39,6 +116,33 @@ impl std::fmt::Display for FileId {
     }
 }
 
+/// Whether paths on this platform's default filesystems ignore case
+/// (APFS and HFS+ on macOS, NTFS on Windows).
+pub const CASE_INSENSITIVE_PATHS: bool = cfg!(any(target_os = "macos", target_os = "windows"));
+
 pub fn normalized_path(path: &Path) -> PathBuf {
     path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
 }
+
+/// Key under which `path` is compared and stored in maps: the normalized
+/// path, case-folded where paths are case-insensitive.
+///
+/// Use it for lookups only; keep the normalized path for display and for
+/// URIs sent to the client.
+pub fn path_key(path: &Path) -> PathBuf {
+    let normalized = normalized_path(path);
+    if CASE_INSENSITIVE_PATHS {
+        fold_case(&normalized)
+    } else {
+        normalized
+    }
+}
+
+/// `path` with every character lowercased.
+pub fn fold_case(path: &Path) -> PathBuf {
+    PathBuf::from(path.to_string_lossy().to_lowercase())
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/vfs/mod_tests.rs"]
+mod tests;
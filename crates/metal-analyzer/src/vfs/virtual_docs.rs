@@ -0,0 +1,70 @@
+//! Stand-in paths for documents that have none, such as `untitled:` buffers
+//! and other virtual documents.
+//!
+//! The compiler and the AST dump name the document by path. A document
+//! without one is compiled from its text under a stand-in path below the
+//! temp directory, derived from its URI and never written, so results can
+//! tell it apart from the headers it includes. Locations in the stand-in
+//! path are mapped back to the virtual URI before they reach the client.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use dashmap::DashMap;
+use tower_lsp::lsp_types::Url;
+
+/// Directory under the system temp directory holding the stand-in paths.
+pub const VIRTUAL_DIR_NAME: &str = "metal-analyzer-virtual";
+
+/// Virtual documents by the stand-in path given to them.
+static VIRTUAL_DOCUMENTS: LazyLock<DashMap<PathBuf, Url>> = LazyLock::new(DashMap::new);
+
+/// The path of the document at `uri`: its file path, or a stand-in path for
+/// a document of another scheme. `None` for a `file:` URI that is not a
+/// valid path.
+pub fn document_path(uri: &Url) -> Option<PathBuf> {
+    if uri.scheme() == "file" {
+        return uri.to_file_path().ok();
+    }
+    let path = virtual_path(uri);
+    VIRTUAL_DOCUMENTS.entry(path.clone()).or_insert_with(|| uri.clone());
+    Some(path)
+}
+
+/// The URI of the document at `path`, the virtual document for a stand-in
+/// path given out by [`document_path`].
+pub fn document_uri(path: &Path) -> Option<Url> {
+    if let Some(uri) = VIRTUAL_DOCUMENTS.get(path) {
+        return Some(uri.clone());
+    }
+    Url::from_file_path(path).ok()
+}
+
+/// The stand-in path of the virtual document at `uri`: a directory named
+/// after the URI holding a file named after its last segment, with a `.metal`
+/// extension unless it already names a Metal source or header.
+fn virtual_path(uri: &Url) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    uri.as_str().hash(&mut hasher);
+    let name = uri
+        .path()
+        .rsplit(['/', '\\'])
+        .next()
+        .map(|segment| segment.replace(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-', "_"))
+        .filter(|name| !name.trim_matches(['.', '_']).is_empty())
+        .unwrap_or_else(|| "untitled".to_string());
+    let extension = Path::new(&name).extension().and_then(|extension| extension.to_str());
+    let name = match extension {
+        Some("metal" | "h" | "hh" | "hpp" | "hxx") => name,
+        _ => format!("{name}.metal"),
+    };
+    std::env::temp_dir().join(VIRTUAL_DIR_NAME).join(format!("{:016x}", hasher.finish())).join(name)
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/vfs/virtual_docs_tests.rs"]
+mod tests;
@@ -0,0 +1,83 @@
+//! Polling change detection for files on disk.
+//!
+//! Backs the CLI's `--watch` modes: a snapshot of modification times is taken
+//! on every tick and diffed against the previous one. Polling needs no
+//! platform-specific notification API and copes with editors that save by
+//! replacing the file.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// A file that appeared, changed or disappeared between two snapshots.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FileChange {
+    Created(PathBuf),
+    Modified(PathBuf),
+    Removed(PathBuf),
+}
+
+impl FileChange {
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Created(path) | Self::Modified(path) | Self::Removed(path) => path,
+        }
+    }
+}
+
+/// Modification times of a set of files at one point in time.
+#[derive(Debug, Clone, Default)]
+pub struct FileSnapshot {
+    mtimes: HashMap<PathBuf, SystemTime>,
+}
+
+impl FileSnapshot {
+    /// Record the modification time of every path that currently exists.
+    pub fn capture(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        let mtimes = paths
+            .into_iter()
+            .filter_map(|path| {
+                let modified = std::fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+                Some((path, modified))
+            })
+            .collect();
+        Self {
+            mtimes,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.mtimes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mtimes.is_empty()
+    }
+
+    /// Changes from `previous` to `self`, sorted by kind and path.
+    pub fn changes_since(
+        &self,
+        previous: &FileSnapshot,
+    ) -> Vec<FileChange> {
+        let mut changes: Vec<FileChange> = self
+            .mtimes
+            .iter()
+            .filter_map(|(path, modified)| match previous.mtimes.get(path) {
+                None => Some(FileChange::Created(path.clone())),
+                Some(before) if before != modified => Some(FileChange::Modified(path.clone())),
+                Some(_) => None,
+            })
+            .collect();
+        changes.extend(
+            previous.mtimes.keys().filter(|path| !self.mtimes.contains_key(*path)).cloned().map(FileChange::Removed),
+        );
+        changes.sort();
+        changes
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src/vfs/watch_tests.rs"]
+mod tests;
@@ -1,5 +1,13 @@
-use metal_analyzer::CompletionProvider;
-use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, Position};
+use std::collections::HashMap;
+
+use metal_analyzer::{
+    CompletionProvider,
+    completion::CompletionSources,
+    definition::{AstIndex, SymbolDef},
+    metal::language_version::LanguageVersion,
+    syntax::SyntaxTree,
+};
+use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, InsertTextFormat, Position};
 
 fn has_label(
     items: &[CompletionItem],
@@ -79,3 +87,242 @@ fn provide_with_none_text_returns_items() {
 
     assert!(!items.is_empty(), "expected non-empty completions");
 }
+
+fn complete_at_marker(source_with_cursor: &str) -> Vec<CompletionItem> {
+    complete_at_marker_with(source_with_cursor, &CompletionSources::default())
+}
+
+fn complete_at_marker_with(
+    source_with_cursor: &str,
+    sources: &CompletionSources<'_>,
+) -> Vec<CompletionItem> {
+    let offset = source_with_cursor.find('|').expect("cursor marker");
+    let text = source_with_cursor.replacen('|', "", 1);
+    let before = &text[..offset];
+    let position = Position {
+        line: before.matches('\n').count() as u32,
+        character: before.rsplit('\n').next().unwrap_or("").encode_utf16().count() as u32,
+    };
+    let tree = SyntaxTree::parse(&text);
+    CompletionProvider::new().provide_with_sources(Some(&text), position, Some(&tree), sources)
+}
+
+#[test]
+fn comments_and_literals_suppress_completion() {
+    for source in [
+        "// license: MIT fl|\nkernel void k() {}",
+        "/* multi\n   line fl| */\n",
+        "kernel void k() { float a = 1.5|; }",
+        "kernel void k() { int a = 12|; }",
+        "constant char* s = \"flo|at\";",
+        "constant char* s = \"unterminated fl|",
+    ] {
+        let items = complete_at_marker(source);
+        assert!(items.is_empty(), "expected no completions for {source:?}, got {} items", items.len());
+    }
+}
+
+#[test]
+fn code_after_closed_comment_still_completes() {
+    let items = complete_at_marker("/* note */ fl|");
+    assert!(has_label(&items, "float"), "expected general completions after a closed block comment");
+}
+
+#[test]
+fn cursor_columns_count_utf16_code_units() {
+    let items = complete_at_marker("/* 😀😀😀 */ fl|// note");
+    assert!(has_label(&items, "float"), "the comment after the cursor should not suppress completion");
+    let items = complete_at_marker("void f(float4 position) { /* é😀 */ position.xy| }");
+    assert!(has_label(&items, "xyz"), "expected swizzles after a non-ASCII comment");
+}
+
+#[test]
+fn digit_separators_do_not_open_char_literals() {
+    let items = complete_at_marker("kernel void k() { int a = 1'000'000; fl| }");
+    assert!(has_label(&items, "float"), "expected completions after a number with digit separators");
+
+    let items = complete_at_marker("kernel void k() { char c = u8'fl|' }");
+    assert!(items.is_empty(), "prefixed char literals still suppress completion");
+}
+
+#[test]
+fn doc_comments_offer_only_tags() {
+    let items = complete_at_marker("/// Computes the sum.\n/// @pa|\nfloat sum(float a);");
+    assert!(has_label(&items, "@param"), "expected @param doc tag");
+    assert!(!has_label(&items, "float"), "doc comments should not offer code completions");
+    let param = items.iter().find(|item| item.label == "@param").unwrap();
+    assert_eq!(param.insert_text.as_deref(), Some("param"), "typed @ marker should not be duplicated");
+
+    let items = complete_at_marker("/** \\br| */");
+    assert!(has_label(&items, "\\brief"), "expected backslash-style doc tag");
+}
+
+#[test]
+fn include_strings_keep_header_completion() {
+    let items = complete_at_marker("#include \"|");
+    assert!(!items.is_empty(), "include paths should still complete inside quotes");
+}
+
+#[test]
+fn member_access_receiver_covers_arrow_and_partial_members() {
+    let items = complete_at_marker("void f(float4 position) { position->xy|; }");
+    assert!(has_label(&items, "xyz"), "expected swizzles after ->");
+    let items = complete_at_marker("void f(float4 position) { tiles[i].position.x| }");
+    assert!(has_label(&items, "xyzw"), "expected swizzles for the last segment of a chain");
+}
+
+#[test]
+fn angle_includes_offer_standard_headers_without_delimiters() {
+    let items = complete_at_marker("#include <metal_|");
+    let stdlib = items.iter().find(|item| item.label == "metal_stdlib").expect("metal_stdlib");
+    assert!(stdlib.insert_text.is_none(), "the open `<` is not repeated");
+    assert!(stdlib.text_edit.is_some());
+}
+
+fn attribute_labels(source_with_cursor: &str) -> Vec<String> {
+    let items = complete_at_marker(source_with_cursor);
+    assert!(items.iter().all(|item| item.kind == Some(CompletionItemKind::PROPERTY)), "only attributes inside [[ ]]");
+    items.into_iter().map(|item| item.label).collect()
+}
+
+#[test]
+fn kernel_parameter_attributes_are_compute_bindings() {
+    let labels = attribute_labels("kernel void k(device float* out [[buffer(0)]], uint gid [[thr|]]) {}");
+    assert!(labels.contains(&"thread_position_in_grid".to_string()));
+    assert!(labels.contains(&"buffer(n)".to_string()));
+    assert!(!labels.contains(&"vertex_id".to_string()));
+    assert!(!labels.contains(&"point_size".to_string()));
+    assert!(!labels.contains(&"early_fragment_tests".to_string()));
+}
+
+#[test]
+fn vertex_parameter_attributes_exclude_compute_inputs() {
+    let labels = attribute_labels("vertex VOut v(uint vid [[|]]) {}");
+    assert!(labels.contains(&"vertex_id".to_string()));
+    assert!(labels.contains(&"stage_in".to_string()));
+    assert!(!labels.contains(&"thread_position_in_grid".to_string()));
+}
+
+#[test]
+fn struct_member_attributes_are_stage_interface_attributes() {
+    let labels = attribute_labels("struct VOut {\n  float4 p [[|]];\n};");
+    assert!(labels.contains(&"position".to_string()));
+    assert!(labels.contains(&"user(name)".to_string()));
+    assert!(!labels.contains(&"buffer(n)".to_string()));
+    assert!(!labels.contains(&"thread_position_in_grid".to_string()));
+}
+
+#[test]
+fn function_and_variable_attributes_are_declaration_qualifiers() {
+    let labels = attribute_labels("[[|]]\nfragment float4 f() { return 0; }");
+    assert!(labels.contains(&"early_fragment_tests".to_string()));
+    assert!(!labels.contains(&"buffer(n)".to_string()));
+
+    let labels = attribute_labels("constant bool use_fast [[|]];");
+    assert_eq!(labels, ["function_constant(n)"]);
+}
+
+#[test]
+fn entry_point_snippets_expand_to_signatures_with_tab_stops() {
+    let items = complete_at_marker("|");
+    for stage in ["kernel", "vertex", "fragment", "mesh", "object"] {
+        let snippet = items
+            .iter()
+            .find(|item| item.label == stage && item.kind == Some(CompletionItemKind::SNIPPET))
+            .unwrap_or_else(|| panic!("missing {stage} snippet"));
+        assert_eq!(snippet.insert_text_format, Some(InsertTextFormat::SNIPPET));
+        assert!(snippet.insert_text.as_deref().is_some_and(|text| text.contains(":name}")), "{stage}");
+    }
+    let kernel = items.iter().find(|item| item.label == "kernel" && item.kind == Some(CompletionItemKind::SNIPPET));
+    let kernel = kernel.and_then(|item| item.insert_text.as_deref()).unwrap();
+    assert!(kernel.contains("[[buffer(${4:0})]]"), "{kernel}");
+    assert!(kernel.contains("${5|uint,uint2,uint3|} ${6:gid} [[thread_position_in_grid]]"), "{kernel}");
+}
+
+#[test]
+fn snippets_are_withheld_when_disabled() {
+    let plain = CompletionSources {
+        snippets: false,
+        ..CompletionSources::default()
+    };
+    let items = complete_at_marker_with("|", &plain);
+    assert!(!items.iter().any(|item| item.kind == Some(CompletionItemKind::SNIPPET)));
+    assert!(has_label(&items, "kernel"), "the keyword is still offered");
+
+    let items = complete_at_marker_with("kernel void k(device float* out [[buf|]]) {}", &plain);
+    let buffer = items.iter().find(|item| item.label == "buffer(n)").unwrap();
+    assert_eq!(buffer.insert_text.as_deref(), Some("buffer"));
+    assert_eq!(buffer.insert_text_format, Some(InsertTextFormat::PLAIN_TEXT));
+}
+
+fn sort_text<'a>(
+    items: &'a [CompletionItem],
+    label: &str,
+) -> &'a str {
+    items.iter().find(|item| item.label == label).and_then(|item| item.sort_text.as_deref()).unwrap()
+}
+
+#[test]
+fn document_symbols_rank_before_project_headers_and_builtins() {
+    let header_def = SymbolDef {
+        id: "0x1".to_string(),
+        name: "shade_light".into(),
+        kind: "FunctionDecl".into(),
+        file: "/project/include/lighting.h".into(),
+        line: 3,
+        col: 8,
+        is_definition: true,
+        type_name: None,
+        qual_type: Some("float3 (float3)".into()),
+        file_local: false,
+    };
+    let index = AstIndex {
+        defs: vec![header_def],
+        refs: Vec::new(),
+        id_to_def: HashMap::new(),
+        name_to_defs: HashMap::from([("shade_light".into(), vec![0])]),
+        target_id_to_refs: HashMap::new(),
+        file_to_defs: HashMap::from([("/project/include/lighting.h".into(), vec![0])]),
+        file_to_refs: HashMap::new(),
+        specializations: Vec::new(),
+    };
+    let sources = CompletionSources {
+        ast: Some((&index, "/project/shaders/main.metal")),
+        ..CompletionSources::default()
+    };
+    let items = complete_at_marker_with("float3 tone_map(float3 c);\nkernel void k() {\n    |\n}\n", &sources);
+
+    assert!(has_label(&items, "shade_light"), "expected project header function");
+    assert!(sort_text(&items, "tone_map") < sort_text(&items, "shade_light"));
+    assert!(sort_text(&items, "shade_light") < sort_text(&items, "float3"));
+}
+
+#[test]
+fn accepted_completions_rank_higher_later_in_the_session() {
+    let source = "kernel void k() {\n    \n}\n";
+    let position = Position::new(1, 4);
+    let tree = SyntaxTree::parse(source);
+    let provider = CompletionProvider::new();
+    let before = provider.provide(Some(source), position, Some(&tree));
+    assert!(sort_text(&before, "half4") > sort_text(&before, "float4"));
+
+    provider.record_selection("half4");
+    let after = provider.provide(Some(source), position, Some(&tree));
+    assert!(sort_text(&after, "half4") < sort_text(&after, "float4"));
+}
+
+#[test]
+fn builtins_newer_than_the_language_version_are_left_out() {
+    let sources = CompletionSources {
+        language_version: Some(LanguageVersion::new(3, 0)),
+        ..CompletionSources::default()
+    };
+    let items = complete_at_marker_with("kernel void k() { bf| }", &sources);
+    assert!(has_label(&items, "float4"));
+    assert!(has_label(&items, "atomic_float"));
+    assert!(!has_label(&items, "bfloat"));
+    assert!(!has_label(&items, "bfloat4"));
+
+    let items = complete_at_marker("kernel void k() { bf| }");
+    assert!(has_label(&items, "bfloat4"));
+}
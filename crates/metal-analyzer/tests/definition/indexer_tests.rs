@@ -1,13 +1,16 @@
 use std::collections::HashMap;
 
-use metal_analyzer::{AstIndex, RefSite, SymbolDef};
+use metal_analyzer::{AstIndex, RefSite, SharedStr, SymbolDef};
 
-fn build_index(defs: Vec<SymbolDef>, refs: Vec<RefSite>) -> AstIndex {
+fn build_index(
+    defs: Vec<SymbolDef>,
+    refs: Vec<RefSite>,
+) -> AstIndex {
     let mut id_to_def = HashMap::new();
-    let mut name_to_defs: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut name_to_defs: HashMap<SharedStr, Vec<usize>> = HashMap::new();
     let mut target_id_to_refs: HashMap<String, Vec<usize>> = HashMap::new();
-    let mut file_to_defs: HashMap<String, Vec<usize>> = HashMap::new();
-    let mut file_to_refs: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut file_to_defs: HashMap<SharedStr, Vec<usize>> = HashMap::new();
+    let mut file_to_refs: HashMap<SharedStr, Vec<usize>> = HashMap::new();
 
     for (i, def) in defs.iter().enumerate() {
         id_to_def.entry(def.id.clone()).or_insert(i);
@@ -16,10 +19,7 @@ fn build_index(defs: Vec<SymbolDef>, refs: Vec<RefSite>) -> AstIndex {
     }
 
     for (i, r) in refs.iter().enumerate() {
-        target_id_to_refs
-            .entry(r.target_id.clone())
-            .or_default()
-            .push(i);
+        target_id_to_refs.entry(r.target_id.clone()).or_default().push(i);
         file_to_refs.entry(r.file.clone()).or_default().push(i);
     }
 
@@ -31,6 +31,7 @@ fn build_index(defs: Vec<SymbolDef>, refs: Vec<RefSite>) -> AstIndex {
         target_id_to_refs,
         file_to_defs,
         file_to_refs,
+        specializations: Vec::new(),
     }
 }
 
@@ -47,6 +48,7 @@ fn get_declarations_returns_only_non_definitions() {
             is_definition: false,
             type_name: None,
             qual_type: None,
+            file_local: false,
         },
         SymbolDef {
             id: "0x2".into(),
@@ -58,6 +60,7 @@ fn get_declarations_returns_only_non_definitions() {
             is_definition: true,
             type_name: None,
             qual_type: None,
+            file_local: false,
         },
     ];
 
@@ -80,17 +83,21 @@ fn get_type_definition_prefers_user_over_system() {
         is_definition: true,
         type_name: None,
         qual_type: None,
+        file_local: false,
     };
     let system_def = SymbolDef {
         id: "0xS".into(),
         name: "MyType".into(),
         kind: "CXXRecordDecl".into(),
-        file: "/Applications/Xcode.app/Contents/Developer/Toolchains/XcodeDefault.xctoolchain/usr/metal/include/my_type".into(),
+        file:
+            "/Applications/Xcode.app/Contents/Developer/Toolchains/XcodeDefault.xctoolchain/usr/metal/include/my_type"
+                .into(),
         line: 42,
         col: 1,
         is_definition: true,
         type_name: None,
         qual_type: None,
+        file_local: false,
     };
     let var_def = SymbolDef {
         id: "0xV".into(),
@@ -102,12 +109,11 @@ fn get_type_definition_prefers_user_over_system() {
         is_definition: true,
         type_name: Some("MyType".into()),
         qual_type: Some("MyType".into()),
+        file_local: false,
     };
 
     let index = build_index(vec![user_def.clone(), system_def, var_def.clone()], vec![]);
-    let ty = index
-        .get_type_definition(&var_def)
-        .expect("type definition");
+    let ty = index.get_type_definition(&var_def).expect("type definition");
 
     assert_eq!(ty.file, user_def.file);
     assert_eq!(ty.name, "MyType");
@@ -125,6 +131,7 @@ fn get_type_definition_prefers_definitions() {
         is_definition: false,
         type_name: None,
         qual_type: None,
+        file_local: false,
     };
     let def = SymbolDef {
         id: "0xF".into(),
@@ -136,6 +143,7 @@ fn get_type_definition_prefers_definitions() {
         is_definition: true,
         type_name: None,
         qual_type: None,
+        file_local: false,
     };
     let var_def = SymbolDef {
         id: "0xV".into(),
@@ -147,12 +155,11 @@ fn get_type_definition_prefers_definitions() {
         is_definition: true,
         type_name: Some("Vec2".into()),
         qual_type: Some("Vec2".into()),
+        file_local: false,
     };
 
     let index = build_index(vec![decl, def.clone(), var_def.clone()], vec![]);
-    let ty = index
-        .get_type_definition(&var_def)
-        .expect("type definition");
+    let ty = index.get_type_definition(&var_def).expect("type definition");
 
     assert!(ty.is_definition);
     assert_eq!(ty.line, def.line);
@@ -250,6 +257,7 @@ fn get_implementations_returns_only_definitions() {
             is_definition: false,
             type_name: None,
             qual_type: None,
+            file_local: false,
         },
         SymbolDef {
             id: "0x2".into(),
@@ -261,6 +269,7 @@ fn get_implementations_returns_only_definitions() {
             is_definition: true,
             type_name: None,
             qual_type: None,
+            file_local: false,
         },
     ];
 
@@ -13,7 +13,7 @@
 
 use std::path::{Path, PathBuf};
 
-use metal_analyzer::{DefinitionProvider, NavigationTarget, syntax::SyntaxTree};
+use metal_analyzer::{DefinitionProvider, NavigationTarget, document::ContentHash, syntax::SyntaxTree};
 use tower_lsp::lsp_types::*;
 
 #[derive(Default, Clone, Debug)]
@@ -81,7 +81,7 @@ fn validate_file(
     let provider = DefinitionProvider::new();
 
     // Pre-index to populate the AST cache.
-    provider.index_document(&uri, &source, include_paths);
+    provider.index_document(&uri, &source, ContentHash::of(&source), include_paths);
 
     let identifiers = extract_identifiers(&source);
     let mut summary = FileValidation {
@@ -101,7 +101,8 @@ fn validate_file(
             character: *col,
         };
 
-        let result = provider.provide(&uri, position, &source, include_paths, &snapshot, || false);
+        let result =
+            provider.provide(&uri, position, &source, ContentHash::of(&source), include_paths, &snapshot, || false);
 
         match result {
             None => {
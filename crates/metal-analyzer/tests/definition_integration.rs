@@ -6,7 +6,7 @@
 
 use std::path::PathBuf;
 
-use metal_analyzer::{DefinitionProvider, IdeLocation, NavigationTarget, syntax::SyntaxTree};
+use metal_analyzer::{DefinitionProvider, IdeLocation, NavigationTarget, document::ContentHash, syntax::SyntaxTree};
 use tower_lsp::lsp_types::{Position, Url};
 
 fn fixtures_dir() -> PathBuf {
@@ -61,7 +61,8 @@ fn goto_def_function_in_same_file() {
         character: 18,
     };
 
-    let result = provider.provide(&uri, position, &source, &include_paths(), &snapshot, || false);
+    let result =
+        provider.provide(&uri, position, &source, ContentHash::of(&source), &include_paths(), &snapshot, || false);
 
     let loc = extract_location(result.expect("expected a definition for 'transform'"));
     assert_eq!(
@@ -88,14 +89,15 @@ fn goto_def_struct_in_included_file() {
     let provider = DefinitionProvider::new();
 
     // Pre-index so we can inspect the AST index.
-    provider.index_document(&uri, &source, &include_paths());
+    provider.index_document(&uri, &source, ContentHash::of(&source), &include_paths());
     // "MyStruct" at line 10, column 11
     let position = Position {
         line: 10,
         character: 11,
     };
 
-    let result = provider.provide(&uri, position, &source, &include_paths(), &snapshot, || false);
+    let result =
+        provider.provide(&uri, position, &source, ContentHash::of(&source), &include_paths(), &snapshot, || false);
 
     let loc = extract_location(result.expect("expected a definition for 'MyStruct'"));
     assert!(
@@ -127,7 +129,8 @@ fn goto_def_struct_cross_file_via_include() {
         character: 19,
     };
 
-    let result = provider.provide(&uri, position, &source, &include_paths(), &snapshot, || false);
+    let result =
+        provider.provide(&uri, position, &source, ContentHash::of(&source), &include_paths(), &snapshot, || false);
 
     let loc = extract_location(result.expect("expected a definition for 'MyParams'"));
     assert!(
@@ -160,7 +163,8 @@ fn goto_def_struct_cross_file_when_cursor_on_pointer_star() {
         character: 27,
     };
 
-    let result = provider.provide(&uri, position, &source, &include_paths(), &snapshot, || false);
+    let result =
+        provider.provide(&uri, position, &source, ContentHash::of(&source), &include_paths(), &snapshot, || false);
 
     let loc = extract_location(result.expect("expected a definition for 'MyParams'"));
     assert!(
@@ -190,7 +194,8 @@ fn goto_def_missing_type_returns_none() {
         character: 19,
     };
 
-    let result = provider.provide(&uri, position, &source, &include_paths(), &snapshot, || false);
+    let result =
+        provider.provide(&uri, position, &source, ContentHash::of(&source), &include_paths(), &snapshot, || false);
 
     assert!(result.is_none(), "should return None for a type from a missing header, got: {result:?}");
 }
@@ -217,7 +222,8 @@ fn goto_def_partial_ast_valid_symbol_resolves() {
         character: 11,
     };
 
-    let result = provider.provide(&uri, position, &source, &include_paths(), &snapshot, || false);
+    let result =
+        provider.provide(&uri, position, &source, ContentHash::of(&source), &include_paths(), &snapshot, || false);
 
     // This may or may not work depending on whether the partial AST
     // contains the included types. If it does, verify correctness.
@@ -251,7 +257,8 @@ fn goto_def_symbol_in_macro_invocation() {
         character: 22,
     };
 
-    let result = provider.provide(&uri, position, &source, &include_paths(), &snapshot, || false);
+    let result =
+        provider.provide(&uri, position, &source, ContentHash::of(&source), &include_paths(), &snapshot, || false);
 
     let loc = extract_location(result.expect("expected definition for 'my_min'"));
     assert_eq!(
@@ -281,7 +288,8 @@ fn goto_def_ambiguous_name_fallback_returns_none() {
         character: 7,
     };
 
-    let result = provider.provide(&uri, position, &source, &include_paths(), &snapshot, || false);
+    let result =
+        provider.provide(&uri, position, &source, ContentHash::of(&source), &include_paths(), &snapshot, || false);
 
     assert!(result.is_none(), "ambiguous fallback should return None, got: {result:?}");
 }
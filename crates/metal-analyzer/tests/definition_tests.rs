@@ -41,6 +41,7 @@ fn def_to_location_converts_to_zero_based() {
         is_definition: true,
         type_name: None,
         qual_type: None,
+        file_local: false,
     };
 
     let loc = def_to_location(&def).expect("expected location");
@@ -219,6 +219,66 @@ async fn open_change_save_suppresses_macro_redefinition_noise() {
     );
 }
 
+#[tokio::test]
+async fn first_change_after_open_keeps_the_compiled_diagnostics() {
+    let (mut service, mut socket) = initialize_service().await;
+    let mut pending_notifications = Vec::new();
+
+    let workspace = temporary_workspace_dir("open-then-change");
+    std::fs::create_dir_all(&workspace).expect("create workspace");
+    let path = workspace.join("broken.metal");
+    let source = "kernel void k(device float* out [[buffer(0)]]) {\n    out[0] = missing_value;\n}\n";
+    std::fs::write(&path, source).expect("write shader");
+    let uri = Url::from_file_path(&path).expect("shader URI");
+
+    send_notification(
+        &mut service,
+        &mut socket,
+        &mut pending_notifications,
+        "textDocument/didOpen",
+        DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "metal".to_owned(),
+                version: 1,
+                text: source.to_owned(),
+            },
+        },
+    )
+    .await;
+    let open_diags = next_publish_for_uri(&mut socket, &mut pending_notifications, &uri).await;
+    assert!(!open_diags.diagnostics.is_empty(), "didOpen should publish compiler diagnostics");
+
+    send_notification(
+        &mut service,
+        &mut socket,
+        &mut pending_notifications,
+        "textDocument/didChange",
+        DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier {
+                uri: uri.clone(),
+                version: 2,
+            },
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: format!("{source}// edited\n"),
+            }],
+        },
+    )
+    .await;
+    let change_diags = next_publish_for_uri(&mut socket, &mut pending_notifications, &uri).await;
+    std::fs::remove_dir_all(&workspace).ok();
+    for diagnostic in &open_diags.diagnostics {
+        assert!(
+            change_diags.diagnostics.iter().any(|changed| changed.message == diagnostic.message),
+            "the first publish after didChange should keep {:?}: {:?}",
+            diagnostic.message,
+            change_diags.diagnostics
+        );
+    }
+}
+
 #[tokio::test]
 async fn header_open_and_change_use_owner_context_diagnostics() {
     let (mut service, mut socket) = initialize_service().await;
@@ -5,6 +5,7 @@ use common::{
 };
 use metal_analyzer::{
     DefinitionProvider, IdeLocation, NavigationTarget,
+    document::ContentHash,
     metal::compiler::{MetalCompiler, MetalDiagnostic},
     syntax::SyntaxTree,
 };
@@ -54,7 +55,7 @@ fn goto_def_include_resolves_generated_header() {
 
     let position = position_of(&source, "../../../generated/matmul.h");
     let result = provider
-        .provide(&uri, position, &source, &include_paths, &snapshot, || false)
+        .provide(&uri, position, &source, ContentHash::of(&source), &include_paths, &snapshot, || false)
         .expect("expected include go-to-definition result");
     let target = first_location(result);
 
@@ -79,7 +80,7 @@ fn goto_def_prefers_qualified_fixture_transform() {
 
     let position = position_of(&source, "../../common/transforms.h");
     let result = provider
-        .provide(&uri, position, &source, &include_paths, &snapshot, || false)
+        .provide(&uri, position, &source, ContentHash::of(&source), &include_paths, &snapshot, || false)
         .expect("expected include resolution for fixture transforms header");
     let target = first_location(result);
 
@@ -104,7 +105,7 @@ fn goto_def_prefers_qualified_steel_transform() {
 
     let position = position_of(&source, "../../common/steel/gemm/transforms.h");
     let result = provider
-        .provide(&uri, position, &source, &include_paths, &snapshot, || false)
+        .provide(&uri, position, &source, ContentHash::of(&source), &include_paths, &snapshot, || false)
         .expect("expected include resolution for steel transforms header");
     let target = first_location(result);
 
@@ -129,7 +130,7 @@ fn goto_def_prefers_qualified_fixture_loader() {
 
     let position = position_of(&source, "../../common/loader.h");
     let result = provider
-        .provide(&uri, position, &source, &include_paths, &snapshot, || false)
+        .provide(&uri, position, &source, ContentHash::of(&source), &include_paths, &snapshot, || false)
         .expect("expected include resolution for fixture loader header");
     let target = first_location(result);
 
@@ -155,7 +156,7 @@ fn goto_def_prefers_qualified_steel_loader() {
 
     let position = position_of(&source, "../../common/steel/gemm/loader.h");
     let result = provider
-        .provide(&uri, position, &source, &include_paths, &snapshot, || false)
+        .provide(&uri, position, &source, ContentHash::of(&source), &include_paths, &snapshot, || false)
         .expect("expected include resolution for steel loader header");
     let target = first_location(result);
 
@@ -181,7 +182,7 @@ fn goto_def_unqualified_loader_resolves_local_header() {
 
     let position = position_of(&source, "\"loader.h\"");
     let result = provider
-        .provide(&uri, position, &source, &include_paths, &snapshot, || false)
+        .provide(&uri, position, &source, ContentHash::of(&source), &include_paths, &snapshot, || false)
         .expect("expected include resolution for unqualified loader.h");
     let target = first_location(result);
 
@@ -206,7 +207,7 @@ fn goto_def_overloaded_symbol_resolves_function_definition() {
 
     let position = position_of(&source, "local_template(sum.re)");
     let result = provider
-        .provide(&uri, position, &source, &include_paths, &snapshot, || false)
+        .provide(&uri, position, &source, ContentHash::of(&source), &include_paths, &snapshot, || false)
         .expect("expected definition for local_template call");
     let target = first_location(result);
 
@@ -235,7 +236,7 @@ struct Kernel {
 
     let position = position_of(&source, "BN > 1 ? BN * (BM + TM) : 0");
     let result = provider
-        .provide(&uri, position, &source, &include_paths, &snapshot, || false)
+        .provide(&uri, position, &source, ContentHash::of(&source), &include_paths, &snapshot, || false)
         .expect("expected definition for template parameter BN");
     let target = first_location(result);
 
@@ -324,7 +325,7 @@ fn ast_dump_does_not_create_source_sidecar_files() {
     let uri = fixture_uri(rel);
     let source = read_fixture(rel);
     let include_paths = include_paths_for(rel);
-    provider.index_document(&uri, &source, &include_paths);
+    provider.index_document(&uri, &source, ContentHash::of(&source), &include_paths);
     let source_dir = fixture_path(rel).parent().expect("fixture should have parent directory").to_path_buf();
 
     assert!(
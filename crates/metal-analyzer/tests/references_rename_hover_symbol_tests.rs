@@ -3,7 +3,11 @@ mod common;
 use std::sync::Arc;
 
 use common::{fixture_path, fixture_uri, has_metal_compiler, include_paths_for, position_of, read_fixture};
-use metal_analyzer::{DefinitionProvider, HoverProvider, SymbolProvider, syntax::SyntaxTree};
+use metal_analyzer::{
+    DefinitionProvider, HoverProvider, SymbolProvider,
+    document::{AnalysisSnapshot, ContentHash},
+    syntax::SyntaxTree,
+};
 use tower_lsp::lsp_types::{HoverContents, MarkedString};
 
 fn marked_string_text(marked: &MarkedString) -> String {
@@ -41,14 +45,18 @@ fn references_include_cross_file_uses_for_shared_symbol() {
     );
 
     let source = read_fixture(file_a);
-    let uri = fixture_uri(file_a);
     let include_paths = include_paths_for(file_a);
-    let snapshot = SyntaxTree::parse(&source);
     let mut position = position_of(&source, "fixture::shared_mul");
     position.character += "fixture::".len() as u32;
+    let document = AnalysisSnapshot {
+        uri: fixture_uri(file_a),
+        hash: ContentHash::of(&source),
+        tree: SyntaxTree::parse(&source),
+        text: source,
+    };
 
     let refs = provider
-        .provide_references(&uri, position, &source, &include_paths, &snapshot, true)
+        .provide_references(&document, position, &include_paths, true)
         .expect("expected references for shared_mul");
     let paths: Vec<String> = refs.iter().map(|loc| loc.file_path.to_string_lossy().to_string()).collect();
 
@@ -78,11 +86,12 @@ fn prepare_rename_allows_project_symbol() {
     let uri = fixture_uri(rel);
     let snapshot = SyntaxTree::parse(&source);
     let include_paths = include_paths_for(rel);
-    provider.index_document(&uri, &source, &include_paths);
+    provider.index_document(&uri, &source, ContentHash::of(&source), &include_paths);
 
     let mut position = position_of(&source, "fixture::shared_mul");
     position.character += "fixture::".len() as u32;
-    let rename_range = provider.prepare_rename(&uri, position, &source, &include_paths, &snapshot);
+    let rename_range =
+        provider.prepare_rename(&uri, position, &source, ContentHash::of(&source), &include_paths, &snapshot);
 
     assert!(rename_range.is_some(), "expected rename range for shared_mul");
 }
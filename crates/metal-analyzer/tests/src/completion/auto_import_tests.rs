@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use tower_lsp::lsp_types::{Position, Range, TextEdit};
+
+use super::*;
+use crate::completion::{CompletionProvider, CompletionSources};
+
+fn def(
+    name: &str,
+    file: &str,
+) -> SymbolDef {
+    SymbolDef {
+        id: format!("{name}-1"),
+        name: name.into(),
+        kind: "FunctionDecl".into(),
+        file: file.into(),
+        line: 3,
+        col: 1,
+        is_definition: true,
+        type_name: None,
+        qual_type: Some("float (float)".into()),
+        file_local: false,
+    }
+}
+
+fn index(files: &[&str]) -> AstIndex {
+    AstIndex {
+        defs: Vec::new(),
+        refs: Vec::new(),
+        id_to_def: HashMap::new(),
+        name_to_defs: HashMap::new(),
+        target_id_to_refs: HashMap::new(),
+        file_to_defs: files.iter().map(|file| ((*file).into(), Vec::new())).collect(),
+        file_to_refs: HashMap::new(),
+        specializations: Vec::new(),
+    }
+}
+
+#[test]
+fn spelling_is_the_shortest_path_from_a_search_directory() {
+    let dirs = [PathBuf::from("/ws/shaders"), PathBuf::from("/ws/shaders/common")];
+    assert_eq!(include_spelling(Path::new("/ws/shaders/common/math.h"), &dirs).as_deref(), Some("math.h"));
+    assert_eq!(include_spelling(Path::new("/ws/shaders/lights/spot.h"), &dirs).as_deref(), Some("lights/spot.h"));
+    assert_eq!(include_spelling(Path::new("/elsewhere/x.h"), &dirs), None);
+}
+
+#[test]
+fn only_headers_the_translation_unit_does_not_reach_are_offered() {
+    let dirs = [PathBuf::from("/ws")];
+    let candidates = vec![
+        def("square", "/ws/math.h"),
+        def("tint", "/ws/colors.h"),
+        def("local", "/ws/main.metal"),
+        def("outside", "/other/far.h"),
+    ];
+    let imports = auto_imports(candidates, &index(&["/ws/math.h", "/ws/main.metal"]), "/ws/main.metal", &dirs);
+    let offered: Vec<(&str, &str)> =
+        imports.iter().map(|import| (import.def.name.as_str(), import.spelling.as_str())).collect();
+    assert_eq!(offered, vec![("tint", "colors.h")]);
+}
+
+#[test]
+fn includes_go_after_existing_includes_or_the_guard() {
+    assert_eq!(include_insertion_line("#include <metal_stdlib>\n#include \"a.h\"\n\nkernel void k() {}\n"), 2);
+    assert_eq!(include_insertion_line("// Lights.\n#ifndef LIGHT_H\n#define LIGHT_H\nstruct L {};\n#endif\n"), 3);
+    assert_eq!(include_insertion_line("// Lights.\n#pragma once\n\nstruct L {};\n"), 3);
+}
+
+#[test]
+fn auto_import_items_insert_their_include() {
+    let text = "#include <metal_stdlib>\n\nkernel void k() {\n    \n}\n";
+    let import = AutoImport {
+        def: def("tint", "/ws/colors.h"),
+        spelling: "colors.h".to_string(),
+    };
+    let sources = CompletionSources {
+        ast: Some((&index(&[]), "/ws/main.metal")),
+        auto_imports: vec![import],
+        ..Default::default()
+    };
+    let items = CompletionProvider::new().provide_with_sources(Some(text), Position::new(3, 4), None, &sources);
+    let tint = items.into_iter().find(|item| item.label == "tint").expect("tint is offered");
+
+    assert_eq!(tint.label_details.and_then(|details| details.description).as_deref(), Some("auto-import"));
+    assert_eq!(tint.detail.as_deref(), Some("float (float) (#include \"colors.h\")"));
+    assert_eq!(
+        tint.additional_text_edits,
+        Some(vec![TextEdit::new(
+            Range::new(Position::new(1, 0), Position::new(1, 0)),
+            "#include \"colors.h\"\n".to_string()
+        )])
+    );
+}
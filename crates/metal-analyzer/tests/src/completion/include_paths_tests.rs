@@ -0,0 +1,116 @@
+use std::{
+    fs,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::*;
+
+/// Create a unique temporary directory for each test.
+fn test_root() -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("include_paths_test_{}_{id}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn directive(line: &str) -> Option<IncludeDirective> {
+    include_directive_at(line, Position::new(0, line.encode_utf16().count() as u32))
+}
+
+fn new_text(item: &CompletionItem) -> &str {
+    match item.text_edit.as_ref() {
+        Some(CompletionTextEdit::Edit(edit)) => &edit.new_text,
+        _ => panic!("expected a text edit"),
+    }
+}
+
+#[test]
+fn directive_splits_typed_directory_and_fragment() {
+    let quoted = directive("  #include \"shaders/common/li").unwrap();
+    assert_eq!(quoted.delimiter, Some('"'));
+    assert_eq!(quoted.directory, "shaders/common/");
+    assert_eq!(quoted.fragment, "li");
+    assert_eq!(quoted.fragment_range, Range::new(Position::new(0, 27), Position::new(0, 29)));
+
+    let angled = directive("#import <metal_").unwrap();
+    assert_eq!(angled.delimiter, Some('<'));
+    assert_eq!(angled.directory, "");
+    assert_eq!(angled.fragment, "metal_");
+
+    assert_eq!(directive("#include ").unwrap().delimiter, None);
+}
+
+#[test]
+fn directive_columns_count_utf16_code_units() {
+    let line = "#include \"textures/😀/bl\" // closed later";
+    let end = "#include \"textures/😀/bl".encode_utf16().count() as u32;
+    let typed = include_directive_at(line, Position::new(0, end)).unwrap();
+    assert_eq!(typed.directory, "textures/😀/");
+    assert_eq!(typed.fragment, "bl");
+    assert_eq!(typed.fragment_range, Range::new(Position::new(0, end - 2), Position::new(0, end)));
+
+    let typed = directive("#include \"é😀").unwrap();
+    assert_eq!(typed.fragment_range, Range::new(Position::new(0, 10), Position::new(0, 13)));
+}
+
+#[test]
+fn closed_or_unrelated_lines_are_not_directives() {
+    assert!(directive("#include \"common.h\"").is_none());
+    assert!(directive("#include <metal_stdlib>").is_none());
+    assert!(directive("#includes \"x").is_none());
+    assert!(directive("#define X \"y").is_none());
+    assert!(directive("float x = 1;").is_none());
+}
+
+#[test]
+fn completes_headers_and_directories_from_search_dirs_in_order() {
+    let root = test_root();
+    let local = root.join("local");
+    let project = root.join("project");
+    fs::create_dir_all(local.join("common")).unwrap();
+    fs::create_dir_all(project.join("common")).unwrap();
+    fs::write(local.join("lighting.h"), "").unwrap();
+    fs::write(local.join("notes.txt"), "").unwrap();
+    fs::write(local.join(".hidden.h"), "").unwrap();
+    fs::write(project.join("lighting.h"), "").unwrap();
+    fs::write(project.join("layout.hpp"), "").unwrap();
+
+    let cache = IncludeListingCache::default();
+    let items =
+        include_path_completions(&directive("#include \"l").unwrap(), &[local.clone(), project.clone()], &cache);
+    let labels: Vec<&str> = items.iter().map(|item| item.label.as_str()).collect();
+    assert_eq!(labels, ["lighting.h", "layout.hpp"]);
+    assert_eq!(items[0].detail.as_deref(), Some(local.join("lighting.h").display().to_string().as_str()));
+
+    let items = include_path_completions(&directive("#include \"").unwrap(), &[local.clone(), project], &cache);
+    let common = items.iter().find(|item| item.label == "common").unwrap();
+    assert_eq!(common.kind, Some(CompletionItemKind::FOLDER));
+    assert_eq!(new_text(common), "common/");
+    assert!(common.command.is_some(), "directories re-trigger completion");
+    assert!(!items.iter().any(|item| item.label == "notes.txt" || item.label == ".hidden.h"));
+    assert_eq!(items.iter().filter(|item| item.label == "common").count(), 1);
+
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn narrows_into_subdirectories_and_caches_listings() {
+    let root = test_root();
+    fs::create_dir_all(root.join("common")).unwrap();
+    fs::write(root.join("common/math.h"), "").unwrap();
+
+    let cache = IncludeListingCache::default();
+    let nested = directive("#include \"common/m").unwrap();
+    let items = include_path_completions(&nested, std::slice::from_ref(&root), &cache);
+    assert_eq!(items.len(), 1);
+    assert_eq!(new_text(&items[0]), "math.h");
+
+    fs::write(root.join("common/matrix.h"), "").unwrap();
+    assert_eq!(include_path_completions(&nested, std::slice::from_ref(&root), &cache).len(), 1);
+    cache.clear();
+    assert_eq!(include_path_completions(&nested, std::slice::from_ref(&root), &cache).len(), 2);
+
+    let _ = fs::remove_dir_all(&root);
+}
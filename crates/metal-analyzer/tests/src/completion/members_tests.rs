@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use super::*;
+use crate::definition::{SharedStr, SymbolDef};
+
+const SOURCE_FILE: &str = "/tmp/lighting.metal";
+
+fn def(
+    name: &str,
+    kind: &str,
+    line: u32,
+    qual_type: Option<&str>,
+) -> SymbolDef {
+    SymbolDef {
+        id: format!("{kind}-{name}-{line}"),
+        name: name.into(),
+        kind: kind.into(),
+        file: SOURCE_FILE.into(),
+        line,
+        col: 5,
+        is_definition: true,
+        type_name: qual_type
+            .filter(|_| matches!(kind, "VarDecl" | "FieldDecl" | "ParmVarDecl"))
+            .and_then(normalize_type_name),
+        qual_type: qual_type.map(Into::into),
+        file_local: false,
+    }
+}
+
+fn index(defs: Vec<SymbolDef>) -> AstIndex {
+    let mut name_to_defs: HashMap<SharedStr, Vec<usize>> = HashMap::new();
+    let mut file_to_defs: HashMap<SharedStr, Vec<usize>> = HashMap::new();
+    for (i, def) in defs.iter().enumerate() {
+        name_to_defs.entry(def.name.clone()).or_default().push(i);
+        file_to_defs.entry(def.file.clone()).or_default().push(i);
+    }
+    AstIndex {
+        defs,
+        refs: Vec::new(),
+        id_to_def: HashMap::new(),
+        name_to_defs,
+        target_id_to_refs: HashMap::new(),
+        file_to_defs,
+        file_to_refs: HashMap::new(),
+        specializations: Vec::new(),
+    }
+}
+
+/// struct Light { float3 color; float intensity; float3 shade(float3 n) const; };
+/// typedef Light LightAlias;
+/// struct Scene { Light key; device Light* fill; };
+/// kernel void k(constant Scene& scene, LightAlias light) { ... }
+fn lighting_index() -> AstIndex {
+    index(vec![
+        def("Light", "CXXRecordDecl", 1, None),
+        def("color", "FieldDecl", 2, Some("float3")),
+        def("intensity", "FieldDecl", 3, Some("float")),
+        def("shade", "CXXMethodDecl", 4, Some("float3 (float3) const")),
+        def("operator==", "CXXMethodDecl", 5, Some("bool (const Light &) const")),
+        def("LightAlias", "TypedefDecl", 7, Some("struct Light")),
+        def("Scene", "CXXRecordDecl", 8, None),
+        def("key", "FieldDecl", 9, Some("Light")),
+        def("fill", "FieldDecl", 10, Some("device Light *")),
+        def("k", "FunctionDecl", 12, Some("void (constant Scene &, LightAlias)")),
+        def("scene", "ParmVarDecl", 12, Some("constant Scene &")),
+        def("light", "ParmVarDecl", 12, Some("LightAlias")),
+    ])
+}
+
+fn labels(items: &[CompletionItem]) -> Vec<&str> {
+    let mut labels: Vec<&str> = items.iter().map(|item| item.label.as_str()).collect();
+    labels.sort_unstable();
+    labels
+}
+
+#[test]
+fn typedef_receiver_offers_fields_and_methods_with_types() {
+    let index = lighting_index();
+    let items = ast_member_completions(&index, SOURCE_FILE, Position::new(13, 10), "light");
+    assert_eq!(labels(&items), ["color", "intensity", "shade"]);
+
+    let shade = items.iter().find(|item| item.label == "shade").unwrap();
+    assert_eq!(shade.kind, Some(CompletionItemKind::METHOD));
+    assert_eq!(shade.detail.as_deref(), Some("float3 (float3) const"));
+    let color = items.iter().find(|item| item.label == "color").unwrap();
+    assert_eq!(color.kind, Some(CompletionItemKind::FIELD));
+    assert_eq!(color.detail.as_deref(), Some("float3"));
+}
+
+#[test]
+fn member_chains_follow_field_types_through_pointers_and_subscripts() {
+    let index = lighting_index();
+    assert_eq!(labels(&ast_member_completions(&index, SOURCE_FILE, Position::new(13, 10), "scene")), ["fill", "key"]);
+    let through_field = ast_member_completions(&index, SOURCE_FILE, Position::new(13, 10), "scene.key");
+    assert_eq!(labels(&through_field), ["color", "intensity", "shade"]);
+    let scalar_field = ast_member_completions(&index, SOURCE_FILE, Position::new(13, 10), "scene.fill[0]->color");
+    assert!(scalar_field.is_empty(), "float3 is not a record");
+    let through_arrow = ast_member_completions(&index, SOURCE_FILE, Position::new(13, 10), "scene.fill");
+    assert_eq!(labels(&through_arrow), ["color", "intensity", "shade"]);
+}
+
+#[test]
+fn this_resolves_to_the_enclosing_record() {
+    let index = lighting_index();
+    let items = ast_member_completions(&index, SOURCE_FILE, Position::new(3, 8), "this");
+    assert_eq!(labels(&items), ["color", "intensity", "shade"]);
+}
+
+#[test]
+fn unknown_or_later_declared_receivers_offer_nothing() {
+    let index = lighting_index();
+    assert!(ast_member_completions(&index, SOURCE_FILE, Position::new(13, 10), "missing").is_empty());
+    assert!(ast_member_completions(&index, SOURCE_FILE, Position::new(5, 0), "light").is_empty());
+    assert!(ast_member_completions(&index, "/tmp/other.metal", Position::new(13, 10), "light").is_empty());
+}
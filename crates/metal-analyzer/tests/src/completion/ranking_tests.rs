@@ -0,0 +1,72 @@
+use tower_lsp::lsp_types::CompletionItem;
+
+use super::*;
+
+fn item(label: &str) -> CompletionItem {
+    CompletionItem {
+        label: label.to_string(),
+        sort_text: Some(format!("1_{label}")),
+        ..Default::default()
+    }
+}
+
+fn sort_text<'a>(
+    items: &'a [CompletionItem],
+    label: &str,
+) -> &'a str {
+    items.iter().find(|item| item.label == label).and_then(|item| item.sort_text.as_deref()).unwrap()
+}
+
+#[test]
+fn locality_orders_tiers_before_usage() {
+    let text = "float4 a = b + b + b;\n";
+    let items = rank(
+        vec![(Locality::Builtin, item("b")), (Locality::ProjectHeader, item("c")), (Locality::CurrentFile, item("d"))],
+        text,
+        Position::new(1, 0),
+        &SelectionHistory::default(),
+    );
+    assert!(sort_text(&items, "d") < sort_text(&items, "c"));
+    assert!(sort_text(&items, "c") < sort_text(&items, "b"));
+}
+
+#[test]
+fn nearby_uses_and_selections_boost_within_a_tier() {
+    let text = "float x = lerp(y, y, t);\nx = \n";
+    let history = SelectionHistory::default();
+    let ranked = |history: &SelectionHistory| {
+        rank(
+            vec![
+                (Locality::CurrentFile, item("y")),
+                (Locality::CurrentFile, item("t")),
+                (Locality::CurrentFile, item("z")),
+            ],
+            text,
+            Position::new(1, 4),
+            history,
+        )
+    };
+
+    let items = ranked(&history);
+    assert!(sort_text(&items, "y") < sort_text(&items, "t"), "two nearby uses beat one");
+    assert!(sort_text(&items, "t") < sort_text(&items, "z"), "a nearby use beats none");
+
+    history.record("z");
+    let items = ranked(&history);
+    assert!(sort_text(&items, "z") < sort_text(&items, "y"), "an accepted item outranks nearby uses");
+}
+
+#[test]
+fn identifier_being_typed_is_not_a_nearby_use() {
+    let counts = nearby_identifiers("float value;\nval\n", Position::new(1, 3));
+    assert_eq!(counts.get("value"), Some(&1));
+    assert_eq!(counts.get("val"), None);
+}
+
+#[test]
+fn ranked_items_record_their_selection() {
+    let items = rank(vec![(Locality::Builtin, item("float4"))], "", Position::new(0, 0), &SelectionHistory::default());
+    let command = items[0].command.as_ref().unwrap();
+    assert_eq!(command.command, RECORD_COMPLETION_COMMAND);
+    assert_eq!(command.arguments, Some(vec!["float4".into()]));
+}
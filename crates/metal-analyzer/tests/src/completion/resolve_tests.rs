@@ -0,0 +1,94 @@
+use super::*;
+use crate::completion::CompletionProvider;
+
+fn documentation(item: &CompletionItem) -> Option<&str> {
+    match item.documentation.as_ref()? {
+        Documentation::MarkupContent(content) => Some(&content.value),
+        Documentation::String(value) => Some(value),
+    }
+}
+
+#[test]
+fn completion_data_round_trips_through_json() {
+    let data = CompletionData::Builtin {
+        index: 3,
+        include_line: Some(2),
+    };
+    let value = data.to_value().unwrap();
+    assert_eq!(value, serde_json::json!({ "source": "builtin", "index": 3, "includeLine": 2 }));
+    let item = CompletionItem {
+        data: Some(value),
+        ..Default::default()
+    };
+    assert_eq!(CompletionData::of(&item), Some(data));
+}
+
+#[test]
+fn builtin_items_are_documented_on_resolve() {
+    let provider = CompletionProvider::new();
+    let text = "#include <metal_stdlib>\nkernel void k() {\n    \n}\n";
+    let items = provider.provide(Some(text), Position::new(2, 4), None);
+    let clamp = items.into_iter().find(|item| item.label == "clamp").expect("clamp is offered");
+    assert!(clamp.documentation.is_none());
+
+    let resolved = resolve_completion(clamp, None);
+    assert!(documentation(&resolved).is_some_and(|docs| !docs.is_empty()));
+    assert!(resolved.additional_text_edits.is_none());
+}
+
+#[test]
+fn builtins_insert_metal_stdlib_into_documents_without_includes() {
+    let provider = CompletionProvider::new();
+    let text = "// Blur pass.\n\nkernel void k() {\n    \n}\n";
+    let items = provider.provide(Some(text), Position::new(3, 4), None);
+    let clamp = items.into_iter().find(|item| item.label == "clamp").expect("clamp is offered");
+
+    let resolved = resolve_completion(clamp, None);
+    assert_eq!(
+        resolved.additional_text_edits,
+        Some(vec![TextEdit::new(
+            Range::new(Position::new(2, 0), Position::new(2, 0)),
+            "#include <metal_stdlib>\n".to_string()
+        )])
+    );
+}
+
+#[test]
+fn include_line_follows_leading_comments_and_pragma_once() {
+    assert_eq!(builtin_include_line("#pragma once\n/* a\n b */\n\nstruct A {};\n"), Some(4));
+    assert_eq!(builtin_include_line("struct A {};\n"), Some(0));
+    assert_eq!(builtin_include_line(""), Some(0));
+    assert_eq!(builtin_include_line("#include \"a.h\"\n"), None);
+    assert_eq!(builtin_include_line("#  include <metal_stdlib>\n"), None);
+}
+
+#[test]
+fn project_symbols_resolve_to_their_declaration_and_docs() {
+    let source =
+        "#pragma once\n\n/// Squares `x`.\n/// Never negative.\ninline float square(float x) { return x * x; }\n";
+    let item = CompletionItem {
+        label: "square".to_string(),
+        data: CompletionData::ProjectSymbol {
+            file: "/p/math.h".to_string(),
+            line: 5,
+        }
+        .to_value(),
+        ..Default::default()
+    };
+    assert_eq!(completion_source_file(&item).as_deref(), Some("/p/math.h"));
+
+    let resolved = resolve_completion(item, Some(source));
+    assert_eq!(
+        documentation(&resolved),
+        Some("```metal\ninline float square(float x) { return x * x; }\n```\n\n---\n\nSquares `x`.\nNever negative.\n")
+    );
+}
+
+#[test]
+fn items_without_data_are_unchanged() {
+    let item = CompletionItem {
+        label: "x".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(resolve_completion(item.clone(), None), item);
+}
@@ -0,0 +1,28 @@
+use super::*;
+
+#[test]
+fn json_schema_nests_settings_by_section() {
+    let schema = generate_json_schema();
+    assert_eq!(schema["type"], "object");
+
+    let enable = &schema["properties"]["formatting"]["properties"]["enable"];
+    assert_eq!(enable["type"], "boolean");
+    assert_eq!(enable["default"], true);
+    assert_eq!(enable["description"], "Enable LSP-backed document formatting.");
+
+    let ranking = &schema["properties"]["navigation"]["properties"]["ranking"];
+    assert_eq!(ranking["type"], "object");
+    assert_eq!(ranking["properties"]["otherFile"]["maximum"], MAX_RANKING_WEIGHT as i64);
+}
+
+#[test]
+fn json_schema_covers_every_field() {
+    let schema = generate_json_schema();
+    for field in schema_fields() {
+        let mut node = &schema;
+        for segment in field.key.split('.') {
+            node = &node["properties"][segment];
+        }
+        assert_eq!(node["default"], field.default, "{}", field.key);
+    }
+}
@@ -0,0 +1,70 @@
+use serde_json::json;
+
+use super::*;
+use crate::config::{MAX_INDEXING_CONCURRENCY, MIN_INDEXING_CONCURRENCY};
+
+#[test]
+fn valid_payloads_have_no_problems() {
+    let payload = json!({
+        "metal-analyzer": {
+            "serverPath": "metal-analyzer",
+            "formatting": { "enable": false, "engine": "internal" },
+            "diagnostics": { "onType": null, "debounceMs": 800 },
+            "compiler": {
+                "platform": " iOS ",
+                "languageVersion": "metal3.1",
+                "overrides": [{ "pathGlob": "ios/**", "flags": ["-DIOS"] }],
+                "virtualIncludeRoots": { "generated": "build/codegen" }
+            },
+            "navigation": { "ranking": { "otherFile": 10 } }
+        }
+    });
+    assert_eq!(validate_settings_payload(&payload), Vec::new());
+}
+
+#[test]
+fn unknown_keys_suggest_the_nearest_setting() {
+    let payload = json!({
+        "diagnostcs": { "onType": true },
+        "diagnostics": { "onTipe": true, "bogus": 1 },
+        "$schema": "./schema.json"
+    });
+    let problems = validate_settings_payload(&payload);
+    let messages: Vec<String> = problems.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "`diagnostcs` is not a setting; did you mean `diagnostics`?",
+            "`diagnostics.bogus` is not a setting",
+            "`diagnostics.onTipe` is not a setting; did you mean `diagnostics.onType`?",
+        ]
+    );
+}
+
+#[test]
+fn wrong_types_and_out_of_range_values_are_reported() {
+    let payload = json!({
+        "formatting": { "enable": "yes", "engine": "prettier" },
+        "indexing": { "concurrency": 0, "excludePaths": "Vendor" },
+        "diagnostics": { "debounceMs": 1.5 },
+        "compiler": { "virtualIncludeRoots": { "generated": ["build"] } },
+        "navigation": "fast"
+    });
+    let problems = validate_settings_payload(&payload);
+    let messages: Vec<String> = problems.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "`compiler.virtualIncludeRoots` should be an object of strings",
+            "`diagnostics.debounceMs` should be a whole number",
+            "`formatting.enable` should be `true` or `false`",
+            "`formatting.engine` should be one of `auto`, `clangFormat`, `internal`",
+            format!(
+                "`indexing.concurrency` should be between {MIN_INDEXING_CONCURRENCY} and {MAX_INDEXING_CONCURRENCY}"
+            )
+            .as_str(),
+            "`indexing.excludePaths` should be an array of strings",
+            "`navigation` should be an object",
+        ]
+    );
+}
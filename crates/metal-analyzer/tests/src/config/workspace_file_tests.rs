@@ -0,0 +1,86 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use serde_json::json;
+
+use super::*;
+
+/// Create a unique temporary workspace root for each test.
+fn test_root() -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("workspace_settings_test_{}_{id}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn reads_file_and_resolves_relative_paths_against_root() {
+    let root = test_root();
+    fs::write(
+        root.join(WORKSPACE_SETTINGS_FILE),
+        r#"{ "compiler": { "includePaths": ["Shaders/Include", "/opt/include"], "tempDir": ".cache" } }"#,
+    )
+    .unwrap();
+
+    let settings = read_workspace_settings_file(&root).unwrap();
+    let include_paths = &settings["compiler"]["includePaths"];
+    assert_eq!(include_paths[0], json!(root.join("Shaders/Include").to_string_lossy()));
+    assert_eq!(include_paths[1], json!("/opt/include"));
+    assert_eq!(settings["compiler"]["tempDir"], json!(root.join(".cache").to_string_lossy()));
+}
+
+#[test]
+fn resolves_compiler_override_globs_and_include_paths_against_root() {
+    let root = test_root();
+    fs::write(
+        root.join(WORKSPACE_SETTINGS_FILE),
+        r#"{ "compiler": { "overrides": [{ "pathGlob": "ios/**", "includePaths": ["ios/include"], "flags": ["-DX"] }] } }"#,
+    )
+    .unwrap();
+
+    let settings = read_workspace_settings_file(&root).unwrap();
+    let entry = &settings["compiler"]["overrides"][0];
+    assert_eq!(entry["pathGlob"], json!(root.join("ios/**").to_string_lossy()));
+    assert_eq!(entry["includePaths"][0], json!(root.join("ios/include").to_string_lossy()));
+    assert_eq!(entry["flags"][0], json!("-DX"));
+}
+
+#[test]
+fn resolves_virtual_include_root_directories_against_root() {
+    let root = test_root();
+    fs::write(
+        root.join(WORKSPACE_SETTINGS_FILE),
+        r#"{ "compiler": { "virtualIncludeRoots": { "generated": "build/codegen", "sdk": "/opt/sdk" } } }"#,
+    )
+    .unwrap();
+
+    let settings = read_workspace_settings_file(&root).unwrap();
+    let roots = &settings["compiler"]["virtualIncludeRoots"];
+    assert_eq!(roots["generated"], json!(root.join("build/codegen").to_string_lossy()));
+    assert_eq!(roots["sdk"], json!("/opt/sdk"));
+}
+
+#[test]
+fn accepts_namespaced_file_and_ignores_malformed_ones() {
+    let root = test_root();
+    assert!(read_workspace_settings_file(&root).is_none());
+
+    fs::write(root.join(WORKSPACE_SETTINGS_FILE), r#"{ "metal-analyzer": { "logging": { "level": "debug" } } }"#)
+        .unwrap();
+    assert_eq!(read_workspace_settings_file(&root).unwrap(), json!({ "logging": { "level": "debug" } }));
+
+    fs::write(root.join(WORKSPACE_SETTINGS_FILE), "{ not json").unwrap();
+    assert!(read_workspace_settings_file(&root).is_none());
+}
+
+#[test]
+fn merge_replaces_leaves_and_null_removes_keys() {
+    let mut base = json!({ "compiler": { "platform": "ios", "extraFlags": ["-DA"] }, "logging": { "level": "warn" } });
+    merge_json_values(&mut base, &json!({ "compiler": { "extraFlags": ["-DB"] }, "logging": { "level": null } }));
+    assert_eq!(base, json!({ "compiler": { "platform": "ios", "extraFlags": ["-DB"] }, "logging": {} }));
+}
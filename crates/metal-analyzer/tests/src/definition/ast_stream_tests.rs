@@ -0,0 +1,75 @@
+use super::*;
+
+fn function(
+    id: &str,
+    name: &str,
+    loc: &str,
+) -> String {
+    format!(r#"{{"id":"{id}","kind":"FunctionDecl","name":"{name}","loc":{loc},"type":{{"qualType":"void ()"}}}}"#)
+}
+
+#[test]
+fn stream_index_collects_top_level_declarations() {
+    let ast = format!(
+        r#"{{"id":"0x1","kind":"TranslationUnitDecl","loc":{{}},"range":{{"begin":{{}},"end":{{}}}},"inner":[{},{}]}}"#,
+        function("0x10", "blur", r#"{"offset":0,"file":"/tmp/stream.metal","line":1,"col":6,"tokLen":4}"#),
+        function("0x11", "sharpen", r#"{"offset":20,"file":"/tmp/stream.metal","line":2,"col":6,"tokLen":7}"#),
+    );
+    let index = stream_index(ast.as_bytes()).expect("valid AST JSON").finish(&[], None);
+
+    let names: Vec<&str> = index.defs.iter().map(|def| def.name.as_str()).collect();
+    assert_eq!(names, vec!["blur", "sharpen"]);
+}
+
+#[test]
+fn stream_index_keeps_the_file_of_earlier_declarations() {
+    // Clang leaves out the file and line that match the previous location,
+    // even across top-level declarations.
+    let ast = format!(
+        r#"{{"id":"0x1","kind":"TranslationUnitDecl","inner":[{},{},{}]}}"#,
+        function("0x10", "blur", r#"{"offset":0,"file":"/tmp/stream.metal","line":3,"col":6,"tokLen":4}"#),
+        function("0x11", "sharpen", r#"{"offset":20,"line":4,"col":6,"tokLen":7}"#),
+        function("0x12", "tint", r#"{"offset":40,"col":20,"tokLen":4}"#),
+    );
+    let index = stream_index(ast.as_bytes()).expect("valid AST JSON").finish(&[], None);
+
+    let locations: Vec<(&str, &str, u32)> =
+        index.defs.iter().map(|def| (def.name.as_str(), def.file.as_str(), def.line)).collect();
+    assert_eq!(
+        locations,
+        vec![("blur", "/tmp/stream.metal", 3), ("sharpen", "/tmp/stream.metal", 4), ("tint", "/tmp/stream.metal", 4)]
+    );
+
+    // Nothing is left behind for the next dump parsed on this thread.
+    let next = format!(
+        r#"{{"id":"0x1","kind":"TranslationUnitDecl","inner":[{}]}}"#,
+        function("0x10", "fresh", r#"{"offset":0,"line":1,"col":6,"tokLen":5}"#),
+    );
+    let index = stream_index(next.as_bytes()).expect("valid AST JSON").finish(&[], None);
+    let files: Vec<&str> = index.defs.iter().map(|def| def.file.as_str()).collect();
+    assert_eq!(files, vec![""]);
+}
+
+#[test]
+fn stream_index_rejects_truncated_output() {
+    let ast = format!(
+        r#"{{"id":"0x1","kind":"TranslationUnitDecl","inner":[{}"#,
+        function("0x10", "blur", r#"{"offset":0,"file":"/tmp/stream.metal","line":1,"col":6,"tokLen":4}"#),
+    );
+    assert!(stream_index(ast.as_bytes()).is_err());
+    assert!(stream_index(&b""[..]).is_err());
+}
+
+#[test]
+fn stream_index_handles_deeply_nested_expressions() {
+    let depth = 2_000;
+    let mut expression = String::from(r#"{"id":"0x100","kind":"IntegerLiteral"}"#);
+    for level in 0..depth {
+        expression = format!(r#"{{"id":"0x{:x}","kind":"ParenExpr","inner":[{expression}]}}"#, 0x1000 + level);
+    }
+    let ast = format!(
+        r#"{{"id":"0x1","kind":"TranslationUnitDecl","inner":[{{"id":"0x10","kind":"FunctionDecl","name":"deep","loc":{{"offset":0,"file":"/tmp/stream.metal","line":1,"col":6,"tokLen":4}},"inner":[{expression}]}}]}}"#
+    );
+    let index = stream_index(ast.as_bytes()).expect("valid AST JSON").finish(&[], None);
+    assert_eq!(index.defs.len(), 1);
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use super::*;
+use crate::definition::ref_site::RefSite;
+
+fn def(
+    id: &str,
+    name: &str,
+    kind: &str,
+    file: &str,
+    line: u32,
+    is_definition: bool,
+) -> SymbolDef {
+    SymbolDef {
+        id: id.to_owned(),
+        name: name.into(),
+        kind: kind.into(),
+        file: file.into(),
+        line,
+        col: 6,
+        is_definition,
+        type_name: None,
+        qual_type: Some("float (float)".into()),
+        file_local: false,
+    }
+}
+
+fn sample_index() -> AstIndex {
+    let defs = vec![
+        def("0x1", "shade", "FunctionDecl", "/tmp/shader.metal", 3, true),
+        def("0x2", "shade", "FunctionDecl", "/tmp/common.h", 1, false),
+        def("0x3", "sqrt", "FunctionDecl", "/Toolchains/metal/include/metal_math", 10, false),
+    ];
+    let refs = vec![RefSite {
+        file: "/tmp/shader.metal".into(),
+        line: 8,
+        col: 12,
+        tok_len: 5,
+        target_id: "0x1".to_owned(),
+        target_name: "shade".into(),
+        target_kind: "FunctionDecl".into(),
+        expansion: None,
+        spelling: None,
+    }];
+    AstIndex {
+        id_to_def: HashMap::from([("0x1".to_owned(), 0), ("0x2".to_owned(), 1), ("0x3".to_owned(), 2)]),
+        name_to_defs: HashMap::new(),
+        target_id_to_refs: HashMap::new(),
+        file_to_defs: HashMap::new(),
+        file_to_refs: HashMap::new(),
+        defs,
+        refs,
+        specializations: Vec::new(),
+    }
+}
+
+#[test]
+fn view_uri_uses_cache_scheme() {
+    let uri = Url::parse("file:///tmp/shader.metal").unwrap();
+    let view = cache_view_uri(&uri).expect("cache uri");
+    assert_eq!(view.as_str(), "metal-analyzer-cache:///tmp/shader.metal");
+    assert!(cache_view_uri(&Url::parse("untitled:Untitled-1").unwrap()).is_none());
+}
+
+#[test]
+fn renders_user_definitions_refs_and_ranks() {
+    let view = render_cache_view(&sample_index(), Path::new("/tmp/shader.metal"), &RankingWeights::default());
+    let lines: Vec<&str> = view.content.lines().collect();
+
+    assert!(lines[1].contains("3 definitions (1 in system headers, not listed), 1 references"));
+    assert!(!view.content.contains("metal_math"), "system header defs are not listed");
+
+    let shader_def = lines.iter().find(|line| line.contains("/tmp/shader.metal:3:6")).expect("shader def line");
+    assert!(shader_def.contains("definition  rank 0"));
+    let header_decl = lines.iter().find(|line| line.contains("/tmp/common.h:1:6")).expect("header decl line");
+    assert!(header_decl.contains("declaration  rank 12 (other file, declaration only)"));
+
+    let reference = lines.iter().find(|line| line.starts_with("8:12")).expect("reference line");
+    assert!(reference.contains("shade -> FunctionDecl 0x1 /tmp/shader.metal:3:6"));
+}
+
+#[test]
+fn links_cover_location_text_and_target_sources() {
+    let view = render_cache_view(&sample_index(), Path::new("/tmp/shader.metal"), &RankingWeights::default());
+    let lines: Vec<&str> = view.content.lines().collect();
+    for link in &view.links {
+        let line = lines[link.range.start.line as usize];
+        let text = &line[link.range.start.character as usize..link.range.end.character as usize];
+        assert!(text.chars().next().is_some_and(|c| c.is_ascii_digit() || c == '/'), "unexpected link text {text:?}");
+    }
+
+    let ref_line = lines.iter().position(|line| line.starts_with("8:12")).unwrap() as u32;
+    let site = view.link_at(Position::new(ref_line, 1)).expect("ref site link");
+    assert_eq!(site.target.range.start, Position::new(7, 11));
+
+    let column = lines[ref_line as usize].find("/tmp/shader.metal").unwrap() as u32 + 2;
+    let target = view.link_at(Position::new(ref_line, column)).expect("target link");
+    assert_eq!(target.target.uri.path(), "/tmp/shader.metal");
+    assert_eq!(target.target.range.start, Position::new(2, 5));
+    assert!(target.detail.contains("rank: `0`"));
+}
+
+#[test]
+fn view_uris_map_back_to_their_source_document() {
+    let source = Url::from_file_path("/tmp/my shaders/shader.metal").unwrap();
+    let view = cache_view_uri(&source).unwrap();
+    assert_eq!(cache_view_source_uri(&view), Some(source.clone()));
+    assert_eq!(cache_view_source_uri(&source), None);
+}
@@ -96,3 +96,52 @@ fn rewrite_includes_skips_framework_include_paths() {
 
     let _ = std::fs::remove_dir(temp_dir);
 }
+
+fn read_all(stdout: &mut dyn Read) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    stdout.read_to_end(&mut bytes).expect("read stdout");
+    bytes
+}
+
+#[test]
+fn run_unless_cancelled_kills_the_process_once_cancelled() {
+    let started = std::time::Instant::now();
+    let mut command = Command::new("sleep");
+    command.arg("30");
+    assert!(run_unless_cancelled(&mut command, &|| true, Duration::from_secs(60), read_all).is_none());
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+
+    let mut command = Command::new("echo");
+    command.arg("dump");
+    let (status, stdout, _) = run_unless_cancelled(&mut command, &|| false, Duration::from_secs(60), read_all)
+        .expect("not cancelled")
+        .expect("echo runs");
+    assert!(status.success());
+    assert_eq!(stdout, b"dump\n");
+}
+
+#[test]
+fn run_unless_cancelled_kills_the_process_on_timeout() {
+    let started = std::time::Instant::now();
+    let mut command = Command::new("sleep");
+    command.arg("30");
+    let error = run_unless_cancelled(&mut command, &|| false, Duration::from_millis(50), read_all)
+        .expect("not cancelled")
+        .expect_err("times out");
+    assert_eq!(error.kind(), ErrorKind::TimedOut);
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+}
+
+#[test]
+fn run_unless_cancelled_drains_what_the_reader_leaves() {
+    let mut command = Command::new("head");
+    command.args(["-c", "1048576", "/dev/zero"]);
+    let (status, first, _) = run_unless_cancelled(&mut command, &|| false, Duration::from_secs(60), |stdout| {
+        let mut byte = [0u8; 1];
+        stdout.read_exact(&mut byte).map(|()| byte[0])
+    })
+    .expect("not cancelled")
+    .expect("head runs");
+    assert!(status.success());
+    assert_eq!(first.expect("one byte"), 0);
+}
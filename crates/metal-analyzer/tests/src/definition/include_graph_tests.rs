@@ -0,0 +1,80 @@
+use super::*;
+
+struct Project(PathBuf);
+
+impl Project {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("metal-analyzer-include-graph-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn write(
+        &self,
+        name: &str,
+        text: &str,
+    ) -> PathBuf {
+        let path = self.0.join(name);
+        std::fs::write(&path, text).unwrap();
+        normalize_path(&path)
+    }
+
+    fn graph(
+        &self,
+        root: &Path,
+    ) -> IncludeGraph {
+        let source = std::fs::read_to_string(root).unwrap();
+        IncludeGraph::build([(root.to_path_buf(), source)], &[], 64, |path| std::fs::read_to_string(path).ok())
+    }
+}
+
+impl Drop for Project {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+#[test]
+fn include_directives_record_names_and_ranges() {
+    let source = "#include <metal_stdlib>\n  #  include \"common/é.h\" // note\n#define X 1\n#include_next <x>\n";
+    let directives = include_directives(source);
+    assert_eq!(directives.len(), 2);
+    assert_eq!(directives[0].name, "metal_stdlib");
+    assert!(directives[0].angled);
+    assert_eq!(directives[0].range, Range::new(Position::new(0, 9), Position::new(0, 23)));
+    assert_eq!(directives[1].name, "common/é.h");
+    assert!(!directives[1].angled);
+    assert_eq!(directives[1].range, Range::new(Position::new(1, 13), Position::new(1, 25)));
+}
+
+#[test]
+fn graph_follows_project_includes() {
+    let project = Project::new("follows");
+    let common = project.write("common.h", "#pragma once\nstruct Common {};\n");
+    project.write("light.h", "#pragma once\n#include \"common.h\"\n");
+    let main = project.write("main.metal", "#include \"light.h\"\n#include \"common.h\"\n#include \"missing.h\"\n");
+
+    let graph = project.graph(&main);
+    let names: Vec<String> =
+        graph.nodes().iter().map(|node| node.path.file_name().unwrap().to_string_lossy().into_owned()).collect();
+    assert_eq!(names, vec!["main.metal", "light.h", "common.h"]);
+    assert_eq!(graph.edges().len(), 3);
+    let common = graph.node(&common).unwrap();
+    assert!(graph.nodes()[common].guarded);
+    assert!(graph.edges().iter().all(|edge| !graph.in_cycle(edge)));
+}
+
+#[test]
+fn cycles_are_found_through_other_headers() {
+    let project = Project::new("cycle");
+    let a = project.write("a.h", "#pragma once\n#include \"b.h\"\n");
+    let b = project.write("b.h", "#pragma once\n#include \"c.h\"\n");
+    let c = project.write("c.h", "#pragma once\n#include \"a.h\"\n");
+
+    let graph = project.graph(&a);
+    let (a, b, c) = (graph.node(&a).unwrap(), graph.node(&b).unwrap(), graph.node(&c).unwrap());
+    assert!(graph.edges().iter().all(|edge| graph.in_cycle(edge)));
+    let chain: Vec<(usize, usize)> = graph.path(b, a).unwrap().iter().map(|edge| (edge.from, edge.to)).collect();
+    assert_eq!(chain, vec![(b, c), (c, a)]);
+    assert_eq!(graph.path(a, a).unwrap().len(), 0);
+}
@@ -14,31 +14,33 @@ fn disk_cache_roundtrip_and_invalidation() {
     let index = AstIndex {
         defs: vec![SymbolDef {
             id: "0x1".to_owned(),
-            name: "foo".to_owned(),
-            kind: "FunctionDecl".to_owned(),
-            file: "/tmp/shader.metal".to_owned(),
+            name: "foo".into(),
+            kind: "FunctionDecl".into(),
+            file: "/tmp/shader.metal".into(),
             line: 1,
             col: 1,
             is_definition: true,
             type_name: None,
             qual_type: None,
+            file_local: false,
         }],
         refs: vec![RefSite {
-            file: "/tmp/shader.metal".to_owned(),
+            file: "/tmp/shader.metal".into(),
             line: 2,
             col: 3,
             tok_len: 3,
             target_id: "0x1".to_owned(),
-            target_name: "foo".to_owned(),
-            target_kind: "FunctionDecl".to_owned(),
+            target_name: "foo".into(),
+            target_kind: "FunctionDecl".into(),
             expansion: None,
             spelling: None,
         }],
         id_to_def: HashMap::from([("0x1".to_owned(), 0)]),
-        name_to_defs: HashMap::from([("foo".to_owned(), vec![0])]),
+        name_to_defs: HashMap::from([("foo".into(), vec![0])]),
         target_id_to_refs: HashMap::from([("0x1".to_owned(), vec![0])]),
-        file_to_defs: HashMap::from([("/tmp/shader.metal".to_owned(), vec![0])]),
-        file_to_refs: HashMap::from([("/tmp/shader.metal".to_owned(), vec![0])]),
+        file_to_defs: HashMap::from([("/tmp/shader.metal".into(), vec![0])]),
+        file_to_refs: HashMap::from([("/tmp/shader.metal".into(), vec![0])]),
+        specializations: Vec::new(),
     };
 
     save_to_root(&root, &file, "source-hash-1", &include_paths, &index);
@@ -55,3 +57,69 @@ fn disk_cache_roundtrip_and_invalidation() {
 
     let _ = std::fs::remove_dir_all(root);
 }
+
+#[test]
+fn stable_hash_hex_is_a_256_bit_blake3_digest() {
+    assert_eq!(stable_hash_hex(""), "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262");
+    assert_ne!(stable_hash_hex("/tmp/a.metal"), stable_hash_hex("/tmp/b.metal"));
+}
+
+#[test]
+fn legacy_entries_are_removed() {
+    let root = std::env::temp_dir().join(format!(
+        "metal-analyzer-index-cache-legacy-test-{}",
+        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).expect("clock drift").as_nanos()
+    ));
+    std::fs::create_dir_all(&root).expect("create cache dir");
+    let legacy = root.join("cbf29ce484222325.json");
+    let current = cache_file_path(&root, Path::new("/tmp/shader.metal"));
+    let unrelated = root.join("notes.json");
+    for file in [&legacy, &current, &unrelated] {
+        std::fs::write(file, "{}").expect("write entry");
+    }
+
+    remove_legacy_entries(&root);
+
+    assert!(!legacy.exists());
+    assert!(current.exists());
+    assert!(unrelated.exists());
+    let _ = std::fs::remove_dir_all(root);
+}
+
+#[test]
+fn clear_removes_every_entry() {
+    let root = std::env::temp_dir().join(format!(
+        "metal-analyzer-index-cache-clear-test-{}",
+        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).expect("clock drift").as_nanos()
+    ));
+    std::fs::create_dir_all(&root).expect("create cache dir");
+    let entries =
+        [cache_file_path(&root, Path::new("/tmp/a.metal")), cache_file_path(&root, Path::new("/tmp/b.metal"))];
+    for file in &entries {
+        std::fs::write(file, "{}").expect("write entry");
+    }
+    let lock = root.join("cache.lock");
+    std::fs::write(&lock, "").expect("write lock");
+
+    assert_eq!(clear_root(&root), 2);
+    assert!(entries.iter().all(|file| !file.exists()));
+    assert!(lock.exists());
+    assert_eq!(clear_root(&root.join("missing")), 0);
+    let _ = std::fs::remove_dir_all(root);
+}
+
+#[test]
+fn disk_usage_counts_entries_and_bytes() {
+    let root = std::env::temp_dir().join(format!(
+        "metal-analyzer-index-cache-usage-test-{}",
+        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).expect("clock drift").as_nanos()
+    ));
+    assert_eq!(disk_usage_of_root(&root), (0, 0));
+    std::fs::create_dir_all(&root).expect("create cache dir");
+    std::fs::write(cache_file_path(&root, Path::new("/tmp/a.metal")), "{}").expect("write entry");
+    std::fs::write(cache_file_path(&root, Path::new("/tmp/b.metal")), "{\"a\":1}").expect("write entry");
+    std::fs::write(root.join("cache.lock"), "ignored").expect("write lock");
+
+    assert_eq!(disk_usage_of_root(&root), (2, 9));
+    let _ = std::fs::remove_dir_all(root);
+}
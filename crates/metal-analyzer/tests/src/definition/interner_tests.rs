@@ -0,0 +1,71 @@
+use super::*;
+
+#[test]
+fn equal_strings_share_one_allocation() {
+    let first = SharedStr::new("interner_test_shade_light");
+    let second = SharedStr::from(String::from("interner_test_shade_light"));
+    assert!(first.ptr_eq(&second));
+    assert_eq!(first, "interner_test_shade_light");
+    assert!(!first.ptr_eq(&SharedStr::new("interner_test_other")));
+}
+
+#[test]
+fn purge_drops_strings_no_one_holds() {
+    let text = format!("interner_test_purge_{}", std::process::id());
+    let held = SharedStr::new(&text);
+    drop(SharedStr::new(&text));
+    purge();
+    assert!(POOL.contains(text.as_str()));
+    assert!(SharedStr::new(&text).ptr_eq(&held));
+
+    drop(held);
+    purge();
+    assert!(!POOL.contains(text.as_str()));
+}
+
+#[test]
+fn deserialized_strings_are_interned() {
+    let original = SharedStr::new("interner_test_kernel");
+    let decoded: SharedStr = serde_json::from_str(&serde_json::to_string(&original).unwrap()).unwrap();
+    assert!(decoded.ptr_eq(&original));
+}
+
+#[test]
+fn cached_path_key_matches_path_key() {
+    let dir = std::env::temp_dir().join(format!("interner_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("shaders.metal");
+    std::fs::write(&file, "kernel void k() {}\n").unwrap();
+    let text = file.display().to_string();
+
+    assert_eq!(*cached_path_key(&text), path_key(&file));
+    assert_eq!(*cached_path_key(&text), path_key(&file));
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn purges_if_due_are_skipped_right_after_a_purge() {
+    let text = format!("interner_test_due_{}", std::process::id());
+    purge();
+    drop(SharedStr::new(&text));
+    assert_eq!(purge_if_due(), 0);
+
+    purge();
+    assert!(!POOL.contains(text.as_str()));
+}
+
+#[test]
+fn purge_keeps_path_keys_of_held_strings() {
+    let dir = std::env::temp_dir().join(format!("interner_test_keys_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let held = SharedStr::new(&dir.display().to_string());
+    cached_path_key(&held);
+    purge();
+    assert!(PATH_KEYS.contains_key(held.as_str()));
+
+    drop(held);
+    purge();
+    assert!(!PATH_KEYS.contains_key(dir.display().to_string().as_str()));
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,297 @@
+use super::*;
+
+fn def(
+    name: &str,
+    file: &str,
+    line: u32,
+) -> SymbolDef {
+    SymbolDef {
+        id: format!("{name}-{line}"),
+        name: name.into(),
+        kind: "FunctionDecl".into(),
+        file: file.into(),
+        line,
+        col: 1,
+        is_definition: true,
+        type_name: None,
+        qual_type: Some("void ()".into()),
+        file_local: false,
+    }
+}
+
+fn index(defs: Vec<SymbolDef>) -> AstIndex {
+    AstIndex {
+        defs,
+        refs: Vec::new(),
+        id_to_def: HashMap::new(),
+        name_to_defs: HashMap::new(),
+        target_id_to_refs: HashMap::new(),
+        file_to_defs: HashMap::new(),
+        file_to_refs: HashMap::new(),
+        specializations: Vec::new(),
+    }
+}
+
+#[test]
+fn all_definitions_dedupes_shared_headers_and_skips_system_headers() {
+    let stdlib = "/Toolchains/XcodeDefault.xctoolchain/usr/metal/include/metal_stdlib";
+    let project_index = ProjectIndex::new();
+    project_index.update_file(
+        PathBuf::from("/ws/a.metal"),
+        index(vec![def("helper", "/ws/common.h", 3), def("kernel_a", "/ws/a.metal", 5), def("sin", stdlib, 9)]),
+    );
+    project_index.update_file(
+        PathBuf::from("/ws/b.metal"),
+        index(vec![def("helper", "/ws/common.h", 3), def("kernel_b", "/ws/b.metal", 2)]),
+    );
+
+    let names = |defs: Vec<SymbolDef>| defs.into_iter().map(|def| def.name).collect::<Vec<_>>();
+    assert_eq!(names(project_index.all_definitions(false)), vec!["kernel_a", "kernel_b", "helper"]);
+    assert_eq!(project_index.all_definitions(true).len(), 4);
+}
+
+#[test]
+fn includable_declarations_pick_one_header_per_name() {
+    let stdlib = "/Toolchains/XcodeDefault.xctoolchain/usr/metal/include/metal_stdlib";
+    let declaration = |name: &str, file: &str, line: u32| SymbolDef {
+        is_definition: false,
+        ..def(name, file, line)
+    };
+    let project_index = ProjectIndex::new();
+    project_index.update_file(
+        PathBuf::from("/ws/a.metal"),
+        index(vec![
+            declaration("blur", "/ws/a/filters.h", 2),
+            def("blur", "/ws/b/filters_impl.h", 7),
+            def("tint", "/ws/z/colors.h", 4),
+            def("tint", "/ws/c/colors.h", 9),
+            def("kernel_a", "/ws/a.metal", 5),
+            def("sin", stdlib, 9),
+        ]),
+    );
+
+    let headers: Vec<(String, String)> = project_index
+        .includable_declarations(&["FunctionDecl"])
+        .into_iter()
+        .map(|def| (def.name.to_string(), def.file.to_string()))
+        .collect();
+    assert_eq!(
+        headers,
+        vec![
+            ("blur".to_string(), "/ws/b/filters_impl.h".to_string()),
+            ("tint".to_string(), "/ws/c/colors.h".to_string())
+        ]
+    );
+    assert!(project_index.includable_declarations(&["CXXRecordDecl"]).is_empty());
+}
+
+#[test]
+fn translation_unit_dump_indexes_its_project_headers() {
+    let root = std::env::temp_dir().join(format!("project_index_headers_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    let header = root.join("common.h");
+    let header_text = "void helper();\nvoid caller() { kernel_a(); }\n";
+    std::fs::write(&header, header_text).unwrap();
+    let main = root.join("a.metal");
+    let header_file = header.display().to_string();
+    let main_file = main.display().to_string();
+
+    let kernel = def("kernel_a", &main_file, 5);
+    let call = RefSite {
+        file: header_file.clone().into(),
+        line: 2,
+        col: 17,
+        tok_len: 8,
+        target_id: kernel.id.clone(),
+        target_name: kernel.name.clone(),
+        target_kind: kernel.kind.clone(),
+        expansion: None,
+        spelling: None,
+    };
+    let tu = AstIndex::from_parts(
+        vec![def("helper", &header_file, 1), kernel, def("unrelated", &main_file, 9)],
+        vec![call],
+        Vec::new(),
+    );
+    let project_index = ProjectIndex::new();
+    project_index.update_file(main.clone(), tu);
+
+    let split = project_index.translation_unit_header_index(&header, ContentHash::of(header_text)).unwrap();
+    let mut names: Vec<&str> = split.defs.iter().map(|def| def.name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, ["helper", "kernel_a"]);
+    assert_eq!(split.get_references_in_file(&header_file).len(), 1);
+    assert!(project_index.translation_unit_header_index(&header, ContentHash::of("void helper();\n")).is_none());
+    assert!(project_index.translation_unit_header_index(&main, ContentHash::of("")).is_none());
+    assert_eq!(project_index.file_count(), 1);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn unreferenced_file_local_definitions_aggregate_references_across_units() {
+    let file_local = |name: &str, file: &str, line: u32| SymbolDef {
+        file_local: true,
+        ..def(name, file, line)
+    };
+    let call = |target: &SymbolDef, file: &str| RefSite {
+        file: file.into(),
+        line: 20,
+        col: 5,
+        tok_len: target.name.len() as u32,
+        target_id: target.id.clone(),
+        target_name: target.name.clone(),
+        target_kind: target.kind.clone(),
+        expansion: None,
+        spelling: None,
+    };
+    let shared = file_local("clamp01", "/ws/common.h", 2);
+    let used_in_b = file_local("lerp3", "/ws/common.h", 6);
+    let dead = file_local("old_blur", "/ws/a.metal", 4);
+    let kernel = def("kernel_a", "/ws/a.metal", 9);
+    let project_index = ProjectIndex::new();
+    project_index.update_file(
+        PathBuf::from("/ws/a.metal"),
+        AstIndex::from_parts(
+            vec![shared.clone(), used_in_b.clone(), dead, kernel],
+            vec![call(&shared, "/ws/a.metal")],
+            Vec::new(),
+        ),
+    );
+    project_index.update_file(
+        PathBuf::from("/ws/b.metal"),
+        AstIndex::from_parts(
+            vec![shared, used_in_b.clone(), file_local("instantiated", "/ws/b.metal", 3)],
+            vec![
+                call(&used_in_b, "/ws/b.metal"),
+                RefSite {
+                    target_id: "implicit-instantiation".to_string(),
+                    ..call(&def("instantiated", "/ws/b.metal", 3), "/ws/b.metal")
+                },
+            ],
+            Vec::new(),
+        ),
+    );
+
+    let unused = |file: &str| -> Vec<String> {
+        project_index.unreferenced_file_local_definitions(file).into_iter().map(|def| def.name.to_string()).collect()
+    };
+    assert_eq!(unused("/ws/a.metal"), vec!["old_blur"]);
+    assert!(unused("/ws/common.h").is_empty());
+    assert!(unused("/ws/b.metal").is_empty());
+}
+
+#[test]
+fn structs_of_a_main_file_are_unused_unless_a_declaration_names_them() {
+    let record = |name: &str, file: &str, line: u32| SymbolDef {
+        kind: "CXXRecordDecl".into(),
+        ..def(name, file, line)
+    };
+    let typed = |name: &str, type_name: &str, qual_type: &str, line: u32| SymbolDef {
+        kind: "VarDecl".into(),
+        type_name: Some(type_name.into()),
+        qual_type: Some(qual_type.into()),
+        ..def(name, "/ws/a.metal", line)
+    };
+    let project_index = ProjectIndex::new();
+    project_index.update_file(
+        PathBuf::from("/ws/a.metal"),
+        AstIndex::from_parts(
+            vec![
+                record("Unused", "/ws/a.metal", 1),
+                record("Params", "/ws/a.metal", 2),
+                record("Vertex", "/ws/a.metal", 3),
+                record("Shared", "/ws/common.h", 4),
+                typed("params", "Params", "constant Params &", 10),
+                SymbolDef {
+                    kind: "FunctionDecl".into(),
+                    qual_type: Some("Vertex (uint)".into()),
+                    ..def("make_vertex", "/ws/a.metal", 11)
+                },
+            ],
+            Vec::new(),
+            Vec::new(),
+        ),
+    );
+
+    let unused: Vec<String> = project_index
+        .unreferenced_file_local_definitions("/ws/a.metal")
+        .into_iter()
+        .map(|def| def.name.to_string())
+        .collect();
+    assert_eq!(unused, vec!["Unused"]);
+    assert!(project_index.unreferenced_file_local_definitions("/ws/common.h").is_empty());
+}
+
+#[test]
+fn approximate_size_grows_with_the_index() {
+    let small = index(vec![def("kernel_a", "/ws/a.metal", 1)]);
+    let large = AstIndex::from_parts(
+        (1..=50).map(|line| def(&format!("helper_{line}"), "/ws/a.metal", line)).collect(),
+        Vec::new(),
+        Vec::new(),
+    );
+    assert!(small.approximate_size() >= size_of::<AstIndex>() + size_of::<SymbolDef>());
+    assert!(large.approximate_size() > 50 * small.approximate_size() / 2);
+}
+
+#[test]
+fn lookups_from_a_file_stay_within_its_workspace_root() {
+    let named = |name: &str, file: &str| {
+        let mut index = index(vec![def(name, file, 4)]);
+        index.name_to_defs.insert(name.into(), vec![0]);
+        index
+    };
+    let project_index = ProjectIndex::new();
+    project_index.update_file(PathBuf::from("/ws/app/a.metal"), named("blur", "/ws/app/a.metal"));
+    project_index.update_file(PathBuf::from("/ws/tools/b.metal"), named("blur", "/ws/tools/b.metal"));
+    project_index.update_file(PathBuf::from("/shared/c.metal"), named("blur", "/shared/c.metal"));
+    project_index.set_workspace_roots(&[PathBuf::from("/ws"), PathBuf::from("/ws/app"), PathBuf::from("/ws/tools")]);
+
+    let files = |defs: Vec<SymbolDef>| {
+        let mut files: Vec<String> = defs.into_iter().map(|def| def.file.to_string()).collect();
+        files.sort();
+        files
+    };
+    assert_eq!(
+        files(project_index.find_definitions_from("blur", Path::new("/ws/app/main.metal"))),
+        vec!["/shared/c.metal", "/ws/app/a.metal"]
+    );
+    assert_eq!(
+        files(project_index.find_definitions_from("blur", Path::new("/elsewhere/x.metal"))),
+        vec!["/shared/c.metal", "/ws/app/a.metal", "/ws/tools/b.metal"]
+    );
+    assert_eq!(project_index.find_definitions("blur").len(), 3);
+}
+
+#[test]
+fn eviction_drops_least_recently_used_indexes_the_disk_cache_can_reload() {
+    let project_index = ProjectIndex::new();
+    let disk_cache = |path: &str| DiskCacheKey {
+        path: PathBuf::from(path),
+        source_hash: "never-cached".to_string(),
+        inputs: Vec::new(),
+    };
+    for name in ["older", "newer"] {
+        let path = format!("/ws/evict/{name}.metal");
+        let index = index(vec![def(name, &path, 1)]);
+        project_index.update_file_from_source(PathBuf::from(&path), index, "", &[], Some(disk_cache(&path)));
+    }
+    project_index
+        .update_file(PathBuf::from("/ws/evict/fixed.metal"), index(vec![def("fixed", "/ws/evict/fixed.metal", 1)]));
+    let file_size = project_index.memory_usage() / 3;
+
+    assert_eq!(project_index.evict_reloadable(1), file_size);
+    assert_eq!(project_index.memory_usage(), 2 * file_size);
+    assert!(project_index.contains_file(Path::new("/ws/evict/older.metal")));
+
+    // Nothing was written to the disk cache, so the evicted index is gone
+    // from queries; the one without a disk cache entry is never evicted.
+    let names = |project_index: &ProjectIndex| {
+        project_index.all_definitions(false).into_iter().map(|def| def.name.to_string()).collect::<Vec<_>>()
+    };
+    assert_eq!(names(&project_index), vec!["fixed", "newer"]);
+    assert_eq!(project_index.evict_reloadable(usize::MAX), file_size);
+    assert_eq!(names(&project_index), vec!["fixed"]);
+    assert_eq!(project_index.memory_usage(), file_size);
+}
@@ -0,0 +1,186 @@
+use std::{
+    fs,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime},
+};
+
+use super::*;
+use crate::definition::SymbolDef;
+
+/// Create a unique temporary workspace root for each test.
+fn test_root() -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("project_store_test_{}_{id}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+/// A workspace with `blur.metal` including `common.h`, indexed into a
+/// project index with the given include paths.
+struct Fixture {
+    root: PathBuf,
+    source: PathBuf,
+    header: PathBuf,
+    snapshot: PathBuf,
+}
+
+const SOURCE: &str = "#include \"common.h\"\nkernel void blur() { helper(); }\n";
+
+impl Fixture {
+    fn new() -> Self {
+        let root = test_root();
+        let source = root.join("blur.metal");
+        let header = root.join("common.h");
+        fs::write(&source, SOURCE).unwrap();
+        fs::write(&header, "inline void helper() {}\n").unwrap();
+        let snapshot = root.join("snapshot/project.json");
+        Self {
+            root,
+            source,
+            header,
+            snapshot,
+        }
+    }
+
+    fn index(&self) -> AstIndex {
+        let def = |name: &str, file: &Path, line: u32| SymbolDef {
+            id: format!("{name}-{line}"),
+            name: name.into(),
+            kind: "FunctionDecl".into(),
+            file: file.display().to_string().into(),
+            line,
+            col: 1,
+            is_definition: true,
+            type_name: None,
+            qual_type: Some("void ()".into()),
+            file_local: false,
+        };
+        let defs = vec![def("helper", &self.header, 1), def("blur", &self.source, 2)];
+        AstIndex {
+            name_to_defs: HashMap::from([("helper".into(), vec![0]), ("blur".into(), vec![1])]),
+            file_to_defs: HashMap::from([
+                (self.header.display().to_string().into(), vec![0]),
+                (self.source.display().to_string().into(), vec![1]),
+            ]),
+            defs,
+            refs: Vec::new(),
+            id_to_def: HashMap::new(),
+            target_id_to_refs: HashMap::new(),
+            file_to_refs: HashMap::new(),
+            specializations: Vec::new(),
+        }
+    }
+
+    fn save(
+        &self,
+        indexed_source: &str,
+    ) {
+        let project_index = ProjectIndex::new();
+        project_index.update_file_from_source(
+            self.source.clone(),
+            self.index(),
+            indexed_source,
+            &include_paths(),
+            None,
+        );
+        let saved = save_to(&self.snapshot, &project_index, std::slice::from_ref(&self.root)).unwrap();
+        assert_eq!(saved, 1);
+    }
+
+    fn restore(
+        &self,
+        include_paths: Vec<String>,
+    ) -> (ProjectIndex, Vec<PathBuf>) {
+        let project_index = ProjectIndex::new();
+        let restored = restore_from(
+            &self.snapshot,
+            &project_index,
+            std::slice::from_ref(&self.root),
+            std::slice::from_ref(&self.source),
+            |_| include_paths.clone(),
+        );
+        (project_index, restored)
+    }
+}
+
+fn include_paths() -> Vec<String> {
+    vec!["/ws/include".to_string()]
+}
+
+fn set_mtime(
+    path: &Path,
+    time: SystemTime,
+) {
+    fs::File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+}
+
+#[test]
+fn restores_unchanged_files_without_reindexing() {
+    let fixture = Fixture::new();
+    fixture.save(SOURCE);
+
+    let (project_index, restored) = fixture.restore(include_paths());
+    assert_eq!(restored, vec![fixture.source.clone()]);
+    let defs = project_index.find_definitions("helper");
+    assert_eq!(defs.len(), 1);
+    assert_eq!(defs[0].file, fixture.header.display().to_string());
+    // Restored entries can be saved again by the next session.
+    assert_eq!(project_index.persistable_files().len(), 1);
+}
+
+#[test]
+fn touched_but_unchanged_files_are_still_restored() {
+    let fixture = Fixture::new();
+    fixture.save(SOURCE);
+    set_mtime(&fixture.header, SystemTime::now() + Duration::from_secs(60));
+
+    let (_, restored) = fixture.restore(include_paths());
+    assert_eq!(restored, vec![fixture.source.clone()]);
+}
+
+#[test]
+fn edited_header_invalidates_its_includers() {
+    let fixture = Fixture::new();
+    fixture.save(SOURCE);
+    fs::write(&fixture.header, "inline void helper(int x) {}\n").unwrap();
+
+    let (project_index, restored) = fixture.restore(include_paths());
+    assert!(restored.is_empty());
+    assert_eq!(project_index.file_count(), 0);
+}
+
+#[test]
+fn changed_include_paths_invalidate_entries() {
+    let fixture = Fixture::new();
+    fixture.save(SOURCE);
+
+    let (_, restored) = fixture.restore(vec!["/ws/other".to_string()]);
+    assert!(restored.is_empty());
+}
+
+#[test]
+fn unsaved_buffers_are_not_restored_from_disk() {
+    let fixture = Fixture::new();
+    fixture.save("kernel void blur() { /* unsaved edit */ }\n");
+
+    let (_, restored) = fixture.restore(include_paths());
+    assert!(restored.is_empty());
+}
+
+#[test]
+fn snapshots_of_other_workspaces_are_ignored() {
+    let fixture = Fixture::new();
+    fixture.save(SOURCE);
+
+    let project_index = ProjectIndex::new();
+    let restored = restore_from(
+        &fixture.snapshot,
+        &project_index,
+        &[fixture.root.join("elsewhere")],
+        std::slice::from_ref(&fixture.source),
+        |_| include_paths(),
+    );
+    assert!(restored.is_empty());
+}
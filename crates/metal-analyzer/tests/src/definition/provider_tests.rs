@@ -68,6 +68,7 @@ static METAL_FUNC void run(constant PrimaryParams* state) {
             is_definition: true,
             type_name: None,
             qual_type: None,
+            file_local: false,
         },
         SymbolDef {
             id: "field-primary".into(),
@@ -79,6 +80,7 @@ static METAL_FUNC void run(constant PrimaryParams* state) {
             is_definition: true,
             type_name: Some("int".into()),
             qual_type: Some("const int".into()),
+            file_local: false,
         },
         SymbolDef {
             id: "record-secondary".into(),
@@ -90,6 +92,7 @@ static METAL_FUNC void run(constant PrimaryParams* state) {
             is_definition: true,
             type_name: None,
             qual_type: None,
+            file_local: false,
         },
         SymbolDef {
             id: "field-secondary".into(),
@@ -101,6 +104,7 @@ static METAL_FUNC void run(constant PrimaryParams* state) {
             is_definition: true,
             type_name: Some("int".into()),
             qual_type: Some("const int".into()),
+            file_local: false,
         },
         SymbolDef {
             id: "parm-state".into(),
@@ -112,12 +116,13 @@ static METAL_FUNC void run(constant PrimaryParams* state) {
             is_definition: true,
             type_name: Some("PrimaryParams".into()),
             qual_type: Some("constant PrimaryParams *".into()),
+            file_local: false,
         },
     ];
 
     let mut name_to_defs = std::collections::HashMap::new();
-    name_to_defs.insert("iteration_limit".to_string(), vec![1, 3]);
-    name_to_defs.insert("state".to_string(), vec![4]);
+    name_to_defs.insert("iteration_limit".into(), vec![1, 3]);
+    name_to_defs.insert("state".into(), vec![4]);
 
     let index = AstIndex {
         defs,
@@ -127,9 +132,10 @@ static METAL_FUNC void run(constant PrimaryParams* state) {
         target_id_to_refs: std::collections::HashMap::new(),
         file_to_defs: std::collections::HashMap::new(),
         file_to_refs: std::collections::HashMap::new(),
+        specializations: Vec::new(),
     };
 
-    let result = resolve_by_name(&index, source_file, source, position, "iteration_limit")
+    let result = resolve_by_name(&index, source_file, source, position, "iteration_limit", &RankingWeights::default())
         .expect("tie should be disambiguated to the primary owner field");
     let NavigationTarget::Single(location) = result else {
         panic!("expected scalar response");
@@ -161,6 +167,7 @@ fn by_name_field_tie_without_receiver_type_remains_ambiguous() {
             is_definition: true,
             type_name: None,
             qual_type: None,
+            file_local: false,
         },
         SymbolDef {
             id: "field-primary".into(),
@@ -172,6 +179,7 @@ fn by_name_field_tie_without_receiver_type_remains_ambiguous() {
             is_definition: true,
             type_name: Some("int".into()),
             qual_type: Some("const int".into()),
+            file_local: false,
         },
         SymbolDef {
             id: "record-secondary".into(),
@@ -183,6 +191,7 @@ fn by_name_field_tie_without_receiver_type_remains_ambiguous() {
             is_definition: true,
             type_name: None,
             qual_type: None,
+            file_local: false,
         },
         SymbolDef {
             id: "field-secondary".into(),
@@ -194,11 +203,12 @@ fn by_name_field_tie_without_receiver_type_remains_ambiguous() {
             is_definition: true,
             type_name: Some("int".into()),
             qual_type: Some("const int".into()),
+            file_local: false,
         },
     ];
 
     let mut name_to_defs = std::collections::HashMap::new();
-    name_to_defs.insert("iteration_limit".to_string(), vec![1, 3]);
+    name_to_defs.insert("iteration_limit".into(), vec![1, 3]);
 
     let index = AstIndex {
         defs,
@@ -208,9 +218,10 @@ fn by_name_field_tie_without_receiver_type_remains_ambiguous() {
         target_id_to_refs: std::collections::HashMap::new(),
         file_to_defs: std::collections::HashMap::new(),
         file_to_refs: std::collections::HashMap::new(),
+        specializations: Vec::new(),
     };
 
-    let result = resolve_by_name(&index, source_file, source, position, "iteration_limit");
+    let result = resolve_by_name(&index, source_file, source, position, "iteration_limit", &RankingWeights::default());
     assert!(result.is_none(), "without receiver type info, tie should remain ambiguous");
 }
 
@@ -236,6 +247,7 @@ static METAL_FUNC void run(thread auto& tile, short lane) {
             is_definition: true,
             type_name: None,
             qual_type: None,
+            file_local: false,
         },
         SymbolDef {
             id: "method-element-at-mutable".into(),
@@ -247,6 +259,7 @@ static METAL_FUNC void run(thread auto& tile, short lane) {
             is_definition: true,
             type_name: None,
             qual_type: Some("thread element_type &(const short, const short)".into()),
+            file_local: false,
         },
         SymbolDef {
             id: "method-element-at-const".into(),
@@ -258,6 +271,7 @@ static METAL_FUNC void run(thread auto& tile, short lane) {
             is_definition: true,
             type_name: None,
             qual_type: Some("const thread element_type &(const short, const short) const".into()),
+            file_local: false,
         },
         SymbolDef {
             id: "parm-tile".into(),
@@ -269,12 +283,13 @@ static METAL_FUNC void run(thread auto& tile, short lane) {
             is_definition: true,
             type_name: Some("TileOwner".into()),
             qual_type: Some("thread TileOwner &".into()),
+            file_local: false,
         },
     ];
 
     let mut name_to_defs = std::collections::HashMap::new();
-    name_to_defs.insert("element_at".to_string(), vec![1, 2]);
-    name_to_defs.insert("tile".to_string(), vec![3]);
+    name_to_defs.insert("element_at".into(), vec![1, 2]);
+    name_to_defs.insert("tile".into(), vec![3]);
 
     let index = AstIndex {
         defs,
@@ -284,9 +299,10 @@ static METAL_FUNC void run(thread auto& tile, short lane) {
         target_id_to_refs: std::collections::HashMap::new(),
         file_to_defs: std::collections::HashMap::new(),
         file_to_refs: std::collections::HashMap::new(),
+        specializations: Vec::new(),
     };
 
-    let result = resolve_by_name(&index, source_file, source, position, "element_at")
+    let result = resolve_by_name(&index, source_file, source, position, "element_at", &RankingWeights::default())
         .expect("method tie should resolve to the mutable overload");
     let NavigationTarget::Single(location) = result else {
         panic!("expected scalar response");
@@ -318,6 +334,7 @@ fn by_name_method_tie_without_receiver_type_keeps_unique_owner() {
             is_definition: true,
             type_name: None,
             qual_type: None,
+            file_local: false,
         },
         SymbolDef {
             id: "method-element-at-mutable".into(),
@@ -329,6 +346,7 @@ fn by_name_method_tie_without_receiver_type_keeps_unique_owner() {
             is_definition: true,
             type_name: None,
             qual_type: Some("thread element_type &(const short, const short)".into()),
+            file_local: false,
         },
         SymbolDef {
             id: "method-element-at-const".into(),
@@ -340,11 +358,12 @@ fn by_name_method_tie_without_receiver_type_keeps_unique_owner() {
             is_definition: true,
             type_name: None,
             qual_type: Some("const thread element_type &(const short, const short) const".into()),
+            file_local: false,
         },
     ];
 
     let mut name_to_defs = std::collections::HashMap::new();
-    name_to_defs.insert("element_at".to_string(), vec![1, 2]);
+    name_to_defs.insert("element_at".into(), vec![1, 2]);
 
     let index = AstIndex {
         defs,
@@ -354,9 +373,10 @@ fn by_name_method_tie_without_receiver_type_keeps_unique_owner() {
         target_id_to_refs: std::collections::HashMap::new(),
         file_to_defs: std::collections::HashMap::new(),
         file_to_refs: std::collections::HashMap::new(),
+        specializations: Vec::new(),
     };
 
-    let result = resolve_by_name(&index, source_file, source, position, "element_at")
+    let result = resolve_by_name(&index, source_file, source, position, "element_at", &RankingWeights::default())
         .expect("method tie with a unique owner should resolve");
     let NavigationTarget::Single(location) = result else {
         panic!("expected scalar response");
@@ -381,6 +401,7 @@ fn by_name_method_tie_without_receiver_type_stays_ambiguous_for_multiple_owners(
             is_definition: true,
             type_name: None,
             qual_type: None,
+            file_local: false,
         },
         SymbolDef {
             id: "method-a".into(),
@@ -392,6 +413,7 @@ fn by_name_method_tie_without_receiver_type_stays_ambiguous_for_multiple_owners(
             is_definition: true,
             type_name: None,
             qual_type: Some("thread element_type &(const short, const short)".into()),
+            file_local: false,
         },
         SymbolDef {
             id: "record-b".into(),
@@ -403,6 +425,7 @@ fn by_name_method_tie_without_receiver_type_stays_ambiguous_for_multiple_owners(
             is_definition: true,
             type_name: None,
             qual_type: None,
+            file_local: false,
         },
         SymbolDef {
             id: "method-b".into(),
@@ -414,11 +437,12 @@ fn by_name_method_tie_without_receiver_type_stays_ambiguous_for_multiple_owners(
             is_definition: true,
             type_name: None,
             qual_type: Some("thread element_type &(const short, const short)".into()),
+            file_local: false,
         },
     ];
 
     let mut name_to_defs = std::collections::HashMap::new();
-    name_to_defs.insert("element_at".to_string(), vec![1, 3]);
+    name_to_defs.insert("element_at".into(), vec![1, 3]);
 
     let index = AstIndex {
         defs,
@@ -428,9 +452,10 @@ fn by_name_method_tie_without_receiver_type_stays_ambiguous_for_multiple_owners(
         target_id_to_refs: std::collections::HashMap::new(),
         file_to_defs: std::collections::HashMap::new(),
         file_to_refs: std::collections::HashMap::new(),
+        specializations: Vec::new(),
     };
 
-    let result = resolve_by_name(&index, source_file, source, position, "element_at");
+    let result = resolve_by_name(&index, source_file, source, position, "element_at", &RankingWeights::default());
     assert!(result.is_none(), "method tie across multiple owners should remain ambiguous without receiver type",);
 }
 
@@ -457,21 +482,23 @@ fn project_index_fallback_prefers_graph_scoped_candidates() {
     let mk_index = |path: &std::path::Path, line: u32| AstIndex {
         defs: vec![SymbolDef {
             id: format!("id-{line}"),
-            name: "shared_symbol".to_string(),
-            kind: "FunctionDecl".to_string(),
-            file: path.display().to_string(),
+            name: "shared_symbol".into(),
+            kind: "FunctionDecl".into(),
+            file: path.display().to_string().into(),
             line,
             col: 1,
             is_definition: true,
             type_name: None,
             qual_type: None,
+            file_local: false,
         }],
         refs: Vec::new(),
         id_to_def: std::collections::HashMap::from([(format!("id-{line}"), 0)]),
-        name_to_defs: std::collections::HashMap::from([("shared_symbol".to_string(), vec![0])]),
+        name_to_defs: std::collections::HashMap::from([("shared_symbol".into(), vec![0])]),
         target_id_to_refs: std::collections::HashMap::new(),
-        file_to_defs: std::collections::HashMap::from([(path.display().to_string(), vec![0])]),
+        file_to_defs: std::collections::HashMap::from([(path.display().to_string().into(), vec![0])]),
         file_to_refs: std::collections::HashMap::new(),
+        specializations: Vec::new(),
     };
 
     let project_index = ProjectIndex::new();
@@ -493,6 +520,7 @@ fn project_index_fallback_prefers_graph_scoped_candidates() {
             line: 0,
             character: 0,
         },
+        &RankingWeights::default(),
     )
     .expect("graph-scoped lookup should resolve near candidate");
 
@@ -508,6 +536,60 @@ fn project_index_fallback_prefers_graph_scoped_candidates() {
     std::fs::remove_dir_all(&temp_dir).ok();
 }
 
+#[test]
+fn included_headers_are_indexed_on_demand_once_nearest_first() {
+    let temp_dir = std::env::temp_dir().join(format!(
+        "metal-analyzer-on-demand-index-test-{}",
+        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).expect("clock drift").as_nanos()
+    ));
+    std::fs::create_dir_all(&temp_dir).expect("create temp project dir");
+
+    let main_file = temp_dir.join("main.metal");
+    let near_file = temp_dir.join("near.h");
+    let far_file = temp_dir.join("far.h");
+    let indexed_file = temp_dir.join("indexed.h");
+    std::fs::write(&main_file, "#include \"near.h\"\n#include \"indexed.h\"\n").expect("write main source");
+    std::fs::write(&near_file, "#include \"far.h\"\n").expect("write near header");
+    std::fs::write(&far_file, "// far header\n").expect("write far header");
+    std::fs::write(&indexed_file, "// already indexed\n").expect("write indexed header");
+
+    let include_paths = vec![temp_dir.display().to_string()];
+    let main_id = FileId::from_path(&main_file);
+    let provider = DefinitionProvider::new();
+    provider.project_graph.update_file(&main_file, &std::fs::read_to_string(&main_file).unwrap(), &include_paths);
+    provider.project_graph.update_file(&near_file, "#include \"far.h\"\n", &include_paths);
+    provider.project_index.update_file(
+        indexed_file.clone(),
+        AstIndex {
+            defs: Vec::new(),
+            refs: Vec::new(),
+            id_to_def: std::collections::HashMap::new(),
+            name_to_defs: std::collections::HashMap::new(),
+            target_id_to_refs: std::collections::HashMap::new(),
+            file_to_defs: std::collections::HashMap::new(),
+            file_to_refs: std::collections::HashMap::new(),
+            specializations: Vec::new(),
+        },
+    );
+
+    assert_eq!(
+        provider.project_graph.included_files(&main_id, 3, 256),
+        vec![FileId::from_path(&indexed_file), FileId::from_path(&near_file), FileId::from_path(&far_file),]
+    );
+
+    provider.index_included_headers(&main_id, &include_paths, &|| false);
+    assert!(provider.on_demand_indexed.contains(&FileId::from_path(&near_file)));
+    assert!(provider.on_demand_indexed.contains(&FileId::from_path(&far_file)));
+    assert!(!provider.on_demand_indexed.contains(&FileId::from_path(&indexed_file)));
+
+    // Attempted headers are not dumped again until they change.
+    assert_eq!(provider.index_included_headers(&main_id, &include_paths, &|| false), 0);
+    provider.invalidate_file(&far_file);
+    assert!(!provider.on_demand_indexed.contains(&FileId::from_path(&far_file)));
+
+    std::fs::remove_dir_all(&temp_dir).ok();
+}
+
 #[test]
 fn cast_operators_are_non_navigable_symbols() {
     assert!(is_non_navigable_symbol("static_cast"));
@@ -678,7 +760,8 @@ kernel void k(device float* sinks [[buffer(0)]], uint tid [[thread_position_in_g
 
     for symbol in ["threadgroup_barrier", "mem_flags", "mem_threadgroup"] {
         let position = position_of(source, symbol);
-        let result = provider.provide(&uri, position, source, &include_paths, &snapshot, || false);
+        let result =
+            provider.provide(&uri, position, source, ContentHash::of(source), &include_paths, &snapshot, || false);
 
         let Some(NavigationTarget::Single(location)) = result else {
             panic!("expected goto-definition target for builtin symbol: {symbol}");
@@ -705,7 +788,9 @@ kernel void k(device float* sinks [[buffer(0)]], uint tid [[thread_position_in_g
     let source = source.to_owned();
     let (tx, rx) = std::sync::mpsc::channel();
     std::thread::spawn(move || {
-        let result = provider.provide(&uri, position, source.as_str(), &Vec::new(), &snapshot, || false);
+        let result =
+            provider
+                .provide(&uri, position, source.as_str(), ContentHash::of(&source), &Vec::new(), &snapshot, || false);
         let _ = tx.send(result);
     });
 
@@ -731,7 +816,7 @@ kernel void k(device float* sinks [[buffer(0)]], uint tid [[thread_position_in_g
     let provider = DefinitionProvider::new();
     let before = super::super::compiler::ast_dump_counter();
 
-    let result = provider.provide(&uri, position, source, &Vec::new(), &snapshot, || false);
+    let result = provider.provide(&uri, position, source, ContentHash::of(source), &Vec::new(), &snapshot, || false);
 
     let after = super::super::compiler::ast_dump_counter();
     assert!(result.is_none(), "goto-definition on `if` should return no symbol location");
@@ -774,7 +859,15 @@ fn concurrent_goto_definition_for_same_file_runs_single_ast_dump() {
         let barrier = Arc::clone(&barrier);
         handles.push(std::thread::spawn(move || {
             barrier.wait();
-            provider.provide(&uri, cursor, source.as_str(), include_paths.as_slice(), snapshot.as_ref(), || false)
+            provider.provide(
+                &uri,
+                cursor,
+                source.as_str(),
+                ContentHash::of(&source),
+                include_paths.as_slice(),
+                snapshot.as_ref(),
+                || false,
+            )
         }));
     }
 
@@ -821,7 +914,7 @@ fn concurrent_index_document_and_provide_share_single_ast_dump() {
         let barrier = Arc::clone(&barrier);
         std::thread::spawn(move || {
             barrier.wait();
-            provider.index_document(&uri, source.as_str(), include_paths.as_slice());
+            provider.index_document(&uri, source.as_str(), ContentHash::of(&source), include_paths.as_slice());
         })
     };
 
@@ -834,7 +927,15 @@ fn concurrent_index_document_and_provide_share_single_ast_dump() {
         let barrier = Arc::clone(&barrier);
         std::thread::spawn(move || {
             barrier.wait();
-            provider.provide(&uri, cursor, source.as_str(), include_paths.as_slice(), snapshot.as_ref(), || false)
+            provider.provide(
+                &uri,
+                cursor,
+                source.as_str(),
+                ContentHash::of(&source),
+                include_paths.as_slice(),
+                snapshot.as_ref(),
+                || false,
+            )
         })
     };
 
@@ -855,3 +956,225 @@ fn matches_position_accepts_cursor_at_token_end_boundary() {
 fn matches_position_rejects_cursor_past_token_end_boundary() {
     assert!(!matches_position("/tmp/member_method_tie.metal", 71, 20, 10, "/tmp/member_method_tie.metal", 71, 31,));
 }
+
+#[test]
+fn resolve_by_name_follows_configured_ranking_weights() {
+    let source_file = "/tmp/ranking_source.metal";
+    let source = "float blend(float a);\nkernel void k() { blend(1.0); }\n";
+    let position = position_of(source, "blend(1.0)");
+    let def = |id: &str, file: &str, line: u32, is_definition: bool| SymbolDef {
+        id: id.into(),
+        name: "blend".into(),
+        kind: "FunctionDecl".into(),
+        file: file.into(),
+        line,
+        col: 7,
+        is_definition,
+        type_name: None,
+        qual_type: Some("float (float)".into()),
+        file_local: false,
+    };
+    let index = AstIndex {
+        defs: vec![def("local-decl", source_file, 1, false), def("header-def", "/tmp/ranking_helpers.h", 4, true)],
+        refs: Vec::new(),
+        id_to_def: std::collections::HashMap::new(),
+        name_to_defs: std::collections::HashMap::from([("blend".into(), vec![0, 1])]),
+        target_id_to_refs: std::collections::HashMap::new(),
+        file_to_defs: std::collections::HashMap::new(),
+        file_to_refs: std::collections::HashMap::new(),
+        specializations: Vec::new(),
+    };
+    let resolved_file = |weights: &RankingWeights| {
+        let Some(NavigationTarget::Single(location)) =
+            resolve_by_name(&index, source_file, source, position, "blend", weights)
+        else {
+            panic!("expected a single location");
+        };
+        location.file_path.display().to_string()
+    };
+
+    assert_eq!(resolved_file(&RankingWeights::default()), source_file);
+    let prefer_definitions = RankingWeights {
+        other_file: 0,
+        ..RankingWeights::default()
+    };
+    assert_eq!(resolved_file(&prefer_definitions), "/tmp/ranking_helpers.h");
+}
+
+#[test]
+fn resolve_by_name_picks_overload_matching_call_arguments() {
+    let source_file = "/tmp/overload_source.metal";
+    let source = "float scale(float x) { return x; }
+float3 scale(float3 v) { return v; }
+float scale(float x, float y) { return x * y; }
+kernel void k() {
+    float3 dir = float3(1.0);
+    scale(dir);
+    scale(2.0);
+    scale(2.0, 3.0);
+    scale(1, dir);
+}
+";
+    let def = |id: &str, name: &str, kind: &str, line: u32, type_name: Option<&str>, qual_type: &str| SymbolDef {
+        id: id.into(),
+        name: name.into(),
+        kind: kind.into(),
+        file: source_file.into(),
+        line,
+        col: 7,
+        is_definition: true,
+        type_name: type_name.map(Into::into),
+        qual_type: Some(qual_type.into()),
+        file_local: false,
+    };
+    let index = AstIndex {
+        defs: vec![
+            def("scalar", "scale", "FunctionDecl", 1, None, "float (float)"),
+            def("vector", "scale", "FunctionDecl", 2, None, "float3 (float3)"),
+            def("binary", "scale", "FunctionDecl", 3, None, "float (float, float)"),
+            def("dir", "dir", "VarDecl", 5, Some("float3"), "float3"),
+        ],
+        refs: Vec::new(),
+        id_to_def: std::collections::HashMap::new(),
+        name_to_defs: std::collections::HashMap::from([("scale".into(), vec![0, 1, 2]), ("dir".into(), vec![3])]),
+        target_id_to_refs: std::collections::HashMap::new(),
+        file_to_defs: std::collections::HashMap::new(),
+        file_to_refs: std::collections::HashMap::new(),
+        specializations: Vec::new(),
+    };
+    let resolved_line = |call: &str| {
+        let position = position_of(source, call);
+        match resolve_by_name(&index, source_file, source, position, "scale", &RankingWeights::default()) {
+            Some(NavigationTarget::Single(location)) => Some(location.range.start.line + 1),
+            _ => None,
+        }
+    };
+
+    assert_eq!(resolved_line("scale(dir)"), Some(2));
+    assert_eq!(resolved_line("scale(2.0)"), Some(1));
+    assert_eq!(resolved_line("scale(2.0, 3.0)"), Some(3));
+    assert_eq!(resolved_line("scale(1, dir)"), None);
+}
+
+#[test]
+fn member_access_resolves_to_instantiated_specialization_member() {
+    let source_file = "/tmp/specialization_source.metal";
+    let source = "template <typename T> struct Tile {
+  T element_at(int i) const;
+};
+template <> struct Tile<half> {
+  half element_at(int i) const;
+};
+template <typename T> T first(Tile<T> tile) {
+  return tile.element_at(0);
+}
+kernel void k() { Tile<half> t; first(t); t.element_at(1); }
+";
+    // Clang emits `id` and `kind` before the other keys, which the AST
+    // deserializer relies on, so the dump is spelled out.
+    let loc = |line: u32, col: u32, len: u32| {
+        format!(r#"{{"offset":0,"file":"{source_file}","line":{line},"col":{col},"tokLen":{len}}}"#)
+    };
+    let decl = |id: &str, kind: &str, name: &str, at: String, qual_type: &str| {
+        format!(r#"{{"id":"{id}","kind":"{kind}","name":"{name}","loc":{at},"type":{{"qualType":"{qual_type}"}}}}"#)
+    };
+    let method = |id: &str, line: u32, col: u32, qual_type: &str| {
+        decl(id, "CXXMethodDecl", "element_at", loc(line, col, 10), qual_type)
+    };
+    let specialization = |id: &str, argument: &str, member: String| {
+        format!(
+            r#"{{"id":"{id}","kind":"ClassTemplateSpecializationDecl","name":"Tile","loc":{},"inner":[{{"id":"0x0","kind":"TemplateArgument","type":{{"qualType":"{argument}"}}}},{member}]}}"#,
+            loc(4, 20, 4)
+        )
+    };
+    let function = |id: &str, parameter: String| {
+        format!(r#"{{"id":"{id}","kind":"FunctionDecl","name":"first","loc":{},"inner":[{parameter}]}}"#, loc(7, 25, 5))
+    };
+    let primary = format!(
+        r#"{{"id":"0x10","kind":"ClassTemplateDecl","name":"Tile","loc":{0},"inner":[{{"id":"0x11","kind":"CXXRecordDecl","name":"Tile","loc":{0},"inner":[{1}]}}]}}"#,
+        loc(1, 30, 4),
+        method("0x12", 2, 5, "T (int) const")
+    );
+    let ast = format!(
+        r#"{{"id":"0x1","kind":"TranslationUnitDecl","inner":[{}]}}"#,
+        [
+            primary,
+            specialization("0x20", "half", method("0x21", 5, 8, "half (int) const")),
+            specialization("0x30", "float", method("0x31", 2, 5, "float (int) const")),
+            function("0x40", decl("0x41", "ParmVarDecl", "tile", loc(7, 39, 4), "Tile<T>")),
+            function("0x42", decl("0x43", "ParmVarDecl", "tile", loc(7, 39, 4), "Tile<half>")),
+            decl("0x50", "VarDecl", "t", loc(10, 30, 1), "Tile<half>"),
+        ]
+        .join(",")
+    );
+    let index = stream_index(ast.as_bytes()).expect("valid AST JSON").finish(&[], None);
+
+    let arguments: Vec<&[String]> = index.specializations.iter().map(|s| s.args.as_slice()).collect();
+    assert_eq!(arguments, vec![["half".to_string()], ["float".to_string()]]);
+
+    let resolved_line = |needle: &str| {
+        let position = position_of(source, needle);
+        match resolve_specialization_member(&index, source_file, source, position, "element_at") {
+            Some(NavigationTarget::Single(location)) => Some(location.range.start.line + 1),
+            _ => None,
+        }
+    };
+    assert_eq!(resolved_line("element_at(0)"), Some(5));
+    assert_eq!(resolved_line("element_at(1)"), Some(5));
+}
+
+#[test]
+fn index_marks_internal_linkage_definitions_file_local() {
+    let source_file = "/tmp/file_local_source.metal";
+    let loc = |line: u32| format!(r#"{{"offset":0,"file":"{source_file}","line":{line},"col":1,"tokLen":1}}"#);
+    let decl = |id: &str, kind: &str, name: &str, line: u32, qual_type: &str, extra: &str| {
+        format!(
+            r#"{{"id":"{id}","kind":"{kind}","name":"{name}","loc":{},"type":{{"qualType":"{qual_type}"}}{extra}}}"#,
+            loc(line)
+        )
+    };
+    let ast = format!(
+        r#"{{"id":"0x1","kind":"TranslationUnitDecl","inner":[{}]}}"#,
+        [
+            decl("0x10", "FunctionDecl", "helper", 1, "float (float)", r#","storageClass":"static""#),
+            decl("0x11", "FunctionDecl", "shared", 2, "float (float)", ""),
+            decl("0x12", "VarDecl", "kScale", 3, "const constant float", ""),
+            decl("0x13", "VarDecl", "table", 4, "const constant float *", ""),
+            decl(
+                "0x14",
+                "FunctionDecl",
+                "counter",
+                5,
+                "int ()",
+                &format!(
+                    r#","storageClass":"static","inner":[{}]"#,
+                    decl("0x15", "VarDecl", "calls", 6, "int", r#","storageClass":"static""#)
+                ),
+            ),
+        ]
+        .join(",")
+    );
+    let index = stream_index(ast.as_bytes()).expect("valid AST JSON").finish(&[], None);
+
+    let file_local: Vec<(&str, bool)> = index.defs.iter().map(|def| (def.name.as_str(), def.file_local)).collect();
+    assert_eq!(
+        file_local,
+        vec![
+            ("helper", true),
+            ("shared", false),
+            ("kScale", true),
+            ("table", false),
+            ("counter", true),
+            ("calls", false),
+        ]
+    );
+}
+
+#[test]
+fn ast_dump_flags_start_with_the_compiler_flags() {
+    let provider = DefinitionProvider::new();
+    provider.configure_compiler_flags(vec!["-DUSE_FAST_PATH=1".to_string(), "-D__METAL_IOS__".to_string()]);
+    let (include_paths, flags) = provider.with_overrides(None, &["/include".to_string()]);
+    assert_eq!(include_paths, vec!["/include".to_string()]);
+    assert_eq!(flags, vec!["-DUSE_FAST_PATH=1".to_string(), "-D__METAL_IOS__".to_string()]);
+}
@@ -0,0 +1,90 @@
+use super::*;
+
+fn fake_toolchain(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!(
+        "metal-analyzer-stdlib-map-{name}-{}-{}",
+        std::process::id(),
+        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).expect("clock drift").as_nanos()
+    ));
+    let dir = root.join("metal");
+    std::fs::create_dir_all(&dir).expect("create fake toolchain");
+    std::fs::write(dir.join("metal_stdlib"), "#include <metal_texture>\n#include <metal_atomic>\n")
+        .expect("write stdlib");
+    std::fs::write(
+        dir.join("metal_texture"),
+        r#"// texture2d is declared below.
+template <typename T, access a = access::sample>
+struct texture2d {
+  METAL_FUNC vec<T, 4> sample(sampler s, float2 coord) const thread;
+  METAL_FUNC vec<T, 4> read(uint2 coord) const thread;
+};
+"#,
+    )
+    .expect("write texture header");
+    std::fs::write(
+        dir.join("metal_atomic"),
+        r#"// see atomic_fetch_add_explicit
+METAL_FUNC int atomic_fetch_add_explicit(volatile device atomic_int *object, int operand, memory_order order);
+"#,
+    )
+    .expect("write atomic header");
+    root
+}
+
+fn line_of(location: &NavigationTarget) -> u32 {
+    let NavigationTarget::Single(location) = location else {
+        panic!("expected a single location");
+    };
+    location.range.start.line
+}
+
+#[test]
+fn lookup_finds_declarations_past_mentions_in_comments() {
+    let root = fake_toolchain("free");
+    let include_paths = vec![root.display().to_string()];
+    let map = StdlibMap::default();
+
+    let texture = map.lookup("texture2d", false, &include_paths).expect("texture2d");
+    assert_eq!(line_of(&texture), 2);
+    let atomic = map.lookup("atomic_fetch_add_explicit", false, &include_paths).expect("atomic");
+    assert_eq!(line_of(&atomic), 1);
+
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[test]
+fn lookup_finds_members_inside_their_type() {
+    let root = fake_toolchain("member");
+    let include_paths = vec![root.display().to_string()];
+    let map = StdlibMap::default();
+
+    let sample = map.lookup("sample", true, &include_paths).expect("sample");
+    assert_eq!(line_of(&sample), 3);
+    assert!(map.lookup("sample", false, &include_paths).is_none());
+    assert!(map.lookup("texture2d", true, &include_paths).is_none());
+
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[test]
+fn lookup_skips_uncurated_symbols_and_missing_headers() {
+    let root = fake_toolchain("missing");
+    let include_paths = vec![root.display().to_string()];
+    let map = StdlibMap::default();
+
+    assert!(map.lookup("my_kernel", false, &include_paths).is_none());
+    assert!(map.lookup("simd_sum", false, &include_paths).is_none());
+    assert!(map.lookup("texture2d", false, &[]).is_none());
+
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[test]
+fn find_declaration_offset_prefers_declarations() {
+    let source = "x = make(foo);\nstruct foo {};";
+    assert_eq!(find_declaration_offset(source, 0, "foo"), source.find("foo {"));
+    let source = "return bar(1);\nint bar(int);";
+    assert_eq!(find_declaration_offset(source, 0, "bar"), source.find("bar(int)"));
+    let source = "only baz mentioned";
+    assert_eq!(find_declaration_offset(source, 0, "baz"), source.find("baz"));
+}
@@ -0,0 +1,27 @@
+use super::*;
+
+#[test]
+fn mentions_identifier_requires_whole_word() {
+    let text = "float blur_radius = radius * 2.0;\nkernel void blur() {}\n";
+    assert!(mentions_identifier(text, "blur"));
+    assert!(mentions_identifier(text, "radius"));
+    assert!(!mentions_identifier(text, "blu"));
+    assert!(!mentions_identifier(text, "adius"));
+    assert!(!mentions_identifier(text, ""));
+}
+
+#[test]
+fn files_mentioning_keeps_matching_readable_files() {
+    let dir = std::env::temp_dir().join(format!("text_scan_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let uses = dir.join("uses.metal");
+    let other = dir.join("other.h");
+    let missing = dir.join("missing.h");
+    std::fs::write(&uses, "#include \"common.h\"\nvoid f() { helper(); }\n").unwrap();
+    std::fs::write(&other, "inline void helper_two() {}\n").unwrap();
+
+    let found = files_mentioning([uses.as_path(), other.as_path(), missing.as_path()], "helper");
+    assert_eq!(found, vec![uses.clone()]);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -30,3 +30,20 @@ fn store_update_unknown_creates() {
     store.update(uri.clone(), "content".to_string(), 1);
     assert!(store.get_content(&uri).is_some());
 }
+
+#[test]
+fn compiler_columns_follow_the_open_text() {
+    let store = DocumentStore::new();
+    let path = std::env::temp_dir().join(format!("document_store_columns_{}.metal", std::process::id()));
+    std::fs::write(&path, "float x;\n").unwrap();
+    let file = path.display().to_string();
+    let uri = Url::from_file_path(&path).unwrap();
+    store.open(uri.clone(), "float x;\n".to_string(), 1);
+    store.update(uri.clone(), "/* é */ float x;\n".to_string(), 2);
+
+    assert_eq!(crate::text_pos::column_in_file(&file, 0, 15), 14);
+    assert_eq!(crate::text_pos::line_in_file(&file, 0).as_deref(), Some("/* é */ float x;"));
+    store.close(&uri);
+    assert_eq!(crate::text_pos::line_in_file(&file, 0).as_deref(), Some("float x;"));
+    let _ = std::fs::remove_file(&path);
+}
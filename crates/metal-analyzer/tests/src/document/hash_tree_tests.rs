@@ -0,0 +1,80 @@
+use super::*;
+
+fn leaf(value: u64) -> blake3::Hash {
+    blake3::hash(&value.to_le_bytes())
+}
+
+/// Leaves drawn from a small alphabet so runs and repeats occur, as with
+/// blank lines and closing braces.
+fn leaves(
+    seed: &mut u64,
+    count: usize,
+) -> Vec<blake3::Hash> {
+    (0..count).map(|_| leaf(next(seed) % 24)).collect()
+}
+
+fn next(seed: &mut u64) -> u64 {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 7;
+    *seed ^= *seed << 17;
+    *seed
+}
+
+fn assert_same_tree(
+    tree: &HashTree,
+    leaves: &[blake3::Hash],
+) {
+    let fresh = HashTree::new(leaves.to_vec());
+    assert_eq!(tree.levels, fresh.levels);
+    assert_eq!(tree.chunk_starts, fresh.chunk_starts);
+}
+
+#[test]
+fn spliced_trees_match_trees_built_from_scratch() {
+    let mut seed = 0x9e37_79b9_7f4a_7c15;
+    let mut expected = leaves(&mut seed, 2_000);
+    let mut tree = HashTree::new(expected.clone());
+    for _ in 0..300 {
+        let start = (next(&mut seed) as usize) % (expected.len() + 1);
+        let len = ((next(&mut seed) as usize) % 40).min(expected.len() - start);
+        let count = (next(&mut seed) as usize) % 40;
+        let inserted = leaves(&mut seed, count);
+        if expected.len() - len + inserted.len() == 0 {
+            continue;
+        }
+        expected.splice(start..start + len, inserted.iter().copied());
+        tree.splice(start..start + len, inserted);
+        assert_same_tree(&tree, &expected);
+    }
+}
+
+#[test]
+fn trees_shrink_to_one_leaf_and_grow_back() {
+    let mut seed = 7;
+    let many = leaves(&mut seed, 500);
+    let mut tree = HashTree::new(many.clone());
+    tree.splice(0..500, vec![leaf(1)]);
+    assert_same_tree(&tree, &[leaf(1)]);
+    assert_eq!(tree.root(), leaf(1));
+
+    tree.splice(1..1, many.clone());
+    let mut expected = vec![leaf(1)];
+    expected.extend(many);
+    assert_same_tree(&tree, &expected);
+}
+
+#[test]
+fn editing_one_leaf_rehashes_only_its_path() {
+    let mut seed = 42;
+    let leaves = leaves(&mut seed, 20_000);
+    let mut tree = HashTree::new(leaves);
+    let before = tree.levels.clone();
+    tree.splice(10_000..10_001, vec![leaf(1_000)]);
+
+    let changed: usize = before[1..]
+        .iter()
+        .zip(&tree.levels[1..])
+        .map(|(old, new)| new.iter().filter(|hash| !old.contains(hash)).count())
+        .sum();
+    assert!(changed <= 2 * tree.levels.len(), "{changed} nodes rehashed over {} levels", tree.levels.len());
+}
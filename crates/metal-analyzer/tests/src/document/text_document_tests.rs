@@ -91,3 +91,53 @@ fn full_content_change() {
     assert_eq!(doc.text, "new content");
     assert_eq!(doc.version, 3);
 }
+
+fn edit(
+    start: (u32, u32),
+    end: (u32, u32),
+    text: &str,
+) -> TextDocumentContentChangeEvent {
+    TextDocumentContentChangeEvent {
+        range: Some(Range {
+            start: Position {
+                line: start.0,
+                character: start.1,
+            },
+            end: Position {
+                line: end.0,
+                character: end.1,
+            },
+        }),
+        range_length: None,
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn incremental_edits_keep_lines_and_hash_in_sync() {
+    let mut doc = test_doc("kernel void k(\n    uint gid [[thread_position_in_grid]])\n{\n}\n");
+    let edits = [
+        edit((2, 1), (2, 1), "\n    float x = 1.0;\n    float y = x;"),
+        edit((1, 4), (1, 8), "uint2"),
+        edit((0, 14), (2, 5), ") {"),
+        edit((0, 0), (0, 0), "#include <metal_stdlib>\r\n"),
+        edit((3, 0), (4, 1), ""),
+    ];
+    for (version, change) in edits.into_iter().enumerate() {
+        doc.apply_changes(vec![change], version as i32 + 2);
+        let fresh = test_doc(&doc.text);
+        assert_eq!(doc.content_hash(), ContentHash::of(&doc.text), "after edit {version}: {:?}", doc.text);
+        assert_eq!(doc.content_hash(), fresh.content_hash());
+        assert_eq!(doc.line_count(), fresh.line_count());
+        for line in 0..doc.line_count() {
+            assert_eq!(doc.line_text(line), fresh.line_text(line));
+        }
+    }
+}
+
+#[test]
+fn content_hash_depends_on_line_order() {
+    assert_ne!(ContentHash::of("a\nb\n"), ContentHash::of("b\na\n"));
+    assert_ne!(ContentHash::of("a"), ContentHash::of("a\n"));
+    assert_eq!(ContentHash::of(""), test_doc("").content_hash());
+}
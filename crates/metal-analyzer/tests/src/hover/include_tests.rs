@@ -0,0 +1,61 @@
+use super::*;
+
+fn directive(source: &str) -> IncludeDirective {
+    include_directives(source).remove(0)
+}
+
+fn markdown(hover: &Hover) -> &str {
+    match &hover.contents {
+        HoverContents::Markup(content) => &content.value,
+        _ => panic!("expected markdown"),
+    }
+}
+
+#[test]
+fn resolved_include_shows_the_root_shadowed_files_and_declarations() {
+    let owner = Path::new("/p/shaders/blur.metal");
+    let directive = directive("#include \"Light.h\"\n");
+    let candidates = [
+        (PathBuf::from("/p/shaders/Light.h"), IncludeRoot::IncludingDirectory(PathBuf::from("/p/shaders"))),
+        (PathBuf::from("/p/shaders/Light.h"), IncludeRoot::SearchPath(PathBuf::from("/p/shaders"))),
+        (PathBuf::from("/p/include/Light.h"), IncludeRoot::SearchPath(PathBuf::from("/p/include"))),
+    ];
+    let header = "#ifndef LIGHT_H\n#define LIGHT_H\n#define MAX_LIGHTS 8\nstruct Light { float3 color; };\n\
+                  float3 shade(Light light);\ntypedef float Lux;\n#endif\n";
+    let hover = make_include_hover(owner, &directive, &candidates, &[], Some(header));
+    assert_eq!(
+        markdown(&hover),
+        "**`/p/shaders/Light.h`**\n\nFound next to the including file, in `/p/shaders`.\n\n\
+         Shadows:\n- `/p/include/Light.h` (include path `/p/include`)\n\n---\n\n\
+         Provides `MAX_LIGHTS` (macro), `Light` (struct), `shade` (function), `Lux` (type alias).\n"
+    );
+    assert_eq!(hover.range, Some(directive.range));
+}
+
+#[test]
+fn long_declaration_lists_are_summarized() {
+    let header: String = (0..12).map(|index| format!("struct S{index} {{}};\n")).collect();
+    let summary = provided_summary(&header).unwrap();
+    assert!(summary.starts_with("Provides `S0` (struct), "));
+    assert!(summary.ends_with("`S9` (struct) and 2 more."));
+    assert_eq!(
+        provided_summary("#include <metal_stdlib>\n").as_deref(),
+        Some("Declares nothing itself; includes 1 header.")
+    );
+    assert_eq!(provided_summary("// empty\n"), None);
+}
+
+#[test]
+fn unresolved_include_lists_the_searched_directories() {
+    let owner = Path::new("/p/shaders/blur.metal");
+    let include_paths = ["/p/include".to_string(), format!("{FRAMEWORK_DIR_PREFIX}/sdk/Frameworks")];
+    let hover = make_include_hover(owner, &directive("#include \"Missing.h\"\n"), &[], &include_paths, None);
+    assert_eq!(
+        markdown(&hover),
+        "`Missing.h` was not found. Searched:\n- `/p/shaders` (including file's directory)\n- `/p/include`\n\
+         - `/sdk/Frameworks` (frameworks)\n"
+    );
+
+    let hover = make_include_hover(owner, &directive("#include <Missing.h>\n"), &[], &include_paths[..1], None);
+    assert_eq!(markdown(&hover), "`Missing.h` was not found. Searched:\n- `/p/include`\n");
+}
@@ -0,0 +1,62 @@
+use super::*;
+
+fn markdown(
+    kind: SyntaxKind,
+    literal: &str,
+) -> String {
+    match make_number_hover(kind, literal).expect("hover").contents {
+        HoverContents::Markup(content) => content.value,
+        _ => panic!("expected markdown"),
+    }
+}
+
+#[test]
+fn integer_literal_shows_decimal_hex_and_binary() {
+    assert_eq!(
+        markdown(SyntaxKind::Integer, "0xFF"),
+        "**Integer literal** · `int`\n\n- Decimal: `255`\n- Hex: `0xFF`\n- Binary: `0b1111'1111`\n"
+    );
+    assert_eq!(
+        markdown(SyntaxKind::Integer, "017u"),
+        "**Integer literal** · `uint`\n\n- Decimal: `15`\n- Hex: `0xF`\n- Octal: `017`\n- Binary: `0b1111`\n"
+    );
+}
+
+#[test]
+fn integer_literal_types_follow_the_cpp_rules() {
+    assert!(markdown(SyntaxKind::Integer, "0xFFFFFFFF").starts_with("**Integer literal** · `uint`"));
+    assert!(markdown(SyntaxKind::Integer, "4294967295").starts_with("**Integer literal** · `long`"));
+    assert!(markdown(SyntaxKind::Integer, "1ul").starts_with("**Integer literal** · `ulong`"));
+    assert!(markdown(SyntaxKind::Integer, "0xFFFFFFFF").contains("- As `int`: `-1`\n"));
+    assert!(markdown(SyntaxKind::Integer, "0xFFFFFFFFFFFFFFFF").contains("- As `long`: `-1`\n"));
+    assert_eq!(markdown(SyntaxKind::Integer, "0x1FFFFFFFFFFFFFFFF"), "**Integer literal**\n\nToo large for `ulong`.\n");
+}
+
+#[test]
+fn float_literal_shows_the_nearest_float_and_its_bits() {
+    assert_eq!(
+        markdown(SyntaxKind::Float, "0.1f"),
+        "**Floating-point literal** · `float`\n\n- Value: `0.1`\n- Nearest `float`: `0.10000000149011612`\n\
+         - Bits: `0x3DCCCCCD`\n- Sign, exponent, mantissa: `0 01111011 10011001100110011001101`\n"
+    );
+    assert_eq!(
+        markdown(SyntaxKind::Float, "1.0"),
+        "**Floating-point literal** · `float`\n\n- Value: `1` (exact)\n- Bits: `0x3F800000`\n\
+         - Sign, exponent, mantissa: `0 01111111 00000000000000000000000`\n"
+    );
+}
+
+#[test]
+fn half_literal_uses_the_half_bit_pattern() {
+    assert_eq!(
+        markdown(SyntaxKind::Float, "0.1h"),
+        "**Floating-point literal** · `half`\n\n- Value: `0.1`\n- Nearest `half`: `0.0999755859375`\n\
+         - Bits: `0x2E66`\n- Sign, exponent, mantissa: `0 01011 1001100110`\n"
+    );
+    assert!(markdown(SyntaxKind::Float, "1e5h").contains("- Nearest `half`: `inf`\n"));
+}
+
+#[test]
+fn other_tokens_have_no_number_hover() {
+    assert!(make_number_hover(SyntaxKind::Ident, "x").is_none());
+}
@@ -0,0 +1,63 @@
+use super::*;
+
+fn function(
+    qual_type: &str,
+    file: &str,
+    line: u32,
+    is_definition: bool,
+) -> SymbolDef {
+    SymbolDef {
+        id: format!("{file}-{line}"),
+        name: "blend".into(),
+        kind: "FunctionDecl".into(),
+        file: file.into(),
+        line,
+        col: 6,
+        is_definition,
+        type_name: None,
+        qual_type: Some(qual_type.into()),
+        file_local: false,
+    }
+}
+
+fn markdown(hover: Hover) -> String {
+    match hover.contents {
+        HoverContents::Markup(content) => content.value,
+        _ => panic!("expected markdown"),
+    }
+}
+
+#[test]
+fn distinct_overloads_keep_one_entry_per_signature_preferring_definitions() {
+    let overloads = distinct_overloads(vec![
+        function("float (float, float)", "/p/blend.h", 3, false),
+        function("half (half, half)", "/p/blend.h", 4, false),
+        function("float (float, float)", "/p/blend.metal", 10, true),
+        SymbolDef {
+            kind: "VarDecl".into(),
+            ..function("float", "/p/blend.metal", 20, true)
+        },
+    ]);
+    let lines: Vec<(String, u32)> = overloads.iter().map(|o| (o.file.to_string(), o.line)).collect();
+    assert_eq!(lines, vec![("/p/blend.metal".to_string(), 10), ("/p/blend.h".to_string(), 4)]);
+}
+
+#[test]
+fn overload_signature_puts_the_name_between_return_and_parameters() {
+    assert_eq!(overload_signature(&function("float (float, float)", "/p/a.h", 1, true)), "float blend(float, float)");
+}
+
+#[test]
+fn overload_hover_lists_every_signature_and_marks_the_call_match() {
+    let overloads = vec![
+        function("float (float, float)", "/p/blend.metal", 10, true),
+        function("half (half, half)", "/p/blend.h", 4, false),
+    ];
+    assert_eq!(
+        markdown(make_overload_hover(&overloads, Some(1))),
+        "```metal\nhalf blend(half, half)\n```\n\n**2 overloads**\n\n\
+         - `float blend(float, float)` — `blend.metal:10`\n\
+         - **`half blend(half, half)`** — `blend.h:4` *(this call)*\n"
+    );
+    assert!(markdown(make_overload_hover(&overloads, None)).starts_with("```metal\nfloat blend(float, float)\n```"));
+}
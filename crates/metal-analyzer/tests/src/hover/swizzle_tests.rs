@@ -0,0 +1,88 @@
+use super::*;
+use crate::syntax::SyntaxTree;
+
+const SOURCE: &str = "struct Light { float4 color; half3 dirs[2]; };\n\
+                      constant float3 kUp = {0, 1, 0};\n\
+                      kernel void k(device float4 *out, constant Light &light, device Light *lights,\n\
+                      \x20             float2 uv [[stage_in]]) {\n\
+                      \x20   float4 v = out[0], w;\n\
+                      \x20   auto s = v.zyx;\n\
+                      \x20   out[0].xy = light.color.bgra.xy + uv.yx + kUp.xx;\n\
+                      \x20   half3 d = light.dirs[1].zyx + lights->dirs[0].xyz;\n\
+                      \x20   float b = float4(1).w + s.x + w.rgba.r;\n\
+                      }\n";
+
+/// The hover on the token right after the first `prefix` in `SOURCE`.
+fn hover_after(
+    prefix: &str,
+    infer: &dyn Fn(&str) -> Option<String>,
+) -> Option<String> {
+    let offset = SOURCE.find(prefix).expect("prefix") + prefix.len();
+    let line = SOURCE[..offset].matches('\n').count() as u32;
+    let character = (offset - SOURCE[..offset].rfind('\n').map_or(0, |newline| newline + 1)) as u32;
+    let tree = SyntaxTree::parse(SOURCE);
+    let hover = make_swizzle_hover(&tree.root(), SOURCE, Position::new(line, character), infer)?;
+    match hover.contents {
+        HoverContents::Markup(content) => Some(content.value),
+        _ => panic!("expected markdown"),
+    }
+}
+
+fn no_infer(_: &str) -> Option<String> {
+    None
+}
+
+#[test]
+fn swizzle_of_a_local_shows_the_mapping() {
+    assert_eq!(
+        hover_after("= v.", &no_infer).as_deref(),
+        Some(
+            "**Swizzle** `.zyx` of `float4` → `float3`\n\nThe first three components in reverse order.\n\n\
+             - `x` ← `z`\n- `y` ← `y`\n- `z` ← `x`\n"
+        )
+    );
+}
+
+#[test]
+fn swizzles_follow_parameters_fields_subscripts_and_globals() {
+    let first_line =
+        |prefix| hover_after(prefix, &no_infer).map(|md| md.lines().next().unwrap_or_default().to_string());
+    assert_eq!(first_line("out[0].").as_deref(), Some("**Swizzle** `.xy` of `float4` → `float2`"));
+    assert_eq!(first_line("light.color.").as_deref(), Some("**Swizzle** `.bgra` of `float4` → `float4`"));
+    assert_eq!(first_line("bgra.").as_deref(), Some("**Swizzle** `.xy` of `float4` → `float2`"));
+    assert_eq!(first_line("uv.").as_deref(), Some("**Swizzle** `.yx` of `float2` → `float2`"));
+    assert_eq!(first_line("light.dirs[1].").as_deref(), Some("**Swizzle** `.zyx` of `half3` → `half3`"));
+    assert_eq!(first_line("lights->dirs[0].").as_deref(), Some("**Swizzle** `.xyz` of `half3` → `half3`"));
+    assert_eq!(first_line("float4(1).").as_deref(), Some("**Swizzle** `.w` of `float4` → `float`"));
+    assert_eq!(first_line("+ w.").as_deref(), Some("**Swizzle** `.rgba` of `float4` → `float4`"));
+}
+
+#[test]
+fn repeated_components_are_not_assignable() {
+    assert_eq!(
+        hover_after("kUp.", &no_infer).as_deref(),
+        Some(
+            "**Swizzle** `.xx` of `float3` → `float2`\n\nComponent `x` repeated two times.\n\n\
+             - `x` ← `x`\n- `y` ← `x`\n\nRepeats a component, so it cannot be assigned to.\n"
+        )
+    );
+}
+
+#[test]
+fn auto_receivers_are_typed_by_infer() {
+    assert_eq!(hover_after("+ s.", &no_infer), None, "`s` is declared `auto`");
+    let infer = |name: &str| (name == "s").then(|| "float3".to_string());
+    assert_eq!(
+        hover_after("+ s.", &infer).as_deref(),
+        Some("**Swizzle** `.x` of `float3` → `float`\n\nComponent `x` (index 0).\n")
+    );
+}
+
+#[test]
+fn members_that_are_not_swizzles_have_no_hover() {
+    assert_eq!(hover_after("= light.", &no_infer), None);
+    assert_eq!(swizzle_indices("xyzw", 3), None);
+    assert_eq!(swizzle_indices("xg", 4), None);
+    assert_eq!(vector_type("packed_float3"), Some(("float", 3)));
+    assert_eq!(vector_type("float4x4"), None);
+}
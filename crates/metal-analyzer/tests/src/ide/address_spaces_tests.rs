@@ -0,0 +1,107 @@
+use super::*;
+use crate::syntax::SyntaxTree;
+
+fn messages(source: &str) -> Vec<(u32, String)> {
+    let tree = SyntaxTree::parse(source);
+    address_space_diagnostics(&tree.root(), source)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.range.start.line, diagnostic.message))
+        .collect()
+}
+
+#[test]
+fn entry_point_pointers_need_an_address_space() {
+    let source = "kernel void k(float* data [[buffer(0)]], const Params& params [[buffer(1)]],\n              \
+                  device float* out [[buffer(2)]], uint id [[thread_position_in_grid]]) {}\nvoid helper(float* \
+                  scratch) {}\n";
+    assert_eq!(
+        messages(source),
+        vec![
+            (
+                0,
+                "`data` is a pointer parameter of an entry point and needs an address space, such as `device` or \
+                 `constant`"
+                    .to_string()
+            ),
+            (
+                0,
+                "`params` is a reference parameter of an entry point and needs an address space, such as `device` \
+                 or `constant`"
+                    .to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn thread_pointers_to_other_address_spaces() {
+    let source = "kernel void k(device float* buffer [[buffer(0)]], threadgroup float* shared [[threadgroup(0)]]) \
+                  {\n    thread float* a = &buffer[4];\n    float* b = buffer + 1;\n    float& c = shared[0];\n    \
+                  device float* d = buffer;\n    auto e = buffer;\n    float f = buffer[0];\n}\n";
+    assert_eq!(
+        messages(source),
+        vec![
+            (1, "`a` is a `thread` pointer but points to `device` memory".to_string()),
+            (2, "`b` is a `thread` pointer by default but points to `device` memory".to_string()),
+            (3, "`c` is a `thread` reference by default but refers to `threadgroup` memory".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn locals_and_program_scope_variables_are_followed() {
+    let source = "constant float weights[4] = {1, 2, 3, 4};\nvoid f() {\n    threadgroup float tile[64];\n    \
+                  float* t = tile;\n    float* w = &weights[1];\n    {\n        float* tile = nullptr;\n        \
+                  float* u = tile;\n    }\n}\n";
+    assert_eq!(
+        messages(source),
+        vec![
+            (3, "`t` is a `thread` pointer by default but points to `threadgroup` memory".to_string()),
+            (4, "`w` is a `thread` pointer by default but points to `constant` memory".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn writes_to_constant_memory() {
+    let source = "constant float scale = 2;\nkernel void k(constant float* input [[buffer(0)]], constant Params& \
+                  params [[buffer(1)]]) {\n    input[0] = 1;\n    params.count += 1;\n    *input = 2;\n    \
+                  input->x++;\n    --input[1];\n    scale = 3;\n    constant float* next = input + 1;\n    next = \
+                  input;\n    for (constant float* p = input; p != next; ++p) {}\n}\n";
+    assert_eq!(
+        messages(source),
+        vec![
+            (2, "`input` points to read-only `constant` memory".to_string()),
+            (3, "`params` refers to read-only `constant` memory".to_string()),
+            (4, "`input` points to read-only `constant` memory".to_string()),
+            (5, "`input` points to read-only `constant` memory".to_string()),
+            (6, "`input` points to read-only `constant` memory".to_string()),
+            (7, "`scale` is in read-only `constant` memory".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn returning_threadgroup_memory_through_a_thread_reference() {
+    let source = "float& slot(threadgroup float* shared, uint i) {\n    return shared[i];\n}\nthread float* \
+                  first(threadgroup float* shared) {\n    if (shared) return &shared[0];\n    return nullptr;\n}\n\
+                  threadgroup float& ok(threadgroup float* shared) { return shared[0]; }\nfloat value(threadgroup \
+                  float* shared) { return shared[0]; }\n";
+    assert_eq!(
+        messages(source),
+        vec![
+            (1, "`slot` returns a `thread` reference by default but this refers to `threadgroup` memory".to_string()),
+            (4, "`first` returns a `thread` pointer but this points to `threadgroup` memory".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn diagnostics_carry_the_code_and_range() {
+    let source = "kernel void k(float* data) {}\n";
+    let tree = SyntaxTree::parse(source);
+    let diagnostics = address_space_diagnostics(&tree.root(), source);
+    assert_eq!(diagnostics[0].code, Some(NumberOrString::String(ADDRESS_SPACE_CODE.to_string())));
+    assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!((diagnostics[0].range.start.character, diagnostics[0].range.end.character), (21, 25));
+}
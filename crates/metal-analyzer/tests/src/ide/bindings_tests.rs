@@ -0,0 +1,89 @@
+use super::*;
+use crate::syntax::SyntaxTree;
+
+const SOURCE: &str = r#"
+kernel void first(device float* input [[buffer(0)]],
+                  device float* output [[buffer(2)]]) {}
+
+kernel void second(constant Params& params [[buffer(2)]],
+                   texture2d<float> tex [[texture(2)]]) {}
+
+struct VertexIn {
+    float4 position [[attribute(2)]];
+};
+"#;
+
+fn slot(
+    name: &str,
+    index: u32,
+) -> BindingSlot {
+    BindingSlot {
+        name: name.to_string(),
+        index,
+    }
+}
+
+#[test]
+fn parses_indexed_attribute_items() {
+    assert_eq!(parse_binding_slot("buffer(2)"), Some(slot("buffer", 2)));
+    assert_eq!(parse_binding_slot("texture( 11 )"), Some(slot("texture", 11)));
+    assert_eq!(parse_binding_slot("user(locn0)"), None);
+    assert_eq!(parse_binding_slot("position"), None);
+}
+
+#[test]
+fn slot_at_cursor_reads_attribute_under_cursor() {
+    let tree = SyntaxTree::parse(SOURCE);
+    let line = SOURCE.lines().nth(2).unwrap();
+    let column = line.find("buffer(2)").unwrap() as u32 + 3;
+    let found = binding_slot_at_position(&tree.root(), SOURCE, Position::new(2, column));
+    assert_eq!(found, Some(slot("buffer", 2)));
+}
+
+#[test]
+fn finds_every_site_sharing_the_slot() {
+    let tree = SyntaxTree::parse(SOURCE);
+    let sites = find_binding_sites(&tree.root(), SOURCE, &slot("buffer", 2));
+    let summary: Vec<_> = sites.iter().map(|s| (s.function.as_deref(), s.declarator.as_deref())).collect();
+    assert_eq!(summary, vec![(Some("first"), Some("output")), (Some("second"), Some("params"))]);
+    assert_eq!(sites[0].range.start.line, 2);
+}
+
+#[test]
+fn different_attribute_kinds_do_not_match() {
+    let tree = SyntaxTree::parse(SOURCE);
+    let sites = find_binding_sites(&tree.root(), SOURCE, &slot("texture", 2));
+    assert_eq!(sites.len(), 1);
+    assert_eq!(sites[0].declarator.as_deref(), Some("tex"));
+
+    let fields = find_binding_sites(&tree.root(), SOURCE, &slot("attribute", 2));
+    assert_eq!(fields.len(), 1);
+    assert_eq!(fields[0].function, None);
+    assert_eq!(fields[0].declarator.as_deref(), Some("position"));
+}
+
+#[test]
+fn duplicate_resource_slots_within_a_function_are_reported() {
+    let source = "\
+kernel void blur(device float* input [[buffer(0)]],
+                 device float* output [[buffer(0)]],
+                 texture2d<float> a [[texture(0)]],
+                 uint gid [[thread_position_in_grid]]) {}
+kernel void copy(device float* input [[buffer(0)]]) {}
+";
+    let uri = Url::from_file_path("/tmp/blur.metal").unwrap();
+    let tree = SyntaxTree::parse(source);
+    let diagnostics = duplicate_bindings(&tree.root(), source, &uri);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "`buffer(0)` is already bound by another parameter");
+    assert_eq!(diagnostics[0].range, Range::new(Position::new(1, 40), Position::new(1, 49)));
+    let related = diagnostics[0].related_information.as_ref().unwrap();
+    assert_eq!(related[0].location.range, Range::new(Position::new(0, 39), Position::new(0, 48)));
+}
+
+#[test]
+fn shared_slots_across_functions_and_fields_are_not_duplicates() {
+    let tree = SyntaxTree::parse(SOURCE);
+    let uri = Url::from_file_path("/tmp/shaders.metal").unwrap();
+    assert!(duplicate_bindings(&tree.root(), SOURCE, &uri).is_empty());
+}
@@ -0,0 +1,142 @@
+use std::path::PathBuf;
+
+use super::*;
+use crate::definition::{AstIndex, RefSite, SymbolDef};
+
+const SOURCE: &str = r#"
+struct Light;
+
+struct Material {
+    float roughness;
+};
+
+float shade(float x) { return x; }
+
+float declared(float x);
+
+kernel void compute_main(device float* out [[buffer(0)]]) {
+    out[0] = shade(1.0);
+}
+"#;
+
+fn uri() -> Url {
+    Url::parse("file:///ws/a.metal").unwrap()
+}
+
+fn def(
+    name: &str,
+    kind: &str,
+    line: u32,
+    type_name: Option<&str>,
+) -> SymbolDef {
+    SymbolDef {
+        id: format!("{name}-{line}"),
+        name: name.into(),
+        kind: kind.into(),
+        file: "/ws/a.metal".into(),
+        line,
+        col: 5,
+        is_definition: true,
+        type_name: type_name.map(str::to_string),
+        qual_type: None,
+        file_local: false,
+    }
+}
+
+fn call(
+    target: &SymbolDef,
+    file: &str,
+    line: u32,
+) -> RefSite {
+    RefSite {
+        file: file.into(),
+        line,
+        col: 14,
+        tok_len: target.name.len() as u32,
+        target_id: target.id.clone(),
+        target_name: target.name.clone(),
+        target_kind: target.kind.clone(),
+        expansion: None,
+        spelling: None,
+    }
+}
+
+#[test]
+fn reference_lenses_cover_helpers_and_defined_structs() {
+    let lenses = reference_lenses(&SyntaxTree::parse(SOURCE));
+    let found: Vec<(u32, ReferenceLensData)> = lenses
+        .iter()
+        .map(|lens| {
+            assert!(lens.command.is_none());
+            (lens.range.start.line, serde_json::from_value(lens.data.clone().unwrap()).unwrap())
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                3,
+                ReferenceLensData {
+                    name: "Material".to_string(),
+                    kind: ReferenceLensKind::Struct,
+                }
+            ),
+            (
+                7,
+                ReferenceLensData {
+                    name: "shade".to_string(),
+                    kind: ReferenceLensKind::Function,
+                }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn resolving_counts_references_across_translation_units() {
+    let shade = def("shade", "FunctionDecl", 7, None);
+    let project_index = ProjectIndex::new();
+    project_index.update_file(
+        PathBuf::from("/ws/a.metal"),
+        AstIndex::from_parts(
+            vec![
+                shade.clone(),
+                def("material", "ParmVarDecl", 12, Some("const Material &")),
+                def("base", "FieldDecl", 4, Some("metal::Material")),
+            ],
+            vec![call(&shade, "/ws/a.metal", 12), call(&shade, "/ws/common.h", 3)],
+            Vec::new(),
+        ),
+    );
+    project_index.update_file(
+        PathBuf::from("/ws/b.metal"),
+        AstIndex::from_parts(vec![shade.clone()], vec![call(&shade, "/ws/common.h", 3)], Vec::new()),
+    );
+
+    let titles: Vec<String> = reference_lenses(&SyntaxTree::parse(SOURCE))
+        .into_iter()
+        .map(|lens| resolve_reference_lens(lens, &project_index).command.unwrap().title)
+        .collect();
+    assert_eq!(titles, vec!["1 reference", "2 references"]);
+}
+
+#[test]
+fn file_actions_pass_the_document_uri() {
+    let lenses = file_action_lenses(&uri());
+    let commands: Vec<(String, String)> = lenses
+        .iter()
+        .map(|lens| {
+            let command = lens.command.clone().unwrap();
+            assert_eq!(command.arguments, Some(vec![serde_json::Value::String(uri().to_string())]));
+            (command.title, command.command)
+        })
+        .collect();
+    assert_eq!(
+        commands,
+        vec![
+            ("Run diagnostics".to_string(), RUN_DIAGNOSTICS_COMMAND.to_string()),
+            ("Re-index file".to_string(), REINDEX_FILE_COMMAND.to_string()),
+        ]
+    );
+    assert_eq!(resolve_reference_lens(lenses[0].clone(), &ProjectIndex::new()), lenses[0]);
+}
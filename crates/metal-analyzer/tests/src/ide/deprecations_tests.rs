@@ -0,0 +1,76 @@
+use tower_lsp::lsp_types::{DiagnosticSeverity, Position};
+
+use super::*;
+use crate::metal::compiler::MetalDiagnostic;
+
+const SOURCE: &str = "\
+kernel void k(device float* out [[buffer(0)]],
+              uint width [[thread_execution_width]])
+{
+}
+";
+
+fn compiler_warning(
+    line: u32,
+    column: u32,
+    message: &str,
+) -> Diagnostic {
+    MetalDiagnostic {
+        file: None,
+        line,
+        column,
+        end: None,
+        severity: DiagnosticSeverity::WARNING,
+        message: message.to_string(),
+        related_information: Vec::new(),
+        fix_its: Vec::new(),
+    }
+    .into_lsp_diagnostic()
+}
+
+fn deprecated_attribute_warning() -> Diagnostic {
+    compiler_warning(
+        1,
+        27,
+        "'thread_execution_width' attribute is deprecated: use threads_per_simdgroup [-Wdeprecated-attributes]",
+    )
+}
+
+#[test]
+fn compiler_deprecations_are_tagged() {
+    assert_eq!(deprecated_attribute_warning().tags, Some(vec![DiagnosticTag::DEPRECATED]));
+    let unused = compiler_warning(1, 19, "unused parameter 'width' [-Wunused-parameter]");
+    assert_eq!(unused.tags, None);
+}
+
+#[test]
+fn deprecation_range_covers_the_identifier() {
+    let mut diagnostics = vec![deprecated_attribute_warning()];
+    widen_deprecation_ranges(&mut diagnostics, SOURCE);
+    assert_eq!(diagnostics[0].range, Range::new(Position::new(1, 27), Position::new(1, 49)));
+}
+
+#[test]
+fn known_replacement_offers_a_quick_fix() {
+    let uri = Url::parse("file:///tmp/k.metal").unwrap();
+    let actions = upgrade_actions(&uri, SOURCE, &[deprecated_attribute_warning()]);
+    let [CodeActionOrCommand::CodeAction(action)] = actions.as_slice() else {
+        panic!("expected one code action, got {actions:?}");
+    };
+    assert_eq!(action.kind, Some(CodeActionKind::QUICKFIX));
+    let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+    assert_eq!(
+        edits,
+        &vec![TextEdit::new(
+            Range::new(Position::new(1, 27), Position::new(1, 49)),
+            "threads_per_simdgroup".to_string()
+        )]
+    );
+}
+
+#[test]
+fn unknown_deprecations_have_no_quick_fix() {
+    let uri = Url::parse("file:///tmp/k.metal").unwrap();
+    let warning = compiler_warning(1, 14, "'legacy_sample' is deprecated [-Wdeprecated-declarations]");
+    assert!(upgrade_actions(&uri, "legacy_sample(t, s, uv);\n", &[warning]).is_empty());
+}
@@ -0,0 +1,61 @@
+use super::*;
+use crate::syntax::SyntaxTree;
+
+fn messages(source: &str) -> Vec<String> {
+    let tree = SyntaxTree::parse(source);
+    entry_point_attribute_diagnostics(&tree.root(), source).into_iter().map(|d| d.message).collect()
+}
+
+#[test]
+fn flags_thread_index_attributes_on_non_integer_types() {
+    let source = "kernel void k(float2 gid [[thread_position_in_grid]],\n\
+                  uint2 ok [[threads_per_grid]],\n\
+                  ushort lane [[thread_index_in_simdgroup]],\n\
+                  Index custom [[thread_index_in_threadgroup]]) {}\n";
+    assert_eq!(
+        messages(source),
+        vec!["`[[thread_position_in_grid]]` needs `uint` or `ushort`, or a vector of them, not `float2`"]
+    );
+}
+
+#[test]
+fn flags_stage_in_on_kernels_only() {
+    let source = "kernel void k(VIn v [[stage_in]]) {}\nvertex float4 vs(VIn v [[stage_in]]) { return 0; }\n";
+    let diagnostics = {
+        let tree = SyntaxTree::parse(source);
+        entry_point_attribute_diagnostics(&tree.root(), source)
+    };
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].message.starts_with("`[[stage_in]]` is not valid on kernel `k`"));
+    assert_eq!(diagnostics[0].range.start.line, 0);
+    assert_eq!(diagnostics[0].code, Some(NumberOrString::String(ENTRY_POINT_ATTRIBUTE_CODE.to_string())));
+    assert_eq!(diagnostics[0].code_description.as_ref().map(|d| d.href.as_str()), Some(SPEC_URL));
+}
+
+#[test]
+fn flags_vertex_structs_without_position() {
+    let source = "struct Out { float4 pos; float2 uv; };\n\
+                  struct Good { float4 pos [[position]]; };\n\
+                  vertex Out bad(uint vid [[vertex_id]]) { Out o; return o; }\n\
+                  vertex Good good(uint vid [[vertex_id]]) { Good o; return o; }\n\
+                  vertex float4 plain(uint vid [[vertex_id]]) { return 0; }\n\
+                  vertex Elsewhere other(uint vid [[vertex_id]]) { return {}; }\n";
+    assert_eq!(
+        messages(source),
+        vec!["vertex function `bad` returns `Out`, which has no field with the `[[position]]` attribute"]
+    );
+}
+
+#[test]
+fn flags_fragment_functions_without_return() {
+    let source = "fragment float4 missing(float4 p [[position]]) { float4 c = p; }\n\
+                  fragment float4 branch(float4 p [[position]]) { if (p.x > 0) { return p; } return 0; }\n\
+                  fragment void side(float4 p [[position]]) {}\n";
+    assert_eq!(messages(source), vec!["fragment function `missing` returns `float4` but has no `return` statement"]);
+}
+
+#[test]
+fn ignores_non_entry_points() {
+    let source = "float4 helper(float2 gid [[thread_position_in_grid]]) {}\n";
+    assert!(messages(source).is_empty());
+}
@@ -0,0 +1,180 @@
+use super::*;
+use crate::definition::ref_site::RefSiteLocation;
+
+const SOURCE: &str = r#"
+float square(float x) { return x * x; }
+
+float shade(float x) {
+    return square(x) + clamp_value<float>(x);
+}
+
+kernel void compute_main(device float* out [[buffer(0)]]) {
+    out[0] = shade(1.0);
+}
+
+fragment float4 fragment_main() {
+    return float4(square(2.0));
+}
+
+vertex float4 vertex_main() { return float4(0.0); }
+"#;
+
+fn uri() -> Url {
+    Url::parse("file:///tmp/shaders.metal").unwrap()
+}
+
+fn graph_for(source: &str) -> CallGraph {
+    let mut graph = CallGraph::new();
+    graph.add_source(&uri(), &SyntaxTree::parse(source));
+    graph
+}
+
+#[test]
+fn finds_entry_points_through_helpers() {
+    let graph = graph_for(SOURCE);
+    let reached = graph.entry_points_reaching("square");
+    let summary: Vec<(&str, Option<&str>, Vec<&str>)> = reached
+        .iter()
+        .map(|r| (r.entry.name.as_str(), r.entry.stage, r.path.iter().map(String::as_str).collect()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("fragment_main", Some("fragment"), vec!["fragment_main", "square"]),
+            ("compute_main", Some("kernel"), vec!["compute_main", "shade", "square"]),
+        ]
+    );
+    assert_eq!(reached[0].entry.location.range.start, Position::new(11, 16));
+}
+
+#[test]
+fn entry_point_reaches_itself() {
+    let graph = graph_for(SOURCE);
+    let reached = graph.entry_points_reaching("vertex_main");
+    assert_eq!(reached.len(), 1);
+    assert_eq!(reached[0].path, vec!["vertex_main".to_string()]);
+}
+
+#[test]
+fn template_calls_are_edges() {
+    let graph =
+        graph_for("float clamp_value(float x) { return x; }\nkernel void k() { float y = clamp_value<float>(1.0); }");
+    let reached = graph.entry_points_reaching("clamp_value");
+    assert_eq!(reached.iter().map(|r| r.entry.name.as_str()).collect::<Vec<_>>(), vec!["k"]);
+}
+
+#[test]
+fn unreached_helper_has_no_entry_points() {
+    let graph = graph_for("float unused(float x) { return x; }\nkernel void k() {}");
+    assert!(graph.entry_points_reaching("unused").is_empty());
+}
+
+#[test]
+fn resolved_calls_add_macro_hidden_edges() {
+    let source =
+        "#define APPLY(x) hidden(x)\nfloat hidden(float x) { return x; }\nkernel void k() { float y = APPLY(1.0); }\n";
+    let tree = SyntaxTree::parse(source);
+    let mut graph = CallGraph::new();
+    graph.add_source(&uri(), &tree);
+    assert!(graph.entry_points_reaching("hidden").is_empty());
+
+    let column = source.lines().nth(2).unwrap().find("APPLY").unwrap() as u32 + 1;
+    let refs = vec![RefSite {
+        file: "/tmp/shaders.metal".into(),
+        line: 1,
+        col: 18,
+        tok_len: 6,
+        target_id: "0x1".to_string(),
+        target_name: "hidden".into(),
+        target_kind: "FunctionDecl".into(),
+        expansion: Some(RefSiteLocation {
+            file: "/tmp/shaders.metal".into(),
+            line: 3,
+            col: column,
+            tok_len: 5,
+        }),
+        spelling: None,
+    }];
+    graph.add_resolved_calls(&tree, &refs);
+    let reached = graph.entry_points_reaching("hidden");
+    assert_eq!(reached.len(), 1);
+    assert_eq!(reached[0].path, vec!["k".to_string(), "hidden".to_string()]);
+}
+
+#[test]
+fn enclosing_function_at_cursor() {
+    let tree = SyntaxTree::parse(SOURCE);
+    assert_eq!(enclosing_function_name(&tree.root(), SOURCE, Position::new(4, 12)), Some("shade".to_string()));
+    assert_eq!(enclosing_function_name(&tree.root(), SOURCE, Position::new(0, 0)), None);
+}
+
+#[test]
+fn entry_points_lists_qualified_functions_in_order() {
+    let sites = entry_points(&uri(), &SyntaxTree::parse(SOURCE));
+    let names: Vec<(&str, Option<&str>)> = sites.iter().map(|site| (site.name.as_str(), site.stage)).collect();
+    assert_eq!(
+        names,
+        vec![("compute_main", Some("kernel")), ("fragment_main", Some("fragment")), ("vertex_main", Some("vertex"))]
+    );
+    assert_eq!(sites[0].location.range.start, Position::new(7, 12));
+}
+
+const ATTRIBUTED: &str = r#"
+[[max_total_threads_per_threadgroup(256)]]
+kernel void blur(device float* out [[buffer(0)]], constant Params& params [[buffer(1)]],
+                 texture2d<float> src [[texture(0)]], uint id [[thread_position_in_grid]]) {}
+
+namespace post {
+[[early_fragment_tests, patch(quad, 4)]] // comment
+fragment float4 tonemap(device float* exposure [[buffer(2)]]) { return 0; }
+}
+"#;
+
+#[test]
+fn entry_point_details_collect_attributes_and_bindings() {
+    let details = entry_point_details(&uri(), &SyntaxTree::parse(ATTRIBUTED));
+    let attributes: Vec<(&str, &[String])> =
+        details.iter().map(|entry| (entry.site.name.as_str(), entry.attributes.as_slice())).collect();
+    assert_eq!(
+        attributes,
+        vec![
+            ("blur", &["max_total_threads_per_threadgroup(256)".to_string()][..]),
+            ("tonemap", &["early_fragment_tests".to_string(), "patch(quad, 4)".to_string()][..]),
+        ]
+    );
+
+    let bindings: Vec<(String, Option<&str>)> = details
+        .iter()
+        .flat_map(|entry| &entry.bindings)
+        .map(|binding| (binding.slot.label(), binding.parameter.as_deref()))
+        .collect();
+    assert_eq!(
+        bindings,
+        vec![
+            ("buffer(0)".to_string(), Some("out")),
+            ("buffer(1)".to_string(), Some("params")),
+            ("texture(0)".to_string(), Some("src")),
+            ("buffer(2)".to_string(), Some("exposure")),
+        ]
+    );
+}
+
+#[test]
+fn lens_title_counts_buffers_and_pipelines() {
+    let details = entry_point_details(&uri(), &SyntaxTree::parse(ATTRIBUTED));
+    let pipelines =
+        PipelineIndex::parse(r#"{"blur_h": ["fullscreen_vertex", "blur"], "blur_v": ["blur"], "post": ["tonemap"]}"#)
+            .unwrap();
+    assert_eq!(pipelines.pipelines_using("blur"), vec!["blur_h".to_string(), "blur_v".to_string()]);
+
+    assert_eq!(entry_point_lens_title(&details[0], None), "kernel · 2 buffer bindings");
+    assert_eq!(
+        entry_point_lens_title(&details[0], Some(&pipelines)),
+        "kernel · 2 buffer bindings · used by 2 pipelines"
+    );
+    assert_eq!(
+        entry_point_lens_title(&details[1], Some(&pipelines)),
+        "fragment · 1 buffer binding · used by 1 pipeline"
+    );
+    assert!(PipelineIndex::parse(r#"{"blur": "blur"}"#).is_err());
+}
@@ -0,0 +1,102 @@
+use tower_lsp::lsp_types::{DiagnosticSeverity, Position, Range};
+
+use super::*;
+use crate::metal::{compiler::MetalDiagnostic, fix_its::attach_fix};
+
+const SOURCE: &str = "\
+kernel void k(device float* out [[buffer(0)]])
+{
+    out[0] = 1.0
+}
+";
+
+fn uri() -> Url {
+    Url::from_file_path("/tmp/k.metal").unwrap()
+}
+
+fn fix_it(
+    line: u32,
+    start: u32,
+    end: u32,
+    replacement: &str,
+) -> FixIt {
+    FixIt {
+        file: "/tmp/k.metal".to_string(),
+        range: Range::new(Position::new(line, start), Position::new(line, end)),
+        replacement: replacement.to_string(),
+    }
+}
+
+fn compiler_error(fix_its: Vec<FixIt>) -> Diagnostic {
+    MetalDiagnostic {
+        file: Some("/tmp/k.metal".to_string()),
+        line: 2,
+        column: 16,
+        end: None,
+        severity: DiagnosticSeverity::ERROR,
+        message: "expected ';' after expression".to_string(),
+        related_information: Vec::new(),
+        fix_its,
+    }
+    .into_lsp_diagnostic()
+}
+
+fn code_actions(actions: &[CodeActionOrCommand]) -> Vec<&CodeAction> {
+    actions
+        .iter()
+        .map(|action| match action {
+            CodeActionOrCommand::CodeAction(action) => action,
+            CodeActionOrCommand::Command(command) => panic!("unexpected command {command:?}"),
+        })
+        .collect()
+}
+
+#[test]
+fn insertion_becomes_a_preferred_quick_fix() {
+    let actions = fix_it_actions(&uri(), SOURCE, &[compiler_error(vec![fix_it(2, 16, 16, ";")])]);
+    let [action] = code_actions(&actions)[..] else {
+        panic!("expected one code action, got {actions:?}");
+    };
+    assert_eq!(action.title, "Insert `;`");
+    assert_eq!(action.kind, Some(CodeActionKind::QUICKFIX));
+    assert_eq!(action.is_preferred, Some(true));
+    let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri()];
+    assert_eq!(edits, &vec![TextEdit::new(Range::new(Position::new(2, 16), Position::new(2, 16)), ";".to_string())]);
+}
+
+#[test]
+fn replacement_title_names_the_replaced_text() {
+    let actions = fix_it_actions(&uri(), SOURCE, &[compiler_error(vec![fix_it(2, 4, 7, "result")])]);
+    assert_eq!(code_actions(&actions)[0].title, "Replace `out` with `result`");
+
+    let actions = fix_it_actions(&uri(), SOURCE, &[compiler_error(vec![fix_it(2, 13, 16, "")])]);
+    assert_eq!(code_actions(&actions)[0].title, "Remove `1.0`");
+}
+
+#[test]
+fn replacement_title_converts_columns_past_non_ascii_text() {
+    let source = "/* é😀 */ float out = 1.0;\n";
+    let actions = fix_it_actions(&uri(), source, &[compiler_error(vec![fix_it(0, 16, 19, "result")])]);
+    assert_eq!(code_actions(&actions)[0].title, "Replace `out` with `result`");
+}
+
+#[test]
+fn note_fixes_are_titled_by_the_note() {
+    let mut diagnostic = compiler_error(vec![fix_it(2, 16, 16, ";")]);
+    attach_fix(
+        &mut diagnostic,
+        CompilerFix {
+            message: Some("place parentheses around the assignment".to_string()),
+            edits: vec![fix_it(2, 4, 4, "("), fix_it(2, 16, 16, ")")],
+        },
+    );
+    let actions = fix_it_actions(&uri(), SOURCE, &[diagnostic]);
+    let titles: Vec<_> =
+        code_actions(&actions).iter().map(|action| (action.title.as_str(), action.is_preferred)).collect();
+    assert_eq!(titles, [("Insert `;`", Some(false)), ("Place parentheses around the assignment", Some(false))]);
+}
+
+#[test]
+fn diagnostics_without_fix_its_offer_nothing() {
+    assert!(fix_it_actions(&uri(), SOURCE, &[compiler_error(Vec::new())]).is_empty());
+}
@@ -0,0 +1,102 @@
+use super::*;
+
+fn defines(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+}
+
+/// Inactive regions as inclusive zero-based line spans.
+fn line_spans(
+    source: &str,
+    defines: &HashMap<String, String>,
+) -> Vec<(u32, u32)> {
+    inactive_regions(source, defines).into_iter().map(|range| (range.start.line, range.end.line)).collect()
+}
+
+#[test]
+fn ifdef_uses_compiler_defines() {
+    let source = "#ifdef __METAL_MACOS__\nfloat mac;\n#else\nfloat other;\n#endif\n";
+    assert_eq!(line_spans(source, &defines(&[("__METAL_MACOS__", "1")])), vec![(3, 3)]);
+    assert_eq!(line_spans(source, &defines(&[])), Vec::new(), "reserved names are unknown without a define");
+
+    let source = "#ifdef USE_FAST\nfloat fast;\n#endif\n";
+    assert_eq!(line_spans(source, &defines(&[])), vec![(1, 1)]);
+    assert_eq!(line_spans(source, &defines(&[("USE_FAST", "1")])), Vec::new());
+}
+
+#[test]
+fn if_elif_else_chain_disables_all_but_the_taken_branch() {
+    let source = "#if TILE > 16\nA\n#elif TILE == 16\nB\n#else\nC\n#endif\n";
+    assert_eq!(line_spans(source, &defines(&[("TILE", "16")])), vec![(1, 1), (5, 5)]);
+    assert_eq!(line_spans(source, &defines(&[("TILE", "0x20")])), vec![(3, 3), (5, 5)]);
+    assert_eq!(line_spans(source, &defines(&[("TILE", "8")])), vec![(1, 1), (3, 3)]);
+}
+
+#[test]
+fn range_spans_whole_lines_between_directives() {
+    let source = "#if 0\nfloat a;\nfloat bé;\n#endif\n";
+    let regions = inactive_regions(source, &defines(&[]));
+    assert_eq!(
+        regions,
+        vec![Range {
+            start: Position::new(1, 0),
+            end: Position::new(2, 9),
+        }]
+    );
+}
+
+#[test]
+fn in_file_defines_and_undefs_are_tracked() {
+    let source = "#define MODE 2\n#if MODE == 2\nA\n#endif\n#undef MODE\n#ifdef MODE\nB\n#endif\n";
+    assert_eq!(line_spans(source, &defines(&[])), vec![(6, 6)]);
+
+    // A define inside a disabled branch has no effect.
+    let source = "#if 0\n#define FOO\n#endif\n#ifdef FOO\nA\n#endif\n";
+    assert_eq!(line_spans(source, &defines(&[])), vec![(1, 1), (4, 4)]);
+}
+
+#[test]
+fn unknown_conditions_are_not_reported() {
+    let source = "#if __METAL_VERSION__ >= 300\nA\n#else\nB\n#endif\n";
+    assert_eq!(line_spans(source, &defines(&[])), Vec::new());
+
+    // A project header may define anything after it is included.
+    let source = "#include \"config.h\"\n#ifdef FROM_HEADER\nA\n#endif\n";
+    assert_eq!(line_spans(source, &defines(&[])), Vec::new());
+    let source = "#include <metal_stdlib>\n#ifdef FROM_HEADER\nA\n#endif\n";
+    assert_eq!(line_spans(source, &defines(&[])), vec![(2, 2)]);
+
+    let source = "#define CHECK(x) x\n#if CHECK(1)\nA\n#endif\n";
+    assert_eq!(line_spans(source, &defines(&[])), Vec::new());
+}
+
+#[test]
+fn logical_operators_short_circuit_around_unknowns() {
+    let source = "#if defined(MISSING) && __METAL_VERSION__ > 200\nA\n#endif\n";
+    assert_eq!(line_spans(source, &defines(&[])), vec![(1, 1)]);
+
+    let source = "#if !defined(MISSING) || __METAL_VERSION__ > 200\nA\n#else\nB\n#endif\n";
+    assert_eq!(line_spans(source, &defines(&[])), vec![(3, 3)]);
+}
+
+#[test]
+fn nested_groups_report_only_the_outermost_disabled_region() {
+    let source = "#if 0\n#if 1\nA\n#else\nB\n#endif\n#endif\n#if 1\n#if 0\nC\n#endif\n#endif\n";
+    assert_eq!(line_spans(source, &defines(&[])), vec![(1, 5), (9, 9)]);
+}
+
+#[test]
+fn continuations_comments_and_macro_values_are_handled() {
+    let source = "#if defined(A) \\\n    || defined(B) // either\nX\n#endif\n";
+    assert_eq!(line_spans(source, &defines(&[])), vec![(2, 2)]);
+    assert_eq!(line_spans(source, &defines(&[("B", "1")])), Vec::new());
+
+    let source = "#define LIMIT (BASE * 2)\n#if LIMIT > 10 /* tuned */\nX\n#endif\n";
+    assert_eq!(line_spans(source, &defines(&[("BASE", "4")])), vec![(2, 2)]);
+    assert_eq!(line_spans(source, &defines(&[("BASE", "8")])), Vec::new());
+}
+
+#[test]
+fn unterminated_group_extends_to_end_of_file() {
+    let source = "#if 0\nA\nB";
+    assert_eq!(line_spans(source, &defines(&[])), vec![(1, 2)]);
+}
@@ -0,0 +1,125 @@
+use super::*;
+
+const ROOT: &str = "/p";
+
+fn roots() -> Vec<PathBuf> {
+    vec![PathBuf::from(ROOT)]
+}
+
+fn uri() -> Url {
+    Url::parse("file:///p/shaders/Light.h").unwrap()
+}
+
+fn edits(action: &CodeActionOrCommand) -> (&str, Vec<TextEdit>) {
+    let CodeActionOrCommand::CodeAction(action) = action else {
+        panic!("expected a code action");
+    };
+    let changes = action.edit.as_ref().and_then(|edit| edit.changes.as_ref()).expect("workspace edit");
+    (action.title.as_str(), changes[&uri()].clone())
+}
+
+#[test]
+fn include_guards_are_recognized() {
+    assert_eq!(include_guard("#pragma once\nstruct A {};\n"), Some(IncludeGuard::PragmaOnce));
+    assert!(include_guard("// Copyright\n/* header\n */\n#ifndef A_H\n#define A_H\nstruct A {};\n#endif\n").is_some());
+    assert!(include_guard("#if !defined(A_H)\n#define A_H\n#endif\n").is_some());
+    assert!(include_guard("#ifndef A_H\n#define B_H\n#endif\n").is_none());
+    assert!(include_guard("struct A {};\n#ifndef A_H\n#define A_H\n#endif\n").is_none());
+    assert!(include_guard("struct A {};\n").is_none());
+}
+
+#[test]
+fn guard_ranges_cover_the_macro_names() {
+    let Some(IncludeGuard::Macro {
+        name,
+        ranges,
+    }) = include_guard("/* é */ #ifndef  A_H\n#define A_H 1\n")
+    else {
+        panic!("expected a macro guard");
+    };
+    assert_eq!(name, "A_H");
+    assert_eq!(ranges[0], Range::new(Position::new(0, 17), Position::new(0, 20)));
+    assert_eq!(ranges[1], Range::new(Position::new(1, 8), Position::new(1, 11)));
+}
+
+#[test]
+fn guard_names_follow_the_path() {
+    assert_eq!(expected_guard_name(Path::new("/p/shaders/common/Light.h"), &roots()), "SHADERS_COMMON_LIGHT_H");
+    assert_eq!(expected_guard_name(Path::new("/elsewhere/tone-map.hpp"), &roots()), "TONE_MAP_HPP");
+    assert_eq!(guard_name(Path::new("3d/mesh.h")), "INCLUDE_3D_MESH_H");
+}
+
+#[test]
+fn conventional_guards_end_with_the_file_name() {
+    let path = Path::new("/p/shaders/Light.h");
+    assert!(follows_convention("LIGHT_H", path));
+    assert!(follows_convention("SHADERS_LIGHT_H", path));
+    assert!(follows_convention("MYLIB_LIGHT_H_", path));
+    assert!(follows_convention("__LIGHT_H__", path));
+    assert!(!follows_convention("SPOTLIGHT_H", path));
+    assert!(!follows_convention("LIGHTS_H", path));
+    assert!(!follows_convention("HEADER_GUARD", path));
+}
+
+#[test]
+fn guarded_headers_have_no_diagnostics() {
+    let path = Path::new("/p/shaders/Light.h");
+    assert!(include_guard_diagnostics(path, "#pragma once\n", &roots()).is_empty());
+    assert!(include_guard_diagnostics(path, "#ifndef LIGHT_H\n#define LIGHT_H\n#endif\n", &roots()).is_empty());
+}
+
+#[test]
+fn missing_guard_is_added_after_the_leading_comments() {
+    let text = "// Lights.\n\nstruct Light {};";
+    let diagnostics = include_guard_diagnostics(Path::new("/p/shaders/Light.h"), text, &roots());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "header has no include guard or `#pragma once`");
+    assert_eq!(diagnostics[0].range, Range::new(Position::new(0, 0), Position::new(0, 10)));
+
+    let actions = include_guard_actions(&uri(), text, &diagnostics);
+    assert_eq!(actions.len(), 2);
+    let (title, pragma) = edits(&actions[0]);
+    assert_eq!(title, "Add `#pragma once`");
+    assert_eq!(
+        pragma,
+        vec![TextEdit::new(Range::new(Position::new(2, 0), Position::new(2, 0)), "#pragma once\n\n".into())]
+    );
+    let (title, guard) = edits(&actions[1]);
+    assert_eq!(title, "Add include guard `SHADERS_LIGHT_H`");
+    assert_eq!(
+        guard,
+        vec![
+            TextEdit::new(
+                Range::new(Position::new(2, 0), Position::new(2, 0)),
+                "#ifndef SHADERS_LIGHT_H\n#define SHADERS_LIGHT_H\n\n".into()
+            ),
+            TextEdit::new(
+                Range::new(Position::new(2, 16), Position::new(2, 16)),
+                "\n\n#endif // SHADERS_LIGHT_H\n".into()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn misnamed_guard_is_renamed() {
+    let text = "#ifndef HEADER_GUARD\n#define HEADER_GUARD\nstruct Light {};\n#endif\n";
+    let diagnostics = include_guard_diagnostics(Path::new("/p/shaders/Light.h"), text, &roots());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        "include guard `HEADER_GUARD` is not named after the header; expected `SHADERS_LIGHT_H`"
+    );
+
+    let actions = include_guard_actions(&uri(), text, &diagnostics);
+    assert_eq!(actions.len(), 1);
+    let (title, rename) = edits(&actions[0]);
+    assert_eq!(title, "Rename include guard to `SHADERS_LIGHT_H`");
+    assert_eq!(
+        rename,
+        vec![
+            TextEdit::new(Range::new(Position::new(0, 8), Position::new(0, 20)), "SHADERS_LIGHT_H".into()),
+            TextEdit::new(Range::new(Position::new(1, 8), Position::new(1, 20)), "SHADERS_LIGHT_H".into()),
+        ]
+    );
+}
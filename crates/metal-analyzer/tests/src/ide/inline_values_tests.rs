@@ -0,0 +1,80 @@
+use tower_lsp::lsp_types::Position;
+
+use super::*;
+use crate::syntax::SyntaxTree;
+
+const SOURCE: &str = "\
+kernel void k(device float* out [[buffer(0)]], uint id [[thread_position_in_grid]]) {
+    float x = out[id] * 2.0;
+    for (int i = 0; i < 4; ++i) {
+        float y = x + i;
+        out[i] = y;
+    }
+    Light light = lights[0];
+    float z = light.x + x;
+}
+";
+
+/// `(line, name)` of each lookup with the debugger stopped on `stopped_line`.
+fn lookups(
+    source: &str,
+    stopped_line: u32,
+) -> Vec<(u32, String)> {
+    let tree = SyntaxTree::parse(source);
+    let whole = Range::new(Position::new(0, 0), Position::new(source.lines().count() as u32, 0));
+    let stopped = Range::new(Position::new(stopped_line, 4), Position::new(stopped_line, 4));
+    inline_values(&tree.root(), source, whole, stopped)
+        .into_iter()
+        .map(|value| match value {
+            InlineValue::VariableLookup(lookup) => {
+                assert!(lookup.case_sensitive_lookup);
+                (lookup.range.start.line, lookup.variable_name.unwrap())
+            },
+            other => panic!("unexpected inline value {other:?}"),
+        })
+        .collect()
+}
+
+fn pairs(expected: &[(u32, &str)]) -> Vec<(u32, String)> {
+    expected.iter().map(|(line, name)| (*line, name.to_string())).collect()
+}
+
+#[test]
+fn looks_up_parameters_and_locals_up_to_the_stopped_line() {
+    assert_eq!(lookups(SOURCE, 1), pairs(&[(0, "out"), (0, "id"), (1, "x"), (1, "out"), (1, "id")]));
+}
+
+#[test]
+fn loop_locals_are_only_in_scope_inside_the_loop() {
+    let inside = lookups(SOURCE, 4);
+    assert!(inside.contains(&(3, "y".to_string())), "{inside:?}");
+    assert!(inside.contains(&(4, "i".to_string())), "{inside:?}");
+    assert!(inside.contains(&(3, "x".to_string())), "{inside:?}");
+
+    let after = lookups(SOURCE, 7);
+    assert!(!after.iter().any(|(_, name)| name == "i" || name == "y"), "{after:?}");
+    assert!(after.contains(&(7, "z".to_string())), "{after:?}");
+    assert!(after.contains(&(7, "light".to_string())), "{after:?}");
+}
+
+#[test]
+fn skips_members_and_unknown_names() {
+    let values = lookups(SOURCE, 7);
+    assert!(!values.iter().any(|(_, name)| name == "lights"), "{values:?}");
+    assert_eq!(values.iter().filter(|(line, name)| *line == 7 && name == "x").count(), 1, "{values:?}");
+}
+
+#[test]
+fn limits_lookups_to_the_requested_range_and_the_stopped_function() {
+    let tree = SyntaxTree::parse(SOURCE);
+    let line_one = Range::new(Position::new(1, 0), Position::new(1, 30));
+    let stopped = Range::new(Position::new(4, 8), Position::new(4, 8));
+    let values = inline_values(&tree.root(), SOURCE, line_one, stopped);
+    assert!(
+        values.iter().all(|value| matches!(value, InlineValue::VariableLookup(lookup) if lookup.range.start.line == 1))
+    );
+    assert_eq!(values.len(), 3);
+
+    let outside = Range::new(Position::new(9, 0), Position::new(9, 0));
+    assert!(inline_values(&tree.root(), SOURCE, line_one, outside).is_empty());
+}
@@ -0,0 +1,63 @@
+use super::*;
+use crate::syntax::SyntaxTree;
+
+fn warnings(
+    source: &str,
+    version: LanguageVersion,
+) -> Vec<(u32, u32, String)> {
+    let tree = SyntaxTree::parse(source);
+    let defines = HashMap::from([("__METAL_VERSION__".to_string(), version.metal_version_macro())]);
+    unavailable_builtins(&tree.root(), source, version, &defines)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.range.start.line, diagnostic.range.start.character, diagnostic.message))
+        .collect()
+}
+
+#[test]
+fn flags_builtins_introduced_after_the_target() {
+    let source = "\
+kernel void k(device metal::bfloat4* out [[buffer(0)]], device atomic_float* sum [[buffer(1)]])
+{
+    bfloat x = 1.0bf;
+    out[0] = bfloat4(x);
+}
+";
+    assert_eq!(
+        warnings(source, LanguageVersion::new(3, 0)),
+        vec![
+            (0, 28, "`bfloat4` requires Metal 3.1, but Metal 3.0 is targeted".to_string()),
+            (2, 4, "`bfloat` requires Metal 3.1, but Metal 3.0 is targeted".to_string()),
+            (3, 13, "`bfloat4` requires Metal 3.1, but Metal 3.0 is targeted".to_string()),
+        ]
+    );
+    let older = warnings(source, LanguageVersion::new(2, 4));
+    assert_eq!(older.len(), 4);
+    assert_eq!(older[1].2, "`atomic_float` requires Metal 3.0, but Metal 2.4 is targeted");
+    assert!(warnings(source, LanguageVersion::new(3, 1)).is_empty());
+}
+
+#[test]
+fn qualified_ray_tracing_types_are_checked_as_a_whole() {
+    let source = "kernel void k(ray_tracing::instance_acceleration_structure scene [[buffer(0)]]) {}\n";
+    assert_eq!(
+        warnings(source, LanguageVersion::new(2, 2)),
+        vec![(
+            0,
+            14,
+            "`ray_tracing::instance_acceleration_structure` requires Metal 2.3, but Metal 2.2 is targeted".to_string()
+        )]
+    );
+}
+
+#[test]
+fn skips_members_other_namespaces_and_disabled_branches() {
+    let source = "\
+#if __METAL_VERSION__ >= 310
+typedef bfloat scalar;
+#else
+typedef half scalar;
+#endif
+float f(S s) { return s.bfloat4 + mylib::bfloat4; }
+";
+    assert!(warnings(source, LanguageVersion::new(3, 0)).is_empty());
+}
@@ -0,0 +1,173 @@
+use super::*;
+
+fn index(source: &str) -> MacroIndex {
+    let mut index = MacroIndex::default();
+    index.add_source(source, None);
+    index
+}
+
+/// Expand the invocation at the last occurrence of `marker` in `source`.
+fn expand_at(
+    source: &str,
+    marker: &str,
+) -> Option<(MacroInvocation, MacroExpansion)> {
+    let index = index(source);
+    let offset = source.rfind(marker).expect("marker in source");
+    let position = position_from_byte_offset(source, offset);
+    let invocation = macro_invocation_at(source, position, &index)?;
+    let expansion = expand_invocation(&invocation, &index)?;
+    Some((invocation, expansion))
+}
+
+#[test]
+fn object_like_macro_expands_one_step_and_fully() {
+    let source = "#define TILE 16\n#define AREA (TILE * TILE)\nuint n = AREA;\n";
+    let (invocation, expansion) = expand_at(source, "AREA;").unwrap();
+    assert_eq!(invocation.text, "AREA");
+    assert_eq!(invocation.range, Range::new(Position::new(2, 9), Position::new(2, 13)));
+    assert_eq!(expansion.single_step, "(TILE * TILE)");
+    assert_eq!(expansion.full, "(16 * 16)");
+}
+
+#[test]
+fn function_like_macro_substitutes_expanded_arguments() {
+    let source = "#define N 4\n#define SQ(x) ((x) * (x))\nfloat y = SQ(a + N);\n";
+    let (invocation, expansion) = expand_at(source, "SQ(a").unwrap();
+    assert_eq!(invocation.args, Some(vec!["a + N".to_string()]));
+    assert_eq!(invocation.range.end, Position::new(2, 19));
+    assert_eq!(expansion.single_step, "((a + N) * (a + N))");
+    assert_eq!(expansion.full, "((a + 4) * (a + 4))");
+}
+
+#[test]
+fn stringify_and_paste_use_arguments_as_written() {
+    let source = "#define N 4\n#define NAME(op, n) op##_##n\n#define STR(x) #x\nNAME(add, N) STR(N \"q\")\n";
+    let (_, expansion) = expand_at(source, "NAME(add").unwrap();
+    assert_eq!(expansion.single_step, "add_N");
+    let (_, expansion) = expand_at(source, "STR(N").unwrap();
+    assert_eq!(expansion.full, "\"N \\\"q\\\"\"");
+}
+
+#[test]
+fn kernel_generator_expands_nested_invocations() {
+    let source = "\
+#define CAT(a, b) a##b
+#define KERNEL(name, T) kernel void CAT(name, _##T)(device T* out [[buffer(0)]])
+#define INSTANTIATE(T) KERNEL(fill, T) {}
+INSTANTIATE(float)
+";
+    let (_, expansion) = expand_at(source, "INSTANTIATE(float)").unwrap();
+    assert_eq!(expansion.single_step, "KERNEL(fill, float) {}");
+    assert_eq!(expansion.full, "kernel void fill_float(device float* out [[buffer(0)]]) {}");
+}
+
+#[test]
+fn variadic_arguments_are_joined() {
+    let source =
+        "#define CALL(f, ...) f(__VA_ARGS__)\n#define LOG(args...) print(args)\nCALL(g, 1, (2, 3)) LOG(x, y)\n";
+    let (invocation, expansion) = expand_at(source, "CALL(g").unwrap();
+    assert_eq!(invocation.args, Some(vec!["g".to_string(), "1".to_string(), "(2, 3)".to_string()]));
+    assert_eq!(expansion.full, "g(1, (2, 3))");
+    let (_, expansion) = expand_at(source, "LOG(x").unwrap();
+    assert_eq!(expansion.full, "print(x, y)");
+}
+
+#[test]
+fn self_reference_is_not_expanded_again() {
+    let source = "#define foo foo + 1\n#define a b\n#define b a\nfoo a\n";
+    assert_eq!(expand_at(source, "foo a").unwrap().1.full, "foo + 1");
+    assert_eq!(expand_at(source, "a\n").unwrap().1.full, "a");
+}
+
+#[test]
+fn function_like_macro_needs_a_matching_argument_list() {
+    let source = "#define MAX(a, b) ((a) > (b) ? (a) : (b))\nauto f = MAX;\nauto g = MAX(1);\nauto h = MAX(1, 2);\n";
+    assert!(expand_at(source, "MAX;").is_none());
+    assert!(expand_at(source, "MAX(1)").is_none());
+    assert_eq!(expand_at(source, "MAX(1, 2)").unwrap().1.full, "((1) > (2) ? (1) : (2))");
+}
+
+#[test]
+fn undef_and_comments_update_the_index() {
+    let index = index("#define A 1 // one\n#define B /* two */ 2 \\\n  + 3\n#undef A\n");
+    assert!(index.get("A").is_none());
+    let b = index.get("B").unwrap();
+    assert_eq!(b.body, "2 + 3");
+    assert_eq!(b.line, 1);
+    assert_eq!(b.signature(), "#define B 2 + 3");
+}
+
+#[test]
+fn compiler_defines_are_object_like_macros() {
+    let defines = HashMap::from([("USE_FAST".to_string(), "1".to_string())]);
+    let mut index = MacroIndex::from_defines(&defines);
+    index.add_source("#define SPEED (USE_FAST * 2)\n", None);
+    let source = "float s = SPEED;";
+    let invocation = macro_invocation_at(source, Position::new(0, 11), &index).unwrap();
+    assert_eq!(expand_invocation(&invocation, &index).unwrap().full, "(1 * 2)");
+    assert!(index.get("USE_FAST").unwrap().file.is_none());
+}
+
+#[test]
+fn macro_names_in_directives_and_non_macros_are_not_invocations() {
+    let source = "#define WIDTH 8\n#ifdef WIDTH\nint width = WIDTH;\n#endif\n";
+    let index = index(source);
+    assert!(macro_invocation_at(source, Position::new(0, 9), &index).is_none());
+    assert!(macro_invocation_at(source, Position::new(1, 8), &index).is_none());
+    assert!(macro_invocation_at(source, Position::new(2, 5), &index).is_none());
+    assert!(macro_invocation_at(source, Position::new(2, 14), &index).is_some());
+}
+
+#[test]
+fn signature_spells_variadic_parameters() {
+    let index = index("#define A(x, ...) x\n#define B(rest...) rest\n#define C() 0\n");
+    assert_eq!(index.get("A").unwrap().signature(), "#define A(x, ...) x");
+    assert_eq!(index.get("B").unwrap().signature(), "#define B(rest...) rest");
+    assert_eq!(index.get("C").unwrap().signature(), "#define C() 0");
+    let source = "C()";
+    let invocation = macro_invocation_at(source, Position::new(0, 0), &index).unwrap();
+    assert_eq!(expand_invocation(&invocation, &index).unwrap().full, "0");
+}
+
+fn unit(files: &[(&str, &str)]) -> Vec<(PathBuf, String)> {
+    files.iter().map(|(path, source)| (PathBuf::from(path), source.to_string())).collect()
+}
+
+#[test]
+fn conflicts_report_differing_definitions_across_files() {
+    let files = unit(&[
+        ("/p/blur.metal", "#include \"a.h\"\n#include \"b.h\"\n"),
+        ("/p/a.h", "#define TILE 16\n#define SQ(x) ((x) * (x))\n#define SAME  1\n"),
+        ("/p/b.h", "#pragma once\n#define TILE 32\n#define SQ(y) ((y) * (y))\n#define SAME 1\n"),
+    ]);
+    let conflicts = find_macro_conflicts(&files);
+    let summary: Vec<(&str, u32, u32)> =
+        conflicts.iter().map(|c| (c.first.name.as_str(), c.first.line, c.second.line)).collect();
+    assert_eq!(summary, [("TILE", 0, 1), ("SQ", 1, 2)]);
+    assert_eq!(conflicts[0].second.file.as_deref(), Some(Path::new("/p/b.h")));
+}
+
+#[test]
+fn defaults_guarded_by_their_own_name_are_not_conflicts() {
+    let files = unit(&[
+        ("/p/blur.metal", "#define TILE 16\n#define STEPS 2\n#define RADIUS 3\n#include \"b.h\"\n"),
+        (
+            "/p/b.h",
+            "#ifndef TILE\n#define TILE 32\n#endif\n#if !defined(STEPS)\n#define STEPS 8\n#endif\n\
+             #ifndef OTHER\n#define RADIUS 5\n#endif\n",
+        ),
+    ]);
+    let names: Vec<String> = find_macro_conflicts(&files).into_iter().map(|c| c.first.name).collect();
+    assert_eq!(names, ["RADIUS"]);
+}
+
+#[test]
+fn alternatives_within_one_file_are_not_conflicts() {
+    let files = unit(&[
+        ("/p/config.h", "#if USE_FAST\n#define STEPS 4\n#else\n#define STEPS 16\n#endif\n#define STEPS 8\n"),
+        ("/p/fast.h", "#define STEPS 4\n"),
+    ]);
+    let conflicts = find_macro_conflicts(&files);
+    assert!(conflicts.is_empty(), "fast.h matches one of config.h's alternatives: {conflicts:?}");
+    assert_eq!(macro_definitions(&files[0].1, None).len(), 3);
+}
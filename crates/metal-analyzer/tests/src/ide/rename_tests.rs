@@ -0,0 +1,168 @@
+use super::*;
+use crate::syntax::SyntaxTree;
+
+fn companion_lines(
+    source: &str,
+    name: &str,
+) -> Vec<(u32, String)> {
+    let tree = SyntaxTree::parse(source);
+    type_name_companion_ranges(&tree.root(), source, name)
+        .into_iter()
+        .map(|range| {
+            let line = source.lines().nth(range.start.line as usize).unwrap();
+            let start = range.start.character as usize;
+            (range.start.line, line[start..range.end.character as usize].to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn finds_constructors_destructors_and_conversions() {
+    let source = "struct Accum {
+    Accum() {}
+    Accum(float v) : value(v) {}
+    ~Accum();
+    operator float() const;
+    float value;
+};
+
+Accum::~Accum() {}
+Accum::Accum(int v) : value(v) {}
+
+struct Wrapper {
+    operator Accum() const;
+    operator const Accum&() const;
+};
+";
+    let lines: Vec<u32> = companion_lines(source, "Accum")
+        .into_iter()
+        .map(|(line, text)| {
+            assert_eq!(text, "Accum");
+            line
+        })
+        .collect();
+    assert_eq!(lines, vec![1, 2, 3, 8, 9, 12, 13]);
+}
+
+#[test]
+fn ignores_plain_type_uses_and_other_types() {
+    let source = "struct Accum { float value; };
+struct Other {
+    Other(Accum a) {}
+};
+
+Accum make(Accum a) { return Accum(a); }
+";
+    assert!(companion_lines(source, "Accum").is_empty());
+
+    let tree = SyntaxTree::parse(source);
+    assert!(defines_type(&tree.root(), "Accum"));
+    assert!(!defines_type(&tree.root(), "make"));
+}
+
+#[test]
+fn new_names_must_be_plain_unreserved_identifiers() {
+    assert_eq!(validate_new_name("blur_radius"), Ok(()));
+    assert_eq!(validate_new_name("_scale2"), Ok(()));
+    assert_eq!(validate_new_name("2x").unwrap_err().reason(), "invalidIdentifier");
+    assert_eq!(validate_new_name("blur-radius").unwrap_err().reason(), "invalidIdentifier");
+    assert_eq!(validate_new_name("").unwrap_err().reason(), "invalidIdentifier");
+    assert_eq!(validate_new_name("__scale").unwrap_err().reason(), "reserved");
+    assert_eq!(validate_new_name("_Scale").unwrap_err().reason(), "reserved");
+    assert_eq!(validate_new_name("kernel").unwrap_err().reason(), "keyword");
+    assert_eq!(validate_new_name("float4").unwrap_err().reason(), "builtin");
+    assert_eq!(validate_new_name("clamp").unwrap_err().reason(), "builtin");
+}
+
+#[test]
+fn declaration_scope_follows_blocks_and_parameters() {
+    let source = "float gain;
+float apply(float x) {
+    float y = x * gain;
+    {
+        float z = y;
+    }
+    return y;
+}
+";
+    let tree = SyntaxTree::parse(source);
+    let root = tree.root();
+    let use_at =
+        |line: u32, character: u32| Range::new(Position::new(line, character), Position::new(line, character + 1));
+
+    assert_eq!(declaration_scope(&root, source, Position::new(0, 6)), None);
+
+    let parameter = declaration_scope(&root, source, Position::new(1, 18)).unwrap();
+    assert!(any_use_in_scope(source, parameter, &[use_at(2, 14)]));
+    assert!(!any_use_in_scope(source, parameter, &[use_at(0, 6)]));
+
+    let inner = declaration_scope(&root, source, Position::new(4, 14)).unwrap();
+    assert!(any_use_in_scope(source, inner, &[use_at(4, 18)]));
+    assert!(!any_use_in_scope(source, inner, &[use_at(6, 11)]));
+}
+
+#[test]
+fn identifier_ranges_skip_comments_strings_and_longer_names() {
+    let source = "float gain; // gain
+float gains = gain * 2.0; const char* s = \"gain\";
+";
+    let tree = SyntaxTree::parse(source);
+    let ranges = identifier_ranges(&tree.root(), source, "gain");
+    assert_eq!(
+        ranges,
+        vec![
+            Range::new(Position::new(0, 6), Position::new(0, 10)),
+            Range::new(Position::new(1, 14), Position::new(1, 18)),
+        ]
+    );
+}
+
+#[test]
+fn annotated_rename_edit_separates_definite_and_candidate_edits() {
+    let main = Url::parse("file:///p/main.metal").unwrap();
+    let other = Url::parse("file:///p/other.metal").unwrap();
+    let edit = |line: u32| TextEdit::new(Range::new(Position::new(line, 0), Position::new(line, 4)), "gain".into());
+    let definite = HashMap::from([(main.clone(), vec![edit(0), edit(3)])]);
+    let candidates = HashMap::from([(main.clone(), vec![edit(3)]), (other.clone(), vec![edit(1)])]);
+
+    let workspace_edit = annotated_rename_edit(definite, candidates, |uri| (uri == &main).then_some(7));
+    assert!(workspace_edit.changes.is_none());
+    let Some(DocumentChanges::Edits(files)) = workspace_edit.document_changes else {
+        panic!("expected document edits");
+    };
+    let summary: Vec<_> = files
+        .iter()
+        .map(|file| {
+            let edits: Vec<(u32, &str)> = file
+                .edits
+                .iter()
+                .map(|edit| match edit {
+                    OneOf::Right(annotated) => (annotated.text_edit.range.start.line, annotated.annotation_id.as_str()),
+                    OneOf::Left(_) => panic!("expected annotated edits"),
+                })
+                .collect();
+            (file.text_document.uri.as_str(), file.text_document.version, edits)
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("file:///p/main.metal", Some(7), vec![(0, DEFINITE_ANNOTATION), (3, DEFINITE_ANNOTATION)]),
+            ("file:///p/other.metal", None, vec![(1, CANDIDATE_ANNOTATION)]),
+        ]
+    );
+
+    let annotations = workspace_edit.change_annotations.unwrap();
+    assert_eq!(annotations[DEFINITE_ANNOTATION].needs_confirmation, Some(false));
+    assert_eq!(annotations[CANDIDATE_ANNOTATION].needs_confirmation, Some(true));
+}
+
+#[test]
+fn annotated_rename_edit_without_candidates_needs_no_confirmation() {
+    let main = Url::parse("file:///p/main.metal").unwrap();
+    let definite = HashMap::from([(main, vec![TextEdit::new(Range::default(), "gain".into())])]);
+    let workspace_edit = annotated_rename_edit(definite, HashMap::new(), |_| None);
+    let annotations = workspace_edit.change_annotations.unwrap();
+    assert_eq!(annotations.len(), 1);
+    assert!(annotations.contains_key(DEFINITE_ANNOTATION));
+}
@@ -0,0 +1,56 @@
+use super::*;
+use crate::syntax::SyntaxTree;
+
+fn expansion_texts(
+    source: &str,
+    needle: &str,
+) -> Vec<String> {
+    let tree = SyntaxTree::parse(source);
+    let offset = source.find(needle).expect("needle in source");
+    let position = crate::text_pos::position_from_byte_offset(source, offset);
+    let mut current = selection_ranges(&tree.root(), source, &[position]).pop();
+    let mut texts = Vec::new();
+    while let Some(selection) = current {
+        let start = crate::text_pos::byte_offset_from_position(source, selection.range.start).unwrap();
+        let end = crate::text_pos::byte_offset_from_position(source, selection.range.end).unwrap();
+        texts.push(source[start..end].to_string());
+        current = selection.parent.map(|parent| *parent);
+    }
+    texts
+}
+
+#[test]
+fn expands_from_identifier_to_function() {
+    let source = "kernel void k(device float* out [[buffer(0)]]) {\n    float t = out[0];\n    out[0] = t;\n}\n";
+    let texts = expansion_texts(source, "t = out");
+    assert_eq!(texts.first().map(String::as_str), Some("t"));
+    assert!(texts.contains(&"float t = out[0];".to_string()), "{texts:?}");
+    assert!(texts.contains(&"float t = out[0];\n    out[0] = t;".to_string()), "{texts:?}");
+    assert!(texts.iter().any(|text| text.starts_with('{') && text.ends_with('}')), "{texts:?}");
+    assert_eq!(texts.last().map(String::as_str), Some(source.trim_end()));
+
+    for pair in texts.windows(2) {
+        assert!(pair[1].len() > pair[0].len(), "each step must grow: {texts:?}");
+    }
+}
+
+#[test]
+fn expands_inside_parameter_list_and_attribute() {
+    let source = "kernel void k(device float* out [[buffer(0)]], uint id [[thread_position_in_grid]]) {}";
+    let texts = expansion_texts(source, "buffer");
+    assert!(texts.contains(&"buffer(0)".to_string()), "{texts:?}");
+    assert!(texts.contains(&"[[buffer(0)]]".to_string()), "{texts:?}");
+    assert!(
+        texts.contains(&"device float* out [[buffer(0)]], uint id [[thread_position_in_grid]]".to_string()),
+        "{texts:?}"
+    );
+}
+
+#[test]
+fn returns_one_result_per_position() {
+    let source = "float a;\nfloat b;\n";
+    let tree = SyntaxTree::parse(source);
+    let ranges =
+        selection_ranges(&tree.root(), source, &[Position::new(0, 6), Position::new(1, 6), Position::new(9, 0)]);
+    assert_eq!(ranges.len(), 3);
+}
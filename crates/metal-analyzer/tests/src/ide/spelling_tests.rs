@@ -0,0 +1,92 @@
+use tower_lsp::lsp_types::{CodeAction, CodeActionOrCommand, DiagnosticSeverity};
+
+use super::*;
+use crate::{ide::fix_its::fix_it_actions, syntax::SyntaxTree};
+
+const SOURCE: &str = "\
+kernel void k(device float* out [[buffer(0)]], uint gid [[thread_position_in_grid]])
+{
+    float scaleFactor = 2.0;
+    out[gid] = scalFactor * clmp(out[gid], 0.0, 1.0);
+}
+";
+
+fn uri() -> Url {
+    Url::from_file_path("/tmp/k.metal").unwrap()
+}
+
+fn error(
+    line: u32,
+    column: u32,
+    message: &str,
+) -> Diagnostic {
+    Diagnostic {
+        range: Range::new(Position::new(line, column), Position::new(line, column)),
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some("metal-compiler".to_string()),
+        message: message.to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn unknown_identifier_reads_undeclared_and_unknown_type_errors() {
+    assert_eq!(unknown_identifier("use of undeclared identifier 'scalFactor'"), Some("scalFactor"));
+    assert_eq!(unknown_identifier("unknown type name 'flaot4'"), Some("flaot4"));
+    assert_eq!(unknown_identifier("use of undeclared identifier 'scal'; did you mean 'scale'?"), None);
+    assert_eq!(unknown_identifier("expected ';' after expression"), None);
+}
+
+#[test]
+fn closest_names_are_ordered_by_distance_and_bounded() {
+    let candidates = ["scaleFactor", "scaleFactors", "ScalFactor", "offset", "scalFactor"];
+    assert_eq!(closest_names("scalFactor", candidates), vec!["ScalFactor", "scaleFactor", "scaleFactors"]);
+    assert_eq!(closest_names("clmp", ["clamp", "exp", "cmp"]), vec!["clamp", "cmp"]);
+    assert!(closest_names("x", ["y", "xy"]).is_empty());
+    assert!(closest_names("offset", ["scale"]).is_empty());
+}
+
+#[test]
+fn closest_names_caps_suggestions() {
+    let candidates = ["fooa", "foob", "fooc", "food"];
+    assert_eq!(closest_names("foo", candidates).len(), 3);
+}
+
+#[test]
+fn suggestions_become_related_information_and_quick_fixes() {
+    let tree = SyntaxTree::parse(SOURCE);
+    let names = identifier_names(&tree.root(), SOURCE);
+    let candidates: HashSet<&str> = names.into_iter().chain(["clamp"]).collect();
+    let mut diagnostics = vec![
+        error(3, 15, "use of undeclared identifier 'scalFactor'"),
+        error(3, 28, "use of undeclared identifier 'clmp'"),
+        error(3, 4, "expected ';' after expression"),
+    ];
+
+    suggest_identifiers(&mut diagnostics, &uri(), &candidates);
+
+    let related = diagnostics[0].related_information.as_ref().unwrap();
+    assert_eq!(related.len(), 1);
+    assert_eq!(related[0].message, "did you mean `scaleFactor`?");
+    assert_eq!(related[0].location.range, Range::new(Position::new(3, 15), Position::new(3, 25)));
+    assert!(diagnostics[2].related_information.is_none());
+
+    let actions = fix_it_actions(&uri(), SOURCE, &diagnostics);
+    let titles: Vec<&str> = actions
+        .iter()
+        .map(|action| match action {
+            CodeActionOrCommand::CodeAction(CodeAction {
+                title,
+                ..
+            }) => title.as_str(),
+            CodeActionOrCommand::Command(command) => panic!("unexpected command {command:?}"),
+        })
+        .collect();
+    assert_eq!(titles, vec!["Change `scalFactor` to `scaleFactor`", "Change `clmp` to `clamp`"]);
+    let CodeActionOrCommand::CodeAction(action) = &actions[1] else {
+        unreachable!();
+    };
+    let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri()];
+    assert_eq!(edits[0].range, Range::new(Position::new(3, 28), Position::new(3, 32)));
+    assert_eq!(edits[0].new_text, "clamp");
+}
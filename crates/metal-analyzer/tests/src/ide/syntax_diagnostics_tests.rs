@@ -0,0 +1,110 @@
+use super::*;
+use crate::syntax::SyntaxTree;
+
+/// `(line, message)` of each diagnostic, with 1-based lines.
+fn diagnostics(source: &str) -> Vec<(u32, String)> {
+    syntax_diagnostics(&SyntaxTree::parse(source).root(), source)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.range.start.line + 1, diagnostic.message))
+        .collect()
+}
+
+#[test]
+fn valid_code_has_no_diagnostics() {
+    let source = "\
+#include <metal_stdlib>
+using namespace metal;
+
+#define WRAP(x) { (x)
+enum class Mode { A, B };
+struct Vertex {
+    float4 position [[position]];
+    float2 uv;
+};
+
+kernel void k(device float* out [[buffer(0)]], uint id [[thread_position_in_grid]]) {
+    float3 v = {
+        1, 2, 3
+    };
+    float x = out[id[0]];
+    if (id > 0)
+        return;
+    for (uint i = 0; i < 4; i++) {
+        out[i] = x;
+    }
+}
+";
+    assert_eq!(diagnostics(source), []);
+}
+
+#[test]
+fn reports_unbalanced_brackets() {
+    let source = "\
+kernel void k(device float* out [[buffer(0)]]) {
+    float x = min(out[0], 1.0;
+    out[1] = x);
+}
+}
+";
+    assert_eq!(
+        diagnostics(source),
+        [(2, "unclosed `(`".to_string()), (3, "unmatched `)`".to_string()), (5, "unmatched `}`".to_string())]
+    );
+
+    assert_eq!(diagnostics("void f() {\n    if (true) {\n}\n"), [(1, "unclosed `{`".to_string())]);
+}
+
+#[test]
+fn skips_brackets_when_conditional_branches_alternate() {
+    let source = "\
+#if USE_FAST
+void f(float x) {
+#else
+void f(half x) {
+#endif
+}
+";
+    assert_eq!(diagnostics(source), []);
+}
+
+#[test]
+fn reports_malformed_attributes() {
+    let source = "\
+vertex float4 v(uint id [[vertex_id]], float4 p [[]]) {
+    return p;
+}
+fragment float4 f(float4 p [[stage_in) {
+    return p;
+}
+";
+    assert_eq!(
+        diagnostics(source),
+        [(1, "empty attribute".to_string()), (4, "unterminated attribute: expected `]]`".to_string())]
+    );
+}
+
+#[test]
+fn reports_missing_semicolon_before_next_statement() {
+    let source = "\
+struct Light {
+    float intensity
+};
+
+float f(float x) {
+    float y = x * 2
+    return y;
+}
+
+float g(float x) {
+    return x
+}
+";
+    assert_eq!(
+        diagnostics(source),
+        [
+            (2, "expected `;` at end of statement".to_string()),
+            (6, "expected `;` at end of statement".to_string()),
+            (11, "expected `;` at end of statement".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,107 @@
+use tower_lsp::lsp_types::Position;
+
+use super::*;
+use crate::syntax::SyntaxTree;
+
+fn usage(source: &str) -> Vec<ThreadgroupUsage> {
+    usage_with_macros(source, &MacroIndex::default())
+}
+
+fn usage_with_macros(
+    source: &str,
+    macros: &MacroIndex,
+) -> Vec<ThreadgroupUsage> {
+    let tree = SyntaxTree::parse(source);
+    threadgroup_usage(&tree.root(), source, macros)
+}
+
+fn sizes(usage: &ThreadgroupUsage) -> Vec<(&str, Option<u64>)> {
+    usage.variables.iter().map(|variable| (variable.name.as_str(), variable.bytes)).collect()
+}
+
+#[test]
+fn sums_scalar_vector_and_matrix_arrays() {
+    let source = "kernel void k() {\n    threadgroup float a[64];\n    threadgroup float3 b[2][4], c;\n    \
+                  threadgroup half4x3 m;\n    threadgroup packed_float3 p[2];\n    threadgroup atomic_uint n;\n}\n";
+    let usages = usage(source);
+    assert_eq!(usages.len(), 1);
+    assert_eq!(usages[0].kernel, "k");
+    assert_eq!(
+        sizes(&usages[0]),
+        vec![("a", Some(256)), ("b", Some(128)), ("c", Some(16)), ("m", Some(32)), ("p", Some(24)), ("n", Some(4))]
+    );
+    assert_eq!(usages[0].total_bytes(), 460);
+    assert!(!usages[0].is_partial());
+}
+
+#[test]
+fn resolves_constants_and_macros_in_extents() {
+    let source = "constant uint TILE = 16;\n#define ROWS (TILE * 2)\nkernel void k() {\n    constexpr int cols = \
+                  TILE + 1;\n    threadgroup float t[ROWS][cols];\n    threadgroup uint s[1 << 4];\n}\n";
+    let mut macros = MacroIndex::default();
+    macros.add_source(source, None);
+    let usages = usage_with_macros(source, &macros);
+    assert_eq!(sizes(&usages[0]), vec![("t", Some(32 * 17 * 4)), ("s", Some(64))]);
+}
+
+#[test]
+fn sizes_structs_declared_in_the_document() {
+    let source = "struct Cell { float3 position; half weight; metal::atomic<int> count; };\nkernel void k() {\n    \
+                  threadgroup Cell cells[8];\n}\n";
+    assert_eq!(sizes(&usage(source)[0]), vec![("cells", Some(8 * 32))]);
+}
+
+#[test]
+fn each_explicit_instantiation_is_sized_on_its_own() {
+    let source = "template <typename T, uint N = 4>\nkernel void k() {\n    threadgroup T t[N * 2];\n}\ntemplate \
+                  [[host_name(\"k_small\")]] kernel void k<half>();\ntemplate [[host_name(\"k_big\")]] kernel void \
+                  k<float, 1024>();\n";
+    let usages = usage(source);
+    let kernels: Vec<(&str, u64)> = usages.iter().map(|usage| (usage.kernel.as_str(), usage.total_bytes())).collect();
+    assert_eq!(kernels, vec![("k<half>", 16), ("k<float, 1024>", 8192)]);
+}
+
+#[test]
+fn unknown_sizes_make_the_total_partial() {
+    let source = "template <uint N>\nkernel void k() {\n    threadgroup float a[N];\n    threadgroup Opaque o;\n    \
+                  threadgroup float b[4];\n}\n";
+    let usages = usage(source);
+    assert_eq!(sizes(&usages[0]), vec![("a", None), ("o", None), ("b", Some(16))]);
+    assert_eq!(usages[0].total_bytes(), 16);
+    assert!(usages[0].is_partial());
+}
+
+#[test]
+fn skips_pointers_parameters_and_non_kernels() {
+    let source = "void helper() { threadgroup float t[4]; }\nvertex float4 v() { return 0; }\nkernel void \
+                  k(threadgroup float* scratch [[threadgroup(0)]]) {\n    threadgroup float* p = scratch;\n}\n";
+    assert!(usage(source).is_empty());
+}
+
+#[test]
+fn warns_past_the_limit_with_the_variables_related() {
+    let source = "kernel void reduce() {\n    threadgroup float4 tile[2048];\n    threadgroup float sum;\n}\n";
+    let tree = SyntaxTree::parse(source);
+    let uri = Url::parse("file:///work/reduce.metal").unwrap();
+    let diagnostics = threadgroup_memory_diagnostics(
+        &tree.root(),
+        source,
+        &uri,
+        &MacroIndex::default(),
+        DEFAULT_THREADGROUP_MEMORY_LIMIT,
+    );
+    assert_eq!(diagnostics.len(), 1);
+    let diagnostic = &diagnostics[0];
+    assert_eq!(diagnostic.range, Range::new(Position::new(0, 12), Position::new(0, 18)));
+    assert_eq!(
+        diagnostic.message,
+        "`reduce` declares 32772 bytes of threadgroup memory, more than the 32768-byte limit"
+    );
+    assert_eq!(diagnostic.code, Some(NumberOrString::String(THREADGROUP_MEMORY_CODE.to_string())));
+    let related = diagnostic.related_information.as_ref().unwrap();
+    assert_eq!(related.len(), 2);
+    assert_eq!(related[0].message, "`tile` takes 32768 bytes");
+
+    assert!(threadgroup_memory_diagnostics(&tree.root(), source, &uri, &MacroIndex::default(), 65536).is_empty());
+    assert!(threadgroup_memory_diagnostics(&tree.root(), source, &uri, &MacroIndex::default(), 0).is_empty());
+}
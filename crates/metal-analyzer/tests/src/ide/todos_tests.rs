@@ -0,0 +1,69 @@
+use tower_lsp::lsp_types::Position;
+
+use super::*;
+use crate::syntax::SyntaxTree;
+
+fn tags() -> Vec<String> {
+    ["TODO", "FIXME", "PERF"].into_iter().map(String::from).collect()
+}
+
+fn todos(source: &str) -> Vec<TodoComment> {
+    find_todos(&SyntaxTree::parse(source).root(), source, &tags())
+}
+
+#[test]
+fn parses_tags_owners_and_text() {
+    let source = "\
+// TODO(alice): clamp the light count
+float x = 1.0; // FIXME handle NaN
+/// PERF: hoist out of the loop
+// TODO
+";
+    let found = todos(source);
+    let summary: Vec<(&str, Option<&str>, &str)> =
+        found.iter().map(|todo| (todo.tag.as_str(), todo.owner.as_deref(), todo.text.as_str())).collect();
+    assert_eq!(
+        summary,
+        [
+            ("TODO", Some("alice"), "clamp the light count"),
+            ("FIXME", None, "handle NaN"),
+            ("PERF", None, "hoist out of the loop"),
+            ("TODO", None, ""),
+        ]
+    );
+    assert_eq!(found[0].range, Range::new(Position::new(0, 3), Position::new(0, 37)));
+    assert_eq!(found[1].range.start, Position::new(1, 18));
+    assert_eq!(found[0].message(), "TODO(alice): clamp the light count");
+    assert_eq!(found[3].message(), "TODO");
+}
+
+#[test]
+fn scans_each_line_of_block_comments() {
+    let source = "/* FIXME(bob): first */\n/*\n * TODO: second\n * not a task\n */\n";
+    let found = todos(source);
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].text, "first");
+    assert_eq!(found[0].range, Range::new(Position::new(0, 3), Position::new(0, 20)));
+    assert_eq!(found[1].text, "second");
+    assert_eq!(found[1].range, Range::new(Position::new(2, 3), Position::new(2, 15)));
+}
+
+#[test]
+fn ignores_strings_identifiers_and_tags_mid_comment() {
+    let source = "\
+constant char* s = \"TODO: not a comment\";
+int TODO_COUNT = 0;
+// TODOS are tracked elsewhere
+// see the TODO above
+";
+    assert!(todos(source).is_empty());
+}
+
+#[test]
+fn reports_information_diagnostics_with_a_code() {
+    let diagnostic = todos("// PERF(carol): cache this\n")[0].to_diagnostic();
+    assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::INFORMATION));
+    assert_eq!(diagnostic.code, Some(NumberOrString::String("todo".to_string())));
+    assert_eq!(diagnostic.message, "PERF(carol): cache this");
+    assert!(find_todos(&SyntaxTree::parse("// TODO: x").root(), "// TODO: x", &[]).is_empty());
+}
@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use super::*;
+use crate::{
+    definition::{RefSite, SymbolDef},
+    syntax::SyntaxTree,
+};
+
+const MAIN: &str = "/p/main.metal";
+
+const SOURCE: &str = "\
+#include \"math.h\"
+#include \"colors.h\"
+#include \"config.h\"
+#include \"unused.h\"
+
+kernel void k(device Params* params [[buffer(0)]])
+{
+    params->value = square(TILE_SIZE);
+}
+";
+
+fn def(
+    id: &str,
+    name: &str,
+    kind: &str,
+    file: &str,
+) -> SymbolDef {
+    SymbolDef {
+        id: id.to_string(),
+        name: name.into(),
+        kind: kind.into(),
+        file: file.into(),
+        line: 1,
+        col: 1,
+        is_definition: true,
+        type_name: None,
+        qual_type: None,
+        file_local: false,
+    }
+}
+
+fn reference(target: &SymbolDef) -> RefSite {
+    RefSite {
+        file: MAIN.into(),
+        line: 8,
+        col: 21,
+        tok_len: target.name.len() as u32,
+        target_id: target.id.clone(),
+        target_name: target.name.clone(),
+        target_kind: target.kind.clone(),
+        expansion: None,
+        spelling: None,
+    }
+}
+
+fn index() -> AstIndex {
+    let square = def("0x1", "square", "FunctionDecl", "/p/math.h");
+    let params = def("0x2", "Params", "CXXRecordDecl", "/p/types.h");
+    let value = def("0x3", "value", "FieldDecl", "/p/unused.h");
+    let unused = def("0x4", "Unused", "CXXRecordDecl", "/p/unused.h");
+    let refs = vec![reference(&square)];
+    AstIndex::from_parts(vec![square, params, value, unused], refs, Vec::new())
+}
+
+fn headers() -> Vec<ReachedHeader<'static>> {
+    vec![
+        ReachedHeader {
+            path: Path::new("/p/math.h"),
+            source: "float square(float x);\n",
+            origin_line: 0,
+        },
+        ReachedHeader {
+            path: Path::new("/p/colors.h"),
+            source: "#include \"types.h\"\n",
+            origin_line: 1,
+        },
+        ReachedHeader {
+            path: Path::new("/p/types.h"),
+            source: "struct Params { float value; };\n",
+            origin_line: 1,
+        },
+        ReachedHeader {
+            path: Path::new("/p/config.h"),
+            source: "#define TILE_SIZE 16\n",
+            origin_line: 2,
+        },
+        ReachedHeader {
+            path: Path::new("/p/unused.h"),
+            source: "struct Unused { float value; };\n",
+            origin_line: 3,
+        },
+    ]
+}
+
+fn unused_lines(source: &str) -> Vec<u32> {
+    let tree = SyntaxTree::parse(source);
+    unused_includes(&tree.root(), source, Path::new(MAIN), &index(), &[0, 1, 2, 3], &headers())
+        .iter()
+        .map(|diagnostic| diagnostic.range.start.line)
+        .collect()
+}
+
+#[test]
+fn reports_only_includes_whose_headers_are_unused() {
+    // `square` is referenced, `Params` is reached through colors.h, and
+    // `TILE_SIZE` is a macro of config.h; naming the field `value` does not
+    // make unused.h needed.
+    assert_eq!(unused_lines(SOURCE), [3]);
+}
+
+#[test]
+fn hint_covers_the_include_line() {
+    let tree = SyntaxTree::parse(SOURCE);
+    let diagnostics = unused_includes(&tree.root(), SOURCE, Path::new(MAIN), &index(), &[3], &headers());
+    let [diagnostic] = diagnostics.as_slice() else {
+        panic!("expected one hint, got {diagnostics:?}");
+    };
+    assert_eq!(diagnostic.range, Range::new(Position::new(3, 0), Position::new(3, 19)));
+    assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::HINT));
+    assert_eq!(diagnostic.tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+    assert_eq!(diagnostic.message, "include not used directly");
+}
+
+#[test]
+fn macro_header_is_unused_once_its_macros_are_not_named() {
+    let source = SOURCE.replace("square(TILE_SIZE)", "0");
+    assert_eq!(unused_lines(&source), [2, 3]);
+}
+
+#[test]
+fn removal_deletes_the_whole_line() {
+    let uri = Url::parse("file:///p/main.metal").unwrap();
+    let tree = SyntaxTree::parse(SOURCE);
+    let diagnostics = unused_includes(&tree.root(), SOURCE, Path::new(MAIN), &index(), &[3], &headers());
+    let actions = remove_include_actions(&uri, &diagnostics);
+    let [CodeActionOrCommand::CodeAction(action)] = actions.as_slice() else {
+        panic!("expected one code action, got {actions:?}");
+    };
+    assert_eq!(action.kind, Some(CodeActionKind::QUICKFIX));
+    let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+    assert_eq!(edits, &vec![TextEdit::new(Range::new(Position::new(3, 0), Position::new(4, 0)), String::new())]);
+}
@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+use super::*;
+use crate::definition::{AstIndex, RefSite, SymbolDef};
+
+const SOURCE: &str = "static float old_blur(float x) { return x; }\nconstant float kScale = 2.0;\n/* ¼ scale */ static float \
+     keep_for_later() { return 0; }\n";
+
+fn file_local(
+    name: &str,
+    kind: &str,
+    line: u32,
+    col: u32,
+) -> SymbolDef {
+    SymbolDef {
+        id: format!("{name}-{line}"),
+        name: name.into(),
+        kind: kind.into(),
+        file: "/ws/a.metal".into(),
+        line,
+        col,
+        is_definition: true,
+        type_name: None,
+        qual_type: None,
+        file_local: true,
+    }
+}
+
+fn project_index(defs: Vec<SymbolDef>) -> ProjectIndex {
+    let project_index = ProjectIndex::new();
+    project_index
+        .update_file(PathBuf::from("/ws/a.metal"), AstIndex::from_parts(defs, Vec::<RefSite>::new(), Vec::new()));
+    project_index
+}
+
+#[test]
+fn hints_at_unused_functions_and_constants() {
+    let index = project_index(vec![
+        file_local("old_blur", "FunctionDecl", 1, 14),
+        file_local("kScale", "VarDecl", 2, 16),
+        file_local("keep_for_later", "FunctionDecl", 3, 29),
+    ]);
+    let diagnostics = unused_symbol_diagnostics(&index, Path::new("/ws/a.metal"), SOURCE, &[]);
+    let mut found: Vec<(u32, u32, u32, String)> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            (
+                diagnostic.range.start.line,
+                diagnostic.range.start.character,
+                diagnostic.range.end.character,
+                diagnostic.message.clone(),
+            )
+        })
+        .collect();
+    found.sort();
+    assert_eq!(
+        found,
+        vec![
+            (0, 13, 21, "function `old_blur` is never used".to_string()),
+            (1, 15, 21, "constant `kScale` is never used".to_string()),
+            (2, 27, 41, "function `keep_for_later` is never used".to_string()),
+        ]
+    );
+    assert!(diagnostics.iter().all(|diagnostic| {
+        diagnostic.severity == Some(DiagnosticSeverity::HINT)
+            && diagnostic.tags == Some(vec![DiagnosticTag::UNNECESSARY])
+            && diagnostic.code == Some(NumberOrString::String(UNUSED_SYMBOL_CODE.to_string()))
+    }));
+}
+
+#[test]
+fn allowlisted_and_stale_definitions_are_skipped() {
+    let index = project_index(vec![
+        file_local("old_blur", "FunctionDecl", 1, 14),
+        file_local("kScale", "VarDecl", 2, 1),
+        file_local("keep_for_later", "FunctionDecl", 3, 29),
+    ]);
+    let allow = vec!["keep_for_later".to_string()];
+    let diagnostics = unused_symbol_diagnostics(&index, Path::new("/ws/a.metal"), SOURCE, &allow);
+    let names: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic.message.as_str()).collect();
+    assert_eq!(names, vec!["function `old_blur` is never used"]);
+    assert!(unused_symbol_diagnostics(&index, Path::new("/ws/b.metal"), SOURCE, &[]).is_empty());
+}
@@ -43,3 +43,36 @@ fn test_no_duplicate_labels() {
         }
     }
 }
+
+#[test]
+fn attribute_table_filters_by_target_and_stage() {
+    let spec = |name: &str| ATTRIBUTES.iter().find(|spec| spec.name == name).expect("attribute in table");
+    assert!(spec("buffer").applies_to(AttributeTarget::Parameter, Some(ShaderStage::Vertex)));
+    assert!(!spec("buffer").applies_to(AttributeTarget::Member, None));
+    assert!(spec("vertex_id").applies_to(AttributeTarget::Parameter, None));
+    assert!(!spec("vertex_id").applies_to(AttributeTarget::Parameter, Some(ShaderStage::Kernel)));
+    assert!(spec("position").applies_to(AttributeTarget::Member, Some(ShaderStage::Kernel)));
+}
+
+#[test]
+fn new_attributes_are_hoverable_only_in_brackets() {
+    assert_eq!(lookup("[[user(n)]]").map(|entry| entry.kind), Some(types::BuiltinKind::Attribute));
+    assert!(lookup("[[depth(n)]]").is_some());
+    assert!(lookup("depth").is_none());
+    assert!(lookup("id").is_none());
+}
+
+#[test]
+fn newer_builtins_record_the_version_that_introduced_them() {
+    use crate::metal::language_version::LanguageVersion;
+
+    let bfloat = lookup("bfloat4").unwrap();
+    assert_eq!(bfloat.since, Some(LanguageVersion::new(3, 1)));
+    assert!(!bfloat.is_available_in(Some(LanguageVersion::new(3, 0))));
+    assert!(bfloat.is_available_in(Some(LanguageVersion::new(3, 1))));
+    assert!(bfloat.is_available_in(None));
+
+    let float4 = lookup("float4").unwrap();
+    assert_eq!(float4.since, None);
+    assert!(float4.is_available_in(Some(LanguageVersion::new(2, 0))));
+}
@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use super::*;
+
+fn override_for(path_glob: &str) -> CompilerOverride {
+    CompilerOverride {
+        path_glob: path_glob.to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn relative_globs_match_the_end_of_the_path() {
+    let ios = override_for("ios/**");
+    assert!(ios.matches(Path::new("/work/shaders/ios/blur.metal")));
+    assert!(ios.matches(Path::new("/work/shaders/ios/post/blur.metal")));
+    assert!(!ios.matches(Path::new("/work/shaders/macos/blur.metal")));
+    assert!(!ios.matches(Path::new("/work/shaders/ios-legacy/blur.metal")));
+
+    assert!(override_for("ios/").matches(Path::new("/work/ios/blur.metal")));
+    assert!(override_for("*.metal").matches(Path::new("/work/ios/blur.metal")));
+    assert!(!override_for("*.metal").matches(Path::new("/work/ios/blur.h")));
+}
+
+#[test]
+fn absolute_globs_match_the_whole_path() {
+    let entry = override_for("/work/**/kernels/*.metal");
+    assert!(entry.matches(Path::new("/work/kernels/a.metal")));
+    assert!(entry.matches(Path::new("/work/macos/kernels/a.metal")));
+    assert!(!entry.matches(Path::new("/other/work/kernels/a.metal")));
+    assert!(!entry.matches(Path::new("/work/kernels/nested/a.metal")));
+}
+
+#[test]
+fn segment_wildcards() {
+    assert!(override_for("blur_?.metal").matches(Path::new("/w/blur_x.metal")));
+    assert!(!override_for("blur_?.metal").matches(Path::new("/w/blur_xy.metal")));
+    assert!(override_for("*_ios*.metal").matches(Path::new("/w/blur_ios_v2.metal")));
+    assert!(!override_for("").matches(Path::new("/w/blur.metal")));
+}
+
+#[test]
+fn matching_keeps_override_order() {
+    let overrides = vec![override_for("**/*.metal"), override_for("macos/**"), override_for("ios/**")];
+    let globs: Vec<&str> =
+        matching(&overrides, Path::new("/w/ios/a.metal")).map(|entry| entry.path_glob.as_str()).collect();
+    assert_eq!(globs, vec!["**/*.metal", "ios/**"]);
+}
@@ -4,7 +4,7 @@ use super::*;
 fn parse_error_line() {
     let compiler = MetalCompiler::new();
     let line = "shader.metal:10:5: error: use of undeclared identifier 'foo'";
-    let diag = compiler.parse_diagnostic_line(line).unwrap();
+    let diag = compiler.parse_diagnostic_line(line, None).unwrap();
 
     assert_eq!(diag.file.as_deref(), Some("shader.metal"));
     assert_eq!(diag.line, 9); // 0-based
@@ -17,7 +17,7 @@ fn parse_error_line() {
 fn parse_warning_line() {
     let compiler = MetalCompiler::new();
     let line = "/tmp/shader.metal:3:12: warning: unused variable 'x'";
-    let diag = compiler.parse_diagnostic_line(line).unwrap();
+    let diag = compiler.parse_diagnostic_line(line, None).unwrap();
 
     assert_eq!(diag.file.as_deref(), Some("/tmp/shader.metal"));
     assert_eq!(diag.line, 2);
@@ -30,17 +30,53 @@ fn parse_warning_line() {
 fn parse_note_line() {
     let compiler = MetalCompiler::new();
     let line = "shader.metal:1:1: note: previous definition is here";
-    let diag = compiler.parse_diagnostic_line(line).unwrap();
+    let diag = compiler.parse_diagnostic_line(line, None).unwrap();
 
     assert_eq!(diag.file.as_deref(), Some("shader.metal"));
     assert_eq!(diag.severity, DiagnosticSeverity::INFORMATION);
 }
 
+#[test]
+fn parse_line_with_source_ranges_spans_them() {
+    let compiler = MetalCompiler::new();
+    let line = "shader.metal:4:12:{4:5-4:11}{4:14-4:20}{9:1-9:4}: error: invalid operands to binary expression";
+    let diag = compiler.parse_diagnostic_line(line, None).unwrap();
+
+    assert_eq!((diag.line, diag.column), (3, 4));
+    assert_eq!(diag.end, Some(Position::new(3, 19)));
+    assert_eq!(diag.message, "invalid operands to binary expression");
+}
+
+#[test]
+fn parse_line_without_source_ranges_spans_the_token() {
+    let dir = std::env::temp_dir().join(format!("metal-analyzer-diag-token-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("shader.metal");
+    std::fs::write(&file, "kernel void k() {\n    float x = undefined_name + 1;\n}\n").unwrap();
+    let compiler = MetalCompiler::new();
+    let line = format!("{}:2:15: error: use of undeclared identifier 'undefined_name'", file.display());
+    let diag = compiler.parse_diagnostic_line(&line, None).unwrap();
+
+    assert_eq!((diag.line, diag.column), (1, 14));
+    assert_eq!(diag.end, Some(Position::new(1, 28)));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn token_end_covers_identifiers_numbers_and_literals() {
+    let line = r#"x = 1.5f + name_2 * "a\"b";"#;
+    assert_eq!(token_end(line, 4), Some(8));
+    assert_eq!(token_end(line, 11), Some(17));
+    assert_eq!(token_end(line, 18), Some(19));
+    assert_eq!(token_end(line, 20), Some(26));
+    assert_eq!(token_end(line, 99), None);
+}
+
 #[test]
 fn parse_non_diagnostic_line() {
     let compiler = MetalCompiler::new();
-    assert!(compiler.parse_diagnostic_line("some random output").is_none());
-    assert!(compiler.parse_diagnostic_line("").is_none());
+    assert!(compiler.parse_diagnostic_line("some random output", None).is_none());
+    assert!(compiler.parse_diagnostic_line("", None).is_none());
 }
 
 #[test]
@@ -71,11 +107,7 @@ End of search list.
         "regular include dirs should be captured from search list"
     );
     let canonical_framework = framework_dir.canonicalize().expect("canonical framework");
-    let expected_entry = PathBuf::from(format!(
-        "{}{}",
-        FRAMEWORK_DIR_PREFIX,
-        canonical_framework.display()
-    ));
+    let expected_entry = PathBuf::from(format!("{}{}", FRAMEWORK_DIR_PREFIX, canonical_framework.display()));
     assert!(
         paths.contains(&expected_entry),
         "framework dirs should be encoded with the framework: prefix (got: {paths:?})"
@@ -182,8 +214,11 @@ fn diagnostic_to_lsp() {
         file: Some("/tmp/shader.metal".to_string()),
         line: 5,
         column: 10,
+        end: None,
         severity: DiagnosticSeverity::ERROR,
         message: "something went wrong".to_string(),
+        related_information: Vec::new(),
+        fix_its: Vec::new(),
     };
     let lsp = diag.into_lsp_diagnostic();
     assert_eq!(lsp.range.start.line, 5);
@@ -191,6 +226,115 @@ fn diagnostic_to_lsp() {
     assert_eq!(lsp.source.as_deref(), Some("metal-compiler"));
 }
 
+#[test]
+fn diagnostic_to_lsp_links_kept_artifact() {
+    let artifact = Location {
+        uri: Url::parse("file:///tmp/metal-analyzer-artifacts/shader-1.metal").unwrap(),
+        range: Range::new(Position::new(5, 10), Position::new(5, 10)),
+    };
+    let diag = MetalDiagnostic {
+        file: Some("/tmp/shader.metal".to_string()),
+        line: 5,
+        column: 10,
+        end: None,
+        severity: DiagnosticSeverity::ERROR,
+        message: "something went wrong".to_string(),
+        related_information: vec![DiagnosticRelatedInformation {
+            location: artifact.clone(),
+            message: "kept".to_string(),
+        }],
+        fix_its: Vec::new(),
+    };
+    let related = diag.into_lsp_diagnostic().related_information.expect("related information");
+    assert_eq!(related.len(), 1);
+    assert_eq!(related[0].location, artifact);
+}
+
+#[test]
+fn temp_dir_follows_configured_base() {
+    let compiler = MetalCompiler::new();
+    let base = std::env::temp_dir().join(format!("metal-analyzer-temp-base-test-{}", std::process::id()));
+    compiler.set_temp_dir(Some(base.clone()));
+    assert_eq!(compiler.temp_dir(), base.join(format!("metal-analyzer-{}", std::process::id())));
+    assert_eq!(compiler.artifacts_dir(), base.join("metal-analyzer-artifacts"));
+
+    compiler.set_temp_dir(None);
+    assert_eq!(compiler.temp_dir().parent(), Some(std::env::temp_dir().as_path()));
+}
+
+#[test]
+fn retired_temp_dir_outlives_compiles_still_using_it() {
+    let compiler = MetalCompiler::new();
+    let base = std::env::temp_dir().join(format!("metal-analyzer-temp-lease-test-{}", std::process::id()));
+    compiler.set_temp_dir(Some(base.join("first")));
+    let lease = compiler.lease_temp_dir();
+    std::fs::create_dir_all(&lease.path).unwrap();
+
+    compiler.set_temp_dir(Some(base.join("second")));
+    assert!(lease.path.exists(), "an in-flight compile keeps its directory");
+    let retired = lease.path.clone();
+    drop(lease);
+    assert!(!retired.exists(), "the directory goes once the last compile is done");
+
+    let kept = compiler.lease_temp_dir();
+    std::fs::create_dir_all(&kept.path).unwrap();
+    compiler.set_temp_dir(Some(base.join("first")));
+    compiler.set_temp_dir(Some(base.join("second")));
+    assert!(Arc::ptr_eq(&kept, &compiler.lease_temp_dir()), "switching back reuses the directory in use");
+    drop(kept);
+    assert!(compiler.temp_dir().exists());
+    let _ = std::fs::remove_dir_all(&base);
+}
+
+#[test]
+fn attach_artifact_maps_main_file_positions() {
+    let error = |file: &str| MetalDiagnostic {
+        file: Some(file.to_string()),
+        line: 3,
+        column: 4,
+        end: None,
+        severity: DiagnosticSeverity::ERROR,
+        message: "error".to_string(),
+        related_information: Vec::new(),
+        fix_its: Vec::new(),
+    };
+    let mut diagnostics = vec![error("/src/main.metal"), error("/src/header.h")];
+    attach_artifact(&mut diagnostics, Path::new("/tmp/artifacts/main-1.metal"), Some("/src/main.metal"));
+
+    let main = &diagnostics[0].related_information[0].location;
+    assert_eq!(main.range.start, Position::new(3, 4));
+    let header = &diagnostics[1].related_information[0].location;
+    assert_eq!(header.range.start, Position::new(0, 0));
+}
+
+#[test]
+fn prune_artifacts_drops_oldest_first_and_keeps_latest() {
+    let dir = std::env::temp_dir().join(format!(
+        "metal-analyzer-prune-test-{}",
+        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).expect("clock drift").as_nanos()
+    ));
+    std::fs::create_dir_all(&dir).expect("create artifacts dir");
+    let write = |name: &str, age_secs: u64| {
+        let path = dir.join(name);
+        std::fs::write(&path, vec![b'x'; 100]).expect("write artifact");
+        let modified = std::time::SystemTime::now() - std::time::Duration::from_secs(age_secs);
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        path
+    };
+    let oldest = write("a-1.metal", 30);
+    let middle = write("b-2.metal", 20);
+    let latest = write("c-3.metal", 10);
+    let latest_cmd = write("c-3.cmd", 10);
+
+    prune_artifacts(&dir, 150, &latest);
+    assert!(!oldest.exists());
+    assert!(!middle.exists());
+    assert!(latest.exists(), "the artifact just written is never pruned");
+    assert!(latest_cmd.exists(), "its command file is kept with it");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
 #[test]
 fn add_include_paths_and_flags() {
     let compiler = MetalCompiler::new();
@@ -261,6 +405,17 @@ fn target_or_sdk_flags_prevent_injection() {
     assert_eq!(effective, user_flags);
 }
 
+#[test]
+fn defines_from_flags_applies_defines_and_undefines_in_order() {
+    let flags = as_flags(&["-DUSE_FAST", "-D", "TILE=16", "-DNAME=a=b", "-std=metal3.1", "-DGONE", "-UGONE"]);
+    let defines = MetalCompiler::defines_from_flags(&flags);
+    assert_eq!(defines.get("USE_FAST").map(String::as_str), Some("1"));
+    assert_eq!(defines.get("TILE").map(String::as_str), Some("16"));
+    assert_eq!(defines.get("NAME").map(String::as_str), Some("a=b"));
+    assert!(!defines.contains_key("GONE"));
+    assert_eq!(defines.len(), 3);
+}
+
 // ── compute_include_paths ───────────────────────────────────────────────
 
 #[test]
@@ -457,3 +612,350 @@ kernel void k(device uint* out [[buffer(0)]], uint id [[thread_position_in_grid]
 
     std::fs::remove_dir_all(&temp_dir).ok();
 }
+
+const SDK_MATH: &str =
+    "/Applications/Xcode.app/Contents/Developer/Toolchains/XcodeDefault.xctoolchain/usr/metal/include/metal_math";
+const SDK_STDLIB: &str =
+    "/Applications/Xcode.app/Contents/Developer/Toolchains/XcodeDefault.xctoolchain/usr/metal/include/metal_stdlib";
+
+#[test]
+fn parse_diagnostics_collects_include_stack() {
+    let compiler = MetalCompiler::new();
+    let output = format!(
+        "In file included from /src/main.metal:1:\n\
+         In file included from /src/common.h:3:\n\
+         In file included from {SDK_STDLIB}:12:\n\
+         {SDK_MATH}:40:5: error: first\n\
+         {SDK_MATH}:41:5: error: second\n\
+         /src/main.metal:9:2: error: in main\n"
+    );
+    let parsed = compiler.parse_diagnostics(&output, None);
+    assert_eq!(parsed.len(), 3);
+    let expected_stack = vec![
+        IncludeFrame {
+            file: "/src/main.metal".to_string(),
+            line: 0,
+        },
+        IncludeFrame {
+            file: "/src/common.h".to_string(),
+            line: 2,
+        },
+        IncludeFrame {
+            file: SDK_STDLIB.to_string(),
+            line: 11,
+        },
+    ];
+    assert_eq!(parsed[0].include_stack, expected_stack);
+    assert_eq!(parsed[1].include_stack, expected_stack, "same file inherits the previous stack");
+    assert!(parsed[2].include_stack.is_empty());
+}
+
+#[test]
+fn classified_messages_carry_a_code_and_spec_link() {
+    let diagnostic = |message: &str| {
+        MetalDiagnostic {
+            file: None,
+            line: 0,
+            column: 0,
+            end: None,
+            severity: DiagnosticSeverity::ERROR,
+            message: message.to_string(),
+            related_information: Vec::new(),
+            fix_its: Vec::new(),
+        }
+        .into_lsp_diagnostic()
+    };
+
+    let classified = diagnostic("cannot write to a read-only texture");
+    assert_eq!(classified.code, Some(NumberOrString::String("texture-access".to_string())));
+    assert_eq!(classified.code_description.map(|description| description.href.to_string()).as_deref(), Some(SPEC_URL));
+
+    let other = diagnostic("use of undeclared identifier 'foo'");
+    assert_eq!(other.code, None);
+    assert_eq!(other.code_description, None);
+}
+
+#[test]
+fn fix_it_lines_attach_to_the_diagnostic_before_them() {
+    let compiler = MetalCompiler::new();
+    let output = "/src/main.metal:3:17: error: expected ';' after expression\n\
+                  \x20   out[0] = 1.0\n\
+                  \x20               ^\n\
+                  \x20               ;\n\
+                  fix-it:\"/src/main.metal\":{3:17-3:17}:\";\"\n\
+                  /src/main.metal:5:1: error: unknown type name 'flaot'\n";
+    let parsed = compiler.parse_diagnostics(output, None);
+
+    assert_eq!(parsed.len(), 2);
+    assert_eq!(
+        parsed[0].diagnostic.fix_its,
+        vec![FixIt {
+            file: "/src/main.metal".to_string(),
+            range: Range::new(Position::new(2, 16), Position::new(2, 16)),
+            replacement: ";".to_string(),
+        }]
+    );
+    assert!(parsed[1].diagnostic.fix_its.is_empty());
+}
+
+#[test]
+fn system_header_errors_move_to_user_include_site() {
+    let compiler = MetalCompiler::new();
+    let output = format!(
+        "In file included from /src/main.metal:1:\n\
+         In file included from /src/common.h:3:\n\
+         In file included from {SDK_STDLIB}:12:\n\
+         {SDK_MATH}:40:5: error: first\n\
+         {SDK_MATH}:40:9: note: candidate here\n\
+         {SDK_MATH}:41:5: error: second\n\
+         /src/main.metal:9:2: error: in main\n"
+    );
+    let diagnostics = attribute_to_include_sites(compiler.parse_diagnostics(&output, None), is_system_header);
+
+    assert_eq!(diagnostics.len(), 3, "second error folds into the first: {diagnostics:#?}");
+    let remapped = &diagnostics[0];
+    assert_eq!(remapped.file.as_deref(), Some("/src/common.h"));
+    assert_eq!((remapped.line, remapped.column), (2, 0));
+    assert_eq!(remapped.message, "In included file: first");
+    let origins: Vec<_> = remapped
+        .related_information
+        .iter()
+        .map(|info| (info.location.range.start.line, info.message.as_str()))
+        .collect();
+    assert_eq!(origins, vec![(39, "first"), (40, "second")]);
+    assert!(remapped.related_information[0].location.uri.path().ends_with("/metal_math"));
+
+    assert_eq!(diagnostics[1].severity, DiagnosticSeverity::INFORMATION, "notes stay where they are");
+    assert_eq!(diagnostics[1].file.as_deref(), Some(SDK_MATH));
+    assert_eq!(diagnostics[2].message, "in main");
+    assert!(diagnostics[2].related_information.is_empty());
+}
+
+#[test]
+fn system_header_errors_without_user_include_are_kept() {
+    let compiler = MetalCompiler::new();
+    let output = format!("{SDK_MATH}:40:5: error: no include stack\n");
+    let diagnostics = attribute_to_include_sites(compiler.parse_diagnostics(&output, None), is_system_header);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].file.as_deref(), Some(SDK_MATH));
+    assert_eq!(diagnostics[0].message, "no include stack");
+}
+
+#[test]
+fn overrides_add_flags_and_include_paths_for_matching_files() {
+    let compiler = MetalCompiler::new();
+    compiler.set_flags(as_flags(&["-std=metal3.1"]));
+    compiler.set_overrides(vec![CompilerOverride {
+        path_glob: "ios/**".to_string(),
+        flags: as_flags(&["-D__METAL_IOS__", "-DUSE_HALF=1"]),
+        include_paths: as_flags(&["/work/ios/include"]),
+    }]);
+
+    let ios = Url::from_file_path("/work/ios/blur.metal").unwrap();
+    let args = compiler.search_path_and_flag_args(ios.as_str(), &[]);
+    assert_eq!(args, as_flags(&["-I", "/work/ios/include", "-std=metal3.1", "-D__METAL_IOS__", "-DUSE_HALF=1"]));
+    assert_eq!(compiler.effective_defines(&ios).get("USE_HALF").map(String::as_str), Some("1"));
+
+    let macos = Url::from_file_path("/work/macos/blur.metal").unwrap();
+    let args = compiler.search_path_and_flag_args(macos.as_str(), &[]);
+    assert_eq!(args, as_flags(&["-std=metal3.1", "-D__METAL_MACOS__"]));
+}
+
+#[test]
+fn language_version_adds_a_std_flag_unless_one_is_passed() {
+    let compiler = MetalCompiler::new();
+    compiler.set_language_version(Some(LanguageVersion::new(3, 0)));
+    let uri = Url::from_file_path("/work/blur.metal").unwrap();
+    assert_eq!(
+        compiler.search_path_and_flag_args(uri.as_str(), &[]),
+        as_flags(&["-std=metal3.0", "-D__METAL_MACOS__"])
+    );
+    assert_eq!(compiler.language_version(&uri), Some(LanguageVersion::new(3, 0)));
+    assert_eq!(compiler.effective_defines(&uri).get("__METAL_VERSION__").map(String::as_str), Some("300"));
+
+    compiler.set_flags(as_flags(&["-std=metal3.1"]));
+    assert_eq!(
+        compiler.search_path_and_flag_args(uri.as_str(), &[]),
+        as_flags(&["-std=metal3.1", "-D__METAL_MACOS__"])
+    );
+    assert_eq!(compiler.language_version(&uri), Some(LanguageVersion::new(3, 1)));
+
+    compiler.set_flags(Vec::new());
+    compiler.set_language_version(None);
+    assert_eq!(compiler.language_version(&uri), None);
+    assert!(!compiler.effective_defines(&uri).contains_key("__METAL_VERSION__"));
+}
+
+#[tokio::test]
+async fn compile_binary_writes_air_and_metallib_named_after_the_document() {
+    if !MetalCompiler::is_available().await {
+        return;
+    }
+
+    let compiler = MetalCompiler::new();
+    let uri = Url::from_file_path("/work/blur.metal").unwrap();
+    let source = "#include <metal_stdlib>\nkernel void blur(device float* out [[buffer(0)]]) { out[0] = 1.0; }\n";
+    let include_paths: Vec<String> =
+        compiler.get_system_include_paths().iter().map(|path| path.display().to_string()).collect();
+    let binary = compiler.compile_binary(source, uri.as_str(), &include_paths).await.unwrap();
+    let metallib = binary.metallib.expect("metallib");
+    assert_eq!(metallib.file_name().and_then(|name| name.to_str()), Some("blur.metallib"));
+    assert!(std::fs::metadata(&metallib).unwrap().len() > 0);
+    assert!(binary.air.is_some_and(|air| air.exists()));
+
+    let broken = compiler.compile_binary("kernel void k() { oops }\n", uri.as_str(), &include_paths).await.unwrap();
+    assert!(broken.air.is_none() && broken.metallib.is_none());
+    assert!(broken.diagnostics.iter().any(|diag| diag.severity == DiagnosticSeverity::ERROR));
+}
+
+#[tokio::test]
+async fn kernel_stats_report_threadgroup_memory_of_each_kernel() {
+    if !MetalCompiler::is_available().await {
+        return;
+    }
+
+    let compiler = MetalCompiler::new();
+    let uri = Url::from_file_path("/work/reduce.metal").unwrap();
+    let source = "#include <metal_stdlib>\nusing namespace metal;\nkernel void reduce(device float* out [[buffer(0)]], \
+                  uint lid [[thread_position_in_threadgroup]]) {\n    threadgroup float tile[64];\n    tile[lid] = \
+                  out[lid];\n    threadgroup_barrier(mem_flags::mem_threadgroup);\n    out[lid] = tile[63 - lid];\n}\n";
+    let include_paths: Vec<String> =
+        compiler.get_system_include_paths().iter().map(|path| path.display().to_string()).collect();
+    let stats = compiler.kernel_stats(source, uri.as_str(), &include_paths).await.unwrap();
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0].name, "reduce");
+    assert_eq!(stats[0].stage, "kernel");
+    assert_eq!(stats[0].threadgroup_memory_bytes, 256);
+    assert!(stats[0].instructions > 0);
+
+    assert!(compiler.kernel_stats("kernel void k() { oops }\n", uri.as_str(), &include_paths).await.is_err());
+}
+
+#[tokio::test]
+async fn disassembly_maps_the_kernel_back_to_the_document() {
+    if !MetalCompiler::is_available().await {
+        return;
+    }
+
+    let compiler = MetalCompiler::new();
+    let dir = std::env::temp_dir().join(format!("disassemble_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.canonicalize().unwrap().join("scale.metal");
+    let uri = Url::from_file_path(&path).unwrap();
+    let source =
+        "#include <metal_stdlib>\nkernel void scale(device float* out [[buffer(0)]]) {\n    out[0] *= 2.0;\n}\n";
+    let include_paths: Vec<String> =
+        compiler.get_system_include_paths().iter().map(|path| path.display().to_string()).collect();
+    let ir = compiler.disassemble(source, uri.as_str(), &include_paths).await.unwrap();
+    let functions = crate::metal::toolchain::ir_functions(&ir, &path);
+    let scale = functions.iter().find(|function| function.name == "scale").expect("scale");
+    assert_eq!(scale.source_line, Some(1));
+    assert!(scale.lines.iter().any(|(_, line)| *line == 2));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn root_settings_apply_to_files_of_the_innermost_root() {
+    let compiler = MetalCompiler::new();
+    compiler.set_include_paths(vec![PathBuf::from("/global/include")]);
+    compiler.set_flags(vec!["-DGLOBAL".to_string()]);
+    let root = |root: &str, include: &str, flag: &str| RootCompilerSettings {
+        root: PathBuf::from(root),
+        include_paths: vec![PathBuf::from(include)],
+        flags: vec![flag.to_string()],
+        platform: CompilerPlatform::Macos,
+        language_version: None,
+    };
+    compiler
+        .set_root_settings(vec![root("/ws", "/ws/include", "-DOUTER"), root("/ws/app", "/ws/app/include", "-DAPP")]);
+
+    let app = Url::parse("file:///ws/app/shaders/blur.metal").unwrap();
+    assert_eq!(compiler.include_paths_for(app.as_str()), vec![PathBuf::from("/ws/app/include")]);
+    assert!(compiler.effective_defines(&app).contains_key("APP"));
+    assert!(!compiler.effective_defines(&app).contains_key("GLOBAL"));
+
+    let outer = Url::parse("file:///ws/tools/copy.metal").unwrap();
+    assert!(compiler.effective_defines(&outer).contains_key("OUTER"));
+
+    let elsewhere = Url::parse("file:///tmp/scratch.metal").unwrap();
+    assert_eq!(compiler.include_paths_for(elsewhere.as_str()), vec![PathBuf::from("/global/include")]);
+    assert!(compiler.effective_defines(&elsewhere).contains_key("GLOBAL"));
+}
+
+#[test]
+fn compute_include_paths_stops_at_the_innermost_workspace_root() {
+    let temp_dir = std::env::temp_dir().join(format!("metal-analyzer-nested-roots-{}", std::process::id()));
+    let outer = temp_dir.join("outer");
+    let inner = outer.join("inner");
+    let shaders = inner.join("shaders");
+    std::fs::create_dir_all(&shaders).unwrap();
+    let file = shaders.join("blur.metal");
+    std::fs::write(&file, "").unwrap();
+
+    let paths = compute_include_paths(&file, Some(&[outer.clone(), inner.clone()]));
+
+    assert!(paths.contains(&inner.display().to_string()));
+    assert!(!paths.contains(&outer.display().to_string()));
+
+    std::fs::remove_dir_all(&temp_dir).ok();
+}
+
+#[test]
+fn stdin_diagnostics_map_to_the_document() {
+    let compiler = MetalCompiler::new();
+    let stderr = "<stdin>:3:5: error: use of undeclared identifier 'x'\n";
+    let diagnostics = compiler.diagnostics_from_stderr(stderr, "", Some("/src/main.metal"), Path::new(STDIN_FILE_NAME));
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].file.as_deref(), Some("/src/main.metal"));
+
+    let header = "/missing/include/common.h";
+    assert_eq!(remap_compiled_path(header, Some("/src/main.metal"), Path::new(STDIN_FILE_NAME)), header);
+}
+
+#[test]
+fn stdin_diagnostics_convert_columns_against_the_source() {
+    let compiler = MetalCompiler::new();
+    let source = "kernel void k() {\n    float héllo = 1; float x = undefined_name;\n}\n";
+    let byte_column = source.lines().nth(1).unwrap().find("undefined_name").unwrap() + 1;
+    let stderr = format!("<stdin>:2:{byte_column}: error: use of undeclared identifier 'undefined_name'\n");
+    let diagnostics =
+        compiler.diagnostics_from_stderr(&stderr, source, Some("/src/main.metal"), Path::new(STDIN_FILE_NAME));
+
+    assert_eq!(diagnostics.len(), 1);
+    let start = byte_column as u32 - 2;
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, start));
+    assert_eq!(diagnostics[0].end, Some(Position::new(1, start + "undefined_name".len() as u32)));
+}
+
+#[test]
+fn rejected_stdin_input_is_detected() {
+    assert!(rejects_stdin_input("error: no such file or directory: '-'\n"));
+    assert!(rejects_stdin_input("error: invalid value 'metal' in '-x metal'\n"));
+    assert!(!rejects_stdin_input("<stdin>:1:1: error: unknown type name 'foo'\n"));
+}
+
+#[test]
+fn stale_temp_dirs_of_stopped_servers_are_collected() {
+    assert_eq!(temp_dir_owner("metal-analyzer-42"), Some(42));
+    assert_eq!(temp_dir_owner("metal-analyzer-def-42"), Some(42));
+    assert_eq!(temp_dir_owner("metal-analyzer-artifacts"), None);
+    assert_eq!(temp_dir_owner("metal-analyzer-index-cache"), None);
+
+    let base = std::env::temp_dir().join(format!("metal-analyzer-gc-test-{}", std::process::id()));
+    // Above the largest pid Linux and macOS hand out.
+    let stale = base.join("metal-analyzer-4000000000");
+    let stale_def = base.join("metal-analyzer-def-4000000000");
+    let current = base.join(format!("metal-analyzer-{}", std::process::id()));
+    let artifacts = base.join("metal-analyzer-artifacts");
+    for dir in [&stale, &stale_def, &current, &artifacts] {
+        std::fs::create_dir_all(dir).unwrap();
+    }
+
+    collect_stale_temp_dirs(&base);
+
+    assert!(!stale.exists());
+    assert!(!stale_def.exists());
+    assert!(current.exists());
+    assert!(artifacts.exists());
+    std::fs::remove_dir_all(&base).ok();
+}
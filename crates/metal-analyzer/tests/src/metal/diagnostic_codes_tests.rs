@@ -0,0 +1,51 @@
+use super::*;
+
+fn code(message: &str) -> Option<&'static str> {
+    classify(message).map(|entry| entry.code)
+}
+
+#[test]
+fn classifies_address_space_mismatches() {
+    assert_eq!(
+        code(
+            "cannot initialize a variable of type 'device float *' with an lvalue of type 'constant float *' (changes address space of pointer)"
+        ),
+        Some("address-space")
+    );
+    assert_eq!(code("program scope variable must reside in constant address space"), Some("address-space"));
+    assert_eq!(code("threadgroup variables must be declared in kernel function scope"), Some("threadgroup-memory"));
+    assert_eq!(code("cannot use threadgroup memory in a vertex function"), Some("threadgroup-memory"));
+}
+
+#[test]
+fn classifies_texture_access_errors() {
+    assert_eq!(
+        code("no matching member function for call to 'write' in 'texture2d<float, access::read>'"),
+        Some("texture-access")
+    );
+    assert_eq!(code("cannot write to a read-only texture"), Some("texture-access"));
+}
+
+#[test]
+fn classifies_bindings_and_samplers() {
+    assert_eq!(
+        code("'buffer' attribute parameter is out of bounds: must be between 0 and 30"),
+        Some("resource-binding")
+    );
+    assert_eq!(code("invalid type for constexpr sampler initializer"), Some("sampler"));
+}
+
+#[test]
+fn leaves_other_messages_unclassified() {
+    assert_eq!(code("use of undeclared identifier 'foo'"), None);
+    assert_eq!(code("use of undeclared identifier 'sampler_state'"), None);
+    assert_eq!(code("expected ';' after expression"), None);
+}
+
+#[test]
+fn every_code_is_unique() {
+    let mut codes: Vec<_> = DIAGNOSTIC_CODES.iter().map(|entry| entry.code).collect();
+    codes.sort();
+    codes.dedup();
+    assert_eq!(codes.len(), DIAGNOSTIC_CODES.len());
+}
@@ -0,0 +1,52 @@
+use super::*;
+
+#[test]
+fn parses_insertion() {
+    let fix_it = parse_fix_it_line(r#"fix-it:"/tmp/shader.metal":{12:17-12:17}:";""#).unwrap();
+    assert_eq!(fix_it.file, "/tmp/shader.metal");
+    assert_eq!(fix_it.range, Range::new(Position::new(11, 16), Position::new(11, 16)));
+    assert_eq!(fix_it.replacement, ";");
+}
+
+#[test]
+fn unescapes_file_and_replacement() {
+    let fix_it = parse_fix_it_line(r#"fix-it:"/tmp/my \"dir\"/a.metal":{1:1-1:4}:"x\\y\n\303\251""#).unwrap();
+    assert_eq!(fix_it.file, "/tmp/my \"dir\"/a.metal");
+    assert_eq!(fix_it.replacement, "x\\y\né");
+}
+
+#[test]
+fn rejects_other_lines() {
+    assert!(parse_fix_it_line("shader.metal:1:1: error: oops").is_none());
+    assert!(parse_fix_it_line(r#"fix-it:"/tmp/a.metal":{1:1}:"x""#).is_none());
+}
+
+#[test]
+fn fixes_round_trip_through_diagnostic_data() {
+    let edit = FixIt {
+        file: "/tmp/a.metal".to_string(),
+        range: Range::new(Position::new(0, 0), Position::new(0, 0)),
+        replacement: ";".to_string(),
+    };
+    let mut diagnostic = Diagnostic::default();
+    attach_fix(
+        &mut diagnostic,
+        CompilerFix {
+            message: None,
+            edits: vec![edit.clone()],
+        },
+    );
+    attach_fix(
+        &mut diagnostic,
+        CompilerFix {
+            message: Some("add parentheses".to_string()),
+            edits: vec![edit],
+        },
+    );
+
+    let fixes = fixes(diagnostic.data.as_ref());
+    assert_eq!(fixes.len(), 2);
+    assert_eq!(fixes[0].message, None);
+    assert_eq!(fixes[1].message.as_deref(), Some("add parentheses"));
+    assert!(super::fixes(None).is_empty());
+}
@@ -0,0 +1,105 @@
+use super::*;
+
+const MODULE: &str = r#"; ModuleID = 'shaders.air'
+source_filename = "shaders.metal"
+target triple = "air64-apple-macosx14.0.0"
+
+%struct.Particle = type { <3 x float>, float }
+
+@_ZZ6reduceE4tile = internal addrspace(3) global [256 x float] undef, align 4
+@_ZZ6reduceE5count = internal addrspace(3) global i32 undef, align 4
+@lut = internal addrspace(2) constant [4 x float] zeroinitializer, align 4
+
+define void @reduce(float addrspace(1)* noalias %0, i32 %1) local_unnamed_addr #0 {
+  %3 = getelementptr inbounds [256 x float], [256 x float] addrspace(3)* @_ZZ6reduceE4tile, i64 0, i64 0
+  store float 0.000000e+00, float addrspace(3)* %3, align 4
+  tail call void @air.wg.barrier(i32 2, i32 1)
+  br label %4
+
+4:                                                ; preds = %2
+  %5 = call float @helper(float addrspace(3)* %3)
+  ret void
+}
+
+define internal float @helper(float addrspace(3)* %0) {
+  %2 = alloca [8 x float], align 4
+  %3 = load i32, i32 addrspace(3)* @_ZZ6reduceE5count, align 4
+  ret float 0.000000e+00
+}
+
+define <4 x float> @vertex_main(%struct.Particle addrspace(1)* %0) {
+  %2 = alloca %struct.Particle, align 16
+  %3 = load float, float addrspace(2)* getelementptr ([4 x float], [4 x float] addrspace(2)* @lut, i64 0, i64 0)
+  ret <4 x float> zeroinitializer
+}
+
+declare void @air.wg.barrier(i32, i32)
+
+!air.kernel = !{!0}
+!air.vertex = !{!3}
+
+!0 = !{void (float addrspace(1)*, i32)* @reduce, !1, !2}
+!1 = !{}
+!2 = !{}
+!3 = !{<4 x float> (%struct.Particle addrspace(1)*)* @vertex_main, !1, !2}
+"#;
+
+#[test]
+fn lists_entry_points_in_metadata_order() {
+    let stats = parse_air_module(MODULE);
+    let names: Vec<(&str, &str)> = stats.iter().map(|entry| (entry.name.as_str(), entry.stage.as_str())).collect();
+    assert_eq!(names, vec![("reduce", "kernel"), ("vertex_main", "vertex")]);
+}
+
+#[test]
+fn counts_callees_into_the_entry_point() {
+    let stats = parse_air_module(MODULE);
+    let reduce = &stats[0];
+    assert_eq!(reduce.instructions, 9);
+    assert_eq!(reduce.basic_blocks, 3);
+    assert_eq!(reduce.private_memory_bytes, 32);
+}
+
+#[test]
+fn sums_the_threadgroup_globals_reached() {
+    let stats = parse_air_module(MODULE);
+    assert_eq!(stats[0].threadgroup_memory_bytes, 256 * 4 + 4);
+    assert_eq!(stats[1].threadgroup_memory_bytes, 0);
+}
+
+#[test]
+fn sizes_named_structs_with_padded_vectors() {
+    let stats = parse_air_module(MODULE);
+    assert_eq!(stats[1].private_memory_bytes, 32);
+}
+
+#[test]
+fn sizes_types_like_the_data_layout() {
+    let named = HashMap::from([("pair".to_string(), "{ i8, i32 }".to_string())]);
+    assert_eq!(type_layout("[4 x <2 x half>]", &named), Some((16, 4)));
+    assert_eq!(type_layout("%pair", &named), Some((8, 4)));
+    assert_eq!(type_layout("<{ i8, i32 }>", &named), Some((5, 1)));
+    assert_eq!(type_layout("float addrspace(1)*", &named), Some((8, 8)));
+    assert_eq!(type_layout("%missing", &named), None);
+}
+
+#[test]
+fn a_module_without_entry_points_has_no_stats() {
+    assert!(parse_air_module("define void @f() {\n  ret void\n}\n").is_empty());
+}
+
+#[test]
+fn markdown_flags_stack_memory() {
+    let entry = KernelStats {
+        name: "reduce".to_string(),
+        stage: "kernel".to_string(),
+        instructions: 8,
+        basic_blocks: 3,
+        threadgroup_memory_bytes: 1028,
+        private_memory_bytes: 32,
+    };
+    let markdown = entry.to_markdown();
+    assert!(markdown.contains("8 instructions in 3 basic blocks"));
+    assert!(markdown.contains("Threadgroup memory: 1028 bytes"));
+    assert!(markdown.contains("Stack memory: 32 bytes (likely spilled"));
+}
@@ -0,0 +1,38 @@
+use super::*;
+
+fn flags(raw: &[&str]) -> Vec<String> {
+    raw.iter().map(|flag| (*flag).to_string()).collect()
+}
+
+#[test]
+fn parses_setting_values_and_std_names() {
+    assert_eq!(LanguageVersion::parse("3.1"), Some(LanguageVersion::new(3, 1)));
+    assert_eq!(LanguageVersion::parse("metal3.2"), Some(LanguageVersion::new(3, 2)));
+    assert_eq!(LanguageVersion::parse("ios-metal2.4"), Some(LanguageVersion::new(2, 4)));
+    assert_eq!(LanguageVersion::parse("macos-metal2.3"), Some(LanguageVersion::new(2, 3)));
+    assert_eq!(LanguageVersion::parse("auto"), None);
+    assert_eq!(LanguageVersion::parse("c++17"), None);
+    assert_eq!(LanguageVersion::parse("3"), None);
+}
+
+#[test]
+fn the_last_std_flag_wins() {
+    assert_eq!(
+        LanguageVersion::from_flags(&flags(&["-std=metal3.0", "-DFOO", "-std=metal3.1"])),
+        Some(LanguageVersion::new(3, 1))
+    );
+    assert_eq!(LanguageVersion::from_flags(&flags(&["-DFOO"])), None);
+}
+
+#[test]
+fn std_flags_name_the_platform_family_before_metal_3() {
+    assert_eq!(LanguageVersion::new(3, 1).std_flag(CompilerPlatform::Ios), "-std=metal3.1");
+    assert_eq!(LanguageVersion::new(2, 4).std_flag(CompilerPlatform::Macos), "-std=macos-metal2.4");
+    assert_eq!(LanguageVersion::new(2, 4).std_flag(CompilerPlatform::Ios), "-std=ios-metal2.4");
+}
+
+#[test]
+fn metal_version_macro_matches_the_compiler() {
+    assert_eq!(LanguageVersion::new(3, 1).metal_version_macro(), "310");
+    assert_eq!(LanguageVersion::new(2, 4).metal_version_macro(), "240");
+}
@@ -0,0 +1,22 @@
+use super::*;
+
+#[test]
+fn prelude_must_come_before_other_directives_and_code() {
+    assert!(includes_prelude_first("#include <metal_stdlib>\nusing namespace metal;\n"));
+    assert!(includes_prelude_first("// Copyright\n/* License\n   text */\n#pragma once\n#  include <metal_stdlib>\n"));
+
+    assert!(!includes_prelude_first("#define USE_HALF 1\n#include <metal_stdlib>\n"));
+    assert!(!includes_prelude_first("#include \"common.h\"\n#include <metal_stdlib>\n"));
+    assert!(!includes_prelude_first("/* a */ int x;\n#include <metal_stdlib>\n"));
+    assert!(!includes_prelude_first("#include <metal_math>\n"));
+    assert!(!includes_prelude_first(""));
+}
+
+#[test]
+fn flags_key_separates_flag_sets_and_toolchains() {
+    let macos = vec!["-D__METAL_MACOS__".to_string()];
+    let ios = vec!["-D__METAL_IOS__".to_string()];
+    assert_eq!(flags_key(&macos, Some("metal-32023")), flags_key(&macos, Some("metal-32023")));
+    assert_ne!(flags_key(&macos, Some("metal-32023")), flags_key(&ios, Some("metal-32023")));
+    assert_ne!(flags_key(&macos, Some("metal-32023")), flags_key(&macos, Some("metal-32024")));
+}
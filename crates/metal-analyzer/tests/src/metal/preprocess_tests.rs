@@ -0,0 +1,121 @@
+use super::*;
+
+const OUTPUT: &str = r#"# 1 "/p/Shaders/blur.metal"
+# 1 "<built-in>" 1
+# 1 "/p/Shaders/blur.metal" 2
+# 1 "/sdk/metal/include/metal_stdlib" 1
+namespace metal {
+float saturate(float x);
+}
+# 2 "/p/Shaders/blur.metal" 2
+# 1 "/p/Shaders/common.h" 1
+struct Params { float radius; };
+struct Unused { int x; };
+typedef float real;
+constant real kScale = 2.0;
+template <typename T> T twice(T v) { return v * kScale; }
+enum Mode { ModeFast, ModeSlow };
+# 3 "/p/Shaders/blur.metal" 2
+
+namespace detail {
+float weight(float r) { return twice(r); }
+}
+
+float unused_helper() { return 0; }
+
+kernel void blur(constant Params& params [[buffer(0)]], device float* out [[buffer(1)]]) {
+    out[0] = metal::saturate(detail::weight(params.radius)) + ModeFast;
+}
+"#;
+
+fn is_system(file: &str) -> bool {
+    file.starts_with("/sdk/")
+}
+
+#[test]
+fn without_files_drops_system_header_lines_and_keeps_the_map_aligned() {
+    let source = PreprocessedSource::parse(OUTPUT).without_files(is_system);
+    assert!(!source.text().contains("saturate(float x)"));
+    assert_eq!(source.text().lines().count(), source.origins().len());
+
+    let lines: Vec<&str> = source.text().lines().collect();
+    let kernel = lines.iter().position(|line| line.starts_with("kernel void blur")).unwrap();
+    assert_eq!(
+        source.origin(kernel),
+        Some(&LineOrigin {
+            file: "/p/Shaders/blur.metal".to_string(),
+            line: 10,
+        })
+    );
+}
+
+#[test]
+fn parse_maps_lines_to_their_origin() {
+    let source = PreprocessedSource::parse(OUTPUT);
+    assert!(!source.text().contains("# 1 "));
+
+    let lines: Vec<&str> = source.text().lines().collect();
+    let params = lines.iter().position(|line| line.starts_with("struct Params")).unwrap();
+    assert_eq!(
+        source.origin(params),
+        Some(&LineOrigin {
+            file: "/p/Shaders/common.h".to_string(),
+            line: 1,
+        })
+    );
+    let kernel = lines.iter().position(|line| line.starts_with("kernel void blur")).unwrap();
+    assert_eq!(
+        source.origin(kernel),
+        Some(&LineOrigin {
+            file: "/p/Shaders/blur.metal".to_string(),
+            line: 10,
+        })
+    );
+}
+
+#[test]
+fn parse_accepts_line_directives() {
+    let source = PreprocessedSource::parse("#line 7 \"/p/a.metal\"\nfloat x;\n");
+    assert_eq!(source.text(), "float x;\n");
+    assert_eq!(source.origin(0).map(|origin| origin.line), Some(7));
+}
+
+#[test]
+fn slice_keeps_transitive_dependencies_of_the_entry_point() {
+    let source = PreprocessedSource::parse(OUTPUT);
+    let slice = source.slice_for_entry_point("blur", is_system).unwrap();
+
+    for expected in [
+        "struct Params { float radius; };",
+        "typedef float real;",
+        "constant real kScale = 2.0;",
+        "template <typename T> T twice(T v) { return v * kScale; }",
+        "enum Mode { ModeFast, ModeSlow };",
+        "float weight(float r) { return twice(r); }",
+        "kernel void blur(",
+    ] {
+        assert!(slice.contains(expected), "missing {expected:?} in:\n{slice}");
+    }
+    assert!(!slice.contains("Unused"));
+    assert!(!slice.contains("unused_helper"));
+    assert!(!slice.contains("float saturate"), "system declarations stay out of the slice");
+}
+
+#[test]
+fn slice_marks_origins_and_namespaces() {
+    let source = PreprocessedSource::parse(OUTPUT);
+    let slice = source.slice_for_entry_point("blur", is_system).unwrap();
+
+    assert!(slice.starts_with("# 1 \"/p/Shaders/common.h\"\nstruct Params"));
+    assert!(slice.contains(
+        "namespace detail {\n# 5 \"/p/Shaders/blur.metal\"\nfloat weight(float r) { return twice(r); }\n}\n"
+    ));
+    assert!(slice.contains("# 10 \"/p/Shaders/blur.metal\"\nkernel void blur("));
+}
+
+#[test]
+fn slice_without_matching_entry_point_is_none() {
+    let source = PreprocessedSource::parse(OUTPUT);
+    assert!(source.slice_for_entry_point("missing", is_system).is_none());
+    assert!(source.slice_for_entry_point("saturate", is_system).is_none());
+}
@@ -0,0 +1,71 @@
+use super::*;
+
+const MODULE: &str = r#"; ModuleID = 'blur.air'
+source_filename = "/work/shaders/blur.metal"
+
+define void @blur(float addrspace(1)* %0) local_unnamed_addr #0 !dbg !8 {
+  %2 = load float, float addrspace(1)* %0, align 4, !dbg !12
+  %3 = call float @weight(float %2), !dbg !13
+  store float %3, float addrspace(1)* %0, align 4, !dbg !14
+  ret void, !dbg !15
+}
+
+define internal float @weight(float %0) !dbg !20 {
+  %2 = call float @air.fast_exp.f32(float %0), !dbg !21
+  ret float %2, !dbg !22
+}
+
+declare float @air.fast_exp.f32(float)
+
+!0 = distinct !DICompileUnit(language: DW_LANG_C_plus_plus_14, file: !1, producer: "Apple metal version 32023.35 (metalfe-32023.35)", emissionKind: LineTablesOnly)
+!1 = !DIFile(filename: "/work/shaders/blur.metal", directory: "/work")
+!2 = !DIFile(filename: "include/math.h", directory: "/work/shaders")
+!8 = distinct !DISubprogram(name: "blur", scope: !1, file: !1, line: 4, type: !9, scopeLine: 4, unit: !0)
+!12 = !DILocation(line: 5, column: 15, scope: !8)
+!13 = !DILocation(line: 6, column: 11, scope: !16)
+!14 = !DILocation(line: 0, scope: !8)
+!15 = !DILocation(line: 8, column: 1, scope: !8)
+!16 = distinct !DILexicalBlock(scope: !8, file: !1, line: 5, column: 3)
+!20 = distinct !DISubprogram(name: "weight", scope: !2, file: !2, line: 2, type: !9, scopeLine: 2, unit: !0)
+!21 = !DILocation(line: 3, column: 10, scope: !20)
+!22 = !DILocation(line: 3, column: 3, scope: !20)
+"#;
+
+#[test]
+fn lists_defined_functions_with_their_ir_lines() {
+    let functions = ir_functions(MODULE, Path::new("/work/shaders/blur.metal"));
+    let spans: Vec<(&str, u32, u32)> =
+        functions.iter().map(|function| (function.name.as_str(), function.start_line, function.end_line)).collect();
+    assert_eq!(spans, vec![("blur", 3, 8), ("weight", 10, 13)]);
+}
+
+#[test]
+fn maps_instructions_to_lines_of_the_source_file() {
+    let functions = ir_functions(MODULE, Path::new("/work/shaders/blur.metal"));
+    let blur = &functions[0];
+    assert_eq!(blur.source_line, Some(3));
+    // Line 0 marks compiler-generated code and is left out.
+    assert_eq!(blur.lines, vec![(4, 4), (5, 5), (7, 7)]);
+}
+
+#[test]
+fn leaves_out_lines_of_other_files() {
+    let functions = ir_functions(MODULE, Path::new("/work/shaders/blur.metal"));
+    let weight = &functions[1];
+    assert_eq!(weight.source_line, None);
+    assert!(weight.lines.is_empty());
+
+    let header = ir_functions(MODULE, Path::new("/work/shaders/include/math.h"));
+    assert_eq!(header[1].source_line, Some(1));
+    assert_eq!(header[1].lines, vec![(11, 2), (12, 2)]);
+}
+
+#[test]
+fn quoted_names_and_commas_in_strings_are_handled() {
+    let ir = "define void @\"a b\"() !dbg !1 {\n  ret void\n}\n\
+              !1 = distinct !DISubprogram(name: \"a, b\", file: !2, line: 7)\n\
+              !2 = !DIFile(filename: \"x.metal\", directory: \"/d\")\n";
+    let functions = ir_functions(ir, Path::new("/d/x.metal"));
+    assert_eq!(functions[0].name, "a b");
+    assert_eq!(functions[0].source_line, Some(6));
+}
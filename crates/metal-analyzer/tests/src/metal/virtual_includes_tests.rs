@@ -0,0 +1,76 @@
+use std::{
+    fs,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::*;
+use crate::server::header_owners::resolve_include_path;
+
+/// Create a unique temporary workspace root for each test.
+fn test_root() -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("virtual_includes_test_{}_{id}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+/// A workspace whose codegen output in `build/codegen` includes itself as
+/// `generated/...`.
+fn codegen_workspace() -> (PathBuf, PathBuf) {
+    let root = test_root();
+    let codegen = root.join("build/codegen");
+    fs::create_dir_all(&codegen).unwrap();
+    fs::write(codegen.join("matmul.h"), "#include \"generated/params.h\"\n").unwrap();
+    fs::write(codegen.join("params.h"), "struct Params { uint rows; };\n").unwrap();
+    (root, codegen)
+}
+
+#[test]
+fn mapped_prefixes_resolve_to_the_real_header() {
+    let (root, codegen) = codegen_workspace();
+    let roots = BTreeMap::from([("generated".to_string(), "build/codegen".to_string())]);
+    let folder = root.join("links");
+    link_roots(&folder, &roots, Some(&root)).unwrap();
+
+    let include_paths = vec![folder.display().to_string()];
+    let owner = codegen.join("matmul.h");
+    assert_eq!(
+        resolve_include_path(&owner, "generated/params.h", false, &include_paths),
+        Some(codegen.join("params.h"))
+    );
+    assert_eq!(resolve_include_path(&owner, "generated/missing.h", false, &include_paths), None);
+}
+
+#[test]
+fn nested_prefixes_and_absolute_directories_are_linked() {
+    let (root, codegen) = codegen_workspace();
+    let roots = BTreeMap::from([
+        ("gen/kernels".to_string(), codegen.display().to_string()),
+        ("../escape".to_string(), "build".to_string()),
+    ]);
+    let folder = root.join("links");
+    link_roots(&folder, &roots, None).unwrap();
+
+    assert!(folder.join("gen/kernels/matmul.h").exists());
+    assert!(!root.join("escape").exists(), "prefixes cannot leave the link folder");
+}
+
+#[test]
+fn link_folders_are_rebuilt_when_the_mapping_changes() {
+    let (root, _) = codegen_workspace();
+    let dir = root.join("virtual-includes");
+    let virtual_roots = VirtualIncludeRoots::default();
+    assert_eq!(virtual_roots.get_or_link(&dir, Some(&root)), None, "nothing is mapped");
+
+    virtual_roots.set(BTreeMap::from([("generated".to_string(), "build/codegen".to_string())]));
+    let first = virtual_roots.get_or_link(&dir, Some(&root)).unwrap();
+    assert_eq!(virtual_roots.get_or_link(&dir, Some(&root)), Some(first.clone()));
+    assert!(first.join("generated/params.h").exists());
+
+    virtual_roots.set(BTreeMap::from([("codegen".to_string(), "build/codegen".to_string())]));
+    let second = virtual_roots.get_or_link(&dir, Some(&root)).unwrap();
+    assert_ne!(first, second);
+    assert!(second.join("codegen/params.h").exists());
+}
@@ -0,0 +1,31 @@
+use super::*;
+
+#[test]
+fn report_summarizes_latencies_into_buckets_and_percentiles() {
+    for ms in [3, 4, 4, 40, 4_000] {
+        record("test.latency", Duration::from_millis(ms));
+    }
+    record("test.slow", Duration::from_secs(60));
+    count("test.counter");
+    count("test.counter");
+
+    let report = report(false);
+    let latency = report.latencies.iter().find(|latency| latency.name == "test.latency").expect("recorded");
+    assert_eq!(latency.count, 5);
+    assert_eq!(latency.p50_ms, Some(5));
+    assert_eq!(latency.p90_ms, Some(5_000));
+    assert_eq!(latency.max_ms, 4_000.0);
+    let bucket =
+        |under_ms| latency.buckets.iter().find(|bucket| bucket.under_ms == under_ms).map(|bucket| bucket.count);
+    assert_eq!(bucket(Some(5)), Some(3));
+    assert_eq!(bucket(Some(50)), Some(1));
+
+    let slow = report.latencies.iter().find(|latency| latency.name == "test.slow").expect("recorded");
+    assert_eq!(slow.p50_ms, None, "past the last bound");
+    assert_eq!(report.counters.get("test.counter"), Some(&2));
+}
+
+#[test]
+fn percentile_bound_is_none_without_samples() {
+    assert_eq!(percentile_bound(&[0; BUCKET_BOUNDS_MS.len() + 1], 0.5), None);
+}
@@ -1,4 +1,8 @@
-use super::prefixed_progress_title;
+use std::sync::{Arc, atomic::Ordering};
+
+use tower_lsp::lsp_types::NumberOrString;
+
+use super::{Cancellation, ProgressCancellations, prefixed_progress_title};
 
 #[test]
 fn progress_title_adds_prefix_when_missing() {
@@ -9,3 +13,32 @@ fn progress_title_adds_prefix_when_missing() {
 fn progress_title_preserves_existing_prefix() {
     assert_eq!(prefixed_progress_title("metal-analyzer: Indexing"), "metal-analyzer: Indexing".to_string());
 }
+
+#[test]
+fn cancelling_flags_only_the_matching_session() {
+    let registry = Arc::new(ProgressCancellations::new());
+    let (first, first_flag) = registry.register("Indexing");
+    let (second, second_flag) = registry.register("Indexing");
+    assert_ne!(first, second);
+
+    assert!(registry.cancel(&second));
+    assert!(!first_flag.load(Ordering::Relaxed));
+    assert!(second_flag.load(Ordering::Relaxed));
+    assert!(!registry.cancel(&NumberOrString::String("metal-analyzer: Diagnostics".to_string())));
+}
+
+#[test]
+fn ended_sessions_leave_the_registry() {
+    let registry = Arc::new(ProgressCancellations::new());
+    let (token, flag) = registry.register("Indexing");
+    let cancellation = Cancellation {
+        registry: registry.clone(),
+        token: token.clone(),
+        flag,
+    };
+    assert_eq!(registry.len(), 1);
+
+    drop(cancellation);
+    assert!(registry.is_empty());
+    assert!(!registry.cancel(&token));
+}
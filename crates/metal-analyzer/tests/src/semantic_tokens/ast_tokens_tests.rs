@@ -0,0 +1,79 @@
+use super::*;
+use crate::definition::ref_site::RefSiteLocation;
+
+const PATH: &str = "/ws/blur.metal";
+
+fn clamp_ref(
+    spelling: (u32, u32),
+    expansion: Option<(&str, u32, u32)>,
+) -> RefSite {
+    RefSite {
+        file: PATH.into(),
+        line: spelling.0,
+        col: spelling.1,
+        tok_len: 5,
+        target_id: "clamp".to_string(),
+        target_name: "clamp".into(),
+        target_kind: "FunctionDecl".into(),
+        expansion: expansion.map(|(file, line, col)| RefSiteLocation {
+            file: file.into(),
+            line,
+            col,
+            tok_len: 7,
+        }),
+        spelling: None,
+    }
+}
+
+fn summary(tokens: &[RawToken]) -> Vec<(u32, u32, SemanticTokenType, u32)> {
+    let mut summary: Vec<_> =
+        tokens.iter().map(|token| (token.line, token.col, token.token_type.clone(), token.modifiers)).collect();
+    summary.sort_by_key(|(line, col, ..)| (*line, *col));
+    summary
+}
+
+#[test]
+fn macro_body_tokens_and_invocations_are_marked() {
+    // `#define CLAMP01(x) clamp(x, 0.0, 1.0)` on line 1, used on line 4.
+    let index = AstIndex::from_parts(Vec::new(), vec![clamp_ref((1, 20), Some((PATH, 4, 12)))], Vec::new());
+    assert_eq!(
+        summary(&tokens_from_ast_index(&index, PATH, "")),
+        vec![
+            (0, 19, SemanticTokenType::FUNCTION, MACRO_EXPANSION_BIT),
+            (3, 11, SemanticTokenType::MACRO, MACRO_EXPANSION_BIT),
+        ]
+    );
+}
+
+#[test]
+fn macro_arguments_and_plain_code_are_unmarked() {
+    let index = AstIndex::from_parts(
+        Vec::new(),
+        vec![clamp_ref((4, 20), Some((PATH, 4, 12))), clamp_ref((6, 5), None)],
+        Vec::new(),
+    );
+    assert_eq!(
+        summary(&tokens_from_ast_index(&index, PATH, "")),
+        vec![(3, 19, SemanticTokenType::FUNCTION, 0), (5, 4, SemanticTokenType::FUNCTION, 0)]
+    );
+}
+
+#[test]
+fn invocations_of_header_macros_are_marked() {
+    let mut from_header = clamp_ref((2, 20), Some((PATH, 9, 3)));
+    from_header.file = "/ws/common.h".into();
+    let index = AstIndex::from_parts(Vec::new(), vec![from_header], Vec::new());
+    assert_eq!(
+        summary(&tokens_from_ast_index(&index, PATH, "")),
+        vec![(8, 2, SemanticTokenType::MACRO, MACRO_EXPANSION_BIT)]
+    );
+}
+
+#[test]
+fn columns_after_non_ascii_text_count_code_units() {
+    // Clang reports `clamp` at column 14, counting the two bytes of `é`.
+    let source = "/* é */ x = clamp(y);";
+    let index = AstIndex::from_parts(Vec::new(), vec![clamp_ref((1, 14), None)], Vec::new());
+    let tokens = tokens_from_ast_index(&index, PATH, source);
+    assert_eq!((tokens[0].col, tokens[0].length), (12, 5));
+}
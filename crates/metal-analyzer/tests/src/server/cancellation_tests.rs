@@ -0,0 +1,13 @@
+use super::*;
+
+#[test]
+fn dropping_the_request_cancels_its_tokens() {
+    let cancellation = RequestCancellation::new();
+    let token = cancellation.token();
+    let copy = token.clone();
+    assert!(!token.is_cancelled());
+
+    drop(cancellation);
+    assert!(token.is_cancelled());
+    assert!(copy.is_cancelled());
+}
@@ -0,0 +1,66 @@
+use std::{
+    fs,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::*;
+
+/// Create a unique temporary workspace root for each test.
+fn test_root() -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("check_test_{}_{id}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+fn diagnostic(
+    file: Option<&str>,
+    severity: DiagnosticSeverity,
+) -> MetalDiagnostic {
+    MetalDiagnostic {
+        file: file.map(str::to_string),
+        line: 4,
+        column: 0,
+        end: None,
+        severity,
+        message: "use of undeclared identifier 'x'".to_string(),
+        related_information: Vec::new(),
+        fix_its: Vec::new(),
+    }
+}
+
+#[test]
+fn watched_sources_include_headers() {
+    let root = test_root();
+    for file in ["Shaders/blur.metal", "Shaders/common.h", "Shaders/notes.txt", "Vendor/lib.h"] {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    let mut settings = ServerSettings::default();
+    settings.indexing.exclude_paths = vec!["Vendor".to_string()];
+    let mut watched = watched_sources(std::slice::from_ref(&root), &settings);
+    watched.sort();
+    assert_eq!(watched, vec![root.join("Shaders/blur.metal"), root.join("Shaders/common.h")]);
+}
+
+#[test]
+fn renders_one_based_positions() {
+    let diag = diagnostic(Some("/p/common.h"), DiagnosticSeverity::ERROR);
+    assert_eq!(
+        render_diagnostic(&diag, Path::new("/p/blur.metal")),
+        "/p/common.h:5:1: error: use of undeclared identifier 'x'"
+    );
+
+    let note = diagnostic(None, DiagnosticSeverity::INFORMATION);
+    assert!(render_diagnostic(&note, Path::new("/p/blur.metal")).starts_with("/p/blur.metal:5:1: note: "));
+}
+
+#[test]
+fn only_errors_fail_the_check() {
+    assert!(!has_errors(&[diagnostic(None, DiagnosticSeverity::WARNING)]));
+    assert!(has_errors(&[diagnostic(None, DiagnosticSeverity::WARNING), diagnostic(None, DiagnosticSeverity::ERROR)]));
+}
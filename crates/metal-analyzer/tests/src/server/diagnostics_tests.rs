@@ -1,4 +1,7 @@
+use tower_lsp::lsp_types::{Position, Range};
+
 use super::*;
+use crate::metal::fix_its::{FixIt, fixes};
 
 #[test]
 fn diagnostics_generation_drops_stale_results() {
@@ -40,15 +43,21 @@ fn filter_target_diagnostics_drops_non_target_in_strict_mode() {
             file: Some("/tmp/header.h".to_string()),
             line: 1,
             column: 1,
+            end: None,
             severity: DiagnosticSeverity::ERROR,
             message: "header error".to_string(),
+            related_information: Vec::new(),
+            fix_its: Vec::new(),
         },
         MetalDiagnostic {
             file: Some("/tmp/owner.metal".to_string()),
             line: 2,
             column: 1,
+            end: None,
             severity: DiagnosticSeverity::ERROR,
             message: "owner error".to_string(),
+            related_information: Vec::new(),
+            fix_its: Vec::new(),
         },
     ];
 
@@ -64,8 +73,11 @@ fn filter_target_diagnostics_keeps_unknown_file_in_non_strict_mode() {
         file: None,
         line: 0,
         column: 0,
+        end: None,
         severity: DiagnosticSeverity::ERROR,
         message: "compiler failed".to_string(),
+        related_information: Vec::new(),
+        fix_its: Vec::new(),
     }];
 
     let filtered = filter_target_diagnostics(diagnostics, Some(target), false);
@@ -80,8 +92,11 @@ fn filter_target_diagnostics_drops_ambiguous_relative_header_path() {
         file: Some("utils.h".to_string()),
         line: 1,
         column: 1,
+        end: None,
         severity: DiagnosticSeverity::ERROR,
         message: "unknown type name 'METAL_FUNC'".to_string(),
+        related_information: Vec::new(),
+        fix_its: Vec::new(),
     }];
 
     let filtered = filter_target_diagnostics(diagnostics, Some(target), true);
@@ -101,15 +116,21 @@ fn filter_attaches_cross_file_note_as_related_info() {
             file: Some("/tmp/gemv.metal".to_string()),
             line: 13,
             column: 8,
+            end: None,
             severity: DiagnosticSeverity::WARNING,
             message: "warning from primary file".to_string(),
+            related_information: Vec::new(),
+            fix_its: Vec::new(),
         },
         MetalDiagnostic {
             file: Some("/tmp/defines.h".to_string()),
             line: 3,
             column: 8,
+            end: None,
             severity: DiagnosticSeverity::INFORMATION,
             message: "related note".to_string(),
+            related_information: Vec::new(),
+            fix_its: Vec::new(),
         },
     ];
 
@@ -123,6 +144,45 @@ fn filter_attaches_cross_file_note_as_related_info() {
     assert!(related[0].location.uri.path().ends_with("defines.h"), "related info should point to the note's file");
 }
 
+#[test]
+fn filter_carries_note_fix_its_to_the_primary() {
+    let target = std::path::Path::new("/tmp/gemv.metal");
+    let parenthesize = |column: u32, text: &str| FixIt {
+        file: "/tmp/gemv.metal".to_string(),
+        range: Range::new(Position::new(4, column), Position::new(4, column)),
+        replacement: text.to_string(),
+    };
+    let diagnostics = vec![
+        MetalDiagnostic {
+            file: Some("/tmp/gemv.metal".to_string()),
+            line: 4,
+            column: 10,
+            end: None,
+            severity: DiagnosticSeverity::WARNING,
+            message: "using the result of an assignment as a condition without parentheses".to_string(),
+            related_information: Vec::new(),
+            fix_its: Vec::new(),
+        },
+        MetalDiagnostic {
+            file: Some("/tmp/gemv.metal".to_string()),
+            line: 4,
+            column: 10,
+            end: None,
+            severity: DiagnosticSeverity::INFORMATION,
+            message: "place parentheses around the assignment to silence this warning".to_string(),
+            related_information: Vec::new(),
+            fix_its: vec![parenthesize(8, "("), parenthesize(13, ")")],
+        },
+    ];
+
+    let filtered = filter_target_diagnostics(diagnostics, Some(target), false);
+    assert_eq!(filtered.len(), 1);
+    let fixes = fixes(filtered[0].data.as_ref());
+    assert_eq!(fixes.len(), 1);
+    assert_eq!(fixes[0].message.as_deref(), Some("place parentheses around the assignment to silence this warning"));
+    assert_eq!(fixes[0].edits, vec![parenthesize(8, "("), parenthesize(13, ")")]);
+}
+
 #[test]
 fn filter_suppresses_macro_redefinition_warning_and_following_note() {
     let target = std::path::Path::new("/tmp/gemv.metal");
@@ -131,15 +191,21 @@ fn filter_suppresses_macro_redefinition_warning_and_following_note() {
             file: Some("/tmp/gemv.metal".to_string()),
             line: 13,
             column: 8,
+            end: None,
             severity: DiagnosticSeverity::WARNING,
             message: "'MTL_CONST' macro redefined [-Wmacro-redefined]".to_string(),
+            related_information: Vec::new(),
+            fix_its: Vec::new(),
         },
         MetalDiagnostic {
             file: Some("/tmp/defines.h".to_string()),
             line: 3,
             column: 8,
+            end: None,
             severity: DiagnosticSeverity::INFORMATION,
             message: "previous definition is here".to_string(),
+            related_information: Vec::new(),
+            fix_its: Vec::new(),
         },
     ];
 
@@ -154,8 +220,11 @@ fn filter_drops_orphan_note_without_primary() {
         file: Some("/tmp/other.h".to_string()),
         line: 5,
         column: 1,
+        end: None,
         severity: DiagnosticSeverity::INFORMATION,
         message: "expanded from macro".to_string(),
+        related_information: Vec::new(),
+        fix_its: Vec::new(),
     }];
 
     let filtered = filter_target_diagnostics(diagnostics, Some(target), false);
@@ -170,15 +239,21 @@ fn filter_keeps_primary_when_note_has_relative_path() {
             file: Some("/tmp/shader.metal".to_string()),
             line: 10,
             column: 1,
+            end: None,
             severity: DiagnosticSeverity::WARNING,
             message: "some warning".to_string(),
+            related_information: Vec::new(),
+            fix_its: Vec::new(),
         },
         MetalDiagnostic {
             file: Some("relative.h".to_string()),
             line: 1,
             column: 1,
+            end: None,
             severity: DiagnosticSeverity::INFORMATION,
             message: "note about it".to_string(),
+            related_information: Vec::new(),
+            fix_its: Vec::new(),
         },
     ];
 
@@ -190,28 +265,28 @@ fn filter_keeps_primary_when_note_has_relative_path() {
 }
 
 #[test]
-fn build_workspace_scan_exclude_prefixes_expands_relative_paths_per_workspace_root() {
-    let workspace_roots = vec![PathBuf::from("/tmp/ws-a"), PathBuf::from("/tmp/ws-b")];
-    let exclude_paths = vec!["external/vendor-shaders".to_string(), "/opt/generated".to_string()];
-
-    let prefixes = build_workspace_scan_exclude_prefixes(&workspace_roots, &exclude_paths);
-    assert!(
-        prefixes.contains(&PathBuf::from("/tmp/ws-a/external/vendor-shaders")),
-        "relative excludes should expand under the first workspace root"
-    );
-    assert!(
-        prefixes.contains(&PathBuf::from("/tmp/ws-b/external/vendor-shaders")),
-        "relative excludes should expand under the second workspace root"
-    );
-    assert!(
-        prefixes.contains(&PathBuf::from("/opt/generated")),
-        "absolute excludes should be kept as absolute prefixes"
-    );
-}
+fn workspace_scans_skip_gitignored_files() {
+    let root = std::env::temp_dir().join(format!("discover_gitignore_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("DerivedShaders")).unwrap();
+    std::fs::write(root.join(".gitignore"), "DerivedShaders/\n*.gen.metal\n").unwrap();
+    std::fs::write(root.join("main.metal"), "").unwrap();
+    std::fs::write(root.join("blur.gen.metal"), "").unwrap();
+    std::fs::write(root.join("DerivedShaders/copy.metal"), "").unwrap();
+    let roots = vec![root.clone()];
+    let is_metal = |path: &Path| path.extension().is_some_and(|ext| ext == "metal");
 
-#[test]
-fn is_path_excluded_matches_prefix_descendants() {
-    let excluded = vec![PathBuf::from("/tmp/ws/external/vendor-shaders")];
-    assert!(is_path_excluded(Path::new("/tmp/ws/external/vendor-shaders/shaders/kernel.metal"), &excluded));
-    assert!(!is_path_excluded(Path::new("/tmp/ws/crates/app/kernel.metal"), &excluded));
+    let names = |files: Vec<PathBuf>| {
+        let mut names: Vec<String> =
+            files.iter().map(|path| path.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        names.sort();
+        names
+    };
+    let exclusions = PathExclusions::default();
+    assert_eq!(names(discover_workspace_files(&roots, u64::MAX, &exclusions, true, is_metal)), ["main.metal"]);
+    assert_eq!(
+        names(discover_workspace_files(&roots, u64::MAX, &exclusions, false, is_metal)),
+        ["blur.gen.metal", "copy.metal", "main.metal"]
+    );
+    let _ = std::fs::remove_dir_all(&root);
 }
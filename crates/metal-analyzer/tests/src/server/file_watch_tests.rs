@@ -0,0 +1,99 @@
+use std::{
+    fs,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use notify::event::{CreateKind, ModifyKind, RemoveKind};
+
+use super::*;
+
+/// Create a unique temporary directory for each test.
+fn test_root() -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("file_watch_test_{}_{id}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn watched_sources_are_metal_files_and_headers() {
+    assert!(is_watched_source(Path::new("/p/Shaders/blur.metal")));
+    assert!(is_watched_source(Path::new("/p/Shaders/common.h")));
+    assert!(is_watched_source(Path::new("/p/Shaders/math.hpp")));
+    assert!(!is_watched_source(Path::new("/p/Shaders/notes.txt")));
+    assert!(!is_watched_source(Path::new("/p/Shaders")));
+}
+
+#[test]
+fn coalesce_keeps_latest_change_per_file() {
+    let changes = coalesce_changes([
+        (PathBuf::from("/p/a.metal"), FileChangeType::CHANGED),
+        (PathBuf::from("/p/b.h"), FileChangeType::CHANGED),
+        (PathBuf::from("/p/a.metal"), FileChangeType::DELETED),
+        (PathBuf::from("/p/README.md"), FileChangeType::CHANGED),
+    ]);
+    assert_eq!(
+        changes,
+        vec![
+            (PathBuf::from("/p/a.metal"), FileChangeType::DELETED),
+            (PathBuf::from("/p/b.h"), FileChangeType::CHANGED),
+        ]
+    );
+}
+
+#[test]
+fn coalesce_treats_created_then_changed_as_created() {
+    let changes = coalesce_changes([
+        (PathBuf::from("/p/new.metal"), FileChangeType::CREATED),
+        (PathBuf::from("/p/new.metal"), FileChangeType::CHANGED),
+    ]);
+    assert_eq!(changes, vec![(PathBuf::from("/p/new.metal"), FileChangeType::CREATED)]);
+}
+
+#[test]
+fn notify_events_map_to_change_types() {
+    let root = test_root();
+    let existing = root.join("kernel.metal");
+    fs::write(&existing, "kernel void k() {}").unwrap();
+    let missing = root.join("gone.metal");
+
+    let created = notify::Event::new(EventKind::Create(CreateKind::File)).add_path(existing.clone());
+    assert_eq!(changes_from_notify_event(&created), vec![(existing.clone(), FileChangeType::CREATED)]);
+
+    let modified = notify::Event::new(EventKind::Modify(ModifyKind::Any)).add_path(existing.clone());
+    assert_eq!(changes_from_notify_event(&modified), vec![(existing.clone(), FileChangeType::CHANGED)]);
+
+    let removed = notify::Event::new(EventKind::Remove(RemoveKind::File)).add_path(missing.clone());
+    assert_eq!(changes_from_notify_event(&removed), vec![(missing.clone(), FileChangeType::DELETED)]);
+
+    // A rename reports both sides; the old path no longer exists.
+    let renamed =
+        notify::Event::new(EventKind::Modify(ModifyKind::Any)).add_path(missing.clone()).add_path(existing.clone());
+    assert_eq!(
+        changes_from_notify_event(&renamed),
+        vec![(missing, FileChangeType::DELETED), (existing.clone(), FileChangeType::CHANGED)]
+    );
+
+    let accessed = notify::Event::new(EventKind::Access(notify::event::AccessKind::Any)).add_path(existing);
+    assert!(changes_from_notify_event(&accessed).is_empty());
+}
+
+#[test]
+fn registration_watches_sources_and_headers() {
+    let registration = watched_files_registration();
+    assert_eq!(registration.id, WATCHED_FILES_REGISTRATION_ID);
+    assert_eq!(registration.method, "workspace/didChangeWatchedFiles");
+    let options: DidChangeWatchedFilesRegistrationOptions =
+        serde_json::from_value(registration.register_options.unwrap()).unwrap();
+    let globs: Vec<_> = options
+        .watchers
+        .into_iter()
+        .map(|watcher| match watcher.glob_pattern {
+            GlobPattern::String(glob) => glob,
+            GlobPattern::Relative(_) => panic!("expected a plain glob"),
+        })
+        .collect();
+    assert_eq!(globs, vec!["**/*.metal", "**/*.{h,hh,hpp,hxx}"]);
+}
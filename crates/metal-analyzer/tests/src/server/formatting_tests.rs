@@ -4,7 +4,7 @@ use super::*;
 
 #[test]
 fn clang_format_args_enforces_project_config_file() {
-    let args = clang_format_args(&Vec::new(), "/tmp/shader.metal".to_string());
+    let args = clang_format_args(&Vec::new(), "/tmp/shader.metal".to_string(), "none");
     assert_eq!(
         args,
         vec![
@@ -20,10 +20,41 @@ fn clang_format_args_enforces_project_config_file() {
 
 #[test]
 fn clang_format_args_preserves_custom_arguments() {
-    let args = clang_format_args(&vec!["--sort-includes=false".to_string()], "/tmp/shader.metal".to_string());
+    let args = clang_format_args(&["--sort-includes=false".to_string()], "/tmp/shader.metal".to_string(), "metal");
     assert_eq!(args[0], "--sort-includes=false".to_string(), "custom args should be preserved as prefix");
 }
 
+#[test]
+fn clang_format_args_use_fallback_style_without_clang_format_file() {
+    let dir = std::env::temp_dir().join(format!("metal-analyzer-fallback-style-{}", std::process::id()));
+    let nested = dir.join("shaders");
+    std::fs::create_dir_all(&nested).expect("create temp dir");
+    let source = nested.join("shader.metal").display().to_string();
+
+    let args = clang_format_args(&[], source.clone(), "metal");
+    let style = &args[args.iter().position(|arg| arg == "--style").expect("--style") + 1];
+    assert!(style.starts_with("{BasedOnStyle: LLVM, "), "bundled style expected, got {style}");
+    assert!(style.contains("PointerAlignment: Left"));
+
+    std::fs::write(dir.join(".clang-format"), "BasedOnStyle: Google\n").expect("write .clang-format");
+    let args = clang_format_args(&[], source, "metal");
+    assert_eq!(args[args.iter().position(|arg| arg == "--style").expect("--style") + 1], "file");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn fallback_style_overrides_bundled_keys_inline() {
+    assert_eq!(resolve_fallback_style("none"), None);
+    assert_eq!(resolve_fallback_style("Google").as_deref(), Some("Google"));
+
+    let style = resolve_fallback_style("{ColumnLimit: 100, BraceWrapping: {AfterFunction: true, AfterStruct: true}}")
+        .expect("inline override");
+    assert!(style.contains("ColumnLimit: 100, "), "override replaces the bundled value in place: {style}");
+    assert!(!style.contains("ColumnLimit: 120"));
+    assert!(style.ends_with(", BraceWrapping: {AfterFunction: true, AfterStruct: true}}"), "{style}");
+}
+
 #[test]
 fn full_document_range_handles_multiline_text() {
     let uri = Url::parse("file:///tmp/shader.metal").expect("valid uri");
@@ -32,3 +63,38 @@ fn full_document_range_handles_multiline_text() {
     assert_eq!(range.start, Position::new(0, 0));
     assert_eq!(range.end, Position::new(2, 0));
 }
+
+#[tokio::test]
+async fn internal_engine_formats_without_clang_format() {
+    let uri = Url::parse("file:///tmp/metal-analyzer-no-such-dir/shader.metal").expect("valid uri");
+    let document = Document::new(uri, "kernel void k()\n{\nreturn;\n}\n".to_string(), 1);
+    let settings = FormattingSettings {
+        engine: FormattingEngine::Internal,
+        command: "metal-analyzer-missing-formatter".to_string(),
+        ..FormattingSettings::default()
+    };
+    let options = FormattingOptions {
+        tab_size: 2,
+        insert_spaces: true,
+        ..FormattingOptions::default()
+    };
+
+    let edit = format_document(&document, &options, &settings).await.expect("internal formatter never fails");
+    assert_eq!(edit.expect("document changes").new_text, "kernel void k() {\n  return;\n}\n");
+}
+
+#[tokio::test]
+async fn clang_format_engine_reports_missing_command() {
+    let uri = Url::parse("file:///tmp/shader.metal").expect("valid uri");
+    let document = Document::new(uri, "kernel void k() {}\n".to_string(), 1);
+    let settings = FormattingSettings {
+        engine: FormattingEngine::ClangFormat,
+        command: "metal-analyzer-missing-formatter".to_string(),
+        ..FormattingSettings::default()
+    };
+
+    let result = format_document(&document, &FormattingOptions::default(), &settings).await;
+    assert!(
+        matches!(result, Err(FormattingError::CommandNotFound(command)) if command == "metal-analyzer-missing-formatter")
+    );
+}
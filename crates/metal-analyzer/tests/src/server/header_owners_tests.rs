@@ -28,3 +28,114 @@ fn update_owner_links_replaces_previous_headers() {
     assert!(headers_to_owners.get(&h2).is_some());
     assert_eq!(owners_to_headers.get(&owner).expect("owner exists").iter().cloned().collect::<Vec<_>>(), vec![h2]);
 }
+
+#[test]
+fn owner_lookups_fold_case_only_where_paths_are_case_insensitive() {
+    let headers_to_owners = DashMap::new();
+    let owners_to_headers = DashMap::new();
+    let owner = PathBuf::from("/nonexistent/Shaders/Blur.metal");
+    let header = PathBuf::from("/nonexistent/Shaders/Common.h");
+    update_owner_links(&headers_to_owners, &owners_to_headers, &owner, BTreeSet::from([header]));
+
+    let candidates = get_owner_candidates_for_header(&headers_to_owners, Path::new("/nonexistent/shaders/common.h"), 8);
+    assert_eq!(!candidates.is_empty(), crate::vfs::CASE_INSENSITIVE_PATHS);
+
+    // Re-linking the same owner under another spelling replaces its links.
+    update_owner_links(
+        &headers_to_owners,
+        &owners_to_headers,
+        Path::new("/nonexistent/shaders/blur.metal"),
+        BTreeSet::new(),
+    );
+    let remaining = get_owner_candidates_for_header(&headers_to_owners, Path::new("/nonexistent/Shaders/Common.h"), 8);
+    assert_eq!(remaining.is_empty(), crate::vfs::CASE_INSENSITIVE_PATHS);
+    for candidate in &remaining {
+        assert_eq!(candidate, &owner, "owners keep their original spelling");
+    }
+}
+
+#[test]
+fn collect_dependent_owners_follows_header_chains() {
+    let headers_to_owners = DashMap::new();
+    let owners_to_headers = DashMap::new();
+    let owner_a = PathBuf::from("/tmp/a.metal");
+    let owner_b = PathBuf::from("/tmp/b.metal");
+    let outer = PathBuf::from("/tmp/outer.h");
+    let inner = PathBuf::from("/tmp/inner.h");
+    let unrelated = PathBuf::from("/tmp/unrelated.h");
+
+    update_owner_links(&headers_to_owners, &owners_to_headers, &owner_a, BTreeSet::from([outer.clone()]));
+    update_owner_links(&headers_to_owners, &owners_to_headers, &owner_b, BTreeSet::from([unrelated.clone()]));
+
+    let includes_of = |path: &Path| {
+        if path == outer {
+            BTreeSet::from([inner.clone()])
+        } else {
+            BTreeSet::new()
+        }
+    };
+
+    let owners = collect_dependent_owners(&headers_to_owners, &owners_to_headers, &inner, includes_of, 64, 64);
+    assert_eq!(owners, vec![owner_a.clone()]);
+
+    let owners = collect_dependent_owners(&headers_to_owners, &owners_to_headers, &unrelated, includes_of, 64, 64);
+    assert_eq!(owners, vec![owner_b]);
+
+    let owners = collect_dependent_owners(&headers_to_owners, &owners_to_headers, &inner, includes_of, 64, 0);
+    assert!(owners.is_empty());
+}
+
+#[tokio::test]
+async fn translation_unit_headers_track_the_main_file_include_line() {
+    let root = std::env::temp_dir().join(format!("header_owners_tu_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("a.h"), "#include \"c.h\"\n").unwrap();
+    std::fs::write(root.join("b.h"), "#include \"a.h\"\n").unwrap();
+    std::fs::write(root.join("c.h"), "#define C 1\n").unwrap();
+    let main = root.join("main.metal");
+    let source = "#include <metal_stdlib>\n#include \"b.h\"\n#include \"a.h\"\n";
+
+    let overlay_path = normalize_path(&root.join("c.h"));
+    let headers =
+        collect_translation_unit_headers(&main, source, &[], 8, Some((overlay_path.as_path(), "#define C 2\n"))).await;
+    let summary: Vec<(String, u32)> = headers
+        .iter()
+        .map(|header| (header.path.file_name().unwrap().to_string_lossy().into_owned(), header.origin_line))
+        .collect();
+    assert_eq!(summary, [("b.h".to_string(), 1), ("a.h".to_string(), 2), ("c.h".to_string(), 2)]);
+    assert_eq!(headers[2].source, "#define C 2\n");
+
+    assert_eq!(collect_translation_unit_headers(&main, source, &[], 1, None).await.len(), 1);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn include_candidates_list_shadowed_headers_in_search_order() {
+    let root = std::env::temp_dir().join(format!("header_owners_candidates_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("shaders")).unwrap();
+    std::fs::create_dir_all(root.join("include")).unwrap();
+    std::fs::write(root.join("shaders/Light.h"), "").unwrap();
+    std::fs::write(root.join("include/Light.h"), "").unwrap();
+    let owner = root.join("shaders/blur.metal");
+    let include_paths = [root.join("include").display().to_string(), root.join("missing").display().to_string()];
+
+    let quoted: Vec<_> = include_candidates(&owner, "Light.h", false, &include_paths).collect();
+    assert_eq!(
+        quoted,
+        [
+            (normalize_path(&root.join("shaders/Light.h")), IncludeRoot::IncludingDirectory(root.join("shaders"))),
+            (normalize_path(&root.join("include/Light.h")), IncludeRoot::SearchPath(root.join("include"))),
+        ]
+    );
+    assert_eq!(resolve_include_path(&owner, "Light.h", false, &include_paths), Some(quoted[0].0.clone()));
+
+    let angled: Vec<_> = include_candidates(&owner, "Light.h", true, &include_paths).collect();
+    assert_eq!(angled, quoted[1..]);
+
+    let absolute = root.join("include/Light.h").display().to_string();
+    let absolute: Vec<_> = include_candidates(&owner, &absolute, false, &include_paths).collect();
+    assert_eq!(absolute, [(normalize_path(&root.join("include/Light.h")), IncludeRoot::Absolute)]);
+    let _ = std::fs::remove_dir_all(&root);
+}
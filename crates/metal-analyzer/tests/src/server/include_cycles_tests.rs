@@ -0,0 +1,67 @@
+use super::*;
+
+struct Project(PathBuf);
+
+impl Project {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("metal-analyzer-include-cycles-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir.canonicalize().unwrap())
+    }
+
+    fn write(
+        &self,
+        name: &str,
+        text: &str,
+    ) -> PathBuf {
+        let path = self.0.join(name);
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    fn diagnostics(
+        &self,
+        path: &Path,
+    ) -> Vec<Diagnostic> {
+        let source = std::fs::read_to_string(path).unwrap();
+        let graph =
+            IncludeGraph::build([(path.to_path_buf(), source)], &[], 64, |file| std::fs::read_to_string(file).ok());
+        cycle_diagnostics(&graph, path)
+    }
+}
+
+impl Drop for Project {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+#[test]
+fn cycle_is_reported_on_the_include_that_starts_it() {
+    let project = Project::new("cycle");
+    let a = project.write("a.h", "#pragma once\n#include \"b.h\"\n#include \"c.h\"\n");
+    let b = project.write("b.h", "#pragma once\n#include \"a.h\"\n");
+    project.write("c.h", "#pragma once\n");
+
+    let diagnostics = project.diagnostics(&a);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "include cycle: a.h -> b.h -> a.h");
+    assert_eq!(diagnostics[0].range.start.line, 1);
+    assert_eq!(diagnostics[0].code, Some(NumberOrString::String(INCLUDE_CYCLE_CODE.to_string())));
+    let related = diagnostics[0].related_information.as_ref().unwrap();
+    assert_eq!(related.len(), 1);
+    assert_eq!(related[0].location.uri, Url::from_file_path(&b).unwrap());
+    assert_eq!(related[0].message, "includes a.h");
+}
+
+#[test]
+fn self_include_is_reported_only_without_a_guard() {
+    let project = Project::new("self");
+    let unguarded = project.write("loop.h", "#include \"loop.h\"\nstruct Loop {};\n");
+    let guarded = project.write("once.h", "#ifndef ONCE_H\n#define ONCE_H\n#include \"once.h\"\n#endif\n");
+
+    let diagnostics = project.diagnostics(&unguarded);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "file includes itself without an include guard");
+    assert!(project.diagnostics(&guarded).is_empty());
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use super::*;
+
+struct Project(PathBuf);
+
+impl Project {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("metal-analyzer-include-graphs-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir.canonicalize().unwrap())
+    }
+
+    fn write(
+        &self,
+        name: &str,
+        text: &str,
+    ) -> PathBuf {
+        let path = self.0.join(name);
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+}
+
+impl Drop for Project {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+fn file_names(graph: &IncludeGraph) -> Vec<String> {
+    graph.nodes().iter().map(|node| node.path.file_name().unwrap().to_string_lossy().into_owned()).collect()
+}
+
+#[tokio::test]
+async fn graphs_are_reused_until_an_input_changes() {
+    let project = Project::new("reuse");
+    let main = project.write("blur.metal", "#include \"a.h\"\n");
+    let header = project.write("a.h", "#include \"b.h\"\n");
+    project.write("b.h", "");
+    project.write("c.h", "");
+    let cache = IncludeGraphCache::new();
+    let source = "#include \"a.h\"\n";
+    let hash = ContentHash::of(source);
+    let overlay = "#include \"c.h\"\n";
+
+    let first = cache.graph(&main, (source, hash), None, &[], 1).await;
+    assert_eq!(file_names(&first), ["blur.metal", "a.h", "b.h"]);
+    assert!(Arc::ptr_eq(&first, &cache.graph(&main, (source, hash), None, &[], 1).await));
+    assert!(!Arc::ptr_eq(&first, &cache.graph(&main, (source, hash), None, &[], 2).await));
+
+    let overlaid = cache.graph(&main, (source, hash), Some((&header, overlay, ContentHash::of(overlay))), &[], 2).await;
+    assert_eq!(file_names(&overlaid), ["blur.metal", "a.h", "c.h"]);
+
+    let before = cache.graph(&main, (source, hash), None, &[], 2).await;
+    std::fs::write(&header, "").unwrap();
+    let file = std::fs::File::options().write(true).open(&header).unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+    let after = cache.graph(&main, (source, hash), None, &[], 2).await;
+    assert!(!Arc::ptr_eq(&before, &after));
+    assert_eq!(file_names(&after), ["blur.metal", "a.h"]);
+}
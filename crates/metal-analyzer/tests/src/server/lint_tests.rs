@@ -0,0 +1,121 @@
+use std::{
+    fs,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::*;
+
+/// Create a unique temporary workspace root for each test.
+fn test_root() -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("lint_test_{}_{id}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+const SOURCE: &str = "\
+#include \"common.h\"
+#define SCALE 2
+// TODO: tidy
+kernel void k(device float* a [[buffer(0)]], device float* b [[buffer(0)]]) {
+    a[0] = b[0] * SCALE
+}
+";
+
+async fn lint(levels: &LintLevels) -> Vec<LintFinding> {
+    let root = test_root();
+    fs::write(root.join("common.h"), "#define SCALE 3\n").unwrap();
+    let path = root.join("k.metal");
+    fs::write(&path, SOURCE).unwrap();
+    let compiler = MetalCompiler::new();
+    lint_file(&compiler, None, &[root], &ServerSettings::default(), levels, &path).await.unwrap()
+}
+
+fn summary(findings: &[LintFinding]) -> Vec<(&'static str, u32, bool)> {
+    findings
+        .iter()
+        .map(|finding| (finding.rule.name(), finding.diagnostic.range.start.line, finding.is_error()))
+        .collect()
+}
+
+#[test]
+fn rule_names_round_trip() {
+    for rule in LintRule::ALL {
+        assert_eq!(LintRule::from_name(rule.name()), Some(rule));
+    }
+    assert_eq!(LintRule::from_name("duplicate-binding"), Some(LintRule::DuplicateBinding));
+    assert_eq!(LintRule::from_name("clippy"), None);
+}
+
+#[test]
+fn todo_follows_the_setting_unless_denied() {
+    let mut settings = ServerSettings::default();
+    let levels = LintLevels::default();
+    assert_eq!(levels.level(LintRule::Todo, &settings), LintLevel::Allow);
+    assert_eq!(levels.level(LintRule::Syntax, &settings), LintLevel::Warn);
+    settings.todos.diagnostics = true;
+    assert_eq!(levels.level(LintRule::Todo, &settings), LintLevel::Warn);
+
+    let levels = LintLevels {
+        allow: vec![LintRule::Syntax, LintRule::Todo],
+        deny: vec![LintRule::Todo],
+    };
+    assert_eq!(levels.level(LintRule::Syntax, &settings), LintLevel::Allow);
+    assert_eq!(levels.level(LintRule::Todo, &settings), LintLevel::Deny);
+    assert!(!enabled_rules(&levels, &settings).contains(&LintRule::Syntax));
+}
+
+#[tokio::test]
+async fn default_rules_run_without_the_toolchain() {
+    let findings = lint(&LintLevels::default()).await;
+    assert_eq!(
+        summary(&findings),
+        vec![("macro-conflict", 1, false), ("duplicate-binding", 3, false), ("syntax", 4, true)]
+    );
+}
+
+#[tokio::test]
+async fn deny_and_allow_change_what_is_reported() {
+    let levels = LintLevels {
+        allow: vec![LintRule::Syntax, LintRule::MacroConflict],
+        deny: vec![LintRule::DuplicateBinding, LintRule::Todo],
+    };
+    let findings = lint(&levels).await;
+    assert_eq!(summary(&findings), vec![("todo", 2, true), ("duplicate-binding", 3, true)]);
+    assert!(
+        render_finding(&findings[1])
+            .ends_with("k.metal:4:64: error: `buffer(0)` is already bound by another parameter [duplicate-binding]")
+    );
+}
+
+#[tokio::test]
+async fn json_and_sarif_use_one_based_positions() {
+    let levels = LintLevels {
+        allow: vec![LintRule::Syntax, LintRule::MacroConflict],
+        deny: Vec::new(),
+    };
+    let findings = lint(&levels).await;
+
+    let json = findings_json(&findings);
+    assert_eq!(json[0]["rule"], "duplicate-binding");
+    assert_eq!(json[0]["severity"], "warning");
+    assert_eq!((json[0]["line"].as_u64(), json[0]["column"].as_u64()), (Some(4), Some(64)));
+    assert_eq!(json[0]["endColumn"], 73);
+
+    let results: Vec<SarifResult> = findings.iter().map(LintFinding::to_sarif).collect();
+    let sarif = crate::server::sarif::sarif_log(&sarif_rules(), &results, &[]);
+    assert_eq!(sarif["version"], "2.1.0");
+    let run = &sarif["runs"][0];
+    let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+    assert_eq!(rules.len(), LintRule::ALL.len());
+    let result = &run["results"][0];
+    assert_eq!(result["ruleId"], "duplicate-binding");
+    assert_eq!(rules[result["ruleIndex"].as_u64().unwrap() as usize]["id"], "duplicate-binding");
+    assert_eq!(result["level"], "warning");
+    let location = &result["locations"][0]["physicalLocation"];
+    assert!(location["artifactLocation"]["uri"].as_str().unwrap().starts_with("file:///"));
+    assert_eq!(location["region"]["startLine"], 4);
+    assert_eq!(location["region"]["startColumn"], 64);
+}
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use super::*;
+
+fn definition(
+    file: &str,
+    line: u32,
+    body: &str,
+) -> MacroDefinition {
+    MacroDefinition {
+        name: "TILE".into(),
+        params: None,
+        variadic: false,
+        body: body.into(),
+        file: Some(PathBuf::from(file)),
+        line,
+    }
+}
+
+#[test]
+fn own_definition_is_flagged_with_the_other_as_related() {
+    let conflict = MacroConflict {
+        first: definition("/p/a.h", 0, "16"),
+        second: definition("/p/b.h", 2, "32"),
+    };
+    let text = "#pragma once\n\n#  define TILE 32\n";
+    let diagnostic = own_conflict_diagnostic(&conflict, Path::new("/p/b.h"), text).expect("b.h owns a definition");
+    assert_eq!(diagnostic.range, Range::new(Position::new(2, 10), Position::new(2, 14)));
+    assert_eq!(diagnostic.message, "macro `TILE` is defined differently in a.h:1: `#define TILE 16`");
+    assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::WARNING));
+    let related = diagnostic.related_information.unwrap();
+    assert_eq!(related.len(), 1);
+    assert_eq!(related[0].location.uri.path(), "/p/a.h");
+
+    assert!(own_conflict_diagnostic(&conflict, Path::new("/p/c.h"), text).is_none());
+}
+
+#[test]
+fn conflict_between_headers_is_flagged_on_the_include_line() {
+    let conflict = MacroConflict {
+        first: definition("/p/a.h", 0, "16"),
+        second: definition("/p/b.h", 2, "32"),
+    };
+    let text = "#include \"a.h\"\n#include \"b.h\"\n";
+    let diagnostic = included_conflict_diagnostic(&conflict, text, 1);
+    assert_eq!(diagnostic.range, Range::new(Position::new(1, 0), Position::new(1, 14)));
+    assert_eq!(
+        diagnostic.message,
+        "macro `TILE` is defined differently by included headers: `#define TILE 16` in a.h:1 and `#define TILE 32` in \
+         b.h:3"
+    );
+    assert_eq!(diagnostic.related_information.unwrap().len(), 2);
+}
@@ -165,3 +165,124 @@ indent_width = 2
 
     let _ = fs::remove_dir_all(&dir);
 }
+
+#[test]
+fn format_source_reindents_and_attaches_braces() {
+    let source = "struct Light\n{\nfloat3 color;\n      float intensity;\n};\nfloat f(float x)\n{\n  if (x > 0)\n  {\n return x;\n  }\n  else\n  {\n        return -x;\n  }\n}\n";
+    let formatted = format_source(source, &InternalStyle::default());
+    assert_eq!(
+        formatted,
+        "struct Light {\n    float3 color;\n    float intensity;\n};\nfloat f(float x) {\n    if (x > 0) {\n        return x;\n    } else {\n        return -x;\n    }\n}\n"
+    );
+}
+
+#[test]
+fn format_source_places_braces_on_own_line_for_allman() {
+    let style = InternalStyle {
+        braces: BraceStyle::Allman,
+        ..InternalStyle::default()
+    };
+    let source =
+        "kernel void k() {\n  if (true) {\n    return;\n  } else {\n    return;\n  }\n}\nfloat g() { return 1.0; }\n";
+    assert_eq!(
+        format_source(source, &style),
+        "kernel void k()\n{\n    if (true)\n    {\n        return;\n    }\n    else\n    {\n        return;\n    }\n}\nfloat g() { return 1.0; }\n"
+    );
+}
+
+#[test]
+fn format_source_aligns_parameter_and_field_attributes() {
+    let source = "struct VertexOut {\n    float4 position [[position]];\n    float2 uv [[user(locn0)]];\n};\nkernel void add(device const float *a [[buffer(0)]],\ndevice float *result [[buffer(1)]],\n   uint id [[thread_position_in_grid]]) {\n}\n";
+    assert_eq!(
+        format_source(source, &InternalStyle::default()),
+        "struct VertexOut {\n    float4 position [[position]];\n    float2 uv       [[user(locn0)]];\n};\nkernel void add(device const float *a [[buffer(0)]],\n                device float *result  [[buffer(1)]],\n                uint id               [[thread_position_in_grid]]) {\n}\n"
+    );
+}
+
+#[test]
+fn format_source_applies_pointer_alignment_to_declarators_only() {
+    let source =
+        "void f(device float* a, thread float & b) {\n    Foo * p;\n    float y = a[0] * b;\n    g(x * y, *a);\n}\n";
+    let expected = [
+        (PointerAlignment::Right, "void f(device float *a, thread float &b) {\n    Foo *p;\n"),
+        (PointerAlignment::Left, "void f(device float* a, thread float& b) {\n    Foo* p;\n"),
+        (PointerAlignment::Middle, "void f(device float * a, thread float & b) {\n    Foo * p;\n"),
+    ];
+    for (alignment, prefix) in expected {
+        let style = InternalStyle {
+            pointer_alignment: alignment,
+            reference_alignment: alignment,
+            ..InternalStyle::default()
+        };
+        let formatted = format_source(source, &style);
+        assert!(formatted.starts_with(prefix), "{alignment:?}: {formatted}");
+        assert!(formatted.contains("    float y = a[0] * b;\n    g(x * y, *a);\n"), "{alignment:?}: {formatted}");
+    }
+}
+
+#[test]
+fn format_source_keeps_directives_and_collapses_blank_lines() {
+    let source = "#include <metal_stdlib>\n#define SQR(x) \\\n    ((x) * (x))\n\n\n\nusing namespace metal;   \n";
+    assert_eq!(
+        format_source(source, &InternalStyle::default()),
+        "#include <metal_stdlib>\n#define SQR(x) \\\n    ((x) * (x))\n\nusing namespace metal;\n"
+    );
+}
+
+#[test]
+fn format_source_indents_labels_and_unbraced_bodies() {
+    let source = "void f(int x) {\nswitch (x) {\ncase 0:\nbreak;\ndefault:\nx = 1;\n}\nif (x)\nx = 2;\n}\n";
+    assert_eq!(
+        format_source(source, &InternalStyle::default()),
+        "void f(int x) {\n    switch (x) {\n    case 0:\n        break;\n    default:\n        x = 1;\n    }\n    if (x)\n        x = 2;\n}\n"
+    );
+}
+
+#[test]
+fn format_source_is_idempotent_with_tabs_and_crlf() {
+    let style = InternalStyle {
+        use_tab: true,
+        ..InternalStyle::default()
+    };
+    let source = "kernel void k(device float *a [[buffer(0)]],\r\n   uint i [[thread_position_in_grid]]) {\r\n/* a\r\n   b */\r\n  a[i] = 1;\r\n}\r\n";
+    let once = format_source(source, &style);
+    assert_eq!(
+        once,
+        "kernel void k(device float *a [[buffer(0)]],\r\n\t\t\t  uint i          [[thread_position_in_grid]]) {\r\n\t/* a\r\n\t   b */\r\n\ta[i] = 1;\r\n}\r\n"
+    );
+    assert_eq!(format_source(&once, &style), once);
+}
+
+#[test]
+fn format_source_keeps_a_byte_order_mark_out_of_the_first_line() {
+    let source = "\u{feff}#pragma once\n\n\tkernel void  z ( ) {\nreturn;\n}\n";
+    let formatted = format_source(source, &InternalStyle::default());
+    assert_eq!(formatted, "\u{feff}#pragma once\n\nkernel void z ( ) {\n    return;\n}\n");
+    assert_eq!(format_source(&formatted, &InternalStyle::default()), formatted);
+}
+
+#[test]
+fn resolve_internal_style_reads_metalfmt_toml() {
+    let dir = test_dir();
+    fs::write(
+        dir.join("metalfmt.toml"),
+        "indent_width = 2\nbreak_before_braces = \"Allman\"\npointer_alignment = \"Left\"\nreference_alignment = \"Right\"\n",
+    )
+    .unwrap();
+    let source = dir.join("shader.metal");
+    fs::write(&source, "").unwrap();
+
+    let options = FormattingOptions {
+        tab_size: 8,
+        insert_spaces: true,
+        ..FormattingOptions::default()
+    };
+    let style = resolve_internal_style(Some(&source), &options);
+    assert_eq!(style.indent_width, 2);
+    assert_eq!(style.tab_width, 8);
+    assert_eq!(style.braces, BraceStyle::Allman);
+    assert_eq!(style.pointer_alignment, PointerAlignment::Left);
+    assert_eq!(style.reference_alignment, PointerAlignment::Right);
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,31 @@
+use std::{
+    fs,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::*;
+
+/// Create a unique temporary workspace root for each test.
+fn test_root() -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("prebuild_test_{}_{id}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+#[test]
+fn targets_are_metal_sources_outside_excluded_paths() {
+    let root = test_root();
+    for file in ["Shaders/blur.metal", "Shaders/common.h", "Vendor/lib.metal", "build/gen.metal"] {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "kernel void k() {}\n").unwrap();
+    }
+
+    let mut settings = ServerSettings::default();
+    settings.indexing.exclude_paths = vec!["Vendor".to_string()];
+    let targets = prebuild_targets(std::slice::from_ref(&root), &settings);
+    assert_eq!(targets, vec![root.join("Shaders/blur.metal")]);
+}
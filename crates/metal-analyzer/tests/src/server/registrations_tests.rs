@@ -0,0 +1,61 @@
+use serde_json::json;
+
+use super::*;
+
+fn client_capabilities(value: serde_json::Value) -> ClientCapabilities {
+    serde_json::from_value(value).expect("client capabilities")
+}
+
+#[test]
+fn only_capabilities_the_client_registers_are_dynamic() {
+    let registrations = DynamicRegistrations::default();
+    registrations.set_supported(&client_capabilities(json!({
+        "textDocument": {
+            "formatting": { "dynamicRegistration": true },
+            "semanticTokens": {
+                "dynamicRegistration": false,
+                "requests": { "full": true },
+                "tokenTypes": [],
+                "tokenModifiers": [],
+                "formats": ["relative"]
+            }
+        }
+    })));
+    assert!(registrations.is_dynamic(DynamicCapability::Formatting));
+    assert!(!registrations.is_dynamic(DynamicCapability::SemanticTokens));
+
+    registrations.set_supported(&ClientCapabilities::default());
+    assert!(!registrations.is_dynamic(DynamicCapability::Formatting));
+}
+
+#[test]
+fn changes_follow_the_settings() {
+    let supported = BTreeSet::from(DynamicCapability::ALL);
+    let mut settings = ServerSettings::default();
+    let (register, unregister) = registration_changes(&supported, &BTreeSet::new(), &settings);
+    assert_eq!(register, DynamicCapability::ALL.to_vec());
+    assert!(unregister.is_empty());
+
+    settings.semantic_tokens.enable = false;
+    let (register, unregister) = registration_changes(&supported, &supported, &settings);
+    assert!(register.is_empty());
+    assert_eq!(unregister, vec![DynamicCapability::SemanticTokens]);
+
+    let registered = BTreeSet::from([DynamicCapability::Formatting]);
+    let only_formatting = BTreeSet::from([DynamicCapability::Formatting]);
+    let (register, unregister) = registration_changes(&only_formatting, &registered, &settings);
+    assert!(register.is_empty() && unregister.is_empty());
+}
+
+#[test]
+fn semantic_tokens_registration_carries_the_legend() {
+    let registration = DynamicCapability::SemanticTokens.registration();
+    assert_eq!(registration.method, "textDocument/semanticTokens");
+    let options = registration.register_options.expect("register options");
+    assert!(options["documentSelector"].is_null());
+    assert!(options["legend"]["tokenTypes"].as_array().is_some_and(|types| !types.is_empty()));
+    assert_eq!(options["full"], true);
+
+    let registration = DynamicCapability::Formatting.registration();
+    assert_eq!(registration.method, "textDocument/formatting");
+}
@@ -0,0 +1,118 @@
+use std::{
+    fs,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use tower_lsp::lsp_types::Position;
+
+use super::*;
+
+/// Create a unique temporary workspace root for each test.
+fn test_root() -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("sarif_test_{}_{id}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+fn result(
+    path: &Path,
+    line: u32,
+    rule_id: &str,
+) -> SarifResult {
+    SarifResult {
+        rule_id: rule_id.to_string(),
+        severity: DiagnosticSeverity::WARNING,
+        message: "`buffer(0)` is already bound by another parameter".to_string(),
+        path: path.to_path_buf(),
+        range: Range::new(Position::new(line, 4), Position::new(line, 13)),
+    }
+}
+
+fn rules() -> Vec<SarifRule> {
+    vec![SarifRule {
+        id: "duplicate-binding".to_string(),
+        description: "Two parameters of one function bind the same resource slot".to_string(),
+        help_uri: None,
+    }]
+}
+
+#[test]
+fn compiler_diagnostics_use_their_code_or_the_compiler_rule() {
+    let mut diagnostic = MetalDiagnostic {
+        file: Some("/p/common.h".to_string()),
+        line: 2,
+        column: 7,
+        end: None,
+        severity: DiagnosticSeverity::ERROR,
+        message: "use of undeclared identifier 'x'".to_string(),
+        related_information: Vec::new(),
+        fix_its: Vec::new(),
+    };
+    let plain = SarifResult::from_metal_diagnostic(Path::new("/p/blur.metal"), &diagnostic);
+    assert_eq!(plain.rule_id, COMPILER_RULE);
+    assert_eq!(plain.path, PathBuf::from("/p/common.h"));
+    assert_eq!(plain.range.start, Position::new(2, 7));
+
+    diagnostic.file = None;
+    diagnostic.message = "program scope variable must reside in constant address space".to_string();
+    let coded = SarifResult::from_metal_diagnostic(Path::new("/p/blur.metal"), &diagnostic);
+    assert_eq!(coded.rule_id, "address-space");
+    assert_eq!(coded.path, PathBuf::from("/p/blur.metal"));
+
+    let rules = compiler_rules();
+    assert_eq!(rules[0].id, COMPILER_RULE);
+    let address_space = rules.iter().find(|rule| rule.id == "address-space").unwrap();
+    assert_eq!(address_space.help_uri.as_deref(), Some(SPEC_URL));
+}
+
+#[test]
+fn locations_are_relative_to_the_workspace_root() {
+    let root = test_root();
+    let inside = root.join("Shaders/blur.metal");
+    let log = sarif_log(
+        &rules(),
+        &[result(&inside, 0, "duplicate-binding"), result(Path::new("/elsewhere/k.metal"), 0, "compiler")],
+        std::slice::from_ref(&root),
+    );
+
+    assert_eq!(log["version"], "2.1.0");
+    let run = &log["runs"][0];
+    assert_eq!(run["originalUriBaseIds"]["SRCROOT"]["uri"], Url::from_directory_path(&root).unwrap().as_str());
+    let first = &run["results"][0];
+    assert_eq!(first["ruleIndex"], 0);
+    assert_eq!(first["level"], "warning");
+    let location = &first["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"], json!({ "uri": "Shaders/blur.metal", "uriBaseId": "SRCROOT" }));
+    assert_eq!(location["region"], json!({ "startLine": 1, "startColumn": 5, "endLine": 1, "endColumn": 14 }));
+
+    let second = &run["results"][1];
+    assert!(second.get("ruleIndex").is_none());
+    assert_eq!(second["locations"][0]["physicalLocation"]["artifactLocation"]["uri"], "file:///elsewhere/k.metal");
+}
+
+#[test]
+fn fingerprints_follow_the_line_text_not_its_number() {
+    let root = test_root();
+    let path = root.join("k.metal");
+    fs::write(&path, "kernel void k(\n    float a [[buffer(0)]],\n    float a [[buffer(0)]],\n    float c);\n")
+        .unwrap();
+    let fingerprint = |log: &Value, index: usize| {
+        log["runs"][0]["results"][index]["partialFingerprints"]["primaryLocationLineHash"].as_str().unwrap().to_string()
+    };
+
+    let log = sarif_log(
+        &rules(),
+        &[result(&path, 1, "duplicate-binding"), result(&path, 2, "duplicate-binding")],
+        std::slice::from_ref(&root),
+    );
+    let (first, second) = (fingerprint(&log, 0), fingerprint(&log, 1));
+    assert!(first.ends_with(":1"));
+    assert_eq!(second, format!("{}:2", first.trim_end_matches(":1")));
+
+    fs::write(&path, "// moved\nkernel void k(\n    float a [[buffer(0)]],\n    float b);\n").unwrap();
+    let moved = sarif_log(&rules(), &[result(&path, 2, "duplicate-binding")], &[root]);
+    assert_eq!(fingerprint(&moved, 0), first);
+}
@@ -0,0 +1,100 @@
+use super::*;
+
+fn uri(name: &str) -> Url {
+    Url::parse(&format!("file:///shaders/{name}.metal")).unwrap()
+}
+
+#[test]
+fn debounce_follows_recent_compile_durations_within_bounds() {
+    let scheduler = DiagnosticsScheduler::new(1);
+    let uri = uri("blur");
+    assert_eq!(scheduler.debounce(&uri, 500), Duration::from_millis(500));
+
+    scheduler.record(&uri, Duration::from_millis(20));
+    assert_eq!(scheduler.debounce(&uri, 500), Duration::from_millis(125));
+
+    scheduler.record(&uri, Duration::from_millis(780));
+    assert_eq!(scheduler.debounce(&uri, 500), Duration::from_millis(400));
+
+    for _ in 0..8 {
+        scheduler.record(&uri, Duration::from_secs(30));
+    }
+    assert_eq!(scheduler.debounce(&uri, 500), Duration::from_millis(2000));
+    assert_eq!(scheduler.debounce(&uri, 2000), Duration::from_millis(MAX_DIAGNOSTIC_DEBOUNCE_MS));
+
+    scheduler.forget(&uri);
+    assert_eq!(scheduler.debounce(&uri, 500), Duration::from_millis(500));
+}
+
+#[tokio::test]
+async fn a_burst_of_compiles_of_one_document_coalesces_into_the_latest() {
+    let scheduler = Arc::new(DiagnosticsScheduler::new(4));
+    let blur = uri("blur");
+    let running = scheduler.schedule(&blur, 1).await.expect("runs at once");
+
+    let second = tokio::spawn({
+        let scheduler = scheduler.clone();
+        let blur = blur.clone();
+        async move { scheduler.schedule(&blur, 2).await.is_some() }
+    });
+    tokio::task::yield_now().await;
+    let third = tokio::spawn({
+        let scheduler = scheduler.clone();
+        let blur = blur.clone();
+        async move { scheduler.schedule(&blur, 3).await.map(CompileTicket::finish).is_some() }
+    });
+    tokio::task::yield_now().await;
+
+    assert!(!second.await.unwrap(), "replaced by the later compile");
+    let status = scheduler.status();
+    assert_eq!((status.running, status.queued, status.coalesced), (1, 1, 1));
+
+    running.finish();
+    assert!(third.await.unwrap());
+    let status = scheduler.status();
+    assert_eq!((status.running, status.queued, status.completed), (0, 0, 2));
+}
+
+#[tokio::test]
+async fn a_ticket_dropped_without_compiling_frees_its_slot_but_is_not_counted() {
+    let scheduler = Arc::new(DiagnosticsScheduler::new(1));
+    let blur = uri("blur");
+    drop(scheduler.schedule(&blur, 1).await.expect("runs at once"));
+
+    let status = scheduler.status();
+    assert_eq!((status.running, status.completed), (0, 0));
+    assert_eq!(scheduler.debounce(&blur, 500), Duration::from_millis(500));
+    assert!(scheduler.schedule(&uri("copy"), 1).await.is_some());
+}
+
+#[tokio::test]
+async fn compiles_beyond_the_limit_wait_for_a_slot() {
+    let scheduler = Arc::new(DiagnosticsScheduler::new(1));
+    let running = scheduler.schedule(&uri("blur"), 1).await.expect("runs at once");
+
+    let waiting = tokio::time::timeout(Duration::from_millis(20), scheduler.schedule(&uri("copy"), 1)).await;
+    assert!(waiting.is_err(), "a second document must wait for the only slot");
+
+    scheduler.set_limit(2);
+    let copy = tokio::time::timeout(Duration::from_secs(1), scheduler.schedule(&uri("copy"), 2)).await;
+    assert!(copy.expect("the raised limit frees a slot").is_some());
+    drop(running);
+}
+
+#[tokio::test]
+async fn a_compile_dropped_while_waiting_leaves_the_queue() {
+    let scheduler = Arc::new(DiagnosticsScheduler::new(1));
+    let blur = uri("blur");
+    let running = scheduler.schedule(&blur, 1).await.expect("runs at once");
+
+    let waiting = tokio::time::timeout(Duration::from_millis(20), scheduler.schedule(&blur, 2)).await;
+    assert!(waiting.is_err(), "the document is still compiling");
+    let status = scheduler.status();
+    assert_eq!((status.queued, status.coalesced), (0, 0));
+
+    drop(running);
+    scheduler.forget(&blur);
+    assert!(scheduler.queues.lock().unwrap().documents.is_empty());
+    assert!(scheduler.schedule(&blur, 3).await.is_some());
+    assert_eq!(scheduler.status().coalesced, 0);
+}
@@ -1,6 +1,7 @@
 use serde_json::json;
 
 use super::*;
+use crate::metal::{compiler_overrides::CompilerOverride, language_version::LanguageVersion};
 
 #[test]
 fn parses_namespaced_payload() {
@@ -8,12 +9,15 @@ fn parses_namespaced_payload() {
         "metal-analyzer": {
             "formatting": {
                 "enable": false,
+                "engine": "internal",
                 "command": "xcrun",
-                "args": ["clang-format"]
+                "args": ["clang-format"],
+                "fallbackStyle": "{ColumnLimit: 100}"
             },
             "diagnostics": {
                 "debounceMs": 1200,
-                "scope": "workspace"
+                "scope": "workspace",
+                "headerContext": "both"
             },
             "indexing": {
                 "concurrency": 4,
@@ -33,10 +37,13 @@ fn parses_namespaced_payload() {
 
     let settings = ServerSettings::from_lsp_payload(Some(&payload));
     assert!(!settings.formatting.enable);
+    assert_eq!(settings.formatting.engine, FormattingEngine::Internal);
     assert_eq!(settings.formatting.command, "xcrun");
     assert_eq!(settings.formatting.args, vec!["clang-format"]);
+    assert_eq!(settings.formatting.fallback_style, "{ColumnLimit: 100}");
     assert_eq!(settings.diagnostics.debounce_ms, 1200);
     assert_eq!(settings.diagnostics.scope, DiagnosticsScope::Workspace);
+    assert_eq!(settings.diagnostics.header_context, HeaderContext::Both);
     assert_eq!(settings.indexing.concurrency, 4);
     assert_eq!(settings.indexing.max_file_size_kb, 256);
     assert_eq!(
@@ -58,7 +65,8 @@ fn parses_direct_payload() {
             "scope": "openFiles"
         },
         "indexing": {
-            "enable": false
+            "enable": false,
+            "persistIndex": false
         }
     });
 
@@ -67,6 +75,96 @@ fn parses_direct_payload() {
     assert!(settings.diagnostics.on_save);
     assert_eq!(settings.diagnostics.scope, DiagnosticsScope::OpenFiles);
     assert!(!settings.indexing.enable);
+    assert!(!settings.indexing.persist_index);
+}
+
+#[test]
+fn syntax_diagnostics_default_on_and_can_be_disabled() {
+    assert!(ServerSettings::default().diagnostics.syntax);
+
+    let payload = json!({ "diagnostics": { "syntax": false } });
+    let settings = ServerSettings::from_lsp_payload(Some(&payload));
+    assert!(!settings.diagnostics.syntax);
+    assert!(settings.diagnostics.on_type);
+}
+
+#[test]
+fn unused_include_hints_can_be_disabled() {
+    assert!(ServerSettings::default().diagnostics.unused_includes);
+
+    let payload = json!({ "diagnostics": { "unusedIncludes": false } });
+    let settings = ServerSettings::from_lsp_payload(Some(&payload));
+    assert!(!settings.diagnostics.unused_includes);
+}
+
+#[test]
+fn threadgroup_memory_limit_can_be_changed_or_disabled() {
+    assert_eq!(ServerSettings::default().diagnostics.threadgroup_memory_limit, 32768);
+
+    let payload = json!({ "diagnostics": { "threadgroupMemoryLimit": 0 } });
+    let settings = ServerSettings::from_lsp_payload(Some(&payload));
+    assert_eq!(settings.diagnostics.threadgroup_memory_limit, 0);
+}
+
+#[test]
+fn entry_point_lenses_read_a_pipelines_file() {
+    let defaults = ServerSettings::default();
+    assert!(defaults.entry_points.code_lens);
+    assert!(defaults.entry_points.pipelines_file.is_empty());
+
+    let payload = json!({ "entryPoints": { "codeLens": false, "pipelinesFile": " shaders/pipelines.json " } });
+    let settings = ServerSettings::from_lsp_payload(Some(&payload));
+    assert!(!settings.entry_points.code_lens);
+    assert_eq!(settings.entry_points.pipelines_file, "shaders/pipelines.json");
+}
+
+#[test]
+fn code_lenses_can_be_turned_off() {
+    let defaults = ServerSettings::default();
+    assert!(defaults.code_lens.references);
+    assert!(defaults.code_lens.file_actions);
+
+    let payload = json!({ "codeLens": { "references": false } });
+    let settings = ServerSettings::from_lsp_payload(Some(&payload));
+    assert!(!settings.code_lens.references);
+    assert!(settings.code_lens.file_actions);
+}
+
+#[test]
+fn semantic_tokens_can_be_turned_off() {
+    assert!(ServerSettings::default().semantic_tokens.enable);
+
+    let payload = json!({ "semanticTokens": { "enable": false } });
+    let settings = ServerSettings::from_lsp_payload(Some(&payload));
+    assert!(!settings.semantic_tokens.enable);
+}
+
+#[test]
+fn unused_symbol_hints_take_an_allowlist() {
+    let defaults = ServerSettings::default();
+    assert!(defaults.diagnostics.unused_symbols);
+    assert!(defaults.diagnostics.unused_symbols_allow.is_empty());
+
+    let payload = json!({ "diagnostics": { "unusedSymbols": false, "unusedSymbolsAllow": ["debug_dump"] } });
+    let settings = ServerSettings::from_lsp_payload(Some(&payload));
+    assert!(!settings.diagnostics.unused_symbols);
+    assert_eq!(settings.diagnostics.unused_symbols_allow, vec!["debug_dump".to_string()]);
+}
+
+#[test]
+fn diagnostics_exclusions_are_trimmed_and_deduplicated() {
+    assert!(ServerSettings::default().diagnostics.exclude.is_empty());
+    let payload = json!({ "diagnostics": { "exclude": ["**/generated/**", " **/generated/** ", "", "*.gen.metal"] } });
+    let settings = ServerSettings::from_lsp_payload(Some(&payload));
+    assert_eq!(settings.diagnostics.exclude, vec!["**/generated/**".to_string(), "*.gen.metal".to_string()]);
+    assert!(settings.indexing.exclude_paths.is_empty());
+}
+
+#[test]
+fn gitignore_is_respected_unless_turned_off() {
+    assert!(ServerSettings::default().indexing.respect_gitignore);
+    let payload = json!({ "indexing": { "respectGitignore": false } });
+    assert!(!ServerSettings::from_lsp_payload(Some(&payload)).indexing.respect_gitignore);
 }
 
 #[test]
@@ -82,6 +180,16 @@ fn clamps_numeric_values() {
     assert_eq!(settings.indexing.max_file_size_kb, MIN_MAX_FILE_SIZE_KB);
 }
 
+#[test]
+fn memory_budget_can_be_lifted_but_not_set_below_the_minimum() {
+    assert_eq!(ServerSettings::default().indexing.max_memory_mb, 2048);
+
+    let unlimited = ServerSettings::from_lsp_payload(Some(&json!({ "indexing": { "maxMemoryMb": 0 } })));
+    assert_eq!(unlimited.indexing.max_memory_mb, 0);
+    let tiny = ServerSettings::from_lsp_payload(Some(&json!({ "indexing": { "maxMemoryMb": 8 } })));
+    assert_eq!(tiny.indexing.max_memory_mb, MIN_MAX_MEMORY_MB);
+}
+
 #[test]
 fn preserves_existing_values_when_payload_is_partial() {
     let base = ServerSettings {
@@ -102,12 +210,71 @@ fn preserves_existing_values_when_payload_is_partial() {
     assert_eq!(merged.diagnostics.debounce_ms, 900);
 }
 
+#[test]
+fn symbol_search_scope_merges_partial_patches() {
+    let defaults = ServerSettings::from_lsp_payload(None);
+    assert_eq!(defaults.symbols.search_scope, SymbolSearchScope::default());
+    assert!(!defaults.symbols.search_scope.include_system_headers);
+
+    let payload = json!({
+        "symbols": {
+            "searchScope": {
+                "includeSystemHeaders": true,
+                "includeTests": false
+            }
+        }
+    });
+    let merged = defaults.merged_with_payload(&payload);
+    assert_eq!(
+        merged.symbols.search_scope,
+        SymbolSearchScope {
+            include_system_headers: true,
+            include_generated: false,
+            include_tests: false,
+        }
+    );
+}
+
+#[test]
+fn lsp_payload_takes_precedence_over_workspace_files() {
+    let shared = json!({
+        "compiler": {
+            "platform": "ios",
+            "extraFlags": ["-DSHARED"]
+        },
+        "diagnostics": {
+            "scope": "workspace"
+        }
+    });
+    let payload = json!({
+        "metal-analyzer": {
+            "compiler": {
+                "extraFlags": ["-DLOCAL"]
+            }
+        }
+    });
+
+    let settings = ServerSettings::layered(&[shared], Some(&payload));
+    assert_eq!(settings.compiler.platform, CompilerPlatform::Ios);
+    assert_eq!(settings.compiler.extra_flags, vec!["-DLOCAL".to_string()]);
+    assert_eq!(settings.diagnostics.scope, DiagnosticsScope::Workspace);
+}
+
 #[test]
 fn diagnostics_scope_defaults_to_open_files() {
     let settings = ServerSettings::from_lsp_payload(None);
     assert_eq!(settings.diagnostics.scope, DiagnosticsScope::OpenFiles);
 }
 
+#[test]
+fn header_context_defaults_to_owner() {
+    let settings = ServerSettings::default();
+    assert_eq!(settings.diagnostics.header_context, HeaderContext::Owner);
+    assert!(HeaderContext::Owner.uses_owner() && !HeaderContext::Owner.uses_standalone());
+    assert!(!HeaderContext::Standalone.uses_owner() && HeaderContext::Standalone.uses_standalone());
+    assert!(HeaderContext::Both.uses_owner() && HeaderContext::Both.uses_standalone());
+}
+
 #[test]
 fn compiler_platform_normalizes_case_and_whitespace() {
     let payload = json!({
@@ -151,3 +318,151 @@ fn indexing_exclude_paths_are_trimmed_and_deduplicated() {
         vec!["external/vendor-shaders".to_string(), "/tmp/generated".to_string(),]
     );
 }
+
+#[test]
+fn compiler_artifact_settings_are_normalized() {
+    let payload = json!({
+        "compiler": {
+            "tempDir": "  /tmp/metal-temp  ",
+            "keepArtifacts": true,
+            "artifactsMaxSizeMb": 0
+        }
+    });
+
+    let settings = ServerSettings::from_lsp_payload(Some(&payload));
+    assert_eq!(settings.compiler.temp_dir.as_deref(), Some("/tmp/metal-temp"));
+    assert!(settings.compiler.keep_artifacts);
+    assert_eq!(settings.compiler.artifacts_max_size_mb, MIN_ARTIFACTS_MAX_SIZE_MB);
+
+    let cleared = settings.merged_with_payload(&json!({ "compiler": { "tempDir": " " } }));
+    assert_eq!(cleared.compiler.temp_dir, None);
+}
+
+#[test]
+fn ast_dump_timeout_is_clamped() {
+    let defaults = ServerSettings::from_lsp_payload(None);
+    assert_eq!(defaults.compiler.ast_dump_timeout(), std::time::Duration::from_secs(30));
+
+    let payload = json!({ "compiler": { "astDumpTimeoutMs": 10 } });
+    let settings = ServerSettings::from_lsp_payload(Some(&payload));
+    assert_eq!(settings.compiler.ast_dump_timeout_ms, MIN_AST_DUMP_TIMEOUT_MS);
+
+    let raised = settings.merged_with_payload(&json!({ "compiler": { "astDumpTimeoutMs": 90000 } }));
+    assert_eq!(raised.compiler.ast_dump_timeout_ms, 90_000);
+}
+
+#[test]
+fn navigation_ranking_weights_are_patched_and_normalized() {
+    let payload = json!({
+        "navigation": {
+            "ranking": {
+                "otherFile": 1,
+                "projectHeader": 5000,
+                "builtinPrefixes": ["  mylib_ ", "", "mylib_"]
+            }
+        }
+    });
+
+    let settings = ServerSettings::from_lsp_payload(Some(&payload));
+    let ranking = &settings.navigation.ranking;
+    assert_eq!(ranking.other_file, 1);
+    assert_eq!(ranking.project_header, MAX_RANKING_WEIGHT);
+    assert_eq!(ranking.declaration_only, RankingWeights::default().declaration_only);
+    assert_eq!(ranking.builtin_prefixes, vec!["mylib_".to_string()]);
+}
+
+#[test]
+fn todo_tags_are_trimmed_and_only_reported_when_enabled() {
+    let defaults = ServerSettings::from_lsp_payload(None);
+    assert_eq!(defaults.todos.tags, vec!["TODO", "FIXME", "PERF"]);
+    assert!(defaults.todos.diagnostic_tags().is_empty());
+
+    let payload = json!({
+        "todos": {
+            "tags": [" HACK ", "", "TODO"],
+            "diagnostics": true
+        }
+    });
+    let merged = defaults.merged_with_payload(&payload);
+    assert_eq!(merged.todos.tags, vec!["HACK", "TODO"]);
+    assert_eq!(merged.todos.diagnostic_tags(), vec!["HACK", "TODO"]);
+}
+
+#[test]
+fn completion_snippets_default_on_and_can_be_disabled() {
+    let defaults = ServerSettings::from_lsp_payload(None);
+    assert!(defaults.completion.snippets);
+
+    let merged = defaults.merged_with_payload(&json!({ "completion": { "snippets": false } }));
+    assert!(!merged.completion.snippets);
+}
+
+#[test]
+fn kernel_stats_are_opt_in() {
+    let defaults = ServerSettings::from_lsp_payload(None);
+    assert!(!defaults.compiler.kernel_stats);
+
+    let merged = defaults.merged_with_payload(&json!({ "compiler": { "kernelStats": true } }));
+    assert!(merged.compiler.kernel_stats);
+    assert_eq!(merged.compiler.invalidation(&defaults.compiler), CompilerInvalidation::Nothing);
+}
+
+#[test]
+fn perf_log_interval_is_off_by_default_and_capped() {
+    let defaults = ServerSettings::from_lsp_payload(None);
+    assert_eq!(defaults.logging.perf_interval_secs, 0);
+
+    let merged = defaults.merged_with_payload(&json!({ "logging": { "perfIntervalSecs": 10_000_000 } }));
+    assert_eq!(merged.logging.perf_interval_secs, MAX_PERF_INTERVAL_SECS);
+}
+
+#[test]
+fn compiler_changes_invalidate_only_what_depends_on_them() {
+    let defaults = ServerSettings::from_lsp_payload(None);
+    let invalidation = |patch: serde_json::Value| {
+        defaults.merged_with_payload(&json!({ "compiler": patch })).compiler.invalidation(&defaults.compiler)
+    };
+
+    assert_eq!(invalidation(json!({ "keepArtifacts": true })), CompilerInvalidation::Nothing);
+    assert_eq!(invalidation(json!({ "extraFlags": ["-Wno-unused-variable"] })), CompilerInvalidation::Diagnostics);
+    assert_eq!(invalidation(json!({ "extraFlags": ["-w"] })), CompilerInvalidation::Diagnostics);
+    assert_eq!(invalidation(json!({ "extraFlags": ["-DUSE_HALF=1"] })), CompilerInvalidation::Preprocessor);
+    assert_eq!(invalidation(json!({ "includePaths": ["/tmp/includes"] })), CompilerInvalidation::Preprocessor);
+    assert_eq!(invalidation(json!({ "platform": "ios" })), CompilerInvalidation::Preprocessor);
+    assert_eq!(invalidation(json!({ "languageVersion": "3.0" })), CompilerInvalidation::Preprocessor);
+    assert_eq!(
+        invalidation(json!({ "overrides": [{ "pathGlob": "ios/**", "flags": ["-D__METAL_IOS__"] }] })),
+        CompilerInvalidation::Preprocessor
+    );
+}
+
+#[test]
+fn compiler_overrides_are_parsed_and_normalized() {
+    let settings = ServerSettings::default().merged_with_payload(&json!({
+        "compiler": {
+            "overrides": [
+                { "pathGlob": " ios/** ", "flags": ["-D__METAL_IOS__", " "], "includePaths": ["/work/ios/include"] },
+                { "flags": ["-DIGNORED"] }
+            ]
+        }
+    }));
+    assert_eq!(
+        settings.compiler.overrides,
+        vec![CompilerOverride {
+            path_glob: "ios/**".to_string(),
+            flags: vec!["-D__METAL_IOS__".to_string()],
+            include_paths: vec!["/work/ios/include".to_string()],
+        }]
+    );
+}
+
+#[test]
+fn compiler_language_version_parses_versions_and_auto() {
+    let settings = |value: &str| {
+        ServerSettings::default().merged_with_payload(&json!({ "compiler": { "languageVersion": value } }))
+    };
+    assert_eq!(settings("3.1").compiler.language_version, Some(LanguageVersion::new(3, 1)));
+    assert_eq!(settings(" 2.4 ").compiler.language_version, Some(LanguageVersion::new(2, 4)));
+    assert_eq!(settings("auto").compiler.language_version, None);
+    assert_eq!(settings("latest").compiler.language_version, None);
+}
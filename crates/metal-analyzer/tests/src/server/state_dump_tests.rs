@@ -0,0 +1,88 @@
+use std::io::Read;
+
+use flate2::read::DeflateDecoder;
+
+use super::*;
+
+fn u16_at(
+    bytes: &[u8],
+    offset: usize,
+) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(
+    bytes: &[u8],
+    offset: usize,
+) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+/// The entries of `archive`, read through its central directory.
+fn unzip(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let end = archive.len() - 22;
+    assert_eq!(u32_at(archive, end), 0x0605_4b50, "end of central directory");
+    let count = usize::from(u16_at(archive, end + 10));
+    let mut record = u32_at(archive, end + 16) as usize;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        assert_eq!(u32_at(archive, record), 0x0201_4b50, "central directory record");
+        let crc = u32_at(archive, record + 16);
+        let compressed_len = u32_at(archive, record + 20) as usize;
+        let name_len = usize::from(u16_at(archive, record + 28));
+        let local = u32_at(archive, record + 42) as usize;
+        let name = String::from_utf8(archive[record + 46..record + 46 + name_len].to_vec()).expect("utf-8 name");
+
+        assert_eq!(u32_at(archive, local), 0x0403_4b50, "local header");
+        let data = local + 30 + usize::from(u16_at(archive, local + 26));
+        let mut contents = Vec::new();
+        DeflateDecoder::new(&archive[data..data + compressed_len]).read_to_end(&mut contents).expect("inflate");
+        let mut expected_crc = Crc::new();
+        expected_crc.update(&contents);
+        assert_eq!(expected_crc.sum(), crc, "crc of {name}");
+
+        entries.push((name, contents));
+        record += 46 + name_len;
+    }
+    entries
+}
+
+#[test]
+fn zip_archive_round_trips_entries() {
+    let archive =
+        zip_archive([("state.json", b"{\"reason\": \"panic\"}".as_slice()), ("log.txt", b"".as_slice())]).expect("zip");
+
+    let entries = unzip(&archive);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0], ("state.json".to_string(), b"{\"reason\": \"panic\"}".to_vec()));
+    assert_eq!(entries[1], ("log.txt".to_string(), Vec::new()));
+}
+
+#[test]
+fn sanitize_replaces_the_home_directory() {
+    let text = "/Users/dev/project/shader.metal and /Users/dev/.metal-analyzer/metal-analyzer.log";
+    assert_eq!(sanitize(text, Some("/Users/dev/")), "~/project/shader.metal and ~/.metal-analyzer/metal-analyzer.log");
+    assert_eq!(sanitize(text, None), text);
+    assert_eq!(sanitize(text, Some("/")), text, "an empty home is ignored");
+}
+
+#[test]
+fn last_lines_keeps_the_tail() {
+    let log: String = (1..=5).map(|line| format!("line {line}\n")).collect();
+    assert_eq!(last_lines(&log, 2), "line 4\nline 5\n");
+    assert_eq!(last_lines(&log, 10), log);
+    assert_eq!(last_lines("", 3), "");
+}
+
+#[test]
+fn log_tail_reads_only_the_end_of_a_large_log() {
+    let path = std::env::temp_dir().join(format!("metal-analyzer-log-tail-{}.log", std::process::id()));
+    let line = "x".repeat(1000);
+    let log: String = (0..LOG_TAIL_BYTES as usize / 1000 + 10).map(|i| format!("{i} {line}\n")).collect();
+    std::fs::write(&path, &log).unwrap();
+
+    let tail = log_tail(&path, LOG_TAIL_LINES).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(tail, last_lines(&log, LOG_TAIL_LINES));
+    assert!(tail.lines().all(|tail_line| tail_line.ends_with(&line)));
+}
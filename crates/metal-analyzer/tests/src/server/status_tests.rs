@@ -0,0 +1,27 @@
+use super::*;
+
+#[test]
+fn healthy_until_something_fails() {
+    let summary = summarize(None, None, 0);
+    assert_eq!(summary.health, Health::Ok);
+    assert!(summary.quiescent);
+    assert_eq!(summary.message, None);
+
+    assert_eq!(summarize(Some(true), None, 2).health, Health::Ok);
+    assert!(!summarize(Some(true), None, 2).quiescent);
+}
+
+#[test]
+fn last_error_is_a_warning() {
+    let summary = summarize(Some(true), Some("Formatting failed".to_string()), 0);
+    assert_eq!(summary.health, Health::Warning);
+    assert_eq!(summary.message.as_deref(), Some("Formatting failed"));
+}
+
+#[test]
+fn missing_toolchain_outweighs_errors() {
+    let summary = summarize(Some(false), Some("Formatting failed".to_string()), 1);
+    assert_eq!(summary.health, Health::Error);
+    assert_eq!(summary.message.as_deref(), Some("Metal compiler toolchain or SDK is unavailable"));
+    assert!(!summary.quiescent);
+}
@@ -0,0 +1,34 @@
+use super::*;
+
+#[test]
+fn matches_subsequences_case_insensitively() {
+    assert!(fuzzy_score("vtx", "VertexOut").is_some());
+    assert!(fuzzy_score("gemm", "steel_gemm_kernel").is_some());
+    assert!(fuzzy_score("xv", "VertexOut").is_none());
+    assert!(fuzzy_score("longer", "long").is_none());
+}
+
+#[test]
+fn prefers_exact_then_prefix_then_scattered() {
+    let exact = fuzzy_score("vertex", "vertex").unwrap();
+    let prefix = fuzzy_score("vertex", "vertex_main").unwrap();
+    let scattered = fuzzy_score("vertex", "v_e_r_t_e_x_shader").unwrap();
+    assert!(exact > prefix, "{exact} <= {prefix}");
+    assert!(prefix > scattered, "{prefix} <= {scattered}");
+}
+
+#[test]
+fn rewards_word_boundary_matches() {
+    let humps = fuzzy_score("vo", "VertexOut").unwrap();
+    let inner = fuzzy_score("vo", "Vlookup").unwrap();
+    assert!(humps > inner, "{humps} <= {inner}");
+
+    let snake = fuzzy_score("gk", "gemm_kernel").unwrap();
+    let middle = fuzzy_score("gk", "gemmkernel").unwrap();
+    assert!(snake > middle, "{snake} <= {middle}");
+}
+
+#[test]
+fn empty_pattern_matches_everything() {
+    assert_eq!(fuzzy_score("", "anything"), Some(0));
+}
@@ -0,0 +1,110 @@
+use std::path::PathBuf;
+
+use tower_lsp::lsp_types::{Position, Range};
+
+use super::*;
+
+fn location(
+    path: &str,
+    kind: SymbolKind,
+) -> SymbolLocation {
+    SymbolLocation {
+        uri: Url::parse(&format!("file://{path}")).unwrap(),
+        range: Range::new(Position::new(0, 0), Position::new(0, 1)),
+        kind,
+        container_name: None,
+    }
+}
+
+fn names(results: &[(String, SymbolLocation)]) -> Vec<&str> {
+    results.iter().map(|(name, _)| name.as_str()).collect()
+}
+
+#[test]
+fn parses_kind_prefixes() {
+    assert_eq!(
+        SymbolQuery::parse("#Vert"),
+        SymbolQuery {
+            filter: SymbolKindFilter::Types,
+            pattern: "Vert"
+        }
+    );
+    assert_eq!(
+        SymbolQuery::parse("f main"),
+        SymbolQuery {
+            filter: SymbolKindFilter::Functions,
+            pattern: "main"
+        }
+    );
+    assert_eq!(
+        SymbolQuery::parse("fmain"),
+        SymbolQuery {
+            filter: SymbolKindFilter::All,
+            pattern: "fmain"
+        }
+    );
+}
+
+#[test]
+fn search_ranks_better_matches_first() {
+    let index = SymbolIndex::new();
+    index.insert("vertex_main".into(), location("/ws/a.metal", SymbolKind::FUNCTION));
+    index.insert("VertexOut".into(), location("/ws/a.metal", SymbolKind::STRUCT));
+    index.insert("unrelated".into(), location("/ws/a.metal", SymbolKind::FUNCTION));
+
+    let results = index.search("vo", None, 10);
+    assert_eq!(names(&results), vec!["VertexOut"]);
+
+    let results = index.search("vertm", None, 10);
+    assert_eq!(names(&results), vec!["vertex_main"]);
+}
+
+#[test]
+fn search_applies_kind_filters() {
+    let index = SymbolIndex::new();
+    index.insert("Light".into(), location("/ws/a.metal", SymbolKind::STRUCT));
+    index.insert("light".into(), location("/ws/a.metal", SymbolKind::FUNCTION));
+    index.insert("lightCount".into(), location("/ws/a.metal", SymbolKind::VARIABLE));
+
+    assert_eq!(names(&index.search("#light", None, 10)), vec!["Light"]);
+    assert_eq!(names(&index.search("f light", None, 10)), vec!["light"]);
+    assert_eq!(index.search("light", None, 10).len(), 3);
+}
+
+#[test]
+fn search_breaks_ties_by_kind_then_proximity() {
+    let index = SymbolIndex::new();
+    index.insert("shade".into(), location("/ws/far/other.metal", SymbolKind::FUNCTION));
+    index.insert("shade".into(), location("/ws/near/sibling.metal", SymbolKind::FUNCTION));
+    index.insert("shade".into(), location("/ws/near/current.metal", SymbolKind::VARIABLE));
+
+    let anchor = Url::parse("file:///ws/near/current.metal").unwrap();
+    let results = index.search("shade", Some(&anchor), 10);
+    let paths: Vec<&str> = results.iter().map(|(_, loc)| loc.uri.path()).collect();
+    assert_eq!(paths, vec!["/ws/near/sibling.metal", "/ws/far/other.metal", "/ws/near/current.metal"]);
+}
+
+#[test]
+fn search_in_scope_filters_by_origin() {
+    let index = SymbolIndex::new();
+    index.insert("blur".into(), location("/ws/shaders/blur.metal", SymbolKind::FUNCTION));
+    index.insert("blur".into(), location("/ws/generated/blur_table.h", SymbolKind::FUNCTION));
+    index.insert("blur".into(), location("/ws/tests/fixtures/blur.metal", SymbolKind::FUNCTION));
+    index.insert("blur".into(), location("/opt/sdk/include/blur.h", SymbolKind::FUNCTION));
+
+    let mut filter = SymbolSearchFilter {
+        workspace_roots: vec![PathBuf::from("/ws")],
+        ..SymbolSearchFilter::default()
+    };
+    let origins = |filter: &SymbolSearchFilter| -> Vec<SymbolOrigin> {
+        index.search_in_scope("blur", None, filter, 10).into_iter().map(|(_, _, origin)| origin).collect()
+    };
+    assert_eq!(origins(&filter), vec![SymbolOrigin::Workspace]);
+
+    filter.include_tests = true;
+    filter.include_system = true;
+    assert_eq!(origins(&filter).len(), 3);
+    assert!(!origins(&filter).contains(&SymbolOrigin::Generated));
+
+    assert_eq!(index.search("blur", None, 10).len(), 4);
+}
@@ -0,0 +1,37 @@
+use super::*;
+
+fn classify(path: &str) -> SymbolOrigin {
+    SymbolOrigin::classify(&Url::parse(&format!("file://{path}")).unwrap(), &[PathBuf::from("/ws")])
+}
+
+#[test]
+fn classifies_by_directory_and_file_name() {
+    assert_eq!(classify("/ws/shaders/blur.metal"), SymbolOrigin::Workspace);
+    assert_eq!(classify("/ws/Generated/tables.h"), SymbolOrigin::Generated);
+    assert_eq!(classify("/ws/DerivedData/x/shaders.metal"), SymbolOrigin::Generated);
+    assert_eq!(classify("/ws/shaders/lut_generated.h"), SymbolOrigin::Generated);
+    assert_eq!(classify("/ws/tests/blur.metal"), SymbolOrigin::Test);
+    assert_eq!(classify("/ws/shaders/blur_test.metal"), SymbolOrigin::Test);
+}
+
+#[test]
+fn files_outside_workspace_roots_are_system() {
+    assert_eq!(classify("/other/project/common.h"), SymbolOrigin::System);
+    let sdk =
+        "/Applications/Xcode.app/Contents/Developer/Toolchains/XcodeDefault.xctoolchain/usr/metal/include/metal_stdlib";
+    assert_eq!(classify(sdk), SymbolOrigin::System);
+}
+
+#[test]
+fn only_path_below_the_root_is_matched() {
+    let uri = Url::parse("file:///home/me/tests/project/blur.metal").unwrap();
+    let origin = SymbolOrigin::classify(&uri, &[PathBuf::from("/home/me/tests/project")]);
+    assert_eq!(origin, SymbolOrigin::Workspace);
+    assert_eq!(origin.label(), None);
+}
+
+#[test]
+fn without_roots_everything_but_system_headers_is_workspace() {
+    let uri = Url::parse("file:///anywhere/blur.metal").unwrap();
+    assert_eq!(SymbolOrigin::classify(&uri, &[]), SymbolOrigin::Workspace);
+}
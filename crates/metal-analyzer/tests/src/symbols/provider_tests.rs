@@ -0,0 +1,101 @@
+use std::{
+    fs,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::*;
+
+/// Create a unique temporary workspace root for each test.
+fn test_root() -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("symbol_provider_test_{}_{id}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+fn uris(
+    provider: &SymbolProvider,
+    name: &str,
+) -> Vec<Url> {
+    provider.index().get(name).into_iter().map(|loc| loc.uri).collect()
+}
+
+#[test]
+fn refresh_from_disk_rescans_only_changed_files() {
+    let root = test_root();
+    let path = root.join("common.h");
+    fs::write(&path, "struct Light {};\n").unwrap();
+    let uri = Url::from_file_path(&path).unwrap();
+    let provider = SymbolProvider::new();
+
+    assert!(provider.refresh_from_disk(&path));
+    assert!(!provider.refresh_from_disk(&path), "unchanged files are not parsed again");
+    assert_eq!(uris(&provider, "Light"), vec![uri.clone()]);
+
+    fs::write(&path, "struct Lamp {};\n").unwrap();
+    assert!(provider.refresh_from_disk(&path));
+    assert!(uris(&provider, "Light").is_empty());
+    assert_eq!(uris(&provider, "Lamp"), vec![uri]);
+
+    fs::remove_file(&path).unwrap();
+    assert!(!provider.refresh_from_disk(&path));
+    assert!(provider.index().map.is_empty());
+}
+
+#[test]
+fn snapshots_hold_disk_files_but_not_editor_buffers() {
+    let root = test_root();
+    let disk = root.join("blur.metal");
+    fs::write(&disk, "kernel void blur() {}\n").unwrap();
+    let provider = SymbolProvider::new();
+    provider.refresh_from_disk(&disk);
+    provider.scan_file(&Url::from_file_path(root.join("open.metal")).unwrap(), "kernel void edit() {}\n");
+
+    let files = provider.snapshot_files();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].stamp.path(), disk);
+    assert_eq!(files[0].symbols.iter().map(|symbol| symbol.name.as_str()).collect::<Vec<_>>(), vec!["blur"]);
+
+    let restored_into = SymbolProvider::new();
+    let restored = restored_into.restore_files(files, |_| true);
+    assert_eq!(restored, vec![disk.clone()]);
+    assert_eq!(uris(&restored_into, "blur"), vec![Url::from_file_path(&disk).unwrap()]);
+    assert!(!restored_into.refresh_from_disk(&disk), "restored files count as scanned");
+}
+
+#[test]
+fn rescanning_a_buffer_replaces_its_symbols() {
+    let provider = SymbolProvider::new();
+    let uri = Url::parse("file:///ws/a.metal").unwrap();
+    let other = Url::parse("file:///ws/b.metal").unwrap();
+    provider.scan_file(&uri, "struct Light {};\nkernel void shade() {}\n");
+    provider.scan_file(&other, "kernel void shade() {}\n");
+    provider.scan_file(&uri, "struct Lamp {};\n");
+
+    assert!(uris(&provider, "Light").is_empty());
+    assert_eq!(uris(&provider, "shade"), vec![other.clone()]);
+    provider.remove_file(&other);
+    assert_eq!(provider.index().map.len(), 1);
+}
+
+#[test]
+fn workspace_symbols_rank_the_requesting_document_first() {
+    let provider = SymbolProvider::new();
+    let first = Url::parse("file:///ws/a/blur.metal").unwrap();
+    let second = Url::parse("file:///ws/b/blur.metal").unwrap();
+    provider.scan_file(&first, "kernel void blur() {}\n");
+    provider.scan_file(&second, "kernel void blur() {}\n");
+
+    let filter = SymbolSearchFilter::everything();
+    let locations = |anchor: &Url| {
+        provider
+            .workspace_symbols("blur", Some(anchor), &filter)
+            .into_iter()
+            .map(|symbol| symbol.location.uri)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(locations(&first), vec![first.clone(), second.clone()]);
+    assert_eq!(locations(&second), vec![second, first]);
+}
@@ -0,0 +1,63 @@
+use std::{
+    fs,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use tower_lsp::lsp_types::Position;
+
+use super::*;
+
+/// Create a unique temporary workspace root for each test.
+fn test_root() -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("symbol_store_test_{}_{id}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+fn snapshot_file(
+    path: &Path,
+    content: &str,
+) -> SnapshotFile {
+    SnapshotFile {
+        stamp: FileStamp::of_content(path, content),
+        symbols: vec![StoredSymbol {
+            name: "blur".into(),
+            range: Range::new(Position::new(0, 12), Position::new(0, 16)),
+            kind: SymbolKind::FUNCTION,
+            container_name: None,
+        }],
+    }
+}
+
+#[test]
+fn loads_only_files_unchanged_since_saving() {
+    let root = test_root();
+    let blur = root.join("blur.metal");
+    let common = root.join("common.h");
+    fs::write(&blur, "kernel void blur() {}\n").unwrap();
+    fs::write(&common, "struct Light {};\n").unwrap();
+    let snapshot = root.join("snapshot/project.symbols.json");
+    let files = vec![snapshot_file(&blur, "kernel void blur() {}\n"), snapshot_file(&common, "struct Light {};\n")];
+    assert_eq!(save_to(&snapshot, files, std::slice::from_ref(&root)).unwrap(), 2);
+
+    fs::write(&common, "struct Light { float3 color; };\n").unwrap();
+    let loaded = load_from(&snapshot, std::slice::from_ref(&root));
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded[0].stamp.path(), blur);
+    assert_eq!(loaded[0].symbols[0].name, "blur");
+}
+
+#[test]
+fn snapshots_of_other_workspaces_are_ignored() {
+    let root = test_root();
+    let blur = root.join("blur.metal");
+    fs::write(&blur, "kernel void blur() {}\n").unwrap();
+    let snapshot = root.join("snapshot/project.symbols.json");
+    save_to(&snapshot, vec![snapshot_file(&blur, "kernel void blur() {}\n")], std::slice::from_ref(&root)).unwrap();
+
+    assert!(load_from(&snapshot, &[root.join("elsewhere")]).is_empty());
+    assert!(load_from(&root.join("missing.symbols.json"), std::slice::from_ref(&root)).is_empty());
+}
@@ -72,3 +72,65 @@ Root@0..29
 "#,
     );
 }
+
+#[test]
+fn test_struct_member_function() {
+    check(
+        "struct S { int f() {} };",
+        r#"
+Root@0..24
+  StructDef@0..24
+    KwStruct@0..6 "struct"
+    Whitespace@6..7 " "
+    Ident@7..8 "S"
+    Whitespace@8..9 " "
+    Block@9..23
+      LBrace@9..10 "{"
+      Whitespace@10..11 " "
+      FunctionDef@11..21
+        TypeRef@11..14
+          KwInt@11..14 "int"
+        Whitespace@14..15 " "
+        Ident@15..16 "f"
+        ParameterList@16..18
+          LParen@16..17 "("
+          RParen@17..18 ")"
+        Whitespace@18..19 " "
+        Block@19..21
+          LBrace@19..20 "{"
+          RBrace@20..21 "}"
+      Whitespace@21..22 " "
+      RBrace@22..23 "}"
+    Semicolon@23..24 ";"
+"#,
+    );
+}
+
+#[test]
+fn test_namespace_items() {
+    check(
+        "namespace n { struct T {}; }",
+        r#"
+Root@0..28
+  NamespaceDef@0..28
+    KwNamespace@0..9 "namespace"
+    Whitespace@9..10 " "
+    Ident@10..11 "n"
+    Whitespace@11..12 " "
+    Block@12..28
+      LBrace@12..13 "{"
+      Whitespace@13..14 " "
+      StructDef@14..26
+        KwStruct@14..20 "struct"
+        Whitespace@20..21 " "
+        Ident@21..22 "T"
+        Whitespace@22..23 " "
+        Block@23..25
+          LBrace@23..24 "{"
+          RBrace@24..25 "}"
+        Semicolon@25..26 ";"
+      Whitespace@26..27 " "
+      RBrace@27..28 "}"
+"#,
+    );
+}
@@ -30,3 +30,12 @@ fn navigation_word_falls_back_on_rvalue_reference() {
     let position = Position::new(0, 9);
     assert_eq!(navigation_word(source, position).as_deref(), Some("Foo"));
 }
+
+#[test]
+fn text_fallbacks_convert_columns_past_non_ascii_text() {
+    let source = "/* é😀 */ float value; [[buffer(0)]]";
+    assert_eq!(word_at_position_text_fallback(source, Position::new(0, 17)).as_deref(), Some("value"));
+    assert_eq!(word_at_position_text_fallback(source, Position::new(0, 40)), None);
+    assert_eq!(attribute_at_position_text_fallback(source, Position::new(0, 27)).as_deref(), Some("[[buffer(0)]]"));
+    assert_eq!(attribute_at_position_text_fallback(source, Position::new(0, 5)), None);
+}
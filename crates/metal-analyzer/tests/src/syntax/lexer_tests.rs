@@ -58,6 +58,21 @@ fn test_identifiers_and_literals() {
     );
 }
 
+#[test]
+fn test_float_suffixes() {
+    let tokens = lex("1.0h 2.5e-3f .5H");
+    assert_eq!(
+        tokens,
+        vec![
+            (SyntaxKind::Float, "1.0h"),
+            (SyntaxKind::Whitespace, " "),
+            (SyntaxKind::Float, "2.5e-3f"),
+            (SyntaxKind::Whitespace, " "),
+            (SyntaxKind::Float, ".5H"),
+        ]
+    );
+}
+
 #[test]
 fn test_preprocessor_tokens() {
     let input = "#include <metal_stdlib>";
@@ -0,0 +1,61 @@
+use tower_lsp::lsp_types::GeneralClientCapabilities;
+
+use super::*;
+
+fn offering(kinds: &[PositionEncodingKind]) -> ClientCapabilities {
+    ClientCapabilities {
+        general: Some(GeneralClientCapabilities {
+            position_encodings: Some(kinds.to_vec()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn negotiate_prefers_the_clients_first_supported_encoding() {
+    let custom = PositionEncodingKind::new("utf-7");
+    assert_eq!(
+        PositionEncoding::negotiate(&offering(&[custom, PositionEncodingKind::UTF8, PositionEncodingKind::UTF16])),
+        PositionEncoding::Utf8
+    );
+    assert_eq!(
+        PositionEncoding::negotiate(&offering(&[PositionEncodingKind::UTF32, PositionEncodingKind::UTF8])),
+        PositionEncoding::Utf32
+    );
+}
+
+#[test]
+fn negotiate_defaults_to_utf16() {
+    assert_eq!(PositionEncoding::negotiate(&ClientCapabilities::default()), PositionEncoding::Utf16);
+    assert_eq!(PositionEncoding::negotiate(&offering(&[])), PositionEncoding::Utf16);
+}
+
+#[test]
+fn widths_count_code_units() {
+    let line = "x = é + 😀;";
+    assert_eq!(PositionEncoding::Utf8.len(line), 14);
+    assert_eq!(PositionEncoding::Utf16.len(line), 11);
+    assert_eq!(PositionEncoding::Utf32.len(line), 10);
+    assert_eq!(PositionEncoding::Utf16.width('😀'), 2);
+    assert_eq!(PositionEncoding::Utf32.width('😀'), 1);
+}
+
+#[test]
+fn columns_and_byte_offsets_round_trip() {
+    let line = "// é 😀 kernel";
+    let kernel = line.find("kernel").unwrap();
+    for (encoding, column) in [(PositionEncoding::Utf8, 11), (PositionEncoding::Utf16, 8), (PositionEncoding::Utf32, 7)]
+    {
+        assert_eq!(encoding.column(line, kernel), column);
+        assert_eq!(encoding.byte_offset(line, column), kernel);
+    }
+}
+
+#[test]
+fn columns_inside_a_character_move_past_it() {
+    let line = "a😀b";
+    assert_eq!(PositionEncoding::Utf16.byte_offset(line, 2), 5);
+    assert_eq!(PositionEncoding::Utf16.column(line, 3), 1);
+    assert_eq!(PositionEncoding::Utf16.byte_offset(line, 99), line.len());
+}
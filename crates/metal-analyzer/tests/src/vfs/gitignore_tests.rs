@@ -0,0 +1,77 @@
+use super::*;
+
+fn workspace(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("gitignore_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    root
+}
+
+#[test]
+fn rules_parse_negations_anchors_and_directories() {
+    assert_eq!(
+        parse_rules("# build output\n\n/build/\n!keep.metal\n*.gen.metal  \nshaders/**/tmp\n\\#literal\n"),
+        vec![
+            Rule {
+                segments: vec!["build".into()],
+                anchored: true,
+                negated: false,
+                directory_only: true,
+            },
+            Rule {
+                segments: vec!["keep.metal".into()],
+                anchored: false,
+                negated: true,
+                directory_only: false,
+            },
+            Rule {
+                segments: vec!["*.gen.metal".into()],
+                anchored: false,
+                negated: false,
+                directory_only: false,
+            },
+            Rule {
+                segments: vec!["shaders".into(), "**".into(), "tmp".into()],
+                anchored: true,
+                negated: false,
+                directory_only: false,
+            },
+            Rule {
+                segments: vec!["#literal".into()],
+                anchored: false,
+                negated: false,
+                directory_only: false,
+            },
+        ]
+    );
+}
+
+#[test]
+fn deeper_files_and_later_rules_win() {
+    let root = workspace("precedence");
+    std::fs::create_dir_all(root.join("app/generated")).unwrap();
+    std::fs::write(root.join(".gitignore"), "*.gen.metal\n/out/\nvendor/\n").unwrap();
+    std::fs::write(root.join("app/.gitignore"), "!keep.gen.metal\n/generated\n").unwrap();
+    let mut gitignore = Gitignore::new(std::slice::from_ref(&root));
+
+    assert!(gitignore.is_ignored(&root.join("blur.gen.metal"), false));
+    assert!(gitignore.is_ignored(&root.join("app/post/blur.gen.metal"), false));
+    assert!(!gitignore.is_ignored(&root.join("app/keep.gen.metal"), false));
+    assert!(gitignore.is_ignored(&root.join("keep.gen.metal"), false));
+    assert!(!gitignore.is_ignored(&root.join("app/blur.metal"), false));
+
+    // Anchored at the root, and only directories.
+    assert!(gitignore.is_ignored(&root.join("out"), true));
+    assert!(!gitignore.is_ignored(&root.join("out"), false));
+    assert!(!gitignore.is_ignored(&root.join("app/out/a.metal"), false));
+
+    // Everything below an ignored directory stays ignored.
+    assert!(gitignore.is_ignored(&root.join("app/generated/a.metal"), false));
+    assert!(gitignore.is_ignored(&root.join("third_party/vendor/lib/a.metal"), false));
+    assert!(!gitignore.is_ignored_entry(&root.join("third_party/vendor/lib/a.metal"), false));
+    assert!(gitignore.is_ignored_entry(&root.join("third_party/vendor"), true));
+
+    // Paths outside the workspace are never ignored.
+    assert!(!gitignore.is_ignored(Path::new("/elsewhere/blur.gen.metal"), false));
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,49 @@
+use super::*;
+
+fn exclusions(patterns: &[&str]) -> PathExclusions {
+    let roots = [PathBuf::from("/tmp/ws-a"), PathBuf::from("/tmp/ws-b")];
+    PathExclusions::new(&roots, &patterns.iter().map(|pattern| pattern.to_string()).collect::<Vec<_>>())
+}
+
+#[test]
+fn plain_paths_exclude_their_descendants_under_every_root() {
+    let excluded = exclusions(&["external/vendor-shaders", "/opt/generated"]);
+    assert!(excluded.is_excluded(Path::new("/tmp/ws-a/external/vendor-shaders/shaders/kernel.metal")));
+    assert!(excluded.is_excluded(Path::new("/tmp/ws-b/external/vendor-shaders/kernel.metal")));
+    assert!(excluded.is_excluded(Path::new("/opt/generated/kernel.metal")));
+    assert!(!excluded.is_excluded(Path::new("/tmp/ws-a/crates/app/kernel.metal")));
+    assert!(!excluded.is_excluded(Path::new("/tmp/ws-a/external/vendor-shaders-extra/kernel.metal")));
+}
+
+#[test]
+fn globs_without_a_slash_match_names_at_any_depth() {
+    let excluded = exclusions(&["*.gen.metal", "generated/"]);
+    assert!(excluded.is_excluded(Path::new("/tmp/ws-a/blur.gen.metal")));
+    assert!(excluded.is_excluded(Path::new("/tmp/ws-b/shaders/post/blur.gen.metal")));
+    assert!(!excluded.is_excluded(Path::new("/tmp/ws-a/shaders/blur.metal")));
+    // Wildcard-free patterns ending in `/` stay prefixes from the roots.
+    assert!(excluded.is_excluded(Path::new("/tmp/ws-a/generated/a.metal")));
+    assert!(!excluded.is_excluded(Path::new("/tmp/ws-a/shaders/generated/a.metal")));
+}
+
+#[test]
+fn globs_with_a_slash_are_anchored_at_the_workspace_roots() {
+    let excluded = exclusions(&["**/generated/**", "shaders/*/legacy?.metal"]);
+    assert!(excluded.is_excluded(Path::new("/tmp/ws-a/generated/a.metal")));
+    assert!(excluded.is_excluded(Path::new("/tmp/ws-b/app/generated/deep/a.metal")));
+    assert!(excluded.is_excluded(Path::new("/tmp/ws-a/shaders/post/legacy1.metal")));
+    assert!(!excluded.is_excluded(Path::new("/tmp/ws-a/app/shaders/post/legacy1.metal")));
+    assert!(!excluded.is_excluded(Path::new("/tmp/ws-a/shaders/post/legacy10.metal")));
+    // Outside every root, only absolute globs apply.
+    assert!(!excluded.is_excluded(Path::new("/elsewhere/generated/a.metal")));
+    assert!(exclusions(&["/elsewhere/**/*.metal"]).is_excluded(Path::new("/elsewhere/generated/a.metal")));
+}
+
+#[test]
+fn matched_directories_exclude_everything_below_them() {
+    let excluded = exclusions(&["vendor*"]);
+    assert!(excluded.is_excluded(Path::new("/tmp/ws-a/third_party/vendor-shaders")));
+    assert!(excluded.is_excluded(Path::new("/tmp/ws-a/third_party/vendor-shaders/kernel.metal")));
+    assert!(exclusions(&[]).is_empty());
+    assert!(!exclusions(&[" "]).is_excluded(Path::new("/tmp/ws-a/kernel.metal")));
+}
@@ -0,0 +1,42 @@
+use super::*;
+
+#[test]
+fn fold_case_lowercases_every_component() {
+    assert_eq!(fold_case(Path::new("/Users/Dev/Shaders/Common.H")), PathBuf::from("/users/dev/shaders/common.h"));
+}
+
+#[test]
+fn path_key_folds_case_only_where_paths_are_case_insensitive() {
+    let path = Path::new("/nonexistent/Shaders/Common.h");
+    let expected = if CASE_INSENSITIVE_PATHS {
+        fold_case(path)
+    } else {
+        path.to_path_buf()
+    };
+    assert_eq!(path_key(path), expected);
+}
+
+#[test]
+fn file_ids_differing_in_case_match_only_on_case_insensitive_platforms() {
+    let upper = FileId::from_path(Path::new("/nonexistent/Shaders/Common.h"));
+    let lower = FileId::from_path(Path::new("/nonexistent/shaders/common.h"));
+    assert_eq!(upper == lower, CASE_INSENSITIVE_PATHS);
+    assert_eq!(upper.cmp(&lower) == std::cmp::Ordering::Equal, CASE_INSENSITIVE_PATHS);
+}
+
+#[test]
+fn file_id_keeps_original_spelling_for_display() {
+    let id = FileId::from_path(Path::new("/nonexistent/Shaders/Common.h"));
+    assert_eq!(id.as_str(), "/nonexistent/Shaders/Common.h");
+    assert_eq!(id.to_string(), "/nonexistent/Shaders/Common.h");
+}
+
+#[test]
+fn file_id_serializes_as_its_path() {
+    let id = FileId::from_path(Path::new("/nonexistent/Shaders/Common.h"));
+    let json = serde_json::to_string(&id).unwrap();
+    assert_eq!(json, "\"/nonexistent/Shaders/Common.h\"");
+    let restored: FileId = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, id);
+    assert_eq!(restored.as_str(), id.as_str());
+}
@@ -0,0 +1,28 @@
+use super::*;
+
+#[test]
+fn untitled_documents_map_to_a_stand_in_path_and_back() {
+    let uri = Url::parse("untitled:Untitled-1").unwrap();
+    let path = document_path(&uri).unwrap();
+
+    assert!(path.starts_with(std::env::temp_dir().join(VIRTUAL_DIR_NAME)));
+    assert_eq!(path.file_name().unwrap(), "Untitled-1.metal");
+    assert_eq!(document_path(&uri).unwrap(), path);
+    assert_eq!(document_uri(&path), Some(uri));
+
+    let other = document_path(&Url::parse("untitled:Untitled-2").unwrap()).unwrap();
+    assert_ne!(other.parent(), path.parent());
+}
+
+#[test]
+fn virtual_headers_keep_their_extension() {
+    let uri = Url::parse("vscode-vfs://github/org/repo/Shaders/common.h").unwrap();
+    assert_eq!(document_path(&uri).unwrap().file_name().unwrap(), "common.h");
+}
+
+#[test]
+fn file_documents_use_their_own_path() {
+    let uri = Url::parse("file:///tmp/shaders/blur.metal").unwrap();
+    assert_eq!(document_path(&uri), Some(PathBuf::from("/tmp/shaders/blur.metal")));
+    assert_eq!(document_uri(Path::new("/tmp/shaders/blur.metal")), Some(uri));
+}
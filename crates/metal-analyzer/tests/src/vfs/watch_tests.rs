@@ -0,0 +1,58 @@
+use std::{
+    fs,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime},
+};
+
+use super::*;
+
+/// Create a unique temporary directory for each test.
+fn test_dir() -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("watch_test_{}_{id}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn set_mtime(
+    path: &Path,
+    time: SystemTime,
+) {
+    fs::File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+}
+
+#[test]
+fn missing_paths_are_not_recorded() {
+    let dir = test_dir();
+    fs::write(dir.join("a.metal"), "").unwrap();
+
+    let snapshot = FileSnapshot::capture([dir.join("a.metal"), dir.join("missing.metal")]);
+    assert_eq!(snapshot.len(), 1);
+}
+
+#[test]
+fn reports_created_modified_and_removed_files() {
+    let dir = test_dir();
+    let (kept, edited, deleted, added) =
+        (dir.join("kept.metal"), dir.join("edited.metal"), dir.join("deleted.h"), dir.join("added.metal"));
+    let epoch = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+    for path in [&kept, &edited, &deleted] {
+        fs::write(path, "").unwrap();
+        set_mtime(path, epoch);
+    }
+    let paths = [kept.clone(), edited.clone(), deleted.clone(), added.clone()];
+    let before = FileSnapshot::capture(paths.clone());
+
+    set_mtime(&edited, epoch + Duration::from_secs(5));
+    fs::remove_file(&deleted).unwrap();
+    fs::write(&added, "").unwrap();
+    let after = FileSnapshot::capture(paths);
+
+    assert_eq!(
+        after.changes_since(&before),
+        vec![FileChange::Created(added), FileChange::Modified(edited), FileChange::Removed(deleted)]
+    );
+    assert!(after.changes_since(&after).is_empty());
+}
@@ -69,3 +69,50 @@ float4 transform(float4 pos, float scale) {
     assert!(!names.contains(&"pos"), "should NOT extract parameter name 'pos' as a symbol, got: {names:?}");
     assert!(!names.contains(&"scale"), "should NOT extract parameter name 'scale' as a symbol, got: {names:?}");
 }
+
+#[test]
+fn outline_nests_members_under_their_containers() {
+    let src = r#"
+namespace shading {
+struct Light {
+    float3 color;
+    Light() {}
+    float intensity(float d) const { return 1.0 / d; }
+};
+}
+kernel void shade(device float* out [[buffer(0)]], uint id [[thread_position_in_grid]]) {}
+"#;
+    let provider = SymbolProvider::new();
+    let symbols = provider.extract_symbols(src);
+    let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["shading", "shade"]);
+
+    let namespace = &symbols[0];
+    assert_eq!(namespace.kind, SymbolKind::NAMESPACE);
+    let light = &namespace.children.as_ref().expect("namespace children")[0];
+    assert_eq!(light.name, "Light");
+    let members: Vec<(&str, SymbolKind)> =
+        light.children.as_ref().expect("struct children").iter().map(|s| (s.name.as_str(), s.kind)).collect();
+    assert_eq!(
+        members,
+        vec![("color", SymbolKind::FIELD), ("Light", SymbolKind::CONSTRUCTOR), ("intensity", SymbolKind::METHOD)]
+    );
+
+    let kernel = &symbols[1];
+    let params: Vec<(&str, Option<&str>)> = kernel
+        .children
+        .as_ref()
+        .expect("kernel parameters")
+        .iter()
+        .map(|s| (s.name.as_str(), s.detail.as_deref()))
+        .collect();
+    assert_eq!(params, vec![("out", Some("[[buffer(0)]]")), ("id", Some("[[thread_position_in_grid]]"))]);
+    assert!(kernel.range.start.line <= kernel.selection_range.start.line);
+}
+
+#[test]
+fn quick_definition_looks_through_namespaces() {
+    let src = "namespace a { float helper(float x) { return x; } }";
+    let provider = SymbolProvider::new();
+    assert!(provider.quick_definition(src, "helper").is_some());
+}
@@ -1,5 +1,6 @@
 import * as vscode from "vscode";
 import {
+  DidChangeConfigurationNotification,
   LanguageClient,
   LanguageClientOptions,
   ServerOptions,
@@ -35,6 +36,72 @@ type GithubRelease = {
   assets: GithubReleaseAsset[];
 };
 
+const AST_CACHE_SCHEME = "metal-analyzer-cache";
+const PREPROCESSED_SCHEME = "metal-analyzer-preprocessed";
+
+type LspPosition = { line: number; character: number };
+
+type AstCacheViewDocument = {
+  uri: string;
+  content: string;
+  links: { range: { start: LspPosition; end: LspPosition }; target?: string }[];
+};
+
+type CompiledBinaryReport = {
+  success: boolean;
+  air: { path: string; size: number } | null;
+  metallib: { path: string; size: number } | null;
+  entryPoints: { name: string; stage: string }[];
+  diagnostics: {
+    location: { uri: string; range: { start: LspPosition } };
+    severity: number;
+    message: string;
+  }[];
+};
+
+type EntryPointInfo = {
+  name: string;
+  stage: string;
+  location: { uri: string; range: { start: LspPosition; end: LspPosition } };
+  attributes: string[];
+  bindings: { kind: string; index: number; parameter: string | null }[];
+  pipelines: string[];
+};
+
+const astCacheViews = new Map<string, AstCacheViewDocument>();
+const astCacheViewChanged = new vscode.EventEmitter<vscode.Uri>();
+
+type PreprocessedDocument = {
+  text: string;
+  lineMap: ({ uri: string; line: number } | null)[];
+};
+
+const preprocessedViews = new Map<string, PreprocessedDocument>();
+const preprocessedViewChanged = new vscode.EventEmitter<vscode.Uri>();
+
+type InactiveRegionsParams = {
+  textDocument: { uri: string; version: number };
+  regions: { start: LspPosition; end: LspPosition }[];
+};
+
+// Code disabled by `#if`/`#ifdef` under the effective compiler defines, as
+// last pushed by the server for each document.
+const inactiveRegions = new Map<string, vscode.Range[]>();
+const inactiveRegionDecoration = vscode.window.createTextEditorDecorationType({
+  opacity: "0.5",
+});
+
+type StatusChangedParams = {
+  health: "ok" | "warning" | "error";
+  quiescent: boolean;
+  message: string | null;
+};
+
+// Server health as last pushed through `metal-analyzer/statusChanged`.
+const statusBarItem = vscode.window.createStatusBarItem(
+  vscode.StatusBarAlignment.Left,
+);
+
 export async function activate(context: vscode.ExtensionContext) {
   isDeactivating = false;
   isRestartingClient = false;
@@ -61,8 +128,43 @@ export async function activate(context: vscode.ExtensionContext) {
     vscode.commands.registerCommand("metal-analyzer.serverVersion", () => {
       return showServerVersion();
     }),
+    vscode.commands.registerCommand("metal-analyzer.showAstCache", () => {
+      return showAstCache();
+    }),
+    vscode.commands.registerCommand("metal-analyzer.rebuildFile", () => {
+      return rebuildFile();
+    }),
+    vscode.commands.registerCommand("metal-analyzer.reindexWorkspace", () => {
+      return reindexWorkspace();
+    }),
+    vscode.commands.registerCommand("metal-analyzer.clearCache", () => {
+      return clearCache();
+    }),
+    vscode.commands.registerCommand("metal-analyzer.showStatus", () => {
+      return showStatus();
+    }),
+    vscode.commands.registerCommand("metal-analyzer.showPerf", () => {
+      return showPerf();
+    }),
+    vscode.commands.registerCommand("metal-analyzer.showPreprocessed", () => {
+      return showPreprocessed();
+    }),
+    vscode.commands.registerCommand("metal-analyzer.captureState", () => {
+      return captureState();
+    }),
+    vscode.commands.registerCommand("metal-analyzer.buildShader", () => {
+      return buildShader();
+    }),
+    vscode.commands.registerCommand("metal-analyzer.goToEntryPoint", () => {
+      return goToEntryPoint();
+    }),
   );
 
+  registerAstCacheViewProviders(context);
+  registerPreprocessedViewProviders(context);
+  registerInactiveRegionDecorations(context);
+  registerStatusBarItem(context);
+
   context.subscriptions.push(
     vscode.workspace.onDidChangeConfiguration((event) => {
       const requiresRestart =
@@ -72,6 +174,12 @@ export async function activate(context: vscode.ExtensionContext) {
           "metal-analyzer.threadPool.formattingThreads",
         );
       if (!requiresRestart) {
+        if (event.affectsConfiguration("metal-analyzer")) {
+          void client?.sendNotification(
+            DidChangeConfigurationNotification.type,
+            { settings: buildServerInitializationOptions() },
+          );
+        }
         return;
       }
 
@@ -191,6 +299,462 @@ function showServerVersion(): void {
   void vscode.window.showInformationMessage(`${name} v${version}`);
 }
 
+async function showAstCache(): Promise<void> {
+  const editor = vscode.window.activeTextEditor;
+  if (!client || client.state !== State.Running || !editor) {
+    void vscode.window.showInformationMessage(
+      "metal-analyzer: open a Metal file with the server running",
+    );
+    return;
+  }
+
+  const view = await client.sendRequest<AstCacheViewDocument | null>(
+    "metal-analyzer/astCacheView",
+    { textDocument: { uri: editor.document.uri.toString() } },
+  );
+  if (!view) {
+    void vscode.window.showInformationMessage(
+      "metal-analyzer: no cached AST index for this file yet",
+    );
+    return;
+  }
+
+  const uri = vscode.Uri.parse(view.uri);
+  astCacheViews.set(uri.toString(), view);
+  astCacheViewChanged.fire(uri);
+  const document = await vscode.workspace.openTextDocument(uri);
+  await vscode.window.showTextDocument(document, {
+    preview: true,
+    viewColumn: vscode.ViewColumn.Beside,
+  });
+}
+
+async function showPreprocessed(): Promise<void> {
+  const editor = vscode.window.activeTextEditor;
+  if (!client || client.state !== State.Running || !editor) {
+    void vscode.window.showInformationMessage(
+      "metal-analyzer: open a Metal file with the server running",
+    );
+    return;
+  }
+
+  const source = editor.document.uri;
+  const entryPoints = await client.sendRequest<EntryPointInfo[] | null>(
+    "metal-analyzer/entryPoints",
+    { textDocument: { uri: source.toString() } },
+  );
+  let entryPoint: string | undefined;
+  if (entryPoints?.length) {
+    const picked = await vscode.window.showQuickPick(
+      [
+        { label: "Whole file", entryPoint: undefined },
+        ...entryPoints.map((entry) => ({
+          label: entry.name,
+          description: entry.stage,
+          entryPoint: entry.name,
+        })),
+      ],
+      { placeHolder: "Preprocess the whole file or what one entry point uses" },
+    );
+    if (!picked) {
+      return;
+    }
+    entryPoint = picked.entryPoint;
+  }
+
+  let preprocessed: PreprocessedDocument | null;
+  try {
+    preprocessed = await client.sendRequest<PreprocessedDocument | null>(
+      "metal-analyzer/preprocess",
+      { textDocument: { uri: source.toString() }, entryPoint },
+    );
+  } catch (error) {
+    void vscode.window.showErrorMessage(`metal-analyzer: ${String(error)}`);
+    return;
+  }
+  if (!preprocessed) {
+    return;
+  }
+
+  const name = path.posix.basename(source.path).replace(/\.metal$/, "");
+  const uri = vscode.Uri.from({
+    scheme: PREPROCESSED_SCHEME,
+    path: `/${name}${entryPoint ? `.${entryPoint}` : ""}.preprocessed.metal`,
+    query: source.toString(),
+  });
+  preprocessedViews.set(uri.toString(), preprocessed);
+  preprocessedViewChanged.fire(uri);
+  const document = await vscode.workspace.openTextDocument(uri);
+  await vscode.window.showTextDocument(document, {
+    preview: true,
+    viewColumn: vscode.ViewColumn.Beside,
+  });
+}
+
+async function rebuildFile(): Promise<void> {
+  const editor = vscode.window.activeTextEditor;
+  if (!client || client.state !== State.Running || !editor) {
+    void vscode.window.showInformationMessage(
+      "metal-analyzer: open a Metal file with the server running",
+    );
+    return;
+  }
+
+  const rebuilt = await client.sendRequest<boolean>(
+    "metal-analyzer/rebuildFile",
+    { textDocument: { uri: editor.document.uri.toString() } },
+  );
+  if (!rebuilt) {
+    void vscode.window.showInformationMessage(
+      "metal-analyzer: this file is not open in the server",
+    );
+  }
+}
+
+async function reindexWorkspace(): Promise<void> {
+  if (!client || client.state !== State.Running) {
+    void vscode.window.showInformationMessage(
+      "metal-analyzer: the server is not running",
+    );
+    return;
+  }
+
+  const indexed = await client.sendRequest<number>(
+    "metal-analyzer/reindex",
+    {},
+  );
+  void vscode.window.showInformationMessage(
+    `metal-analyzer: indexed ${indexed} file(s)`,
+  );
+}
+
+async function clearCache(): Promise<void> {
+  if (!client || client.state !== State.Running) {
+    void vscode.window.showInformationMessage(
+      "metal-analyzer: the server is not running",
+    );
+    return;
+  }
+
+  const removed = await client.sendRequest<number>(
+    "metal-analyzer/clearCache",
+  );
+  void vscode.window.showInformationMessage(
+    `metal-analyzer: removed ${removed} cached index file(s)`,
+  );
+}
+
+async function showStatus(): Promise<void> {
+  if (!client || client.state !== State.Running) {
+    void vscode.window.showInformationMessage(
+      "metal-analyzer: the server is not running",
+    );
+    return;
+  }
+
+  const status = await client.sendRequest<unknown>("metal-analyzer/status");
+  client.outputChannel.appendLine(
+    `Server status: ${JSON.stringify(status, null, 2)}`,
+  );
+  client.outputChannel.show();
+}
+
+async function showPerf(): Promise<void> {
+  if (!client || client.state !== State.Running) {
+    void vscode.window.showInformationMessage(
+      "metal-analyzer: the server is not running",
+    );
+    return;
+  }
+
+  const report = await client.sendRequest<unknown>("metal-analyzer/perf", {});
+  client.outputChannel.appendLine(
+    `Performance report: ${JSON.stringify(report, null, 2)}`,
+  );
+  client.outputChannel.show();
+}
+
+async function captureState(): Promise<void> {
+  if (!client || client.state !== State.Running) {
+    void vscode.window.showInformationMessage(
+      "metal-analyzer: the server is not running",
+    );
+    return;
+  }
+
+  let captured: { path: string };
+  try {
+    captured = await client.sendRequest<{ path: string }>(
+      "metal-analyzer/captureState",
+    );
+  } catch (error) {
+    void vscode.window.showErrorMessage(`metal-analyzer: ${String(error)}`);
+    return;
+  }
+  const reveal = "Reveal in File Explorer";
+  const choice = await vscode.window.showInformationMessage(
+    `metal-analyzer: state snapshot written to ${captured.path}`,
+    reveal,
+  );
+  if (choice === reveal) {
+    await vscode.commands.executeCommand(
+      "revealFileInOS",
+      vscode.Uri.file(captured.path),
+    );
+  }
+}
+
+async function buildShader(): Promise<void> {
+  const editor = vscode.window.activeTextEditor;
+  if (!client || client.state !== State.Running || !editor) {
+    void vscode.window.showInformationMessage(
+      "metal-analyzer: open a Metal file with the server running",
+    );
+    return;
+  }
+
+  let report: CompiledBinaryReport | null;
+  try {
+    report = await client.sendRequest<CompiledBinaryReport | null>(
+      "metal-analyzer/compileBinary",
+      { textDocument: { uri: editor.document.uri.toString() } },
+    );
+  } catch (error) {
+    void vscode.window.showErrorMessage(`metal-analyzer: ${String(error)}`);
+    return;
+  }
+  if (!report) {
+    void vscode.window.showInformationMessage(
+      "metal-analyzer: this file is not open in the server",
+    );
+    return;
+  }
+
+  const output = client.outputChannel;
+  for (const diagnostic of report.diagnostics) {
+    const { line, character } = diagnostic.location.range.start;
+    const severity = diagnostic.severity === 1 ? "error" : "warning";
+    output.appendLine(
+      `${vscode.Uri.parse(diagnostic.location.uri).fsPath}:${line + 1}:${character + 1}: ${severity}: ${diagnostic.message}`,
+    );
+  }
+  if (!report.success || !report.metallib) {
+    output.show(true);
+    void vscode.window.showErrorMessage(
+      "metal-analyzer: the shader failed to build; see the output for details",
+    );
+    return;
+  }
+  const kilobytes = (report.metallib.size / 1024).toFixed(1);
+  const entryPoints = report.entryPoints.map((entry) => entry.name).join(", ");
+  output.appendLine(`Built ${report.metallib.path} (${kilobytes} KB)`);
+  void vscode.window.showInformationMessage(
+    `metal-analyzer: built ${kilobytes} KB metallib` +
+      (entryPoints ? ` with ${entryPoints}` : "") +
+      (report.diagnostics.length ? `, ${report.diagnostics.length} warning(s)` : ""),
+  );
+}
+
+async function goToEntryPoint(): Promise<void> {
+  if (!client || client.state !== State.Running) {
+    void vscode.window.showInformationMessage(
+      "metal-analyzer: server is not running",
+    );
+    return;
+  }
+
+  const entryPoints = await client.sendRequest<EntryPointInfo[] | null>(
+    "metal-analyzer/entryPoints",
+    {},
+  );
+  if (!entryPoints?.length) {
+    void vscode.window.showInformationMessage(
+      "metal-analyzer: no entry points found in the workspace",
+    );
+    return;
+  }
+
+  const picked = await vscode.window.showQuickPick(
+    entryPoints.map((entry) => ({
+      label: entry.name,
+      description: entry.stage,
+      detail:
+        vscode.workspace.asRelativePath(
+          vscode.Uri.parse(entry.location.uri),
+        ) +
+        (entry.pipelines.length ? ` · ${entry.pipelines.join(", ")}` : ""),
+      entry,
+    })),
+    { matchOnDescription: true, matchOnDetail: true },
+  );
+  if (!picked) {
+    return;
+  }
+  const { uri, range } = picked.entry.location;
+  await vscode.window.showTextDocument(vscode.Uri.parse(uri), {
+    selection: new vscode.Range(
+      range.start.line,
+      range.start.character,
+      range.end.line,
+      range.end.character,
+    ),
+  });
+}
+
+// The cache views are virtual documents: their text is kept client-side and
+// hover/definition requests are forwarded to the server, which remembers the
+// rendered view.
+function registerAstCacheViewProviders(
+  context: vscode.ExtensionContext,
+): void {
+  const selector = { scheme: AST_CACHE_SCHEME };
+  context.subscriptions.push(
+    vscode.workspace.registerTextDocumentContentProvider(AST_CACHE_SCHEME, {
+      onDidChange: astCacheViewChanged.event,
+      provideTextDocumentContent: (uri) =>
+        astCacheViews.get(uri.toString())?.content ?? "",
+    }),
+    vscode.languages.registerDocumentLinkProvider(selector, {
+      provideDocumentLinks: (document) =>
+        (astCacheViews.get(document.uri.toString())?.links ?? []).map(
+          (link) =>
+            new vscode.DocumentLink(
+              new vscode.Range(
+                link.range.start.line,
+                link.range.start.character,
+                link.range.end.line,
+                link.range.end.character,
+              ),
+              link.target ? vscode.Uri.parse(link.target) : undefined,
+            ),
+        ),
+    }),
+    vscode.languages.registerHoverProvider(selector, {
+      provideHover: async (document, position) => {
+        if (!client || client.state !== State.Running) {
+          return undefined;
+        }
+        const hover = await client.sendRequest("textDocument/hover", {
+          textDocument: { uri: document.uri.toString() },
+          position,
+        });
+        return client.protocol2CodeConverter.asHover(hover as never);
+      },
+    }),
+    vscode.languages.registerDefinitionProvider(selector, {
+      provideDefinition: async (document, position) => {
+        if (!client || client.state !== State.Running) {
+          return undefined;
+        }
+        const definition = await client.sendRequest("textDocument/definition", {
+          textDocument: { uri: document.uri.toString() },
+          position,
+        });
+        return client.protocol2CodeConverter.asDefinitionResult(
+          definition as never,
+        );
+      },
+    }),
+  );
+}
+
+// Preprocessed views are virtual documents kept client-side. Go to
+// definition on any line jumps to the source line it came from.
+function registerPreprocessedViewProviders(
+  context: vscode.ExtensionContext,
+): void {
+  context.subscriptions.push(
+    vscode.workspace.registerTextDocumentContentProvider(PREPROCESSED_SCHEME, {
+      onDidChange: preprocessedViewChanged.event,
+      provideTextDocumentContent: (uri) =>
+        preprocessedViews.get(uri.toString())?.text ?? "",
+    }),
+    vscode.languages.registerDefinitionProvider(
+      { scheme: PREPROCESSED_SCHEME },
+      {
+        provideDefinition: (document, position) => {
+          const origin = preprocessedViews.get(document.uri.toString())
+            ?.lineMap[position.line];
+          if (!origin) {
+            return undefined;
+          }
+          return new vscode.Location(
+            vscode.Uri.parse(origin.uri),
+            new vscode.Position(origin.line, 0),
+          );
+        },
+      },
+    ),
+  );
+}
+
+function registerInactiveRegionDecorations(
+  context: vscode.ExtensionContext,
+): void {
+  context.subscriptions.push(
+    inactiveRegionDecoration,
+    vscode.window.onDidChangeVisibleTextEditors((editors) => {
+      editors.forEach(applyInactiveRegions);
+    }),
+    vscode.workspace.onDidCloseTextDocument((document) => {
+      inactiveRegions.delete(document.uri.toString());
+    }),
+  );
+}
+
+function applyInactiveRegions(editor: vscode.TextEditor): void {
+  const regions = inactiveRegions.get(editor.document.uri.toString());
+  if (regions) {
+    editor.setDecorations(inactiveRegionDecoration, regions);
+  }
+}
+
+function handleInactiveRegions(params: InactiveRegionsParams): void {
+  const uri = vscode.Uri.parse(params.textDocument.uri).toString();
+  inactiveRegions.set(
+    uri,
+    params.regions.map(
+      (region) =>
+        new vscode.Range(
+          region.start.line,
+          region.start.character,
+          region.end.line,
+          region.end.character,
+        ),
+    ),
+  );
+  for (const editor of vscode.window.visibleTextEditors) {
+    if (editor.document.uri.toString() === uri) {
+      applyInactiveRegions(editor);
+    }
+  }
+}
+
+function registerStatusBarItem(context: vscode.ExtensionContext): void {
+  statusBarItem.command = "metal-analyzer.showStatus";
+  handleStatusChanged({ health: "ok", quiescent: true, message: null });
+  statusBarItem.show();
+  context.subscriptions.push(statusBarItem);
+}
+
+function handleStatusChanged(params: StatusChangedParams): void {
+  const icon = !params.quiescent
+    ? "$(sync~spin)"
+    : params.health === "error"
+      ? "$(error)"
+      : params.health === "warning"
+        ? "$(warning)"
+        : "$(check)";
+  statusBarItem.text = `${icon} metal-analyzer`;
+  statusBarItem.tooltip = params.message ?? "metal-analyzer is running";
+  statusBarItem.backgroundColor =
+    params.health === "error"
+      ? new vscode.ThemeColor("statusBarItem.errorBackground")
+      : params.health === "warning"
+        ? new vscode.ThemeColor("statusBarItem.warningBackground")
+        : undefined;
+}
+
 function createLanguageClient(serverPath: string): LanguageClient {
   const initializationOptions = buildServerInitializationOptions();
   const serverOptions: ServerOptions = {
@@ -199,10 +763,31 @@ function createLanguageClient(serverPath: string): LanguageClient {
   };
 
   const clientOptions: LanguageClientOptions = {
-    documentSelector: [{ scheme: "file", language: "metal" }],
+    documentSelector: [
+      { scheme: "file", language: "metal" },
+      { scheme: "untitled", language: "metal" },
+    ],
     initializationOptions,
-    synchronize: {
-      configurationSection: "metal-analyzer",
+    middleware: {
+      workspace: {
+        // Answer the server's per-folder settings requests like the
+        // initialization options, with only the settings configured for
+        // that folder.
+        configuration: (params, token, next) => {
+          const perFolder = params.items.every(
+            (item) => item.section === "metal-analyzer" && item.scopeUri,
+          );
+          if (!perFolder) {
+            return next(params, token);
+          }
+          return params.items.map(
+            (item) =>
+              buildServerInitializationOptions(
+                vscode.Uri.parse(item.scopeUri as string),
+              )["metal-analyzer"] as never,
+          );
+        },
+      },
     },
     outputChannelName: "metal-analyzer",
     traceOutputChannel: vscode.window.createOutputChannel(
@@ -210,60 +795,207 @@ function createLanguageClient(serverPath: string): LanguageClient {
     ),
   };
 
-  return new LanguageClient(
+  const languageClient = new LanguageClient(
     "metal-analyzer",
     "metal-analyzer",
     serverOptions,
     clientOptions,
   );
+  // Tell the server we grey out inactive preprocessor regions and show its
+  // status in the status bar.
+  languageClient.registerFeature({
+    fillClientCapabilities: (capabilities) => {
+      capabilities.experimental = {
+        ...capabilities.experimental,
+        inactiveRegions: true,
+        statusChanged: true,
+      };
+    },
+    initialize: () => {},
+    getState: () => ({ kind: "static" }),
+    clear: () => {},
+  });
+  languageClient.onNotification(
+    "metal-analyzer/inactiveRegions",
+    handleInactiveRegions,
+  );
+  languageClient.onNotification(
+    "metal-analyzer/statusChanged",
+    handleStatusChanged,
+  );
+  return languageClient;
 }
 
-function buildServerInitializationOptions(): Record<string, unknown> {
-  const config = vscode.workspace.getConfiguration("metal-analyzer");
+// Only settings the user configured are forwarded; unset ones are sent as
+// `null` so values from a checked-in `.metal-analyzer.json` are not masked by
+// editor defaults. With a `scope`, the settings of that workspace folder.
+function buildServerInitializationOptions(
+  scope?: vscode.Uri,
+): Record<string, unknown> {
+  const config = vscode.workspace.getConfiguration("metal-analyzer", scope);
 
   return {
     "metal-analyzer": {
       formatting: {
-        enabled: config.get<boolean>("formatting.enabled", true),
-        command: config.get<string>("formatting.command", "clang-format"),
-        args: config.get<string[]>("formatting.args", []),
+        enabled: configured<boolean>(config, "formatting.enabled"),
+        engine: configured<string>(config, "formatting.engine"),
+        command: configured<string>(config, "formatting.command"),
+        args: configured<string[]>(config, "formatting.args"),
+        fallbackStyle: configured<string>(config, "formatting.fallbackStyle"),
       },
       diagnostics: {
-        onType: config.get<boolean>("diagnostics.onType", true),
-        onSave: config.get<boolean>("diagnostics.onSave", true),
-        debounceMs: config.get<number>("diagnostics.debounceMs", 500),
-        scope: config.get<string>("diagnostics.scope", "openFiles"),
+        onType: configured<boolean>(config, "diagnostics.onType"),
+        syntax: configured<boolean>(config, "diagnostics.syntax"),
+        onSave: configured<boolean>(config, "diagnostics.onSave"),
+        debounceMs: configured<number>(config, "diagnostics.debounceMs"),
+        scope: configured<string>(config, "diagnostics.scope"),
+        headerContext: configured<string>(config, "diagnostics.headerContext"),
+        unusedIncludes: configured<boolean>(config, "diagnostics.unusedIncludes"),
+        threadgroupMemoryLimit: configured<number>(
+          config,
+          "diagnostics.threadgroupMemoryLimit",
+        ),
+        unusedSymbols: configured<boolean>(config, "diagnostics.unusedSymbols"),
+        unusedSymbolsAllow: configured<string[]>(
+          config,
+          "diagnostics.unusedSymbolsAllow",
+        ),
+        exclude: configured<string[]>(config, "diagnostics.exclude"),
+      },
+      completion: {
+        snippets: configured<boolean>(config, "completion.snippets"),
       },
       indexing: {
-        enabled: config.get<boolean>("indexing.enabled", true),
-        concurrency: config.get<number>("indexing.concurrency", 1),
-        maxFileSizeKb: config.get<number>("indexing.maxFileSizeKb", 512),
-        projectGraphDepth: config.get<number>("indexing.projectGraphDepth", 3),
-        projectGraphMaxNodes: config.get<number>(
+        enabled: configured<boolean>(config, "indexing.enabled"),
+        concurrency: configured<number>(config, "indexing.concurrency"),
+        maxFileSizeKb: configured<number>(config, "indexing.maxFileSizeKb"),
+        projectGraphDepth: configured<number>(
+          config,
+          "indexing.projectGraphDepth",
+        ),
+        projectGraphMaxNodes: configured<number>(
+          config,
           "indexing.projectGraphMaxNodes",
-          256,
         ),
-        excludePaths: config.get<string[]>("indexing.excludePaths", []),
+        excludePaths: configured<string[]>(config, "indexing.excludePaths"),
+        respectGitignore: configured<boolean>(
+          config,
+          "indexing.respectGitignore",
+        ),
+        persistIndex: configured<boolean>(config, "indexing.persistIndex"),
+        maxMemoryMb: configured<number>(config, "indexing.maxMemoryMb"),
+      },
+      symbols: {
+        searchScope: {
+          includeSystemHeaders: configured<boolean>(
+            config,
+            "symbols.searchScope.includeSystemHeaders",
+          ),
+          includeGenerated: configured<boolean>(
+            config,
+            "symbols.searchScope.includeGenerated",
+          ),
+          includeTests: configured<boolean>(
+            config,
+            "symbols.searchScope.includeTests",
+          ),
+        },
+      },
+      navigation: {
+        ranking: {
+          otherFile: configured<number>(config, "navigation.ranking.otherFile"),
+          declarationOnly: configured<number>(
+            config,
+            "navigation.ranking.declarationOnly",
+          ),
+          parameter: configured<number>(config, "navigation.ranking.parameter"),
+          systemHeader: configured<number>(
+            config,
+            "navigation.ranking.systemHeader",
+          ),
+          builtinOutsideSystem: configured<number>(
+            config,
+            "navigation.ranking.builtinOutsideSystem",
+          ),
+          projectHeader: configured<number>(
+            config,
+            "navigation.ranking.projectHeader",
+          ),
+          builtinPrefixes: configured<string[]>(
+            config,
+            "navigation.ranking.builtinPrefixes",
+          ),
+        },
       },
       compiler: {
-        includePaths: config.get<string[]>("compiler.includePaths", []),
-        extraFlags: config.get<string[]>("compiler.extraFlags", []),
-        platform: config.get<string>("compiler.platform", "auto"),
+        includePaths: configured<string[]>(config, "compiler.includePaths"),
+        virtualIncludeRoots: configured<Record<string, string>>(
+          config,
+          "compiler.virtualIncludeRoots",
+        ),
+        extraFlags: configured<string[]>(config, "compiler.extraFlags"),
+        platform: configured<string>(config, "compiler.platform"),
+        languageVersion: configured<string>(config, "compiler.languageVersion"),
+        tempDir: configured<string>(config, "compiler.tempDir"),
+        keepArtifacts: configured<boolean>(config, "compiler.keepArtifacts"),
+        artifactsMaxSizeMb: configured<number>(
+          config,
+          "compiler.artifactsMaxSizeMb",
+        ),
+        astDumpTimeoutMs: configured<number>(
+          config,
+          "compiler.astDumpTimeoutMs",
+        ),
+        precompiledHeaders: configured<boolean>(
+          config,
+          "compiler.precompiledHeaders",
+        ),
+        kernelStats: configured<boolean>(config, "compiler.kernelStats"),
+        overrides: configured<object[]>(config, "compiler.overrides"),
       },
       logging: {
-        level: config.get<string>("logging.level", "info"),
+        level: configured<string>(config, "logging.level"),
+        perfIntervalSecs: configured<number>(config, "logging.perfIntervalSecs"),
       },
       threadPool: {
-        workerThreads: config.get<number>("threadPool.workerThreads", 0),
-        formattingThreads: config.get<number>(
+        workerThreads: configured<number>(config, "threadPool.workerThreads"),
+        formattingThreads: configured<number>(
+          config,
           "threadPool.formattingThreads",
-          1,
         ),
       },
+      todos: {
+        tags: configured<string[]>(config, "todos.tags"),
+        diagnostics: configured<boolean>(config, "todos.diagnostics"),
+      },
+      entryPoints: {
+        codeLens: configured<boolean>(config, "entryPoints.codeLens"),
+        pipelinesFile: configured<string>(config, "entryPoints.pipelinesFile"),
+      },
+      codeLens: {
+        references: configured<boolean>(config, "codeLens.references"),
+        fileActions: configured<boolean>(config, "codeLens.fileActions"),
+      },
+      semanticTokens: {
+        enable: configured<boolean>(config, "semanticTokens.enable"),
+      },
     },
   };
 }
 
+function configured<T>(
+  config: vscode.WorkspaceConfiguration,
+  key: string,
+): T | null {
+  const inspected = config.inspect<T>(key);
+  return (
+    inspected?.workspaceFolderValue ??
+    inspected?.workspaceValue ??
+    inspected?.globalValue ??
+    null
+  );
+}
+
 function registerClientStateSubscription(
   context: vscode.ExtensionContext,
   languageClient: LanguageClient,